@@ -0,0 +1,59 @@
+// LiteralMatcher 和转义之后的正则表达式逐行判断匹配的基准测试
+//
+// --literal-match-only 用 LiteralMatcher 代替正则表达式判断每一行是否匹配。这里直接比较 Matcher::is_match，
+// 不经过 process_bytes，衡量的只是匹配器本身；整个内容一次查找的情况见 whole_text 基准测试。
+// 每 1000 行有一行匹配，两种匹配器找到的行必须相同。
+//
+// 这个构建没有包含 criterion，所以用 std::time::Instant 计时，每种组合取多次运行的最短时间。
+//
+// 运行方式: `cargo bench --bench literal`
+
+use std::time::{Duration, Instant};
+
+use pgrep::matcher::{LiteralMatcher, Matcher};
+use regex::Regex;
+
+/// 每种组合运行的次数
+const ROUNDS: usize = 10;
+
+/// 测试数据的行数
+const LINES: usize = 500_000;
+
+fn main() {
+    let lines: Vec<String> = (0..LINES)
+        .map(|i| {
+            if i % 1000 == 0 {
+                format!("{} ERROR connection reset by peer (code=104) 连接被重置", i)
+            } else {
+                format!("{} INFO request served in {}ms by worker-{}", i, i % 97, i % 16)
+            }
+        })
+        .collect();
+    let bytes: usize = lines.iter().map(|l| l.len() + 1).sum();
+
+    println!("{:<18} {:<8} {:>12} {:>12}", "模式", "匹配器", "最短耗时", "MB/s");
+    for literal in ["connection reset", "(code=104)", "连接被重置", "r"] {
+        let lm = LiteralMatcher::new(literal);
+        let re = Regex::new(&regex::escape(literal)).unwrap();
+        let matchers: [(&str, &dyn Matcher); 2] = [("literal", &lm), ("regex", &re)];
+        let mut counts = Vec::new();
+        for (name, m) in matchers {
+            let (best, n) = run(&lines, m);
+            counts.push(n);
+            let mbps = bytes as f64 / best.as_secs_f64() / 1e6;
+            println!("{:<18} {:<8} {:>12.2?} {:>12.1}", literal, name, best, mbps);
+        }
+        assert_eq!(counts[0], counts[1], "{}", literal);
+    }
+}
+
+fn run(lines: &[String], m: &dyn Matcher) -> (Duration, usize) {
+    let mut best = Duration::MAX;
+    let mut n = 0;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        n = lines.iter().filter(|l| m.is_match(l)).count();
+        best = best.min(start.elapsed());
+    }
+    (best, n)
+}
@@ -0,0 +1,69 @@
+// --match-all 逐个检查和 --parallel-regex 分组并行检查的基准测试
+//
+// 10 个模式 `w0` 到 `w9` 合并成一个正则表达式找出候选行，再用 MatchAll 检查每个候选行是否匹配全部的模式。
+// 候选行是 20 万行中的每一行，每行缺少几个词，大约 5% 的行匹配全部的模式。
+// 线程数为 1 时逐个检查，其余的把 10 个模式分成这么多组，每组一个线程。
+// 逐个检查遇到第一个不匹配的模式就停下，分组之后每组都要检查完自己的模式，
+// 所以只有一个 CPU 时多线程总是更慢，开头输出的 CPU 数是解读结果的前提。
+//
+// 这个构建没有包含 criterion，所以用 std::time::Instant 计时，每种组合取多次运行的最短时间。
+//
+// 运行方式: `cargo bench --bench match_all`
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use pgrep::matchall::MatchAll;
+use pgrep::{GrepConfig, Record, process_bytes};
+use regex::Regex;
+
+/// 每种组合运行的次数
+const ROUNDS: usize = 10;
+
+/// 测试数据的行数
+const LINES: usize = 200_000;
+
+fn main() {
+    let data: String = (0..LINES)
+        .map(|i| {
+            let h = (i as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            let missing = (h >> 20) & (h >> 40);
+            let words: Vec<String> = (0..10).filter(|d| missing & (1 << d) == 0).map(|d| format!("w{}", d)).collect();
+            format!("{} {}\n", i, words.join(" "))
+        })
+        .collect();
+    let sources: Vec<String> = (0..10).map(|i| format!(r"\bw{}\b", i)).collect();
+    let any = Regex::new(&sources.join("|")).unwrap();
+
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    println!("可用的 CPU: {}", cpus);
+    println!("{:>6} {:>10} {:>12}", "线程", "保留", "最短耗时");
+    let mut expected = None;
+    for threads in [1, 2, 4, 10] {
+        let all = MatchAll::new(&sources, threads).unwrap();
+        let mut kept = 0;
+        let best = (0..ROUNDS)
+            .map(|_| {
+                let v = candidates(&data, &any);
+                let (n, elapsed) = run(&all, v);
+                kept = n;
+                elapsed
+            })
+            .min()
+            .unwrap_or_default();
+        // 所有线程数的结果必须相同
+        assert_eq!(*expected.get_or_insert(kept), kept);
+        println!("{:>6} {:>10} {:>12.2?}", threads, kept, best);
+    }
+}
+
+/// 合并后的表达式找出的候选行，不计入耗时
+fn candidates(data: &str, any: &Regex) -> Vec<Record> {
+    process_bytes(Path::new("bench.txt"), data.as_bytes().to_vec(), any, &GrepConfig::default()).unwrap()
+}
+
+fn run(all: &MatchAll, v: Vec<Record>) -> (usize, Duration) {
+    let start = Instant::now();
+    let kept = all.filter(v).len();
+    (kept, start.elapsed())
+}
@@ -0,0 +1,52 @@
+// 结果向量预分配容量的基准测试
+//
+// 比较不同的 `match_buffer_size` 在稀疏匹配和密集匹配两种情况下的吞吐量：
+// * 稀疏：10 万行中只有 1% 的行匹配，预分配太大只是浪费内存
+// * 密集：每一行都匹配，预分配太小时结果向量要反复扩容复制
+//
+// 这个构建没有包含 criterion，所以用 std::time::Instant 计时，每种组合取多次运行的最短时间。
+//
+// 运行方式: `cargo bench --bench match_buffer`
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use pgrep::{GrepConfig, process_bytes};
+use regex::Regex;
+
+/// 每种组合运行的次数
+const ROUNDS: usize = 10;
+
+/// 测试数据的行数
+const LINES: usize = 100_000;
+
+fn main() {
+    let sparse: String = (0..LINES)
+        .map(|i| if i % 100 == 0 { format!("match {}\n", i) } else { format!("line {}\n", i) })
+        .collect();
+    let dense: String = (0..LINES).map(|i| format!("match {}\n", i)).collect();
+    let re = Regex::new("match").unwrap();
+
+    println!("{:<8} {:>10} {:>12} {:>12}", "数据", "容量", "最短耗时", "MB/s");
+    for (name, data) in [("稀疏", &sparse), ("密集", &dense)] {
+        for size in [None, Some(0), Some(1024), Some(LINES)] {
+            let cfg = GrepConfig {
+                match_buffer_size: size,
+                ..GrepConfig::default()
+            };
+            let best = (0..ROUNDS).map(|_| run(data, &re, &cfg)).min().unwrap_or_default();
+            let label = size.map_or("默认".to_string(), |s| s.to_string());
+            let mbps = data.len() as f64 / best.as_secs_f64() / 1e6;
+            println!("{:<8} {:>10} {:>12.2?} {:>12.1}", name, label, best, mbps);
+        }
+    }
+}
+
+fn run(data: &str, re: &Regex, cfg: &GrepConfig) -> Duration {
+    let bts = data.as_bytes().to_vec();
+    let start = Instant::now();
+    let res = process_bytes(Path::new("bench.txt"), bts, re, cfg).unwrap();
+    let elapsed = start.elapsed();
+    assert!(!res.is_empty());
+    elapsed
+}
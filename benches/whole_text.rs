@@ -0,0 +1,68 @@
+// 在整个内容上查找和逐行匹配的基准测试
+//
+// 匹配稀疏的大文件中绝大多数行都不匹配，scan_whole_text 在整个内容上用多行模式的正则表达式
+// （按字面查找时用 memmem）找出候选，只确认候选所在的行；逐行匹配则对每一行调用一次正则表达式。
+// 逐行匹配的一组用 `skip_empty_lines` 关掉快速路径：测试数据中没有空行，两组的结果完全相同。
+//
+// 这个构建没有包含 criterion，所以用 std::time::Instant 计时，每种组合取多次运行的最短时间。
+//
+// 运行方式: `cargo bench --bench whole_text`
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use pgrep::matcher::LiteralMatcher;
+use pgrep::{GrepConfig, process_bytes};
+use regex::Regex;
+
+/// 每种组合运行的次数
+const ROUNDS: usize = 10;
+
+/// 测试数据的行数，每 10000 行有一行匹配
+const LINES: usize = 1_000_000;
+
+fn main() {
+    let data: String = (0..LINES)
+        .map(|i| {
+            if i % 10_000 == 0 {
+                format!("{} ERROR connection reset by peer\n", i)
+            } else {
+                format!("{} INFO request served in {}ms\n", i, i % 97)
+            }
+        })
+        .collect();
+
+    println!("{:<18} {:<10} {:>12} {:>12}", "模式", "方式", "最短耗时", "MB/s");
+    for pattern in ["connection reset", r"reset by \w+$", r"^\d+ ERROR"] {
+        let re = Regex::new(pattern).unwrap();
+        let whole = GrepConfig::default();
+        let per_line = GrepConfig {
+            skip_empty_lines: true,
+            ..GrepConfig::default()
+        };
+        let mut groups = vec![("整个内容", whole), ("逐行", per_line)];
+        if !pattern.contains(['\\', '^', '$']) {
+            groups.push((
+                "按字面",
+                GrepConfig {
+                    literal: Some(LiteralMatcher::new(pattern)),
+                    ..GrepConfig::default()
+                },
+            ));
+        }
+        for (name, cfg) in groups {
+            let best = (0..ROUNDS).map(|_| run(&data, &re, &cfg)).min().unwrap_or_default();
+            let mbps = data.len() as f64 / best.as_secs_f64() / 1e6;
+            println!("{:<18} {:<10} {:>12.2?} {:>12.1}", pattern, name, best, mbps);
+        }
+    }
+}
+
+fn run(data: &str, re: &Regex, cfg: &GrepConfig) -> Duration {
+    let bts = data.as_bytes().to_vec();
+    let start = Instant::now();
+    let res = process_bytes(Path::new("bench.txt"), bts, re, cfg).unwrap();
+    let elapsed = start.elapsed();
+    assert_eq!(res.len(), LINES / 10_000);
+    elapsed
+}
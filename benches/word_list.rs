@@ -0,0 +1,78 @@
+// --word-list 的 Aho-Corasick 自动机和 `|` 连接的正则表达式的基准测试
+//
+// 词表有 1 万个词，比较两种做法构建和逐行查找 10 万行的耗时：
+// * WordList - 由词表构建的 Aho-Corasick 自动机，也就是 --word-list 的实现
+// * Regex - 转义之后用 `|` 连接起来的一个正则表达式，整词时外面加上 `\b(?:...)\b`
+//
+// 每 100 行有一行含有词表中的词，两种做法找到的行必须相同。
+// 这个构建没有包含 criterion，所以用 std::time::Instant 计时，每种组合取多次运行的最短时间。
+//
+// 运行方式: `cargo bench --bench word_list`
+
+use std::time::{Duration, Instant};
+
+use pgrep::WordList;
+use regex::Regex;
+
+/// 每种组合运行的次数
+const ROUNDS: usize = 5;
+
+/// 词表中的词数
+const WORDS: usize = 10_000;
+
+/// 测试数据的行数
+const LINES: usize = 100_000;
+
+fn main() {
+    // 词表中的词是 kw 加上一个编号再加上字母，彼此之间没有包含关系
+    let words: Vec<String> = (0..WORDS).map(|i| format!("kw{}x", i)).collect();
+    let lines: Vec<String> = (0..LINES)
+        .map(|i| {
+            if i % 100 == 0 {
+                format!("{} request handled by {} in {}ms", i, words[i % WORDS], i % 97)
+            } else {
+                format!("{} request handled by worker{} in {}ms", i, i % 64, i % 97)
+            }
+        })
+        .collect();
+    let list = std::env::temp_dir().join(format!("pgrep-bench-words-{}.txt", std::process::id()));
+    std::fs::write(&list, words.join("\n")).unwrap();
+
+    println!("{:<10} {:<6} {:>12} {:>12} {:>8}", "实现", "整词", "构建", "查找", "匹配行");
+    for whole_word in [false, true] {
+        let mut counts = Vec::new();
+
+        let (build, wl) = best(|| WordList::load(&list, false, whole_word).unwrap());
+        let (scan, n) = best(|| lines.iter().filter(|l| wl.find(l).is_some()).count());
+        report("WordList", whole_word, build, scan, n);
+        counts.push(n);
+
+        let alternation = words.iter().map(|w| regex::escape(w)).collect::<Vec<_>>().join("|");
+        let source = if whole_word { format!(r"\b(?:{})\b", alternation) } else { alternation };
+        let (build, re) = best(|| Regex::new(&source).unwrap());
+        let (scan, n) = best(|| lines.iter().filter(|l| re.is_match(l)).count());
+        report("Regex", whole_word, build, scan, n);
+        counts.push(n);
+
+        assert_eq!(counts, [LINES / 100, LINES / 100]);
+    }
+    let _ = std::fs::remove_file(&list);
+}
+
+/// 运行 ROUNDS 次，返回最短耗时和最后一次的结果
+fn best<T>(mut f: impl FnMut() -> T) -> (Duration, T) {
+    let mut shortest = Duration::MAX;
+    let mut last = None;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        let v = f();
+        shortest = shortest.min(start.elapsed());
+        last = Some(v);
+    }
+    (shortest, last.unwrap())
+}
+
+fn report(name: &str, whole_word: bool, build: Duration, scan: Duration, n: usize) {
+    let ww = if whole_word { "是" } else { "否" };
+    println!("{:<10} {:<6} {:>12.2?} {:>12.2?} {:>8}", name, ww, build, scan, n);
+}
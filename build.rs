@@ -0,0 +1,99 @@
+// 构建脚本
+//
+// 在编译期收集构建元数据，通过 `cargo:rustc-env` 传给主程序，
+// 由 `pgrep --version` 输出：
+// * PGREP_GIT_HASH - git 提交哈希（短格式），工作区有未提交修改时带 `-dirty` 后缀
+// * PGREP_BUILD_DATE - 构建日期（UTC），设置了 SOURCE_DATE_EPOCH 时以它为准，便于可重现构建
+// * PGREP_TARGET - 目标平台三元组
+// * PGREP_RUSTC_VERSION - 编译器版本
+// * PGREP_FEATURES - 启用的 cargo feature，逗号分隔
+//
+// 不在 git 仓库中构建（例如从 crates.io 安装）或者找不到 git 命令时，
+// 提交哈希记为 "unknown"，不会导致构建失败。
+//
+// 相关文档:
+// * 构建脚本: <https://doc.rust-lang.org/cargo/reference/build-scripts.html>
+// * 构建脚本可用的环境变量: <https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-build-scripts>
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 运行命令并返回去掉首尾空白的标准输出，失败时返回 None
+fn command_output(prog: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(prog).args(args).output().ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// 获取 git 提交哈希，工作区有修改时追加 `-dirty`
+fn git_hash() -> String {
+    let Some(hash) = command_output("git", &["rev-parse", "--short", "HEAD"]) else {
+        return "unknown".to_string();
+    };
+    match command_output("git", &["status", "--porcelain"]) {
+        Some(s) if !s.is_empty() => format!("{}-dirty", hash),
+        _ => hash,
+    }
+}
+
+/// 把 Unix 时间戳（秒）转换为 `YYYY-MM-DD` 格式的 UTC 日期
+///
+/// 使用 Howard Hinnant 的 civil_from_days 算法:
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn format_date(secs: u64) -> String {
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+fn main() {
+    // 只有在 git 仓库中构建时才监视 git 状态，否则 cargo 会因为文件不存在而每次都重新运行脚本
+    // 监视 src 是为了让源码修改后 dirty 标记及时更新
+    if Path::new(".git").exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        println!("cargo:rerun-if-changed=.git/index");
+        println!("cargo:rerun-if-changed=src");
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let secs = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = command_output(&rustc, &["--version"]).unwrap_or_else(|| "unknown".to_string());
+
+    // cargo 为每个启用的 feature 设置 CARGO_FEATURE_<NAME> 环境变量
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(k, _)| k.strip_prefix("CARGO_FEATURE_").map(|f| f.to_lowercase().replace('_', "-")))
+        .collect();
+    features.sort();
+    let features = if features.is_empty() {
+        "none".to_string()
+    } else {
+        features.join(",")
+    };
+
+    println!("cargo:rustc-env=PGREP_GIT_HASH={}", git_hash());
+    println!("cargo:rustc-env=PGREP_BUILD_DATE={}", format_date(secs));
+    println!("cargo:rustc-env=PGREP_TARGET={}", env::var("TARGET").unwrap_or_default());
+    println!("cargo:rustc-env=PGREP_RUSTC_VERSION={}", rustc_version);
+    println!("cargo:rustc-env=PGREP_FEATURES={}", features);
+}
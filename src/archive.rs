@@ -0,0 +1,373 @@
+// 打包文件
+//
+// --archives 用来搜索 tar 和 zip 包中的文件。这里只负责识别格式、列出成员并取出成员内容，
+// 成员的搜索和嵌套包的处理在 lib.rs 的 process_content 中。
+//
+// | 格式 | 识别方式                               | 扩展名 | 支持的成员                     |
+// |------|----------------------------------------|--------|--------------------------------|
+// | tar  | 第 257 字节起的 "ustar"，或扩展名       | .tar   | 普通文件，含 GNU 长文件名和 PAX |
+// | zip  | 开头的 `PK\x03\x04` / `PK\x05\x06`     | .zip   | 不压缩（stored）和 DEFLATE      |
+//
+// `.tar.gz` / `.tgz` 先由 compress 模块解压，再按 tar 处理；从文件名看是压缩的 tar 包却无法解压（例如被截断）时
+// 和损坏的包一样报告错误，不会像没有使用 -z 时的普通压缩文件那样悄悄跳过。
+//
+// 成员用 `包的路径!成员名` 形式的虚拟路径表示，例如 `backups/snap.tar.gz!etc/config`，
+// 嵌套的包再接一个 `!`。目录、符号链接、设备文件这类成员没有可以搜索的内容，
+// 加密的 zip 成员无法解密，都会报告为跳过的成员而不是错误。
+//
+// 被截断的包报告为错误，截断之前的成员照常搜索。tar 包正好在 512 字节的块边界处截断时
+// 和没有写结尾的全零块的包无法区分，这种情况不报告。
+//
+// 相关文档:
+// * POSIX ustar 格式: <https://pubs.opengroup.org/onlinepubs/9699919799/utilities/pax.html#tag_20_92_13_06>
+// * ZIP 格式说明 (APPNOTE.TXT): <https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT>
+
+use failure::{Error, Fail};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::gzip;
+
+/// 虚拟路径中分隔包和成员名的字符
+pub const SEPARATOR: char = '!';
+
+/// 打包格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Tar,
+    Zip,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Tar => "tar",
+            Kind::Zip => "zip",
+        })
+    }
+}
+
+/// 打包文件损坏或者使用了不支持的特性
+#[derive(Debug, Fail)]
+#[fail(display = "无法读取 {} 包 {}: {}", kind, path, msg)]
+pub struct ArchiveErr {
+    path: String,
+    kind: Kind,
+    msg: String,
+}
+
+/// 包中的一个成员
+#[derive(Debug)]
+pub enum Member {
+    /// 可以搜索的普通文件
+    File { name: String, data: Vec<u8> },
+    /// 被跳过的成员及原因
+    Skipped { name: String, reason: String },
+    /// 加密的 zip 成员，和其他跳过的成员不同，需要提醒用户
+    Encrypted { name: String },
+}
+
+/// 拼出成员的虚拟路径 `包的路径!成员名`
+pub fn member_path(archive: &Path, name: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}{}", archive.display(), SEPARATOR, name))
+}
+
+/// 检测文件的打包格式
+///
+/// 和 compress::detect 一样魔数优先，内容看不出来时再看扩展名
+///
+/// # 返回值
+/// 不是 tar 或 zip 时返回 None
+pub fn detect(p: &Path, bts: &[u8]) -> Option<Kind> {
+    if bts.starts_with(b"PK\x03\x04") || bts.starts_with(b"PK\x05\x06") {
+        return Some(Kind::Zip);
+    }
+    if bts.get(257..262) == Some(b"ustar") {
+        return Some(Kind::Tar);
+    }
+    match p.extension()?.to_string_lossy().to_ascii_lowercase().as_str() {
+        "tar" => Some(Kind::Tar),
+        "zip" => Some(Kind::Zip),
+        _ => None,
+    }
+}
+
+/// 按文件名看是不是压缩的 tar 包：`.tar.gz` 这样的 `.tar` 加压缩扩展名，或者 `.tgz`
+///
+/// 这样的文件即使解压失败也应该是一个包，解压错误要报告出来，而不是像普通的压缩文件一样跳过
+pub fn compressed_tar(p: &Path) -> bool {
+    let ext = |p: &Path| p.extension().map(|e| e.to_string_lossy().to_ascii_lowercase());
+    match ext(p).as_deref() {
+        Some("tgz") => true,
+        Some(_) => ext(&p.with_extension("")).as_deref() == Some("tar"),
+        None => false,
+    }
+}
+
+/// 依次取出包中的每个成员
+///
+/// # 参数
+/// * `p` - 包的路径，只用于错误信息
+/// * `kind` - detect 检测出的格式
+/// * `bts` - 包的全部内容
+/// * `max_size` - 单个成员解压后的最大字节数，超过的成员被跳过
+/// * `f` - 每个成员调用一次，返回错误时停止并把错误原样返回
+///
+/// # 返回值
+/// * `Err(ArchiveErr)` - 包的结构损坏，已经交给 `f` 的成员不受影响
+pub fn for_each_member<F>(p: &Path, kind: Kind, bts: &[u8], max_size: u64, f: F) -> Result<(), Error>
+where
+    F: FnMut(Member) -> Result<(), Error>,
+{
+    let err = |msg: String| -> Error {
+        ArchiveErr {
+            path: p.display().to_string(),
+            kind,
+            msg,
+        }
+        .into()
+    };
+    match kind {
+        Kind::Tar => tar_members(bts, max_size, f, err),
+        Kind::Zip => zip_members(bts, max_size, f, err),
+    }
+}
+
+/// 超过大小限制时跳过成员的原因
+fn too_large(size: u64, max: u64) -> String {
+    format!("解压后有 {} 字节，超过了 --max-member-size 的 {} 字节", size, max)
+}
+
+/// 读取 tar 头部中以 NUL 结尾的字段
+fn tar_str(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// 读取 tar 头部中的数字字段
+///
+/// 一般是八进制文本；GNU tar 对放不下的大数使用首字节最高位为 1 的 base-256 编码
+fn tar_num(field: &[u8]) -> Option<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        return Some(field[1..].iter().fold(0u64, |n, &b| (n << 8) | b as u64));
+    }
+    let s = tar_str(field);
+    let s = s.trim_matches(|c: char| c == ' ' || c == '\0');
+    if s.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(s, 8).ok()
+}
+
+/// 解析 PAX 扩展头部中的 `path` 和 `size`
+///
+/// 每条记录的格式是 `长度 key=value\n`，长度包括它自己
+fn pax_records(mut data: &[u8]) -> (Option<String>, Option<u64>) {
+    let (mut path, mut size) = (None, None);
+    while let Some(sp) = data.iter().position(|&b| b == b' ') {
+        let Some(len) = std::str::from_utf8(&data[..sp]).ok().and_then(|s| s.parse::<usize>().ok()) else {
+            break;
+        };
+        if len <= sp || len > data.len() {
+            break;
+        }
+        let rec = String::from_utf8_lossy(&data[sp + 1..len]);
+        let rec = rec.strip_suffix('\n').unwrap_or(&rec);
+        if let Some((k, v)) = rec.split_once('=') {
+            match k {
+                "path" => path = Some(v.to_string()),
+                "size" => size = v.parse().ok(),
+                _ => {}
+            }
+        }
+        data = &data[len..];
+    }
+    (path, size)
+}
+
+fn tar_members<F, E>(bts: &[u8], max_size: u64, mut f: F, err: E) -> Result<(), Error>
+where
+    F: FnMut(Member) -> Result<(), Error>,
+    E: Fn(String) -> Error,
+{
+    let mut off = 0;
+    // GNU 长文件名（类型 L）和 PAX 扩展头部（类型 x）作用于紧随其后的那个成员
+    let mut long_name: Option<String> = None;
+    let mut pax_size: Option<u64> = None;
+
+    while off + 512 <= bts.len() {
+        let h = &bts[off..off + 512];
+        // 两个全零的块是包的结尾，很多工具只写一个
+        if h.iter().all(|&b| b == 0) {
+            return Ok(());
+        }
+        // 校验和的计算方式是把校验和字段本身当作 8 个空格
+        let sum: u64 = h
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| if (148..156).contains(&i) { b' ' as u64 } else { b as u64 })
+            .sum();
+        if tar_num(&h[148..156]) != Some(sum) {
+            return Err(err(format!("第 {} 字节处的头部校验和不正确", off)));
+        }
+
+        let mut name = tar_str(&h[0..100]);
+        if &h[257..262] == b"ustar" {
+            let prefix = tar_str(&h[345..500]);
+            if !prefix.is_empty() {
+                name = format!("{}/{}", prefix, name);
+            }
+        }
+        let size = pax_size
+            .take()
+            .or_else(|| tar_num(&h[124..136]))
+            .ok_or_else(|| err(format!("成员 {} 的大小字段无效", name)))?;
+        let start = off + 512;
+        let end = usize::try_from(size)
+            .ok()
+            .and_then(|s| start.checked_add(s))
+            .filter(|&e| e <= bts.len())
+            .ok_or_else(|| err(format!("成员 {} 被截断", name)))?;
+        let data = &bts[start..end];
+        off = start + (end - start).div_ceil(512) * 512;
+
+        let typeflag = h[156];
+        match typeflag {
+            b'L' => {
+                long_name = Some(tar_str(data));
+                continue;
+            }
+            b'x' => {
+                let (path, size) = pax_records(data);
+                long_name = path.or(long_name);
+                pax_size = size;
+                continue;
+            }
+            // 全局 PAX 头部只有一些元数据，与搜索无关
+            b'g' => continue,
+            _ => {}
+        }
+        let name = long_name.take().unwrap_or(name);
+
+        let member = match typeflag {
+            b'0' | b'\0' | b'7' if size > max_size => Member::Skipped {
+                name,
+                reason: too_large(size, max_size),
+            },
+            b'0' | b'\0' | b'7' => Member::File {
+                name,
+                data: data.to_vec(),
+            },
+            // 目录本身没有内容，其中的文件是单独的成员
+            b'5' => continue,
+            b'1' => Member::Skipped {
+                name,
+                reason: "包中的硬链接".to_string(),
+            },
+            b'2' => Member::Skipped {
+                name,
+                reason: "包中的符号链接".to_string(),
+            },
+            t => Member::Skipped {
+                name,
+                reason: format!("不是普通文件（tar 类型 '{}'）", t as char),
+            },
+        };
+        f(member)?;
+    }
+    // 最后一个成员的填充不完整，或者剩下不到一个块的非零字节（被截断的头部），包都在这里被截断了；
+    // 全零的是结尾的填充
+    if bts.get(off..).is_none_or(|rest| rest.iter().any(|&b| b != 0)) {
+        return Err(err(format!("包在第 {} 字节处被截断", bts.len())));
+    }
+    Ok(())
+}
+
+fn le16(b: &[u8], at: usize) -> usize {
+    u16::from_le_bytes([b[at], b[at + 1]]) as usize
+}
+
+fn le32(b: &[u8], at: usize) -> u64 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]]) as u64
+}
+
+fn zip_members<F, E>(bts: &[u8], max_size: u64, mut f: F, err: E) -> Result<(), Error>
+where
+    F: FnMut(Member) -> Result<(), Error>,
+    E: Fn(String) -> Error,
+{
+    // 中央目录结尾记录在文件末尾，后面最多还有 65535 字节的注释
+    const EOCD: &[u8] = b"PK\x05\x06";
+    let lo = bts.len().saturating_sub(22 + 0xffff);
+    let eocd = (lo..=bts.len().saturating_sub(22))
+        .rev()
+        .find(|&i| bts[i..].starts_with(EOCD))
+        .ok_or_else(|| err("找不到中央目录".to_string()))?;
+    let count = le16(bts, eocd + 10);
+    let cd_off = le32(bts, eocd + 16);
+    if count == 0xffff || cd_off == 0xffff_ffff {
+        return Err(err("不支持 Zip64 格式".to_string()));
+    }
+
+    let mut off = cd_off as usize;
+    for _ in 0..count {
+        let h = bts
+            .get(off..off + 46)
+            .filter(|h| h.starts_with(b"PK\x01\x02"))
+            .ok_or_else(|| err(format!("第 {} 字节处的中央目录记录无效", off)))?;
+        let flags = le16(h, 8);
+        let method = le16(h, 10);
+        let crc = le32(h, 16) as u32;
+        let csize = le32(h, 20) as usize;
+        let size = le32(h, 24);
+        let (nlen, xlen, clen) = (le16(h, 28), le16(h, 30), le16(h, 32));
+        let local = le32(h, 42) as usize;
+        let name = bts
+            .get(off + 46..off + 46 + nlen)
+            .map(|n| String::from_utf8_lossy(n).into_owned())
+            .ok_or_else(|| err("中央目录被截断".to_string()))?;
+        off += 46 + nlen + xlen + clen;
+
+        if name.ends_with('/') {
+            continue;
+        }
+        if flags & 1 != 0 {
+            f(Member::Encrypted { name })?;
+            continue;
+        }
+        if size > max_size {
+            f(Member::Skipped {
+                reason: too_large(size, max_size),
+                name,
+            })?;
+            continue;
+        }
+
+        // 本地头部中的文件名和扩展字段长度可能和中央目录中的不同，要以本地头部为准
+        let lh = bts
+            .get(local..local + 30)
+            .filter(|h| h.starts_with(b"PK\x03\x04"))
+            .ok_or_else(|| err(format!("成员 {} 的本地头部无效", name)))?;
+        let start = local + 30 + le16(lh, 26) + le16(lh, 28);
+        let Some(raw) = bts.get(start..start + csize) else {
+            return Err(err(format!("成员 {} 被截断", name)));
+        };
+        let data = match method {
+            0 => raw.to_vec(),
+            8 => miniz_oxide::inflate::decompress_to_vec_with_limit(raw, max_size as usize)
+                .map_err(|e| err(format!("成员 {} 的压缩数据损坏 ({:?})", name, e.status)))?,
+            m => {
+                f(Member::Skipped {
+                    name,
+                    reason: format!("不支持的压缩方法 {}", m),
+                })?;
+                continue;
+            }
+        };
+        if gzip::crc32(&data) != crc {
+            return Err(err(format!("成员 {} 的 CRC32 校验和不一致", name)));
+        }
+        f(Member::File { name, data })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,214 @@
+// 匹配基线
+//
+// 逐步淘汰某个写法时，已有的匹配先保留下来，只阻止新增的匹配：
+// * --save-baseline FILE: 把这次搜索到的所有匹配写入 FILE
+// * --baseline FILE: 只输出 FILE 中没有记录的匹配，有新的匹配时以状态 1 退出，没有时以状态 0 退出，
+//   基线文件不存在或者无法解析时以状态 2 退出
+//
+// 基线中每个匹配记录为 `路径 + 规范化后的行内容`，不记录行号，所以在匹配的上方增删代码、
+// 或者在文件中移动已有的匹配都不会让它变成新的匹配。规范化去掉行首和行尾的空白，
+// 并把中间连续的空白合并成一个空格，只改变缩进也不算新的匹配。
+// 同一文件中内容相同的行可以出现多次，基线记录了几次就允许几次，多出来的按在文件中的顺序算作新的匹配。
+//
+// 基线文件的格式（第 1 版）是 UTF-8 编码的 JSON：
+//
+//     {
+//       "version": 1,
+//       "matches": [
+//         {"path": "src/client.rs", "line": "legacy_api();"},
+//         {"path": "src/server.rs", "line": "let x = legacy_api();"}
+//       ]
+//     }
+//
+// * `version` 目前总是 1，格式以后如果有不兼容的变化会使用新的版本号，读取时拒绝不认识的版本
+// * `matches` 中每个匹配一项，按 path、line 排序，每项单独一行，便于在代码审查中阅读基线的变化
+// * `path` 是输出中的路径去掉开头的 `./`，所以应该用相同的搜索路径生成和使用基线；
+//   配合 --git-relative 时路径相对于仓库根目录，和从哪个目录运行无关
+// * `line` 是规范化后的行内容
+// * 读取时忽略不认识的字段，手工编辑过的基线（缩进、字段顺序不同）也能读取
+//
+// 相关文档:
+// * JSON: <https://www.rfc-editor.org/rfc/rfc8259>
+
+use failure::{Error, Fail};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+use crate::json::{Json, Parser, quote};
+use pgrep::Record;
+
+/// 基线文件的格式版本
+pub const VERSION: u64 = 1;
+
+/// 基线文件无法解析
+#[derive(Debug, Fail)]
+#[fail(display = "无法读取基线文件 {}: {}", path, reason)]
+pub struct BaselineErr {
+    path: String,
+    reason: String,
+}
+
+/// 读入的基线
+#[derive(Debug, Default)]
+pub struct Baseline {
+    /// (路径, 规范化后的行内容) 允许出现的次数
+    known: HashMap<(String, String), usize>,
+}
+
+impl Baseline {
+    /// 读取 `--baseline` 指定的文件
+    pub fn load(p: &Path) -> Result<Baseline, Error> {
+        let err = |reason: String| BaselineErr {
+            path: p.display().to_string(),
+            reason,
+        };
+        let text = std::fs::read_to_string(p).map_err(|e| err(e.to_string()))?;
+        let value = Parser::new(&text).document().map_err(err)?;
+        let Json::Object(top) = value else {
+            return Err(err("顶层不是对象".to_string()).into());
+        };
+        match top.iter().find(|(k, _)| k == "version").map(|(_, v)| v) {
+            Some(Json::Number(v)) if *v == VERSION as f64 => {}
+            Some(Json::Number(v)) => return Err(err(format!("不支持第 {} 版的格式，只支持第 {} 版", v, VERSION)).into()),
+            _ => return Err(err("缺少 version 字段".to_string()).into()),
+        }
+        let Some((_, Json::Array(matches))) = top.iter().find(|(k, _)| k == "matches") else {
+            return Err(err("缺少 matches 数组".to_string()).into());
+        };
+
+        let mut known = HashMap::new();
+        for (i, m) in matches.iter().enumerate() {
+            let field = |name: &str| match m {
+                Json::Object(fields) => fields.iter().find_map(|(k, v)| match v {
+                    Json::String(s) if k == name => Some(s.clone()),
+                    _ => None,
+                }),
+                _ => None,
+            };
+            let (Some(path), Some(line)) = (field("path"), field("line")) else {
+                return Err(err(format!("matches 的第 {} 项缺少字符串字段 path 或 line", i + 1)).into());
+            };
+            // 手工编辑过的基线中行内容可能没有规范化
+            *known.entry((path, normalize(&line))).or_insert(0) += 1;
+        }
+        Ok(Baseline { known })
+    }
+
+    /// 基线中记录的匹配总数
+    pub fn len(&self) -> usize {
+        self.known.values().sum()
+    }
+
+    /// 去掉一个文件的结果中基线已经记录的匹配，返回新的匹配
+    ///
+    /// # 参数
+    /// * `path` - 输出中的路径
+    /// * `v` - 这个文件的搜索结果，按行号排列
+    pub fn new_matches(&self, path: &str, v: Vec<Record>) -> Vec<Record> {
+        let path = key_path(path);
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        v.into_iter()
+            .filter(|r| {
+                let line = normalize(&r.tx);
+                let allowed = self.known.get(&(path.to_string(), line.clone())).copied().unwrap_or(0);
+                let n = seen.entry(line).or_insert(0);
+                *n += 1;
+                *n > allowed
+            })
+            .collect()
+    }
+}
+
+/// 一个匹配在基线中的记录，即 (路径, 规范化后的行内容)
+pub fn entry(path: &str, r: &Record) -> (String, String) {
+    (key_path(path).to_string(), normalize(&r.tx))
+}
+
+/// 把匹配写入 `--save-baseline` 指定的文件
+///
+/// 和检查点文件一样先写临时文件再重命名，写入失败时不会留下不完整的基线
+pub fn save(p: &Path, mut entries: Vec<(String, String)>) -> Result<(), Error> {
+    entries.sort();
+    let mut tmp = p.as_os_str().to_os_string();
+    tmp.push(".tmp");
+    let tmp = std::path::PathBuf::from(tmp);
+
+    let mut f = std::io::BufWriter::new(std::fs::File::create(&tmp)?);
+    writeln!(f, "{{")?;
+    writeln!(f, "  \"version\": {},", VERSION)?;
+    if entries.is_empty() {
+        writeln!(f, "  \"matches\": []")?;
+    } else {
+        writeln!(f, "  \"matches\": [")?;
+        for (i, (path, line)) in entries.iter().enumerate() {
+            let sep = if i + 1 < entries.len() { "," } else { "" };
+            writeln!(f, "    {{\"path\": {}, \"line\": {}}}{}", quote(path), quote(line), sep)?;
+        }
+        writeln!(f, "  ]")?;
+    }
+    writeln!(f, "}}")?;
+    f.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    std::fs::rename(&tmp, p)?;
+    Ok(())
+}
+
+/// 去掉首尾的空白，中间连续的空白合并成一个空格
+fn normalize(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 去掉路径开头的 `./`，`-f .` 和 `-f ./` 得到相同的路径
+fn key_path(path: &str) -> &str {
+    let mut p = path;
+    while let Some(rest) = p.strip_prefix("./") {
+        p = rest.trim_start_matches('/');
+    }
+    p
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(line: usize, tx: &str) -> Record {
+        Record {
+            line,
+            tx: tx.to_string(),
+            fuzzy: None,
+            replaced: None,
+            word: None,
+            suppressed: false,
+            rules: Vec::new(),
+            is_context: false,
+        }
+    }
+
+    #[test]
+    fn moved_and_reindented_matches_are_known() {
+        let mut b = Baseline::default();
+        b.known.insert(("src/a.rs".to_string(), "legacy_api();".to_string()), 2);
+        b.known.insert(("src/a.rs".to_string(), "let x = legacy_api();".to_string()), 1);
+        assert_eq!(b.len(), 3);
+
+        let v = vec![
+            record(40, "\tlet   x = legacy_api();"),
+            record(41, "legacy_api();"),
+            record(50, "    legacy_api();  "),
+            record(60, "legacy_api();"),
+            record(70, "let y = legacy_api();"),
+        ];
+        let new: Vec<usize> = b.new_matches("./src/a.rs", v).iter().map(|r| r.line).collect();
+        // 第三个 legacy_api(); 超过了基线中的次数，let y 是新的内容
+        assert_eq!(new, [60, 70]);
+        assert_eq!(b.new_matches("src/b.rs", vec![record(0, "legacy_api();")]).len(), 1);
+    }
+
+    #[test]
+    fn entries_drop_the_leading_dot() {
+        assert_eq!(
+            entry(".//src/a.rs", &record(3, "  a   b ")),
+            ("src/a.rs".to_string(), "a b".to_string())
+        );
+    }
+}
@@ -0,0 +1,178 @@
+// 内置模式
+//
+// 为常见的搜索目标提供现成的正则表达式，通过 `--builtin-pattern NAME` 使用，
+// 例如在日志中查找所有 IP 地址，而不必每次都手写一长串正则。
+//
+// 这些正则表达式追求"日志里好用"，而不是严格符合 RFC：
+// * IPv6 不匹配以 `::` 结尾的写法和单独的 `::`，否则 `std::` 这类源码会被大量误报；
+//   但由十六进制字母组成的单词仍可能误报，例如 `bad::add`
+// * 邮箱和 URL 只做形式检查，不验证域名是否存在
+// * 信用卡号只检查卡组织的号段和位数，不做 Luhn 校验
+
+use clap::ValueEnum;
+
+/// 内置模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BuiltinPattern {
+    /// IPv4 或 IPv6 地址
+    IpAddress,
+    /// 电子邮件地址
+    Email,
+    /// http / https / ftp URL
+    Url,
+    /// 8-4-4-4-12 格式的 UUID
+    Uuid,
+    /// Visa、MasterCard、American Express、Discover 信用卡号，允许空格或 `-` 分隔
+    CreditCard,
+}
+
+/// IP 地址：IPv4 或者 IPv6
+///
+/// IPv4 每段 0-255，放在最前面，避免 IPv6 的某个分支先匹配到 IPv4 地址的一部分；
+/// IPv6 包括完整写法以及在中间或开头使用 `::` 压缩的写法。
+/// 压缩写法的分支按 `::` 之前最多几段从少到多排列：选择分支按顺序尝试，第一个成功的就是结果，
+/// 而 `::` 之前有 n 段的地址只有前面最多 n 段以上的分支才能匹配，第一个这样的分支 `::` 之后允许的段数
+/// 也一定够用，所以匹配到的总是整个地址。反过来排列时 `2001:db8::8a2e:370:7334` 会先被
+/// `(?:h:){1,6}:h` 匹配成 `2001:db8::8a2e`，后面的 `:` 恰好满足 `\b`
+const IP_ADDRESS: &str = concat!(
+    r"\b(?:(?:25[0-5]|2[0-4][0-9]|1[0-9]{2}|[1-9]?[0-9])\.){3}(?:25[0-5]|2[0-4][0-9]|1[0-9]{2}|[1-9]?[0-9])\b",
+    r"|\b(?:[0-9A-Fa-f]{1,4}:){7}[0-9A-Fa-f]{1,4}\b",
+    r"|\b[0-9A-Fa-f]{1,4}:(?::[0-9A-Fa-f]{1,4}){1,6}\b",
+    r"|\b(?:[0-9A-Fa-f]{1,4}:){1,2}(?::[0-9A-Fa-f]{1,4}){1,5}\b",
+    r"|\b(?:[0-9A-Fa-f]{1,4}:){1,3}(?::[0-9A-Fa-f]{1,4}){1,4}\b",
+    r"|\b(?:[0-9A-Fa-f]{1,4}:){1,4}(?::[0-9A-Fa-f]{1,4}){1,3}\b",
+    r"|\b(?:[0-9A-Fa-f]{1,4}:){1,5}(?::[0-9A-Fa-f]{1,4}){1,2}\b",
+    r"|\b(?:[0-9A-Fa-f]{1,4}:){1,6}:[0-9A-Fa-f]{1,4}\b",
+    r"|::(?:[0-9A-Fa-f]{1,4}:){0,6}[0-9A-Fa-f]{1,4}\b",
+);
+
+/// 返回内置模式对应的正则表达式
+///
+/// # 示例
+/// `resolve_builtin(BuiltinPattern::Uuid)` 可以匹配 `123e4567-e89b-12d3-a456-426614174000`
+pub fn resolve_builtin(b: BuiltinPattern) -> &'static str {
+    match b {
+        BuiltinPattern::IpAddress => IP_ADDRESS,
+        BuiltinPattern::Email => r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b",
+        BuiltinPattern::Url => r#"\b(?:https?|ftp)://[^\s<>"']+"#,
+        BuiltinPattern::Uuid => {
+            r"\b[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12}\b"
+        }
+        BuiltinPattern::CreditCard => concat!(
+            // Visa、MasterCard（51-55 和 2221-2720 号段）、Discover：16 位，四位一组
+            r"\b(?:4[0-9]{3}|5[1-5][0-9]{2}|2[2-7][0-9]{2}|6(?:011|5[0-9]{2}))(?:[ -]?[0-9]{4}){3}\b",
+            // American Express：15 位，4-6-5 分组
+            r"|\b3[47][0-9]{2}[ -]?[0-9]{6}[ -]?[0-9]{5}\b",
+        ),
+    }
+}
+
+/// 返回内置模式在命令行上的名字，例如 `ip-address`
+pub fn name(b: BuiltinPattern) -> String {
+    b.to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// 把用户模式和内置模式合并成一个正则表达式
+///
+/// 每个模式都用非捕获分组包起来再用 `|` 连接，任意一个匹配即视为匹配。
+pub fn combine(user: &[String], builtins: &[BuiltinPattern]) -> String {
+    user.iter()
+        .map(String::as_str)
+        .chain(builtins.iter().map(|b| resolve_builtin(*b)))
+        .map(|p| format!("(?:{})", p))
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    /// 每个 good 样本都必须整个被匹配，放在一句话中间时也一样；bad 样本中不能有任何匹配
+    fn check(b: BuiltinPattern, good: &[&str], bad: &[&str]) {
+        let re = Regex::new(resolve_builtin(b)).unwrap();
+        for g in good {
+            assert_eq!(re.find(g).map(|m| m.as_str()), Some(*g), "{}: {}", name(b), g);
+            let line = format!("see {} here", g);
+            assert_eq!(re.find(&line).map(|m| m.as_str()), Some(*g), "{}: {}", name(b), line);
+        }
+        for s in bad {
+            assert_eq!(re.find(s).map(|m| m.as_str()), None, "{}: {}", name(b), s);
+        }
+    }
+
+    #[test]
+    fn ip_address() {
+        check(
+            BuiltinPattern::IpAddress,
+            &[
+                "192.168.0.1",
+                "0.0.0.0",
+                "255.255.255.255",
+                "2001:0db8:85a3:0000:0000:8a2e:0370:7334",
+                "2001:db8::8a2e:370:7334",
+                "2001:db8:85a3::8a2e:370:7334",
+                "1::2:3:4:5:6:7",
+                "1:2:3:4:5:6::7",
+                "fe80::1",
+                "FE80::ABCD:1",
+                "::1",
+                "::ffff:1:2",
+            ],
+            &["256.1.1.1", "1.1.1.256", "1.2.3", "std::vec::Vec", "a::", "::", "1:2:3"],
+        );
+    }
+
+    #[test]
+    fn email() {
+        check(
+            BuiltinPattern::Email,
+            &["user@example.com", "first.last+tag@sub.example.org", "a_b%c@x-y.io"],
+            &["user@localhost", "@example.com", "user@example.c", "no at sign.com"],
+        );
+    }
+
+    #[test]
+    fn url() {
+        check(
+            BuiltinPattern::Url,
+            &["https://example.com/path?q=1&r=2", "http://localhost:8080", "ftp://files.example.org/a.txt"],
+            &["example.com", "mailto:user@example.com", "htp://x", "https//example.com"],
+        );
+    }
+
+    #[test]
+    fn uuid() {
+        check(
+            BuiltinPattern::Uuid,
+            &["123e4567-e89b-12d3-a456-426614174000", "123E4567-E89B-12D3-A456-426614174000"],
+            &[
+                "123e4567-e89b-12d3-a456-42661417400",
+                "123e4567e89b12d3a456426614174000",
+                "g23e4567-e89b-12d3-a456-426614174000",
+                "123e4567-e89b-12d3-a456-4266141740001",
+            ],
+        );
+    }
+
+    #[test]
+    fn credit_card() {
+        check(
+            BuiltinPattern::CreditCard,
+            &[
+                "4111 1111 1111 1111",
+                "4111-1111-1111-1111",
+                "4111111111111111",
+                "5500 0000 0000 0004",
+                "2221000000000009",
+                "6011000000000004",
+                "378282246310005",
+                "3782 822463 10005",
+            ],
+            &["1234 5678 9012 3456", "4111 1111 1111 111", "5600000000000000", "41111111111111112"],
+        );
+    }
+}
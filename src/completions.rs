@@ -0,0 +1,256 @@
+// 命令行补全脚本
+//
+// --generate-completions 根据 clap 中定义的参数生成各个 shell 的补全脚本，
+// 参数增加或者改名后重新生成一次即可，不需要手工维护。
+//
+// 补全的内容：
+// * 所有选项的长名字和短名字（包括可见的别名），zsh / fish / PowerShell 还会显示说明
+// * 取值是枚举的选项（例如 `--type-filter`）补全可选的值
+// * 取值是 FILE 的选项和位置参数补全文件名
+//
+// 安装方式：
+// * bash: `pgrep --generate-completions bash > /etc/bash_completion.d/pgrep`
+// * zsh: `pgrep --generate-completions zsh > "${fpath[1]}/_pgrep"`
+// * fish: `pgrep --generate-completions fish > ~/.config/fish/completions/pgrep.fish`
+// * PowerShell: 把 `pgrep --generate-completions powershell` 的输出加入 `$PROFILE`
+// * elvish: 把 `pgrep --generate-completions elvish` 的输出加入 `~/.config/elvish/rc.elv`
+//
+// 相关文档:
+// * clap::Command: <https://docs.rs/clap/latest/clap/struct.Command.html>
+// * bash 的 complete 内建命令: <https://www.gnu.org/software/bash/manual/html_node/Programmable-Completion-Builtins.html>
+// * zsh 的 _arguments: <https://zsh.sourceforge.io/Doc/Release/Completion-System.html#Completion-Functions>
+// * fish 的 complete: <https://fishshell.com/docs/current/cmds/complete.html>
+
+use clap::{Arg, Command, ValueEnum};
+
+/// 支持的 shell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+    Elvish,
+}
+
+/// 从 clap 的参数定义中整理出的一个选项
+struct Opt {
+    longs: Vec<String>,
+    shorts: Vec<char>,
+    help: String,
+    takes_value: bool,
+    values: Vec<String>,
+    is_file: bool,
+}
+
+/// 收集所有可见的选项，位置参数不在其中
+fn options(cmd: &Command) -> Vec<Opt> {
+    cmd.get_arguments()
+        .filter(|a| !a.is_positional() && !a.is_hide_set())
+        .map(|a: &Arg| Opt {
+            longs: a
+                .get_long()
+                .into_iter()
+                .chain(a.get_visible_aliases().unwrap_or_default())
+                .map(str::to_string)
+                .collect(),
+            shorts: a
+                .get_short()
+                .into_iter()
+                .chain(a.get_visible_short_aliases().unwrap_or_default())
+                .collect(),
+            // 只取说明的第一行，和 `-h` 的简短帮助一致
+            help: a
+                .get_help()
+                .map(|h| h.to_string().lines().next().unwrap_or_default().to_string())
+                .unwrap_or_default(),
+            takes_value: a.get_action().takes_values(),
+            values: a
+                .get_possible_values()
+                .iter()
+                .filter(|v| !v.is_hide_set())
+                .map(|v| v.get_name().to_string())
+                .collect(),
+            is_file: a
+                .get_value_names()
+                .is_some_and(|n| n.iter().any(|n| n.as_str() == "FILE")),
+        })
+        .collect()
+}
+
+/// 生成补全脚本
+///
+/// # 参数
+/// * `shell` - 目标 shell
+/// * `cmd` - 完整的命令定义，通常是 `Args::command()`
+pub fn generate(shell: Shell, mut cmd: Command) -> String {
+    // build 之后才有 clap 自动添加的 --help 和 --version
+    cmd.build();
+    let name = cmd.get_name().to_string();
+    let opts = options(&cmd);
+    match shell {
+        Shell::Bash => bash(&name, &opts),
+        Shell::Zsh => zsh(&name, &opts),
+        Shell::Fish => fish(&name, &opts),
+        Shell::Powershell => powershell(&name, &opts),
+        Shell::Elvish => elvish(&name, &opts),
+    }
+}
+
+/// 选项的所有写法，例如 `-p --pattern -e --regexp`
+fn flags(o: &Opt) -> Vec<String> {
+    o.shorts
+        .iter()
+        .map(|s| format!("-{}", s))
+        .chain(o.longs.iter().map(|l| format!("--{}", l)))
+        .collect()
+}
+
+/// 用单引号包围，内容中的单引号按 POSIX shell 的方式转义
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+fn bash(name: &str, opts: &[Opt]) -> String {
+    let all: Vec<String> = opts.iter().flat_map(flags).collect();
+    let mut cases = String::new();
+    for o in opts.iter().filter(|o| o.takes_value) {
+        let words = flags(o).join("|");
+        let body = if !o.values.is_empty() {
+            format!("COMPREPLY=($(compgen -W {} -- \"$cur\"))", sh_quote(&o.values.join(" ")))
+        } else if o.is_file {
+            "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string()
+        } else {
+            "COMPREPLY=()".to_string()
+        };
+        cases.push_str(&format!("        {})\n            {}\n            return 0\n            ;;\n", words, body));
+    }
+    let func = format!("_{}", name.replace('-', "_"));
+    format!(
+        r#"# bash completion for {name}
+{func}() {{
+    local cur prev
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+
+    case "$prev" in
+{cases}    esac
+
+    if [[ "$cur" == -* ]]; then
+        COMPREPLY=($(compgen -W {all} -- "$cur"))
+        return 0
+    fi
+    COMPREPLY=($(compgen -f -- "$cur"))
+}}
+complete -F {func} -o bashdefault -o default {name}
+"#,
+        all = sh_quote(&all.join(" ")),
+    )
+}
+
+fn zsh(name: &str, opts: &[Opt]) -> String {
+    // _arguments 的说明写在 [] 中，其中的 [ ] : \ 需要转义
+    let esc = |s: &str| {
+        s.replace('\\', r"\\")
+            .replace('[', r"\[")
+            .replace(']', r"\]")
+            .replace(':', r"\:")
+            .replace('\'', r"'\''")
+    };
+    let mut specs = String::new();
+    for o in opts {
+        let action = if !o.takes_value {
+            String::new()
+        } else if !o.values.is_empty() {
+            format!(":value:({})", o.values.join(" "))
+        } else if o.is_file {
+            ":file:_files".to_string()
+        } else {
+            ":value:".to_string()
+        };
+        // 可以重复的选项（例如 -p）不排除自己
+        for f in flags(o) {
+            specs.push_str(&format!("    '*{}[{}]{}' \\\n", f, esc(&o.help), action));
+        }
+    }
+    format!(
+        "#compdef {name}\n\n_{name}() {{\n    _arguments -s \\\n{specs}    '*:file:_files'\n}}\n\n_{name} \"$@\"\n",
+        name = name,
+        specs = specs
+    )
+}
+
+fn fish(name: &str, opts: &[Opt]) -> String {
+    let q = |s: &str| format!("'{}'", s.replace('\\', r"\\").replace('\'', r"\'"));
+    let mut out = format!("# fish completion for {}\n", name);
+    for o in opts {
+        let mut line = format!("complete -c {}", name);
+        for s in &o.shorts {
+            line.push_str(&format!(" -s {}", s));
+        }
+        for l in &o.longs {
+            line.push_str(&format!(" -l {}", l));
+        }
+        if !o.help.is_empty() {
+            line.push_str(&format!(" -d {}", q(&o.help)));
+        }
+        if o.takes_value {
+            line.push_str(" -r");
+            if !o.values.is_empty() {
+                line.push_str(&format!(" -f -a {}", q(&o.values.join(" "))));
+            } else if o.is_file {
+                line.push_str(" -F");
+            }
+        }
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+fn powershell(name: &str, opts: &[Opt]) -> String {
+    let q = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let mut items = String::new();
+    for o in opts {
+        let help = if o.help.is_empty() { o.longs.first().cloned().unwrap_or_default() } else { o.help.clone() };
+        for f in flags(o) {
+            items.push_str(&format!(
+                "        [CompletionResult]::new({}, {}, [CompletionResultType]::ParameterName, {})\n",
+                q(&f),
+                q(&f),
+                q(&help)
+            ));
+        }
+    }
+    format!(
+        r#"using namespace System.Management.Automation
+
+Register-ArgumentCompleter -Native -CommandName {q_name} -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    @(
+{items}    ) | Where-Object {{ $_.CompletionText -like "$wordToComplete*" }}
+}}
+"#,
+        q_name = q(name),
+    )
+}
+
+fn elvish(name: &str, opts: &[Opt]) -> String {
+    let q = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let mut items = String::new();
+    for o in opts {
+        for f in flags(o) {
+            items.push_str(&format!("        edit:complex-candidate {} &display={}\n", q(&f), q(&format!("{} ({})", f, o.help))));
+        }
+    }
+    format!(
+        r#"set edit:completion:arg-completer[{name}] = {{|@words|
+    var cur = $words[-1]
+    if (has-prefix $cur -) {{
+{items}    }} else {{
+        edit:complete-filename $cur
+    }}
+}}
+"#,
+    )
+}
@@ -0,0 +1,112 @@
+// 压缩格式
+//
+// -z / --search-zip 的格式检测和分发。先看文件开头的魔数，没有识别出来时再看扩展名，
+// 然后交给对应的解压实现。
+//
+// | 格式  | 魔数                | 扩展名        | 解压方式                           |
+// |-------|---------------------|---------------|------------------------------------|
+// | gzip  | `1f 8b`             | .gz .tgz      | 内置，见 gzip 模块                  |
+// | bzip2 | `42 5a 68` ("BZh")¹ | .bz2 .tbz2    | 外部的 `bzip2 -dc`，bzip2 feature  |
+// | xz    | `fd 37 7a 58 5a 00` | .xz .txz      | 外部的 `xz -dc`，xz feature        |
+// | zstd  | `28 b5 2f fd`       | .zst .tzst    | 外部的 `zstd -dcq`，zstd feature   |
+//
+// ¹ 后面还要跟着块大小和块魔数，只有 "BZh" 的普通文本不算
+//
+// 离线构建中没有 bzip2、xz2、zstd 这些解压库，所以这三种格式交给 PATH 中的同名程序，
+// 压缩数据从标准输入传入（见 preprocess::pipe），包中的成员也能这样解压。三个 feature 默认启用；
+// 关闭了 feature 或者找不到程序时，这类文件作为单个文件的错误报告出来，而不是被当成乱码静默跳过。
+//
+// 相关文档:
+// * Cargo features: <https://doc.rust-lang.org/cargo/reference/features.html>
+
+use failure::{Error, Fail};
+use std::fmt;
+use std::path::Path;
+
+use crate::gzip;
+use crate::preprocess;
+
+/// 压缩格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Format::Gzip => "gzip",
+            Format::Bzip2 => "bzip2",
+            Format::Xz => "xz",
+            Format::Zstd => "zstd",
+        };
+        f.write_str(s)
+    }
+}
+
+/// 这个构建关闭了对应 feature 的压缩格式
+#[derive(Debug, Fail)]
+#[fail(display = "{0} 是 {1} 压缩文件，但这个构建关闭了 {1} feature，请用 `cargo build --features {1}` 重新构建", path, format)]
+pub struct Unsupported {
+    path: String,
+    format: Format,
+}
+
+/// 检测文件的压缩格式
+///
+/// 魔数优先；内容不以任何已知魔数开头时按扩展名判断，
+/// 这样扩展名是 `.gz` 但内容损坏的文件会在解压时报告错误，而不是被当成普通文本。
+///
+/// # 返回值
+/// 不是压缩文件时返回 None
+pub fn detect(p: &Path, bts: &[u8]) -> Option<Format> {
+    const MAGIC: [(&[u8], Format); 3] = [
+        (&[0x1f, 0x8b], Format::Gzip),
+        (&[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00], Format::Xz),
+        (&[0x28, 0xb5, 0x2f, 0xfd], Format::Zstd),
+    ];
+    if let Some((_, f)) = MAGIC.iter().find(|(m, _)| bts.starts_with(m)) {
+        return Some(*f);
+    }
+    // "BZh" 太容易出现在普通文本的开头，还要检查块大小和第一个块（或流结束标记）的魔数
+    if bts.len() >= 10
+        && bts.starts_with(b"BZh")
+        && (b'1'..=b'9').contains(&bts[3])
+        && (bts[4..10] == [0x31, 0x41, 0x59, 0x26, 0x53, 0x59] || bts[4..10] == [0x17, 0x72, 0x45, 0x38, 0x50, 0x90])
+    {
+        return Some(Format::Bzip2);
+    }
+
+    let ext = p.extension()?.to_string_lossy().to_ascii_lowercase();
+    match ext.as_str() {
+        "gz" | "tgz" => Some(Format::Gzip),
+        "bz2" | "tbz2" => Some(Format::Bzip2),
+        "xz" | "txz" => Some(Format::Xz),
+        "zst" | "tzst" => Some(Format::Zstd),
+        _ => None,
+    }
+}
+
+/// 按检测出的格式解压整个文件
+///
+/// 由多个流拼接而成的文件（例如日志程序不断追加的 gzip 或 zstd 帧）会被完整读完，
+/// 结果是所有流内容的拼接。bzip2、xz、zstd 由外部程序解压，它们的错误输出附在错误信息中。
+pub fn decompress(p: &Path, format: Format, bts: &[u8]) -> Result<Vec<u8>, Error> {
+    let (program, args): (&str, &[&str]) = match format {
+        Format::Gzip => return Ok(gzip::decompress(p, bts)?),
+        Format::Bzip2 if cfg!(feature = "bzip2") => ("bzip2", &["-dc"]),
+        Format::Xz if cfg!(feature = "xz") => ("xz", &["-dc"]),
+        Format::Zstd if cfg!(feature = "zstd") => ("zstd", &["-dcq"]),
+        format => {
+            return Err(Unsupported {
+                path: p.display().to_string(),
+                format,
+            }
+            .into());
+        }
+    };
+    preprocess::pipe(program, args, p, bts)
+}
@@ -0,0 +1,714 @@
+// 配置文件与命名 profile
+//
+// 配置文件采用 TOML 的一个子集，用来定义命名 profile（`--rules` 的规则文件也使用同样的语法，见 load_rules）：
+//
+// ```toml
+// [profile.code]
+// fuzzy = 1
+//
+// [profile.logs]
+// inherits = "code"
+// fuzzy-cpu-limit = 10
+// ```
+//
+// 每个 profile 中的键就是命令行长选项的名字（`_` 和 `-` 等价），
+// 使用 `--profile NAME` 激活时，这些键值会被展开成命令行参数，
+// 插在用户实际输入的参数之前，所以命令行上显式给出的选项总是优先。
+//
+// 支持的值类型：
+// * 字符串 `"..."`（支持 `\"` `\\` `\n` `\t` 转义）和 `'...'`（不转义）
+// * 整数和浮点数
+// * 布尔值 `true` / `false`：`true` 展开为不带值的开关，`false` 则什么也不展开
+// * 单行数组 `["a", "b"]`：每个元素展开为一次重复的选项
+//
+// 配置文件的查找顺序：
+// 1. `--config FILE` 指定的文件（只读这一个，文件不存在时报错）
+// 2. 否则依次读取 `$XDG_CONFIG_HOME/pgrep/config.toml`（默认 `~/.config/pgrep/config.toml`）
+//    和当前目录下的 `.pgrep.toml`，不存在的文件直接跳过，同名 profile 以后读取的为准
+
+use failure::{Error, Fail};
+use regex::Regex;
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::output::Output;
+
+/// 配置文件中的值
+#[derive(Debug, Clone)]
+pub enum Value {
+    Str(String),
+    // 数字原样保留文本，交给 clap 去解析成具体的类型
+    Num(String),
+    Bool(bool),
+    List(Vec<Value>),
+}
+
+/// 一个命名 profile
+///
+/// # 字段
+/// * `name` - profile 名字，即 `[profile.NAME]` 中的 NAME
+/// * `source` - 定义它的配置文件
+/// * `inherits` - 继承的父 profile
+/// * `options` - 按出现顺序排列的选项
+#[derive(Debug)]
+pub struct Profile {
+    pub name: String,
+    pub source: PathBuf,
+    pub inherits: Option<String>,
+    pub options: Vec<(String, Value)>,
+}
+
+/// 已加载的全部配置
+#[derive(Debug, Default)]
+pub struct Config {
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// 配置文件语法错误
+#[derive(Debug, Fail)]
+#[fail(display = "{}:{}: {}", path, line, msg)]
+pub struct ConfigErr {
+    path: String,
+    line: usize,
+    msg: String,
+}
+
+/// 请求的 profile 不存在
+#[derive(Debug, Fail)]
+#[fail(display = "未知的 profile '{}'（已定义: {}）", name, known)]
+pub struct UnknownProfile {
+    name: String,
+    known: String,
+}
+
+/// profile 的继承关系出现了循环
+#[derive(Debug, Fail)]
+#[fail(display = "profile 继承出现循环: {}", chain)]
+pub struct ProfileCycle {
+    chain: String,
+}
+
+/// 加载配置文件
+///
+/// # 参数
+/// * `explicit` - `--config` 指定的文件；为 `None` 时读取默认位置的配置文件
+///
+/// # 返回值
+/// * `Ok(Config)` - 所有配置文件合并后的结果，没有任何配置文件时为空配置
+/// * `Err(Error)` - 读取失败或语法错误
+pub fn load(explicit: Option<&Path>) -> Result<Config, Error> {
+    let mut cfg = Config::default();
+    match explicit {
+        Some(p) => parse_file(p, &std::fs::read_to_string(p)?, &mut cfg)?,
+        None => {
+            for p in default_paths() {
+                if p.is_file() {
+                    parse_file(&p, &std::fs::read_to_string(&p)?, &mut cfg)?;
+                }
+            }
+        }
+    }
+    Ok(cfg)
+}
+
+/// 默认的配置文件位置，越靠后优先级越高
+fn default_paths() -> Vec<PathBuf> {
+    let mut res = Vec::new();
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".config")));
+    if let Some(b) = base {
+        res.push(b.join("pgrep").join("config.toml"));
+    }
+    res.push(PathBuf::from(".pgrep.toml"));
+    res
+}
+
+/// 解析一个配置文件的内容并合并到 `cfg` 中
+fn parse_file(path: &Path, text: &str, cfg: &mut Config) -> Result<(), Error> {
+    // 当前所在的 [profile.NAME] 段
+    let mut current: Option<String> = None;
+
+    for (i, raw) in text.lines().enumerate() {
+        let err = |msg: String| ConfigErr {
+            path: path.display().to_string(),
+            line: i + 1,
+            msg,
+        };
+
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        // 段标题
+        if let Some(h) = line.strip_prefix('[') {
+            let h = h
+                .strip_suffix(']')
+                .ok_or_else(|| err("段标题缺少 ']'".to_string()))?
+                .trim();
+            let name = h
+                .strip_prefix("profile.")
+                .ok_or_else(|| err(format!("不支持的段 [{}]，目前只支持 [profile.NAME]", h)))?;
+            let name = name.trim().trim_matches('"').to_string();
+            cfg.profiles.insert(
+                name.clone(),
+                Profile {
+                    name: name.clone(),
+                    source: path.to_path_buf(),
+                    inherits: None,
+                    options: Vec::new(),
+                },
+            );
+            current = Some(name);
+            continue;
+        }
+
+        // 键值对
+        let (k, v) = line
+            .split_once('=')
+            .ok_or_else(|| err(format!("无法解析的行: {}", line)))?;
+        let key = k.trim().trim_matches('"').replace('_', "-");
+        let val = parse_value(v.trim()).map_err(err)?;
+        let profile = current
+            .as_ref()
+            .and_then(|n| cfg.profiles.get_mut(n))
+            .ok_or_else(|| err("选项必须写在 [profile.NAME] 段内".to_string()))?;
+
+        if key == "inherits" {
+            match val {
+                Value::Str(s) => profile.inherits = Some(s),
+                _ => return Err(err("inherits 的值必须是字符串".to_string()).into()),
+            }
+        } else {
+            profile.options.push((key, val));
+        }
+    }
+    Ok(())
+}
+
+/// 去掉行尾的 `#` 注释，字符串中的 `#` 不算注释
+fn strip_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return &line[..i],
+            None => {}
+        }
+    }
+    line
+}
+
+/// 解析一个完整的值，值后面不允许再有其他内容
+fn parse_value(s: &str) -> Result<Value, String> {
+    let (v, rest) = parse_value_prefix(s)?;
+    if !rest.trim().is_empty() {
+        return Err(format!("值后面有多余的内容: {}", rest.trim()));
+    }
+    Ok(v)
+}
+
+/// 从字符串开头解析一个值，返回值和剩余部分
+fn parse_value_prefix(s: &str) -> Result<(Value, &str), String> {
+    let s = s.trim_start();
+
+    // 双引号字符串，处理转义
+    if let Some(body) = s.strip_prefix('"') {
+        let mut out = String::new();
+        let mut chars = body.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::Str(out), &body[i + 1..])),
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((_, e @ ('"' | '\\'))) => out.push(e),
+                    Some((_, e)) => return Err(format!("不支持的转义 \\{}", e)),
+                    None => break,
+                },
+                c => out.push(c),
+            }
+        }
+        return Err("字符串缺少结尾的 '\"'".to_string());
+    }
+
+    // 单引号字符串，内容原样保留
+    if let Some(body) = s.strip_prefix('\'') {
+        let end = body.find('\'').ok_or("字符串缺少结尾的 '''")?;
+        return Ok((Value::Str(body[..end].to_string()), &body[end + 1..]));
+    }
+
+    // 数组
+    if let Some(mut body) = s.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            body = body.trim_start();
+            if let Some(rest) = body.strip_prefix(']') {
+                return Ok((Value::List(items), rest));
+            }
+            let (v, rest) = parse_value_prefix(body)?;
+            items.push(v);
+            body = rest.trim_start();
+            if let Some(rest) = body.strip_prefix(',') {
+                body = rest;
+            } else if !body.starts_with(']') {
+                return Err("数组元素之间缺少 ','".to_string());
+            }
+        }
+    }
+
+    // 布尔值和数字：读到下一个分隔符为止
+    let end = s.find([',', ']', ' ', '\t']).unwrap_or(s.len());
+    let (word, rest) = s.split_at(end);
+    match word {
+        "true" => Ok((Value::Bool(true), rest)),
+        "false" => Ok((Value::Bool(false), rest)),
+        w if !w.is_empty() && w.parse::<f64>().is_ok() => Ok((Value::Num(w.to_string()), rest)),
+        w => Err(format!("无法识别的值: {}", w)),
+    }
+}
+
+/// 按继承关系解析 profile，返回从最顶层祖先到 `name` 自身的链
+///
+/// # 错误
+/// * `UnknownProfile` - `name` 或者链上某个 `inherits` 指向的 profile 不存在
+/// * `ProfileCycle` - 继承关系出现循环
+fn resolve_chain<'a>(cfg: &'a Config, name: &str) -> Result<Vec<&'a Profile>, Error> {
+    let unknown = |n: &str| UnknownProfile {
+        name: n.to_string(),
+        known: if cfg.profiles.is_empty() {
+            "无".to_string()
+        } else {
+            cfg.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+        },
+    };
+
+    let mut seen = vec![name.to_string()];
+    let mut chain = Vec::new();
+    let mut cur = cfg.profiles.get(name).ok_or_else(|| unknown(name))?;
+    loop {
+        chain.push(cur);
+        let Some(parent) = &cur.inherits else { break };
+        let cycle = seen.contains(parent);
+        seen.push(parent.clone());
+        if cycle {
+            return Err(ProfileCycle {
+                chain: seen.join(" -> "),
+            }
+            .into());
+        }
+        cur = cfg.profiles.get(parent).ok_or_else(|| unknown(parent))?;
+    }
+    chain.reverse();
+    Ok(chain)
+}
+
+/// 规则的严重程度，决定输出中的级别和退出状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    /// 有这个级别的匹配时退出状态是 1
+    Error,
+}
+
+impl Severity {
+    /// 规则文件中的写法，也用于文本和 JSON 输出
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// 规则文件中的一条规则
+///
+/// # 字段
+/// * `name` - 规则的 id，没有写时为 `rule-N`（N 从 1 开始），同一个文件中不能重复
+/// * `paths` - 这条规则适用的路径通配符，为空时适用于所有文件
+/// * `pattern` - 在这些路径中搜索的正则表达式
+/// * `message` - 输出中代替匹配行的说明
+/// * `severity` - 严重程度，默认是 warning
+/// * `case_insensitive` - 模式不区分大小写
+/// * `re` - 编译好的模式，见 RuleDef::source
+#[derive(Debug)]
+pub struct RuleDef {
+    pub name: String,
+    pub paths: Vec<String>,
+    pub pattern: String,
+    pub message: Option<String>,
+    pub severity: Severity,
+    pub case_insensitive: bool,
+    pub re: Regex,
+}
+
+impl RuleDef {
+    /// 加上了规则级别选项的模式，和其他规则的模式合并时每个模式都在自己的分组中，标志不会影响其他规则
+    pub fn source(&self) -> String {
+        with_flags(&self.pattern, self.case_insensitive)
+    }
+
+    /// 输出中的说明，规则没有 `message` 时用去掉首尾空白的匹配行
+    pub fn message_or<'a>(&'a self, tx: &'a str) -> &'a str {
+        self.message.as_deref().unwrap_or(tx.trim())
+    }
+}
+
+/// 读取 `--rules` 指定的规则文件
+///
+/// 规则文件使用和配置文件相同的 TOML 子集，每条规则是一个 `[[rule]]` 段：
+///
+/// ```toml
+/// [[rule]]
+/// id = "rust-unsafe"
+/// paths = ["src/**/*.rs"]
+/// pattern = 'unsafe\s*\{'
+/// message = "新代码中不要使用 unsafe"
+/// severity = "error"
+///
+/// [[rule]]
+/// paths = "scripts/*.py"
+/// pattern = "eval\\("
+/// case_insensitive = true
+/// ```
+///
+/// `pattern` 是必需的；`paths` 可以是单个字符串或字符串数组，省略时规则适用于所有文件；
+/// `name` 是 `id` 的旧写法；`severity` 是 `error`、`warning`（默认）或 `info`。
+/// 每条规则的模式在这里编译，`prepare` 把模式变成实际编译的文本（例如 `-x` 的 `(?x)`），
+/// 模式无效或者 id 重复时报告规则所在的行和它的 id。
+pub fn load_rules(path: &Path, prepare: impl Fn(&str) -> String) -> Result<Vec<RuleDef>, Error> {
+    let text = std::fs::read_to_string(path)?;
+    // 读到一半的规则，缺少必需的键时报告段标题所在的行
+    struct Partial {
+        line: usize,
+        name: Option<String>,
+        paths: Vec<String>,
+        pattern: Option<String>,
+        message: Option<String>,
+        severity: Severity,
+        case_insensitive: bool,
+    }
+    let mut rules: Vec<Partial> = Vec::new();
+
+    for (i, raw) in text.lines().enumerate() {
+        let err = |line: usize, msg: String| ConfigErr {
+            path: path.display().to_string(),
+            line,
+            msg,
+        };
+
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "[[rule]]" {
+            rules.push(Partial {
+                line: i + 1,
+                name: None,
+                paths: Vec::new(),
+                pattern: None,
+                message: None,
+                severity: Severity::default(),
+                case_insensitive: false,
+            });
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(err(i + 1, format!("不支持的段 {}，规则文件中只能有 [[rule]]", line)).into());
+        }
+
+        let (k, v) = line
+            .split_once('=')
+            .ok_or_else(|| err(i + 1, format!("无法解析的行: {}", line)))?;
+        let val = parse_value(v.trim()).map_err(|m| err(i + 1, m))?;
+        let rule = rules
+            .last_mut()
+            .ok_or_else(|| err(i + 1, "键值对必须写在 [[rule]] 段内".to_string()))?;
+        let as_str = |v: Value| match v {
+            Value::Str(s) => Ok(s),
+            _ => Err(err(i + 1, format!("{} 的值必须是字符串", k.trim()))),
+        };
+        match k.trim().trim_matches('"').replace('-', "_").as_str() {
+            "id" | "name" => rule.name = Some(as_str(val)?),
+            "paths" | "path" => match val {
+                Value::List(items) => {
+                    for it in items {
+                        rule.paths.push(as_str(it)?);
+                    }
+                }
+                v => rule.paths.push(as_str(v)?),
+            },
+            "pattern" => rule.pattern = Some(as_str(val)?),
+            "message" => rule.message = Some(as_str(val)?),
+            "severity" => {
+                rule.severity = match as_str(val)?.as_str() {
+                    "error" => Severity::Error,
+                    "warning" => Severity::Warning,
+                    "info" => Severity::Info,
+                    s => return Err(err(i + 1, format!("未知的 severity '{}'，可选的值: error, warning, info", s)).into()),
+                }
+            }
+            "case_insensitive" => match val {
+                Value::Bool(b) => rule.case_insensitive = b,
+                _ => return Err(err(i + 1, "case_insensitive 的值必须是 true 或 false".to_string()).into()),
+            },
+            k => return Err(err(i + 1, format!("规则中不支持的键 {}", k)).into()),
+        }
+    }
+
+    // 已经用过的 id 和定义它的行
+    let mut ids: BTreeMap<String, usize> = BTreeMap::new();
+    rules
+        .into_iter()
+        .enumerate()
+        .map(|(n, r)| {
+            let err = |msg: String| ConfigErr {
+                path: path.display().to_string(),
+                line: r.line,
+                msg,
+            };
+            let name = r.name.unwrap_or_else(|| format!("rule-{}", n + 1));
+            if let Some(first) = ids.insert(name.clone(), r.line) {
+                return Err(err(format!("规则 id '{}' 重复，第 {} 行已经定义过", name, first)).into());
+            }
+            let pattern = r.pattern.ok_or_else(|| err(format!("规则 {} 缺少 pattern", name)))?;
+            let re = Regex::new(&prepare(&with_flags(&pattern, r.case_insensitive)))
+                .map_err(|e| err(format!("规则 {} 的模式无效: {}", name, e)))?;
+            Ok(RuleDef {
+                name,
+                paths: r.paths,
+                pattern,
+                message: r.message,
+                severity: r.severity,
+                case_insensitive: r.case_insensitive,
+                re,
+            })
+        })
+        .collect()
+}
+
+/// 把规则级别的选项写成模式中的标志
+fn with_flags(pattern: &str, case_insensitive: bool) -> String {
+    if case_insensitive {
+        format!("(?i){}", pattern)
+    } else {
+        pattern.to_string()
+    }
+}
+
+/// 把 profile（连同继承来的选项）展开成命令行参数
+///
+/// 父 profile 的选项排在前面，子 profile 的选项排在后面，
+/// 配合 clap 的 `args_override_self`，后出现的单值选项会覆盖先出现的。
+pub fn profile_args(cfg: &Config, name: &str) -> Result<Vec<OsString>, Error> {
+    let mut out = Vec::new();
+    for p in resolve_chain(cfg, name)? {
+        for (k, v) in &p.options {
+            push_option(&mut out, k, v);
+        }
+    }
+    Ok(out)
+}
+
+/// 把一个键值对转换成命令行参数追加到 `out`
+///
+/// 带值的选项使用 `--key=value` 形式，这样以 `-` 开头的值也不会被误认为是选项
+fn push_option(out: &mut Vec<OsString>, key: &str, v: &Value) {
+    match v {
+        Value::Bool(true) => out.push(format!("--{}", key).into()),
+        Value::Bool(false) => {}
+        Value::Str(s) | Value::Num(s) => out.push(format!("--{}={}", key, s).into()),
+        Value::List(items) => {
+            for it in items {
+                push_option(out, key, it);
+            }
+        }
+    }
+}
+
+/// 输出所有已定义的 profile 以及定义它们的文件
+///
+/// 和搜索结果一样经过 Output，`--list-profiles | head -1` 关闭管道时安静地退出
+pub fn list_profiles(cfg: &Config, out: &Output) {
+    if cfg.profiles.is_empty() {
+        out.line(format_args!("没有定义任何 profile"));
+        return;
+    }
+    for p in cfg.profiles.values() {
+        match &p.inherits {
+            Some(parent) => out.line(format_args!("{}\t(继承 {})\t{}", p.name, parent, p.source.display())),
+            None => out.line(format_args!("{}\t{}", p.name, p.source.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// 解析一段配置文件文本
+    fn config(text: &str) -> Config {
+        let mut cfg = Config::default();
+        parse_file(Path::new("test.toml"), text, &mut cfg).unwrap();
+        cfg
+    }
+
+    /// 像 main 一样把 profile 展开在命令行参数之前再解析
+    fn args_with_profile(cfg: &Config, name: &str, cli: &[&str]) -> Result<crate::Args, Error> {
+        let mut full: Vec<OsString> = vec!["pgrep".into()];
+        full.extend(profile_args(cfg, name)?);
+        full.extend(cli.iter().map(OsString::from));
+        Ok(crate::Args::try_parse_from(full)?)
+    }
+
+    const PROFILES: &str = r#"
+[profile.base]
+skip-empty-lines = true
+max-symlink-depth = 3
+line-prefix = ["ERROR"]
+
+[profile.logs]
+inherits = "base"
+max-symlink-depth = 5
+line-prefix = ["WARN"]
+
+[profile.loop-a]
+inherits = "loop-b"
+
+[profile.loop-b]
+inherits = "loop-a"
+"#;
+
+    #[test]
+    fn child_options_follow_parent_options() {
+        let cfg = config(PROFILES);
+        let args: Vec<String> =
+            profile_args(&cfg, "logs").unwrap().into_iter().map(|a| a.into_string().unwrap()).collect();
+        assert_eq!(
+            args,
+            [
+                "--skip-empty-lines",
+                "--max-symlink-depth=3",
+                "--line-prefix=ERROR",
+                "--max-symlink-depth=5",
+                "--line-prefix=WARN"
+            ]
+        );
+
+        // 单值选项以子 profile 为准，多值选项两者都保留
+        let a = args_with_profile(&cfg, "logs", &["-p", "x"]).unwrap();
+        assert!(a.skip_empty_lines);
+        assert_eq!(a.max_symlink_depth, 5);
+        assert_eq!(a.line_prefix, ["ERROR", "WARN"]);
+    }
+
+    #[test]
+    fn command_line_overrides_profile() {
+        let cfg = config(PROFILES);
+        let a = args_with_profile(&cfg, "logs", &["-p", "x", "--max-symlink-depth", "1"]).unwrap();
+        assert_eq!(a.max_symlink_depth, 1);
+        let a = args_with_profile(&cfg, "base", &["-p", "x"]).unwrap();
+        assert_eq!(a.max_symlink_depth, 3);
+        assert_eq!(a.line_prefix, ["ERROR"]);
+    }
+
+    #[test]
+    fn unknown_profiles_and_cycles() {
+        let cfg = config(PROFILES);
+        let e = profile_args(&cfg, "nope").unwrap_err();
+        assert!(e.downcast_ref::<UnknownProfile>().is_some());
+        assert_eq!(e.to_string(), "未知的 profile 'nope'（已定义: base, logs, loop-a, loop-b）");
+
+        let missing_parent = config("[profile.child]\ninherits = \"gone\"\n");
+        let e = profile_args(&missing_parent, "child").unwrap_err();
+        assert_eq!(e.to_string(), "未知的 profile 'gone'（已定义: child）");
+
+        let e = profile_args(&cfg, "loop-a").unwrap_err();
+        assert!(e.downcast_ref::<ProfileCycle>().is_some(), "{}", e);
+        assert!(e.to_string().starts_with("profile 继承出现循环: loop-a"), "{}", e);
+    }
+
+    /// 把 `text` 写成一个临时的规则文件再读取
+    fn rules(name: &str, text: &str) -> Result<Vec<RuleDef>, Error> {
+        let p = std::env::temp_dir().join(format!("pgrep-rules-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&p, text).unwrap();
+        let r = load_rules(&p, |s| s.to_string());
+        let _ = std::fs::remove_file(&p);
+        r
+    }
+
+    fn message(r: Result<Vec<RuleDef>, Error>) -> String {
+        let e = r.unwrap_err();
+        assert!(e.downcast_ref::<ConfigErr>().is_some(), "{}", e);
+        e.to_string()
+    }
+
+    #[test]
+    fn rules_with_defaults() {
+        let r = rules(
+            "defaults",
+            "[[rule]]\nid = \"rust-unsafe\"\npaths = [\"src/**/*.rs\", \"lib/*.rs\"]\npattern = 'unsafe\\s*\\{'\n\
+             message = \"no unsafe\"\nseverity = \"error\"\n\n# 注释\n[[rule]]\npath = \"*.py\"\npattern = \"todo\"\n\
+             case-insensitive = true\n",
+        )
+        .unwrap();
+        assert_eq!(r.len(), 2);
+        assert_eq!(r[0].name, "rust-unsafe");
+        assert_eq!(r[0].paths, ["src/**/*.rs", "lib/*.rs"]);
+        assert_eq!(r[0].severity, Severity::Error);
+        assert!(r[0].re.is_match("unsafe {"));
+        assert_eq!(r[1].name, "rule-2");
+        assert_eq!(r[1].paths, ["*.py"]);
+        assert_eq!(r[1].severity, Severity::Warning);
+        assert_eq!(r[1].source(), "(?i)todo");
+        assert!(r[1].re.is_match("# TODO"));
+        assert_eq!(r[1].message_or("  # TODO  "), "# TODO");
+    }
+
+    #[test]
+    fn duplicate_ids_name_both_lines() {
+        let m = message(rules("dup", "[[rule]]\nid = \"a\"\npattern = \"x\"\n\n[[rule]]\nid = \"a\"\npattern = \"y\"\n"));
+        assert!(m.ends_with(":5: 规则 id 'a' 重复，第 1 行已经定义过"), "{}", m);
+
+        // 自动生成的 id 也不能和显式的 id 冲突
+        let m = message(rules("dup-auto", "[[rule]]\nid = \"rule-2\"\npattern = \"x\"\n[[rule]]\npattern = \"y\"\n"));
+        assert!(m.contains(":4: 规则 id 'rule-2' 重复"), "{}", m);
+    }
+
+    #[test]
+    fn invalid_pattern_names_the_rule() {
+        let m = message(rules("invalid", "[[rule]]\nid = \"ok\"\npattern = \"x\"\n[[rule]]\nid = \"broken\"\npattern = \"a(b\"\n"));
+        assert!(m.contains(":4: 规则 broken 的模式无效"), "{}", m);
+
+        let m = message(rules("invalid-auto", "[[rule]]\npattern = \"[z-a]\"\n"));
+        assert!(m.contains(":1: 规则 rule-1 的模式无效"), "{}", m);
+    }
+
+    #[test]
+    fn malformed_rules() {
+        let m = message(rules("no-pattern", "[[rule]]\nid = \"p\"\n"));
+        assert!(m.contains(":1: 规则 p 缺少 pattern"), "{}", m);
+        let m = message(rules("severity", "[[rule]]\npattern = \"x\"\nseverity = \"fatal\"\n"));
+        assert!(m.contains(":3: 未知的 severity 'fatal'"), "{}", m);
+        let m = message(rules("section", "[profile.x]\n"));
+        assert!(m.contains(":1: 不支持的段 [profile.x]"), "{}", m);
+        let m = message(rules("outside", "pattern = \"x\"\n"));
+        assert!(m.contains(":1: 键值对必须写在 [[rule]] 段内"), "{}", m);
+        let m = message(rules("key", "[[rule]]\npattern = \"x\"\nlevel = \"error\"\n"));
+        assert!(m.contains(":3: 规则中不支持的键 level"), "{}", m);
+        let m = message(rules("not-string", "[[rule]]\npattern = 3\n"));
+        assert!(m.contains(":2: pattern 的值必须是字符串"), "{}", m);
+    }
+}
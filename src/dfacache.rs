@@ -0,0 +1,320 @@
+// --cache-dir 使用的正则表达式自动机磁盘缓存
+//
+// regex::Regex 不能序列化，能写到磁盘上的只有 regex-automata 的完整 DFA。
+// 在整个内容上查找候选行时使用的多行模式（见 lib.rs 的 scan_whole_text）被编译成一对稠密 DFA：
+// 正向的找到匹配的结尾，反向的从结尾找回开头。两个 DFA 序列化之后保存在缓存目录中，
+// 之后使用同一个模式的运行直接读取，不再构建。构建完整 DFA 比 Regex::new 慢得多，
+// 很大的选择分支（例如几千个词）可能要几百毫秒，读取只需要校验一遍字节。
+//
+// 缓存文件名是模式的 FNV-1a 64 位哈希，文件头中还保存了完整的模式，两者都相同才使用。
+// 读取失败（文件损坏、regex-automata 升级后格式不同）时重新构建并覆盖旧文件。
+// 模式不能编译成完整 DFA（例如有 Unicode 单词边界 `\b`，或者超过大小限制）时返回错误，
+// 调用者照常只使用 regex::Regex。逐行确认、输出中的匹配位置和替换仍然使用 regex::Regex。
+//
+// 相关文档:
+// * regex_automata::dfa::regex::Regex: <https://docs.rs/regex-automata/latest/regex_automata/dfa/regex/struct.Regex.html>
+// * DFA::to_bytes_native_endian: <https://docs.rs/regex-automata/latest/regex_automata/dfa/dense/struct.DFA.html#method.to_bytes_native_endian>
+// * DFA::from_bytes: <https://docs.rs/regex-automata/latest/regex_automata/dfa/dense/struct.DFA.html#method.from_bytes>
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use failure::{Error, Fail};
+use regex_automata::Input;
+use regex_automata::dfa::dense::{self, DFA};
+use regex_automata::dfa::regex::Regex as DfaRegex;
+
+use crate::log::{self, Level};
+
+/// 缓存文件开头的标记，之后是 FORMAT
+const MAGIC: &[u8; 16] = b"pgrep-dfa-cache\0";
+
+/// 缓存文件的格式版本，改变文件布局时加一，旧文件的哈希随之改变，不会被读到
+const FORMAT: u32 = 1;
+
+/// 每个 DFA 最多占用的字节数，构建时超过的模式不使用缓存
+pub const DFA_SIZE_LIMIT: usize = 64 << 20;
+
+/// 构建或读取 DFA 时的错误
+#[derive(Debug, Fail)]
+pub enum CacheError {
+    #[fail(display = "模式不能在整个内容上查找：{}", _0)]
+    NotMultiline(String),
+    #[fail(display = "模式不能编译成完整 DFA：{}", _0)]
+    Build(String),
+    #[fail(display = "缓存文件格式错误：{}", _0)]
+    Corrupt(&'static str),
+    #[fail(display = "缓存文件中的 DFA 无效：{}", _0)]
+    Deserialize(String),
+}
+
+/// DFA 是从哪里来的
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// 从缓存文件中读取
+    Cache,
+    /// 这一次构建，并且尝试写入了缓存文件
+    Built,
+}
+
+/// 模式的多行模式版本编译成的一对 DFA
+#[derive(Debug)]
+pub struct Automaton {
+    pattern: String,
+    re: DfaRegex<DFA<Vec<u32>>>,
+}
+
+impl Automaton {
+    /// 构建时使用的原始模式（没有加上多行模式标志）
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// 从 `start` 开始的第一个匹配的起始位置
+    ///
+    /// 和 `Regex::find_at` 一样，`start` 之前的内容仍然参与 `^`、`\b` 这样的判断
+    pub fn find_at(&self, text: &str, start: usize) -> Option<usize> {
+        let input = Input::new(text).span(start..text.len());
+        // 构建时没有设置 quit 字节，也没有启用 Unicode 单词边界的启发式支持，搜索不会中途放弃
+        self.re.try_search(&input).ok().flatten().map(|m| m.start())
+    }
+}
+
+/// 模式在缓存目录中的文件路径
+///
+/// 文件名是格式版本、字节序和模式的 FNV-1a 64 位哈希，不同字节序的机器共用一个目录时互不干扰
+pub fn cache_path(dir: &Path, pattern: &str) -> PathBuf {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    let key = format!("{}\0{}\0{}", FORMAT, cfg!(target_endian = "big"), pattern);
+    for b in key.bytes() {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    dir.join(format!("{:016x}.rc", h))
+}
+
+/// 读取模式的 DFA，缓存中没有时构建并写入缓存
+///
+/// # 参数
+/// * `dir` - 缓存目录，不存在时自动创建
+/// * `pattern` - 原始模式，即 `Regex::as_str`
+///
+/// # 返回值
+/// DFA 和它的来源。模式不能在整个内容上查找或者不能编译成完整 DFA 时返回错误；
+/// 缓存文件无法读取或写入只给出警告，仍然返回构建出的 DFA
+///
+/// # 示例
+/// ```
+/// use pgrep::dfacache::{load_or_build, Source};
+///
+/// let dir = std::env::temp_dir().join("pgrep-doc-dfacache");
+/// let _ = std::fs::remove_dir_all(&dir);
+/// let (a, src) = load_or_build(&dir, "wor[a-z]+")?;
+/// assert_eq!(src, Source::Built);
+/// assert_eq!(a.find_at("hello\nworld\n", 0), Some(6));
+///
+/// let (_, src) = load_or_build(&dir, "wor[a-z]+")?;
+/// assert_eq!(src, Source::Cache);
+/// # Ok::<(), failure::Error>(())
+/// ```
+pub fn load_or_build(dir: &Path, pattern: &str) -> Result<(Automaton, Source), Error> {
+    let multi = crate::multiline_source(pattern)
+        .ok_or_else(|| CacheError::NotMultiline(pattern.to_string()))?;
+    let path = cache_path(dir, &multi);
+    match fs::read(&path) {
+        Ok(bts) => match decode(&bts, &multi) {
+            Ok(re) => {
+                debug!("从缓存读取 DFA: {}", path.display());
+                return Ok((Automaton { pattern: pattern.to_string(), re }, Source::Cache));
+            }
+            Err(e) => warn(format_args!("缓存文件 {} 无法使用（{}），重新构建", path.display(), e)),
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => warn(format_args!("无法读取缓存文件 {}: {}", path.display(), e)),
+    }
+
+    let started = Instant::now();
+    let re = build(&multi)?;
+    debug!("构建 DFA: {}，耗时 {:?}", multi, started.elapsed());
+    match save(dir, &path, &multi, &re) {
+        Ok(()) => debug!("写入缓存: {}", path.display()),
+        Err(e) => warn(format_args!("无法写入缓存文件 {}: {}", path.display(), e)),
+    }
+    Ok((Automaton { pattern: pattern.to_string(), re }, Source::Built))
+}
+
+fn warn(args: std::fmt::Arguments) {
+    log::log(Level::Warn, module_path!(), args);
+}
+
+fn build(multi: &str) -> Result<DfaRegex<DFA<Vec<u32>>>, CacheError> {
+    let config = dense::Config::new()
+        .dfa_size_limit(Some(DFA_SIZE_LIMIT))
+        .determinize_size_limit(Some(DFA_SIZE_LIMIT));
+    DfaRegex::builder().dense(config).build(multi).map_err(|e| CacheError::Build(e.to_string()))
+}
+
+/// 缓存文件的布局，整数都是小端序：
+/// MAGIC、FORMAT (u32)、模式长度 (u64)、模式、正向 DFA 长度 (u64)、正向 DFA、反向 DFA 长度 (u64)、反向 DFA
+///
+/// DFA 本身是本机字节序，文件名中的哈希已经区分了字节序
+fn encode(multi: &str, re: &DfaRegex<DFA<Vec<u32>>>) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT.to_le_bytes());
+    for part in [multi.as_bytes(), &dfa_bytes(re.forward()), &dfa_bytes(re.reverse())] {
+        out.extend_from_slice(&(part.len() as u64).to_le_bytes());
+        out.extend_from_slice(part);
+    }
+    out
+}
+
+fn dfa_bytes(dfa: &DFA<Vec<u32>>) -> Vec<u8> {
+    let (mut bts, pad) = dfa.to_bytes_native_endian();
+    bts.drain(..pad);
+    bts
+}
+
+fn decode(bts: &[u8], multi: &str) -> Result<DfaRegex<DFA<Vec<u32>>>, CacheError> {
+    let rest = bts.strip_prefix(MAGIC.as_slice()).ok_or(CacheError::Corrupt("不是缓存文件"))?;
+    let (format, mut rest) = rest.split_at_checked(4).ok_or(CacheError::Corrupt("文件不完整"))?;
+    if format != FORMAT.to_le_bytes() {
+        return Err(CacheError::Corrupt("格式版本不同"));
+    }
+    let mut parts = [&[][..]; 3];
+    for part in &mut parts {
+        let (len, tail) = rest.split_at_checked(8).ok_or(CacheError::Corrupt("文件不完整"))?;
+        let len = usize::try_from(u64::from_le_bytes(len.try_into().unwrap()))
+            .map_err(|_| CacheError::Corrupt("长度错误"))?;
+        (*part, rest) = tail.split_at_checked(len).ok_or(CacheError::Corrupt("文件不完整"))?;
+    }
+    if !rest.is_empty() {
+        return Err(CacheError::Corrupt("文件末尾有多余的内容"));
+    }
+    // 哈希冲突：文件属于另一个模式
+    if parts[0] != multi.as_bytes() {
+        return Err(CacheError::Corrupt("模式不同"));
+    }
+    let forward = load_dfa(parts[1])?;
+    let reverse = load_dfa(parts[2])?;
+    Ok(DfaRegex::builder().build_from_dfas(forward, reverse))
+}
+
+/// 从字节中读取 DFA
+///
+/// `DFA::from_bytes` 要求字节按 4 字节对齐，从文件读出的 `Vec<u8>` 没有这个保证，
+/// 所以先复制到一个 `Vec<u32>` 中
+fn load_dfa(bts: &[u8]) -> Result<DFA<Vec<u32>>, CacheError> {
+    let mut words = vec![0u32; bts.len().div_ceil(4)];
+    for (w, chunk) in words.iter_mut().zip(bts.chunks(4)) {
+        let mut b = [0; 4];
+        b[..chunk.len()].copy_from_slice(chunk);
+        *w = u32::from_ne_bytes(b);
+    }
+    // SAFETY: words 有 bts.len().div_ceil(4) * 4 >= bts.len() 个字节，u32 没有无效的位模式，
+    // 读成 u8 总是合法的，切片的生命周期也没有超过 words
+    let aligned = unsafe { std::slice::from_raw_parts(words.as_ptr().cast::<u8>(), bts.len()) };
+    let (dfa, _) = DFA::from_bytes(aligned).map_err(|e| CacheError::Deserialize(e.to_string()))?;
+    Ok(dfa.to_owned())
+}
+
+/// 先写入同一目录中的临时文件再改名，同时运行的另一个进程不会读到写了一半的文件
+fn save(dir: &Path, path: &Path, multi: &str, re: &DfaRegex<DFA<Vec<u32>>>) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let tmp = path.with_extension(format!("rc.{}.tmp", std::process::id()));
+    fs::write(&tmp, encode(multi, re))?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pgrep-dfacache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn roundtrip_finds_same_starts_as_regex() {
+        let dir = scratch("roundtrip");
+        let text = "alpha\n  beta gamma\nbetamax\n\nend beta\n";
+        for pattern in [r"beta", r"^\s*beta", r"gamma$", r"(?i)BETA\w*", r"a\s+b", r"[0-9]*"] {
+            let multi = regex::Regex::new(&format!("(?m:{})", pattern)).unwrap();
+            let (built, src) = load_or_build(&dir, pattern).unwrap();
+            assert_eq!(src, Source::Built);
+            let (cached, src) = load_or_build(&dir, pattern).unwrap();
+            assert_eq!(src, Source::Cache);
+            for start in 0..=text.len() {
+                let want = multi.find_at(text, start).map(|m| m.start());
+                assert_eq!(built.find_at(text, start), want, "{} at {}", pattern, start);
+                assert_eq!(cached.find_at(text, start), want, "{} at {}", pattern, start);
+            }
+        }
+    }
+
+    #[test]
+    fn file_name_depends_on_pattern() {
+        let dir = Path::new("cache");
+        assert_eq!(cache_path(dir, "a"), cache_path(dir, "a"));
+        assert_ne!(cache_path(dir, "a"), cache_path(dir, "b"));
+        assert_eq!(cache_path(dir, "a").extension().unwrap(), "rc");
+    }
+
+    #[test]
+    fn corrupt_file_is_rebuilt() {
+        let dir = scratch("corrupt");
+        let (_, src) = load_or_build(&dir, "needle").unwrap();
+        assert_eq!(src, Source::Built);
+        let path = cache_path(&dir, "(?m:needle)");
+        let mut bts = fs::read(&path).unwrap();
+        let n = bts.len();
+        bts.truncate(n - 7);
+        fs::write(&path, &bts).unwrap();
+        assert!(decode(&bts, "(?m:needle)").is_err());
+
+        let (a, src) = load_or_build(&dir, "needle").unwrap();
+        assert_eq!(src, Source::Built);
+        assert_eq!(a.find_at("hay needle", 0), Some(4));
+        assert_eq!(fs::read(&path).unwrap().len(), n);
+    }
+
+    #[test]
+    fn other_pattern_in_file_is_not_used() {
+        let dir = scratch("collision");
+        load_or_build(&dir, "first").unwrap();
+        let bts = fs::read(cache_path(&dir, "(?m:first)")).unwrap();
+        assert!(matches!(decode(&bts, "(?m:second)"), Err(CacheError::Corrupt(_))));
+    }
+
+    #[test]
+    fn unsupported_patterns_are_errors() {
+        let dir = scratch("unsupported");
+        // 完整 DFA 不支持 Unicode 单词边界
+        assert!(load_or_build(&dir, r"\bword\b").is_err());
+        assert!(load_or_build(&dir, r"\Afirst").is_err());
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn reading_is_faster_than_building() {
+        let dir = scratch("faster");
+        let words: Vec<String> = (0..1500).map(|i| format!("w{}x{}", i * 7919 % 10007, i)).collect();
+        let pattern = words.join("|");
+        let started = Instant::now();
+        let (_, src) = load_or_build(&dir, &pattern).unwrap();
+        let built = started.elapsed();
+        assert_eq!(src, Source::Built);
+        let started = Instant::now();
+        let (a, src) = load_or_build(&dir, &pattern).unwrap();
+        let read = started.elapsed();
+        assert_eq!(src, Source::Cache);
+        assert_eq!(a.find_at("x w7919x1 y", 0), Some(2));
+        assert!(read < built, "读取 {:?}，构建 {:?}", read, built);
+    }
+}
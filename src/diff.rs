@@ -0,0 +1,289 @@
+// 统一差异格式（unified diff）
+//
+// 为 --patch 生成可以直接交给 `patch -p1` 或 `git apply` 的补丁，`--diff` 用它预览 `--write-replace` 的修改。
+// 按行比较新旧内容，使用 Myers 差异算法找出最短编辑序列，
+// 再按统一差异格式的规范输出：
+//
+// ```text
+// --- a/src/main.rs
+// +++ b/src/main.rs
+// @@ -10,7 +10,7 @@
+//  上下文
+// -旧行
+// +新行
+// ```
+//
+// * 行号从 1 开始；某一侧行数为 0 时，起始行号是该位置之前的那一行（文件开头为 0）
+// * 最后一行没有换行符时，在该行之后输出 `\ No newline at end of file`
+//
+// 相关资料:
+// * 统一差异格式: <https://www.gnu.org/software/diffutils/manual/html_node/Detailed-Unified.html>
+// * Myers 算法: Eugene W. Myers, "An O(ND) Difference Algorithm and Its Variations", 1986
+
+use std::path::{Component, Path};
+
+/// 编辑操作，下标分别指向旧内容和新内容中的行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// 用 Myers 算法计算把 `a` 变成 `b` 的最短编辑序列
+///
+/// 时间复杂度为 O((N + M) × D)，D 是差异的行数。替换产生的差异通常很少，所以很快。
+fn myers(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = (n + m) as usize;
+    let off = max as isize;
+
+    // v[k + off] 是对角线 k 上走得最远的 x；trace 保存每一步之前的 v，用于回溯
+    let mut v = vec![0isize; 2 * max + 2];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'outer: for d in 0..=max as isize {
+        trace.push(v.clone());
+        let mut k = -d;
+        while k <= d {
+            let idx = (k + off) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                break 'outer;
+            }
+            k += 2;
+        }
+    }
+
+    // 从终点沿着 trace 回溯出编辑序列
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let idx = (k + off) as usize;
+        let prev_k = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[(prev_k + off) as usize];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push(Op::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push(Op::Insert(prev_y as usize));
+            } else {
+                ops.push(Op::Delete(prev_x as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.reverse();
+    ops
+}
+
+/// 生成统一差异格式的补丁
+///
+/// # 参数
+/// * `old_name` / `new_name` - 写在 `---` / `+++` 头部中的路径
+/// * `old` / `new` - 新旧内容
+/// * `context` - 每个修改块前后保留的上下文行数，标准值为 3
+///
+/// # 返回值
+/// 新旧内容相同时返回空字符串，否则返回带头部的完整补丁，以换行符结尾
+pub fn unified(old_name: &str, new_name: &str, old: &str, new: &str, context: usize) -> String {
+    let a: Vec<&str> = old.split_inclusive('\n').collect();
+    let b: Vec<&str> = new.split_inclusive('\n').collect();
+    let ops = myers(&a, &b);
+
+    // 找出所有修改的位置，中间相同的行不超过 2 × context 行的修改合并到同一个块中
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, Op::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &i in &changed {
+        match groups.last_mut() {
+            Some((_, end)) if i - *end - 1 <= 2 * context => *end = i,
+            _ => groups.push((i, i)),
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (first, last) in groups {
+        let start = first.saturating_sub(context);
+        let end = (last + context + 1).min(ops.len());
+        let hunk = &ops[start..end];
+
+        // 块在新旧内容中的起始位置：第一个操作之前已经消耗的行数
+        let (mut old_start, mut new_start) = (0, 0);
+        for op in &ops[..start] {
+            match op {
+                Op::Equal(..) => {
+                    old_start += 1;
+                    new_start += 1;
+                }
+                Op::Delete(_) => old_start += 1,
+                Op::Insert(_) => new_start += 1,
+            }
+        }
+        let old_len = hunk.iter().filter(|op| !matches!(op, Op::Insert(_))).count();
+        let new_len = hunk.iter().filter(|op| !matches!(op, Op::Delete(_))).count();
+
+        // 行数为 0 时起始行号指向之前的一行
+        let pos = |s: usize, len: usize| if len == 0 { s } else { s + 1 };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            pos(old_start, old_len),
+            old_len,
+            pos(new_start, new_len),
+            new_len
+        ));
+
+        for op in hunk {
+            let (mark, line) = match *op {
+                Op::Equal(i, _) => (' ', a[i]),
+                Op::Delete(i) => ('-', a[i]),
+                Op::Insert(j) => ('+', b[j]),
+            };
+            out.push(mark);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// 把路径转换成补丁头部使用的相对路径
+///
+/// 去掉当前目录前缀和 `.` 组件，统一使用 `/` 分隔，
+/// 这样 `-f ./src` 和 `-f src` 生成的补丁完全一样，`git apply` 也能接受。
+/// 不在当前目录下的绝对路径去掉开头的 `/`，相当于相对于根目录。
+pub fn patch_path(p: &Path) -> String {
+    let cwd = std::env::current_dir().ok();
+    let rel = cwd
+        .as_deref()
+        .and_then(|c| p.strip_prefix(c).ok())
+        .unwrap_or(p);
+    rel.components()
+        .filter(|c| matches!(c, Component::Normal(_) | Component::ParentDir))
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 给补丁加上终端颜色：删除的行红色，新增的行绿色，块头青色，文件头粗体
+///
+/// 只用于在终端中查看，加了颜色的补丁不能再交给 `git apply`
+pub fn colorize(patch: &str) -> String {
+    let mut out = String::with_capacity(patch.len() + patch.len() / 4);
+    for l in patch.split_inclusive('\n') {
+        let body = l.strip_suffix('\n').unwrap_or(l);
+        let color = if body.starts_with("--- ") || body.starts_with("+++ ") {
+            "1"
+        } else if body.starts_with("@@") {
+            "36"
+        } else if body.starts_with('-') {
+            "31"
+        } else if body.starts_with('+') {
+            "32"
+        } else {
+            out.push_str(l);
+            continue;
+        };
+        out.push_str(&format!("\x1b[{}m{}\x1b[0m{}", color, body, &l[body.len()..]));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `l1\n` 到 `lN\n`，`changed` 中的行换成大写
+    fn lines(n: usize, changed: &[usize]) -> String {
+        (1..=n)
+            .map(|i| if changed.contains(&i) { format!("L{}\n", i) } else { format!("l{}\n", i) })
+            .collect()
+    }
+
+    #[test]
+    fn separate_hunks_for_distant_changes() {
+        let patch = unified("a/x", "b/x", &lines(20, &[]), &lines(20, &[3, 17]), 3);
+        assert_eq!(
+            patch,
+            "--- a/x\n+++ b/x\n\
+             @@ -1,6 +1,6 @@\n l1\n l2\n-l3\n+L3\n l4\n l5\n l6\n\
+             @@ -14,7 +14,7 @@\n l14\n l15\n l16\n-l17\n+L17\n l18\n l19\n l20\n"
+        );
+    }
+
+    #[test]
+    fn close_changes_share_a_hunk() {
+        // 两处修改之间正好隔着 2 × context 行，合并成一个块
+        let patch = unified("a/x", "b/x", &lines(20, &[]), &lines(20, &[5, 12]), 3);
+        assert_eq!(
+            patch,
+            "--- a/x\n+++ b/x\n\
+             @@ -2,14 +2,14 @@\n l2\n l3\n l4\n-l5\n+L5\n l6\n l7\n l8\n l9\n l10\n l11\n-l12\n+L12\n l13\n l14\n l15\n"
+        );
+        // 多隔一行就分开
+        let patch = unified("a/x", "b/x", &lines(20, &[]), &lines(20, &[5, 13]), 3);
+        assert_eq!(patch.matches("\n@@ ").count(), 2, "{}", patch);
+        assert!(patch.contains("@@ -2,7 +2,7 @@\n"), "{}", patch);
+        assert!(patch.contains("@@ -10,7 +10,7 @@\n"), "{}", patch);
+    }
+
+    #[test]
+    fn insertions_and_deletions_change_the_counts() {
+        let old = lines(10, &[]);
+        let new = old.replace("l2\n", "l2\nnew\n").replace("l8\n", "");
+        let patch = unified("a/x", "b/x", &old, &new, 1);
+        assert_eq!(patch, "--- a/x\n+++ b/x\n@@ -2,2 +2,3 @@\n l2\n+new\n l3\n@@ -7,3 +8,2 @@\n l7\n-l8\n l9\n");
+    }
+
+    #[test]
+    fn empty_sides_and_missing_newline() {
+        assert_eq!(unified("a/x", "b/x", "", "a\n", 3), "--- a/x\n+++ b/x\n@@ -0,0 +1,1 @@\n+a\n");
+        assert_eq!(unified("a/x", "b/x", "a\n", "", 3), "--- a/x\n+++ b/x\n@@ -1,1 +0,0 @@\n-a\n");
+        assert_eq!(
+            unified("a/x", "b/x", "a\nb", "a\nc", 3),
+            "--- a/x\n+++ b/x\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+c\n\\ No newline at end of file\n"
+        );
+        assert_eq!(unified("a/x", "b/x", "same\n", "same\n", 3), "");
+    }
+
+    #[test]
+    fn colors_only_change_lines_and_headers() {
+        let patch = unified("a/x", "b/x", "a\nb\n", "a\nc\n", 3);
+        assert_eq!(
+            colorize(&patch),
+            "\x1b[1m--- a/x\x1b[0m\n\x1b[1m+++ b/x\x1b[0m\n\x1b[36m@@ -1,2 +1,2 @@\x1b[0m\n a\n\x1b[31m-b\x1b[0m\n\x1b[32m+c\x1b[0m\n"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+// 文本编码
+//
+// --encoding-chain 按顺序尝试一组编码，用第一个能无错误解码整个文件的编码来搜索，
+// 适合编码来源已知、但混杂着几种旧编码的文件集合。
+//
+// 支持的编码：
+// * `utf-8` - 开头的 BOM 会被去掉
+// * `utf-16le` / `utf-16be` - 长度必须是偶数，不能有孤立的代理项；开头的 BOM 会被去掉。
+//   几乎任何偶数长度的 8 位文本都能被当作 UTF-16 解码，所以没有 BOM 时还要求内容中
+//   至少有一个 UTF-16 的换行符（U+000A），只有一行且没有 BOM 的 UTF-16 文件因此不会被识别
+// * `windows-1252` - 0x81、0x8D、0x8F、0x90、0x9D 五个字节没有定义，出现时解码失败
+// * `latin1` - ISO-8859-1，每个字节都对应一个字符，所以总是成功；放在链的末尾作为兜底
+//
+// 解码失败指的是遇到了这种编码中不合法的字节序列，
+// 链中的每个编码都失败时按 UTF-8 有损解码，非法的字节替换为 U+FFFD。
+//
+// 相关文档:
+// * Windows-1252: <https://en.wikipedia.org/wiki/Windows-1252>
+// * char::decode_utf16: <https://doc.rust-lang.org/std/char/fn.decode_utf16.html>
+
+use clap::ValueEnum;
+use std::fmt;
+
+/// 文本编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    /// UTF-8
+    #[value(name = "utf-8", alias = "utf8")]
+    Utf8,
+    /// UTF-16 小端序
+    #[value(name = "utf-16le", alias = "utf16le")]
+    Utf16Le,
+    /// UTF-16 大端序
+    #[value(name = "utf-16be", alias = "utf16be")]
+    Utf16Be,
+    /// Windows 西欧语言代码页
+    #[value(name = "windows-1252", alias = "cp1252")]
+    Windows1252,
+    /// ISO-8859-1
+    #[value(name = "latin1", alias = "iso-8859-1")]
+    Latin1,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(v) => f.write_str(v.get_name()),
+            None => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// Windows-1252 中 0x80..=0x9F 对应的字符，0 表示没有定义
+const CP1252_HIGH: [u16; 32] = [
+    0x20AC, 0, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039, 0x0152, 0, 0x017D, 0,
+    0, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, 0x02DC, 0x2122, 0x0161, 0x203A, 0x0153, 0, 0x017E,
+    0x0178,
+];
+
+impl Encoding {
+    /// 用这种编码解码整个内容
+    ///
+    /// # 返回值
+    /// 遇到不合法的字节序列时返回 None
+    pub fn decode(self, bts: &[u8]) -> Option<String> {
+        match self {
+            Encoding::Utf8 => {
+                let s = std::str::from_utf8(bts).ok()?;
+                Some(s.strip_prefix('\u{feff}').unwrap_or(s).to_string())
+            }
+            Encoding::Utf16Le => utf16(bts, u16::from_le_bytes),
+            Encoding::Utf16Be => utf16(bts, u16::from_be_bytes),
+            Encoding::Windows1252 => bts
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9f => char::from_u32(CP1252_HIGH[(b - 0x80) as usize] as u32).filter(|&c| c != '\0'),
+                    b => Some(b as char),
+                })
+                .collect(),
+            Encoding::Latin1 => Some(bts.iter().map(|&b| b as char).collect()),
+        }
+    }
+}
+
+fn utf16(bts: &[u8], unit: fn([u8; 2]) -> u16) -> Option<String> {
+    if !bts.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = bts.chunks_exact(2).map(|c| unit([c[0], c[1]]));
+    let s: String = char::decode_utf16(units).collect::<Result<_, _>>().ok()?;
+    match s.strip_prefix('\u{feff}') {
+        Some(rest) => Some(rest.to_string()),
+        None if s.contains('\n') => Some(s),
+        None => None,
+    }
+}
+
+/// 按顺序尝试链中的编码
+///
+/// # 返回值
+/// 第一个成功的编码和解码结果；全部失败时编码为 None，文本是有损解码的 UTF-8
+pub fn decode_chain(bts: &[u8], chain: &[Encoding]) -> (Option<Encoding>, String) {
+    chain
+        .iter()
+        .find_map(|&e| e.decode(bts).map(|s| (Some(e), s)))
+        .unwrap_or_else(|| (None, String::from_utf8_lossy(bts).into_owned()))
+}
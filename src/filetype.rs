@@ -0,0 +1,151 @@
+// 按语言选择文件
+//
+// --type NAME 只搜索属于这些类型的文件，--type-not NAME 排除属于这些类型的文件，
+// 每个类型是一组路径通配符（语法见 glob 模块），例如 `rust` 是 `*.rs`，`test` 是 `*_test.rs`、`**/tests/**` 等。
+//
+// 选择的顺序：
+// 1. 有 --type 时，文件至少要匹配其中一个类型，没有 --type 时所有文件都候选
+// 2. 匹配任意一个 --type-not 类型的文件被排除，即使它也匹配 --type，所以
+//    `--type rust --type-not test` 是"除了测试以外的 Rust 文件"
+// 3. 之后才轮到按路径选择模式的 --rules，以及 --type-filter 之类按文件种类的过滤；
+//    --git-tracked 等选项先决定了候选的文件，类型在这些文件中再筛选一次
+//
+// 这里没有 grep 的 --include / --exclude，需要临时的通配符时用 `--type-add` 定义一个类型即可：
+// `--type-add 'gen:*.pb.go' --type-not gen`。
+//
+// 类型只过滤文件，目录总是会被递归；直接在命令行上给出的文件也会被过滤。
+//
+// 相关文档:
+// * ripgrep 的文件类型: <https://github.com/BurntSushi/ripgrep/blob/master/GUIDE.md#manual-filtering-file-types>
+
+use failure::{Error, Fail};
+use std::path::Path;
+
+use crate::glob::Glob;
+
+/// 内置的类型
+const BUILTIN: &[(&str, &[&str])] = &[
+    ("c", &["*.c", "*.h"]),
+    ("cpp", &["*.cpp", "*.cc", "*.cxx", "*.hpp", "*.hh", "*.hxx", "*.h"]),
+    ("go", &["*.go"]),
+    ("java", &["*.java"]),
+    ("js", &["*.js", "*.mjs", "*.cjs", "*.jsx"]),
+    ("json", &["*.json"]),
+    ("md", &["*.md", "*.markdown"]),
+    ("py", &["*.py", "*.pyi"]),
+    ("rust", &["*.rs"]),
+    ("sh", &["*.sh", "*.bash", "*.zsh"]),
+    ("toml", &["*.toml"]),
+    ("ts", &["*.ts", "*.tsx", "*.mts", "*.cts"]),
+    ("yaml", &["*.yaml", "*.yml"]),
+    (
+        "test",
+        &[
+            "*_test.rs",
+            "*_test.go",
+            "test_*.py",
+            "*_test.py",
+            "*.test.js",
+            "*.spec.js",
+            "*.test.ts",
+            "*.spec.ts",
+            "*Test.java",
+            "**/tests/**",
+            "**/test/**",
+        ],
+    ),
+];
+
+/// 没有这个名字的类型
+#[derive(Debug, Fail)]
+#[fail(display = "未知的文件类型 {:?}，可以是: {}", name, known)]
+pub struct UnknownType {
+    name: String,
+    known: String,
+}
+
+/// `--type-add` 的值无法解析
+#[derive(Debug, Fail)]
+#[fail(display = "--type-add 的值 {:?} 应该写成 `名字:通配符`", _0)]
+pub struct TypeAddErr(String);
+
+/// 所有类型的定义：内置的类型加上 `--type-add` 定义的类型
+#[derive(Debug, Clone)]
+pub struct TypeDefs {
+    defs: Vec<(String, Vec<String>)>,
+}
+
+impl Default for TypeDefs {
+    fn default() -> TypeDefs {
+        TypeDefs {
+            defs: BUILTIN
+                .iter()
+                .map(|(name, globs)| (name.to_string(), globs.iter().map(|g| g.to_string()).collect()))
+                .collect(),
+        }
+    }
+}
+
+impl TypeDefs {
+    /// 按 `--type-add` 的 `名字:通配符` 增加一个通配符
+    ///
+    /// 类型已经存在时通配符加到原来的定义中，所以可以多次指定同一个名字，也可以扩充内置的类型
+    pub fn add(&mut self, spec: &str) -> Result<(), Error> {
+        let err = || TypeAddErr(spec.to_string());
+        let (name, glob) = spec.split_once(':').ok_or_else(err)?;
+        if name.is_empty() || glob.is_empty() {
+            return Err(err().into());
+        }
+        // 在这里先编译一次，写错的通配符在定义时就报告
+        Glob::new(glob)?;
+        match self.defs.iter_mut().find(|(n, _)| n == name) {
+            Some((_, globs)) => globs.push(glob.to_string()),
+            None => self.defs.push((name.to_string(), vec![glob.to_string()])),
+        }
+        Ok(())
+    }
+
+    /// 类型的通配符
+    pub fn globs(&self, name: &str) -> Result<&[String], UnknownType> {
+        self.defs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, globs)| globs.as_slice())
+            .ok_or_else(|| UnknownType {
+                name: name.to_string(),
+                known: self.defs.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>().join(", "),
+            })
+    }
+
+    /// 按 `--type` / `--type-not` 的类型名生成选择条件
+    pub fn select(&self, include: &[String], exclude: &[String]) -> Result<TypeSelect, Error> {
+        let compile = |names: &[String]| -> Result<Vec<Glob>, Error> {
+            let mut globs = Vec::new();
+            for name in names {
+                for g in self.globs(name)? {
+                    globs.push(Glob::new(g)?);
+                }
+            }
+            Ok(globs)
+        };
+        Ok(TypeSelect {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+}
+
+/// 按类型选择文件的条件，见 `GrepConfig::type_select`
+#[derive(Debug)]
+pub struct TypeSelect {
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+}
+
+impl TypeSelect {
+    /// 是否搜索这个文件：匹配任意一个包含的类型（没有时总是成立），并且不匹配任何排除的类型
+    pub fn selects(&self, p: &Path) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|g| g.is_match(p)))
+            && !self.exclude.iter().any(|g| g.is_match(p))
+    }
+}
@@ -0,0 +1,107 @@
+// 流过滤模式
+//
+// --filter 像 `sed 's/.../.../g'` 一样工作：从标准输入逐行读取，用 `--replace` 的模板替换每一行中的所有匹配，
+// 把结果写到标准输出。没有匹配的行也原样输出，输出中没有文件名和行号。
+//
+// * 每次只读入一行，输入可以是任意长度的流（例如 `tail -f` 的输出）
+// * 行尾（`\n`、`\r\n` 或者最后一行没有行尾）保持不变，没有被替换的行逐字节与输入相同
+// * 不是合法 UTF-8 的行不参与匹配，原样输出
+// * `--line-prefix`、`--skip-prefix`、`--skip-empty-lines` 排除的行原样输出
+// * 输入暂时没有更多数据时立即刷新输出，用在管道中间时下游能及时看到结果
+// * `--replace-verify` 检查失败时停止，已经输出的行不受影响；`--replace-verify-skip` 时原样输出这一行
+//
+// 相关文档:
+// * std::io::BufRead::read_until: <https://doc.rust-lang.org/std/io/trait.BufRead.html#method.read_until>
+// * sed(1): <https://man7.org/linux/man-pages/man1/sed.1.html>
+
+use failure::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use pgrep::GrepConfig;
+use pgrep::replace::Template;
+use regex::Regex;
+
+/// 逐行替换 `input` 中的匹配，写入 `output`
+///
+/// # 参数
+/// * `input` - 输入流，通常是标准输入
+/// * `output` - 输出流，通常是标准输出
+/// * `re` - 编译好的正则表达式对象
+/// * `cfg` - 搜索配置，被 `--line-prefix` 等排除的行不替换
+/// * `t` - 替换模板
+///
+/// # 返回值
+/// 有匹配并被替换的行数
+pub fn filter<R: Read, W: Write>(
+    input: &mut BufReader<R>,
+    output: &mut W,
+    re: &Regex,
+    cfg: &GrepConfig,
+    t: &Template,
+) -> Result<usize, Error> {
+    let mut replaced = 0;
+    let mut buf = Vec::new();
+    // 从 0 开始的行号，用于 --replace-verify 的错误信息
+    let mut line_no = 0;
+    loop {
+        buf.clear();
+        if input.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        let body_len = match buf.strip_suffix(b"\r\n").or_else(|| buf.strip_suffix(b"\n")) {
+            Some(b) => b.len(),
+            None => buf.len(),
+        };
+        let (body, eol) = buf.split_at(body_len);
+        match std::str::from_utf8(body) {
+            Ok(line) if cfg.line_allowed(line) && re.is_match(line) => {
+                let new = cfg.replacement(Path::new("-"), line_no, re, line)?.unwrap_or_else(|| t.replace_all(re, line));
+                output.write_all(new.as_bytes())?;
+                output.write_all(eol)?;
+                replaced += 1;
+            }
+            _ => output.write_all(&buf)?,
+        }
+        line_no += 1;
+        // 缓冲区里没有下一行时，下一次读取可能会阻塞，先把已经处理的部分交给下游
+        if input.buffer().is_empty() {
+            output.flush()?;
+        }
+    }
+    output.flush()?;
+    Ok(replaced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: &[u8], pattern: &str, template: &str) -> (usize, Vec<u8>) {
+        let re = Regex::new(pattern).unwrap();
+        let t = Template::parse(template, &re).unwrap();
+        let mut out = Vec::new();
+        let n = filter(&mut BufReader::new(input), &mut out, &re, &GrepConfig::default(), &t).unwrap();
+        (n, out)
+    }
+
+    /// 各种行尾、空行、空白、非 UTF-8 的字节和没有行尾的最后一行
+    const AWKWARD: &[u8] = b"plain\n\ncrlf\r\n\r\nlone\rcr\n  trailing \t \n\xff\xfe bytes\n\xe4\xbd\xa0\xe5\xa5\xbd\nno eol";
+
+    #[test]
+    fn unmatched_input_is_byte_identical() {
+        let (n, out) = run(AWKWARD, "needle", "x");
+        assert_eq!(n, 0);
+        assert_eq!(out, AWKWARD);
+
+        let (n, out) = run(b"", "needle", "x");
+        assert_eq!((n, out), (0, Vec::new()));
+    }
+
+    #[test]
+    fn only_matched_lines_change() {
+        let (n, out) = run(AWKWARD, "crlf|eol", "[$0]");
+        assert_eq!(n, 2);
+        assert_eq!(out, b"plain\n\n[crlf]\r\n\r\nlone\rcr\n  trailing \t \n\xff\xfe bytes\n\xe4\xbd\xa0\xe5\xa5\xbd\nno [eol]");
+    }
+}
@@ -0,0 +1,247 @@
+// 跟踪不断增长的文件
+//
+// --follow-lines 先搜索文件中已有的内容，然后像 `tail -F` 一样继续等待，
+// 文件中追加了新的行时立即搜索并输出其中的匹配。
+//
+// * 每隔 POLL_INTERVAL 检查一次文件的大小和 inode，只读取新增的部分
+// * 只搜索完整的行，还没有写完换行符的最后一行留到下次再读
+// * 行号在追加之间连续计数；文件被截断（变小）或者被轮转（inode 变化）时
+//   在标准错误中给出提示，然后从新文件的开头重新读取，行号也从 1 开始
+// * 文件暂时不存在（轮转的间隙）时继续等待，重新出现后从头读取
+//
+// 相关文档:
+// * tail(1) 的 -F: <https://man7.org/linux/man-pages/man1/tail.1.html>
+// * std::os::unix::fs::MetadataExt: <https://doc.rust-lang.org/std/os/unix/fs/trait.MetadataExt.html>
+
+use failure::{Error, Fail};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use pgrep::{GrepConfig, Record, is_fatal, process_bytes};
+use regex::Regex;
+
+/// 两次检查之间的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// `--follow-lines` 的参数不是普通文件
+#[derive(Debug, Fail)]
+#[fail(display = "--follow-lines 只能跟踪文件，{} 不是普通文件", path)]
+pub struct NotAFile {
+    path: String,
+}
+
+/// 一个被跟踪的文件
+struct Tail {
+    path: PathBuf,
+    // 已经打开的文件和它的 inode，文件不存在时为 None
+    file: Option<(File, u64)>,
+    // 已经搜索过的字节数，不包括还没有换行符的最后一行
+    offset: u64,
+    // 已经搜索过的行数，新读到的行从这里继续编号
+    lines: usize,
+}
+
+impl Tail {
+    /// 读取新增的完整行
+    ///
+    /// # 返回值
+    /// 新增的内容，文件没有变化时为空
+    fn read_new(&mut self) -> Result<Vec<u8>, Error> {
+        let md = match self.path.metadata() {
+            Ok(md) => md,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if self.file.take().is_some() {
+                    eprintln!("pgrep: {} 已被删除或移走，等待它重新出现", self.path.display());
+                }
+                return Ok(Vec::new());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let reopen = match &self.file {
+            None => true,
+            Some((_, ino)) if *ino != md.ino() => {
+                eprintln!("pgrep: {} 已被轮转，从新文件的开头读取", self.path.display());
+                true
+            }
+            Some(_) if md.len() < self.offset => {
+                eprintln!("pgrep: {} 已被截断，从头读取", self.path.display());
+                true
+            }
+            Some(_) => false,
+        };
+        if reopen {
+            self.file = Some((File::open(&self.path)?, md.ino()));
+            self.offset = 0;
+            self.lines = 0;
+        }
+        let Some((f, _)) = &mut self.file else {
+            return Ok(Vec::new());
+        };
+        if md.len() == self.offset {
+            return Ok(Vec::new());
+        }
+
+        f.seek(SeekFrom::Start(self.offset))?;
+        let mut buf = Vec::new();
+        f.read_to_end(&mut buf)?;
+        // 最后一个换行符之后的内容可能还没有写完
+        let Some(end) = buf.iter().rposition(|&b| b == b'\n') else {
+            return Ok(Vec::new());
+        };
+        buf.truncate(end + 1);
+        self.offset += buf.len() as u64;
+        Ok(buf)
+    }
+}
+
+/// 搜索文件中已有的内容，然后一直跟踪新增的行，直到遇到致命错误
+///
+/// # 参数
+/// * `paths` - 要跟踪的文件，必须都是普通文件
+/// * `ff` / `ef` - 与 process_path 的回调相同，行号是在整个文件中的行号
+pub fn follow<P, FF, EF>(paths: &[P], re: &Regex, cfg: &GrepConfig, ff: &FF, ef: &EF) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    FF: Fn(&Path, Vec<Record>) -> Result<(), Error>,
+    EF: Fn(Error),
+{
+    let mut tails = Vec::new();
+    for p in paths {
+        let p = p.as_ref();
+        if p == Path::new("-") || !p.is_file() {
+            return Err(NotAFile {
+                path: p.display().to_string(),
+            }
+            .into());
+        }
+        tails.push(Tail {
+            path: p.to_path_buf(),
+            file: None,
+            offset: 0,
+            lines: 0,
+        });
+    }
+
+    loop {
+        for t in &mut tails {
+            let result = t.read_new().and_then(|bts| {
+                if bts.is_empty() {
+                    return Ok(());
+                }
+                let added = bts.iter().filter(|&&b| b == b'\n').count();
+                let mut v = process_bytes(&t.path, bts, re, cfg)?;
+                for r in &mut v {
+                    r.line += t.lines;
+                }
+                t.lines += added;
+                ff(&t.path, v)
+            });
+            if let Err(e) = result {
+                if is_fatal(&e) {
+                    return Err(e);
+                }
+                ef(e);
+            }
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pgrep::Halt;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, mpsc};
+
+    /// 在后台线程中跟踪 `path`，每个匹配以 (从 1 开始的行号, 文本) 交给返回的通道；
+    /// `stop` 设置之后下一次有新内容时 follow 返回 Halt
+    fn spawn(
+        path: PathBuf,
+        pattern: &str,
+        stop: Arc<AtomicBool>,
+    ) -> (mpsc::Receiver<(usize, String)>, std::thread::JoinHandle<Error>) {
+        let re = Regex::new(pattern).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            let cfg = GrepConfig::default();
+            let ff = |_: &Path, v: Vec<Record>| {
+                if stop.load(Ordering::SeqCst) {
+                    return Err(Halt { reason: "测试结束".to_string() }.into());
+                }
+                for r in v {
+                    tx.send((r.line + 1, r.tx)).unwrap();
+                }
+                Ok(())
+            };
+            follow(&[path], &re, &cfg, &ff, &|e| panic!("{}", e)).unwrap_err()
+        });
+        (rx, handle)
+    }
+
+    fn next(rx: &mpsc::Receiver<(usize, String)>) -> (usize, String) {
+        rx.recv_timeout(Duration::from_secs(5)).expect("没有等到新的匹配")
+    }
+
+    fn append(path: &Path, s: &str) {
+        std::fs::OpenOptions::new().append(true).open(path).unwrap().write_all(s.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn appended_truncated_and_rotated() {
+        let dir = std::env::temp_dir().join(format!("pgrep-follow-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("app.log");
+        std::fs::write(&log, "ok\nERROR old\nok\n").unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (rx, handle) = spawn(log.clone(), "ERROR", Arc::clone(&stop));
+        assert_eq!(next(&rx), (2, "ERROR old".to_string()));
+
+        // 另一个线程追加，没写完换行符的行等到写完才搜索，行号接着已有的行
+        let writer = {
+            let log = log.clone();
+            std::thread::spawn(move || {
+                append(&log, "ok\nERROR par");
+                std::thread::sleep(Duration::from_millis(600));
+                append(&log, "tial\n");
+            })
+        };
+        assert_eq!(next(&rx), (5, "ERROR partial".to_string()));
+        writer.join().unwrap();
+
+        // 截断之后从头读取，行号从 1 开始
+        std::fs::write(&log, "ERROR new\n").unwrap();
+        assert_eq!(next(&rx), (1, "ERROR new".to_string()));
+
+        // 轮转：原来的文件被改名，同名的新文件 inode 不同
+        std::fs::rename(&log, dir.join("app.log.1")).unwrap();
+        std::fs::write(&log, "ok\nok\nERROR rotated\n").unwrap();
+        assert_eq!(next(&rx), (3, "ERROR rotated".to_string()));
+        append(&log, "ERROR again\n");
+        assert_eq!(next(&rx), (4, "ERROR again".to_string()));
+
+        stop.store(true, Ordering::SeqCst);
+        append(&log, "ERROR last\n");
+        let err = handle.join().unwrap();
+        assert!(err.downcast_ref::<Halt>().is_some(), "{}", err);
+        assert!(rx.try_recv().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_regular_files() {
+        let re = Regex::new("x").unwrap();
+        let cfg = GrepConfig::default();
+        let ff = |_: &Path, _: Vec<Record>| Ok(());
+        let err = follow(&["-"], &re, &cfg, &ff, &|_| {}).unwrap_err();
+        assert_eq!(err.to_string(), "--follow-lines 只能跟踪文件，- 不是普通文件");
+        let err = follow(&[std::env::temp_dir()], &re, &cfg, &ff, &|_| {}).unwrap_err();
+        assert!(err.downcast_ref::<NotAFile>().is_some(), "{}", err);
+    }
+}
@@ -0,0 +1,228 @@
+// 按 git 的状态选择要搜索的文件
+//
+// 这几个选项先用 git 列出每个搜索路径下的文件，然后只搜索这些文件：
+// * --git-tracked: `git ls-files` 列出的被跟踪的文件。没有被跟踪的文件（包括没有被 .gitignore 忽略的新文件）
+//   和被忽略的文件都不搜索，子模块中被跟踪的文件也会被搜索（`--recurse-submodules`）
+// * --staged: `git diff --cached` 中的文件，也就是下一次提交会包含的修改，适合在 pre-commit 钩子中使用
+// * --changed [BASE]: 工作区中和 BASE（默认 HEAD）不同的文件，包括已经暂存和还没有暂存的修改
+// * --git-untracked: 和上面任意一个一起使用，再加上没有被跟踪、也没有被忽略的新文件
+//   （`git ls-files --others --exclude-standard`）
+//
+// `--git-staged-only` 和 `--git-modified-only` 分别是 `--staged` 和 `--changed` 的别名。
+//
+// 共同的规则：
+// * 不在 git 工作区中的搜索路径给出警告，搜索其中所有的文件，就像没有使用这些选项一样
+// * 文件名按搜索路径解析（`--relative`），在仓库的子目录中运行时也能找到正确的文件
+// * 已经从工作区删除的文件被跳过；重命名的文件使用新的路径
+// * 搜索的是工作区中的内容；`--staged` 时如果暂存之后又修改了文件，搜索到的是修改后的内容
+// * 其他过滤条件（`--type-filter`、`--rules` 等）照常作用于这些文件
+//
+// --git-relative 与上面的选项无关，它只改变输出中的路径：从当前目录向上找到包含 `.git` 的目录，
+// 输出相对于这个仓库根目录的路径，在不同的目录中运行、或者仓库签出在不同的位置时输出都相同。
+// * 文件列表只在启动时取得一次，`--watch` 期间新加入仓库的文件不会被搜索
+// * clap 的参数是 String，文件名不是 UTF-8 的文件被跳过并给出警告
+//
+// 相关文档:
+// * git-ls-files(1): <https://git-scm.com/docs/git-ls-files>
+// * git-diff(1): <https://git-scm.com/docs/git-diff>
+// * pathspec 的写法: <https://git-scm.com/docs/gitglossary#Documentation/gitglossary.txt-aiddefpathspecapathspec>
+
+use failure::{Error, Fail};
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// 搜索路径不在 git 仓库中，作为警告输出
+#[derive(Debug, Fail)]
+#[fail(display = "{}: {} 不在 git 仓库中，搜索其中所有的文件", flag, path)]
+pub struct NotInRepo {
+    flag: &'static str,
+    path: String,
+}
+
+/// git 无法运行或者执行失败
+#[derive(Debug, Fail)]
+#[fail(display = "{}: 无法列出 {} 中的文件: {}", flag, path, reason)]
+pub struct GitErr {
+    flag: &'static str,
+    path: String,
+    reason: String,
+}
+
+/// 标准输入没有对应的仓库
+#[derive(Debug, Fail)]
+#[fail(display = "{} 不支持标准输入", _0)]
+pub struct GitStdin(&'static str);
+
+/// 选择哪些文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// `--git-tracked`: 被跟踪的文件
+    Tracked,
+    /// `--staged`: 暂存区中有修改的文件
+    Staged,
+    /// `--changed BASE`: 和 BASE 不同的文件
+    Changed(String),
+}
+
+impl Selection {
+    /// 对应的命令行选项，用在错误信息中
+    fn flag(&self) -> &'static str {
+        match self {
+            Selection::Tracked => "--git-tracked",
+            Selection::Staged => "--staged",
+            Selection::Changed(_) => "--changed",
+        }
+    }
+
+    /// 列出文件的 git 子命令和参数，之后是 `--` 和 pathspec
+    fn git_args(&self) -> Vec<&str> {
+        // --diff-filter=d 排除删除的文件；--no-renames 没有指定，重命名时只输出新的路径
+        let diff = ["diff", "--name-only", "-z", "--relative", "--diff-filter=d"];
+        match self {
+            Selection::Tracked => vec!["ls-files", "-z", "--recurse-submodules"],
+            Selection::Staged => [&diff[..], &["--cached"]].concat(),
+            Selection::Changed(base) => [&diff[..], &[base.as_str()]].concat(),
+        }
+    }
+}
+
+/// 把搜索路径换成其中被选中的文件
+///
+/// # 参数
+/// * `roots` - 搜索路径，可以是目录也可以是文件
+/// * `sel` - 选择哪些文件
+/// * `untracked` - 再加上没有被跟踪、也没有被忽略的文件（`--git-untracked`）
+/// * `warn` - 是否输出跳过文件时的警告，`--no-warnings` 时为 false
+///
+/// # 返回值
+/// 被选中并且还在工作区中的文件。目录下的文件写成 `目录/相对路径`（例如 `./src/main.rs`），
+/// 和遍历目录时的路径形式相同；直接给出的文件被选中时原样保留。不在仓库中的搜索路径原样保留
+pub fn select_files(roots: &[String], sel: &Selection, untracked: bool, warn: bool) -> Result<Vec<String>, Error> {
+    let flag = sel.flag();
+    let mut files = Vec::new();
+    for root in roots {
+        if root == "-" {
+            return Err(GitStdin(flag).into());
+        }
+        let p = Path::new(root);
+        let is_dir = p.metadata()?.is_dir();
+        let (dir, spec) = if is_dir {
+            (p, OsStr::new("."))
+        } else {
+            let dir = match p.parent() {
+                Some(d) if !d.as_os_str().is_empty() => d,
+                _ => Path::new("."),
+            };
+            (dir, p.file_name().unwrap_or(p.as_os_str()))
+        };
+
+        // 在仓库之外 git diff 会退化成比较两个路径的 --no-index 模式，所以先确认是在工作区中
+        let inside = git(dir, &["rev-parse", "--is-inside-work-tree"]).map_err(|e| GitErr {
+            flag,
+            path: root.clone(),
+            reason: format!("无法运行 git: {}", e),
+        })?;
+        if !inside.status.success() || inside.stdout.trim_ascii() != b"true" {
+            if warn {
+                eprintln!(
+                    "警告: {}",
+                    NotInRepo {
+                        flag,
+                        path: root.clone(),
+                    }
+                );
+            }
+            files.push(root.clone());
+            continue;
+        }
+
+        let mut lists = vec![sel.git_args()];
+        if untracked {
+            lists.push(vec!["ls-files", "-z", "--others", "--exclude-standard"]);
+        }
+        let mut stdout = Vec::new();
+        for mut args in lists {
+            args.push("--");
+            let out = Command::new("git")
+                // 文件名中的 `*`、`?` 等按字面匹配，不当作通配符
+                .arg("--literal-pathspecs")
+                .arg("-C")
+                .arg(dir)
+                .args(args)
+                .arg(spec)
+                .output()?;
+            if !out.status.success() {
+                let stderr = String::from_utf8_lossy(&out.stderr);
+                return Err(GitErr {
+                    flag,
+                    path: root.clone(),
+                    reason: stderr.lines().next().unwrap_or_default().trim().to_string(),
+                }
+                .into());
+            }
+            stdout.extend(out.stdout);
+            stdout.push(0);
+        }
+
+        for entry in stdout.split(|&b| b == 0).filter(|e| !e.is_empty()) {
+            let f = if is_dir {
+                p.join(OsStr::from_bytes(entry))
+            } else {
+                p.to_path_buf()
+            };
+            // 已经删除的文件和没有初始化的子模块（空目录）都没有内容可以搜索
+            match f.symlink_metadata() {
+                Ok(md) if !md.is_dir() => {}
+                _ => continue,
+            }
+            match f.into_os_string().into_string() {
+                Ok(s) => files.push(s),
+                Err(name) => {
+                    if warn {
+                        eprintln!("警告: 跳过文件名不是 UTF-8 的文件 {}", Path::new(&name).display());
+                    }
+                }
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// 在 `dir` 中运行 git
+fn git(dir: &Path, args: &[&str]) -> std::io::Result<std::process::Output> {
+    Command::new("git").arg("-C").arg(dir).args(args).output()
+}
+
+/// 从 `start` 开始向上查找仓库的根目录，即包含 `.git`（目录，或者工作树和子模块中的文件）的目录
+pub fn repo_root(start: &Path) -> Option<PathBuf> {
+    start.ancestors().find(|d| d.join(".git").exists()).map(Path::to_path_buf)
+}
+
+/// `p` 相对于仓库根目录 `root` 的路径
+///
+/// 先按当前目录把 `p` 转换成绝对路径，再按字面去掉其中的 `.` 和 `..`，不解析符号链接。
+/// 不在仓库中的路径和标准输入 `-` 原样返回。
+pub fn relative_to(root: &Path, p: &Path) -> PathBuf {
+    if p == Path::new("-") {
+        return p.to_path_buf();
+    }
+    let Ok(abs) = std::path::absolute(p) else {
+        return p.to_path_buf();
+    };
+    let mut norm = PathBuf::new();
+    for c in abs.components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                norm.pop();
+            }
+            c => norm.push(c),
+        }
+    }
+    match norm.strip_prefix(root) {
+        Ok(rel) => rel.to_path_buf(),
+        Err(_) => p.to_path_buf(),
+    }
+}
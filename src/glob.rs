@@ -0,0 +1,95 @@
+// 路径通配符
+//
+// 把 shell 风格的通配符翻译成正则表达式，用于按路径筛选文件。
+//
+// 支持的语法：
+// * `*` - 任意数量的字符，不跨越 `/`
+// * `?` - 任意一个字符，不包括 `/`
+// * `**` - 任意数量的字符，可以跨越 `/`；`**/` 也可以匹配空，所以 `**/a` 能匹配 `a`
+// * `[abc]` / `[a-z]` / `[!abc]` - 字符集合及其取反
+//
+// 和 .gitignore 一样，不含 `/` 的通配符只和文件名比较（`*.rs` 匹配任意目录下的 rs 文件），
+// 含有 `/` 的通配符和整个路径比较。路径开头的 `./` 会被忽略。
+
+use failure::Error;
+use regex::Regex;
+use std::path::{Component, Path};
+
+/// 编译好的通配符
+#[derive(Debug)]
+pub struct Glob {
+    re: Regex,
+    // 是否只和文件名比较
+    basename: bool,
+}
+
+impl Glob {
+    /// 编译通配符
+    ///
+    /// # 返回值
+    /// * `Err(Error)` - 字符集合没有闭合等语法错误
+    pub fn new(pat: &str) -> Result<Glob, Error> {
+        let pat = pat.strip_prefix("./").unwrap_or(pat);
+        let mut re = String::from("^");
+        let mut chars = pat.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        re.push_str("(?:.*/)?");
+                    } else {
+                        re.push_str(".*");
+                    }
+                }
+                '*' => re.push_str("[^/]*"),
+                '?' => re.push_str("[^/]"),
+                '[' => {
+                    re.push('[');
+                    if let Some(&n) = chars.peek()
+                        && (n == '!' || n == '^')
+                    {
+                        chars.next();
+                        re.push('^');
+                    }
+                    // 集合中只保留 `-` 的范围含义，其他字符都按字面处理
+                    for c in chars.by_ref() {
+                        if c == ']' {
+                            break;
+                        }
+                        match c {
+                            '-' => re.push('-'),
+                            c => re.push_str(&regex::escape(&c.to_string())),
+                        }
+                    }
+                    re.push(']');
+                }
+                c => re.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        re.push('$');
+        Ok(Glob {
+            re: Regex::new(&re)?,
+            basename: !pat.contains('/'),
+        })
+    }
+
+    /// 判断路径是否匹配
+    pub fn is_match(&self, p: &Path) -> bool {
+        if self.basename {
+            return p
+                .file_name()
+                .is_some_and(|n| self.re.is_match(&n.to_string_lossy()));
+        }
+        let s = p
+            .components()
+            .filter(|c| !matches!(c, Component::CurDir))
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        // 根目录组件本身就是 `/`，join 之后开头会多出一个
+        let s = s.strip_prefix("//").map(|r| format!("/{}", r)).unwrap_or(s);
+        self.re.is_match(&s)
+    }
+}
@@ -0,0 +1,70 @@
+// 看起来像文件名通配符的模式的提示
+//
+// 新用户常常写 `pgrep -p "*.rs" -f .`，以为 `-p` 按文件名筛选。这样的模式作为正则表达式要么无法编译，
+// 要么匹配的不是想要的内容。模式无法编译、或者编译成功但形状像通配符时，在错误或警告后面附上
+// 按文件名筛选（--type-add/--type）和按字面搜索（-F）的写法。这里只影响提示，不改变模式怎样匹配。
+
+/// 判断模式是不是更像文件名通配符而不是正则表达式
+///
+/// 只用来给出提示，不影响模式怎样匹配。以下几种情况看作通配符：
+/// * 以 `*` 或 `?` 开头（`*.rs`），作为正则表达式这本来就无法编译
+/// * 含有 `**`（`**/name`、`src/**`）
+/// * 除了 `*`、`?`、`.`、`/` 以外没有其他元字符，含有 `*` 或 `?`，并且以 `.扩展名` 结尾（`src/*.rs`、`file?.txt`）；
+///   `.*` 和 `.?` 是正则表达式的常见写法，有它们时不算
+pub fn glob_shaped(p: &str) -> bool {
+    if p.starts_with(['*', '?']) || p.contains("**") {
+        return true;
+    }
+    if !p.contains(['*', '?'])
+        || p.contains(['\\', '(', ')', '[', ']', '{', '}', '|', '^', '$', '+'])
+        || p.contains(".*")
+        || p.contains(".?")
+    {
+        return false;
+    }
+    p.rsplit_once('.').is_some_and(|(_, ext)| {
+        (1..=6).contains(&ext.len()) && ext.bytes().all(|b| b.is_ascii_alphanumeric())
+    })
+}
+
+/// 看起来像通配符的模式的提示，说明按文件名筛选和按字面搜索的写法
+pub fn glob_hint(p: &str) -> String {
+    format!(
+        "模式 {:?} 看起来像文件名通配符。要按文件名筛选，请使用 --type-add 'mine:{}' --type mine；\
+         要按字面搜索这段文本，请使用 -F -p '{}'，或者转义其中的元字符: -p '{}'",
+        p,
+        p,
+        p,
+        regex::escape(p)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_like_patterns() {
+        for p in ["*.rs", "?.txt", "*", "**/Cargo.toml", "src/**", "a/**/b", "src/*.rs", "file?.txt", "*.tar.gz"] {
+            assert!(glob_shaped(p), "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn regex_like_patterns() {
+        for p in [
+            "TODO", "main.rs", "a*b", "colou?r", ".*\\.rs", "foo.*bar.txt", "x.?y.md", "^src/*.rs$", "(a|b)*.rs",
+            "[a-z]*.rs", "\\w+*.rs", "ab*.verylongext", "a*.r_s", "",
+        ] {
+            assert!(!glob_shaped(p), "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn hint_names_type_filter_and_fixed_strings() {
+        let hint = glob_hint("*.rs");
+        assert!(hint.contains("--type-add 'mine:*.rs' --type mine"), "{}", hint);
+        assert!(hint.contains("-F -p '*.rs'"), "{}", hint);
+        assert!(hint.contains("-p '\\*\\.rs'"), "{}", hint);
+    }
+}
@@ -0,0 +1,163 @@
+// gzip 解压
+//
+// -z / --search-zip 用来搜索 gzip 压缩的文件，例如轮转后的日志 `app.log.1.gz`。
+// 格式检测在 compress 模块中。
+// gzip 只是在 DEFLATE 数据外面包了一层头部和校验和，这里自己解析头部（RFC 1952），
+// DEFLATE 数据交给 miniz_oxide 解压，最后校验 CRC32 和原始长度。
+//
+// 多个 gzip 成员直接拼接在一起（`cat a.gz b.gz`）也是合法的 gzip 文件，
+// 解压结果是各个成员内容的拼接。
+//
+// 相关文档:
+// * RFC 1952: <https://www.rfc-editor.org/rfc/rfc1952>
+// * miniz_oxide::inflate::core::decompress: <https://docs.rs/miniz_oxide/latest/miniz_oxide/inflate/core/fn.decompress.html>
+
+use failure::Fail;
+use miniz_oxide::inflate::TINFLStatus;
+use miniz_oxide::inflate::core::{DecompressorOxide, decompress as inflate, inflate_flags};
+use std::path::Path;
+
+/// gzip 数据损坏或者格式不支持
+#[derive(Debug, Fail)]
+#[fail(display = "无法解压 gzip 文件 {}: {}", path, msg)]
+pub struct GzipErr {
+    path: String,
+    msg: String,
+}
+
+// 头部标志位
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+/// 解压整个 gzip 文件
+///
+/// # 参数
+/// * `p` - 文件路径，只用于错误信息
+/// * `data` - 压缩后的内容
+///
+/// # 返回值
+/// * `Ok(Vec<u8>)` - 所有成员解压后拼接起来的内容
+/// * `Err(GzipErr)` - 头部无效、数据被截断或者校验和不一致
+pub fn decompress(p: &Path, data: &[u8]) -> Result<Vec<u8>, GzipErr> {
+    let err = |msg: String| GzipErr {
+        path: p.display().to_string(),
+        msg,
+    };
+
+    let mut out = Vec::new();
+    let mut rest = data;
+    loop {
+        let body = skip_header(rest).map_err(|m| err(m.to_string()))?;
+        let start = out.len();
+        let used = inflate_member(body, &mut out).map_err(err)?;
+
+        // 成员末尾是 CRC32 和原始长度（模 2^32），都是小端序
+        let trailer = body
+            .get(used..used + 8)
+            .ok_or_else(|| err("文件被截断，缺少校验和".to_string()))?;
+        let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        if crc32(&out[start..]) != crc {
+            return Err(err("CRC32 校验失败".to_string()));
+        }
+        if (out.len() - start) as u32 != size {
+            return Err(err("解压后的长度和记录的不一致".to_string()));
+        }
+
+        rest = &body[used + 8..];
+        // 有的工具会在末尾补零，只有以魔数开头时才当作下一个成员
+        if !rest.starts_with(&[0x1f, 0x8b]) {
+            return Ok(out);
+        }
+    }
+}
+
+/// 跳过一个成员的头部，返回 DEFLATE 数据开始的位置
+fn skip_header(data: &[u8]) -> Result<&[u8], &'static str> {
+    if data.len() < 10 || data[0] != 0x1f || data[1] != 0x8b {
+        return Err("不是 gzip 格式");
+    }
+    if data[2] != 8 {
+        return Err("不支持的压缩方法");
+    }
+    let flags = data[3];
+    let mut rest = &data[10..];
+
+    if flags & FEXTRA != 0 {
+        if rest.len() < 2 {
+            return Err("头部被截断");
+        }
+        let len = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+        rest = rest.get(2 + len..).ok_or("头部被截断")?;
+    }
+    // 文件名和注释都以 0 结尾
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let end = rest.iter().position(|&b| b == 0).ok_or("头部被截断")?;
+            rest = &rest[end + 1..];
+        }
+    }
+    if flags & FHCRC != 0 {
+        rest = rest.get(2..).ok_or("头部被截断")?;
+    }
+    Ok(rest)
+}
+
+/// 解压一个成员的 DEFLATE 数据，追加到 `out`，返回消耗的输入字节数
+fn inflate_member(input: &[u8], out: &mut Vec<u8>) -> Result<usize, String> {
+    let flags = inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF
+        | inflate_flags::TINFL_FLAG_HAS_MORE_INPUT;
+    let mut state = Box::<DecompressorOxide>::default();
+
+    let base = out.len();
+    let mut out_pos = base;
+    let mut in_pos = 0;
+    out.resize(base + (input.len() * 4).max(4096), 0);
+    loop {
+        let (status, consumed, written) = inflate(&mut state, &input[in_pos..], out, out_pos, flags);
+        in_pos += consumed;
+        out_pos += written;
+        match status {
+            TINFLStatus::Done => {
+                out.truncate(out_pos);
+                return Ok(in_pos);
+            }
+            TINFLStatus::HasMoreOutput => {
+                let len = out.len();
+                out.resize(len * 2, 0);
+            }
+            TINFLStatus::NeedsMoreInput => {
+                out.truncate(out_pos);
+                return Err("文件被截断".to_string());
+            }
+            status => {
+                out.truncate(out_pos);
+                return Err(format!("压缩数据损坏 ({:?})", status));
+            }
+        }
+    }
+}
+
+/// gzip 使用的 CRC32（IEEE 802.3 多项式，按位反转的形式为 0xEDB88320）
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut c = i as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+                k += 1;
+            }
+            table[i] = c;
+            i += 1;
+        }
+        table
+    };
+    !data
+        .iter()
+        .fold(!0u32, |c, &b| TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8))
+}
@@ -0,0 +1,283 @@
+// 交互模式
+//
+// `--interactive` 在输出结果时为每个匹配编号，搜索结束后提示选择要打开的匹配，
+// 然后用 `$VISUAL` / `$EDITOR` 指定的编辑器跳转到对应的行。
+//
+// 不同编辑器跳转到指定行的参数写法不同：
+// * vi / vim / nvim / emacs / emacsclient / nano 等: `+LINE FILE`
+// * VS Code (code / codium): `--goto FILE:LINE`
+// * Sublime Text (subl) / Helix (hx): `FILE:LINE`
+//
+// 编辑器的退出状态不影响 pgrep 本身的结果。
+//
+// `--confirm` 是另一种交互方式：每条结果输出之前先在终端上询问 `[y/n/q/a]?`，
+// 回答 y 才输出，n 跳过，a 输出这一条和之后的所有结果，q 停止搜索。
+// 提示和回答都通过 `/dev/tty` 进行，不占用标准输入和标准输出：
+// 标准输入可以是要搜索的内容（`-f -`），标准输出可以重定向到文件，只保存确认过的结果。
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// 一个可以被打开的匹配
+#[derive(Debug)]
+pub struct Hit {
+    pub path: PathBuf,
+    // 从 1 开始的行号，和编辑器中看到的一致
+    pub line: usize,
+}
+
+/// 解析用户输入的选择
+///
+/// 支持逗号或空白分隔的编号和范围，例如 `1,3,7` 或 `2-4 9`，编号从 1 开始。
+///
+/// # 返回值
+/// * `Ok(Vec<usize>)` - 按输入顺序排列的编号，去掉了重复项
+/// * `Err(String)` - 无法解析或者超出范围时的提示信息
+pub fn parse_selection(input: &str, max: usize) -> Result<Vec<usize>, String> {
+    let mut res: Vec<usize> = Vec::new();
+    let check = |n: usize| {
+        if n == 0 || n > max {
+            Err(format!("编号 {} 超出范围 1-{}", n, max))
+        } else {
+            Ok(n)
+        }
+    };
+    let num = |s: &str| s.trim().parse::<usize>().map_err(|_| format!("无法识别的编号: {}", s));
+
+    for tok in input.split(|c: char| c == ',' || c.is_whitespace()) {
+        if tok.is_empty() {
+            continue;
+        }
+        let (lo, hi) = match tok.split_once('-') {
+            Some((a, b)) => (check(num(a)?)?, check(num(b)?)?),
+            None => {
+                let n = check(num(tok)?)?;
+                (n, n)
+            }
+        };
+        if lo > hi {
+            return Err(format!("范围 {} 的起点大于终点", tok));
+        }
+        for n in lo..=hi {
+            if !res.contains(&n) {
+                res.push(n);
+            }
+        }
+    }
+    if res.is_empty() {
+        return Err("没有选择任何匹配".to_string());
+    }
+    Ok(res)
+}
+
+/// 构造打开文件并跳转到指定行的编辑器命令
+///
+/// # 参数
+/// * `editor` - 编辑器命令，可以带参数，例如 `code -w`
+/// * `path` - 要打开的文件
+/// * `line` - 从 1 开始的行号
+pub fn editor_command(editor: &str, path: &Path, line: usize) -> Vec<OsString> {
+    let mut argv: Vec<OsString> = crate::split_command(editor).into_iter().map(OsString::from).collect();
+    if argv.is_empty() {
+        argv.push("vi".into());
+    }
+
+    let prog = Path::new(&argv[0])
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let mut file_line = path.as_os_str().to_os_string();
+    file_line.push(format!(":{}", line));
+
+    match prog.as_str() {
+        "code" | "code-insiders" | "codium" => {
+            argv.push("--goto".into());
+            argv.push(file_line);
+        }
+        "subl" | "sublime_text" | "hx" | "helix" => argv.push(file_line),
+        _ => {
+            argv.push(format!("+{}", line).into());
+            argv.push(path.as_os_str().to_os_string());
+        }
+    }
+    argv
+}
+
+/// 读取用户选择并依次打开匹配，直到输入 `q` 或者标准输入结束
+pub fn prompt_loop(hits: &[Hit]) {
+    if hits.is_empty() {
+        return;
+    }
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    let stdin = std::io::stdin();
+    loop {
+        print!("open which match? [1-{}, q 退出] ", hits.len());
+        let _ = std::io::stdout().flush();
+
+        let mut input = String::new();
+        match stdin.lock().read_line(&mut input) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        let input = input.trim();
+        if input.is_empty() {
+            continue;
+        }
+        if input.eq_ignore_ascii_case("q") {
+            return;
+        }
+
+        let sel = match parse_selection(input, hits.len()) {
+            Ok(sel) => sel,
+            Err(msg) => {
+                println!("{}", msg);
+                continue;
+            }
+        };
+        for n in sel {
+            let h = &hits[n - 1];
+            let argv = editor_command(&editor, &h.path, h.line);
+            // 编辑器的退出状态被有意忽略，只有无法启动时才提示
+            if let Err(e) = Command::new(&argv[0]).args(&argv[1..]).status() {
+                println!("无法启动编辑器 {}: {}", editor, e);
+            }
+        }
+    }
+}
+
+/// `--confirm` 对一条结果的回答
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    /// 输出这条结果
+    Yes,
+    /// 跳过这条结果
+    No,
+    /// 停止搜索
+    Quit,
+}
+
+/// `--confirm` 的终端
+#[derive(Debug)]
+pub struct Confirm {
+    tty: BufReader<File>,
+    /// 回答过 `a`，之后的结果不再询问
+    all: bool,
+}
+
+impl Confirm {
+    /// 打开控制终端 `/dev/tty`，没有控制终端时（例如在 CI 中）返回错误
+    pub fn open() -> std::io::Result<Confirm> {
+        let tty = File::options().read(true).write(true).open("/dev/tty")?;
+        Ok(Confirm {
+            tty: BufReader::new(tty),
+            all: false,
+        })
+    }
+
+    /// 显示一条结果并询问是否输出，直到得到能识别的回答
+    ///
+    /// 终端的输入结束（Ctrl-D）或者读写出错时当作 `q`
+    pub fn ask(&mut self, shown: &str) -> Answer {
+        if self.all {
+            return Answer::Yes;
+        }
+        loop {
+            if write!(self.tty.get_mut(), "{}\n[y/n/q/a]? ", shown).is_err() {
+                return Answer::Quit;
+            }
+            let mut input = String::new();
+            match self.tty.read_line(&mut input) {
+                Ok(0) | Err(_) => return Answer::Quit,
+                Ok(_) => {}
+            }
+            match input.trim().to_ascii_lowercase().as_str() {
+                "y" | "yes" => return Answer::Yes,
+                "n" | "no" => return Answer::No,
+                "q" | "quit" => return Answer::Quit,
+                "a" | "all" => {
+                    self.all = true;
+                    return Answer::Yes;
+                }
+                _ => {
+                    let _ = writeln!(self.tty.get_mut(), "y 输出，n 跳过，q 停止搜索，a 输出这一条和之后的所有结果");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(editor: &str, path: &str, line: usize) -> Vec<String> {
+        editor_command(editor, Path::new(path), line).into_iter().map(|a| a.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn selection_of_single_numbers() {
+        assert_eq!(parse_selection("1,3,7", 7), Ok(vec![1, 3, 7]));
+        assert_eq!(parse_selection(" 2  5 ", 5), Ok(vec![2, 5]));
+        assert_eq!(parse_selection("3,1,3", 3), Ok(vec![3, 1]));
+    }
+
+    #[test]
+    fn selection_of_ranges() {
+        assert_eq!(parse_selection("2-4 9", 9), Ok(vec![2, 3, 4, 9]));
+        assert_eq!(parse_selection("1-2,2-3", 3), Ok(vec![1, 2, 3]));
+        assert_eq!(parse_selection("4-4", 4), Ok(vec![4]));
+        assert!(parse_selection("4-2", 4).unwrap_err().contains("起点大于终点"));
+    }
+
+    #[test]
+    fn selection_out_of_range_or_invalid() {
+        assert_eq!(parse_selection("8", 7), Err("编号 8 超出范围 1-7".to_string()));
+        assert_eq!(parse_selection("0", 7), Err("编号 0 超出范围 1-7".to_string()));
+        assert!(parse_selection("1-9", 7).is_err());
+        assert!(parse_selection("x", 7).unwrap_err().contains("无法识别的编号"));
+        assert!(parse_selection(" , ", 7).is_err());
+    }
+
+    #[test]
+    fn vim_style_arguments() {
+        assert_eq!(argv("vim", "src/a.rs", 12), ["vim", "+12", "src/a.rs"]);
+        assert_eq!(argv("/usr/bin/nvim -u NONE", "a.rs", 3), ["/usr/bin/nvim", "-u", "NONE", "+3", "a.rs"]);
+        assert_eq!(argv("emacsclient -n", "a.rs", 3), ["emacsclient", "-n", "+3", "a.rs"]);
+        assert_eq!(argv("", "a.rs", 1), ["vi", "+1", "a.rs"]);
+    }
+
+    #[test]
+    fn code_and_subl_arguments() {
+        assert_eq!(argv("code -w", "src/a.rs", 12), ["code", "-w", "--goto", "src/a.rs:12"]);
+        assert_eq!(argv("codium", "a.rs", 1), ["codium", "--goto", "a.rs:1"]);
+        assert_eq!(argv("subl", "a.rs", 7), ["subl", "a.rs:7"]);
+        assert_eq!(argv("hx", "a.rs", 7), ["hx", "a.rs:7"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fake_editor_receives_the_arguments() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("pgrep-fake-editor-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("args.txt");
+        let editor = dir.join("fake-editor");
+        std::fs::write(&editor, format!("#!/bin/sh\nprintf '%s\\n' \"$@\" > '{}'\nexit 3\n", log.display())).unwrap();
+        std::fs::set_permissions(&editor, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let argv = editor_command(&editor.to_string_lossy(), Path::new("src/main.rs"), 42);
+        let status = Command::new(&argv[0]).args(&argv[1..]).status().unwrap();
+        // 编辑器的退出状态由 prompt_loop 忽略，这里只确认假的编辑器确实运行了
+        assert_eq!(status.code(), Some(3));
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "+42\nsrc/main.rs\n");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,247 @@
+// 最简单的 JSON 读写
+//
+// 这个构建没有包含 serde_json，基线文件和 --serve 的 JSON-RPC 消息都用这里的代码读写：
+// * `Parser` 是一个完整但是不追求速度的递归下降解析器，错误信息带有行号和列号
+// * `Json::render` 和 `quote` 输出紧凑的 JSON 文本，非 ASCII 字符原样输出，控制字符用 `\u` 转义
+// * 对象保持字段的顺序，重复的字段以第一个为准
+//
+// 相关文档:
+// * JSON: <https://www.rfc-editor.org/rfc/rfc8259>
+
+/// 写成 JSON 字符串
+pub fn quote(s: &str) -> String {
+    let mut q = String::with_capacity(s.len() + 2);
+    q.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => q.push_str("\\\""),
+            '\\' => q.push_str("\\\\"),
+            '\n' => q.push_str("\\n"),
+            '\r' => q.push_str("\\r"),
+            '\t' => q.push_str("\\t"),
+            c if (c as u32) < 0x20 => q.push_str(&format!("\\u{:04x}", c as u32)),
+            c => q.push(c),
+        }
+    }
+    q.push('"');
+    q
+}
+
+/// JSON 的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// 保持字段的顺序，重复的字段以第一个为准
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// 对象中的字段，不是对象或者没有这个字段时是 None
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// 写成紧凑的 JSON 文本，没有多余的空白
+    pub fn render(&self) -> String {
+        match self {
+            Json::Null => "null".to_string(),
+            Json::Bool(b) => b.to_string(),
+            Json::Number(n) => n.to_string(),
+            Json::String(s) => quote(s),
+            Json::Array(items) => format!("[{}]", items.iter().map(Json::render).collect::<Vec<_>>().join(",")),
+            Json::Object(fields) => format!(
+                "{{{}}}",
+                fields
+                    .iter()
+                    .map(|(k, v)| format!("{}:{}", quote(k), v.render()))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+        }
+    }
+}
+
+/// 递归下降的 JSON 解析器
+pub struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(s: &'a str) -> Parser<'a> {
+        Parser { s, pos: 0 }
+    }
+
+    /// 整个文本是一个 JSON 值，之后只能有空白
+    pub fn document(mut self) -> Result<Json, String> {
+        let v = self.value()?;
+        self.skip_ws();
+        if self.pos < self.s.len() {
+            return Err(self.error("值之后还有多余的内容"));
+        }
+        Ok(v)
+    }
+
+    fn error(&self, what: &str) -> String {
+        let before = &self.s[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let col = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+        format!("第 {} 行第 {} 列: {}", line, col, what)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.s.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), String> {
+        self.skip_ws();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("应该是 `{}`", c as char)))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_ws();
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => {
+                for (word, v) in [("true", Json::Bool(true)), ("false", Json::Bool(false)), ("null", Json::Null)] {
+                    if self.s[self.pos..].starts_with(word) {
+                        self.pos += word.len();
+                        return Ok(v);
+                    }
+                }
+                Err(self.error("应该是一个 JSON 值"))
+            }
+        }
+    }
+
+    /// `{ ... }` 或 `[ ... ]` 中用逗号分开的项
+    fn items(&mut self, close: u8, mut item: impl FnMut(&mut Self) -> Result<(), String>) -> Result<(), String> {
+        self.pos += 1;
+        self.skip_ws();
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            self.skip_ws();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error(&format!("应该是 `,` 或 `{}`", close as char))),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        let mut fields = Vec::new();
+        self.items(b'}', |p| {
+            p.skip_ws();
+            if p.peek() != Some(b'"') {
+                return Err(p.error("字段名应该是字符串"));
+            }
+            let k = p.string()?;
+            p.expect(b':')?;
+            let v = p.value()?;
+            fields.push((k, v));
+            Ok(())
+        })?;
+        Ok(Json::Object(fields))
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        let mut items = Vec::new();
+        self.items(b']', |p| {
+            items.push(p.value()?);
+            Ok(())
+        })?;
+        Ok(Json::Array(items))
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        self.s[start..self.pos]
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| self.error("无效的数字"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let Some(c) = self.s[self.pos..].chars().next() else {
+                return Err(self.error("字符串没有结束"));
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    let Some(e) = self.peek() else {
+                        return Err(self.error("字符串没有结束"));
+                    };
+                    self.pos += 1;
+                    match e {
+                        b'"' => out.push('"'),
+                        b'\\' => out.push('\\'),
+                        b'/' => out.push('/'),
+                        b'b' => out.push('\u{8}'),
+                        b'f' => out.push('\u{c}'),
+                        b'n' => out.push('\n'),
+                        b'r' => out.push('\r'),
+                        b't' => out.push('\t'),
+                        b'u' => {
+                            let hi = self.hex4()?;
+                            // 基本平面之外的字符写成一对代理项
+                            let code = if (0xd800..0xdc00).contains(&hi) && self.s[self.pos..].starts_with("\\u") {
+                                self.pos += 2;
+                                let lo = self.hex4()?;
+                                0x10000 + ((hi - 0xd800) << 10) + (lo.wrapping_sub(0xdc00) & 0x3ff)
+                            } else {
+                                hi
+                            };
+                            out.push(char::from_u32(code).ok_or_else(|| self.error("无效的 \\u 转义"))?);
+                        }
+                        _ => return Err(self.error("无效的转义字符")),
+                    }
+                }
+                c if (c as u32) < 0x20 => return Err(self.error("字符串中有没有转义的控制字符")),
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self.s.get(self.pos..self.pos + 4).ok_or_else(|| self.error("\\u 之后应该有 4 个十六进制数字"))?;
+        let n = u32::from_str_radix(digits, 16).map_err(|_| self.error("\\u 之后应该有 4 个十六进制数字"))?;
+        self.pos += 4;
+        Ok(n)
+    }
+}
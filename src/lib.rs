@@ -0,0 +1,2230 @@
+//! pgrep 的搜索核心
+//!
+//! 命令行工具 `pgrep` 的目录遍历和逐行匹配都在这个库里，其他程序也可以直接调用。
+//! 最简单的入口是 [`grep`]：给出模式、路径和 [`GrepConfig`]，
+//! 它在后台线程中搜索，并以迭代器的形式逐条返回匹配结果。
+//!
+//! 内容已经在内存中时使用 [`search_bytes`]。
+//! 需要更细的控制（例如自己处理目录完成事件）时可以直接使用 [`process_path`]、
+//! [`process_file`] 和 [`process_bytes`]，它们和命令行工具使用的是同一套代码。
+//!
+//! # 示例
+//!
+//! 在一个目录中搜索，收集所有匹配的行：
+//!
+//! ```
+//! use pgrep::{grep, GrepConfig};
+//!
+//! let dir = std::env::temp_dir().join("pgrep-doc-basic");
+//! std::fs::create_dir_all(&dir)?;
+//! std::fs::write(dir.join("a.txt"), "hello\nworld\nhello world\n")?;
+//!
+//! let hits = grep("world", &dir, GrepConfig::default()).collect::<Result<Vec<_>, _>>()?;
+//! assert_eq!(hits.len(), 2);
+//!
+//! let (path, rec) = &hits[0];
+//! assert!(path.ends_with("a.txt"));
+//! // 行号从 0 开始
+//! assert_eq!(rec.line, 1);
+//! assert_eq!(rec.tx, "world");
+//! # Ok::<(), failure::Error>(())
+//! ```
+//!
+//! 搜索选项都在 [`GrepConfig`] 中，没有设置的字段保持默认值即可：
+//!
+//! ```
+//! use pgrep::{grep, GrepConfig};
+//!
+//! let dir = std::env::temp_dir().join("pgrep-doc-config");
+//! std::fs::create_dir_all(&dir)?;
+//! std::fs::write(dir.join("notes.txt"), "# TODO: 注释\nTODO: 正文\n   \n")?;
+//!
+//! let cfg = GrepConfig {
+//!     skip_prefixes: vec!["#".to_string()],
+//!     ..GrepConfig::default()
+//! };
+//! let lines: Vec<String> = grep("TODO", &dir, cfg)
+//!     .map(|r| r.map(|(_, rec)| rec.tx))
+//!     .collect::<Result<_, _>>()?;
+//! assert_eq!(lines, ["TODO: 正文"]);
+//! # Ok::<(), failure::Error>(())
+//! ```
+//!
+//! 结果是惰性产生的，提前丢弃迭代器会让后台的搜索尽快停下来：
+//!
+//! ```
+//! use pgrep::{grep, GrepConfig};
+//!
+//! let dir = std::env::temp_dir().join("pgrep-doc-take");
+//! std::fs::create_dir_all(&dir)?;
+//! std::fs::write(dir.join("many.txt"), "x\n".repeat(10_000))?;
+//!
+//! let first = grep("x", &dir, GrepConfig::default()).take(3).count();
+//! assert_eq!(first, 3);
+//! # Ok::<(), failure::Error>(())
+//! ```
+//!
+//! 错误和匹配结果混在同一个迭代器中。无法编译的模式只产生一个错误；
+//! 单个文件的错误（例如路径不存在）不会影响其他文件的结果：
+//!
+//! ```
+//! use pgrep::{grep, GrepConfig};
+//!
+//! let mut it = grep("(", ".", GrepConfig::default());
+//! assert!(it.next().unwrap().is_err());
+//! assert!(it.next().is_none());
+//!
+//! let missing = std::env::temp_dir().join("pgrep-doc-missing/none.txt");
+//! let res: Vec<_> = grep("x", missing, GrepConfig::default()).collect();
+//! assert_eq!(res.len(), 1);
+//! assert!(res[0].is_err());
+//! ```
+
+// failure 的 derive 宏会在 `const _: () = { ... }` 块内生成 impl，
+// 新版编译器会对此给出 non_local_definitions 警告，这里统一允许
+#![allow(non_local_definitions)]
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use failure::{Error, Fail};
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, mpsc};
+use std::time::{Duration, Instant};
+
+// 输出到标准错误的分级日志，日志宏需要先于其他模块声明
+#[doc(hidden)]
+#[macro_use]
+pub mod log;
+
+// --archives 使用的 tar / zip 成员遍历
+pub mod archive;
+use archive::Member;
+
+// --encoding-chain 使用的文本编码
+pub mod encoding;
+use encoding::Encoding;
+
+// -z 使用的压缩格式检测和 gzip 解压
+pub mod compress;
+mod gzip;
+
+// max_line_bytes 截断过长的行
+mod longline;
+
+// newline 使用的行尾识别方式
+pub mod newline;
+use newline::Newline;
+
+// 路径通配符
+pub mod glob;
+use glob::Glob;
+
+// --type / --type-not 使用的文件类型
+pub mod filetype;
+use filetype::TypeSelect;
+
+// --pre 使用的预处理命令
+pub mod preprocess;
+use preprocess::Preprocessor;
+
+// Soundex / Metaphone 语音编码
+pub mod phonetic;
+use phonetic::PhoneticConfig;
+
+// --replace 的模板引擎
+pub mod replace;
+use replace::Template;
+
+// --debug-timing 使用的每个文件各阶段的耗时
+pub mod timing;
+use timing::{Phase, Timings};
+
+// --literal-match-only 使用的按字面查找
+pub mod matcher;
+use matcher::{LiteralMatcher, Matcher};
+
+// --match-all 和 --parallel-regex 检查一行是否匹配所有的模式
+pub mod matchall;
+
+// --cache-dir 把整个内容上查找时使用的 DFA 保存到磁盘
+pub mod dfacache;
+use dfacache::Automaton;
+
+// 不存在的搜索路径的拼写建议
+pub mod suggest;
+
+/// 在路径下搜索模式，以迭代器的形式返回所有匹配
+///
+/// 路径是文件时只搜索这个文件，是目录时递归搜索其中的所有文件，`-` 表示标准输入。
+/// 搜索在后台线程中进行，结果通过有界的通道传回，
+/// 调用方处理得慢时后台线程会等待，不会把所有结果都堆在内存里。
+///
+/// # 参数
+/// * `pattern` - 正则表达式；设置了 `fuzzy`、`phonetic` 或 `words` 时只用于 `replace`
+/// * `path` - 要搜索的文件或目录
+/// * `config` - 搜索配置，`GrepConfig::default()` 就是普通的正则搜索
+///
+/// # 返回值
+/// 每一项是 `(文件路径, 匹配记录)` 或者一个错误。
+/// 单个文件的错误不会终止搜索；模式无法编译、模糊匹配超时这类致命错误是最后一项。
+///
+/// # 示例
+/// ```
+/// use pgrep::{grep, GrepConfig};
+///
+/// let f = std::env::temp_dir().join("pgrep-doc-fn.txt");
+/// std::fs::write(&f, "fn main() {}\n")?;
+/// for hit in grep(r"fn \w+", &f, GrepConfig::default()) {
+///     let (path, rec) = hit?;
+///     println!("{}:{}:{}", path.display(), rec.line + 1, rec.tx);
+/// }
+/// # Ok::<(), failure::Error>(())
+/// ```
+///
+/// # 相关文档
+/// * std::sync::mpsc::sync_channel: <https://doc.rust-lang.org/std/sync/mpsc/fn.sync_channel.html>
+pub fn grep(
+    pattern: &str,
+    path: impl AsRef<Path>,
+    config: GrepConfig,
+) -> impl Iterator<Item = Result<(PathBuf, Record), Error>> {
+    let (tx, rx) = mpsc::sync_channel(64);
+    let re = Regex::new(pattern);
+    let path = path.as_ref().to_path_buf();
+
+    std::thread::spawn(move || {
+        let re = match re {
+            Ok(re) => re,
+            Err(e) => {
+                let _ = tx.send(Err(e.into()));
+                return;
+            }
+        };
+        // 发送失败说明迭代器已经被丢弃，用 Halt 让 process_path 尽快返回
+        let ff = |p: &Path, v: Vec<Record>| -> Result<(), Error> {
+            for r in v {
+                if tx.send(Ok((p.to_path_buf(), r))).is_err() {
+                    return Err(Halt {
+                        reason: "结果已经没有人接收".to_string(),
+                    }
+                    .into());
+                }
+            }
+            Ok(())
+        };
+        let ef = |e: Error| {
+            let _ = tx.send(Err(e));
+        };
+        if let Err(e) = process_path(&path, &re, &config, &WalkContext::default(), &ff, &|_: &Path| {}, &ef) {
+            let _ = tx.send(Err(e));
+        }
+    });
+
+    rx.into_iter()
+}
+
+/// 记录结构体
+///
+/// 用于存储在文件中找到的匹配结果
+///
+/// # 字段
+/// * `line` - 匹配行号（从0开始计数）
+/// * `tx` - 匹配行的文本内容
+/// * `fuzzy` - 模糊匹配模式下找到的最接近的子串及其编辑距离
+/// * `replaced` - 使用 `--replace` 时，替换所有匹配后的行文本
+/// * `word` - 使用 `--word-list` 时，命中的词表中的词
+/// * `suppressed` - 这一行或者上一行带有 `GrepConfig::ignore_marker` 标记，调用者通常不输出这样的匹配
+/// * `rules` - 使用 `GrepConfig::rules` 时，匹配了这一行的规则在其中的下标，按规则的顺序排列
+/// * `is_context` - 这一行不是匹配，而是 `GrepConfig::before_context` / `after_context` 加上的上下文行
+#[derive(Debug)]
+pub struct Record {
+    pub line: usize,
+    pub tx: String,
+    pub fuzzy: Option<FuzzyHit>,
+    pub replaced: Option<String>,
+    pub word: Option<String>,
+    pub suppressed: bool,
+    pub rules: Vec<usize>,
+    pub is_context: bool,
+}
+
+/// 模糊匹配结果
+///
+/// 记录一行中与模式编辑距离最小的子串
+///
+/// # 字段
+/// * `tx` - 最接近模式的子串
+/// * `dist` - 该子串与模式之间的 Levenshtein 编辑距离
+#[derive(Debug)]
+pub struct FuzzyHit {
+    pub tx: String,
+    pub dist: usize,
+}
+
+/// 模糊匹配配置
+///
+/// # 字段
+/// * `pattern` - 按字面文本处理的模式（不是正则表达式）
+/// * `max_edits` - 允许的最大编辑距离
+/// * `started` - 搜索开始的时间
+/// * `limit` - 从 `started` 起允许运行的最长时间，防止搜索无限期地运行下去
+#[derive(Debug)]
+pub struct FuzzyConfig {
+    pub pattern: String,
+    pub max_edits: usize,
+    pub started: Instant,
+    pub limit: Option<Duration>,
+}
+
+/// 词表匹配配置
+///
+/// # 字段
+/// * `ac` - 由词表构建的 Aho-Corasick 自动机
+/// * `words` - 词表，下标与自动机中的模式编号一致
+/// * `whole_word` - 是否要求命中的词前后都是单词边界
+#[derive(Debug)]
+pub struct WordList {
+    ac: AhoCorasick,
+    words: Vec<String>,
+    whole_word: bool,
+}
+
+impl WordList {
+    /// 从文件读取词表并构建自动机
+    ///
+    /// 文件每行一个词，忽略空行和行尾的空白。
+    ///
+    /// # 相关文档
+    /// * AhoCorasickBuilder: <https://docs.rs/aho-corasick/latest/aho_corasick/struct.AhoCorasickBuilder.html>
+    pub fn load(p: &Path, case_insensitive: bool, whole_word: bool) -> Result<WordList, Error> {
+        let text = std::fs::read_to_string(p)?;
+        let words: Vec<String> = text
+            .lines()
+            .map(str::trim_end)
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect();
+        if words.is_empty() {
+            return Err(ArgErr { arg: "word list" }.into());
+        }
+        // 整词模式下需要检查所有重叠的候选，才不会因为一个不满足边界的词挡住其他词，
+        // 重叠搜索只支持 Standard 匹配方式
+        let kind = if whole_word {
+            MatchKind::Standard
+        } else {
+            MatchKind::LeftmostLongest
+        };
+        let ac = AhoCorasick::builder()
+            .ascii_case_insensitive(case_insensitive)
+            .match_kind(kind)
+            .build(&words)?;
+        Ok(WordList { ac, words, whole_word })
+    }
+
+    /// 查找一行中第一个命中的词
+    pub fn find(&self, line: &str) -> Option<&str> {
+        if !self.whole_word {
+            return self.ac.find(line).map(|m| self.words[m.pattern()].as_str());
+        }
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        self.ac
+            .find_overlapping_iter(line)
+            .find(|m| {
+                let before = line[..m.start()].chars().next_back();
+                let after = line[m.end()..].chars().next();
+                !before.is_some_and(is_word) && !after.is_some_and(is_word)
+            })
+            .map(|m| self.words[m.pattern()].as_str())
+    }
+}
+
+/// 按文件类型过滤
+///
+/// 过滤只作用于文件，普通目录总是会被递归
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TypeFilter {
+    /// 只搜索普通文件，跳过所有符号链接（包括指向目录的符号链接）
+    Regular,
+    /// 只搜索符号链接指向的文件，不递归指向目录的符号链接
+    Symlink,
+    /// 普通文件和符号链接都搜索，符号链接指向的目录也会递归（默认行为）
+    #[default]
+    All,
+}
+
+/// 目录遍历的状态
+///
+/// 和 GrepConfig 不同，它描述的是"当前走到了哪里"，每进入一层目录就生成一份新的
+///
+/// # 字段
+/// * `symlink_depth` - 从搜索起点到当前路径，一共经过了多少个符号链接
+/// * `ancestors` - 当前路径上所有祖先目录的 (设备号, inode)，用来发现符号链接造成的环
+#[derive(Debug, Default, Clone)]
+pub struct WalkContext {
+    pub symlink_depth: usize,
+    pub ancestors: Vec<(u64, u64)>,
+}
+
+/// 从符号链接 `p` 出发，一跳一跳地解析到不是符号链接的路径，返回经过的跳数
+///
+/// 相对的目标相对于链接所在的目录解析。超过 `max` 跳就停下来返回 `max + 1`，链接成环时也不会一直走下去
+fn link_hops(p: &Path, max: usize) -> usize {
+    let mut hops = 0;
+    let mut cur = p.to_path_buf();
+    while let Ok(target) = std::fs::read_link(&cur) {
+        hops += 1;
+        if hops > max {
+            break;
+        }
+        cur = match cur.parent() {
+            Some(dir) => dir.join(target),
+            None => target,
+        };
+    }
+    hops
+}
+
+/// 返回文件的 (设备号, inode)，用来判断两个路径是不是同一个目录
+///
+/// 非 Unix 平台上没有 inode，返回 None，此时不做环检测
+#[cfg(unix)]
+fn file_id(md: &std::fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((md.dev(), md.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_md: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+
+/// 搜索配置
+///
+/// 汇总影响单个文件匹配方式的选项，在 process_path 的递归过程中一路向下传递
+///
+/// # 字段
+/// * `fuzzy` - 设置后使用模糊匹配代替正则表达式匹配
+/// * `phonetic` - 设置后按单词发音匹配代替正则表达式匹配
+/// * `replace` - 设置后为每个匹配行计算替换后的文本
+/// * `words` - 设置后用词表匹配代替正则表达式匹配
+/// * `skip_dirs` - 需要跳过的目录，`--resume` 时为检查点中记录的已完成目录
+/// * `type_filter` - 按文件类型过滤要搜索的条目
+/// * `max_symlink_depth` - 一条路径上最多跟随多少个符号链接，链接指向的链接也算一层；默认 MAX_SYMLINK_DEPTH
+/// * `debug_skip` - 报告每个没有被搜索的路径及其原因
+/// * `debug_skip_glob` - 只报告匹配这个通配符的路径
+/// * `line_prefixes` - 只有以其中某个前缀开头的行才参与匹配，为空时不限制
+/// * `skip_prefixes` - 以其中某个前缀开头的行不参与匹配
+/// * `skip_empty_lines` - 空行和只有空白字符的行不参与匹配
+/// * `search_zip` - 解压压缩文件后再搜索
+/// * `search_archives` - 搜索 tar / zip 包中的每个成员，见 archive 模块
+/// * `max_archive_depth` - 最多打开几层包，1 表示只打开磁盘上的包，不打开包中的包
+/// * `max_member_size` - 包中单个成员的最大字节数，超过的成员被跳过；None 表示不限制
+/// * `crlf_is_lf` - 匹配之前把 `\r\n` 和单独的 `\r` 都换成 `\n`
+/// * `io_retry` - 读取文件遇到暂时性的 I/O 错误时最多重试几次，默认 DEFAULT_IO_RETRY
+/// * `pre` - 设置后先用预处理命令转换文件，搜索命令的输出
+/// * `rules` - 设置后每个文件只用路径匹配的规则的模式搜索，见 Rule
+/// * `encoding_chain` - 依次尝试的文本编码，为空时只接受 UTF-8，不是合法 UTF-8 的文件被跳过
+/// * `profile_regex` - 把每个文件的正则表达式匹配总耗时记在 `timing` 中，见 Timings::regex_report
+/// * `profile_per_line` - 把每一行的正则表达式匹配耗时记在 `timing` 中；这两个选项都需要设置 `timing`
+/// * `match_buffer_size` - 每个文件的结果向量预先分配的容量，None 时为 DEFAULT_MATCH_BUFFER_SIZE
+/// * `ignore_marker` - 设置后，自身或上一行含有这个标记的匹配记为 `Record::suppressed`
+/// * `type_select` - 设置后只搜索被选中的文件类型，见 filetype 模块
+/// * `max_line_bytes` - 设置后每行只有前这么多字节参与匹配，普通文件边读边截断，见 longline 模块
+/// * `binary_scan_bytes` - 检查内容的前这么多字节，其中有 NUL 字节的内容看作二进制文件并跳过；0 表示不检查
+/// * `null_ratio` - 设置后改为按比例判断：检查的字节中 NUL 和控制字符的比例超过它（0 到 1）才算二进制文件
+/// * `all_lines` - 每一行都算匹配，不运行正则表达式；行过滤（`line_prefixes` 等）照常生效
+/// * `sorted_walk` - 目录中的条目按名字排序后再搜索，每次运行的搜索顺序都相同；替换模板中有 `{{n}}` 时需要
+/// * `warn_mixed_endings` - 文件混用了 `\n`、`\r\n` 和单独的 `\r` 中的几种行尾时，对这个文件给出一条警告
+/// * `skip_matches` - 每个文件跳过前这么多个匹配的行，只返回之后的匹配；被抑制的匹配不计入
+/// * `newline` - 哪些字符序列是行尾，见 newline 模块；`crlf_is_lf` 统一行尾之后只剩 `\n`
+/// * `context_re` - 设置后只保留之后 `context_after` 行之内有一行匹配它的结果，见 GrepConfig::context_filter
+/// * `context_after` - `context_re` 检查每个匹配之后的多少行
+/// * `before_context` / `after_context` - 在每个匹配之前 / 之后加上这么多行上下文记录，见 GrepConfig::with_context；
+///   和只用来筛选的 `context_after` 不同，这些行会被返回
+/// * `timing` - 设置后记录每个文件读取和解码、匹配、输出各阶段的耗时，见 timing 模块
+/// * `overlapping` - 一行中的匹配可以互相重叠，见 match_spans；只影响输出时找出的匹配，不影响哪些行匹配
+/// * `fields` - 设置后每行分成字段，只用其中的一部分字段匹配，见 Fields
+/// * `only_field` - 记录中的文本只是匹配的字段而不是整行
+/// * `replace_verify` - 设置后每一行替换之后的结果都要匹配它，见 GrepConfig::replacement
+/// * `replace_verify_skip` - 替换结果不匹配 `replace_verify` 时保留原来的行，而不是停止整个搜索
+/// * `literal` - 设置后用它按字面判断哪些行匹配，不运行正则表达式；它必须和传入的正则表达式匹配同样的行，
+///   例如正则表达式是 `regex::escape` 转义之后的同一个子串，见 matcher 模块
+/// * `multiline` - 在整个内容上查找时使用的多行模式正则表达式的缓存，由搜索自己填写，见 MultilineCache
+/// * `automaton` - 设置后在整个内容上查找时用它代替多行模式的正则表达式，它的模式和传入的正则表达式不同时不使用，
+///   见 dfacache 模块
+#[derive(Debug)]
+pub struct GrepConfig {
+    pub fuzzy: Option<FuzzyConfig>,
+    pub phonetic: Option<PhoneticConfig>,
+    pub replace: Option<Template>,
+    pub words: Option<WordList>,
+    pub skip_dirs: HashSet<PathBuf>,
+    pub type_filter: TypeFilter,
+    pub max_symlink_depth: usize,
+    pub debug_skip: bool,
+    pub debug_skip_glob: Option<Glob>,
+    pub line_prefixes: Vec<String>,
+    pub skip_prefixes: Vec<String>,
+    pub crlf_is_lf: bool,
+    pub skip_empty_lines: bool,
+    pub search_zip: bool,
+    pub search_archives: bool,
+    pub max_archive_depth: usize,
+    pub max_member_size: Option<u64>,
+    pub encoding_chain: Vec<Encoding>,
+    pub io_retry: u32,
+    pub pre: Option<Preprocessor>,
+    pub rules: Vec<Rule>,
+    pub profile_regex: bool,
+    pub profile_per_line: bool,
+    pub match_buffer_size: Option<usize>,
+    pub ignore_marker: Option<String>,
+    pub type_select: Option<TypeSelect>,
+    pub max_line_bytes: Option<usize>,
+    pub newline: Newline,
+    pub skip_matches: usize,
+    pub warn_mixed_endings: bool,
+    pub sorted_walk: bool,
+    pub all_lines: bool,
+    pub binary_scan_bytes: usize,
+    pub null_ratio: Option<f64>,
+    pub overlapping: bool,
+    pub timing: Option<Timings>,
+    pub context_re: Option<Regex>,
+    pub context_after: usize,
+    pub before_context: usize,
+    pub after_context: usize,
+    pub fields: Option<Fields>,
+    pub only_field: bool,
+    pub replace_verify: Option<Regex>,
+    pub replace_verify_skip: bool,
+    pub literal: Option<LiteralMatcher>,
+    pub multiline: MultilineCache,
+    pub automaton: Option<Automaton>,
+}
+
+/// 一条路径上默认最多跟随的符号链接数，和 Linux 内核的 MAXSYMLINKS 相同
+pub const MAX_SYMLINK_DEPTH: usize = 40;
+
+/// 读取文件遇到暂时性的 I/O 错误时默认的重试次数
+pub const DEFAULT_IO_RETRY: u32 = 3;
+
+/// 默认检查内容的前多少字节来判断是不是二进制文件
+pub const DEFAULT_BINARY_SCAN_BYTES: usize = 8192;
+
+/// 默认最多打开几层包
+pub const DEFAULT_MAX_ARCHIVE_DEPTH: usize = 1;
+
+/// 包中单个成员默认的最大字节数，100 MiB
+pub const DEFAULT_MAX_MEMBER_SIZE: u64 = 100 << 20;
+
+/// 每行默认最多有多少字节参与匹配，64 MiB
+pub const DEFAULT_MAX_LINE_BYTES: usize = 64 << 20;
+
+/// 默认的行内抑制标记
+pub const DEFAULT_IGNORE_MARKER: &str = "pgrep-ignore";
+
+/// 和命令行工具不加任何选项时相同的配置：普通的正则搜索
+///
+/// 和命令行的默认值不同于各自类型默认值的字段：
+/// * `max_symlink_depth` - MAX_SYMLINK_DEPTH
+/// * `io_retry` - DEFAULT_IO_RETRY
+/// * `binary_scan_bytes` - DEFAULT_BINARY_SCAN_BYTES，含有 NUL 字节的内容被跳过
+/// * `max_archive_depth` - DEFAULT_MAX_ARCHIVE_DEPTH，只在设置了 `search_archives` 时生效
+/// * `max_member_size` - DEFAULT_MAX_MEMBER_SIZE
+/// * `max_line_bytes` - DEFAULT_MAX_LINE_BYTES
+/// * `ignore_marker` - DEFAULT_IGNORE_MARKER
+///
+/// 其他字段都是各自类型的默认值
+impl Default for GrepConfig {
+    fn default() -> GrepConfig {
+        GrepConfig {
+            fuzzy: Default::default(),
+            phonetic: Default::default(),
+            replace: Default::default(),
+            words: Default::default(),
+            skip_dirs: Default::default(),
+            type_filter: Default::default(),
+            max_symlink_depth: MAX_SYMLINK_DEPTH,
+            debug_skip: Default::default(),
+            debug_skip_glob: Default::default(),
+            line_prefixes: Default::default(),
+            skip_prefixes: Default::default(),
+            crlf_is_lf: Default::default(),
+            skip_empty_lines: Default::default(),
+            search_zip: Default::default(),
+            search_archives: Default::default(),
+            max_archive_depth: DEFAULT_MAX_ARCHIVE_DEPTH,
+            max_member_size: Some(DEFAULT_MAX_MEMBER_SIZE),
+            encoding_chain: Default::default(),
+            io_retry: DEFAULT_IO_RETRY,
+            pre: Default::default(),
+            rules: Default::default(),
+            profile_regex: Default::default(),
+            profile_per_line: Default::default(),
+            match_buffer_size: Default::default(),
+            ignore_marker: Some(DEFAULT_IGNORE_MARKER.to_string()),
+            type_select: Default::default(),
+            max_line_bytes: Some(DEFAULT_MAX_LINE_BYTES),
+            newline: Default::default(),
+            skip_matches: Default::default(),
+            warn_mixed_endings: Default::default(),
+            sorted_walk: Default::default(),
+            all_lines: Default::default(),
+            binary_scan_bytes: DEFAULT_BINARY_SCAN_BYTES,
+            null_ratio: Default::default(),
+            overlapping: Default::default(),
+            timing: Default::default(),
+            context_re: Default::default(),
+            context_after: Default::default(),
+            before_context: Default::default(),
+            after_context: Default::default(),
+            fields: Default::default(),
+            only_field: Default::default(),
+            replace_verify: Default::default(),
+            replace_verify_skip: Default::default(),
+            literal: Default::default(),
+            multiline: Default::default(),
+            automaton: Default::default(),
+        }
+    }
+}
+
+/// 结果向量默认预先分配的容量
+///
+/// 大多数文件只有几个匹配，16 条记录足够，而且每个文件只多占用几百字节；
+/// 几乎每一行都匹配的文件可以用 `--match-buffer-size` 设置更大的值，减少扩容时的复制
+pub const DEFAULT_MATCH_BUFFER_SIZE: usize = 16;
+
+impl GrepConfig {
+    /// 设置了 `timing` 时开始计时，没有设置时不读取时钟
+    fn timer(&self) -> Option<Instant> {
+        self.timing.as_ref().map(|_| Instant::now())
+    }
+
+    /// 把从 `started` 开始到现在的耗时记到 `p` 的一个阶段上
+    fn record_time(&self, p: &Path, phase: Phase, started: Option<Instant>) {
+        if let (Some(t), Some(started)) = (&self.timing, started) {
+            t.add(p, phase, started.elapsed());
+        }
+    }
+
+    /// 设置了 `context_re` 时只保留之后 `context_after` 行之内有一行匹配它的记录
+    ///
+    /// 检查的是匹配之后的原始行，行过滤（`line_prefixes` 等）不影响检查的范围，
+    /// 匹配行本身不算；窗口超出文件末尾时只检查到最后一行
+    fn context_filter(&self, ss: &str, mut res: Vec<Record>) -> Vec<Record> {
+        let Some(cre) = &self.context_re else {
+            return res;
+        };
+        if res.is_empty() {
+            return res;
+        }
+        let lines: Vec<&str> = self.newline.lines(ss).collect();
+        res.retain(|r| {
+            let end = (r.line + 1 + self.context_after).min(lines.len());
+            lines.get(r.line + 1..end).is_some_and(|w| w.iter().any(|l| cre.is_match(l)))
+        });
+        res
+    }
+
+    /// 在匹配的记录之间插入前 `before_context` 行、后 `after_context` 行上下文记录
+    ///
+    /// 上下文记录的 `is_context` 为 true，和匹配的记录一起按行号排列；本身匹配的行不会再作为上下文出现，
+    /// 相邻的两个匹配的上下文重叠时每一行只出现一次。和 `context_filter` 一样检查的是原始的行，
+    /// 被行过滤（`line_prefixes` 等）排除的行也可以是上下文
+    fn with_context(&self, ss: &str, res: Vec<Record>) -> Vec<Record> {
+        if (self.before_context == 0 && self.after_context == 0) || res.is_empty() {
+            return res;
+        }
+        let lines: Vec<&str> = self.newline.lines(ss).collect();
+        let context = |i: usize| Record {
+            line: i,
+            tx: lines[i].to_string(),
+            fuzzy: None,
+            replaced: None,
+            word: None,
+            suppressed: false,
+            rules: Vec::new(),
+            is_context: true,
+        };
+        let mut out = Vec::with_capacity(res.len() * (1 + self.before_context + self.after_context));
+        // 下一个还没有加入的行，和上一个匹配的后文到哪一行为止（不包括）
+        let (mut next, mut after_end) = (0, 0);
+        for r in res {
+            let end = after_end.min(r.line).max(next);
+            out.extend((next..end).map(context));
+            out.extend((r.line.saturating_sub(self.before_context).max(end)..r.line).map(context));
+            next = r.line + 1;
+            after_end = (next + self.after_context).min(lines.len());
+            out.push(r);
+        }
+        out.extend((next..after_end).map(context));
+        out
+    }
+
+    /// 一行中参与匹配的部分：没有设置 `fields` 时只有整行，否则是范围之内的每个字段
+    ///
+    /// 字段数不够的行只有范围之内存在的字段，一个字段也没有时这一行不会匹配
+    ///
+    /// # 示例
+    /// ```
+    /// use pgrep::{Fields, GrepConfig};
+    ///
+    /// let cfg = GrepConfig {
+    ///     fields: Some(Fields {
+    ///         separator: '\t',
+    ///         first: 2,
+    ///         last: Some(3),
+    ///     }),
+    ///     ..Default::default()
+    /// };
+    /// let targets: Vec<&str> = cfg.match_targets("10:01\tGET\t/index\t200").collect();
+    /// assert_eq!(targets, ["GET", "/index"]);
+    /// assert_eq!(cfg.match_targets("10:02\tPOST").collect::<Vec<_>>(), ["POST"]);
+    /// assert_eq!(cfg.match_targets("10:03").count(), 0);
+    /// assert_eq!(GrepConfig::default().match_targets("a\tb").collect::<Vec<_>>(), ["a\tb"]);
+    /// ```
+    pub fn match_targets<'a>(&self, line: &'a str) -> impl Iterator<Item = &'a str> {
+        let whole = self.fields.is_none().then_some(line);
+        let fields = self.fields.as_ref().map(|f| {
+            let first = f.first.max(1);
+            let n = f.last.map_or(usize::MAX, |last| (last + 1).saturating_sub(first));
+            line.split(f.separator).skip(first - 1).take(n)
+        });
+        whole.into_iter().chain(fields.into_iter().flatten())
+    }
+
+    /// 替换后的一行是否匹配 `replace_verify`，没有设置时总是 true
+    pub fn verified(&self, replaced: &str) -> bool {
+        self.replace_verify.as_ref().is_none_or(|v| v.is_match(replaced))
+    }
+
+    /// 一行替换之后的文本，没有替换模板时为 None；`line` 是从 0 开始的行号，只用于错误信息
+    ///
+    /// 替换结果不匹配 `replace_verify` 时：`replace_verify_skip` 时给出警告并保留原来的行，
+    /// 否则返回 ReplaceVerifyFailed，它会终止整个搜索，之后的文件都不会被替换
+    pub fn replacement(&self, p: &Path, line: usize, re: &Regex, tx: &str) -> Result<Option<String>, Error> {
+        let Some(t) = &self.replace else {
+            return Ok(None);
+        };
+        let new = t.replace_all(re, tx);
+        if self.verified(&new) {
+            return Ok(Some(new));
+        }
+        let failed = ReplaceVerifyFailed {
+            path: p.display().to_string(),
+            line: line + 1,
+            text: new,
+            verify: self.replace_verify.as_ref().map_or(String::new(), |v| v.as_str().to_string()),
+        };
+        if !self.replace_verify_skip {
+            return Err(failed.into());
+        }
+        log::log(log::Level::Warn, module_path!(), format_args!("{}，保留原来的行", failed));
+        Ok(Some(tx.to_string()))
+    }
+
+    /// 按 `match_buffer_size` 创建空的结果向量
+    fn match_buffer(&self) -> Vec<Record> {
+        Vec::with_capacity(self.match_buffer_size.unwrap_or(DEFAULT_MATCH_BUFFER_SIZE))
+    }
+
+    /// 匹配的行 `line` 或者它的上一行 `above` 中是否有 `ignore_marker` 标记
+    ///
+    /// 标记通常写在注释中（例如 `// pgrep-ignore`），这里只查找标记文本本身，不关心注释的语法
+    fn suppressed(&self, line: &str, above: &str) -> bool {
+        self.ignore_marker
+            .as_deref()
+            .is_some_and(|m| !m.is_empty() && (line.contains(m) || above.contains(m)))
+    }
+
+    /// 去掉一个文件的前 `skip_matches` 个匹配
+    ///
+    /// 记录的行号不变。被抑制的匹配默认不会输出，不算在跳过的个数中，
+    /// 但出现在跳过范围之内的也一起去掉，`--show-suppressed` 时不会单独留下它们
+    fn skip_leading(&self, mut res: Vec<Record>) -> Vec<Record> {
+        if self.skip_matches == 0 {
+            return res;
+        }
+        let mut seen = 0;
+        let cut = res
+            .iter()
+            .position(|r| {
+                if seen == self.skip_matches {
+                    return true;
+                }
+                seen += usize::from(!r.suppressed && !r.is_context);
+                false
+            })
+            .unwrap_or(res.len());
+        if self.before_context == 0 && self.after_context == 0 {
+            res.drain(..cut);
+        } else {
+            // 有上下文时跳过的匹配留作上下文行，之后的匹配的上文不会缺一行
+            for r in &mut res[..cut] {
+                r.is_context = true;
+            }
+        }
+        res
+    }
+
+    /// 按 `--line-prefix` / `--skip-prefix` / `--skip-empty-lines` 判断一行是否需要参与匹配
+    ///
+    /// 只做前缀比较和空白检查，比运行正则表达式便宜得多
+    pub fn line_allowed(&self, line: &str) -> bool {
+        (self.line_prefixes.is_empty() || self.line_prefixes.iter().any(|p| line.starts_with(p.as_str())))
+            && !self.skip_prefixes.iter().any(|p| line.starts_with(p.as_str()))
+            && !(self.skip_empty_lines && line.trim().is_empty())
+    }
+}
+
+/// 按字段匹配时参与匹配的字段，见 `GrepConfig::fields`
+///
+/// 每行按 `separator` 切分，字段从 1 开始编号；范围之内的每个字段单独和模式比较，
+/// 有一个字段匹配这一行就算匹配，`^`、`$` 是字段的开头和结尾。
+/// 只按分隔符切分，不处理 CSV 的引号，相邻的两个分隔符之间是一个空字段。
+///
+/// # 字段
+/// * `separator` - 分隔字段的字符
+/// * `first` - 第一个参与匹配的字段
+/// * `last` - 最后一个参与匹配的字段（包括在内），None 表示直到行尾
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fields {
+    pub separator: char,
+    pub first: usize,
+    pub last: Option<usize>,
+}
+
+/// 按路径选择模式的规则
+///
+/// 设置了 `GrepConfig::rules` 时，每个文件只用路径匹配的那些规则的模式搜索：
+/// * 一个文件匹配多条规则时所有匹配的规则都生效，一行只要匹配其中任意一条规则的模式就输出，
+///   而且只输出一次，所以规则的先后顺序不影响结果；匹配了哪些规则记在 `Record::rules` 中
+/// * 没有任何规则匹配的文件不会被搜索（见 `--debug-skip`）
+/// * 没有路径通配符的规则适用于所有文件
+/// * 路径通配符的语法见 glob 模块：不含 `/` 的通配符只和文件名比较，
+///   含有 `/` 的通配符和搜索时看到的整个路径（即命令行上给出的路径加上子路径）比较
+///
+/// # 字段
+/// * `name` - 规则的名字，用于日志
+/// * `paths` - 规则适用的路径，匹配其中任意一个即可；为空时适用于所有路径
+/// * `re` - 在这些路径中搜索的正则表达式
+#[derive(Debug)]
+pub struct Rule {
+    pub name: String,
+    pub paths: Vec<Glob>,
+    pub re: Regex,
+}
+
+impl Rule {
+    /// 判断规则是否适用于这个路径
+    pub fn applies_to(&self, p: &Path) -> bool {
+        self.paths.is_empty() || self.paths.iter().any(|g| g.is_match(p))
+    }
+}
+
+/// 替换之后的行不匹配 `GrepConfig::replace_verify`
+#[derive(Debug, Fail)]
+#[fail(display = "{}:{}: 替换结果 {:?} 不匹配 --replace-verify {}", path, line, text, verify)]
+pub struct ReplaceVerifyFailed {
+    pub path: String,
+    pub line: usize,
+    pub text: String,
+    pub verify: String,
+}
+
+/// 模糊匹配超时错误
+///
+/// 当模糊匹配耗时超过 `--fuzzy-cpu-limit` 指定的秒数时返回，
+/// 与普通的单文件错误不同，它会终止整个搜索
+#[derive(Debug, Fail)]
+#[fail(display = "模糊匹配超过了 {} 秒的时间限制", secs)]
+pub struct FuzzyTimeout {
+    secs: f64,
+}
+
+
+/// 符号链接指向的目标不存在
+#[derive(Debug, Fail)]
+#[fail(display = "悬空的符号链接: {}", path)]
+pub struct DanglingSymlink {
+    path: String,
+}
+
+/// 经过的符号链接超过了 `--max-symlink-depth`
+#[derive(Debug, Fail)]
+#[fail(display = "经过的符号链接超过了 {} 层，跳过: {}", max, path)]
+pub struct SymlinkDepthExceeded {
+    path: String,
+    max: usize,
+}
+
+/// 符号链接指向了自己的祖先目录，继续递归会陷入死循环
+#[derive(Debug, Fail)]
+#[fail(display = "符号链接造成了目录环，跳过: {}", path)]
+pub struct SymlinkLoop {
+    path: String,
+}
+
+
+/// 主动终止整个搜索
+///
+/// 回调函数返回它时 process_path 不再处理剩下的文件，和模糊匹配超时一样是致命错误
+#[derive(Debug, Fail)]
+#[fail(display = "停止搜索: {}", reason)]
+pub struct Halt {
+    pub reason: String,
+}
+
+/// 参数错误结构体
+///
+/// 使用 failure 库的 Fail derive 宏来实现自定义错误类型
+/// 这个结构体演示了如何创建结构化的错误信息
+///
+/// # 使用示例
+/// ```
+/// use pgrep::ArgErr;
+///
+/// let error = ArgErr { arg: "file" };
+/// println!("{}", error); // 输出: Argument not provided file
+/// ```
+///
+/// # 相关文档
+/// * failure 库文档: <https://docs.rs/failure/>
+/// * Fail trait 文档: <https://docs.rs/failure/latest/failure/trait.Fail.html>
+#[derive(Debug, Fail)]
+#[fail(display = "Argument not provided {}", arg)]
+pub struct ArgErr {
+    pub arg: &'static str,
+}
+
+// 注意：下面的代码被注释掉了，因为使用了 Fail derive 宏后，
+// Rust 会自动为我们实现 Fail trait 和 Display trait
+//
+// 如果不使用 derive 宏，我们需要手动实现这些 trait：
+
+// impl Fail for ArgErr {}
+
+/*
+// 手动实现 Display trait 以支持错误信息的格式化
+impl std::fmt::Display for ArgErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Argument Not provided: {}", self.arg)
+    }
+}
+*/
+
+
+/// 处理单个文件的函数
+///
+/// 读取指定文件的内容，逐行检查是否匹配给定的正则表达式，
+/// 并返回所有匹配的记录。
+///
+/// # 参数
+/// * `p` - 文件路径，实现了 AsRef<Path> trait，可以接受 &str, &Path, String 等类型
+/// * `re` - 编译好的正则表达式对象
+/// * `cfg` - 搜索配置，设置了 `fuzzy` 或 `phonetic` 时不使用正则表达式
+///
+/// # 返回值
+/// * `Ok(Vec<Record>)` - 包含所有匹配记录的向量
+/// * `Err(Error)` - 文件读取或处理过程中的错误
+///
+/// # 泛型约束
+/// `P: AsRef<Path>` - 允许函数接受多种路径类型作为参数
+///
+/// # 错误处理
+/// 使用 `?` 操作符自动处理 I/O 错误，将其转换为 failure::Error
+///
+/// # 相关文档
+/// * std::fs::read: <https://doc.rust-lang.org/std/fs/fn.read.html>
+/// * String::from_utf8: <https://doc.rust-lang.org/std/string/struct.String.html#method.from_utf8>
+/// * AsRef trait: <https://doc.rust-lang.org/std/convert/trait.AsRef.html>
+pub fn process_file<P: AsRef<Path>>(p: P, re: &Regex, cfg: &GrepConfig) -> Result<Vec<Record>, Error> {
+    // 读取文件的二进制内容
+    // `std::fs::read` 会将整个文件内容读入内存
+    let started = cfg.timer();
+    let bts = read_input(p.as_ref(), cfg)?;
+    cfg.record_time(p.as_ref(), Phase::Read, started);
+
+    Ok(cfg.skip_leading(process_bytes(p.as_ref(), bts, re, cfg)?))
+}
+
+/// 搜索内存中的一段内容
+///
+/// 和 process_file 使用相同的逐行匹配逻辑，内容已经在内存中时（例如来自数据库）
+/// 不需要先写到临时文件。使用默认的 GrepConfig，需要其他选项时请使用 process_bytes。
+///
+/// # 参数
+/// * `data` - 要搜索的内容
+/// * `re` - 编译好的正则表达式对象
+/// * `name` - 内容的名字，只用于日志和 `.gz` 之类按扩展名识别的格式
+///
+/// # 返回值
+/// 和 process_file 一样，不是合法 UTF-8 的内容没有任何匹配
+///
+/// # 示例
+/// ```
+/// use pgrep::search_bytes;
+/// use regex::Regex;
+///
+/// let re = Regex::new(r"\d+")?;
+/// let recs = search_bytes(b"no digits\norder 42\n", &re, "blob")?;
+/// assert_eq!(recs.len(), 1);
+/// assert_eq!((recs[0].line, recs[0].tx.as_str()), (1, "order 42"));
+///
+/// // 不是合法的 UTF-8
+/// assert!(search_bytes(b"order \xff 42\n", &re, "blob")?.is_empty());
+/// # Ok::<(), failure::Error>(())
+/// ```
+pub fn search_bytes(data: &[u8], re: &Regex, name: &str) -> Result<Vec<Record>, Error> {
+    process_bytes(Path::new(name), data.to_vec(), re, &GrepConfig::default())
+}
+
+/// 读取要搜索的内容：需要预处理的文件是预处理命令的输出，其他文件是文件本身
+fn read_input(p: &Path, cfg: &GrepConfig) -> Result<Vec<u8>, Error> {
+    match &cfg.pre {
+        Some(pre) if pre.applies_to(p) => pre.run(p),
+        _ => match cfg.max_line_bytes {
+            Some(max) => Ok(retry_transient(cfg.io_retry, || read_capped(p, max, cfg.newline))?),
+            None => Ok(read_retrying(p, cfg.io_retry)?),
+        },
+    }
+}
+
+/// 读取文件，超过 `max` 字节的行边读边截断
+///
+/// 不超过 `max` 的文件不可能有过长的行，直接读入；压缩文件和包要完整地读入才能解压，
+/// 按文件开头判断出是这两种格式时也直接读入
+fn read_capped(p: &Path, max: usize, newline: Newline) -> std::io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut f = std::fs::File::open(p)?;
+    if f.metadata()?.len() <= max as u64 {
+        let mut bts = Vec::new();
+        f.read_to_end(&mut bts)?;
+        return Ok(bts);
+    }
+    let mut head = Vec::with_capacity(512);
+    (&mut f).take(512).read_to_end(&mut head)?;
+    if compress::detect(p, &head).is_some() || archive::detect(p, &head).is_some() {
+        f.read_to_end(&mut head)?;
+        return Ok(head);
+    }
+    longline::read_capped(p, &head, f, max, newline)
+}
+
+/// 读取整个文件，遇到暂时性的 I/O 错误时重试
+///
+/// 网络文件系统或者容器中的读取偶尔会被 `EINTR` / `EAGAIN` 打断，
+/// 这两种错误（`Interrupted` 和 `WouldBlock`）会在等待一段时间后重新读取整个文件，
+/// 第 n 次重试前等待 50 × n 毫秒。其他错误和重试次数用完后的错误原样返回。
+///
+/// # 参数
+/// * `p` - 文件路径
+/// * `retries` - 最多重试几次，0 表示不重试
+///
+/// # 相关文档
+/// * std::io::ErrorKind: <https://doc.rust-lang.org/std/io/enum.ErrorKind.html>
+pub fn read_retrying(p: &Path, retries: u32) -> std::io::Result<Vec<u8>> {
+    retry_transient(retries, || std::fs::read(p))
+}
+
+/// 执行一个 I/O 操作，遇到暂时性的错误时按 read_retrying 的规则重试
+fn retry_transient<T>(retries: u32, mut op: impl FnMut() -> std::io::Result<T>) -> std::io::Result<T> {
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e)
+                if attempt < retries
+                    && matches!(e.kind(), std::io::ErrorKind::Interrupted | std::io::ErrorKind::WouldBlock) =>
+            {
+                attempt += 1;
+                debug!("暂时性的 I/O 错误 ({})，第 {} 次重试", e, attempt);
+                std::thread::sleep(Duration::from_millis(50 * attempt as u64));
+            }
+            r => return r,
+        }
+    }
+}
+
+/// 处理标准输入
+///
+/// 读取标准输入的全部内容后按与普通文件相同的方式匹配
+///
+/// # 相关文档
+/// * std::io::Read::read_to_end: <https://doc.rust-lang.org/std/io/trait.Read.html#method.read_to_end>
+pub fn process_stdin(re: &Regex, cfg: &GrepConfig) -> Result<Vec<Record>, Error> {
+    use std::io::Read;
+
+    let started = cfg.timer();
+    let mut bts = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut bts)?;
+    cfg.record_time(Path::new("-"), Phase::Read, started);
+    Ok(cfg.skip_leading(process_bytes(Path::new("-"), bts, re, cfg)?))
+}
+
+/// 逐行匹配已经读入内存的内容
+///
+/// process_file 和 process_stdin 共用的匹配逻辑
+///
+/// # 参数
+/// * `p` - 内容来自的路径，只用于报告跳过的原因
+/// * `bts` - 文件的原始字节，不是合法 UTF-8 时不产生任何匹配
+/// * `re` - 编译好的正则表达式对象
+/// * `cfg` - 搜索配置
+pub fn process_bytes(p: &Path, bts: Vec<u8>, re: &Regex, cfg: &GrepConfig) -> Result<Vec<Record>, Error> {
+    // 压缩文件先解压，没有指定 -z 时跳过
+    let bts = match compress::detect(p, &bts) {
+        Some(format) if !cfg.search_zip => {
+            skip(cfg, p, &zip_skip_reason(format));
+            return Ok(Vec::new());
+        }
+        Some(format) => {
+            let started = cfg.timer();
+            let bts = compress::decompress(p, format, &bts)?;
+            cfg.record_time(p, Phase::Read, started);
+            bts
+        }
+        None => bts,
+    };
+    search_lines(p, bts, re, cfg)
+}
+
+/// 没有指定 -z 时跳过压缩文件的原因
+fn zip_skip_reason(format: compress::Format) -> String {
+    format!("{} 压缩文件，使用 -z 搜索其中的内容", format)
+}
+
+/// 逐行匹配已经解压的内容
+fn search_lines(p: &Path, bts: Vec<u8>, re: &Regex, cfg: &GrepConfig) -> Result<Vec<Record>, Error> {
+    // --debug-timing: 从这里到开始匹配之前都算作读取和解码
+    let decode_started = cfg.timer();
+    if let Some(t) = &cfg.timing {
+        t.count(p, bts.len(), 0);
+    }
+
+    // 用于存储匹配结果的向量
+    let mut res = cfg.match_buffer();
+
+    // 替换模板中的 `{{n}}` 从这里开始算作这个文件的编号
+    if let Some(t) = &cfg.replace {
+        t.begin_file();
+    }
+
+    // 标准输入、解压出来的内容等在这里截断过长的行，普通文件读取时已经截断过了
+    let bts = match cfg.max_line_bytes {
+        Some(max) => longline::cap_lines(p, bts, max, cfg.newline),
+        None => bts,
+    };
+
+    if let Some(why) = binary_reason(&bts, cfg) {
+        skip(cfg, p, &why);
+        return Ok(res);
+    }
+
+    // 使用规则时，只用路径匹配的规则的模式搜索
+    let rule_res: Vec<(usize, &Regex)> = cfg
+        .rules
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.applies_to(p))
+        .map(|(i, r)| (i, &r.re))
+        .collect();
+    if !cfg.rules.is_empty() && rule_res.is_empty() {
+        skip(cfg, p, "没有适用于这个路径的规则");
+        return Ok(res);
+    }
+
+    // 尝试将字节数组转换为 UTF-8 字符串
+    // 使用 match 来处理可能的编码错误
+    let ss = if cfg.encoding_chain.is_empty() {
+        match String::from_utf8(bts) {
+            Ok(ss) => ss,
+            Err(e) => {
+                let at = e.utf8_error().valid_up_to();
+                skip(cfg, p, &format!("不是合法的 UTF-8（第 {} 字节）", at));
+                return Ok(res);
+            }
+        }
+    } else {
+        // --encoding-chain 依次尝试每个编码，都失败时有损解码，文件不会被跳过
+        match encoding::decode_chain(&bts, &cfg.encoding_chain) {
+            (Some(enc), ss) => {
+                info!("{} 使用 {} 编码", p.display(), enc);
+                ss
+            }
+            (None, ss) => {
+                info!("{} 无法用编码链中的任何编码解码，按 UTF-8 有损解码", p.display());
+                ss
+            }
+        }
+    };
+
+    // 混用的行尾在统一之前检查，统一之后就看不出来了
+    if cfg.warn_mixed_endings {
+        let e = newline::Endings::count(&ss);
+        if e.is_mixed() {
+            log::log(
+                log::Level::Warn,
+                module_path!(),
+                format_args!(
+                    "{} 混用了不同的行尾（{} 个 LF，{} 个 CRLF，{} 个单独的 CR），以 $ 结尾的模式在一部分行上可能匹配不到",
+                    p.display(),
+                    e.lf,
+                    e.crlf,
+                    e.cr
+                ),
+            );
+        }
+    }
+
+    // --crlf-is-lf 在解码之后统一行尾，UTF-16 的 `\r` 在字节层面是两个字节
+    let ss = if cfg.crlf_is_lf {
+        normalize_line_endings(ss)
+    } else {
+        ss
+    };
+
+    cfg.record_time(p, Phase::Read, decode_started);
+    let match_started = cfg.timer();
+    if let Some(t) = &cfg.timing {
+        t.count(p, 0, cfg.newline.lines(&ss).count());
+    }
+
+    // 匹配稀疏的大文件中绝大多数行都不匹配，能在整个内容上查找时就不必逐行调用正则表达式
+    if let Some(res) = scan_whole_text(&ss, re, cfg) {
+        let res = cfg.context_filter(&ss, res);
+        cfg.record_time(p, Phase::Match, match_started);
+        return Ok(cfg.with_context(&ss, res));
+    }
+
+    // --profile-regex: 正则表达式匹配的总耗时、匹配过的行数和每一行的耗时
+    let profile = (cfg.profile_regex || cfg.profile_per_line) && cfg.timing.is_some();
+    let mut regex_time = Duration::ZERO;
+    let mut regex_lines = 0;
+    let mut per_line = Vec::new();
+
+    // 上一行的内容，用于查找写在匹配上方的抑制标记
+    let mut prev = "";
+
+    // 逐行处理文件内容
+    // enumerate() 为每一行提供行号（从0开始）
+    for (i, l) in cfg.newline.lines(&ss).enumerate() {
+        let above = std::mem::replace(&mut prev, l);
+        // 先用便宜的前缀比较排除不关心的行
+        if !cfg.line_allowed(l) {
+            continue;
+        }
+
+        // 模糊匹配模式下找到的最接近的子串
+        let mut fuzzy = None;
+        // 词表模式下命中的词
+        let mut word = None;
+        // 使用规则时匹配了这一行的规则
+        let mut hits = Vec::new();
+        // 匹配的部分：整行，或者按字段匹配时第一个匹配的字段
+        let mut target = l;
+
+        // 按字段匹配时依次检查范围之内的每个字段，字段数不够的行可能一个字段也没有
+        let mut matched = cfg.all_lines;
+        if !matched {
+            for t in cfg.match_targets(l) {
+                let m = if let Some(fz) = &cfg.fuzzy {
+                    // 模糊匹配模式：计算编辑距离而不是运行正则表达式
+                    if let Some(limit) = fz.limit
+                        && fz.started.elapsed() > limit
+                    {
+                        return Err(FuzzyTimeout {
+                            secs: limit.as_secs_f64(),
+                        }
+                        .into());
+                    }
+                    fuzzy = fuzzy_find(t, &fz.pattern, fz.max_edits);
+                    fuzzy.is_some()
+                } else if let Some(ph) = &cfg.phonetic {
+                    // 语音匹配模式：比较单词的语音编码
+                    ph.is_match(t)
+                } else if let Some(wl) = &cfg.words {
+                    // 词表模式：用 Aho-Corasick 自动机查找
+                    word = wl.find(t).map(str::to_string);
+                    word.is_some()
+                } else {
+                    // 检查当前行是否匹配正则表达式
+                    let started = profile.then(Instant::now);
+                    let found = if let Some(lit) = &cfg.literal {
+                        lit.is_match(t)
+                    } else if cfg.rules.is_empty() {
+                        re.is_match(t)
+                    } else {
+                        hits = rule_res.iter().filter(|(_, r)| r.is_match(t)).map(|&(i, _)| i).collect();
+                        !hits.is_empty()
+                    };
+                    if let Some(started) = started {
+                        let d = started.elapsed();
+                        regex_time += d;
+                        regex_lines += 1;
+                        if cfg.profile_per_line {
+                            per_line.push((i, d));
+                        }
+                    }
+                    found
+                };
+                if m {
+                    matched = true;
+                    target = t;
+                    break;
+                }
+            }
+        }
+
+        if matched {
+            let tx = if cfg.only_field { target } else { l };
+            // 如果匹配，创建一个新的 Record 并添加到结果中
+            res.push(Record {
+                line: i,
+                tx: tx.to_string(),
+                fuzzy,
+                replaced: cfg.replacement(p, i, re, tx)?,
+                word,
+                suppressed: cfg.suppressed(l, above),
+                rules: hits,
+                is_context: false,
+            })
+        }
+    }
+
+    let res = cfg.context_filter(&ss, res);
+    cfg.record_time(p, Phase::Match, match_started);
+
+    if profile && let Some(t) = &cfg.timing {
+        t.add_regex(p, regex_time, regex_lines, res.len(), per_line);
+    }
+
+    // 返回匹配结果
+    Ok(cfg.with_context(&ss, res))
+}
+
+
+/// 判断内容是不是二进制文件，是的话返回跳过的原因
+///
+/// 只检查前 `binary_scan_bytes` 个字节：检查得越多越不容易漏掉在文本开头之后才出现 NUL 的二进制文件，
+/// 但每个文件都要多扫描这么多字节；检查得少则更快，代价是可能把二进制文件当作文本搜索。
+/// 有一个 NUL 就算二进制文件的规则很快，但偶尔夹带一个 NUL 的文本文件也会被跳过；
+/// `null_ratio` 按 NUL 和控制字符（制表符、换行、回车、换页和 ESC 除外）的比例判断，对这类文件更宽容。
+/// 编码链中有 UTF-16 时不检查，UTF-16 文本中本来就有大量的 NUL 字节。
+fn binary_reason(bts: &[u8], cfg: &GrepConfig) -> Option<String> {
+    if cfg.binary_scan_bytes == 0
+        || cfg
+            .encoding_chain
+            .iter()
+            .any(|e| matches!(e, Encoding::Utf16Le | Encoding::Utf16Be))
+    {
+        return None;
+    }
+    let head = &bts[..bts.len().min(cfg.binary_scan_bytes)];
+    match cfg.null_ratio {
+        None => memchr::memchr(0, head).map(|i| format!("看起来是二进制文件（第 {} 字节是 NUL）", i)),
+        Some(max) => {
+            let control = head
+                .iter()
+                .filter(|&&b| b == 0x7f || (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b)))
+                .count();
+            let ratio = control as f64 / head.len().max(1) as f64;
+            (ratio > max).then(|| {
+                format!(
+                    "看起来是二进制文件（前 {} 字节中有 {:.1}% 是 NUL 或控制字符）",
+                    head.len(),
+                    ratio * 100.0
+                )
+            })
+        }
+    }
+}
+
+/// 在整个内容上查找候选匹配，只对候选所在的行做逐行确认
+///
+/// 多行模式（`(?m)`）下 `^`、`$` 和 `\b` 在行边界上的行为和逐行匹配时相同，
+/// 所以每个逐行匹配的行中都一定有一个候选匹配的起点；候选匹配可能跨越行尾，
+/// 因此还要用原来的正则表达式确认候选所在的整行，结果和逐行匹配完全一致。
+/// 行号用 memchr 数出候选之前的换行符得到，游标只向前移动，整个内容只数一遍。
+/// 设置了 `literal` 时候选就是子串出现的位置，子串不跨越行尾，不需要再确认。
+///
+/// # 返回值
+/// 不适用时返回 None，由调用者逐行匹配：
+/// * 使用了模糊匹配、语音匹配、词表、规则、行过滤、按字段匹配、替换结果检查或者耗时统计
+/// * 内容中有 `\r`：`str::lines` 会去掉行尾的 `\r`，多行模式的 `$` 却不把它当作行尾
+/// * `newline` 是 `cr` 或 `crlf`：`\n` 不是行尾，多行模式的 `^`、`$` 却把它当作行尾
+/// * 模式中有只在整个文本开头结尾成立的 `\A` / `\z`，或者用 `(?-m)` 这样的标志组关闭了多行模式
+/// * 按字面查找的子串中有换行符
+fn scan_whole_text(ss: &str, re: &Regex, cfg: &GrepConfig) -> Option<Vec<Record>> {
+    let per_line_only = cfg.fuzzy.is_some()
+        || cfg.phonetic.is_some()
+        || cfg.words.is_some()
+        || !cfg.rules.is_empty()
+        || !cfg.line_prefixes.is_empty()
+        || !cfg.skip_prefixes.is_empty()
+        || cfg.skip_empty_lines
+        || cfg.profile_regex
+        || cfg.profile_per_line
+        || cfg.all_lines
+        || cfg.fields.is_some()
+        || cfg.replace_verify.is_some()
+        || matches!(cfg.newline, Newline::Cr | Newline::Crlf);
+    if per_line_only || memchr::memchr(b'\r', ss.as_bytes()).is_some() {
+        return None;
+    }
+    // 按字面查找时直接在整个内容上查找子串，子串中有换行符时逐行匹配
+    let automaton = cfg.automaton.as_ref().filter(|a| a.pattern() == re.as_str());
+    let multi = match (&cfg.literal, automaton) {
+        (Some(lit), _) if lit.literal().contains('\n') => return None,
+        (Some(_), _) | (None, Some(_)) => None,
+        (None, None) => Some(multiline_regex(re, &cfg.multiline)?),
+    };
+    let next = |pos: usize| match (&cfg.literal, automaton, &multi) {
+        (Some(lit), _, _) => lit.find_at(ss, pos).map(|m| m.start),
+        (None, Some(a), _) => a.find_at(ss, pos),
+        (None, None, Some(multi)) => multi.find_at(ss, pos).map(|m| m.start()),
+        (None, None, None) => None,
+    };
+
+    let bts = ss.as_bytes();
+    let mut res = cfg.match_buffer();
+    // 已经数过换行符的位置和它之前的行数
+    let (mut counted, mut line) = (0, 0);
+    let mut pos = 0;
+    while let Some(at) = next(pos) {
+        let start = memchr::memrchr(b'\n', &bts[..at]).map_or(0, |i| i + 1);
+        // 以换行符结尾的内容，最后的空字符串不算一行，和 `str::lines` 一致
+        if start == bts.len() {
+            break;
+        }
+        let end = memchr::memchr(b'\n', &bts[at..]).map_or(bts.len(), |i| at + i);
+        line += memchr::memchr_iter(b'\n', &bts[counted..start]).count();
+        counted = start;
+
+        let l = &ss[start..end];
+        if cfg.literal.is_some() || re.is_match(l) {
+            let above = match start.checked_sub(1) {
+                Some(nl) => &ss[memchr::memrchr(b'\n', &bts[..nl]).map_or(0, |i| i + 1)..nl],
+                None => "",
+            };
+            res.push(Record {
+                line,
+                tx: l.to_string(),
+                fuzzy: None,
+                replaced: cfg.replace.as_ref().map(|t| t.replace_all(re, l)),
+                word: None,
+                suppressed: cfg.suppressed(l, above),
+                rules: Vec::new(),
+                is_context: false,
+            });
+        }
+        if end == bts.len() {
+            break;
+        }
+        pos = end + 1;
+    }
+    Some(res)
+}
+
+/// GrepConfig 中缓存的多行模式正则表达式，见 `GrepConfig::multiline`
+///
+/// 第一次在整个内容上查找时编译，之后同一个配置搜索的所有文件（包括其他线程中的）共用；
+/// 每个配置各有一份，`--serve` 中同时进行的使用不同模式的搜索互不影响。
+/// 同一个配置换了模式时（例如 `--tui` 中修改了模式）不使用缓存，每次重新编译。
+#[derive(Debug, Default)]
+pub struct MultilineCache(OnceLock<(String, Option<Regex>)>);
+
+/// 返回模式的多行模式版本，不能安全地改成多行模式时返回 None
+fn multiline_regex(re: &Regex, cache: &MultilineCache) -> Option<Regex> {
+    let pattern = re.as_str();
+    let (p, multi) = cache.0.get_or_init(|| (pattern.to_string(), compile_multiline(pattern)));
+    if p == pattern {
+        multi.clone()
+    } else {
+        compile_multiline(pattern)
+    }
+}
+
+fn compile_multiline(pattern: &str) -> Option<Regex> {
+    Regex::new(&multiline_source(pattern)?).ok()
+}
+
+/// 模式的多行模式版本的文本，不能安全地改成多行模式时返回 None
+fn multiline_source(pattern: &str) -> Option<String> {
+    if pattern.contains("\\A") || pattern.contains("\\z") || disables_multiline(pattern) {
+        None
+    } else {
+        Some(format!("(?m:{})", pattern))
+    }
+}
+
+/// 模式中是否有关闭多行模式的标志组，例如 `(?-m)`、`(?i-m:...)`、`(?s-im)`
+///
+/// 只看真正的标志组：转义的 `\(`、普通文本中的 `-m` 和 `(?P<name>...)` 这样的分组都不算
+fn disables_multiline(pattern: &str) -> bool {
+    let b = pattern.as_bytes();
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b'\\' {
+            i += 2;
+            continue;
+        }
+        if b[i..].starts_with(b"(?") {
+            let flags = &b[i + 2..];
+            let n = flags.iter().take_while(|c| c.is_ascii_alphabetic() || **c == b'-').count();
+            if matches!(flags.get(n), Some(b':' | b')'))
+                && let Some(minus) = flags[..n].iter().position(|&c| c == b'-')
+                && flags[minus..n].contains(&b'm')
+            {
+                return true;
+            }
+        }
+        i += 1;
+    }
+    false
+}
+
+/// 把 `\r\n` 和单独的 `\r` 替换为 `\n`
+fn normalize_line_endings(s: String) -> String {
+    // 没有 `\r` 的文件（绝大多数）直接原样返回，省掉一次复制
+    if !s.contains('\r') {
+        return s;
+    }
+    s.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// 找出一条记录中匹配到的文本及其所在的列
+///
+/// 词表和模糊匹配模式下使用命中的词或子串，其他模式下使用正则表达式的第一个匹配。
+/// 语音匹配没有确切的匹配位置，此时返回整行。
+///
+/// # 返回值
+/// 从 1 开始、按字符计算的列号，以及匹配到的文本
+pub fn record_match<'a>(r: &'a Record, re: &Regex) -> (usize, &'a str) {
+    let span = match (&r.word, &r.fuzzy) {
+        (Some(w), _) => r.tx.find(w.as_str()).map(|s| (s, s + w.len())),
+        (_, Some(fz)) => r.tx.find(fz.tx.as_str()).map(|s| (s, s + fz.tx.len())),
+        _ => re.find(&r.tx).map(|m| (m.start(), m.end())),
+    };
+    match span {
+        Some((s, e)) => (r.tx[..s].chars().count() + 1, &r.tx[s..e]),
+        None => (1, &r.tx),
+    }
+}
+
+/// 一行中每个匹配的字节位置 (开始, 结束)
+///
+/// `overlapping` 为 false 时和 `Regex::find_iter` 相同，匹配互不重叠，下一次查找从上一个匹配的结尾开始。
+/// 为 true 时下一次查找从上一个匹配开头的下一个字符开始，因此可以找到和前一个匹配重叠的匹配；
+/// 查找仍然看得到之前的文本，`\b`、`^` 之类的断言和整行查找时的结果一致。
+/// 每次至少前进一个字符，空的匹配不会造成死循环。
+///
+/// # 复杂度
+/// 重叠查找对每个匹配的开头重新运行一次正则表达式，每次都可能扫描到行尾。
+/// 匹配很多的行（例如 `a+` 用在一长串 `a` 上）的开销是 O(n²)，而不是 `find_iter` 的 O(n)。
+///
+/// # 示例
+/// ```
+/// use pgrep::match_spans;
+/// use regex::Regex;
+///
+/// let re = Regex::new("aba").unwrap();
+/// assert_eq!(match_spans(&re, "ababa", false), [(0, 3)]);
+/// assert_eq!(match_spans(&re, "ababa", true), [(0, 3), (2, 5)]);
+/// // --count-mode matches 按匹配的个数计数
+/// assert_eq!(match_spans(&re, "ababa aba", true).len(), 3);
+/// ```
+pub fn match_spans(re: &Regex, text: &str, overlapping: bool) -> Vec<(usize, usize)> {
+    if !overlapping {
+        return re.find_iter(text).map(|m| (m.start(), m.end())).collect();
+    }
+    overlapping_scan(text, |at| re.find_at(text, at).map(|m| ((m.start(), m.end()), m.start())))
+}
+
+/// 一行中每个匹配的捕获组，`overlapping` 的含义和 match_spans 相同
+pub fn match_captures<'t>(re: &Regex, text: &'t str, overlapping: bool) -> Vec<regex::Captures<'t>> {
+    if !overlapping {
+        return re.captures_iter(text).collect();
+    }
+    overlapping_scan(text, |at| {
+        re.captures_at(text, at).map(|caps| {
+            let start = caps.get(0).map_or(at, |m| m.start());
+            (caps, start)
+        })
+    })
+}
+
+/// 重叠查找：`find(at)` 返回从 `at` 开始的第一个匹配及其开头的位置
+fn overlapping_scan<T>(text: &str, mut find: impl FnMut(usize) -> Option<(T, usize)>) -> Vec<T> {
+    let mut found = Vec::new();
+    let mut at = 0;
+    while let Some((m, start)) = find(at) {
+        found.push(m);
+        // 从匹配开头的下一个字符继续，在行尾的空匹配之后结束
+        match text[start..].chars().next() {
+            Some(c) => at = start + c.len_utf8(),
+            None => break,
+        }
+    }
+    found
+}
+
+/// 在一行文本中查找与模式最接近的子串
+///
+/// 枚举行中长度在 `[m - k, m + k]` 之间的所有子串（N-gram，m 为模式长度，
+/// k 为允许的最大编辑距离），用 Levenshtein 距离与模式比较，返回距离最小的那个。
+/// 长度差超过 k 的子串编辑距离必然大于 k，因此不需要考虑。
+///
+/// # 参数
+/// * `line` - 要搜索的一行文本
+/// * `pat` - 按字面文本处理的模式
+/// * `max_edits` - 允许的最大编辑距离
+///
+/// # 返回值
+/// * `Some(FuzzyHit)` - 存在距离 ≤ max_edits 的子串时，返回距离最小（相同时取最靠前）的一个
+/// * `None` - 没有足够接近的子串
+///
+/// # 复杂度
+/// 子串按字符（而不是字节）切分，不会截断多字节字符。
+/// 每行的开销约为 O(n × k × m²)，远高于正则匹配。
+///
+/// # 相关文档
+/// * strsim::levenshtein: <https://docs.rs/strsim/latest/strsim/fn.levenshtein.html>
+fn fuzzy_find(line: &str, pat: &str, max_edits: usize) -> Option<FuzzyHit> {
+    let m = pat.chars().count();
+
+    // 记录每个字符的字节起始位置，末尾再补上行长度，方便按字符切分子串
+    let mut bounds: Vec<usize> = line.char_indices().map(|(b, _)| b).collect();
+    bounds.push(line.len());
+    let n = bounds.len() - 1;
+
+    let min_len = m.saturating_sub(max_edits).max(1);
+    let max_len = m + max_edits;
+
+    let mut best: Option<FuzzyHit> = None;
+    for start in 0..n {
+        for len in min_len..=max_len {
+            if start + len > n {
+                break;
+            }
+            let sub = &line[bounds[start]..bounds[start + len]];
+            let dist = strsim::levenshtein(sub, pat);
+            if dist > max_edits {
+                continue;
+            }
+            if best.as_ref().is_none_or(|b| dist < b.dist) {
+                best = Some(FuzzyHit {
+                    tx: sub.to_string(),
+                    dist,
+                });
+                // 完全匹配已经是最优结果
+                if dist == 0 {
+                    return best;
+                }
+            }
+        }
+    }
+    best
+}
+
+/// 递归处理路径的函数
+///
+/// 这个函数可以处理文件和目录。对于文件，直接调用 process_file 进行搜索；
+/// 对于目录，递归遍历其中的所有文件并进行搜索。
+///
+/// # 参数
+/// * `p` - 要处理的路径（文件或目录）
+/// * `re` - 编译好的正则表达式对象
+/// * `cfg` - 搜索配置，原样传递给 process_file
+/// * `ctx` - 遍历状态，记录经过的符号链接层数和祖先目录
+/// * `ff` - 文件处理完成时的回调函数，接收路径和匹配结果，返回错误时终止整个搜索
+/// * `df` - 目录处理完成时的回调函数，接收目录路径，用于报告进度
+/// * `ef` - 错误处理回调函数，接收发生的错误
+///
+/// # 泛型参数和约束
+/// * `P: AsRef<Path>` - 路径类型，支持多种路径输入
+/// * `FF: Fn(&Path, Vec<Record>) -> Result<(), Error>` - 文件处理回调函数类型
+/// * `DF: Fn(&Path)` - 目录处理完成回调函数类型
+/// * `EF: Fn(Error)` - 错误处理回调函数类型
+///
+/// # 函数式编程特性
+/// 这个函数展示了 Rust 中函数式编程的特性：
+/// - 使用闭包作为回调函数
+/// - 泛型约束确保类型安全
+/// - 函数式风格的错误处理
+///
+/// # 递归处理
+/// 目录处理是递归的，会遍历所有子目录和文件
+///
+/// # 相关文档
+/// * std::fs::metadata: <https://doc.rust-lang.org/std/fs/fn.metadata.html>
+/// * std::fs::read_dir: <https://doc.rust-lang.org/std/fs/fn.read_dir.html>
+/// * 闭包文档: <https://doc.rust-lang.org/rust-by-example/fn/closures.html>
+pub fn process_path<P, FF, DF, EF>(
+    p: P,
+    re: &Regex,
+    cfg: &GrepConfig,
+    ctx: &WalkContext,
+    ff: &FF,
+    df: &DF,
+    ef: &EF,
+) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+    FF: Fn(&Path, Vec<Record>) -> Result<(), Error>,
+    DF: Fn(&Path),
+    EF: Fn(Error),
+{
+    // 将输入路径转换为 Path 引用
+    let p = p.as_ref();
+
+    // `-` 表示标准输入
+    if p == Path::new("-") {
+        let v = process_stdin(re, cfg)?;
+        let started = cfg.timer();
+        let r = ff(p, v);
+        cfg.record_time(p, Phase::Output, started);
+        return r;
+    }
+
+    // 获取路径本身的元数据信息（文件类型、大小、权限等）
+    // symlink_metadata 不跟随符号链接，这样才能知道路径本身是不是符号链接
+    let lmd = p.symlink_metadata()?;
+    let is_link = lmd.file_type().is_symlink();
+
+    // 按文件类型过滤：--type-filter regular 跳过所有符号链接
+    if is_link && cfg.type_filter == TypeFilter::Regular {
+        skip(cfg, p, "--type-filter regular 不搜索符号链接");
+        return Ok(());
+    }
+
+    // 符号链接链上的每一跳都算一层（`l3 -> l2 -> l1 -> real` 是三层），超过上限时跳过
+    let symlink_depth = ctx.symlink_depth + if is_link { link_hops(p, cfg.max_symlink_depth) } else { 0 };
+    if symlink_depth > cfg.max_symlink_depth {
+        return Err(SymlinkDepthExceeded {
+            path: p.display().to_string(),
+            max: cfg.max_symlink_depth,
+        }
+        .into());
+    }
+
+    // 对于符号链接，继续获取它指向的目标的元数据
+    let md = if is_link {
+        match p.metadata() {
+            Ok(md) => md,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(DanglingSymlink {
+                    path: p.display().to_string(),
+                }
+                .into());
+            }
+            Err(e) => return Err(e.into()),
+        }
+    } else {
+        lmd
+    };
+
+    // 获取文件类型信息
+    let ft = md.file_type();
+
+    if log::enabled(log::Level::Debug, module_path!()) {
+        let kind = match (is_link, ft.is_file(), ft.is_dir()) {
+            (false, true, _) => "普通文件",
+            (false, _, true) => "目录",
+            (true, true, _) => "符号链接 -> 文件",
+            (true, _, true) => "符号链接 -> 目录",
+            (true, _, _) => "符号链接 -> 其他",
+            _ => "其他",
+        };
+        debug!("文件类型: {} {}", p.display(), kind);
+    }
+
+    // --type-filter symlink 只搜索符号链接指向的文件，不跟随指向目录的符号链接
+    if cfg.type_filter == TypeFilter::Symlink {
+        if !is_link && ft.is_file() {
+            skip(cfg, p, "--type-filter symlink 不搜索普通文件");
+            return Ok(());
+        }
+        if is_link && !ft.is_file() {
+            skip(cfg, p, "--type-filter symlink 不跟随指向目录的符号链接");
+            return Ok(());
+        }
+    }
+
+    // --type / --type-not 先于规则检查，排除的类型总是优先
+    if ft.is_file()
+        && let Some(ts) = &cfg.type_select
+        && !ts.selects(p)
+    {
+        skip(cfg, p, "--type / --type-not 没有选中这种文件");
+        return Ok(());
+    }
+
+    // 使用规则时，没有规则适用的文件不必读取
+    if ft.is_file() && !cfg.search_archives && !cfg.rules.is_empty() && !cfg.rules.iter().any(|r| r.applies_to(p)) {
+        skip(cfg, p, "没有适用于这个路径的规则");
+        return Ok(());
+    }
+
+    // 处理文件：如果是文件，直接搜索其内容
+    if ft.is_file() && cfg.search_archives {
+        // --archives 时文件可能是包，由 process_content 决定如何搜索
+        let started = Instant::now();
+        let read_started = cfg.timer();
+        let bts = read_input(p, cfg)?;
+        cfg.record_time(p, Phase::Read, read_started);
+        process_content(p, bts, re, cfg, 0, ff, ef)?;
+        debug!("搜索 {}: 耗时 {:?}", p.display(), started.elapsed());
+    } else if ft.is_file() {
+        // 调用 process_file 处理文件内容
+        let started = Instant::now();
+        let dt = process_file(p, re, cfg)?;
+        debug!("搜索 {}: {} 个匹配，耗时 {:?}", p.display(), dt.len(), started.elapsed());
+
+        // 调用文件处理回调函数，传递路径和匹配结果
+        let started = cfg.timer();
+        let r = ff(p, dt);
+        cfg.record_time(p, Phase::Output, started);
+        r?;
+    }
+
+    // 既不是文件也不是目录（设备文件、套接字、管道等）
+    if !ft.is_file() && !ft.is_dir() {
+        skip(cfg, p, "既不是普通文件也不是目录");
+    }
+
+    // 从检查点恢复时，已经完成的目录直接跳过
+    if ft.is_dir() && cfg.skip_dirs.contains(p) {
+        skip(cfg, p, "检查点中记录为已完成");
+        return Ok(());
+    }
+
+    // 处理目录：如果是目录，递归遍历其中的所有条目
+    if ft.is_dir() {
+        // 记录当前目录，子目录中的符号链接一旦指回这里就是环
+        let mut child = WalkContext {
+            symlink_depth,
+            ancestors: ctx.ancestors.clone(),
+        };
+        if let Some(id) = file_id(&md) {
+            if ctx.ancestors.contains(&id) {
+                return Err(SymlinkLoop {
+                    path: p.display().to_string(),
+                }
+                .into());
+            }
+            child.ancestors.push(id);
+        }
+
+        // 读取目录内容，返回一个迭代器
+        let dd = std::fs::read_dir(p)?;
+        // sorted_walk 时先读出所有条目按名字排序，读取条目的错误留到遍历时再报告
+        let dd: Box<dyn Iterator<Item = std::io::Result<std::fs::DirEntry>>> = if cfg.sorted_walk {
+            let mut all: Vec<_> = dd.collect();
+            all.sort_by(|a, b| match (a, b) {
+                (Ok(a), Ok(b)) => a.file_name().cmp(&b.file_name()),
+                (a, b) => a.is_ok().cmp(&b.is_ok()),
+            });
+            Box::new(all.into_iter())
+        } else {
+            Box::new(dd)
+        };
+
+        // 遍历目录中的每个条目
+        for d in dd {
+            // 获取目录条目（可能失败，使用 ? 操作符处理）
+            let entry = d?;
+
+            // 递归调用 process_path 处理子路径
+            // 如果递归调用失败，调用错误处理回调函数而不是直接返回错误
+            // 致命错误是例外：继续处理剩下的文件已经没有意义，直接向上返回
+            if let Err(e) = process_path(entry.path(), re, cfg, &child, ff, df, ef) {
+                if is_fatal(&e) {
+                    return Err(e);
+                }
+                ef(e);
+            }
+        }
+
+        // 目录中的所有条目都已处理完毕（个别条目出错也算完成）
+        df(p);
+    }
+
+    // 返回成功
+    Ok(())
+}
+
+/// 搜索一个文件或包成员的内容，内容是 tar / zip 包时递归搜索其中的每个成员
+///
+/// 只在 `--archives` 时使用。每个成员以 `包的路径!成员名` 的虚拟路径交给 `ff`，
+/// 单个成员的错误交给 `ef`，不影响同一个包中的其他成员。
+///
+/// # 参数
+/// * `p` - 文件路径或成员的虚拟路径
+/// * `bts` - 文件或成员的原始内容
+/// * `depth` - 已经打开了几层包，磁盘上的文件为 0
+/// * `ff` / `ef` - 与 process_path 的回调相同
+fn process_content<FF, EF>(
+    p: &Path,
+    bts: Vec<u8>,
+    re: &Regex,
+    cfg: &GrepConfig,
+    depth: usize,
+    ff: &FF,
+    ef: &EF,
+) -> Result<(), Error>
+where
+    FF: Fn(&Path, Vec<Record>) -> Result<(), Error>,
+    EF: Fn(Error),
+{
+    // 压缩的 tar 包（.tar.gz）要先解压才能看出是不是包，所以没有 -z 时也要解压；
+    // 这时解压失败或者解压出来不是包的文件和不使用 --archives 时一样跳过，
+    // 只有文件名是 `.tar.gz`、`.tgz` 的文件解压失败时报告错误，它多半是一个损坏的包
+    let (bts, compressed) = match compress::detect(p, &bts) {
+        Some(format) => match compress::decompress(p, format, &bts) {
+            Ok(bts) => (bts, Some(format)),
+            Err(_) if !cfg.search_zip && !archive::compressed_tar(p) => {
+                skip(cfg, p, &zip_skip_reason(format));
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        },
+        None => (bts, None),
+    };
+    // 解压后按去掉压缩扩展名的文件名判断，`a.tar.gz` 看作 `a.tar`
+    let name = match compressed {
+        Some(_) => p.with_extension(""),
+        None => p.to_path_buf(),
+    };
+
+    let Some(kind) = archive::detect(&name, &bts) else {
+        return match compressed {
+            Some(format) if !cfg.search_zip => {
+                skip(cfg, p, &zip_skip_reason(format));
+                Ok(())
+            }
+            _ => {
+                let v = cfg.skip_leading(search_lines(p, bts, re, cfg)?);
+                let started = cfg.timer();
+                let r = ff(p, v);
+                cfg.record_time(p, Phase::Output, started);
+                r
+            }
+        };
+    };
+    if depth >= cfg.max_archive_depth {
+        skip(
+            cfg,
+            p,
+            &format!("嵌套的 {} 包超过了 --max-archive-depth 的 {} 层", kind, cfg.max_archive_depth),
+        );
+        return Ok(());
+    }
+
+    let max_size = cfg.max_member_size.unwrap_or(u64::MAX);
+    archive::for_each_member(p, kind, &bts, max_size, |m| {
+        match m {
+            Member::File { name, data } => {
+                let mp = archive::member_path(p, &name);
+                if let Err(e) = process_content(&mp, data, re, cfg, depth + 1, ff, ef) {
+                    if is_fatal(&e) {
+                        return Err(e);
+                    }
+                    ef(e);
+                }
+            }
+            Member::Skipped { name, reason } => skip(cfg, &archive::member_path(p, &name), &reason),
+            Member::Encrypted { name } => log::log(
+                log::Level::Warn,
+                module_path!(),
+                format_args!("跳过加密的 zip 成员: {}", archive::member_path(p, &name).display()),
+            ),
+        }
+        Ok(())
+    })
+}
+
+/// 报告一个没有被搜索的路径
+///
+/// 所有跳过文件或目录的地方都要经过这里，`--debug-skip` 才能给出完整的解释
+///
+/// # 参数
+/// * `cfg` - 搜索配置，没有开启 `--debug-skip` 时只输出 debug 级别的日志
+/// * `p` - 被跳过的路径
+/// * `reason` - 排除它的规则
+fn skip(cfg: &GrepConfig, p: &Path, reason: &str) {
+    if cfg.debug_skip {
+        if cfg.debug_skip_glob.as_ref().is_none_or(|g| g.is_match(p)) {
+            eprintln!("跳过 {}: {}", p.display(), reason);
+        }
+    } else {
+        debug!("跳过 {}: {}", p.display(), reason);
+    }
+}
+
+/// 判断错误是否应该终止整个搜索
+///
+/// 模糊匹配超时、替换结果检查失败和 Halt 是致命的，
+/// 其他错误只影响出错的那个文件
+pub fn is_fatal(e: &Error) -> bool {
+    e.downcast_ref::<FuzzyTimeout>().is_some()
+        || e.downcast_ref::<ReplaceVerifyFailed>().is_some()
+        || e.downcast_ref::<Halt>().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fuzzy(pattern: &str, max_edits: usize, limit: Option<Duration>) -> GrepConfig {
+        GrepConfig {
+            fuzzy: Some(FuzzyConfig {
+                pattern: pattern.to_string(),
+                max_edits,
+                started: Instant::now(),
+                limit,
+            }),
+            ..GrepConfig::default()
+        }
+    }
+
+    #[test]
+    fn fuzzy_distance_of_known_typos() {
+        let hit = |line, pat, k| fuzzy_find(line, pat, k).map(|h| (h.tx, h.dist));
+        assert_eq!(hit("say hallo there", "hello", 1), Some(("hallo".to_string(), 1)));
+        assert_eq!(hit("we recive it", "receive", 1), Some(("recive".to_string(), 1)));
+        // 同一个起点上距离相同时取较短的子串："recie" 补上 "ve" 也是两处编辑
+        assert_eq!(hit("we recieve it", "receive", 2), Some(("recie".to_string(), 2)));
+        assert_eq!(hit("x hello y", "hello", 2), Some(("hello".to_string(), 0)));
+        // 换位（transposition）在 Levenshtein 距离中是两处编辑
+        assert_eq!(hit("the calss", "class", 1), None);
+        assert_eq!(hit("goodbye", "hello", 1), None);
+    }
+
+    #[test]
+    fn fuzzy_picks_the_closest_then_earliest_substring() {
+        // 前面的 "helo" 差一处，后面的 "hello" 完全相同，取距离更小的
+        let h = fuzzy_find("helo and hello", "hello", 1).unwrap();
+        assert_eq!((h.tx.as_str(), h.dist), ("hello", 0));
+        // 距离相同时取最靠前的
+        let h = fuzzy_find("hallo hullo", "hello", 1).unwrap();
+        assert_eq!((h.tx.as_str(), h.dist), ("hallo", 1));
+        // 按字符切分，不会截断多字节字符
+        let h = fuzzy_find("他说你好世介", "你好世界", 1).unwrap();
+        assert_eq!((h.tx.as_str(), h.dist), ("你好世", 1));
+    }
+
+    #[test]
+    fn fuzzy_records_carry_the_closest_match() {
+        let cfg = fuzzy("receive", 1, None);
+        let text = "please recive this\nrecieve\nreceive ok\n";
+        let res = process_bytes(Path::new("t"), text.into(), &Regex::new("receive").unwrap(), &cfg).unwrap();
+        let hits: Vec<_> =
+            res.iter().map(|r| (r.line, r.fuzzy.as_ref().map(|h| (h.tx.as_str(), h.dist)))).collect();
+        assert_eq!(hits, [(0, Some(("recive", 1))), (2, Some(("receive", 0)))]);
+    }
+
+    #[test]
+    fn fuzzy_cpu_limit_stops_the_search() {
+        let cfg = fuzzy("receive", 2, Some(Duration::ZERO));
+        std::thread::sleep(Duration::from_millis(1));
+        let err = process_bytes(Path::new("t"), b"recieve\n".to_vec(), &Regex::new("x").unwrap(), &cfg).unwrap_err();
+        assert!(err.downcast_ref::<FuzzyTimeout>().is_some());
+        assert!(is_fatal(&err));
+
+        let cfg = fuzzy("receive", 2, Some(Duration::from_secs(60)));
+        assert!(process_bytes(Path::new("t"), b"recieve\n".to_vec(), &Regex::new("x").unwrap(), &cfg).is_ok());
+    }
+
+    /// 前 `failures` 次返回 `kind` 错误、之后成功的操作，返回结果和调用次数
+    fn flaky(retries: u32, failures: u32, kind: std::io::ErrorKind) -> (std::io::Result<&'static str>, u32) {
+        let mut calls = 0;
+        let r = retry_transient(retries, || {
+            calls += 1;
+            if calls <= failures { Err(kind.into()) } else { Ok("data") }
+        });
+        (r, calls)
+    }
+
+    #[test]
+    fn transient_errors_are_retried_until_success() {
+        let (r, calls) = flaky(DEFAULT_IO_RETRY, 2, std::io::ErrorKind::Interrupted);
+        assert_eq!(r.unwrap(), "data");
+        assert_eq!(calls, 3);
+        let (r, calls) = flaky(DEFAULT_IO_RETRY, 2, std::io::ErrorKind::WouldBlock);
+        assert_eq!(r.unwrap(), "data");
+        assert_eq!(calls, 3);
+        assert_eq!(GrepConfig::default().io_retry, DEFAULT_IO_RETRY);
+    }
+
+    #[test]
+    fn profile_regex_records_time_per_file() {
+        let cfg = GrepConfig {
+            profile_regex: true,
+            profile_per_line: true,
+            timing: Some(Timings::default()),
+            ..GrepConfig::default()
+        };
+        let re = Regex::new(r"(\w+\s?)+$").unwrap();
+        let text = "alpha beta\n!!!\ngamma\n";
+        let res = process_bytes(Path::new("p.txt"), text.into(), &re, &cfg).unwrap();
+        assert_eq!(res.len(), 2);
+
+        let files = cfg.timing.as_ref().unwrap().slowest();
+        assert_eq!(files.len(), 1);
+        let t = &files[0].1;
+        assert!(t.regex > Duration::ZERO);
+        assert_eq!((t.regex_lines, t.matches), (3, 2));
+        assert_eq!(t.per_line.iter().map(|&(i, _)| i).collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(t.per_line.iter().map(|&(_, d)| d).sum::<Duration>(), t.regex);
+
+        let report = cfg.timing.as_ref().unwrap().regex_report(true, true);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 4, "{}", report);
+        assert!(lines[0].starts_with("正则耗时 p.txt:1: "), "{}", report);
+        assert!(lines[3].starts_with("正则耗时 p.txt: ") && lines[3].ends_with("（3 行，2 个匹配）"), "{}", report);
+    }
+
+    #[test]
+    fn default_config_matches_command_line_defaults() {
+        let cfg = GrepConfig::default();
+        assert_eq!(cfg.binary_scan_bytes, 8192);
+        assert_eq!(cfg.max_archive_depth, 1);
+        assert_eq!(cfg.max_member_size, Some(100 << 20));
+        assert_eq!(cfg.max_line_bytes, Some(64 << 20));
+        assert_eq!(cfg.ignore_marker.as_deref(), Some("pgrep-ignore"));
+
+        let re = Regex::new("x").unwrap();
+        let binary = process_bytes(Path::new("b"), b"x\0\n".to_vec(), &re, &cfg).unwrap();
+        assert!(binary.is_empty());
+        let marked = process_bytes(Path::new("t"), b"x // pgrep-ignore\nx\ny\nx\n".to_vec(), &re, &cfg).unwrap();
+        assert_eq!(marked.iter().map(|r| r.suppressed).collect::<Vec<_>>(), [true, true, false]);
+    }
+
+    #[test]
+    fn retries_run_out() {
+        let (r, calls) = flaky(1, 2, std::io::ErrorKind::Interrupted);
+        assert_eq!(r.unwrap_err().kind(), std::io::ErrorKind::Interrupted);
+        assert_eq!(calls, 2);
+        let (r, calls) = flaky(0, 1, std::io::ErrorKind::Interrupted);
+        assert!(r.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn other_errors_are_not_retried() {
+        let (r, calls) = flaky(DEFAULT_IO_RETRY, 1, std::io::ErrorKind::NotFound);
+        assert_eq!(r.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn flag_groups_that_disable_multiline() {
+        assert!(disables_multiline("(?-m)^a$"));
+        assert!(disables_multiline("x(?i-m:^a)"));
+        assert!(disables_multiline("(?s-im)a"));
+        assert!(!disables_multiline("(?m-i)^a"));
+        assert!(!disables_multiline("(?i)a"));
+    }
+
+    #[test]
+    fn literal_dash_m_keeps_the_fast_path() {
+        assert!(!disables_multiline("--max-m"));
+        assert!(!disables_multiline("a-m"));
+        assert!(!disables_multiline(r"\(?-m)"));
+        assert!(!disables_multiline("(?P<m>-m)"));
+
+        let cfg = GrepConfig::default();
+        let re = Regex::new("-m").unwrap();
+        let res = scan_whole_text("run -m 3\nskip\nx-m\n", &re, &cfg).unwrap();
+        assert_eq!(res.iter().map(|r| r.line).collect::<Vec<_>>(), [0, 2]);
+    }
+
+    #[test]
+    fn whole_text_scan_agrees_with_per_line_matching() {
+        let text = "alpha\nbeta end\n\nend\ngamma end x\n";
+        for pattern in ["end$", "^end", r"\bend\b", "a.", "(?-m)^alpha"] {
+            let re = Regex::new(pattern).unwrap();
+            let fast = process_bytes(Path::new("t"), text.into(), &re, &GrepConfig::default()).unwrap();
+            let slow_cfg = GrepConfig {
+                skip_empty_lines: true,
+                ..GrepConfig::default()
+            };
+            let slow = process_bytes(Path::new("t"), text.into(), &re, &slow_cfg).unwrap();
+            let lines = |v: &[Record]| v.iter().map(|r| r.line).collect::<Vec<_>>();
+            assert_eq!(lines(&fast), lines(&slow), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn context_filter_drops_matches_without_the_context_pattern() {
+        let text = "retry 1\nok\nretry 2\nwait\ngave up\nretry 3\ngave up\nretry 4\n";
+        let re = Regex::new("retry").unwrap();
+        let lines = |cfg: &GrepConfig| {
+            let res = process_bytes(Path::new("t"), text.into(), &re, cfg).unwrap();
+            res.iter().map(|r| r.line).collect::<Vec<_>>()
+        };
+        let window = |n| GrepConfig {
+            context_re: Some(Regex::new("gave up").unwrap()),
+            context_after: n,
+            ..GrepConfig::default()
+        };
+        assert_eq!(lines(&GrepConfig::default()), [0, 2, 5, 7]);
+        // retry 2 之后的第 2 行才是 gave up，retry 1 要到第 4 行
+        assert_eq!(lines(&window(1)), [5]);
+        assert_eq!(lines(&window(2)), [2, 5]);
+        assert_eq!(lines(&window(4)), [0, 2, 5]);
+        // 窗口超出文件末尾时只检查到最后一行，retry 4 之后没有行
+        assert_eq!(lines(&window(100)), [0, 2, 5]);
+        assert_eq!(lines(&window(0)), Vec::<usize>::new());
+
+        // 逐行匹配时结果相同，行过滤不影响检查的范围
+        let filtered = GrepConfig {
+            line_prefixes: vec!["retry".to_string()],
+            ..window(2)
+        };
+        assert_eq!(lines(&filtered), [2, 5]);
+    }
+
+    #[test]
+    fn context_records_surround_matches() {
+        let text = "a\nb\nX\nc\nX\nd\ne\nf\ng\nX\n";
+        let re = Regex::new("X").unwrap();
+        let lines = |before, after, skip_matches| {
+            let cfg = GrepConfig {
+                before_context: before,
+                after_context: after,
+                skip_matches,
+                ..GrepConfig::default()
+            };
+            let res = cfg.skip_leading(process_bytes(Path::new("t"), text.into(), &re, &cfg).unwrap());
+            res.iter().map(|r| (r.line, r.is_context)).collect::<Vec<_>>()
+        };
+        assert_eq!(lines(0, 0, 0), [(2, false), (4, false), (9, false)]);
+        // 重叠的上下文只出现一次，匹配的行不会作为上下文
+        assert_eq!(
+            lines(1, 1, 0),
+            [(1, true), (2, false), (3, true), (4, false), (5, true), (8, true), (9, false)]
+        );
+        // 窗口在文件的开头和末尾截断
+        assert_eq!(lines(3, 0, 0)[..3], [(0, true), (1, true), (2, false)]);
+        assert_eq!(lines(0, 5, 0).last(), Some(&(9, false)));
+        // 跳过的匹配留作上下文
+        assert_eq!(lines(1, 0, 1), [(1, true), (2, true), (3, true), (4, false), (8, true), (9, false)]);
+    }
+
+    #[test]
+    fn each_config_caches_its_own_pattern() {
+        let (a, b) = (GrepConfig::default(), GrepConfig::default());
+        let (ra, rb) = (Regex::new("^a").unwrap(), Regex::new("^b").unwrap());
+        assert_eq!(multiline_regex(&ra, &a.multiline).unwrap().as_str(), "(?m:^a)");
+        assert_eq!(multiline_regex(&rb, &b.multiline).unwrap().as_str(), "(?m:^b)");
+        // 换了模式的配置重新编译，缓存中仍然是第一个模式
+        assert_eq!(multiline_regex(&rb, &a.multiline).unwrap().as_str(), "(?m:^b)");
+        assert_eq!(multiline_regex(&ra, &a.multiline).unwrap().as_str(), "(?m:^a)");
+    }
+}
@@ -0,0 +1,148 @@
+// 分级日志
+//
+// 调试遍历和匹配过程时使用的诊断信息，全部输出到标准错误，标准输出只留给搜索结果。
+// 接口仿照 log 库的宏（目前用到了 info! 和 debug!），
+// 日志行的格式为 `[LEVEL target] 消息`，target 是调用处的模块路径。
+//
+// 级别由两处决定，取两者中更详细的一个：
+// * `-v` 开启 info，`-vv` 开启 debug，`-vvv` 开启 trace；不指定时只输出 warn 和 error
+// * `RUST_LOG` 环境变量，和 env_logger 一样由逗号分隔的 `[target=]level` 组成，
+//   例如 `RUST_LOG=pgrep::config=trace,info`，target 按最长前缀匹配
+//
+// 警告（例如模式会匹配所有的行、行被截断）也是 warn 级别的日志，`--no-warnings` 关掉所有 target 的 warn，
+// 不管 `-v` 和 `RUST_LOG` 怎么设置；error 和更详细的级别不受影响。
+//
+// 相关文档:
+// * log 库: <https://docs.rs/log/>
+// * env_logger 的 RUST_LOG 语法: <https://docs.rs/env_logger/#enabling-logging>
+
+use std::fmt;
+use std::sync::OnceLock;
+
+/// 日志级别，越靠后越详细
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn parse(s: &str) -> Option<Level> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Some(Level::Error),
+            "warn" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        };
+        f.pad(s)
+    }
+}
+
+/// 日志过滤规则
+struct Filter {
+    // 没有指定 target 的默认级别
+    default: Level,
+    // 按 target 前缀指定的级别
+    targets: Vec<(String, Level)>,
+    // --no-warnings 时为 false
+    warnings: bool,
+}
+
+static FILTER: OnceLock<Filter> = OnceLock::new();
+
+/// 初始化日志级别，只有第一次调用生效
+///
+/// # 参数
+/// * `verbose` - 命令行上 `-v` 出现的次数
+/// * `warnings` - 是否输出 warn 级别的日志，`--no-warnings` 时为 false
+pub fn init(verbose: u8, warnings: bool) {
+    let from_flags = match verbose {
+        0 => Level::Warn,
+        1 => Level::Info,
+        2 => Level::Debug,
+        _ => Level::Trace,
+    };
+    let mut filter = Filter {
+        default: from_flags,
+        targets: Vec::new(),
+        warnings,
+    };
+    // 无法识别的项被忽略，和 env_logger 的行为一致
+    if let Ok(spec) = std::env::var("RUST_LOG") {
+        for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match item.split_once('=') {
+                Some((target, level)) => {
+                    if let Some(l) = Level::parse(level) {
+                        filter.targets.push((target.to_string(), l.max(from_flags)));
+                    }
+                }
+                None => {
+                    if let Some(l) = Level::parse(item) {
+                        filter.default = l.max(from_flags);
+                    }
+                }
+            }
+        }
+    }
+    // 最长的前缀排在最前面，查找时第一个命中的就是最具体的规则
+    filter.targets.sort_by_key(|t| std::cmp::Reverse(t.0.len()));
+    let _ = FILTER.set(filter);
+}
+
+/// 判断某个级别的日志是否需要输出
+pub fn enabled(level: Level, target: &str) -> bool {
+    let Some(filter) = FILTER.get() else {
+        return level <= Level::Warn;
+    };
+    if level == Level::Warn && !filter.warnings {
+        return false;
+    }
+    let max = filter
+        .targets
+        .iter()
+        .find(|(t, _)| target == t || target.strip_prefix(t.as_str()).is_some_and(|r| r.starts_with("::")))
+        .map(|(_, l)| *l)
+        .unwrap_or(filter.default);
+    level <= max
+}
+
+/// 输出一条日志，由日志宏调用
+pub fn log(level: Level, target: &str, args: fmt::Arguments) {
+    if enabled(level, target) {
+        eprintln!("[{:<5} {}] {}", level, target, args);
+    }
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Info, module_path!(), format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Debug, module_path!(), format_args!($($arg)*))
+    };
+}
+
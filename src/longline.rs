@@ -0,0 +1,211 @@
+// 截断过长的行
+//
+// 压缩过的 JavaScript、一行一个的 JSON 导出之类的文件可能整个文件只有一行，几百 MB。
+// 设置了 `GrepConfig::max_line_bytes` 时，每行只保留前这么多字节参与匹配，之后的部分直接丢弃：
+// * 普通文件边读边截断（见 `read_capped`），读入内存的内容不超过每行的上限，不会先读完整个文件
+// * 标准输入、解压出来的内容、包中的文件和预处理命令的输出已经在内存中，在匹配之前截断
+// * 截断的位置退回到字符边界，不会把一个 UTF-8 字符切成两半，让整个文件变得不是合法的 UTF-8
+// * 每个有行被截断的文件输出一条警告，说明有几行被截断
+//
+// 除了 `--newline lf`，单独的 `\r` 也算作长度计算的行边界，只用 `\r` 分行的文件不会被当作一个长行截断。
+//
+// 被截断的行仍然按行号输出，内容是截断后的部分；匹配在被丢弃的部分中的行不会被找到。
+//
+// 相关文档:
+// * std::io::BufRead::fill_buf: <https://doc.rust-lang.org/std/io/trait.BufRead.html#tymethod.fill_buf>
+
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::log;
+use crate::newline::Newline;
+
+/// 边写入边截断过长的行
+pub(crate) struct LineCap {
+    max: usize,
+    kept: Vec<u8>,
+    /// 当前行已经看到的字节数，包括被丢弃的部分
+    line_len: usize,
+    /// 当前行在 `kept` 中开始的位置
+    line_start: usize,
+    /// `\r` 也结束一行
+    cr: bool,
+    /// 被截断的行数
+    truncated: usize,
+}
+
+impl LineCap {
+    pub(crate) fn new(max: usize, capacity: usize, newline: Newline) -> LineCap {
+        LineCap {
+            max,
+            kept: Vec::with_capacity(capacity),
+            line_len: 0,
+            line_start: 0,
+            cr: newline != Newline::Lf,
+            truncated: 0,
+        }
+    }
+
+    /// 写入一段内容，段的边界可以在行的中间
+    pub(crate) fn push(&mut self, mut chunk: &[u8]) {
+        while !chunk.is_empty() {
+            let end = if self.cr {
+                memchr::memchr2(b'\n', b'\r', chunk)
+            } else {
+                memchr::memchr(b'\n', chunk)
+            };
+            let (seg, eol) = match end {
+                Some(i) => (&chunk[..i], Some(chunk[i])),
+                None => (chunk, None),
+            };
+            let room = self.max.saturating_sub(self.line_len);
+            self.kept.extend_from_slice(&seg[..seg.len().min(room)]);
+            if seg.len() > room && self.line_len <= self.max {
+                self.truncated += 1;
+                self.trim_partial_char();
+            }
+            self.line_len += seg.len();
+            let Some(eol) = eol else {
+                return;
+            };
+            self.kept.push(eol);
+            self.line_len = 0;
+            self.line_start = self.kept.len();
+            chunk = &chunk[seg.len() + 1..];
+        }
+    }
+
+    /// 截断的位置在一个多字节字符的中间时，去掉这个字符已经写入的字节
+    fn trim_partial_char(&mut self) {
+        let line = &self.kept[self.line_start..];
+        // 最后一个字符最多 4 个字节，前面是起始字节，之后是 0b10xxxxxx 的后续字节
+        let tail = line.len().saturating_sub(4);
+        let Some(lead) = (tail..line.len()).rev().find(|&i| line[i] & 0xc0 != 0x80) else {
+            return;
+        };
+        let want = match line[lead] {
+            b if b < 0x80 => 1,
+            b if b >= 0xf0 => 4,
+            b if b >= 0xe0 => 3,
+            b if b >= 0xc0 => 2,
+            _ => 1,
+        };
+        if line.len() - lead < want {
+            self.kept.truncate(self.line_start + lead);
+        }
+    }
+
+    /// 结束写入，有行被截断时给出警告
+    pub(crate) fn finish(self, p: &Path) -> Vec<u8> {
+        if self.truncated > 0 {
+            log::log(
+                log::Level::Warn,
+                module_path!(),
+                format_args!(
+                    "{} 中有 {} 行超过了 --max-line-bytes，只匹配了每行的前 {} 字节",
+                    p.display(),
+                    self.truncated,
+                    self.max
+                ),
+            );
+        }
+        self.kept
+    }
+}
+
+/// 截断已经在内存中的内容，没有超过上限的行时原样返回，不做复制
+pub(crate) fn cap_lines(p: &Path, bts: Vec<u8>, max: usize, newline: Newline) -> Vec<u8> {
+    if bts.len() <= max {
+        return bts;
+    }
+    let mut start = 0;
+    let ends: Box<dyn Iterator<Item = usize>> = if newline == Newline::Lf {
+        Box::new(memchr::memchr_iter(b'\n', &bts))
+    } else {
+        Box::new(memchr::memchr2_iter(b'\n', b'\r', &bts))
+    };
+    let too_long = ends.chain(std::iter::once(bts.len())).any(|end| {
+        let long = end - start > max;
+        start = end + 1;
+        long
+    });
+    if !too_long {
+        return bts;
+    }
+    let mut cap = LineCap::new(max, bts.len().min(max.saturating_mul(2)), newline);
+    cap.push(&bts);
+    cap.finish(p)
+}
+
+/// 边读边截断一个文件
+///
+/// # 参数
+/// * `head` - 已经读出的文件开头，接在读取的内容之前
+/// * `r` - 文件剩下的部分
+pub(crate) fn read_capped(p: &Path, head: &[u8], r: impl Read, max: usize, newline: Newline) -> std::io::Result<Vec<u8>> {
+    let mut cap = LineCap::new(max, head.len(), newline);
+    cap.push(head);
+    let mut r = BufReader::with_capacity(1 << 16, r);
+    loop {
+        let buf = r.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        let n = buf.len();
+        cap.push(buf);
+        r.consume(n);
+    }
+    Ok(cap.finish(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 不在内存中保存内容的输入：`len` 字节的 `x`，之后是 `tail`
+    struct Synthetic {
+        left: usize,
+        tail: &'static [u8],
+    }
+
+    impl Read for Synthetic {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.left > 0 {
+                let n = buf.len().min(self.left);
+                buf[..n].fill(b'x');
+                self.left -= n;
+                return Ok(n);
+            }
+            let n = buf.len().min(self.tail.len());
+            buf[..n].copy_from_slice(&self.tail[..n]);
+            self.tail = &self.tail[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn a_100mb_line_keeps_only_the_cap() {
+        const MAX: usize = 1 << 20;
+        let r = Synthetic {
+            left: 100 << 20,
+            tail: b" needle\nshort needle\n",
+        };
+        let kept = read_capped(Path::new("big.txt"), b"head ", r, MAX, Newline::Auto).unwrap();
+        // 读入的内容和分配的内存都只和上限有关，和行的长度无关
+        assert_eq!(kept.len(), MAX + "\nshort needle\n".len());
+        assert!(kept.capacity() <= 2 * MAX + (1 << 16), "{}", kept.capacity());
+        assert!(kept.starts_with(b"head xxx"));
+        assert!(kept.ends_with(b"xxx\nshort needle\n"));
+    }
+
+    #[test]
+    fn truncation_backs_off_to_a_char_boundary() {
+        // 第 4 个字节在“你”的中间
+        let kept = cap_lines(Path::new("a.txt"), "ab你好\nok\n".as_bytes().to_vec(), 4, Newline::Auto);
+        assert_eq!(kept, b"ab\nok\n");
+        // 单独的 \r 也是行边界，这样的行不算过长
+        let cr = b"abc\rdef\r".to_vec();
+        assert_eq!(cap_lines(Path::new("a.txt"), cr.clone(), 3, Newline::Auto), cr);
+        assert_eq!(cap_lines(Path::new("a.txt"), cr, 3, Newline::Lf), b"abc");
+    }
+}
@@ -6,14 +6,46 @@
 // 3. 正则表达式匹配（使用 regex 库）
 // 4. 文件系统操作和目录遍历
 // 5. 泛型和闭包的使用
+// 6. 基于编辑距离的模糊匹配（使用 strsim 库）
+// 7. 配置文件与命名 profile（见 config 模块）
+// 8. 按发音匹配单词（见 phonetic 模块）
+// 9. 带大小写转换的替换模板（见 replace 模块）
+// 10. 用 Aho-Corasick 自动机同时匹配大量关键词
+// 11. 常用的内置模式（见 builtin 模块）
+// 12. 交互式地在编辑器中打开匹配，或者逐条确认要输出的结果（见 interactive 模块）
+// 13. 通过分页器输出（见 output 模块）
+// 14. 生成统一差异格式的补丁（见 diff 模块）
+// 15. 把搜索核心拆分成库供其他程序使用（见 lib.rs）
+// 16. 搜索 tar / zip 包中的文件（见 archive 模块）
+// 17. 生成 shell 补全脚本（见 completions 模块）
+// 18. 文件变化时自动重新搜索（见 watch 模块）
+// 19. 生成 man 手册（见 manpage 模块）
+// 20. 像 tail -F 一样跟踪不断增长的文件（见 follow 模块）
+// 21. 用原子的重命名直接修改文件（见 rewrite 模块）
+// 22. 把参数翻译成 ripgrep 的命令行（见 rg 模块）
+// 23. 像 sed 一样替换标准输入中的每一行（见 filter 模块）
+// 24. 全屏的终端界面，边改模式边浏览结果（见 tui 模块）
+// 25. 只搜索 git 跟踪的或者有修改的文件，输出相对于仓库根目录的路径（见 git 模块）
+// 26. 可以配置的高亮样式（见 style 模块）
+// 27. 只报告基线之外新出现的匹配（见 baseline 模块）
+// 28. 用模板自定义输出的格式，可以加上时间戳（见 record 模块）
+// 29. 用行内标记和抑制文件抑制已知的匹配（见 suppress 模块）
+// 30. 把结果写入 SQLite 数据库，通过 FFI 调用 C 库（见 sqlite 模块）
+// 31. 常驻进程，通过标准输入输出上的 JSON-RPC 接受搜索请求（见 serve 模块）
+// 32. 在多个线程中检查一行是否匹配所有的模式（见 matchall 模块）
+// 33. 为不存在的搜索路径给出拼写建议（见 suggest 模块）
+// 34. 按规则文件检查代码，输出代码扫描工具使用的 SARIF（见 sarif 模块）
+
+// failure 的 derive 宏会在 `const _: () = { ... }` 块内生成 impl，
+// 新版编译器会对此给出 non_local_definitions 警告，这里统一允许
+#![allow(non_local_definitions)]
 
 // 引入外部库
 //
 // clap: 命令行参数解析库
 // 文档: <https://docs.rs/clap/>
 // GitHub: <https://github.com/clap-rs/clap>
-use clap;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 
 // failure: 错误处理库，提供结构化错误处理
 // 文档: <https://docs.rs/failure/>
@@ -25,64 +57,295 @@ use failure::{Error, Fail};
 // regex: 正则表达式库
 // 文档: <https://docs.rs/regex/>
 // GitHub: <https://github.com/rust-lang/regex>
-use regex::Regex;
+use regex::{Regex, RegexSet};
 
 // 标准库引入
-use std::fmt;
-use std::path::Path;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsString;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+// 目录遍历、逐行匹配等搜索核心（见 lib.rs）
+use pgrep::dfacache;
+use pgrep::encoding::Encoding;
+use pgrep::filetype::TypeDefs;
+use pgrep::glob::Glob;
+use pgrep::log;
+use pgrep::matchall;
+use pgrep::matcher::LiteralMatcher;
+use pgrep::newline::Newline;
+use pgrep::phonetic::{PhoneticConfig, PhoneticMode};
+use pgrep::preprocess::Preprocessor;
+use pgrep::replace::Template;
+use pgrep::suggest;
+use pgrep::{
+    ArgErr, Fields, FuzzyConfig, GrepConfig, Halt, Record, ReplaceVerifyFailed, Rule, TypeFilter, WalkContext, WordList,
+    debug, info, is_fatal, match_captures, match_spans, process_path, record_match,
+};
+
+// 配置文件解析与 profile 展开
+mod config;
+
+// --generate-completions 的补全脚本
+mod completions;
+
+// --generate-man-page 的 man 手册
+mod manpage;
+
+// IP 地址、邮箱等内置模式
+mod builtin;
+use builtin::BuiltinPattern;
+
+// --patch 使用的统一差异格式
+mod diff;
+
+// --interactive 的选择解析和编辑器调用，--confirm 的逐条确认
+mod interactive;
+
+// 标准输出和分页器
+mod output;
+use output::{LineEnding, Output, outln, outrec};
+
+// --watch 的文件变化检测
+mod watch;
+
+// --follow-lines 跟踪文件中新增的行
+mod follow;
+
+// --write-replace 改写文件
+mod rewrite;
+
+// --print-rg-command 的参数翻译
+mod rg;
+
+// --filter 流过滤模式
+mod filter;
+
+// --tui 终端界面
+mod tui;
+
+// --git-tracked、--staged 和 --changed 的文件列表，--git-relative 的路径
+mod git;
+
+// 输出中路径、行号和匹配的颜色
+mod style;
+use style::HighlightStyle;
+
+// 基线文件和 --serve 的消息共用的 JSON 读写
+mod json;
+use json::Json;
+
+// --baseline 和 --save-baseline 的基线文件
+mod baseline;
+
+// --record-format 的模板和时间戳
+mod record;
+use record::{Precision, RecordFormat};
+
+// 行内标记和 --suppressions 抑制文件
+mod suppress;
+
+// --format sqlite 的数据库输出，只在启用 sqlite feature 时编译
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+// --serve 的 JSON-RPC 服务
+mod serve;
+
+// --format sarif 的代码扫描结果
+mod sarif;
+
+// 看起来像文件名通配符的模式的提示
+mod globhint;
+use globhint::{glob_hint, glob_shaped};
+
+/// `--group-by` 的分组依据
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum GroupBy {
+    /// 按匹配到的模式分组
+    Pattern,
+    /// 按文件所在的目录分组
+    Directory,
+}
+
+/// `--dedup-by` 比较的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum DedupBy {
+    /// 整行的内容
+    Line,
+    /// 匹配到的文本
+    Match,
+    /// 文件路径，每个文件只输出第一条结果
+    Path,
+}
+
+/// `--count-mode` 统计的对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum CountMode {
+    /// 每个文件中匹配的行数，和 `grep -c` 相同
+    Lines,
+    /// 每个文件中匹配的次数，一行中的多个匹配分别计数
+    Matches,
+    /// 每个文件中匹配到的文本的字节数
+    Bytes,
+    /// 包含匹配的文件数
+    Files,
+    /// 所有文件中不同的匹配文本的个数，同一个文本出现多次只算一个
+    Unique,
+}
+
+/// `--format` 的取值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// 文本，和不指定 `--format` 时相同
+    Text,
+    /// JSON Lines，每个匹配一行 JSON 对象，有 path、line、column、text 字段，`--replace` 时还有 replaced
+    Json,
+    /// 写入 `--output` 指定的 SQLite 数据库，需要 sqlite feature
+    Sqlite,
+    /// SARIF 2.1.0，搜索结束后输出一个 JSON 文档，见 sarif 模块
+    Sarif,
+    /// GitHub Actions 的注解命令，每个匹配一行 `::warning file=...::...`
+    Github,
+}
+
+/// `--color` 的取值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ColorChoice {
+    /// 标准输出是终端时使用颜色
+    Auto,
+    /// 总是使用颜色
+    Always,
+    /// 不使用颜色
+    Never,
+}
 
 // Failure 库的教程链接
 // <https://boats.gitlab.io/failure/>
 // 这个教程详细介绍了如何使用 failure 库进行错误处理
 
-/// 记录结构体
-///
-/// 用于存储在文件中找到的匹配结果
+/// 没有提供搜索模式
 ///
-/// # 字段
-/// * `line` - 匹配行号（从0开始计数）
-/// * `tx` - 匹配行的文本内容
-#[derive(Debug)]
-struct Record {
-    line: usize,
-    tx: String,
+/// 最常见的原因是模式以 `-` 开头而被当成了选项，错误信息中会提示 `--` 的用法
+#[derive(Debug, Fail)]
+#[fail(
+    display = "没有提供搜索模式（如果模式以 '-' 开头，请使用 `-e -foo`、`-p=-foo` 或者 `pgrep -- -foo PATH`）"
+)]
+struct MissingPattern;
+
+/// 模式是空的
+#[derive(Debug, Fail)]
+#[fail(display = "模式是空的，会匹配每一行；确实要选中每一行时请使用 --all-lines")]
+struct EmptyPattern;
+
+/// `--expand-env` 时模式引用了没有定义的环境变量
+#[derive(Debug, Fail)]
+#[fail(display = "模式中的环境变量 ${} 没有定义（用 --expand-env-empty 把它替换成空字符串）", _0)]
+struct UndefinedEnv(String);
+
+/// `--filter` 只处理标准输入
+#[derive(Debug, Fail)]
+#[fail(display = "--filter 从标准输入读取，不能再指定要搜索的路径 {}", _0)]
+struct FilterPaths(String);
+
+/// 模糊匹配、语音匹配和按字面查找只支持一个模式
+#[derive(Debug, Fail)]
+#[fail(display = "{} 只支持一个模式", flags)]
+struct TooManyPatterns {
+    flags: &'static str,
 }
 
-/// 参数错误结构体
-///
-/// 使用 failure 库的 Fail derive 宏来实现自定义错误类型
-/// 这个结构体演示了如何创建结构化的错误信息
-///
-/// # 使用示例
-/// ```
-/// let error = ArgErr { arg: "file" };
-/// println!("{}", error); // 输出: Argument not provided file
-/// ```
+/// `--confirm` 需要从终端读取回答
+#[derive(Debug, Fail)]
+#[fail(display = "--confirm 需要从终端读取回答，无法打开 /dev/tty: {}", _0)]
+struct NoTty(std::io::Error);
+
+/// `--interactive` 需要终端
+#[derive(Debug, Fail)]
+#[fail(display = "--interactive 只能在标准输出是终端时使用")]
+struct NotATerminal;
+
+/// 外部命令执行失败
 ///
-/// # 相关文档
-/// * failure 库文档: <https://docs.rs/failure/>
-/// * Fail trait 文档: <https://docs.rs/failure/latest/failure/trait.Fail.html>
+/// `--exec` / `--exec-file` / `--exec-batch` 启动的命令以非零状态退出时返回
 #[derive(Debug, Fail)]
-#[fail(display = "Argument not provided {}", arg)]
-struct ArgErr {
-    arg: &'static str,
+#[fail(display = "命令 `{}` 执行失败: {}", cmd, status)]
+struct ExecErr {
+    cmd: String,
+    status: std::process::ExitStatus,
 }
 
-// 注意：下面的代码被注释掉了，因为使用了 Fail derive 宏后，
-// Rust 会自动为我们实现 Fail trait 和 Display trait
-//
-// 如果不使用 derive 宏，我们需要手动实现这些 trait：
+/// 搜索正常结束，但需要以非零状态退出，例如 `--diff` 发现了会被修改的文件
+///
+/// main 收到这个错误时不输出错误信息，直接以其中的状态退出
+#[derive(Debug, Fail)]
+#[fail(display = "退出状态 {}", _0)]
+struct ExitStatus(i32);
+
+/// `--frequency-analysis` 和 `--count-mode` 的其他统计方式一起使用
+#[derive(Debug, Fail)]
+#[fail(display = "--frequency-analysis 只能和 --count-mode unique 一起使用，不能和 --count-mode {} 一起使用", _0)]
+struct FrequencyModeErr(String);
 
-// impl Fail for ArgErr {}
+/// `--top` 和按文件数或不同文本统计的 `--count-mode` 一起使用
+#[derive(Debug, Fail)]
+#[fail(display = "--top 只能和 --count-mode lines、matches 或 bytes 一起使用，不能和 --count-mode {} 一起使用", _0)]
+struct TopModeErr(String);
 
-/*
-// 手动实现 Display trait 以支持错误信息的格式化
-impl std::fmt::Display for ArgErr {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Argument Not provided: {}", self.arg)
-    }
+/// `--replace-verify` 没有可以检查的替换
+#[derive(Debug, Fail)]
+#[fail(display = "--replace-verify 需要和 -r、--write-replace、--insert-before 或 --insert-after 一起使用")]
+struct VerifyWithoutReplace;
+
+/// `--field-range` 不是 `START-END` 或 `START-` 的形式
+#[derive(Debug, Fail)]
+#[fail(display = "无法解析 --field-range '{}'：应该写成 START-END 或 START-，字段从 1 开始，START 不能大于 END", _0)]
+struct FieldRangeErr(String);
+
+/// `--null-ratio` 不在 0 到 1 之间
+#[derive(Debug, Fail)]
+#[fail(display = "--null-ratio 必须在 0 到 1 之间，而不是 {}", _0)]
+struct NullRatioErr(f64);
+
+/// 模式无法编译，而且其中有看起来像文件名通配符的模式，错误信息后面附上 glob_hint 的建议
+#[derive(Debug, Fail)]
+#[fail(display = "{}\n提示: {}", err, hint)]
+struct GlobLikePattern {
+    err: regex::Error,
+    hint: String,
 }
-*/
+
+/// `--format` 和 `--output` 的组合不能使用
+#[derive(Debug, Fail)]
+#[fail(display = "{}", _0)]
+struct FormatErr(&'static str);
+
+/// `--patch` 需要重新读取文件，标准输入无法再读一遍
+#[derive(Debug, Fail)]
+#[fail(display = "--patch 不支持标准输入")]
+struct PatchStdin;
+
+/// `--version` 输出的完整版本信息
+///
+/// 版本号来自 Cargo.toml，其余构建元数据由 build.rs 在编译期收集。
+/// `-V` 只输出版本号，`--version` 额外输出这些信息。
+const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "\n提交: ",
+    env!("PGREP_GIT_HASH"),
+    "\n构建日期: ",
+    env!("PGREP_BUILD_DATE"),
+    "\n目标平台: ",
+    env!("PGREP_TARGET"),
+    "\n编译器: ",
+    env!("PGREP_RUSTC_VERSION"),
+    "\n启用的 feature: ",
+    env!("PGREP_FEATURES"),
+);
 
 /// 命令行参数结构体
 ///
@@ -98,7 +361,13 @@ impl std::fmt::Display for ArgErr {
 /// * clap 文档: <https://docs.rs/clap/>
 /// * Parser derive 宏: <https://docs.rs/clap/latest/clap/trait.Parser.html>
 #[derive(Parser, Debug)]
-#[command(version = "0.1.0", about = "一个简单的 grep 工具")]
+#[command(version, long_version = LONG_VERSION, about = "一个简单的 grep 工具")]
+// 允许同一个选项出现多次，以最后一次为准，profile 展开的参数才能被命令行覆盖
+#[command(args_override_self = true)]
+// `--git-untracked` 需要其中的一个
+#[command(group(clap::ArgGroup::new("git_selection").args(["git_tracked", "staged", "changed"])))]
+// `--patch` 和 `--filter` 需要其中至少一个
+#[command(group(clap::ArgGroup::new("replacement").args(["replace", "insert_before", "insert_after"]).multiple(true)))]
 struct Args {
     /// 要搜索的文件路径
     ///
@@ -107,8 +376,9 @@ struct Args {
     /// # 示例
     /// * `-f test.txt` - 搜索单个文件
     /// * `-f ./testdir` - 搜索整个目录
-    #[arg(short = 'f', long)]
-    file: String,
+    /// * `-f -` - 搜索标准输入
+    #[arg(short = 'f', long, value_name = "FILE")]
+    file: Option<String>,
 
     /// 要搜索的正则表达式模式
     ///
@@ -122,226 +392,3606 @@ struct Args {
     /// * `-p "abc"` - 搜索字符串 "abc"
     /// * `-p "a.*b"` - 搜索以 a 开头、b 结尾的行
     /// * `-p "[0-9]+"` - 搜索数字
-    #[arg(short = 'p', long)]
-    pattern: String,
-}
+    /// * `-e "-v"` - 搜索以 `-` 开头的模式，`-e` 是 `-p` 的别名
+    /// * `-e foo -e bar` - 搜索包含 foo 或者 bar 的行
+    ///
+    /// 这个选项的值允许以 `-` 开头，所以 `-p -v` 搜索的是 "-v" 而不是 `-v` 选项。
+    /// 可以重复指定，多个模式之间是"或"的关系。
+    #[arg(
+        short = 'p',
+        long,
+        visible_short_alias = 'e',
+        visible_alias = "regexp",
+        allow_hyphen_values = true
+    )]
+    pattern: Vec<String>,
 
-/// 处理单个文件的函数
-///
-/// 读取指定文件的内容，逐行检查是否匹配给定的正则表达式，
-/// 并返回所有匹配的记录。
-///
-/// # 参数
-/// * `p` - 文件路径，实现了 AsRef<Path> trait，可以接受 &str, &Path, String 等类型
-/// * `re` - 编译好的正则表达式对象
-///
-/// # 返回值
-/// * `Ok(Vec<Record>)` - 包含所有匹配记录的向量
-/// * `Err(Error)` - 文件读取或处理过程中的错误
-///
-/// # 泛型约束
-/// `P: AsRef<Path>` - 允许函数接受多种路径类型作为参数
-///
-/// # 错误处理
-/// 使用 `?` 操作符自动处理 I/O 错误，将其转换为 failure::Error
-///
-/// # 相关文档
-/// * std::fs::read: <https://doc.rust-lang.org/std/fs/fn.read.html>
-/// * String::from_utf8: <https://doc.rust-lang.org/std/string/struct.String.html#method.from_utf8>
-/// * AsRef trait: <https://doc.rust-lang.org/std/convert/trait.AsRef.html>
-fn process_file<P: AsRef<Path>>(p: P, re: &Regex) -> Result<Vec<Record>, Error> {
-    // 用于存储匹配结果的向量
-    let mut res = Vec::new();
+    /// 从 FILE 读取模式，每个非空行是一个模式，和 `-p` 给出的模式之间是"或"的关系
+    ///
+    /// 同时使用 `-E` 或 `--strip-pattern-comments` 时整个文件是一个模式，
+    /// 可以把一个复杂的模式分成多行来写，每行加上注释。
+    ///
+    /// # 示例
+    /// * `--pattern-file secrets.txt -f .` - 每行一个要查找的模式
+    /// * `-E --pattern-file date.re -f logs` - 多行带注释的模式
+    #[arg(long, value_name = "FILE")]
+    pattern_file: Option<PathBuf>,
 
-    // 读取文件的二进制内容
-    // `std::fs::read` 会将整个文件内容读入内存
-    let bts = std::fs::read(p)?;
-
-    // 尝试将字节数组转换为 UTF-8 字符串
-    // 使用 if let 来处理可能的编码错误
-    if let Ok(ss) = String::from_utf8(bts) {
-        // 逐行处理文件内容
-        // enumerate() 为每一行提供行号（从0开始）
-        for (i, l) in ss.lines().enumerate() {
-            // 检查当前行是否匹配正则表达式
-            if re.is_match(l) {
-                // 如果匹配，创建一个新的 Record 并添加到结果中
-                res.push(Record {
-                    line: i,
-                    tx: l.to_string(),
-                })
-            }
-        }
-    }
+    /// 扩展模式：模式中的空白被忽略，`#` 到行尾是注释，相当于正则表达式的 `(?x)`
+    ///
+    /// 和 POSIX grep 的 `-E` 不同，这里的模式本来就是扩展的正则表达式语法。
+    /// 要匹配空格或者 `#` 时写成 `\ `、`[ ]` 或 `\#`。不影响 `--builtin-pattern`。
+    ///
+    /// # 示例
+    /// * `-E -p "(\d{4}) - (\d{2})  # 年-月"`
+    #[arg(short = 'E', long, conflicts_with_all = ["fuzzy", "sound_like"])]
+    extended_regex: bool,
 
-    // 返回匹配结果
-    Ok(res)
-}
+    /// 模式是普通文本而不是正则表达式，其中的 `.`、`*`、`(` 等都按字面匹配
+    ///
+    /// 每个模式（包括 `--pattern-file` 中的每一行）分别转义之后再合并，输出中的匹配位置和 `--replace` 照常使用。
+    /// 只有一个模式、没有内置模式时自动使用 `--literal-match-only` 的按字面查找。
+    ///
+    /// # 示例
+    /// * `-F -p "a.b[0]" -f src` - 查找 `a.b[0]` 本身
+    #[arg(short = 'F', long, conflicts_with_all = ["extended_regex", "strip_pattern_comments", "rules"])]
+    fixed_strings: bool,
 
-/// 递归处理路径的函数
-///
-/// 这个函数可以处理文件和目录。对于文件，直接调用 process_file 进行搜索；
-/// 对于目录，递归遍历其中的所有文件并进行搜索。
-///
-/// # 参数
-/// * `p` - 要处理的路径（文件或目录）
-/// * `re` - 编译好的正则表达式对象
-/// * `ff` - 文件处理完成时的回调函数，接收路径和匹配结果
-/// * `ef` - 错误处理回调函数，接收发生的错误
-///
-/// # 泛型参数和约束
-/// * `P: AsRef<Path>` - 路径类型，支持多种路径输入
-/// * `FF: Fn(&Path, Vec<Record>)` - 文件处理回调函数类型
-/// * `EF: Fn(Error)` - 错误处理回调函数类型
-///
-/// # 函数式编程特性
-/// 这个函数展示了 Rust 中函数式编程的特性：
-/// - 使用闭包作为回调函数
-/// - 泛型约束确保类型安全
-/// - 函数式风格的错误处理
-///
-/// # 递归处理
-/// 目录处理是递归的，会遍历所有子目录和文件
-///
-/// # 相关文档
-/// * std::fs::metadata: <https://doc.rust-lang.org/std/fs/fn.metadata.html>
-/// * std::fs::read_dir: <https://doc.rust-lang.org/std/fs/fn.read_dir.html>
-/// * 闭包文档: <https://doc.rust-lang.org/rust-by-example/fn/closures.html>
-fn process_path<P, FF, EF>(p: P, re: &Regex, ff:&FF, ef: &EF) -> Result<(), Error>
-where
-    P: AsRef<Path>,
-    FF: Fn(&Path, Vec<Record>),
-    EF: Fn(Error),
-{
-    // 将输入路径转换为 Path 引用
-    let p = p.as_ref();
-
-    // 获取路径的元数据信息（文件类型、大小、权限等）
-    let md = p.metadata()?;
-
-    // 获取文件类型信息
-    let ft = md.file_type();
-
-    // 处理文件：如果是文件，直接搜索其内容
-    if ft.is_file() {
-        // 调用 process_file 处理文件内容
-        let dt = process_file(p, re)?;
-
-        // 调用文件处理回调函数，传递路径和匹配结果
-        ff(p, dt);
-    }
-
-    // 处理目录：如果是目录，递归遍历其中的所有条目
-    if ft.is_dir() {
-        // 读取目录内容，返回一个迭代器
-        let dd = std::fs::read_dir(p)?;
-
-        // 遍历目录中的每个条目
-        for d in dd {
-            // 获取目录条目（可能失败，使用 ? 操作符处理）
-            let entry = d?;
-
-            // 递归调用 process_path 处理子路径
-            // 如果递归调用失败，调用错误处理回调函数而不是直接返回错误
-            if let Err(e) = process_path(entry.path(), re, ff, ef) {
-                ef(e);
-            }
-        }
-    }
+    /// 按字面判断哪些行匹配，不运行正则表达式引擎，隐含 `-F`
+    ///
+    /// 用 memchr 的 SIMD 子串查找代替正则表达式，结果和 `-F` 完全相同，在匹配稀疏的大文件上更快。
+    /// 只能有一个模式，不能和内置模式、`--match-empty-lines`、模糊匹配、语音匹配或者词表一起使用。
+    ///
+    /// # 示例
+    /// * `--literal-match-only -p "connection reset" -f big.log`
+    #[arg(
+        long,
+        conflicts_with_all = ["extended_regex", "strip_pattern_comments", "rules", "fuzzy", "sound_like", "word_list",
+            "builtin_pattern", "ip_address", "match_empty_lines", "all_lines"]
+    )]
+    literal_match_only: bool,
 
-    // 返回成功
-    Ok(())
-}
-/// 主运行函数
-///
-/// 这个函数是程序的主要逻辑入口点，负责：
-/// 1. 解析命令行参数
-/// 2. 编译正则表达式
-/// 3. 调用路径处理函数
-/// 4. 处理结果和错误
-///
-/// # 返回值
-/// * `Ok(())` - 程序成功执行
-/// * `Err(Error)` - 执行过程中发生错误
-///
-/// # 错误处理
-/// 使用 failure 库的 Result 类型进行错误处理，
-/// 所有的 I/O 错误、正则表达式编译错误等都会被自动捕获
-///
-/// # 相关文档
-/// * Regex::new: <https://docs.rs/regex/latest/regex/struct.Regex.html#method.new>
-/// * Args::parse: <https://docs.rs/clap/latest/clap/trait.Parser.html#tymethod.parse>
-fn run() -> Result<(), Error> {
-    // 使用 clap 自动解析命令行参数
-    // 如果参数格式不正确，clap 会自动显示帮助信息并退出
-    let args = Args::parse();
+    /// 编译之前去掉模式中每一行没有转义的 `#` 之后的内容，再把各行连接起来
+    ///
+    /// 和 `-E` 不同，空白仍然是模式的一部分，只是每行首尾的空白被去掉。
+    /// `\#` 和字符集合中的 `#`（例如 `[#]`）不是注释。主要用于 `--pattern-file` 中分成多行写的模式。
+    ///
+    /// # 示例
+    /// * `--strip-pattern-comments --pattern-file version.re -f .`
+    #[arg(long, conflicts_with_all = ["fuzzy", "sound_like"])]
+    strip_pattern_comments: bool,
 
-    // 编译用户提供的正则表达式模式
-    // 如果正则表达式语法错误，这里会返回编译错误
-    let re = Regex::new(&args.pattern)?;
+    /// 编译之前把模式中的 `$VAR` 和 `${VAR}` 替换成环境变量的值
+    ///
+    /// 变量名由字母、数字和下划线组成，不以数字开头；`$` 之后不是变量名时（例如行尾锚点 `foo$`）保持原样。
+    /// `\$` 和其他转义一样原样保留，在正则表达式中匹配字面的 `$`。变量的值按正则表达式插入，不做转义。
+    /// 没有定义的变量是错误，除非同时使用 `--expand-env-empty`。
+    /// 对 `-p`、位置参数给出的模式和 `--pattern-file` 中的模式都生效，不影响 `--builtin-pattern`。
+    ///
+    /// # 示例
+    /// * `--expand-env -p '$USER logged in' -f /var/log/auth.log` - 在脚本中搜索当前用户的登录记录
+    #[arg(long, conflicts_with_all = ["rules", "word_list", "all_lines"])]
+    expand_env: bool,
 
-    // 调用递归路径处理函数
-    // 使用闭包作为回调函数来处理文件处理结果和错误
+    /// `--expand-env` 时没有定义的环境变量替换成空字符串，而不是报错
+    #[arg(long, requires = "expand_env")]
+    expand_env_empty: bool,
 
-    // 注释掉的代码：处理单个文件的方式
-    //let p = process_file(args.file, &re);
+    /// 一行必须匹配所有的模式才输出，而不是任意一个
+    ///
+    /// 每个 `-p`、`--pattern-file` 中的每一行和每个 `--builtin-pattern` 各算一个模式。
+    /// 只有一个模式时和不使用这个选项相同。
+    ///
+    /// # 示例
+    /// * `--match-all -p ERROR -p timeout -f logs` - 同时含有 ERROR 和 timeout 的行
+    #[arg(long, overrides_with = "match_any", conflicts_with_all = ["fuzzy", "sound_like", "word_list", "rules", "filter", "tui"])]
+    match_all: bool,
 
-    // 实际使用的代码：处理路径（文件或目录）的方式
-    let p = process_path(
-        // 要处理的路径
-        args.file,
-        // 编译好的正则表达式
-        &re,
+    /// 任意一个模式匹配就输出，这是默认的行为，用来覆盖 profile 中的 `--match-all`
+    #[arg(long, overrides_with = "match_all")]
+    match_any: bool,
 
-        // 文件处理完成回调函数
-        // 这个闭包会在每个文件处理完成后被调用
-        &|pt, v| {
-            println!("文件路径: {:?}", pt);
-            println!("匹配结果: {:?}", v);
-        },
+    /// `--match-all` 时用 N 个线程同时检查各个模式
+    ///
+    /// 模式被分成 N 组，每组在一个线程中检查所有候选行，结果和逐个检查相同。
+    /// 模式很多、匹配的行也很多时才有明显的效果，每个文件的候选行少于 64 行时不会创建线程。
+    ///
+    /// # 示例
+    /// * `--match-all --parallel-regex 4 --pattern-file checks.txt -f big.log`
+    #[arg(long, value_name = "N", requires = "match_all", value_parser = clap::value_parser!(u32).range(1..))]
+    parallel_regex: Option<u32>,
 
-        // 错误处理回调函数
-        // 这个闭包会在处理过程中发生错误时被调用
-        &|e| {
-            println!("处理错误: {}", e);
-        }
-    );
+    /// 模糊匹配：查找与模式编辑距离不超过 MAX_EDITS 的行
+    ///
+    /// 开启后模式会被当作普通文本而不是正则表达式，
+    /// 只要行中存在某个子串与模式的 Levenshtein 距离 ≤ MAX_EDITS 即视为匹配。
+    /// 这比正则匹配慢得多，大目录下建议配合 `--fuzzy-cpu-limit` 使用。
+    ///
+    /// # 示例
+    /// * `-p "recieve" --fuzzy 1` - 可以找到拼写正确的 "receive"
+    #[arg(long, visible_alias = "approximate", value_name = "MAX_EDITS")]
+    fuzzy: Option<usize>,
 
-    // 输出整体处理结果
-    // 这里的 Result 表示整个处理过程是否成功
-    println!("整体处理结果: {:?}", p);
+    /// 模糊匹配的时间上限（秒）
+    ///
+    /// 从搜索开始计时，超时后停止搜索并报告错误。只在 `--fuzzy` 模式下生效。
+    #[arg(long, value_name = "SEC", requires = "fuzzy")]
+    fuzzy_cpu_limit: Option<f64>,
 
-    // 返回成功
-    Ok(())
-}
+    /// 按发音匹配：查找读音与模式相同的单词
+    ///
+    /// 模式和每一行都按非字母字符切分成单词，计算每个单词的语音编码，
+    /// 行中存在连续的单词与模式的单词编码依次相同即视为匹配。只处理 ASCII 字母。
+    ///
+    /// # 示例
+    /// * `-p Jeffrey --sound-like` - 也能找到 "Jeffery"
+    #[arg(long, conflicts_with = "fuzzy")]
+    sound_like: bool,
 
-/// 程序主入口函数
-///
-/// 这是程序的入口点，负责：
-/// 1. 调用主运行函数
-/// 2. 处理顶层错误并优雅退出
-///
-/// # 错误处理模式
-/// 使用 Rust 推荐的错误处理模式：
-/// - 使用 if let Err(e) 来检查 Result
-/// - 打印友好的错误信息给用户
-/// - 程序以非零状态码退出（通过 panic! 或 std::process::exit）
-///
-/// # 设计原则
-/// 这种设计遵循了 Rust 的最佳实践：
-/// 1. 将核心逻辑与错误处理分离
-/// 2. main 函数保持简洁
-/// 3. 提供清晰的错误信息
-///
-/// # 相关文档
-/// * main 函数文档: <https://doc.rust-lang.org/std/fn.main.html>
-/// * 错误处理指南: <https://doc.rust-lang.org/book/ch09.html>
-fn main() {
-    // 调用主运行函数并处理可能发生的错误
-    // 这种模式确保程序在遇到错误时能够优雅地退出
-    if let Err(e) = run() {
-        // 打印用户友好的错误信息
-        println!("程序执行时发生错误: {}", e);
+    /// `--sound-like` 使用的语音编码算法
+    #[arg(long, value_enum, value_name = "ALGO", default_value = "metaphone")]
+    phonetic_algo: PhoneticMode,
+
+    /// 用模板替换每个匹配，输出替换后的行（不修改文件）
+    ///
+    /// 除了 `$1`、`${name}` 分组引用外，还支持大小写转换运算符：
+    /// - `\U` / `\L`: 之后的文本转为大写 / 小写，直到 `\E` 或模板结束
+    /// - `\u` / `\l`: 只转换紧随其后的一个字符
+    /// - `\E`: 结束 `\U` / `\L`；`\\` 和 `$$` 分别表示字面的 `\` 和 `$`
+    /// - `{{n}}`: 这是第几次替换，从 1 开始在整个运行中递增；`{{n:04}}` 补 0 到 4 位
+    ///
+    /// 转换的作用范围不会延续到下一个匹配。模板在启动时校验，
+    /// 不支持的运算符或者引用不存在的分组会直接报错。
+    /// 使用 `{{n}}` 时目录中的文件按名字排序后依次搜索，同样的文件树每次得到同样的编号。
+    ///
+    /// # 示例
+    /// * `-p "get_(\w+)" -r "get\u$1"` - 把 get_name 改写为 getName
+    /// * `-p "(\w+)_id" -r "\U$1\E_ID"` - 把 user_id 改写为 USER_ID
+    /// * `-p "TODO" --write-replace "TODO-{{n:03}}" -f src` - 给每个 TODO 编号
+    #[arg(short = 'r', long, value_name = "TEMPLATE", conflicts_with_all = ["fuzzy", "sound_like"])]
+    replace: Option<String>,
+
+    /// 在每个匹配之前插入 TEXT，匹配本身保留
+    ///
+    /// TEXT 是和 `--replace` 语法相同的模板，可以引用分组。单独使用时相当于 `-r '<TEXT>$0'`，
+    /// 同时使用 `-r` 或 `--write-replace` 时插入到替换结果之前。
+    /// 和替换一样用于输出、`--patch`、`--diff` 和 `--write-replace`。
+    ///
+    /// # 示例
+    /// * `-p TODO --insert-before "<mark>" --insert-after "</mark>" -f notes` - 预览加上标记的行
+    /// * `-p "fn (\w+)" --insert-before "/* $1 */ " --write-replace -f src` - 直接修改文件
+    #[arg(long, value_name = "TEXT", conflicts_with_all = ["fuzzy", "sound_like"])]
+    insert_before: Option<String>,
+
+    /// 在每个匹配之后插入 TEXT，匹配本身保留，用法和 `--insert-before` 相同
+    #[arg(long, value_name = "TEXT", conflicts_with_all = ["fuzzy", "sound_like"])]
+    insert_after: Option<String>,
+
+    /// 每一行替换之后的结果都必须匹配 REGEX，否则报告 `文件:行号` 和替换结果并停止
+    ///
+    /// 用来防止模板写错而产生格式不对的内容，例如改版本号之后检查它仍然是 `x.y.z` 的形式。
+    /// 检查在计算出每一行的替换结果之后立即进行，作用于 `-r`、`--write-replace`、`--patch`、`--diff` 和 `--filter`。
+    /// 停止时出错的文件和之后的文件都不会被修改，之前已经改写的文件不会恢复，
+    /// 修改很多文件之前可以先用 `--diff` 检查一遍。REGEX 和整个替换后的行比较，需要整行符合时加上 `^...$`。
+    ///
+    /// # 示例
+    /// * `-p 'version = "[^"]*"' --write-replace 'version = "2.0.0"' --replace-verify '^version = "\d+\.\d+\.\d+"$' -f Cargo.toml`
+    #[arg(long, value_name = "REGEX", conflicts_with_all = ["fuzzy", "sound_like"])]
+    replace_verify: Option<String>,
+
+    /// `--replace-verify` 检查失败时给出警告并保留原来的行，继续处理其他的行
+    #[arg(long, requires = "replace_verify", conflicts_with = "expect_matches")]
+    replace_verify_skip: bool,
+
+    /// 替换模板中的 `{{n}}` 在每个文件中重新从 1 开始，用于 `--replace`、`--write-replace` 等所有的替换模板
+    #[arg(long)]
+    counter_per_file: bool,
+
+    /// 以统一差异格式（unified diff）输出 `--replace` 的结果，而不是匹配列表
+    ///
+    /// 输出可以直接交给 `git apply` 或 `patch -p1` 来修改文件。
+    /// 补丁头部使用相对于当前目录的 `a/PATH` / `b/PATH`，每个修改块带 3 行上下文。
+    /// 这个模式下错误信息输出到标准错误，标准输出中只有补丁本身。
+    ///
+    /// # 示例
+    /// * `-p "get_(\w+)" -r "get\u$1" -f src --patch | git apply` - 批量重命名
+    #[arg(long, requires = "replacement")]
+    patch: bool,
+
+    /// 用模板替换每个匹配，并直接改写文件
+    ///
+    /// 模板的语法和 `--replace` 相同，只用 `--insert-before` / `--insert-after` 时可以省略，相当于 `$0`。
+    /// 每个包含匹配的文件先写入同一目录下的临时文件，
+    /// 再原子地重命名覆盖原文件，权限（以 root 运行时还有所有者）保持不变；
+    /// 替换后内容没有变化的文件不会被重写。每个修改过的文件输出一行 `路径: N 处替换`，
+    /// 最后输出修改的文件总数和替换总数。
+    /// 二进制文件（含有 NUL 字节）和不是 UTF-8 的文件跳过并给出警告，见 `--force-write`。
+    /// 建议先用 `-r TEMPLATE --patch` 检查一遍结果。
+    ///
+    /// # 示例
+    /// * `-p "get_(\w+)" --write-replace "get\u$1" -f src --backup .orig`
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        num_args = 0..=1,
+        default_missing_value = "$0",
+        conflicts_with_all = ["replace", "fuzzy", "sound_like", "interactive", "group_by", "archives", "pre", "follow_lines"]
+    )]
+    write_replace: Option<String>,
+
+    /// 只预览 `--write-replace` 的修改：每个会被修改的文件输出一个统一差异格式的补丁，不写任何文件
+    ///
+    /// 补丁和 `--patch` 的格式相同，修改的文件数和替换总数输出到标准错误。
+    /// 有文件会被修改时以状态 1 退出，没有时以状态 0 退出，可以在 CI 中检查代码里是否还有要替换的内容。
+    /// 标准输出是终端时补丁带有颜色，见 `--color`。
+    ///
+    /// # 示例
+    /// * `-p "get_(\w+)" --write-replace "get\u$1" -f src --diff` - 先看看会改些什么
+    #[arg(long, requires = "write_replace", conflicts_with = "backup")]
+    diff: bool,
+
+    /// 什么时候在输出中使用颜色
+    ///
+    /// 颜色用在搜索结果的路径、行号和匹配到的文本上（见 `--match-highlight-style`），
+    /// 以及 `--diff` 和 `--patch` 的补丁中。`auto` 在标准输出是终端时使用颜色。
+    /// 加了颜色的补丁不能再交给 `git apply`。
+    #[arg(long, value_enum, value_name = "WHEN", default_value = "auto")]
+    color: ColorChoice,
+
+    /// 输出中各个部分的颜色和字体，写法类似 CSS
+    ///
+    /// 部分之间用逗号分隔，每个部分写成 `部分:属性;属性`。
+    /// 部分可以是 `match`、`filename`、`lineno`、`context`（`--tui` 的上下文行和 `--highlight-context-lines`）；
+    /// 属性可以是 `fg=颜色`、`bg=颜色`、`bold`、`dim`、`italic`、`underline`、`reverse` 和 `none`。
+    /// 颜色是 black、red、green、yellow、blue、magenta、cyan、white，加 `bright-` 前缀的亮色，或者 0-255 的编号。
+    /// 没有写到的部分保持默认样式（和 GNU grep 相同）。只在使用颜色时生效，见 `--color`。
+    ///
+    /// # 示例
+    /// * `--match-highlight-style "match:fg=red;bold, filename:fg=cyan, lineno:fg=green;dim"`
+    /// * `--match-highlight-style "match:reverse"` - 用反色标出匹配
+    #[arg(long, value_name = "STYLE")]
+    match_highlight_style: Option<String>,
+
+    /// 所有高亮样式都不使用粗体
+    #[arg(long)]
+    no_bold: bool,
+
+    /// 用 `--match-highlight-style` 的 `context` 样式（默认是暗淡的 `\x1b[2m`）显示 `-A` / `-B` 的上下文行
+    ///
+    /// 匹配行照常用 `match` 样式标出匹配的文本，上下文行整行变暗，一眼就能分出哪些行是匹配。
+    /// 只在使用颜色时生效，见 `--color`。
+    ///
+    /// # 示例
+    /// * `-B 2 -A 2 --highlight-context-lines --color always -p ERROR -f app.log | less -R`
+    #[arg(long)]
+    highlight_context_lines: bool,
+
+    /// `--write-replace` 覆盖文件之前把原文件复制为 `文件名SUFFIX`，例如 `--backup .bak`
+    #[arg(long, value_name = "SUFFIX", requires = "write_replace")]
+    backup: Option<String>,
+
+    /// `--write-replace` 的替换总数必须正好是 N，否则一个文件都不修改
+    ///
+    /// 先为所有文件计算替换结果（这期间所有文件的新旧内容都保存在内存中），
+    /// 总数等于 N 时才写入；不等时报告预期和实际的数量，以状态 1 退出。
+    /// 用在 CI 中，防止模式意外地匹配了比预想多得多的内容。
+    ///
+    /// # 示例
+    /// * `-p "OldName" --write-replace NewName -f src --expect-matches 12`
+    #[arg(long, value_name = "N", requires = "write_replace", conflicts_with_all = ["diff", "watch"])]
+    expect_matches: Option<usize>,
+
+    /// `--write-replace` 也改写二进制文件和不是 UTF-8 的文件
+    ///
+    /// 不是 UTF-8 的文件按 latin1 逐字节搜索和替换，其余字节原样写回；
+    /// 替换结果中有 latin1 无法表示的字符时这个文件报告错误，不会被修改。
+    #[arg(long, requires = "write_replace")]
+    force_write: bool,
+
+    /// 流过滤模式：把标准输入的每一行按 `--replace` 替换后写到标准输出
+    ///
+    /// 相当于 `sed 's/PATTERN/TEMPLATE/g'`：没有匹配的行也原样输出，输出中没有文件名和行号，
+    /// 每行的行尾逐字节保持不变。每次只读入一行，可以处理任意长度的输入。
+    /// 不是 UTF-8 的行不参与匹配，原样输出。有行被替换时以状态 0 退出，否则以状态 1 退出。
+    /// 不能再指定要搜索的路径。
+    ///
+    /// # 示例
+    /// * `cat config | pgrep --filter -p 'host=(\S+)' -r 'host=REDACTED'` - 隐去配置中的主机名
+    #[arg(
+        long,
+        requires = "replacement",
+        conflicts_with_all = ["file", "patch", "word_list", "interactive", "group_by", "watch", "follow_lines",
+            "exec", "exec_file", "exec_batch", "checkpoint", "archives"]
+    )]
+    filter: bool,
+
+    /// 从文件读取词表（每行一个词），匹配包含其中任意一个词的行
+    ///
+    /// 所有词被编译成一个 Aho-Corasick 自动机，扫描一遍就能同时匹配全部的词，
+    /// 耗时与文件大小成正比，而与词的数量基本无关，适合成千上万个关键词的场景。
+    /// 词按字面文本处理，不支持正则语法。
+    ///
+    /// # 示例
+    /// * `--word-list pii.txt -f ./data` - 查找包含任意敏感词的行
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["pattern", "fuzzy", "sound_like", "replace", "insert_before", "insert_after"])]
+    word_list: Option<PathBuf>,
+
+    /// 使用内置模式，可以重复指定，也可以和 `-p` 同时使用
+    ///
+    /// 多个模式之间是"或"的关系：行中出现任意一个即视为匹配。
+    /// 可选的名字: ip-address, email, url, uuid, credit-card
+    ///
+    /// # 示例
+    /// * `--builtin-pattern email -f ./logs` - 查找包含邮箱地址的行
+    /// * `--builtin-pattern uuid -p "request_id="` - 查找 UUID 或者 "request_id="
+    #[arg(long, value_enum, value_name = "NAME", conflicts_with_all = ["fuzzy", "sound_like", "word_list"])]
+    builtin_pattern: Vec<BuiltinPattern>,
+
+    /// 查找 IPv4 或 IPv6 地址，等价于 `--builtin-pattern ip-address`
+    #[arg(long, conflicts_with_all = ["fuzzy", "sound_like", "word_list"])]
+    ip_address: bool,
+
+    /// 词表匹配时忽略 ASCII 字母的大小写
+    #[arg(long, requires = "word_list")]
+    word_list_case_insensitive: bool,
+
+    /// 词表中的词必须作为完整的单词出现（前后是单词边界）
+    #[arg(long, requires = "word_list")]
+    word_list_whole_word: bool,
+
+    /// 激活配置文件中定义的命名 profile
+    ///
+    /// profile 中的选项作为默认值，命令行上显式给出的选项会覆盖它们。
+    ///
+    /// # 示例
+    /// * `--profile logs -p ERROR` - 使用 `[profile.logs]` 中的设置搜索 ERROR
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// 指定配置文件，代替默认位置的配置文件
+    ///
+    /// 默认读取 `~/.config/pgrep/config.toml` 和当前目录下的 `.pgrep.toml`
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// 列出所有已定义的 profile 及定义它们的文件
+    #[arg(long)]
+    list_profiles: bool,
+
+    /// 从规则文件读取"路径通配符 → 模式"的规则，每个文件只用适用于它的规则搜索
+    ///
+    /// 用于在一次搜索中对不同的目录使用不同的模式，例如 `src/` 下的 rs 文件查找一个模式，
+    /// `scripts/` 下的 py 文件查找另一个模式。规则文件的格式:
+    ///
+    /// ```toml
+    /// [[rule]]
+    /// id = "rust-unsafe"
+    /// paths = ["src/**/*.rs"]
+    /// pattern = 'unsafe\s*\{'
+    /// message = "新代码中不要使用 unsafe"
+    /// severity = "error"
+    /// case_insensitive = false
+    /// ```
+    ///
+    /// 只有 `pattern` 是必需的；省略 `paths` 的规则适用于所有文件，`severity` 默认是 warning。
+    /// id 不能重复，每条规则的模式在搜索之前编译，出错时报告是哪一条规则。
+    ///
+    /// 一个文件匹配多条规则时所有匹配的规则都生效，一行匹配其中任意一个模式就输出；
+    /// 没有任何规则适用的文件不搜索。含有 `/` 的通配符和命令行上给出的路径加上子路径比较：
+    /// `-f .` 时看到的是 `./src/a.rs`（开头的 `./` 被忽略），可以匹配 `src/**/*.rs`；
+    /// `-f /repo` 时看到的是 `/repo/src/a.rs`，需要写成 `**/src/**/*.rs`。
+    ///
+    /// 输出的每一行是 `路径:行号:列号: 严重程度[id]: message`，一行匹配了几条规则就输出几行，
+    /// 没有 message 的规则输出匹配的行；`--format json` 的对象多了 rule、severity 和 message，
+    /// `--format sarif` 和 `--format github` 带有规则的 id 和说明。
+    /// 有 error 级别的匹配时退出状态是 1。
+    ///
+    /// # 示例
+    /// * `--rules scan.toml -f .` - 按 scan.toml 中的规则搜索当前目录
+    /// * `--rules policy.toml --format sarif -f . > pgrep.sarif` - 上传到代码扫描的结果
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["pattern", "word_list", "builtin_pattern", "ip_address", "fuzzy", "sound_like", "replace", "insert_before", "insert_after", "match_empty_lines", "record_format"])]
+    rules: Option<PathBuf>,
+
+    /// 输出 shell 的补全脚本后退出
+    ///
+    /// 可选的 shell: bash, zsh, fish, powershell, elvish。脚本补全所有选项、
+    /// 枚举类型选项的取值以及文件路径。默认输出到标准输出，见 `--completions-output`。
+    ///
+    /// # 示例
+    /// * `--generate-completions bash > /etc/bash_completion.d/pgrep`
+    /// * `--generate-completions fish --completions-output ~/.config/fish/completions/pgrep.fish`
+    #[arg(long, value_enum, value_name = "SHELL")]
+    generate_completions: Option<completions::Shell>,
+
+    /// 把 `--generate-completions` 的脚本写入 FILE 而不是标准输出
+    #[arg(long, value_name = "FILE", requires = "generate_completions")]
+    completions_output: Option<PathBuf>,
+
+    /// 输出 roff 格式的 man 手册后退出
+    ///
+    /// 手册中有所有选项的完整说明、可选的值和默认值，
+    /// 各个选项说明中的例子汇总在 EXAMPLES 一节。默认输出到标准输出，见 `--man-output`。
+    ///
+    /// # 示例
+    /// * `--generate-man-page | man -l -` - 不安装直接查看
+    /// * `--generate-man-page --man-output /usr/local/share/man/man1/pgrep.1`
+    #[arg(long, conflicts_with = "generate_completions")]
+    generate_man_page: bool,
+
+    /// 把 `--generate-man-page` 的手册写入 FILE 而不是标准输出
+    #[arg(long, value_name = "FILE", requires = "generate_man_page")]
+    man_output: Option<PathBuf>,
+
+    /// man 手册的章节号
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "generate_man_page")]
+    man_section: u8,
+
+    /// 输出效果相同的 ripgrep（`rg`）命令后退出，不执行搜索
+    ///
+    /// 在 rg 中没有对应、但不影响匹配结果的选项（例如 `-v`）在标准错误中给出警告；
+    /// 改变匹配结果或者执行额外操作的选项（例如 `--fuzzy`、`--rules`、`--exec`）无法翻译，报告错误。
+    /// `--profile` 展开后的参数也会被翻译。
+    ///
+    /// # 示例
+    /// * `--print-rg-command -p "TODO|FIXME" -f src` - 输出 `rg ... -e 'TODO|FIXME' -- src`
+    #[arg(long, alias = "convert-to-ripgrep-args")]
+    print_rg_command: bool,
+
+    /// 位置参数：[PATTERN] [PATH]...
+    ///
+    /// 没有给出 `-p` / `--word-list` 时，第一个位置参数是模式，其余的都是要搜索的路径；
+    /// 给出了 `-p` 时，所有位置参数都是路径，并和 `-f` 一起被搜索。
+    ///
+    /// 单独的 `-` 在模式的位置上就是模式本身（搜索字符 "-"），
+    /// 在路径的位置上（包括 `-f -`）表示标准输入。
+    ///
+    /// 以 `-` 开头的模式要放在 `--` 之后，`--` 之后的参数不会再被当作选项。
+    ///
+    /// # 示例
+    /// * `pgrep TODO src` - 等价于 `pgrep -p TODO -f src`
+    /// * `pgrep -- "-->" src` - 搜索 "-->"
+    /// * `pgrep -- - notes.txt` - 搜索字符 "-"
+    #[arg(value_name = "ARGS")]
+    args: Vec<String>,
+
+    /// 对每个匹配（每一行）执行一次命令
+    ///
+    /// 命令中可以使用以下占位符：
+    /// - `{path}`: 文件路径（`{}` 是它的简写）
+    /// - `{line}` / `{column}`: 从 1 开始的行号和列号（按字符计算）
+    /// - `{text}`: 整行文本
+    /// - `{match}`: 匹配到的文本
+    ///
+    /// 命令不经过 shell，而是先按空白切分成参数再替换占位符，
+    /// 所以替换进去的文本中即使有空格或引号也不会被拆开或解释。
+    /// 没有任何占位符时路径附加在命令末尾。命令按顺序逐个执行，不会并发。
+    /// 命令失败时报告错误并继续搜索，见 `--exec-halt-on-error`。
+    ///
+    /// # 示例
+    /// * `-p "TODO\(bob\)" -f src --exec "notify {path}:{line}"`
+    #[arg(long, value_name = "CMD")]
+    exec: Option<String>,
+
+    /// 对每个包含匹配的文件执行一次命令
+    ///
+    /// 命令中的 `{path}`（或 `{}`）会被替换为文件路径；没有占位符时路径附加在命令末尾。
+    /// 命令不经过 shell，而是按空白切分成参数，可以用引号包含空格。
+    ///
+    /// # 示例
+    /// * `--exec-file "cp {} /tmp/hits/"` - 把匹配的文件复制到 /tmp/hits
+    /// * `--exec-file "clang-format -i {path}"` - 格式化包含匹配的文件
+    #[arg(long, value_name = "CMD")]
+    exec_file: Option<String>,
+
+    /// 搜索结束后把所有包含匹配的文件一次性传给命令（类似 xargs）
+    ///
+    /// `{}` 会被展开为全部路径（每个路径一个参数）；没有 `{}` 时路径附加在命令末尾。
+    /// 没有任何匹配文件时不会执行命令。
+    ///
+    /// # 示例
+    /// * `--exec-batch "vim -p"` - 在 vim 的标签页中打开所有匹配的文件
+    #[arg(long, value_name = "CMD")]
+    exec_batch: Option<String>,
+
+    /// `--exec` / `--exec-file` / `--exec-batch` 的命令失败时停止整个搜索
+    #[arg(long)]
+    exec_halt_on_error: bool,
+
+    /// 把搜索进度写入检查点文件，配合 `--resume` 在中断后继续搜索
+    ///
+    /// 每搜索完一个顶层目录（搜索路径下的直接子目录），就把已完成的目录列表写入 FILE。
+    /// 写入时先写临时文件再重命名，即使中途崩溃检查点文件也不会损坏。
+    /// 整个搜索正常结束后检查点文件会被删除。
+    ///
+    /// # 示例
+    /// * `-f /archive --checkpoint /tmp/ck` - 记录进度
+    /// * `-f /archive --checkpoint /tmp/ck --resume` - 跳过上次已经完成的目录
+    #[arg(long, value_name = "FILE")]
+    checkpoint: Option<PathBuf>,
+
+    /// 只匹配以 PREFIX 开头的行，可以重复指定，行以其中任意一个开头即可
+    ///
+    /// 前缀比较在正则表达式之前进行，其他行直接跳过，适合只关心特定级别的结构化日志。
+    /// 被前缀排除的行不参与任何匹配，之后的其他过滤也看不到它们。
+    ///
+    /// # 示例
+    /// * `--line-prefix "ERROR " --line-prefix "WARN " -p timeout -f app.log`
+    #[arg(long, value_name = "PREFIX")]
+    line_prefix: Vec<String>,
+
+    /// 跳过以 PREFIX 开头的行，可以重复指定，和 `--line-prefix` 同时使用时两个条件都要满足
+    ///
+    /// # 示例
+    /// * `--skip-prefix "DEBUG " --skip-prefix "#" -p user -f app.log`
+    #[arg(long, value_name = "PREFIX")]
+    skip_prefix: Vec<String>,
+
+    /// 搜索压缩文件的内容
+    ///
+    /// 先按文件开头的魔数、再按扩展名识别压缩格式，解压后再搜索，
+    /// 输出中的路径仍然是压缩文件本身，行号是解压后内容的行号。
+    /// gzip（`.gz` / `.tgz`）由内置的解压实现处理；bzip2（`.bz2`）、xz（`.xz`）和 zstd（`.zst`）
+    /// 交给 PATH 中的 `bzip2`、`xz`、`zstd` 程序解压，找不到程序时报告为这个文件的错误。
+    /// 损坏或者不支持的压缩文件作为这个文件的错误报告出来，不影响其他文件。
+    /// 不指定时压缩文件会被跳过（见 `--debug-skip`）。
+    ///
+    /// # 示例
+    /// * `-z -p "OutOfMemory" -f /var/log/app` - 同时搜索 app.log 和 app.log.1.gz
+    #[arg(short = 'z', long)]
+    search_zip: bool,
+
+    /// 搜索 tar 和 zip 包中的每个文件
+    ///
+    /// 支持 `.tar`、`.tar.gz` / `.tgz` 和 `.zip`（不压缩或 DEFLATE 压缩的成员）。
+    /// 结果中的路径是 `包的路径!成员名`，例如 `backups/snap.tar.gz!etc/config:12:...`，
+    /// 所有输出格式以及 `--exec` 系列命令的 `{path}` 中都是这个形式。
+    /// 包中的符号链接、目录等没有内容的成员会被跳过（见 `--debug-skip`），
+    /// 加密的 zip 成员跳过并给出警告。`.tar.gz` 即使没有 `-z` 也会被解压，
+    /// 其他压缩文件仍然需要 `-z` 才会被搜索。
+    /// 虚拟路径无法在编辑器中打开，也无法生成补丁，所以不能和 `--interactive`、`--patch` 同时使用。
+    ///
+    /// # 示例
+    /// * `--archives -p "listen_port" -f backups/` - 搜索备份目录中的所有包
+    #[arg(long, conflicts_with_all = ["interactive", "patch"])]
+    archives: bool,
+
+    /// 最多打开几层嵌套的包，默认只打开磁盘上的包，不打开包中的包
+    ///
+    /// 限制层数可以防止层层嵌套的压缩炸弹。超过层数的包被跳过（见 `--debug-skip`）。
+    #[arg(long, value_name = "N", default_value_t = pgrep::DEFAULT_MAX_ARCHIVE_DEPTH, requires = "archives")]
+    max_archive_depth: usize,
+
+    /// 包中单个文件解压后的最大字节数，超过的文件被跳过，默认 100 MiB
+    #[arg(long, value_name = "BYTES", default_value_t = pgrep::DEFAULT_MAX_MEMBER_SIZE, requires = "archives")]
+    max_member_size: u64,
+
+    /// 匹配空行（长度为 0 的行），相当于 `-p '^$'`
+    ///
+    /// 和 `-p` 同时使用时两者是"或"的关系：空行和匹配模式的行都会输出。
+    /// 单独使用时不需要给出模式，第一个位置参数就是要搜索的路径。
+    /// 只有空白字符的行不是空行。
+    ///
+    /// # 示例
+    /// * `--match-empty-lines -f notes.txt` - 找出所有空行的位置
+    #[arg(long, visible_alias = "blank-lines", conflicts_with_all = ["fuzzy", "sound_like", "word_list"])]
+    match_empty_lines: bool,
+
+    /// 选中每一行，不需要模式，也不运行正则表达式
+    ///
+    /// 空模式 `-p ""` 能匹配每一行，但几乎总是手误，所以会被拒绝；真的需要每一行时使用这个选项。
+    /// 单独使用时第一个位置参数就是要搜索的路径。`--line-prefix`、`--skip-empty-lines` 等行过滤照常生效，
+    /// 和 `-c` 一起使用时统计的是行数。（`--match-all` 是另一个选项：一行要匹配所有的模式。）
+    ///
+    /// # 示例
+    /// * `--all-lines -c -f src/main.rs` - 数出文件的行数
+    /// * `--all-lines --skip-prefix "#" -f config.ini` - 去掉注释之后的所有行
+    #[arg(
+        long,
+        conflicts_with_all = ["pattern", "pattern_file", "word_list", "builtin_pattern", "ip_address", "rules", "fuzzy",
+            "sound_like", "match_empty_lines", "match_all", "replacement", "write_replace", "only_matching", "tui"]
+    )]
+    all_lines: bool,
+
+    /// 只输出之后 `--context-after` 行之内还有一行匹配 PATTERN 的结果
+    ///
+    /// 例如 `-p ERROR --context-pattern FATAL --context-after 5` 只输出之后 5 行之内出现了 FATAL 的 ERROR。
+    /// 只用来筛选，之后的这几行本身不会被输出。检查的是原始的行，`--line-prefix` 之类的行过滤不影响检查的范围，
+    /// 匹配行本身不算在窗口之内。PATTERN 是独立的正则表达式，不受 `-E`、`--word-list` 等选项的影响。
+    ///
+    /// # 示例
+    /// * `-p "retrying" --context-pattern "gave up" --context-after 3 -f app.log` - 最终失败了的重试
+    #[arg(long, value_name = "PATTERN", requires = "context_after", conflicts_with_all = ["follow_lines", "filter"])]
+    context_pattern: Option<String>,
+
+    /// `--context-pattern` 检查每个匹配之后的多少行
+    #[arg(long, value_name = "N", requires = "context_pattern")]
+    context_after: Option<usize>,
+
+    /// 在每个匹配之前输出 N 行上下文，和 grep 的 `-B` 相同
+    ///
+    /// 上下文行写成 `路径-行号-内容`，和匹配行的 `路径:行号:内容` 区分开，不相邻的两组结果之间输出一行 `--`。
+    /// 相邻的匹配的上下文重叠时每一行只输出一次；被抑制、跳过或者筛掉的匹配所在的行也可以作为上下文出现。
+    /// 只用于默认的逐行输出，`--highlight-context-lines` 用另一种样式显示上下文行。
+    ///
+    /// # 示例
+    /// * `-B 2 -p "panicked at" -f test.log` - 每个 panic 和它之前的 2 行
+    #[arg(
+        short = 'B',
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        conflicts_with_all = ["count", "count_mode", "count_unique_matches", "frequency_analysis", "top", "count_exit",
+            "output_sorted_unique_texts", "only_matching", "interactive", "confirm", "patch", "diff", "group_by",
+            "write_replace", "follow_lines", "watch", "tui", "filter", "rules", "format"]
+    )]
+    before_context: usize,
+
+    /// 在每个匹配之后输出 N 行上下文，和 grep 的 `-A` 相同
+    ///
+    /// 写法和 `--before-context` 相同。和只用来筛选的 `--context-after` 不同，这些行会被输出。
+    ///
+    /// # 示例
+    /// * `-A 3 -p "^Traceback" -f app.log` - 每个异常和之后的 3 行
+    #[arg(
+        short = 'A',
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        conflicts_with_all = ["count", "count_mode", "count_unique_matches", "frequency_analysis", "top", "count_exit",
+            "output_sorted_unique_texts", "only_matching", "interactive", "confirm", "patch", "diff", "group_by",
+            "write_replace", "follow_lines", "watch", "tui", "filter", "rules", "format"]
+    )]
+    after_context: usize,
+
+    /// 每行按 CHAR 分成字段，每个字段单独和模式比较，有一个字段匹配这一行就算匹配
+    ///
+    /// `^`、`$` 是字段的开头和结尾，例如 `-p '^-$' --field-separator ' '` 找有一个字段正好是 `-` 的行。
+    /// 用 `--field-number` 或 `--field-range` 只检查一部分字段。只按分隔符切分，不处理 CSV 的引号，
+    /// 相邻的两个分隔符之间是一个空字段；制表符在 shell 中写作 `$'\t'`。
+    /// 默认输出整行，`--only-field` 只输出匹配的字段。
+    /// 高亮、`-o` 和 `-r` 的替换在输出的文本上查找匹配，输出整行时也可能找到其他字段中的匹配。
+    ///
+    /// # 示例
+    /// * `-p '^admin$' --field-separator , --field-number 3 -f users.csv` - 第 3 列是 admin 的用户
+    /// * `-p 5.. --field-separator $'\t' --field-number 2 --only-field -f access.tsv` - 第 2 列中的 5xx 状态码
+    #[arg(
+        long,
+        value_name = "CHAR",
+        conflicts_with_all = ["all_lines", "follow_lines", "filter", "write_replace", "patch", "diff"]
+    )]
+    field_separator: Option<char>,
+
+    /// 只用第 N 个字段（从 1 开始）匹配，字段不够 N 个的行不匹配
+    #[arg(
+        long,
+        visible_alias = "field",
+        value_name = "N",
+        requires = "field_separator",
+        conflicts_with = "field_range",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    field_number: Option<u32>,
+
+    /// 只用第 START 到第 END 个字段（都包括在内）匹配，写成 `START-` 时直到行尾
+    ///
+    /// 字段不够 END 个的行只检查存在的字段，不够 START 个的行不匹配。
+    ///
+    /// # 示例
+    /// * `-p error --field-separator $'\t' --field-range 3-5 -f app.tsv`
+    #[arg(long, value_name = "START-END", requires = "field_separator")]
+    field_range: Option<String>,
+
+    /// 按字段匹配时只输出匹配的字段而不是整行，有几个字段匹配时输出第一个
+    #[arg(long, requires = "field_separator")]
+    only_field: bool,
+
+    /// 空行和只有空白字符的行不参与匹配
+    ///
+    /// 和 `--line-prefix` 一样在正则表达式之前检查，
+    /// 例如 `-p '^\s*$'` 配合 `--skip-empty-lines` 不会有任何结果。
+    #[arg(long, conflicts_with = "match_empty_lines")]
+    skip_empty_lines: bool,
+
+    /// 把 CRLF（`\r\n`）和单独的 CR（`\r`）都当作 LF 行尾
+    ///
+    /// 在匹配之前先把文件内容中的换行统一成 `\n`，行中残留的 `\r` 不会再
+    /// 影响 `$` 之类的锚点。`\r\n` 本来就是一个行尾，行号不变；
+    /// 单独的 `\r` 会被当作新的一行，旧式 Mac 文件的行号因此才是正确的。
+    /// 匹配的文本和以后报告的字节位置都是相对于统一之后的内容的。
+    ///
+    /// # 示例
+    /// * `-p "done$" -f windows.log --crlf-is-lf`
+    #[arg(long)]
+    crlf_is_lf: bool,
+
+    /// 哪些字符序列是行尾，决定行号和每一行的范围
+    ///
+    /// * auto（默认）: `\n` 和 `\r\n`；文件中没有 `\n` 但有 `\r` 时是单独的 `\r`，旧式 Mac 文件不用另外指定
+    /// * lf: 只有 `\n`，行尾的 `\r` 是行内容的一部分，`$` 不能匹配在它之前
+    /// * crlf: 只有 `\r\n`
+    /// * cr: 只有单独的 `\r`
+    /// * any: `\n`、`\r\n` 和单独的 `\r`，适用于混用行尾的文件
+    ///
+    /// 和 `--crlf-is-lf` 不同，文件内容不会被改写，`--write-replace` 写回时每行保持原来的行尾。
+    ///
+    /// # 示例
+    /// * `-p ERROR -f serial.log --newline any`
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = Newline::Auto, conflicts_with = "crlf_is_lf")]
+    newline: Newline,
+
+    /// 文件混用了 LF、CRLF 和单独的 CR 中的几种行尾时，对这个文件给出一条警告
+    ///
+    /// 混用行尾的文件中，`--newline lf` 之类的设置只对一部分行正确，`$` 结尾的模式看起来时灵时不灵。
+    /// 警告中列出每种行尾的个数，检查在 `--crlf-is-lf` 统一行尾之前进行。
+    ///
+    /// # 示例
+    /// * `-p 'done$' -f logs --warn-mixed-endings`
+    #[arg(long)]
+    warn_mixed_endings: bool,
+
+    /// 按顺序尝试一组编码，用第一个能无错误解码整个文件的编码搜索这个文件
+    ///
+    /// 可选的编码: utf-8, utf-16le, utf-16be, windows-1252, latin1，用逗号分隔。
+    /// latin1 能解码任何内容，应该放在最后；没有 BOM 的 UTF-16 文件至少要有一个换行符才能被识别。
+    /// 每个文件选用的编码在 `-v` 时输出到标准错误。
+    /// 所有编码都失败时按 UTF-8 有损解码（非法字节显示为 U+FFFD），而不是跳过这个文件。
+    /// 不指定时只接受 UTF-8，其他文件被跳过（见 `--debug-skip`）。
+    ///
+    /// # 示例
+    /// * `--encoding-chain utf-8,windows-1252 -p café -f legacy/`
+    /// * `--encoding-chain utf-8,utf-16le,latin1 -p ERROR -f exports/`
+    #[arg(long, value_enum, value_name = "LIST", value_delimiter = ',')]
+    encoding_chain: Vec<Encoding>,
+
+    /// 分组输出结果：每个模式或者每个目录一节，列出其中所有匹配的行
+    ///
+    /// * pattern: 用于多个 `-e` / `--builtin-pattern` 时的分类统计。一行同时匹配多个模式时
+    ///   会出现在每个对应的小节中。
+    /// * directory: 按文件所在的目录分组，目录按字典序排列，小节中的文件名去掉了目录部分。
+    ///   只搜索一个目录时目录相对于它，直接在这个目录下的文件在 `.` 一节中；
+    ///   搜索多个路径时目录就是输出的路径中的目录，路径中没有目录的文件（例如直接给出的 `a.txt` 和标准输入）
+    ///   在 `.` 一节中。使用颜色时目录名加粗显示。
+    ///
+    /// 所有结果要等搜索结束才能输出，因此这个模式下没有流式输出，结果全部保存在内存中。
+    ///
+    /// # 示例
+    /// * `-e ERROR -e WARN -f app.log --group-by pattern`
+    /// * `-p TODO -f src --group-by directory`
+    #[arg(long, value_enum, value_name = "KEY", conflicts_with_all = ["fuzzy", "sound_like", "word_list", "interactive", "patch"])]
+    group_by: Option<GroupBy>,
+
+    /// 在每条结果前面标出匹配了这一行的模式，写成 `[模式] 路径:行号:内容`
+    ///
+    /// 一行匹配了多个模式时按模式给出的顺序全部列出，用逗号分隔。
+    /// 标签默认是模式本身，内置模式是它的名字；写成 `-e NAME=REGEX` 时标签是 NAME，
+    /// NAME 由字母、数字、`_` 和 `-` 组成，不以数字或 `-` 开头。
+    /// 使用这个选项时要搜索字面的 `key=value` 请写成 `-e 'key\=value'`。
+    /// `-o` 的每个匹配带上它所在的行的标签，`--format json` 的每个对象多一个 `patterns` 数组。
+    ///
+    /// # 示例
+    /// * `--show-pattern -e todo=TODO -e fixme=FIXME -f src` - 输出 `[todo] src/main.rs:12:...`
+    #[arg(
+        long,
+        conflicts_with_all = ["fuzzy", "sound_like", "word_list", "all_lines", "rules", "count", "count_mode", "group_by",
+            "record_format", "interactive", "tui", "write_replace", "patch", "diff"]
+    )]
+    show_pattern: bool,
+
+    /// 按文件类型过滤要搜索的文件
+    ///
+    /// - `regular`: 只搜索普通文件，跳过所有符号链接（包括指向目录的符号链接）
+    /// - `symlink`: 只搜索符号链接指向的文件，不递归指向目录的符号链接
+    /// - `all`: 两者都搜索（默认）
+    ///
+    /// 普通目录在任何模式下都会被递归。除了 `regular` 模式会直接跳过之外，
+    /// 悬空的符号链接都会作为错误报告出来，所以 `--type-filter symlink`
+    /// 可以用来找出目录中所有悬空的符号链接。
+    #[arg(long, value_enum, value_name = "TYPE", default_value = "all")]
+    type_filter: TypeFilter,
+
+    /// 只搜索类型为 NAME 的文件，可以重复指定，匹配其中任意一个类型即可
+    ///
+    /// 内置的类型有 c、cpp、go、java、js、json、md、py、rust、sh、toml、ts、yaml 和 test
+    /// （各种语言的测试文件和 `tests/` 目录），可以用 `--type-add` 扩充或者定义新的类型。
+    /// 和其他过滤条件的先后顺序见 filetype 模块的说明。
+    ///
+    /// # 示例
+    /// * `--type rust --type toml -p serde -f .`
+    #[arg(long = "type", value_name = "NAME")]
+    file_type: Vec<String>,
+
+    /// 不搜索类型为 NAME 的文件，可以重复指定；和 `--type` 冲突时 `--type-not` 优先
+    ///
+    /// # 示例
+    /// * `--type rust --type-not test -p unwrap -f .` - 除了测试以外的 Rust 文件
+    #[arg(long, value_name = "NAME")]
+    type_not: Vec<String>,
+
+    /// 定义文件类型，写成 `名字:通配符`，可以重复指定；名字已经存在时通配符加到原来的定义中
+    ///
+    /// 通配符的语法和 `--rules` 中的路径相同，不含 `/` 的通配符只和文件名比较。
+    ///
+    /// # 示例
+    /// * `--type-add "proto:*.proto" --type proto -p "message " -f .`
+    /// * `--type-add "test:**/fixtures/**" --type-not test -p TODO -f .` - 把 fixtures 目录也算作测试
+    #[arg(long, value_name = "NAME:GLOB")]
+    type_add: Vec<String>,
+
+    /// 一条路径上最多跟随多少个符号链接，超过时报告错误并跳过
+    ///
+    /// 默认值 40 和 Linux 内核的 MAXSYMLINKS 相同。指向另一个符号链接的链接，链上的每一跳都算一层；
+    /// 计数还沿着递归累加：从一个符号链接进入的目录里又遇到符号链接，就算两层。
+    /// 指回祖先目录的符号链接不论层数多少都会被当作环跳过。
+    #[arg(long, value_name = "N", default_value_t = pgrep::MAX_SYMLINK_DEPTH)]
+    max_symlink_depth: usize,
+
+    /// 在标准错误中说明每个没有被搜索的文件或目录是被哪条规则排除的
+    ///
+    /// 每个被跳过的路径输出一行，例如 `跳过 data.bin: 不是合法的 UTF-8（第 12 字节）`。
+    /// 可以用 `--debug-skip=GLOB` 只查看匹配通配符的路径，必须使用 `=` 连接，
+    /// 否则后面的位置参数会被当成通配符。
+    ///
+    /// # 示例
+    /// * `--debug-skip` - 报告所有被跳过的路径
+    /// * `--debug-skip='*.log'` - 只报告被跳过的 log 文件
+    #[arg(
+        long,
+        value_name = "GLOB",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    debug_skip: Option<String>,
+
+    /// 在标准错误中输出诊断日志，可以重复指定
+    ///
+    /// - `-v`: 搜索的路径、启用的过滤条件、每个目录的完成进度
+    /// - `-vv`: 每个条目的文件类型、每个文件的匹配数量和耗时
+    /// - `-vvv`: 所有日志
+    ///
+    /// `RUST_LOG` 环境变量可以按模块设置级别，例如 `RUST_LOG=pgrep::config=debug`。
+    /// 不指定时只输出警告，搜索成功时标准错误中没有任何内容。
+    #[arg(short = 'v', long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// 交互模式：为每个匹配编号，搜索结束后选择要在编辑器中打开的匹配
+    ///
+    /// 使用 `$VISUAL` 或 `$EDITOR` 指定的编辑器（默认 vi），并按编辑器的习惯传递行号
+    /// （vim 等使用 `+LINE FILE`，VS Code 使用 `--goto FILE:LINE`）。
+    /// 可以一次输入多个编号，如 `1,3,7` 或 `2-4`，输入 `q` 退出。
+    /// 标准输出不是终端时会报错。
+    #[arg(long)]
+    interactive: bool,
+
+    /// 每条结果输出之前先在终端上显示出来，询问 `[y/n/q/a]?`
+    ///
+    /// `y` 输出这条结果，`n` 跳过，`a` 输出这一条和之后的所有结果，`q` 停止搜索（已经输出的结果保留）。
+    /// 提示和回答都通过 `/dev/tty` 进行，所以标准输出重定向到文件或管道时文件中只有确认过的结果，
+    /// 标准输入也可以是要搜索的内容；没有控制终端（例如在 CI 中）时报错。
+    /// 作用于所有输出方式，包括 `-o` 和 `--format json`；统计数量、修改文件的选项不能和它一起使用。
+    ///
+    /// # 示例
+    /// * `-I -p "password" -f config/ > reviewed.txt` - 逐条检查后只保存真正有问题的结果
+    #[arg(
+        short = 'I',
+        long,
+        conflicts_with_all = ["interactive", "tui", "count", "count_mode", "write_replace", "patch", "diff", "follow_lines", "watch"]
+    )]
+    confirm: bool,
+
+    /// 标准输出是终端时，通过这个分页器输出结果
+    ///
+    /// 命令按空白切分成参数，不经过 shell。分页器无法启动时直接输出到终端。
+    /// 在 `--interactive`、`--exec`、`--exec-file`、`--exec-batch` 模式下不使用分页器，
+    /// 因为它们需要直接和终端交互。
+    #[arg(long, value_name = "CMD", default_value = "less -R")]
+    pager: String,
+
+    /// 在每条结果之后附上这一行的十六进制转储，格式和 `xxd` 类似
+    ///
+    /// 每行 16 个字节：`偏移: 十六进制 ASCII`，偏移从行首开始计算，
+    /// 不可打印的字符在 ASCII 部分显示为 `.`。用来查看日志中混入的控制字符或者不可见字符。
+    #[arg(long, conflicts_with_all = ["interactive", "patch", "group_by", "print0"])]
+    hex_dump: bool,
+
+    /// 用 NUL 字符（`\0`）而不是换行分隔每一条 `路径:行号:内容` 结果
+    ///
+    /// 和只在文件名后面加 NUL 的 `-Z` 不同，这里分隔的是整条结果，
+    /// 即使结果的内容中含有换行也能被准确地切分，例如用 `xargs -0` 或 `read -d ''` 处理。
+    #[arg(long, conflicts_with_all = ["interactive", "patch", "group_by"])]
+    print0: bool,
+
+    /// 每条结果之后的行尾，默认是当前平台的行尾（Windows 上是 crlf，其他平台是 lf）
+    ///
+    /// 只影响搜索结果（包括 `-o` 和 `-c` 的输出），不影响汇总信息和补丁。
+    /// 同时使用 `--print0` 时总是用 NUL 分隔。
+    ///
+    /// # 示例
+    /// * `--line-ending-output crlf -p TODO -f src > todo.txt` - 给需要 `\r\n` 的 Windows 工具使用
+    #[arg(long, value_enum, value_name = "ENDING")]
+    line_ending_output: Option<LineEnding>,
+
+    /// 把分隔行写到标准错误，标准输出上只有搜索结果
+    ///
+    /// 分隔行是 `--watch` 每次重新搜索之前的 `==> ... <==` 一行。
+    /// 这个程序不输出上下文行，所以没有 grep 在上下文之间的 `--` 分隔行。
+    ///
+    /// # 示例
+    /// * `--watch --emit-separators-to-stderr -p TODO -f src | tee todo.log`
+    #[arg(long)]
+    emit_separators_to_stderr: bool,
+
+    /// 把标题行写到标准错误，标准输出上只有搜索结果
+    ///
+    /// 标题行是 `--group-by` 每一节开头的 `模式 ... (N 行):` 一行。
+    ///
+    /// # 示例
+    /// * `--group-by pattern -p TODO -p FIXME -f src --emit-headings-to-stderr | wc -l`
+    #[arg(long)]
+    emit_headings_to_stderr: bool,
+
+    /// 结果的输出格式，`sqlite` 时写入 `--output` 指定的数据库而不是标准输出
+    ///
+    /// 数据库中有 runs、files、matches 三个表，结构见 sqlite 模块的说明。
+    /// `sqlite` 需要用 `cargo build --features sqlite` 构建。
+    ///
+    /// # 示例
+    /// * `--format sqlite --output audit.db -p "password\s*=" -f /srv`
+    /// * `--format sqlite --output audit.db --append -p "api_key" -f /srv` - 在同一个数据库中记录第二次运行
+    /// * `--format json -p TODO -f src | jq -r .path` - 每个匹配一行 JSON，可以边搜索边处理
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    format: Option<OutputFormat>,
+
+    /// 把所有匹配放在一个 JSON 数组中输出，整个输出是一个 JSON 文档
+    ///
+    /// 对象和 `--format json` 的相同，只是写成 `[ {...}, {...} ]`，没有匹配时输出 `[]`。
+    /// 匹配仍然是边搜索边输出的，只有最后的 `]` 要等到搜索结束；需要逐行处理结果时用 `--format json`。
+    /// 可以省略 `--format json`，不能和其他格式一起使用。
+    ///
+    /// # 示例
+    /// * `--json-array -p TODO -f src > todo.json`
+    #[arg(
+        long,
+        conflicts_with_all = ["count", "count_mode", "only_matching", "interactive", "group_by", "diff", "patch", "write_replace",
+            "filter", "tui", "record_format", "watch", "follow_lines", "output"]
+    )]
+    json_array: bool,
+
+    /// `--format sqlite` 写入的数据库文件
+    #[arg(
+        long,
+        value_name = "FILE",
+        required_if_eq("format", "sqlite"),
+        conflicts_with_all = ["count", "count_mode", "only_matching", "interactive", "group_by", "diff", "patch", "write_replace", "filter", "tui", "record_format"]
+    )]
+    output: Option<PathBuf>,
+
+    /// `--output` 的数据库已经存在时追加一次新的运行，而不是报错
+    #[arg(long, requires = "output")]
+    append: bool,
+
+    /// 用模板 TEMPLATE 代替默认的 `路径:行号:内容` 输出每个匹配行
+    ///
+    /// 占位符有 `{file}`、`{line}`、`{text}`、`{ts}`（Unix 时间戳）和 `{ts:iso}`（ISO 8601 格式的 UTC 时间），
+    /// `{{` 和 `}}` 是字面的花括号。时间戳是输出这一行时的时间，不是读取文件的时间。
+    /// 只影响普通的匹配行，`-c`、`-o` 等其他输出格式不能和它一起使用。
+    ///
+    /// # 示例
+    /// * `--record-format "{ts:iso} {file}:{line} {text}" -p ERROR -f app.log --follow-lines`
+    /// * `--record-format "{ts}\t{text}" --timestamp-precision us -p timeout -f logs`
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = ["count", "count_mode", "only_matching", "interactive", "group_by", "diff", "patch", "write_replace", "filter", "tui"]
+    )]
+    record_format: Option<String>,
+
+    /// `--record-format` 中时间戳的精度：`{ts}` 的单位和 `{ts:iso}` 小数部分的位数
+    #[arg(long, value_enum, value_name = "UNIT", default_value = "ms", requires = "record_format")]
+    timestamp_precision: Precision,
+
+    /// 在行号前面补空格，让同一个文件中各行的行号右对齐，内容从同一列开始
+    ///
+    /// 宽度是这个文件中最大的行号的位数，不同文件的宽度可能不同。作用于普通的匹配行、
+    /// `-o` 和 `--record-format` 的 `{line}`；`--follow-lines` 时每一行单独输出，行号不会对齐。
+    ///
+    /// # 示例
+    /// * `--align -p TODO -f src/main.rs` - 输出 `src/main.rs:  12:...` 和 `src/main.rs:1024:...`
+    #[arg(long)]
+    align: bool,
+
+    /// 文件中连续几行都是空行结果时只输出第一行，类似 `cat -s`
+    ///
+    /// 用 `--match-empty-lines` 之类会匹配到很多空行的模式时，减少输出中的噪音。
+    /// 只合并行号相邻的空行，不同文件的结果之间不会合并；
+    /// 空行指的是内容（`--replace` 时是替换后的内容）长度为 0 的行。
+    #[arg(long, conflicts_with_all = ["interactive", "patch", "group_by"])]
+    squeeze_blank: bool,
+
+    /// 只输出匹配到的文本，而不是整行，每个匹配一行 `路径:行号:匹配`
+    ///
+    /// 一行中有多个匹配时每个匹配各输出一行，和 GNU grep 的 `-o` 相同；
+    /// 用 `--o-inline` 把同一行的所有匹配合并到一行输出。空的匹配不输出。
+    /// 使用 `--replace` 时输出每个匹配替换后的文本。
+    /// `--word-list` 和 `--fuzzy` 输出命中的词或子串，`--sound-like` 没有确切的匹配位置，输出整行。
+    ///
+    /// # 示例
+    /// * `-o -p "[0-9]+ms" -f app.log` - 提取所有耗时
+    #[arg(short = 'o', long, conflicts_with_all = ["interactive", "patch", "group_by", "write_replace", "hex_dump"])]
+    only_matching: bool,
+
+    /// 一行中的匹配可以互相重叠，例如 `-p aba` 在 `ababa` 中找到两个匹配
+    ///
+    /// 默认和 GNU grep 一样，找到一个匹配之后从它的结尾继续找，匹配互不重叠；
+    /// 使用这个选项时从匹配开头的下一个字符继续找。影响 `-o` 输出的匹配、
+    /// `--count-mode matches` / `bytes` / `unique` 的计数和匹配文本的高亮，不影响哪些行匹配。
+    /// 高亮时重叠的匹配合并成一段。
+    ///
+    /// 每个匹配的开头都要重新运行一次正则表达式，匹配很多的行开销是行长度的平方，
+    /// 例如 `-p 'a+'` 用在一长串 `a` 上会明显变慢。
+    ///
+    /// # 示例
+    /// * `--overlapping --count-mode matches -p aba -f dna.txt` - 统计包含重叠在内的出现次数
+    #[arg(long, conflicts_with_all = ["word_list", "fuzzy", "sound_like", "all_lines"])]
+    overlapping: bool,
+
+    /// `-o` 时把同一行的所有匹配合并成一行 `路径:行号:匹配1,匹配2,...` 输出
+    #[arg(long, requires = "only_matching")]
+    o_inline: bool,
+
+    /// 在整次搜索中去掉重复的结果，重复与否按 FIELD 比较，只输出第一次出现的那一条
+    ///
+    /// * `line`: 内容完全相同的行只输出一次，不管在哪个文件中
+    /// * `match`: 匹配到的文本只输出一次；和 `-o` 一起使用时逐个匹配比较（使用 `--replace` 时比较替换后的文本），
+    ///   否则一行中至少有一个没有出现过的匹配时才输出这一行
+    /// * `path`: 每个文件只输出第一条结果
+    ///
+    /// 比较的都是替换之前的原文（`-o` 除外）。`-v` 时在搜索结束后报告不同的值的个数和省略的重复结果数。
+    ///
+    /// # 示例
+    /// * `--dedup-by match -o -p "E[0-9]{4}" -f src` - 列出代码中用到的每一个错误码
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FIELD",
+        conflicts_with_all = ["count", "count_mode", "write_replace", "patch", "diff", "interactive", "follow_lines", "tui"]
+    )]
+    dedup_by: Option<DedupBy>,
+
+    /// 不输出匹配的行，只输出统计的数量
+    ///
+    /// `lines`、`matches`、`bytes` 为每个被搜索的文件输出一行 `路径:数量`，没有匹配的文件数量为 0；
+    /// `files` 在搜索结束后输出一个数字，即包含匹配的文件数；
+    /// `unique` 在搜索结束后输出所有文件中不同的匹配文本的个数（空的匹配不算）。
+    /// `matches` 和 `bytes` 按 `-o` 的方式找出一行中的每个匹配，
+    /// `--word-list` 和 `--fuzzy` 每行只算命中的那个词或子串，`--sound-like` 算整行。
+    ///
+    /// # 示例
+    /// * `--count-mode matches -p TODO -f src` - 每个文件中有多少个 TODO
+    /// * `--count-mode files -p "unsafe" -f src` - 有多少个文件用到了 unsafe
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        conflicts_with_all = ["count", "only_matching", "interactive", "patch", "group_by", "write_replace", "follow_lines"]
+    )]
+    count_mode: Option<CountMode>,
+
+    /// 只输出每个文件中匹配的行数，等同于 `--count-mode lines`
+    #[arg(short = 'c', long, conflicts_with_all = ["only_matching", "interactive", "patch", "group_by", "write_replace", "follow_lines"])]
+    count: bool,
+
+    /// 只输出所有文件中不同的匹配文本的个数，等同于 `--count-mode unique`
+    ///
+    /// 统计的是匹配到的文本而不是行：一行中的每个匹配分别计入，同一个文本无论出现多少次只算一个。
+    ///
+    /// # 示例
+    /// * `--count-unique-matches --builtin-pattern ip-address -f access.log` - 日志中出现了多少个不同的 IP 地址
+    #[arg(
+        long,
+        conflicts_with_all = ["count", "count_mode", "only_matching", "interactive", "patch", "group_by", "write_replace", "follow_lines"]
+    )]
+    count_unique_matches: bool,
+
+    /// 输出每个不同的匹配文本出现的次数，而不只是不同文本的个数
+    ///
+    /// 每行一个 `次数<TAB>文本`，按次数从多到少排列，次数相同时按文本排列。
+    /// 隐含 `--count-mode unique`，不能和其他的统计方式一起使用。
+    ///
+    /// # 示例
+    /// * `--frequency-analysis --builtin-pattern ip-address -f access.log | head` - 出现最多的 10 个 IP 地址
+    #[arg(long, conflicts_with_all = ["count", "only_matching", "interactive", "patch", "group_by", "write_replace", "follow_lines"])]
+    frequency_analysis: bool,
+
+    /// 不输出匹配的行，搜索结束后只输出匹配最多的 N 个文件，0 表示所有包含匹配的文件
+    ///
+    /// 每行一个 `数量<TAB>路径`，按数量从多到少排列，数量相同时按路径排列，每次运行的顺序都相同。
+    /// 默认数的是匹配的行数，和 `--count-mode matches` 或 `bytes` 一起使用时数的是匹配的次数或字节数；
+    /// 没有匹配的文件不列出。`--format json` 时每个文件一个有 path 和 count 字段的 JSON 对象。
+    ///
+    /// # 示例
+    /// * `--top 10 -p "ERROR" -f logs` - 错误最集中的 10 个日志文件
+    /// * `--top 0 --count-mode matches -p TODO -f src` - 所有文件按 TODO 的个数排列
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["count_exit", "count_unique_matches", "frequency_analysis", "only_matching", "interactive",
+            "patch", "group_by", "write_replace", "follow_lines", "tui"]
+    )]
+    top: Option<usize>,
+
+    /// 不输出匹配的行，搜索结束后按字母顺序输出所有不同的匹配行，每个只输出一次
+    ///
+    /// 和 `-o` 一起使用时收集的是每个匹配到的文本（空的匹配不算），例如一个模式能匹配到的所有变量名。
+    /// 比较的是替换之前的原文，按字节顺序排列，不带路径和行号。
+    /// 所有不同的文本都要保存到搜索结束，占用的内存和它们的总长度成正比，匹配很多不同的长行时要注意。
+    ///
+    /// # 示例
+    /// * `--output-sorted-unique-texts -o -p "cfg_[a-z_]+" -f src` - 用到的所有 cfg_ 开头的名字
+    #[arg(
+        long,
+        conflicts_with_all = ["count", "count_mode", "count_unique_matches", "frequency_analysis", "top", "count_exit",
+            "interactive", "confirm", "patch", "diff", "group_by", "write_replace", "follow_lines", "tui", "format",
+            "json_array"]
+    )]
+    output_sorted_unique_texts: bool,
+
+    /// `--output-sorted-unique-texts` 按相反的顺序输出
+    #[arg(long, requires = "output_sorted_unique_texts")]
+    output_sorted_unique_texts_desc: bool,
+
+    /// `--output-sorted-unique-texts` 的每行写成 `次数<TAB>文本`，次数是这个文本出现了多少次
+    ///
+    /// 仍然按文本排列；要按次数从多到少排列请使用 `--frequency-analysis`。
+    #[arg(long, requires = "output_sorted_unique_texts")]
+    output_sorted_unique_counts: bool,
+
+    /// 不输出任何结果，以包含匹配的文件数作为退出状态，供 shell 中的健康检查读取 `$?`
+    ///
+    /// 退出状态只有一个字节，所以最大是 255：有 255 个或者更多的文件包含匹配时都是 255，
+    /// 需要确切的数量时请使用 `--count-mode files`。没有任何匹配时退出状态是 0，和 grep 的习惯相反。
+    /// 搜索中出错的文件不计入，错误照常输出到标准错误。
+    ///
+    /// # 示例
+    /// * `pgrep --count-exit -p "FATAL" -f /var/log/app; echo "$? 个文件中有 FATAL"`
+    #[arg(
+        long,
+        conflicts_with_all = ["count", "count_mode", "count_unique_matches", "frequency_analysis", "only_matching", "interactive",
+            "confirm", "patch", "diff", "group_by", "write_replace", "follow_lines", "watch", "tui", "format", "json_array"]
+    )]
+    count_exit: bool,
+
+    /// 匹配超过 N 处（默认 0，即有任何匹配）时以状态 3 退出，供 CI 中的检查使用
+    ///
+    /// N 必须用 `=` 连着写，这样 `--error-if-matches TODO` 中的 TODO 仍然是模式。
+    /// 数的是和输出一样经过抑制、基线等筛选之后的匹配行；使用 `--rules` 时数的是每条规则的匹配，
+    /// 一行匹配了两条规则算两处。结果照常输出，失败时在标准错误上说明阈值、实际的数量和前几处匹配的位置。
+    /// 状态 3 和表示出错的 2、`--rules` 的 error 级别的 1 不同，CI 可以分别处理。
+    ///
+    /// # 示例
+    /// * `--error-if-matches -p "dbg!" -f src` - 不允许提交 dbg!
+    /// * `--error-if-matches=20 -p TODO -f src` - TODO 不能超过 20 个
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "0",
+        conflicts_with_all = ["count_exit", "watch", "follow_lines", "tui", "interactive"]
+    )]
+    error_if_matches: Option<usize>,
+
+    /// 没有任何匹配时以状态 3 退出，用于检查必须存在的标记（例如许可证声明）
+    ///
+    /// 数法和 `--error-if-matches` 相同，可以和它一起使用，要求匹配数在 1 到 N 之间。
+    ///
+    /// # 示例
+    /// * `--error-if-no-matches -p "SPDX-License-Identifier" -f LICENSE.header`
+    #[arg(long, conflicts_with_all = ["count_exit", "watch", "follow_lines", "tui", "interactive"])]
+    error_if_no_matches: bool,
+
+    /// `--o-inline` 时匹配之间的分隔符
+    ///
+    /// # 示例
+    /// * `-o --o-inline --o-separator $'\t' -p "id=\d+" -f app.log` - 用制表符分隔
+    #[arg(long, value_name = "STR", default_value = ",", requires = "o_inline")]
+    o_separator: String,
+
+    /// 不输出警告，例如模式会匹配所有行时的提醒
+    ///
+    /// 也关掉搜索过程中 warn 级别的日志，例如 `--max-line-bytes` 截断了行、包中有加密的成员，
+    /// 和 `-v`、`RUST_LOG` 的设置无关。
+    #[arg(long)]
+    no_warnings: bool,
+
+    /// 先用命令转换文件，再搜索命令的标准输出，例如提取 PDF 或 Office 文档中的文字
+    ///
+    /// 命令直接运行而不经过 shell，文件路径是它唯一的参数，需要额外参数时请写一个包装脚本。
+    /// 命令以非零状态退出时，这个文件的错误信息中附有命令的标准错误输出。
+    /// 每个需要预处理的文件都要启动一次命令，比直接读取慢得多，建议配合 `--pre-glob` 使用。
+    ///
+    /// # 示例
+    /// * `--pre pdftotext-wrapper --pre-glob '*.pdf' -p invoice -f docs/`
+    #[arg(long, value_name = "CMD")]
+    pre: Option<PathBuf>,
+
+    /// 只有匹配 GLOB 的文件才经过 `--pre` 预处理，可以重复指定；不指定时所有文件都经过预处理
+    #[arg(long, value_name = "GLOB", requires = "pre")]
+    pre_glob: Vec<String>,
+
+    /// 预处理一个文件的最长时间（秒），超时的命令被杀掉并作为这个文件的错误报告出来
+    #[arg(long, value_name = "SEC", default_value_t = 60.0, requires = "pre")]
+    pre_timeout: f64,
+
+    /// 读取文件遇到暂时性的 I/O 错误（EINTR、EAGAIN）时最多重试 N 次
+    ///
+    /// 网络文件系统或者容器中偶尔会出现这类错误。第 n 次重试前等待 50 × n 毫秒，
+    /// 重试次数用完后作为这个文件的错误报告出来。`--io-retry 0` 不重试。
+    #[arg(long, value_name = "N", default_value_t = pgrep::DEFAULT_IO_RETRY)]
+    io_retry: u32,
+
+    /// 在标准错误中报告每个文件的正则表达式匹配耗时，用来找出让搜索变慢的文件
+    ///
+    /// 每个搜索过的文件输出一行 `正则耗时 路径: 耗时（N 行，M 个匹配）`。
+    /// 只计算正则表达式本身（包括 `--rules` 的模式）的匹配时间，不包括读取、解码，
+    /// 也不包括 `--fuzzy`、`--sound-like`、`--word-list` 这些不使用正则表达式的模式。
+    /// 每一行都要读两次时钟，每行大约多花几十纳秒，对很短的行来说可能和匹配本身的时间相当，
+    /// 所以报告的时间适合在文件之间比较，而不是当作绝对值。
+    ///
+    /// # 示例
+    /// * `--profile-regex -p '(\w+\s?)+$' -f logs/ > /dev/null` - 只看每个文件的耗时
+    #[arg(long)]
+    profile_regex: bool,
+
+    /// 搜索结束后在标准错误中报告最慢的几个文件，以及读取和解码、匹配、输出三个阶段各自的耗时
+    ///
+    /// 每个文件还报告解码之前的字节数和行数，最后一行是所有文件的合计。
+    /// 和 `--profile-regex` 不同，这里不逐行计时，不会明显拖慢搜索；只想知道慢在哪个阶段时用这个选项。
+    /// 输出阶段包括格式化、写标准输出和 `--exec` 之类的命令，标准输出是很慢的管道时这一项也会很大。
+    ///
+    /// # 示例
+    /// * `--debug-timing --debug-timing-top 5 -p ERROR -f logs/ > /dev/null`
+    #[arg(long)]
+    debug_timing: bool,
+
+    /// `--debug-timing` 报告多少个最慢的文件
+    #[arg(long, value_name = "N", default_value_t = 10, requires = "debug_timing")]
+    debug_timing_top: usize,
+
+    /// 在标准错误中报告每一行的正则表达式匹配耗时，输出量和文件的行数相同
+    ///
+    /// 格式为 `正则耗时 路径:行号: 耗时`。每一行都要写一次标准错误，
+    /// 整个搜索会明显变慢，只适合在已经知道的慢文件上使用。
+    #[arg(long)]
+    profile_per_line: bool,
+
+    /// 每个文件的结果预先分配的条数，默认为 16
+    ///
+    /// 只影响内存分配，不影响输出。匹配很密集的文件（例如用 `.` 搜索整个文件）
+    /// 结果超过这个数量之后要反复扩容复制，设置成接近匹配行数的值可以避免；
+    /// 但每个被搜索的文件都会先分配这么多条记录的空间，文件很多而匹配稀疏时设置得太大反而浪费内存。
+    ///
+    /// # 示例
+    /// * `-p . --match-buffer-size 10000 big.log`
+    #[arg(long, value_name = "N")]
+    match_buffer_size: Option<usize>,
+
+    /// 把在整个内容上查找时使用的 DFA 保存在这个目录中，之后使用同一个模式的运行直接读取
+    ///
+    /// 很大的选择分支（例如几千个关键词）每次启动都要重新编译，用这个选项只在第一次运行时构建，
+    /// 保存的文件名是模式的哈希，目录不存在时自动创建。缓存文件损坏时重新构建并覆盖；
+    /// 模式不能编译成完整 DFA 时（例如有 Unicode 单词边界 `\b`）不使用缓存，`-vv` 会说明原因。
+    ///
+    /// # 示例
+    /// * `-p "$(paste -sd'|' keywords.txt)" --cache-dir ~/.cache/pgrep src/`
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// 不使用 `--cache-dir`，例如在配置文件的默认参数中设置了缓存目录时
+    #[arg(long)]
+    no_cache: bool,
+
+    /// 每行最多有多少字节参与匹配，默认 64 MiB
+    ///
+    /// 更长的行只匹配前这么多字节，之后的部分被丢弃，并对这个文件给出一条警告。
+    /// 普通文件边读边截断，整个文件只有一行的几百 MB 的压缩代码也只占用这么多内存。
+    /// `--write-replace` 仍然读取完整的文件，但被丢弃的部分中的匹配不会被替换。
+    ///
+    /// # 示例
+    /// * `-p sourceMappingURL --max-line-bytes 1048576 -f dist`
+    #[arg(long, value_name = "BYTES", default_value_t = pgrep::DEFAULT_MAX_LINE_BYTES as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    max_line_bytes: u64,
+
+    /// 检查每个文件的前 N 个字节判断是不是二进制文件，二进制文件不搜索；0 表示不检查
+    ///
+    /// 默认前 8192 个字节中有 NUL 字节的文件算作二进制文件。N 越大越不容易漏掉在一段文本开头之后
+    /// 才出现 NUL 的二进制文件，但每个文件都要多扫描这么多字节；N 越小越快，可能把二进制文件当作文本搜索
+    /// （不是合法 UTF-8 的内容仍然会被跳过）。用 `--debug-skip` 查看被跳过的文件。
+    /// `--encoding-chain` 中有 UTF-16 或者使用 `--force-write` 时不检查。
+    ///
+    /// # 示例
+    /// * `-p PNG --binary-scan-bytes 0 -f assets` - 也搜索二进制文件
+    #[arg(long, value_name = "N", default_value_t = pgrep::DEFAULT_BINARY_SCAN_BYTES)]
+    binary_scan_bytes: usize,
+
+    /// 检查的字节中 NUL 和控制字符的比例超过 R（0 到 1）时才算二进制文件
+    ///
+    /// 有一个 NUL 就算二进制文件的默认规则最快，但会跳过偶尔夹带一个 NUL 的日志之类的文本文件；
+    /// 按比例判断对这类文件更宽容，需要数出所有检查的字节。制表符、换行、回车、换页和 ESC 不算控制字符。
+    ///
+    /// # 示例
+    /// * `-p ERROR --null-ratio 0.1 -f logs`
+    #[arg(long, value_name = "R")]
+    null_ratio: Option<f64>,
+
+    /// 超过 N 字节的匹配行不输出内容，只输出 `[省略了 M 字节的长行]`
+    ///
+    /// 只影响普通的匹配行输出，路径和行号照常输出；`--format json` 总是输出完整的行。
+    ///
+    /// # 示例
+    /// * `-p apiKey --max-columns 300 -f dist`
+    #[arg(long, value_name = "N")]
+    max_columns: Option<usize>,
+
+    /// 匹配行超过 N 个字符时只输出前 N 个字符，后面接上 `--truncate-suffix`
+    ///
+    /// 按字符计算，不会切开多字节的字符，也不会切开由多个码点组成的 emoji（国旗、肤色、`ZWJ` 连接的组合），
+    /// 它们各算一个字符；颜色转义序列不算字符。和 `--max-columns` 一起使用时，
+    /// 超过 `--max-columns` 字节的行仍然整行省略。`--format json` 等机器可读的输出总是包含完整的行。
+    ///
+    /// # 示例
+    /// * `-p apiKey --truncate-matches 120 -f dist`
+    #[arg(long, value_name = "N")]
+    truncate_matches: Option<usize>,
+
+    /// `--truncate-matches` 截断的行后面接上的文本
+    #[arg(long, value_name = "TEXT", default_value = "...", requires = "truncate_matches")]
+    truncate_suffix: String,
+
+    /// 匹配行比终端宽时在单词之间折行，续行缩进到和第一行的内容对齐
+    ///
+    /// 只在标准输出是终端时生效，输出到管道或文件时不折行；用 `--wrap-width` 指定宽度时总是折行。
+    /// 比一整行还长的单词只能在字符之间折开。和 `--truncate-matches` 一起使用时先截断再折行，
+    /// `--record-format` 的输出不折行。
+    ///
+    /// # 示例
+    /// * `-p error --wrap-matches -f logs`
+    #[arg(long)]
+    wrap_matches: bool,
+
+    /// `--wrap-matches` 使用的宽度，代替终端的宽度
+    #[arg(long, value_name = "N", requires = "wrap_matches", value_parser = clap::value_parser!(u16).range(1..))]
+    wrap_width: Option<u16>,
+
+    /// 每个文件跳过前 N 个匹配的行，只输出之后的匹配
+    ///
+    /// 用于分批查看有成千上万个匹配的文件，输出的行号仍然是在文件中的行号。
+    /// 被 `--ignore-marker` 抑制的匹配不计入 N。`--write-replace` 和 `--patch` 总是替换整个文件，不能和这个选项一起使用。
+    ///
+    /// # 示例
+    /// * `-p TODO -f big.rs --skip-matches 100`
+    #[arg(long, value_name = "N", default_value_t = 0, conflicts_with_all = ["write_replace", "patch", "follow_lines", "match_all"])]
+    skip_matches: usize,
+
+    /// 不使用分页器
+    #[arg(long)]
+    no_pager: bool,
+
+    /// 搜索结束后不退出，搜索路径下的文件发生变化时重新搜索
+    ///
+    /// 每次重新搜索之前，标准输出是终端时先清屏，然后输出一行带时间的分隔线。
+    /// 文件变化通过定期检查修改时间和大小发现，`--type-filter`、`--rules`
+    /// 排除的文件的变化不会触发重新搜索。不使用分页器。
+    /// 需要在全部结果出来之后才能处理的 `--interactive`、`--patch`、`--group-by`、
+    /// `--exec-batch` 和 `--checkpoint` 不能和它同时使用。
+    /// 等待时按 Ctrl-C 退出。
+    ///
+    /// # 示例
+    /// * `--watch -p "TODO" -f src/` - 一边编辑一边查看剩下的 TODO
+    #[arg(long, conflicts_with_all = ["interactive", "patch", "group_by", "exec_batch", "checkpoint"])]
+    watch: bool,
+
+    /// 只搜索被 git 跟踪的文件（`git ls-files` 列出的文件）
+    ///
+    /// 没有被跟踪的新文件和被 .gitignore 忽略的文件都不搜索，子模块中被跟踪的文件也会被搜索。
+    /// 其他过滤条件照常生效。不在 git 工作区中的搜索路径给出警告，搜索其中所有的文件。
+    /// 文件列表在启动时取得一次，不能和 `--checkpoint` 一起使用。
+    ///
+    /// # 示例
+    /// * `--git-tracked -p TODO -f .` - 不会搜索到构建产物和本地的临时文件
+    #[arg(long, conflicts_with = "checkpoint")]
+    git_tracked: bool,
+
+    /// 输出相对于 git 仓库根目录的路径，和在哪个目录中运行无关
+    ///
+    /// 仓库根目录是从当前目录向上第一个包含 `.git` 的目录。只影响输出的路径，
+    /// `--exec` 的 `{path}`、`--patch` / `--diff` 的补丁和编辑器打开的文件仍然使用原来的路径。
+    /// 当前目录不在仓库中时给出警告，输出原来的路径。
+    ///
+    /// # 示例
+    /// * `--git-relative -p TODO -f .` - 在 CI 中输出 `src/main.rs:12:...`，而不是签出目录下的绝对路径
+    #[arg(long)]
+    git_relative: bool,
+
+    /// 只搜索暂存区中有修改的文件（`git diff --cached` 列出的文件），例如在 pre-commit 钩子中检查
+    ///
+    /// 删除的文件被跳过，重命名的文件搜索新的路径。搜索的是工作区中的内容，不是暂存的版本。
+    /// 没有给出搜索路径时搜索当前目录下的文件。
+    ///
+    /// # 示例
+    /// * `--staged -p "dbg!|console\.log"` - 提交之前检查有没有留下调试代码
+    #[arg(long, visible_alias = "git-staged-only", conflicts_with_all = ["checkpoint", "git_tracked"])]
+    staged: bool,
+
+    /// 只搜索工作区中和 BASE（默认为 HEAD）不同的文件，包括已经暂存和还没有暂存的修改
+    ///
+    /// BASE 可以是任何 git 能识别的提交，例如分支名 `main` 或者 `HEAD~3`，必须用 `=` 连着写，
+    /// 这样 `--changed TODO` 中的 TODO 仍然是模式。
+    /// 没有被跟踪的新文件不在其中，需要时加上 `--git-untracked`。其余规则和 `--staged` 相同。
+    /// `--git-modified-only` 是没有给出 BASE 时的别名，相当于 `git diff --name-only HEAD`。
+    ///
+    /// # 示例
+    /// * `--changed=main -p TODO` - 这个分支中修改过的文件里的 TODO
+    /// * `--git-modified-only --git-untracked -p TODO` - 还没有提交的所有改动中的 TODO
+    #[arg(
+        long,
+        visible_alias = "git-modified-only",
+        value_name = "BASE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "HEAD",
+        conflicts_with_all = ["checkpoint", "git_tracked", "staged"]
+    )]
+    changed: Option<String>,
+
+    /// 和 `--git-tracked`、`--staged` 或 `--changed` 一起使用，再搜索没有被跟踪的新文件
+    ///
+    /// 新文件是 `git ls-files --others --exclude-standard` 列出的文件，被 .gitignore 忽略的文件不算。
+    ///
+    /// # 示例
+    /// * `--staged --git-untracked -p "dbg!"` - 包括还没有 `git add` 的新文件
+    #[arg(long, requires = "git_selection")]
+    git_untracked: bool,
+
+    /// 匹配的行或者上一行含有 MARKER 时不输出这个匹配，默认的标记是 `pgrep-ignore`
+    ///
+    /// 标记一般写在注释中，例如 `legacy_api(); // pgrep-ignore`，或者单独写在匹配的上一行。
+    /// 只查找标记文本，任何语言的注释语法都可以。
+    ///
+    /// # 示例
+    /// * `--ignore-marker "nolint:legacy" -p "legacy_api\(" -f src`
+    #[arg(long, value_name = "MARKER", default_value = suppress::DEFAULT_MARKER, conflicts_with = "no_ignore_marker")]
+    ignore_marker: String,
+
+    /// 不处理行内的抑制标记，含有 `pgrep-ignore` 的行也照常输出
+    #[arg(long)]
+    no_ignore_marker: bool,
+
+    /// 抑制文件 FILE 中列出的匹配，每行一个 `路径:正则表达式` 或 `路径:行号` 条目
+    ///
+    /// 路径可以是通配符，`#` 开头的行是注释。搜索结束后报告没有抑制过任何匹配的条目，
+    /// 便于及时删除已经过时的条目。条目的写法见 suppress 模块的说明。
+    ///
+    /// # 示例
+    /// * `-p "unwrap\(\)" -f . --suppressions .pgrep-suppressions`
+    #[arg(long, value_name = "FILE")]
+    suppressions: Option<PathBuf>,
+
+    /// 照常输出被行内标记或者抑制文件抑制的匹配，被抑制的数量仍然会报告
+    #[arg(long)]
+    show_suppressed: bool,
+
+    /// 只输出基线文件 FILE 中没有记录的匹配，有这样的匹配时以状态 1 退出
+    ///
+    /// 基线由 `--save-baseline` 生成，按路径和去掉多余空白后的行内容比较，不比较行号，
+    /// 已有的匹配在文件中移动了位置也不会被报告。基线文件的格式见 baseline 模块的说明。
+    /// 输出最后在标准错误中报告新的匹配数。基线文件无法读取时以状态 2 退出。
+    ///
+    /// # 示例
+    /// * `-p "legacy_api\(" -f src --save-baseline legacy.json` - 记录现有的匹配
+    /// * `-p "legacy_api\(" -f src --baseline legacy.json` - 在 CI 中阻止新的匹配
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["write_replace", "diff", "patch", "watch", "follow_lines", "tui", "filter"]
+    )]
+    baseline: Option<PathBuf>,
+
+    /// 把这次搜索到的所有匹配保存为基线文件 FILE，供之后的 `--baseline` 使用
+    ///
+    /// 和 `--baseline` 同时使用时保存的是全部匹配（包括基线中已有的），可以用来更新基线。
+    /// 保存的只是匹配的路径和行内容，输出照常进行。
+    ///
+    /// # 示例
+    /// * `-p TODO -f . --git-relative --save-baseline todo.json`
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["watch", "follow_lines", "tui", "filter"])]
+    save_baseline: Option<PathBuf>,
+
+    /// 打开全屏的终端界面浏览搜索结果
+    ///
+    /// 左栏列出包含匹配的文件，右栏显示选中文件中的匹配行和前后各 2 行上下文。
+    /// 第一行是模式，直接输入就能修改，停止输入后立即重新搜索；没有给出模式时从空模式开始。
+    /// 上下方向键选择文件，PageUp / PageDown 滚动右栏，Ctrl-U 清空模式，Esc 或 Ctrl-C 退出。
+    /// 标准输入和标准输出都必须是终端，不能搜索标准输入。只浏览，不会修改任何文件。
+    ///
+    /// # 示例
+    /// * `--tui -p "fn \w+" -f src`
+    #[arg(
+        long,
+        conflicts_with_all = ["interactive", "patch", "group_by", "watch", "exec", "exec_file", "exec_batch", "checkpoint",
+            "write_replace", "filter", "replace", "insert_before", "insert_after", "only_matching", "count", "count_mode", "fuzzy", "sound_like",
+            "word_list", "rules"]
+    )]
+    tui: bool,
+
+    /// 作为常驻的搜索服务运行，从标准输入读取 JSON-RPC 请求
+    ///
+    /// 每行一个 JSON-RPC 2.0 请求，`search` 的参数是 `pattern`、`paths` 和 `options`，
+    /// 搜索过程中以 `matches` 通知分批发送匹配，结束时响应汇总；`cancel` 取消进行中的搜索，
+    /// `shutdown` 或者标准输入结束时退出。多个搜索可以同时进行，消息格式见 serve 模块。
+    /// 命令行上不需要模式和路径；`--type-add` 和 `--max-symlink-depth` 对所有请求都有效。
+    ///
+    /// # 示例
+    /// * `--serve --type-add 'gen:*.pb.go'`
+    /// * `echo '{"jsonrpc":"2.0","id":1,"method":"search","params":{"pattern":"TODO","paths":["src"]}}' | pgrep --serve`
+    #[arg(long, conflicts_with_all = ["tui", "filter", "watch", "follow_lines", "interactive", "print_rg_command"])]
+    serve: bool,
+
+    /// 像 `tail -F` 一样跟踪文件：输出已有的匹配后继续等待，新追加的匹配行立即输出
+    ///
+    /// 只能跟踪文件，不能是目录或标准输入；可以同时跟踪多个文件。
+    /// 行号在追加之间连续计数，文件被截断或者被轮转（inode 变化）时在标准错误中提示，
+    /// 然后从新文件的开头读取，行号重新从 1 开始。还没有写完换行符的最后一行要等换行符写入后才搜索。
+    /// 结果逐行输出，不使用分页器。按 Ctrl-C 结束。
+    ///
+    /// # 示例
+    /// * `--follow-lines -p ERROR -f app.log` - 实时查看新的错误日志
+    #[arg(long, conflicts_with_all = ["interactive", "patch", "group_by", "exec_batch", "checkpoint", "watch", "archives"])]
+    follow_lines: bool,
+
+    /// `--watch` 时最后一次变化之后等待多少毫秒才重新搜索，一次改动多个文件只触发一次搜索
+    #[arg(long, value_name = "MS", default_value_t = 200, requires = "watch")]
+    watch_debounce: u64,
+
+    /// 读取 `--checkpoint` 指定的检查点文件，跳过其中记录的已完成目录
+    ///
+    /// 检查点文件不存在时从头开始搜索。
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+}
+
+/// 分出 `--show-pattern` 的 `NAME=REGEX` 中的名字，没有名字时原样返回模式
+fn split_pattern_name(p: &str) -> (Option<String>, String) {
+    if let Some((name, re)) = p.split_once('=')
+        && name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return (Some(name.to_string()), re.to_string());
+    }
+    (None, p.to_string())
+}
+
+/// 把模式中的 `$VAR` 和 `${VAR}` 替换成环境变量的值，见 `--expand-env`
+///
+/// `\` 和它之后的字符原样保留，所以 `\$` 仍然是正则表达式中字面的 `$`。
+/// `$` 之后不是变量名、或者 `${` 没有对应的 `}` 时 `$` 保持原样。
+///
+/// # 参数
+/// * `empty` - 没有定义的变量替换成空字符串；为 false 时返回 UndefinedEnv
+fn expand_env(p: &str, empty: bool) -> Result<String, UndefinedEnv> {
+    let mut out = String::with_capacity(p.len());
+    let mut rest = p;
+    while let Some(i) = rest.find(['\\', '$']) {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        if rest.as_bytes()[i] == b'\\' {
+            let n = after.chars().next().map_or(0, char::len_utf8);
+            out.push_str(&rest[i..i + 1 + n]);
+            rest = &after[n..];
+            continue;
+        }
+        let (name, tail) = match after.strip_prefix('{') {
+            Some(b) => match b.find('}') {
+                Some(j) => (&b[..j], &b[j + 1..]),
+                None => ("", after),
+            },
+            None => {
+                let n = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+                (&after[..n], &after[n..])
+            }
+        };
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+        match std::env::var(name) {
+            Ok(v) => out.push_str(&v),
+            Err(_) if empty => {}
+            Err(_) => return Err(UndefinedEnv(name.to_string())),
+        }
+        rest = tail;
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// 去掉模式中每一行的注释再把各行连接起来，见 `--strip-pattern-comments`
+///
+/// `\` 之后的字符和字符集合 `[...]` 中的 `#` 不开始注释。去掉注释后每行首尾的空白也被去掉，
+/// 但是行尾转义的空格（`\ `）会被保留。
+fn strip_pattern_comments(p: &str) -> String {
+    let mut out = String::with_capacity(p.len());
+    for line in p.lines() {
+        let (mut escaped, mut class) = (false, false);
+        let mut end = line.len();
+        for (i, c) in line.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '[' => class = true,
+                ']' => class = false,
+                '#' if !class => {
+                    end = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let code = line[..end].trim_start();
+        let trimmed = code.trim_end();
+        // 以奇数个反斜杠结尾时，被去掉的第一个空白是转义的，要放回去
+        let backslashes = trimmed.len() - trimmed.trim_end_matches('\\').len();
+        if backslashes % 2 == 1 && trimmed.len() < code.len() {
+            out.push_str(&code[..trimmed.len() + 1]);
+        } else {
+            out.push_str(trimmed);
+        }
+    }
+    out
+}
+
+/// `-E` 的模式：开头加上 `(?x)`，结尾加上换行
+///
+/// 没有用 RegexBuilder::ignore_whitespace，而是把标志写进模式本身：多行匹配、`--rules`
+/// 和合并多个模式时都会用 `re.as_str()` 重新编译，写在模式里的标志不会丢失；
+/// 合并时外面包着的 `(?:...)` 也限制了它的作用范围，内置模式中的空格不受影响。
+/// 结尾的换行结束最后一行的注释，否则注释会吞掉外面的 `)`。
+///
+/// # 相关文档
+/// * 正则表达式的标志: <https://docs.rs/regex/latest/regex/#grouping-and-flags>
+fn verbose_pattern(p: &str) -> String {
+    format!("(?x){}\n", p)
+}
+
+/// 检查模式是不是会匹配所有行
+///
+/// 在任意位置都能匹配空字符串的模式（例如 `a*`、`x?`）对每一行都成立，空模式在这之前已经被拒绝；
+/// `.+` 这类模式虽然不匹配空字符串，但匹配所有非空的行，同样没有意义。
+///
+/// # 返回值
+/// * `Some(&str)` - 模式有问题时返回原因
+/// * `None` - 没有发现问题，无法编译的模式也返回 None，由之后的编译报告错误
+fn catastrophic_pattern(p: &str) -> Option<&'static str> {
+    let re = Regex::new(p).ok()?;
+    // `^$` 之类的模式也能匹配空字符串，但只匹配空行，所以还要看它是否匹配非空的文本
+    if re.is_match("") && re.is_match("x") {
+        return Some("能匹配空字符串");
+    }
+    let core = p.trim_start_matches('^').trim_end_matches('$');
+    let core = core
+        .strip_prefix('(')
+        .and_then(|c| c.strip_suffix(')'))
+        .unwrap_or(core);
+    if matches!(core, ".+" | "(?s).+" | "[\\s\\S]+") {
+        return Some("匹配任意非空的行");
+    }
+    None
+}
+
+/// 读取检查点文件，返回已完成的目录
+///
+/// 检查点文件每行记录一个已完成的目录，文件不存在时返回空集合
+fn read_checkpoint(p: &Path) -> Result<HashSet<PathBuf>, Error> {
+    match std::fs::read_to_string(p) {
+        Ok(s) => Ok(s.lines().filter(|l| !l.is_empty()).map(PathBuf::from).collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashSet::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 原子地写入检查点文件
+///
+/// 先写入同目录下的临时文件并刷到磁盘，再重命名覆盖原文件。
+/// 同一文件系统内的 rename 是原子操作，读取方要么看到旧内容，要么看到新内容。
+///
+/// # 相关文档
+/// * std::fs::rename: <https://doc.rust-lang.org/std/fs/fn.rename.html>
+/// * File::sync_all: <https://doc.rust-lang.org/std/fs/struct.File.html#method.sync_all>
+fn write_checkpoint(p: &Path, done: &[PathBuf]) -> Result<(), Error> {
+    use std::io::Write;
+
+    let mut tmp = p.as_os_str().to_os_string();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut f = std::fs::File::create(&tmp)?;
+    for d in done {
+        writeln!(f, "{}", d.display())?;
+    }
+    f.sync_all()?;
+    std::fs::rename(&tmp, p)?;
+    Ok(())
+}
+
+/// 把命令模板切分成参数
+///
+/// 按空白切分，单引号和双引号内的空白不切分，引号本身会被去掉。
+/// 这里刻意不经过 shell，文件路径中的特殊字符不会被解释。
+///
+/// # 示例
+/// `cp '{}' "/tmp/my dir"` 切分为 `["cp", "{}", "/tmp/my dir"]`
+fn split_command(t: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut cur = String::new();
+    // 当前参数是否已经开始，用来保留 `''` 这样的空参数
+    let mut started = false;
+    let mut quote: Option<char> = None;
+    for c in t.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => cur.push(c),
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                started = true;
+            }
+            None if c.is_whitespace() => {
+                if started {
+                    res.push(std::mem::take(&mut cur));
+                    started = false;
+                }
+            }
+            None => {
+                cur.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        res.push(cur);
+    }
+    res
+}
+
+/// 替换一个参数中的 `{name}` 占位符
+///
+/// 只扫描一遍，替换进去的值中即使含有 `{line}` 这样的文本也不会被再次替换。
+/// 不认识的占位符原样保留。
+///
+/// # 返回值
+/// 替换后的参数，以及是否替换了至少一个占位符
+fn substitute(part: &str, vars: &[(&str, &str)]) -> (String, bool) {
+    let mut out = String::with_capacity(part.len());
+    let mut substituted = false;
+    let mut rest = part;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after
+            .find('}')
+            .and_then(|close| vars.iter().find(|(k, _)| *k == &after[..close]).map(|(_, v)| (close, v)));
+        match value {
+            Some((close, v)) => {
+                out.push_str(v);
+                substituted = true;
+                rest = &after[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    (out, substituted)
+}
+
+/// 执行命令模板，把 `{path}` / `{}` 替换为给定的路径
+///
+/// 模板中单独的 `{path}` 或 `{}` 参数替换为全部路径（每个路径一个参数），
+/// 参数内部的占位符（如 `--file={}`）替换为空格连接的路径；
+/// `vars` 中的其他占位符按名字替换。模板中没有任何占位符时把路径附加在末尾。
+///
+/// # 参数
+/// * `template` - 命令模板，见 split_command
+/// * `paths` - 要传给命令的路径
+/// * `vars` - 其他占位符的名字和值，例如 `("line", "12")`
+///
+/// # 返回值
+/// * `Ok(())` - 命令执行成功
+/// * `Err(Error)` - 命令无法启动或以非零状态退出
+///
+/// # 相关文档
+/// * std::process::Command: <https://doc.rust-lang.org/std/process/struct.Command.html>
+fn exec_command(template: &str, paths: &[&Path], vars: &[(&str, &str)]) -> Result<(), Error> {
+    let parts = split_command(template);
+    let joined = paths
+        .iter()
+        .map(|p| p.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut all: Vec<(&str, &str)> = vec![("", &joined), ("path", &joined)];
+    all.extend_from_slice(vars);
+
+    let mut argv: Vec<OsString> = Vec::new();
+    let mut substituted = false;
+    for part in &parts {
+        if part == "{}" || part == "{path}" {
+            // 单独的路径参数直接使用 OsStr，不经过有损的 UTF-8 转换
+            argv.extend(paths.iter().map(|p| p.as_os_str().to_os_string()));
+            substituted = true;
+        } else {
+            let (arg, sub) = substitute(part, &all);
+            argv.push(arg.into());
+            substituted |= sub;
+        }
+    }
+    if !substituted {
+        argv.extend(paths.iter().map(|p| p.as_os_str().to_os_string()));
+    }
+
+    let Some((prog, rest)) = argv.split_first() else {
+        return Err(ArgErr { arg: "exec command" }.into());
+    };
+    let status = Command::new(prog).args(rest).status()?;
+    if !status.success() {
+        return Err(ExecErr {
+            cmd: template.to_string(),
+            status,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// 输出一个文件的替换结果补丁
+///
+/// 重新读取整个文件，逐行替换后和原内容比较，生成统一差异格式的补丁。
+/// 文件不是合法的 UTF-8 时和搜索一样跳过。
+///
+/// # 参数
+/// * `out` - 输出目标
+/// * `p` - 包含匹配的文件
+/// * `re` - 编译好的正则表达式对象
+/// * `cfg` - 搜索配置
+/// * `t` - `--replace` 的替换模板
+/// * `color` - 是否给补丁加上终端颜色
+fn write_patch(out: &Output, p: &Path, re: &Regex, cfg: &GrepConfig, t: &Template, color: bool) -> Result<(), Error> {
+    if p == Path::new("-") {
+        return Err(PatchStdin.into());
+    }
+    let Ok(old) = String::from_utf8(std::fs::read(p)?) else {
+        return Ok(());
+    };
+    // 被 --line-prefix / --skip-prefix 等排除的行在搜索中不算匹配，也不替换
+    let new = t.replace_lines(re, &old, cfg.newline, |l| cfg.line_allowed(l), |new| cfg.verified(new));
+    write_diff(out, p, &old, &new, color);
+    Ok(())
+}
+
+/// 输出一个文件新旧内容的补丁，头部是 `a/PATH` / `b/PATH`
+fn write_diff(out: &Output, p: &Path, old: &str, new: &str, color: bool) {
+    let name = diff::patch_path(p);
+    let patch = diff::unified(&format!("a/{}", name), &format!("b/{}", name), old, new, 3);
+    if color {
+        out.raw(&diff::colorize(&patch));
+    } else {
+        out.raw(&patch);
+    }
+}
+
+/// `-A` / `-B` 要输出的上下文行：在某个匹配之前 `before` 行或者之后 `after` 行之内，本身不是匹配
+///
+/// `nearby` 是搜索返回的所有行，包括后来被筛掉的匹配；`nearby` 和 `v` 都按行号排列
+fn context_of<'a>(nearby: &'a [(usize, String)], v: &[Record], before: usize, after: usize) -> Vec<(usize, &'a str)> {
+    let mut out = Vec::new();
+    // 第一个窗口还没有在这一行之前结束的匹配
+    let mut m = 0;
+    for (line, tx) in nearby {
+        while v.get(m).is_some_and(|r| r.line + after < *line) {
+            m += 1;
+        }
+        let Some(r) = v.get(m) else { break };
+        if r.line <= line + before && v.binary_search_by_key(line, |r| r.line).is_err() {
+            out.push((*line, tx.as_str()));
+        }
+    }
+    out
+}
+
+/// 解析 `--field-range`，返回第一个字段和最后一个字段（None 表示直到行尾）
+fn parse_field_range(s: &str) -> Result<(usize, Option<usize>), FieldRangeErr> {
+    let err = || FieldRangeErr(s.to_string());
+    let (start, end) = s.split_once('-').ok_or_else(err)?;
+    let first: usize = start.trim().parse().map_err(|_| err())?;
+    let last = match end.trim() {
+        "" => None,
+        e => Some(e.parse::<usize>().map_err(|_| err())?),
+    };
+    if first == 0 || last.is_some_and(|l| l < first) {
+        return Err(err());
+    }
+    Ok((first, last))
+}
+
+/// `--error-if-matches` 和 `--error-if-no-matches` 数出的匹配
+///
+/// # 字段
+/// * `count` - 匹配的总数
+/// * `examples` - 前 `Gate::EXAMPLES` 处匹配的位置，失败时在说明中列出
+/// * `by_rule` - 使用 `--rules` 时每条规则的匹配数，下标和规则的顺序相同
+#[derive(Debug, Default)]
+struct Gate {
+    count: usize,
+    examples: Vec<String>,
+    by_rule: Vec<usize>,
+}
+
+impl Gate {
+    const EXAMPLES: usize = 5;
+
+    fn add(&mut self, location: String) {
+        self.count += 1;
+        if self.examples.len() < Gate::EXAMPLES {
+            self.examples.push(location);
+        }
+    }
+
+    /// 检查没有通过时返回说明，通过时返回 None
+    fn failure(&self, max: Option<usize>, required: bool, rules: &[config::RuleDef]) -> Option<String> {
+        let mut why = match max {
+            Some(max) if self.count > max => {
+                format!("--error-if-matches: 找到了 {} 处匹配，最多允许 {} 处", self.count, max)
+            }
+            _ if required && self.count == 0 => return Some("--error-if-no-matches: 没有找到任何匹配".to_string()),
+            _ => return None,
+        };
+        let per_rule: Vec<String> = rules
+            .iter()
+            .zip(&self.by_rule)
+            .filter(|&(_, &n)| n > 0)
+            .map(|(r, n)| format!("{} {} 处", r.name, n))
+            .collect();
+        if !per_rule.is_empty() {
+            why.push_str(&format!("（{}）", per_rule.join("，")));
+        }
+        let shown = if self.count > self.examples.len() { "前几处匹配" } else { "匹配的位置" };
+        why.push_str(&format!("，{}:", shown));
+        for e in &self.examples {
+            why.push_str("\n  ");
+            why.push_str(e);
+        }
+        Some(why)
+    }
+}
+
+/// `--format json` 输出的一个匹配，写成一行 JSON 对象
+///
+/// 行号从 1 开始，列号是第一个匹配从 1 开始的字符位置，见 record_match；
+/// `--show-pattern` 时 `patterns` 是匹配了这一行的模式的标签；
+/// `--rules` 时每条匹配的规则一个对象，`rule` 是其中的一条，列号是这条规则的第一个匹配
+fn json_record(path: &str, r: &Record, re: &Regex, patterns: Option<Vec<&str>>, rule: Option<&config::RuleDef>) -> String {
+    let re = rule.map_or(re, |d| &d.re);
+    let mut fields = vec![
+        ("path".to_string(), Json::String(path.to_string())),
+        ("line".to_string(), Json::Number((r.line + 1) as f64)),
+        ("column".to_string(), Json::Number(record_match(r, re).0 as f64)),
+        ("text".to_string(), Json::String(r.tx.clone())),
+    ];
+    if let Some(d) = rule {
+        fields.push(("rule".to_string(), Json::String(d.name.clone())));
+        fields.push(("severity".to_string(), Json::String(d.severity.name().to_string())));
+        if let Some(m) = &d.message {
+            fields.push(("message".to_string(), Json::String(m.clone())));
+        }
+    }
+    if let Some(replaced) = &r.replaced {
+        fields.push(("replaced".to_string(), Json::String(replaced.clone())));
+    }
+    if let Some(p) = patterns {
+        let p = p.into_iter().map(|l| Json::String(l.to_string())).collect();
+        fields.push(("patterns".to_string(), Json::Array(p)));
+    }
+    Json::Object(fields).render()
+}
+
+/// `--format github` 输出的一个匹配，GitHub Actions 会把它显示为代码上的注解
+///
+/// 没有规则时级别是 warning，说明是匹配的行；数据中的 `%` 和换行、属性中的 `:` 和 `,` 按命令的要求转义
+fn github_annotation(path: &str, line: usize, column: usize, tx: &str, rule: Option<&config::RuleDef>) -> String {
+    let data = |s: &str| s.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A");
+    let prop = |s: &str| data(s).replace(':', "%3A").replace(',', "%2C");
+    let level = match rule.map(|d| d.severity) {
+        Some(config::Severity::Error) => "error",
+        Some(config::Severity::Info) => "notice",
+        _ => "warning",
+    };
+    let title = rule.map_or(String::new(), |d| format!(",title={}", prop(&d.name)));
+    let message = rule.map_or(tx, |d| d.message_or(tx));
+    format!(
+        "::{} file={},line={},col={}{}::{}",
+        level,
+        prop(path.strip_prefix("./").unwrap_or(path)),
+        line,
+        column,
+        title,
+        data(message)
+    )
+}
+
+/// `-o` 要输出的匹配文本
+///
+/// 正则表达式模式下是这一行中每个非空的匹配，使用 `--replace` 时是每个匹配替换后的文本；
+/// 其他模式下只有一个匹配，见 record_match。
+fn matched_parts(r: &Record, re: &Regex, cfg: &GrepConfig) -> Vec<String> {
+    if r.word.is_some() || r.fuzzy.is_some() || cfg.phonetic.is_some() {
+        return vec![record_match(r, re).1.to_string()];
+    }
+    match_captures(re, &r.tx, cfg.overlapping)
+        .into_iter()
+        .filter(|caps| caps.get(0).is_some_and(|m| !m.is_empty()))
+        .map(|caps| match &cfg.replace {
+            Some(t) => {
+                let mut s = String::new();
+                t.expand(&caps, &mut s);
+                s
+            }
+            None => caps[0].to_string(),
+        })
+        .collect()
+}
+
+/// 用高亮样式标出一行中匹配到的文本
+///
+/// `--replace` 时输出的是替换后的内容，`--sound-like` 没有确切的匹配位置，这两种情况不标出匹配
+fn highlight_line(r: &Record, re: &Regex, cfg: &GrepConfig, hl: &HighlightStyle) -> String {
+    if let Some(tx) = &r.replaced {
+        return tx.clone();
+    }
+    if cfg.phonetic.is_some() {
+        return r.tx.clone();
+    }
+    let spans: Vec<(usize, usize)> = if r.word.is_some() || r.fuzzy.is_some() {
+        let m = record_match(r, re).1;
+        let start = m.as_ptr() as usize - r.tx.as_ptr() as usize;
+        vec![(start, start + m.len())]
+    } else {
+        match_spans(re, &r.tx, cfg.overlapping).into_iter().filter(|(s, e)| s < e).collect()
+    };
+    let mut s = String::with_capacity(r.tx.len());
+    let mut last = 0;
+    for (start, end) in spans {
+        // --overlapping 的匹配可以互相重叠，已经标出的部分不再重复
+        let start = start.max(last);
+        if start >= end {
+            continue;
+        }
+        s.push_str(&r.tx[last..start]);
+        s.push_str(&hl.matched.paint(&r.tx[start..end]));
+        last = end;
+    }
+    s.push_str(&r.tx[last..]);
+    s
+}
+
+/// 一条记录中每个匹配到的文本，用于 `--count-mode matches` / `bytes`
+///
+/// 和 matched_parts 不同，这里是替换之前的原文，空的匹配也算一次
+fn matched_spans<'a>(r: &'a Record, re: &Regex, cfg: &GrepConfig) -> Vec<&'a str> {
+    if r.word.is_some() || r.fuzzy.is_some() || cfg.phonetic.is_some() {
+        return vec![record_match(r, re).1];
+    }
+    match_spans(re, &r.tx, cfg.overlapping).into_iter().map(|(s, e)| &r.tx[s..e]).collect()
+}
+
+/// 主运行函数
+///
+/// 这个函数是程序的主要逻辑入口点，负责：
+/// 1. 解析命令行参数
+/// 2. 编译正则表达式
+/// 3. 调用路径处理函数
+/// 4. 处理结果和错误
+///
+/// # 返回值
+/// * `Ok(())` - 程序成功执行
+/// * `Err(Error)` - 执行过程中发生错误
+///
+/// # 错误处理
+/// 使用 failure 库的 Result 类型进行错误处理，
+/// 所有的 I/O 错误、正则表达式编译错误等都会被自动捕获
+///
+/// # 相关文档
+/// * Regex::new: <https://docs.rs/regex/latest/regex/struct.Regex.html#method.new>
+/// * Args::parse: <https://docs.rs/clap/latest/clap/trait.Parser.html#tymethod.parse>
+fn run() -> Result<(), Error> {
+    // 使用 clap 自动解析命令行参数
+    // 如果参数格式不正确，clap 会自动显示帮助信息并退出
+    let argv: Vec<OsString> = std::env::args_os().collect();
+    let mut args = Args::parse_from(&argv);
+    log::init(args.verbose, !args.no_warnings);
+
+    if let Some(shell) = args.generate_completions {
+        let script = completions::generate(shell, Args::command());
+        match &args.completions_output {
+            Some(f) => std::fs::write(f, script)?,
+            // 和搜索结果一样经过 Output，`| head` 提前关闭管道时安静地结束
+            None => Output::stdout().raw(&script),
+        }
+        return Ok(());
+    }
+    if args.generate_man_page {
+        let page = manpage::generate(Args::command(), args.man_section);
+        match &args.man_output {
+            Some(f) => std::fs::write(f, page)?,
+            None => Output::stdout().raw(&page),
+        }
+        return Ok(());
+    }
+
+    // 只有用到 profile 时才读取配置文件，配置文件有错误也不会影响普通搜索
+    if args.list_profiles || args.profile.is_some() {
+        let conf = config::load(args.config.as_deref())?;
+        if args.list_profiles {
+            config::list_profiles(&conf, &Output::stdout());
+            return Ok(());
+        }
+        if let Some(name) = &args.profile {
+            // 把 profile 展开成参数插在用户参数之前，再重新解析一遍
+            let mut full = vec![argv[0].clone()];
+            full.extend(config::profile_args(&conf, name)?);
+            full.extend(argv[1..].iter().cloned());
+            args = Args::parse_from(full);
+        }
+    }
+
+    // 模式和路径来自每个请求，命令行上的 -p 和 -f 都不需要
+    if args.serve {
+        let mut type_defs = TypeDefs::default();
+        for spec in &args.type_add {
+            type_defs.add(spec)?;
+        }
+        return serve::run(std::io::stdin().lock(), std::io::stdout(), type_defs, args.max_symlink_depth);
+    }
+
+    // -f 和 -p 可能来自 profile，所以放到最后检查
+    // 没有 -p 时第一个位置参数是模式，剩下的位置参数都是路径
+    let mut rest = args.args.iter().cloned();
+    // 使用了内置模式时 -p 是可选的
+    let mut builtins = args.builtin_pattern.clone();
+    if args.ip_address {
+        builtins.push(BuiltinPattern::IpAddress);
+    }
+    // --rules 的模式来自规则文件，合并起来的正则表达式只用于 --exec 的 {match} 等需要匹配位置的地方
+    let rules = match &args.rules {
+        Some(f) => config::load_rules(f, |p| if args.extended_regex { verbose_pattern(p) } else { p.to_string() })?,
+        None => Vec::new(),
+    };
+    // 词表模式下不需要 -p，正则表达式也不会被用到
+    let mut patterns = if rules.is_empty() {
+        args.pattern.clone()
+    } else {
+        rules.iter().map(|r| r.source()).collect()
+    };
+    if let Some(f) = &args.pattern_file {
+        let text = std::fs::read_to_string(f)?;
+        if args.extended_regex || args.strip_pattern_comments {
+            patterns.push(text);
+        } else {
+            patterns.extend(text.lines().filter(|l| !l.is_empty()).map(str::to_string));
+        }
+    }
+    if args.strip_pattern_comments {
+        patterns = patterns.iter().map(|p| strip_pattern_comments(p)).collect();
+    }
+    if args.expand_env {
+        patterns = patterns
+            .iter()
+            .map(|p| expand_env(p, args.expand_env_empty))
+            .collect::<Result<_, _>>()?;
+    }
+    if patterns.is_empty()
+        && args.word_list.is_none()
+        && builtins.is_empty()
+        && !args.match_empty_lines
+        && !args.all_lines
+    {
+        // --tui 可以从空模式开始，在界面中再输入
+        match rest.next() {
+            Some(p) => patterns.push(p),
+            None if args.tui => {}
+            None => return Err(MissingPattern.into()),
+        }
+    }
+    // --show-pattern 的 `-e NAME=REGEX`，名字不是模式的一部分
+    let pattern_names: Vec<Option<String>> = if args.show_pattern {
+        let (names, res): (Vec<_>, Vec<_>) = patterns.iter().map(|p| split_pattern_name(p)).unzip();
+        patterns = res;
+        names
+    } else {
+        Vec::new()
+    };
+    // 空模式在 grep 中表示匹配每一行，这里要求明确地使用 --all-lines；
+    // --pattern-file 中的空行在读取时已经跳过了，不会走到这里
+    if patterns.iter().any(String::is_empty) {
+        return Err(EmptyPattern.into());
+    }
+    // 匹配一切的模式通常是手误，搜索前先提醒一下
+    // 模糊匹配、语音匹配、词表模式和 -F 的模式不是正则表达式，不做检查
+    // 这两种提醒和库中的警告一样是 warn 级别的日志，--no-warnings 和 RUST_LOG 对它们的作用相同
+    let fixed_strings = args.fixed_strings || args.literal_match_only;
+    if args.fuzzy.is_none() && !args.sound_like && !fixed_strings {
+        for p in &patterns {
+            if let Some(why) = catastrophic_pattern(p) {
+                log::log(
+                    log::Level::Warn,
+                    module_path!(),
+                    format_args!("模式 {:?} {}，会输出几乎所有的行；请使用更具体的模式（用 --no-warnings 关闭这个警告）", p, why),
+                );
+            }
+            // 能编译的通配符式模式按正则表达式匹配，多半不是用户想要的；无法编译的在编译时报告
+            if glob_shaped(p) && Regex::new(p).is_ok() {
+                log::log(log::Level::Warn, module_path!(), format_args!("{}", glob_hint(p)));
+            }
+        }
+    }
+    if args.extended_regex {
+        patterns = patterns.iter().map(|p| verbose_pattern(p)).collect();
+    }
+    // -F 只有一个模式时按字面查找，转义之前的模式就是要查找的子串
+    let literal = (fixed_strings && patterns.len() == 1 && builtins.is_empty() && !args.match_empty_lines)
+        .then(|| patterns[0].clone());
+    if fixed_strings {
+        patterns = patterns.iter().map(|p| regex::escape(p)).collect();
+    }
+    // --match-empty-lines 相当于多了一个 `^$` 模式，它本来就是要匹配空行，不在上面的检查范围内
+    if args.match_empty_lines {
+        patterns.push("^$".to_string());
+        // --match-all 要求每个模式都匹配同一行，其他模式很少能匹配空行
+        if args.match_all && patterns.len() + builtins.len() > 1 {
+            log::log(
+                log::Level::Warn,
+                module_path!(),
+                format_args!("--match-empty-lines 和 --match-all 同时使用时，只有其他模式也能匹配空行才会有结果"),
+            );
+        }
+    }
+    // 模糊匹配和语音匹配按字面比较，只能处理一个模式
+    if patterns.len() > 1 && (args.fuzzy.is_some() || args.sound_like) {
+        return Err(TooManyPatterns {
+            flags: "--fuzzy 和 --sound-like",
+        }
+        .into());
+    }
+    if patterns.len() > 1 && args.literal_match_only {
+        return Err(TooManyPatterns {
+            flags: "--literal-match-only",
+        }
+        .into());
+    }
+    // 把多个用户模式和内置模式合并成一个正则表达式
+    let pattern = if patterns.len() == 1 && builtins.is_empty() {
+        patterns[0].clone()
+    } else {
+        builtin::combine(&patterns, &builtins)
+    };
+    let paths: Vec<String> = args.file.iter().cloned().chain(rest).collect();
+    if args.filter && !paths.is_empty() {
+        return Err(FilterPaths(paths.join(" ")).into());
+    }
+    // --staged / --changed 通常在仓库中直接运行，没有给出路径时搜索当前目录
+    let selection = if args.git_tracked {
+        Some(git::Selection::Tracked)
+    } else if args.staged {
+        Some(git::Selection::Staged)
+    } else {
+        args.changed.clone().map(git::Selection::Changed)
+    };
+    let paths = if paths.is_empty() && matches!(selection, Some(git::Selection::Staged | git::Selection::Changed(_))) {
+        vec![".".to_string()]
+    } else {
+        paths
+    };
+    if paths.is_empty() && !args.filter {
+        return Err(ArgErr { arg: "file" }.into());
+    }
+
+    // 内置的文件类型加上 --type-add 定义的类型，写错的类型名在搜索之前报告
+    let mut type_defs = TypeDefs::default();
+    for spec in &args.type_add {
+        type_defs.add(spec)?;
+    }
+    for name in args.file_type.iter().chain(&args.type_not) {
+        type_defs.globs(name)?;
+    }
+
+    if args.print_rg_command {
+        let t = rg::translate(&args, &patterns, &builtins, &paths, &type_defs)?;
+        if !args.no_warnings {
+            for w in &t.warnings {
+                eprintln!("警告: {}", w);
+            }
+        }
+        Output::stdout().line(format_args!("{}", t.command));
+        return Ok(());
+    }
+
+    // 把搜索路径换成其中被选中的文件，之后的搜索、监视都只针对这些文件
+    let paths = match &selection {
+        Some(sel) => git::select_files(&paths, sel, args.git_untracked, !args.no_warnings)?,
+        None => paths,
+    };
+
+    // 编译用户提供的正则表达式模式
+    // 如果正则表达式语法错误，这里会返回编译错误
+    // 模糊匹配和语音匹配模式下模式是普通文本，先转义再编译，避免其中的元字符导致编译失败
+    let re = if args.fuzzy.is_some() || args.sound_like {
+        Regex::new(&regex::escape(&pattern))?
+    } else {
+        Regex::new(&pattern).map_err(|err| match patterns.iter().find(|p| glob_shaped(p)) {
+            Some(p) => GlobLikePattern { err, hint: glob_hint(p) }.into(),
+            None => Error::from(err),
+        })?
+    };
+
+    // 根据命令行参数构造搜索配置
+    let mut cfg = GrepConfig::default();
+    if let Some(max_edits) = args.fuzzy {
+        // 空模式与任何子串的距离都只取决于子串长度，没有意义
+        if pattern.is_empty() {
+            return Err(ArgErr { arg: "pattern" }.into());
+        }
+        // 负数或 NaN 的时间上限会在这里报错
+        let limit = match args.fuzzy_cpu_limit {
+            Some(secs) => Some(Duration::try_from_secs_f64(secs)?),
+            None => None,
+        };
+        cfg.fuzzy = Some(FuzzyConfig {
+            pattern: pattern.clone(),
+            max_edits,
+            started: Instant::now(),
+            limit,
+        });
+    }
+    if args.sound_like {
+        cfg.phonetic = Some(PhoneticConfig::new(args.phonetic_algo, &pattern));
+    }
+    if let Some(wl) = &args.word_list {
+        cfg.words = Some(WordList::load(
+            wl,
+            args.word_list_case_insensitive,
+            args.word_list_whole_word,
+        )?);
+    }
+    if let Some(t) = args.replace.as_ref().or(args.write_replace.as_ref()) {
+        // 在开始搜索之前校验模板，避免处理到一半才发现模板写错了
+        cfg.replace = Some(Template::parse(t, &re)?);
+    }
+    // --insert-before / --insert-after: 前 + 匹配（或替换结果）+ 后
+    if args.insert_before.is_some() || args.insert_after.is_some() {
+        let mut parts = Vec::new();
+        if let Some(t) = &args.insert_before {
+            parts.push(Template::parse(t, &re)?);
+        }
+        parts.push(cfg.replace.take().unwrap_or_else(Template::whole_match));
+        if let Some(t) = &args.insert_after {
+            parts.push(Template::parse(t, &re)?);
+        }
+        cfg.replace = Some(Template::concat(&parts));
+    }
+    if let Some(v) = &args.replace_verify {
+        if cfg.replace.is_none() {
+            return Err(VerifyWithoutReplace.into());
+        }
+        cfg.replace_verify = Some(Regex::new(v)?);
+        cfg.replace_verify_skip = args.replace_verify_skip;
+    }
+    cfg.literal = literal.as_deref().map(LiteralMatcher::new);
+    // 只有在整个内容上查找时才用得到 DFA，按字面、模糊、语音和词表匹配都不需要
+    if let Some(dir) = &args.cache_dir
+        && !args.no_cache
+        && cfg.literal.is_none()
+        && cfg.fuzzy.is_none()
+        && cfg.phonetic.is_none()
+        && cfg.words.is_none()
+    {
+        match dfacache::load_or_build(dir, re.as_str()) {
+            Ok((a, _)) => cfg.automaton = Some(a),
+            Err(e) => debug!("不使用 --cache-dir: {}", e),
+        }
+    }
+    // `{{n}}` 按搜索的顺序编号，目录中的条目要按名字排序，每次运行的编号才一样
+    if let Some(t) = &cfg.replace
+        && t.has_counter()
+    {
+        if args.counter_per_file {
+            t.set_counter_per_file();
+        }
+        cfg.sorted_walk = true;
+    }
+    if let Some(ck) = &args.checkpoint
+        && args.resume
+    {
+        cfg.skip_dirs = read_checkpoint(ck)?;
+    }
+    cfg.type_filter = args.type_filter;
+    if !args.file_type.is_empty() || !args.type_not.is_empty() {
+        cfg.type_select = Some(type_defs.select(&args.file_type, &args.type_not)?);
+    }
+    cfg.max_symlink_depth = args.max_symlink_depth;
+    cfg.crlf_is_lf = args.crlf_is_lf;
+    cfg.newline = args.newline;
+    cfg.skip_matches = args.skip_matches;
+    cfg.all_lines = args.all_lines;
+    cfg.overlapping = args.overlapping;
+    cfg.warn_mixed_endings = args.warn_mixed_endings;
+    cfg.encoding_chain = args.encoding_chain.clone();
+    // --force-write 要能搜索到不是 UTF-8 的文件，和 rewrite 一样按 latin1 解码
+    if args.force_write && cfg.encoding_chain.is_empty() {
+        cfg.encoding_chain = vec![Encoding::Utf8, Encoding::Latin1];
+    }
+    cfg.binary_scan_bytes = if args.force_write { 0 } else { args.binary_scan_bytes };
+    if let Some(r) = args.null_ratio
+        && !(0.0..=1.0).contains(&r)
+    {
+        return Err(NullRatioErr(r).into());
+    }
+    cfg.null_ratio = args.null_ratio;
+    cfg.io_retry = args.io_retry;
+    cfg.before_context = args.before_context;
+    cfg.after_context = args.after_context;
+    if let Some(cp) = &args.context_pattern {
+        cfg.context_re = Some(Regex::new(cp)?);
+        cfg.context_after = args.context_after.unwrap_or_default();
+    }
+    if let Some(separator) = args.field_separator {
+        let (first, last) = match (&args.field_range, args.field_number) {
+            (Some(r), _) => parse_field_range(r)?,
+            (None, Some(n)) => (n as usize, Some(n as usize)),
+            (None, None) => (1, None),
+        };
+        cfg.fields = Some(Fields { separator, first, last });
+    }
+    cfg.only_field = args.only_field;
+    for r in &rules {
+        cfg.rules.push(Rule {
+            name: r.name.clone(),
+            paths: r.paths.iter().map(|g| Glob::new(g)).collect::<Result<_, _>>()?,
+            re: r.re.clone(),
+        });
+        info!("规则 {}: {:?} 中搜索 {}", r.name, r.paths, r.pattern);
+    }
+    if let Some(cmd) = &args.pre {
+        cfg.pre = Some(Preprocessor {
+            cmd: cmd.clone(),
+            globs: args.pre_glob.iter().map(|g| Glob::new(g)).collect::<Result<_, _>>()?,
+            timeout: Some(Duration::try_from_secs_f64(args.pre_timeout)?),
+        });
+    }
+    cfg.skip_empty_lines = args.skip_empty_lines;
+    cfg.profile_regex = args.profile_regex;
+    cfg.timing = (args.debug_timing || args.profile_regex || args.profile_per_line).then(Default::default);
+    cfg.profile_per_line = args.profile_per_line;
+    cfg.match_buffer_size = args.match_buffer_size;
+    cfg.max_line_bytes = Some(usize::try_from(args.max_line_bytes).unwrap_or(usize::MAX));
+    cfg.ignore_marker = (!args.no_ignore_marker).then(|| args.ignore_marker.clone());
+    cfg.search_zip = args.search_zip;
+    cfg.search_archives = args.archives;
+    cfg.max_archive_depth = args.max_archive_depth;
+    cfg.max_member_size = Some(args.max_member_size);
+    cfg.line_prefixes = args.line_prefix.clone();
+    cfg.skip_prefixes = args.skip_prefix.clone();
+    if let Some(g) = &args.debug_skip {
+        cfg.debug_skip = true;
+        if !g.is_empty() {
+            cfg.debug_skip_glob = Some(Glob::new(g)?);
+        }
+    }
+
+    // clap 保证 --filter 和 -r 一起出现
+    if args.filter
+        && let Some(t) = &cfg.replace
+    {
+        let mut input = std::io::BufReader::new(std::io::stdin().lock());
+        let mut output = std::io::BufWriter::new(std::io::stdout().lock());
+        return match filter::filter(&mut input, &mut output, &re, &cfg, t) {
+            Ok(0) => Err(ExitStatus(1).into()),
+            Ok(_) => Ok(()),
+            // 下游提前退出（例如 `| head`）时和 sed 一样安静地结束
+            Err(e) if e.downcast_ref::<std::io::Error>().map(|e| e.kind()) == Some(std::io::ErrorKind::BrokenPipe) => {
+                Ok(())
+            }
+            Err(e) => Err(e),
+        };
+    }
+
+    let mut hl = match &args.match_highlight_style {
+        Some(spec) => HighlightStyle::parse(spec)?,
+        None => HighlightStyle::default(),
+    };
+    if args.no_bold {
+        hl = hl.without_bold();
+    }
+
+    if args.tui {
+        return tui::run(&paths, &pattern, &cfg, &hl);
+    }
+
+    if args.interactive && !std::io::stdout().is_terminal() {
+        return Err(NotATerminal.into());
+    }
+
+    // 选择输出目标：标准输出是终端且没有禁用时使用分页器
+    let use_pager = std::io::stdout().is_terminal()
+        && !args.no_pager
+        && !args.interactive
+        && !args.confirm
+        && !args.watch
+        && !args.follow_lines
+        && args.exec.is_none()
+        && args.exec_file.is_none()
+        && args.exec_batch.is_none();
+    // 模板写错时在搜索之前报告
+    let record_format = args
+        .record_format
+        .as_deref()
+        .map(|t| RecordFormat::parse(t, args.timestamp_precision))
+        .transpose()?;
+    let mut out = if use_pager {
+        Output::pager(&args.pager)
+    } else {
+        Output::stdout()
+    };
+    out.set_separators_to_stderr(args.emit_separators_to_stderr);
+    out.set_headings_to_stderr(args.emit_headings_to_stderr);
+    if args.print0 {
+        out.set_record_separator(b"\0");
+    } else {
+        out.set_record_separator(args.line_ending_output.unwrap_or_else(LineEnding::native).bytes());
+    }
+
+    // 调用递归路径处理函数
+    // 使用闭包作为回调函数来处理文件处理结果和错误
+
+    // 注释掉的代码：处理单个文件的方式
+    //let p = process_file(args.file, &re);
+
+    // 错误处理回调函数
+    // 这个闭包会在处理过程中发生错误时被调用
+    // 错误信息输出到标准错误，标准输出中只有搜索结果
+    let ef = |e: Error| {
+        eprintln!("处理错误: {}", e);
+    };
+
+    // --exec-batch 需要在搜索结束后统一执行，这里先收集包含匹配的文件
+    let batch: RefCell<Vec<PathBuf>> = RefCell::new(Vec::new());
+
+    // 文件处理完成回调函数
+    // 这个闭包会在每个文件处理完成后被调用
+    // --interactive 需要在搜索结束后提示选择，匹配结果要一直保留在内存中
+    let hits: RefCell<Vec<interactive::Hit>> = RefCell::new(Vec::new());
+
+    // --group-by pattern 用 RegexSet 判断每一行匹配了哪些模式
+    // 标签是用户写的模式，内置模式使用它的名字
+    let (group_set, labels) = if args.group_by == Some(GroupBy::Pattern) {
+        let sources = patterns
+            .iter()
+            .cloned()
+            .chain(builtins.iter().map(|b| builtin::resolve_builtin(*b).to_string()));
+        let labels: Vec<String> = patterns
+            .iter()
+            .cloned()
+            .chain(builtins.iter().map(|b| format!("--builtin-pattern {}", builtin::name(*b))))
+            .collect();
+        (Some(RegexSet::new(sources)?), labels)
+    } else {
+        (None, Vec::new())
+    };
+    let groups: RefCell<Vec<Vec<String>>> = RefCell::new(vec![Vec::new(); labels.len()]);
+    // --show-pattern 同样用 RegexSet 判断，标签优先使用 `NAME=` 给出的名字
+    let pattern_set = if args.show_pattern {
+        let sources = patterns
+            .iter()
+            .cloned()
+            .chain(builtins.iter().map(|b| builtin::resolve_builtin(*b).to_string()));
+        let names: Vec<String> = patterns
+            .iter()
+            .zip(&pattern_names)
+            .map(|(p, n)| n.clone().unwrap_or_else(|| p.clone()))
+            .chain(builtins.iter().map(|b| builtin::name(*b).to_string()))
+            .collect();
+        Some((RegexSet::new(sources)?, names))
+    } else {
+        None
+    };
+    // 一条记录匹配的模式的标签
+    let pattern_labels = |r: &Record| -> Option<Vec<&str>> {
+        let (set, names) = pattern_set.as_ref()?;
+        Some(set.matches(&r.tx).iter().map(|i| names[i].as_str()).collect())
+    };
+    // 加上 `[标签] ` 的路径
+    let tagged = |path: String, r: &Record| match pattern_labels(r) {
+        Some(l) => format!("[{}] {}", l.join(","), path),
+        None => path,
+    };
+    // --group-by directory 按目录缓存的结果，BTreeMap 保证目录按字典序输出
+    let dir_groups: RefCell<BTreeMap<String, Vec<String>>> = RefCell::new(BTreeMap::new());
+    // 只有一个搜索路径并且它是目录时，目录的小节相对于它，直接在它下面的文件在 `.` 一节中
+    let group_root = match paths.as_slice() {
+        [root] if Path::new(root).is_dir() => Some(PathBuf::from(root)),
+        _ => None,
+    };
+    // --dedup-by: 已经输出过的值和省略的重复结果数
+    let seen: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+    let dups = RefCell::new(0usize);
+    // `-o` 时 --dedup-by match 逐个匹配比较，在输出时去重
+    let dedup_parts = args.only_matching && args.dedup_by == Some(DedupBy::Match);
+    // --confirm: 逐条询问是否输出，回答 q 之后停止搜索
+    let confirm = match args.confirm {
+        true => Some(RefCell::new(interactive::Confirm::open().map_err(NoTty)?)),
+        false => None,
+    };
+    let quit = Cell::new(false);
+    // --rules 的 error 级别的匹配数，--format sarif 等到搜索结束才输出的结果
+    let rule_errors = Cell::new(0usize);
+    let sarif_results: RefCell<Vec<Json>> = RefCell::new(Vec::new());
+    let gate = RefCell::new(Gate {
+        by_rule: vec![0; rules.len()],
+        ..Default::default()
+    });
+    // 确认过的结果输出之后再让 process_path 停下来
+    let stop_if_quit = || -> Result<(), Error> {
+        if quit.get() {
+            return Err(Halt {
+                reason: "--confirm 时回答了 q".to_string(),
+            }
+            .into());
+        }
+        Ok(())
+    };
+
+    // --match-all 时每个模式单独编译，合并后的表达式只用来找出候选行
+    let match_all = if args.match_all && patterns.len() + builtins.len() > 1 {
+        let sources: Vec<String> = patterns
+            .iter()
+            .cloned()
+            .chain(builtins.iter().map(|b| builtin::resolve_builtin(*b).to_string()))
+            .collect();
+        Some(matchall::MatchAll::new(&sources, args.parallel_regex.unwrap_or(1) as usize)?)
+    } else {
+        None
+    };
+
+    // 外部命令失败时：默认报告错误后继续，--exec-halt-on-error 时终止搜索
+    let exec_result = |r: Result<(), Error>| match r {
+        Err(e) if args.exec_halt_on_error => Err(Error::from(Halt {
+            reason: format!("外部命令失败: {}", e),
+        })),
+        Err(e) => {
+            ef(e);
+            Ok(())
+        }
+        Ok(()) => Ok(()),
+    };
+
+    // --write-replace 修改的文件数和替换总数
+    let rewritten = RefCell::new((0usize, 0usize));
+    let write_opts = rewrite::Options {
+        backup: args.backup.as_deref(),
+        force: args.force_write,
+    };
+    // --format sqlite: 结果写入数据库
+    let sqlite_output = args.format == Some(OutputFormat::Sqlite);
+    if args.output.is_some() && !sqlite_output {
+        return Err(FormatErr("--output 只用于 --format sqlite").into());
+    }
+    #[cfg(not(feature = "sqlite"))]
+    if sqlite_output {
+        return Err(FormatErr("这个构建没有启用 sqlite feature，请用 `cargo build --features sqlite` 重新构建").into());
+    }
+    // --format json / --json-array: 每个匹配一个 JSON 对象
+    let json_output = args.json_array || args.format == Some(OutputFormat::Json);
+    if json_output && args.format.is_some_and(|f| f != OutputFormat::Json) {
+        return Err(FormatErr("--json-array 只能和 --format json 一起使用").into());
+    }
+    // --json-array 时是否还没有输出过对象，决定下一个对象之前要不要逗号
+    let json_first = Cell::new(true);
+    if args.json_array {
+        out.raw("[");
+    }
+    #[cfg(feature = "sqlite")]
+    let sink = match &args.output {
+        Some(db) if sqlite_output => {
+            let cmdline: Vec<String> = argv[1..].iter().map(|a| rg::quote(&a.to_string_lossy())).collect();
+            Some(sqlite::Sink::open(db, args.append, re.as_str(), &cmdline.join(" "))?)
+        }
+        _ => None,
+    };
+
+    // --suppressions 读入的条目和被抑制的匹配数
+    let suppressions = args.suppressions.as_deref().map(suppress::Suppressions::load).transpose()?;
+    let suppressed = RefCell::new(0usize);
+    // --baseline 读入的基线和基线之外的匹配数，--save-baseline 要保存的匹配
+    let known = args.baseline.as_deref().map(baseline::Baseline::load).transpose()?;
+    let new_matches = RefCell::new(0usize);
+    let saved = RefCell::new(Vec::new());
+    // --expect-matches 时等待核对的替换结果
+    let pending: RefCell<Vec<(PathBuf, rewrite::Plan)>> = RefCell::new(Vec::new());
+
+    // 搜索结果和 --diff / --patch 的补丁是否带颜色
+    let color = match args.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => std::io::stdout().is_terminal(),
+    };
+    // --wrap-matches 折行的宽度，不折行时为 None
+    let wrap_width = match args.wrap_width {
+        Some(w) => Some(usize::from(w)),
+        None if args.wrap_matches && std::io::stdout().is_terminal() => {
+            Some(output::terminal_size().map_or(80, |(w, _)| w))
+        }
+        None => None,
+    };
+    // --git-relative: 输出的路径相对于仓库根目录
+    let repo_root = if args.git_relative {
+        let root = std::env::current_dir().ok().and_then(|d| git::repo_root(&d));
+        if root.is_none() && !args.no_warnings {
+            eprintln!("警告: 当前目录不在 git 仓库中，--git-relative 不起作用，输出原来的路径");
+        }
+        root
+    } else {
+        None
+    };
+    let shown = |pt: &Path| match &repo_root {
+        Some(root) => git::relative_to(root, pt).display().to_string(),
+        None => pt.display().to_string(),
+    };
+    // 使用颜色时路径和行号的写法
+    let path_of = |pt: &Path| {
+        if color {
+            hl.filename.paint(&shown(pt))
+        } else {
+            shown(pt)
+        }
+    };
+    // `width` 是 --align 时行号的宽度，不对齐时为 0
+    let lineno_of = |line: usize, width: usize| {
+        let n = format!("{:>width$}", line + 1);
+        if color { hl.lineno.paint(&n) } else { n }
+    };
+    let line_of = |r: &Record, width: usize| lineno_of(r.line, width);
+    // -A/-B: 是否输出上下文，上一条输出的结果的行号，以及之前的文件有没有输出过结果
+    let with_context = args.before_context > 0 || args.after_context > 0;
+    let last_shown: Cell<Option<usize>> = Cell::new(None);
+    let shown_any = Cell::new(false);
+    // 不相邻的两组结果之间输出 `--`，和 grep 相同；换到下一个文件时也算不相邻
+    let separate = |line: usize| {
+        if with_context {
+            let adjacent = last_shown.get().is_some_and(|l| l + 1 == line);
+            if !adjacent && shown_any.replace(true) {
+                outrec!(out, "--");
+            }
+            last_shown.set(Some(line));
+        }
+    };
+
+    // -c 是 --count-mode lines 的简写，--count-unique-matches 和 --frequency-analysis 是 --count-mode unique；
+    // --count-mode files 时统计包含匹配的文件数
+    let count_mode = args
+        .count_mode
+        .or(args.count.then_some(CountMode::Lines))
+        .or((args.count_unique_matches || args.frequency_analysis).then_some(CountMode::Unique))
+        .or(args.count_exit.then_some(CountMode::Files))
+        .or(args.top.is_some().then_some(CountMode::Lines));
+    if args.frequency_analysis
+        && let Some(mode) = count_mode.filter(|m| *m != CountMode::Unique)
+    {
+        return Err(FrequencyModeErr(clap::ValueEnum::to_possible_value(&mode).map(|v| v.get_name().to_string()).unwrap_or_default()).into());
+    }
+    if args.top.is_some()
+        && let Some(mode) = count_mode.filter(|m| matches!(m, CountMode::Files | CountMode::Unique))
+    {
+        return Err(TopModeErr(clap::ValueEnum::to_possible_value(&mode).map(|v| v.get_name().to_string()).unwrap_or_default()).into());
+    }
+    if args.top.is_some() && args.format.is_some_and(|f| !matches!(f, OutputFormat::Text | OutputFormat::Json)) {
+        return Err(FormatErr("--top 只能和 --format text 或 json 一起使用").into());
+    }
+    let counted_files = RefCell::new(0usize);
+    // --count-exit: 搜索结束时包含匹配的文件数，作为退出状态
+    let exit_files = Cell::new(0usize);
+    // --count-mode unique: 每个不同的匹配文本出现的次数
+    let unique: RefCell<HashMap<String, usize>> = RefCell::new(HashMap::new());
+    // --output-sorted-unique-texts: 每个不同的行或者匹配文本出现的次数，按文本排列
+    let sorted_unique = args.output_sorted_unique_texts;
+    let texts: RefCell<BTreeMap<String, usize>> = RefCell::new(BTreeMap::new());
+    // --top: 每个包含匹配的文件的数量和输出中的路径
+    let top: RefCell<Vec<(usize, String)>> = RefCell::new(Vec::new());
+
+    // 输出 --write-replace 改写一个文件的结果
+    let report = |pt: &Path, outcome: Result<rewrite::Outcome, Error>| match outcome {
+        Ok(rewrite::Outcome::Changed(n)) => {
+            let mut total = rewritten.borrow_mut();
+            total.0 += 1;
+            total.1 += n;
+            outln!(out, "{}: {} 处替换", shown(pt), n);
+        }
+        Ok(rewrite::Outcome::Unchanged) => {}
+        Ok(rewrite::Outcome::Skipped(why)) => {
+            if !args.no_warnings {
+                eprintln!("警告: 没有修改 {}: {}（用 --force-write 强制修改）", pt.display(), why);
+            }
+        }
+        Err(e) => ef(e),
+    };
+
+    let ff = |pt: &Path, v: Vec<Record>| {
+        // -A/-B: 下面的筛选只针对匹配，所有的行都留在 nearby 中，被筛掉的匹配输出时也可以作为上下文
+        let nearby: Vec<(usize, String)> =
+            if with_context { v.iter().map(|r| (r.line, r.tx.clone())).collect() } else { Vec::new() };
+        let v: Vec<Record> = if with_context { v.into_iter().filter(|r| !r.is_context).collect() } else { v };
+        last_shown.set(None);
+        let v = match &match_all {
+            Some(m) => m.filter(v),
+            None => v,
+        };
+        // 行内标记已经在搜索时标出，抑制文件在这里检查；所有条目都要检查，才能知道哪些条目没有用过
+        let v: Vec<Record> = v
+            .into_iter()
+            .filter(|r| {
+                let by_file = suppressions.as_ref().is_some_and(|s| s.matches(pt, r));
+                if r.suppressed || by_file {
+                    *suppressed.borrow_mut() += 1;
+                    return args.show_suppressed;
+                }
+                true
+            })
+            .collect();
+        if args.save_baseline.is_some() {
+            saved.borrow_mut().extend(v.iter().map(|r| baseline::entry(&shown(pt), r)));
+        }
+        let v = match &known {
+            Some(b) => {
+                let v = b.new_matches(&shown(pt), v);
+                *new_matches.borrow_mut() += v.len();
+                v
+            }
+            None => v,
+        };
+        let v = match args.dedup_by {
+            Some(by) if !dedup_parts => {
+                let mut seen = seen.borrow_mut();
+                let mut kept = Vec::with_capacity(v.len());
+                for r in v {
+                    let new = match by {
+                        DedupBy::Line => seen.insert(r.tx.clone()),
+                        // 这一行的每个匹配都要记下来，不能在第一个新的匹配处停下
+                        DedupBy::Match => matched_spans(&r, &re, &cfg)
+                            .into_iter()
+                            .filter(|m| !m.is_empty() && seen.insert(m.to_string()))
+                            .count()
+                            > 0,
+                        DedupBy::Path => seen.insert(shown(pt)),
+                    };
+                    if new {
+                        kept.push(r);
+                    } else {
+                        *dups.borrow_mut() += 1;
+                    }
+                }
+                kept
+            }
+            _ => v,
+        };
+        let v = match &confirm {
+            Some(c) => {
+                let mut c = c.borrow_mut();
+                let mut kept = Vec::with_capacity(v.len());
+                for r in v {
+                    let tx = r.replaced.as_deref().unwrap_or(&r.tx);
+                    match c.ask(&format!("{}:{}: {}", shown(pt), r.line + 1, tx)) {
+                        interactive::Answer::Yes => kept.push(r),
+                        interactive::Answer::No => {}
+                        interactive::Answer::Quit => {
+                            quit.set(true);
+                            break;
+                        }
+                    }
+                }
+                kept
+            }
+            None => v,
+        };
+        // --rules: 经过上面的筛选之后仍然输出的 error 级别的匹配决定退出状态
+        rule_errors.set(
+            rule_errors.get()
+                + v.iter()
+                    .filter(|r| r.rules.iter().any(|&i| rules[i].severity == config::Severity::Error))
+                    .count(),
+        );
+        // --error-if-matches / --error-if-no-matches: 同样只数筛选之后的匹配，记下前几处的位置用于失败时的说明
+        if args.error_if_matches.is_some() || args.error_if_no_matches {
+            let mut g = gate.borrow_mut();
+            for r in &v {
+                if rules.is_empty() {
+                    g.add(format!("{}:{}", shown(pt), r.line + 1));
+                }
+                for &i in &r.rules {
+                    g.add(format!("{}:{} [{}]", shown(pt), r.line + 1, rules[i].name));
+                    g.by_rule[i] += 1;
+                }
+            }
+        }
+        let context = if with_context {
+            context_of(&nearby, &v, args.before_context, args.after_context)
+        } else {
+            Vec::new()
+        };
+        // --align: 结果已经按文件缓存在 v 中，先求出最大行号的位数
+        let width = match v.iter().map(|r| r.line).chain(context.iter().map(|c| c.0)).map(|l| l + 1).max() {
+            Some(max) if args.align => max.to_string().len(),
+            _ => 0,
+        };
+        if let Some(mode) = count_mode {
+            let n = match mode {
+                CountMode::Lines => v.len(),
+                CountMode::Matches => v.iter().map(|r| matched_spans(r, &re, &cfg).len()).sum(),
+                CountMode::Bytes => v
+                    .iter()
+                    .flat_map(|r| matched_spans(r, &re, &cfg))
+                    .map(str::len)
+                    .sum(),
+                CountMode::Files => {
+                    if !v.is_empty() {
+                        *counted_files.borrow_mut() += 1;
+                    }
+                    0
+                }
+                CountMode::Unique => {
+                    let mut unique = unique.borrow_mut();
+                    for m in v.iter().flat_map(|r| matched_spans(r, &re, &cfg)).filter(|m| !m.is_empty()) {
+                        match unique.get_mut(m) {
+                            Some(n) => *n += 1,
+                            None => {
+                                unique.insert(m.to_string(), 1);
+                            }
+                        }
+                    }
+                    0
+                }
+            };
+            if args.top.is_some() {
+                if n > 0 {
+                    top.borrow_mut().push((n, shown(pt)));
+                }
+            } else if !matches!(mode, CountMode::Files | CountMode::Unique) {
+                outrec!(out, "{}:{}", path_of(pt), n);
+            }
+        } else if sorted_unique {
+            // 搜索结束后统一输出
+            let mut texts = texts.borrow_mut();
+            for r in &v {
+                let found: Vec<&str> = if args.only_matching {
+                    matched_spans(r, &re, &cfg).into_iter().filter(|m| !m.is_empty()).collect()
+                } else {
+                    vec![r.tx.as_str()]
+                };
+                for m in found {
+                    match texts.get_mut(m) {
+                        Some(n) => *n += 1,
+                        None => {
+                            texts.insert(m.to_string(), 1);
+                        }
+                    }
+                }
+            }
+        } else if sqlite_output {
+            // 结果只写入数据库，标准输出上没有内容
+            #[cfg(feature = "sqlite")]
+            if let Some(db) = &sink
+                && !v.is_empty()
+            {
+                let rows: Vec<_> = v.iter().map(|r| (r.line + 1, record_match(r, &re).0, r.tx.as_str())).collect();
+                db.write_file(pt, &rows)?;
+            }
+        } else if json_output {
+            for r in &v {
+                // 没有使用 --rules 时每个匹配一个对象，使用时每条匹配的规则一个对象
+                let objs: Vec<String> = if r.rules.is_empty() {
+                    vec![json_record(&shown(pt), r, &re, pattern_labels(r), None)]
+                } else {
+                    r.rules.iter().map(|&i| json_record(&shown(pt), r, &re, None, Some(&rules[i]))).collect()
+                };
+                for obj in objs {
+                    if args.json_array {
+                        out.raw(if json_first.replace(false) { "\n" } else { ",\n" });
+                        out.raw(&obj);
+                    } else {
+                        outln!(out, "{}", obj);
+                    }
+                }
+            }
+        } else if args.format == Some(OutputFormat::Sarif) {
+            // SARIF 是一个完整的文档，搜索结束后再输出
+            let mut results = sarif_results.borrow_mut();
+            for r in &v {
+                if r.rules.is_empty() {
+                    results.push(sarif::result(&shown(pt), r.line + 1, record_match(r, &re).0, &r.tx, None));
+                }
+                for &i in &r.rules {
+                    let col = record_match(r, &rules[i].re).0;
+                    results.push(sarif::result(&shown(pt), r.line + 1, col, &r.tx, Some((i, &rules[i]))));
+                }
+            }
+        } else if args.format == Some(OutputFormat::Github) {
+            for r in &v {
+                if r.rules.is_empty() {
+                    outrec!(out, "{}", github_annotation(&shown(pt), r.line + 1, record_match(r, &re).0, &r.tx, None));
+                }
+                for &i in &r.rules {
+                    let col = record_match(r, &rules[i].re).0;
+                    outrec!(out, "{}", github_annotation(&shown(pt), r.line + 1, col, &r.tx, Some(&rules[i])));
+                }
+            }
+        } else if args.diff {
+            // --diff 和 --write-replace 计算同样的替换结果，但只输出补丁
+            if !v.is_empty()
+                && let Some(t) = &cfg.replace
+            {
+                match rewrite::plan(pt, &re, &cfg, t, args.force_write) {
+                    Ok(rewrite::Plan::Change { old, new, count, .. }) => {
+                        let mut total = rewritten.borrow_mut();
+                        total.0 += 1;
+                        total.1 += count;
+                        write_diff(&out, pt, &old, &new, color);
+                    }
+                    Ok(rewrite::Plan::Unchanged) => {}
+                    Ok(rewrite::Plan::Skipped(why)) => {
+                        if !args.no_warnings {
+                            eprintln!("警告: 不会修改 {}: {}（用 --force-write 强制修改）", pt.display(), why);
+                        }
+                    }
+                    Err(e) => ef(e),
+                }
+            }
+        } else if args.write_replace.is_some() {
+            if !v.is_empty()
+                && let Some(t) = &cfg.replace
+            {
+                if args.expect_matches.is_some() {
+                    // 先只计算替换结果，搜索结束后核对了总数再写入
+                    match rewrite::plan(pt, &re, &cfg, t, args.force_write) {
+                        Ok(plan @ rewrite::Plan::Change { .. }) => pending.borrow_mut().push((pt.to_path_buf(), plan)),
+                        Ok(rewrite::Plan::Unchanged) => {}
+                        Ok(rewrite::Plan::Skipped(why)) => report(pt, Ok(rewrite::Outcome::Skipped(why))),
+                        Err(e) => ef(e),
+                    }
+                } else {
+                    report(pt, rewrite::rewrite(pt, &re, &cfg, t, &write_opts));
+                }
+            }
+        } else if args.patch {
+            if !v.is_empty()
+                && let Some(t) = &cfg.replace
+                && let Err(e) = write_patch(&out, pt, &re, &cfg, t, color)
+            {
+                ef(e);
+            }
+        } else if let Some(set) = &group_set {
+            // 按模式分组时先缓存起来，搜索结束后统一输出
+            let mut groups = groups.borrow_mut();
+            for r in &v {
+                for i in set.matches(&r.tx).iter() {
+                    groups[i].push(format!("{}:{}: {}", shown(pt), r.line + 1, r.tx));
+                }
+            }
+        } else if args.group_by == Some(GroupBy::Directory) {
+            let path = shown(pt);
+            let p = match group_root.as_deref().and_then(|root| pt.strip_prefix(root).ok()) {
+                Some(rel) => rel.to_path_buf(),
+                None => PathBuf::from(&path),
+            };
+            let dir = match p.parent() {
+                Some(d) if !d.as_os_str().is_empty() => d.display().to_string(),
+                _ => ".".to_string(),
+            };
+            let name = p.file_name().map_or(path.clone(), |n| n.to_string_lossy().into_owned());
+            let mut groups = dir_groups.borrow_mut();
+            let lines = groups.entry(dir).or_default();
+            for r in &v {
+                lines.push(format!("{}:{}: {}", name, r.line + 1, r.tx));
+            }
+        } else if args.interactive {
+            // 交互模式下为每个匹配编号，行号从 1 开始，和编辑器一致
+            let mut hits = hits.borrow_mut();
+            for r in &v {
+                hits.push(interactive::Hit {
+                    path: pt.to_path_buf(),
+                    line: r.line + 1,
+                });
+                outln!(out, "[{}] {}:{}: {}", hits.len(), shown(pt), r.line + 1, r.tx);
+            }
+        } else if args.only_matching {
+            // -o: 每个匹配输出一行，--o-inline 时同一行的匹配合并输出
+            // 替换模板中的 `{{n}}` 重新按输出的匹配编号
+            if let Some(t) = &cfg.replace {
+                t.rewind_file();
+            }
+            for r in &v {
+                let mut parts = matched_parts(r, &re, &cfg);
+                if dedup_parts {
+                    let mut seen = seen.borrow_mut();
+                    let before = parts.len();
+                    parts.retain(|m| seen.insert(m.clone()));
+                    *dups.borrow_mut() += before - parts.len();
+                }
+                if parts.is_empty() {
+                    continue;
+                }
+                let parts: Vec<String> = if color {
+                    parts.iter().map(|m| hl.matched.paint(m)).collect()
+                } else {
+                    parts
+                };
+                let path = tagged(path_of(pt), r);
+                if args.o_inline {
+                    outrec!(out, "{}:{}:{}", path, line_of(r, width), parts.join(&args.o_separator));
+                } else {
+                    for m in parts {
+                        outrec!(out, "{}:{}:{}", path, line_of(r, width), m);
+                    }
+                }
+            }
+        } else if !rules.is_empty() {
+            // --rules: 每条匹配的规则输出一行 `路径:行号:列号: 严重程度[id]: 说明`
+            for r in &v {
+                for &i in &r.rules {
+                    let d = &rules[i];
+                    let col = record_match(r, &d.re).0;
+                    let tx = r.replaced.as_deref().unwrap_or(&r.tx);
+                    outrec!(out, "{}:{}:{}: {}[{}]: {}", path_of(pt), line_of(r, width), col, d.severity.name(), d.name, d.message_or(tx));
+                }
+            }
+        } else {
+            // 和 grep 一样每个匹配输出一行 `路径:行号:内容`，--replace 时输出替换后的内容
+            // --squeeze-blank: 上一条输出的空行的行号
+            let mut prev_blank = None;
+            let mut context = context.into_iter().peekable();
+            // 上下文行写成 `路径-行号-内容`，--highlight-context-lines 时整行使用 context 样式
+            let context_line = |line: usize, tx: &str| {
+                separate(line);
+                let text = if color && args.highlight_context_lines { hl.context.paint(tx) } else { tx.to_string() };
+                let cut = args.truncate_matches.map(|max| output::display_tx(&text, max, &args.truncate_suffix));
+                let text = cut.as_deref().unwrap_or(&text);
+                match &record_format {
+                    Some(fmt) => outrec!(out, "{}", fmt.render(&path_of(pt), &lineno_of(line, width), text)),
+                    None => outrec!(out, "{}-{}-{}", path_of(pt), lineno_of(line, width), text),
+                }
+            };
+            for r in &v {
+                while let Some((line, tx)) = context.next_if(|c| c.0 < r.line) {
+                    context_line(line, tx);
+                }
+                let tx = r.replaced.as_deref().unwrap_or(&r.tx);
+                if args.squeeze_blank && tx.is_empty() {
+                    let squeeze = prev_blank.is_some_and(|l| l + 1 == r.line);
+                    prev_blank = Some(r.line);
+                    if squeeze {
+                        continue;
+                    }
+                }
+                separate(r.line);
+                if let Some(max) = args.max_columns
+                    && tx.len() > max
+                {
+                    let omitted = format!("[省略了 {} 字节的长行]", tx.len());
+                    match &record_format {
+                        Some(fmt) => outrec!(out, "{}", fmt.render(&path_of(pt), &line_of(r, width), &omitted)),
+                        None => outrec!(out, "{}:{}:{}", tagged(path_of(pt), r), line_of(r, width), omitted),
+                    }
+                } else {
+                    let text = if color { Cow::Owned(highlight_line(r, &re, &cfg, &hl)) } else { Cow::Borrowed(tx) };
+                    let cut = args.truncate_matches.map(|max| output::display_tx(&text, max, &args.truncate_suffix));
+                    let text = cut.as_deref().unwrap_or(&text);
+                    match (&record_format, wrap_width) {
+                        (Some(fmt), _) => outrec!(out, "{}", fmt.render(&path_of(pt), &line_of(r, width), text)),
+                        (None, Some(w)) => {
+                            let prefix = if color {
+                                format!("{}:{}:", tagged(path_of(pt), r), line_of(r, width))
+                            } else {
+                                format!("{}:{}:", tagged(shown(pt), r), line_of(r, width))
+                            };
+                            let indent = output::display_width(&prefix);
+                            outrec!(out, "{}{}", prefix, output::word_wrap(text, w, indent));
+                        }
+                        (None, None) if color => outrec!(out, "{}:{}:{}", tagged(path_of(pt), r), line_of(r, width), text),
+                        (None, None) => outrec!(out, "{}:{}:{}", tagged(shown(pt), r), line_of(r, width), text),
+                    }
+                }
+                if args.hex_dump {
+                    out.raw(&output::hex_dump(tx.as_bytes()));
+                }
+            }
+            for (line, tx) in context {
+                context_line(line, tx);
+            }
+        }
+
+        if v.is_empty() {
+            return stop_if_quit();
+        }
+        // 每个匹配执行一次 --exec
+        if let Some(cmd) = &args.exec {
+            for r in &v {
+                let (col, m) = record_match(r, &re);
+                let (line, col) = ((r.line + 1).to_string(), col.to_string());
+                let vars = [("line", line.as_str()), ("column", &col), ("text", &r.tx), ("match", m)];
+                exec_result(exec_command(cmd, &[pt], &vars))?;
+            }
+        }
+        // 文件搜索完成后立即执行 --exec-file，命令失败默认不影响后续文件
+        if let Some(cmd) = &args.exec_file {
+            exec_result(exec_command(cmd, &[pt], &[]))?;
+        }
+        if args.exec_batch.is_some() {
+            batch.borrow_mut().push(pt.to_path_buf());
+        }
+        stop_if_quit()
+    };
+
+    // 已完成的顶层目录，恢复时沿用检查点中的记录
+    let done: RefCell<Vec<PathBuf>> = RefCell::new(cfg.skip_dirs.iter().cloned().collect());
+
+    // 目录处理完成回调函数
+    // 每完成一个顶层目录就更新一次检查点
+    let df = |d: &Path| {
+        info!("完成目录 {}", d.display());
+        let Some(ck) = &args.checkpoint else { return };
+        let top_level = paths.iter().any(|r| d.parent() == Some(Path::new(r)));
+        if !top_level {
+            return;
+        }
+        let mut done = done.borrow_mut();
+        done.push(d.to_path_buf());
+        if let Err(e) = write_checkpoint(ck, &done) {
+            ef(e);
+        }
+    };
+
+    info!("搜索路径: {:?}", paths);
+    info!("正则表达式: {}", re.as_str());
+    if !cfg.line_prefixes.is_empty() || !cfg.skip_prefixes.is_empty() {
+        info!("行前缀过滤: 保留 {:?}，跳过 {:?}", cfg.line_prefixes, cfg.skip_prefixes);
+    }
+    if cfg.type_filter != TypeFilter::All {
+        info!("文件类型过滤: {:?}", cfg.type_filter);
+    }
+    if !cfg.skip_dirs.is_empty() {
+        info!("从检查点恢复，跳过 {} 个已完成的目录", cfg.skip_dirs.len());
+    }
+    let started = Instant::now();
+
+    // 实际使用的代码：依次处理每个路径（文件或目录）
+    // 某个路径出错时报告错误并继续处理下一个路径
+    let search = || {
+        for f in &paths {
+            // 不存在的路径在这里报告，错误信息中带上路径和拼写建议
+            if let Some(e) = suggest::check_root(f) {
+                ef(e.into());
+                continue;
+            }
+            if let Err(e) = process_path(f, &re, &cfg, &WalkContext::default(), &ff, &df, &ef) {
+                // 模糊匹配超时等致命错误，剩下的路径也不必再处理
+                if is_fatal(&e) {
+                    return Err(e);
+                }
+                ef(e);
+            }
+        }
+        // 每次搜索（包括 --watch 的重新搜索）结束时输出这次的文件数
+        if count_mode == Some(CountMode::Files) {
+            let n = counted_files.replace(0);
+            if args.count_exit {
+                exit_files.set(n);
+            } else {
+                outrec!(out, "{}", n);
+            }
+        }
+        if count_mode == Some(CountMode::Unique) {
+            let unique = unique.take();
+            if args.frequency_analysis {
+                let mut table: Vec<_> = unique.into_iter().collect();
+                table.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+                for (m, n) in table {
+                    outrec!(out, "{}\t{}", n, m);
+                }
+            } else {
+                outrec!(out, "{}", unique.len());
+            }
+        }
+        if sorted_unique {
+            let texts = texts.take();
+            // BTreeMap 已经按文本排好序，倒序时反过来遍历即可
+            let table: Box<dyn Iterator<Item = (String, usize)>> = if args.output_sorted_unique_texts_desc {
+                Box::new(texts.into_iter().rev())
+            } else {
+                Box::new(texts.into_iter())
+            };
+            for (m, n) in table {
+                if args.output_sorted_unique_counts {
+                    outrec!(out, "{}\t{}", n, m);
+                } else {
+                    outrec!(out, "{}", m);
+                }
+            }
+        }
+        if let Some(k) = args.top {
+            let mut table = top.take();
+            table.sort_by(|(x, a), (y, b)| y.cmp(x).then_with(|| a.cmp(b)));
+            if k > 0 {
+                table.truncate(k);
+            }
+            for (n, pt) in table {
+                if !json_output {
+                    outrec!(out, "{}\t{}", n, if color { hl.filename.paint(&pt) } else { pt });
+                    continue;
+                }
+                let obj = Json::Object(vec![
+                    ("path".to_string(), Json::String(pt)),
+                    ("count".to_string(), Json::Number(n as f64)),
+                ])
+                .render();
+                if args.json_array {
+                    out.raw(if json_first.replace(false) { "\n" } else { ",\n" });
+                    out.raw(&obj);
+                } else {
+                    outln!(out, "{}", obj);
+                }
+            }
+        }
+        Ok(())
+    };
+    let mut p = if args.follow_lines {
+        follow::follow(&paths, &re, &cfg, &ff, &ef)
+    } else {
+        search()
+    };
+    // 回答 q 是用户主动停止，不是错误
+    if quit.get() && p.as_ref().is_err_and(|e| e.downcast_ref::<Halt>().is_some()) {
+        p = Ok(());
+    }
+
+    // 监视模式：每次文件变化后重新搜索，按 Ctrl-C 时以最后一次搜索的结果结束
+    if args.watch {
+        let mut watcher = watch::Watcher::new(&paths, &cfg, Duration::from_millis(args.watch_debounce));
+        while watcher.wait() {
+            if std::io::stdout().is_terminal() {
+                out.raw("\x1b[2J\x1b[H");
+            }
+            out.separator(format_args!("==> {} 文件发生变化，重新搜索 <==", watch::timestamp()));
+            p = search();
+        }
+    }
+
+    // 搜索出错时也要闭合数组，已经输出的匹配仍然是合法的 JSON
+    if args.json_array {
+        out.raw(if json_first.get() { "]\n" } else { "\n]\n" });
+    }
+    if args.format == Some(OutputFormat::Sarif) {
+        out.raw(&sarif::document(&rules, sarif_results.take()));
+        out.raw("\n");
+    }
+
+    // 所有文件搜索完后一次性执行 --exec-batch
+    let batch = batch.into_inner();
+    if let Some(cmd) = &args.exec_batch
+        && !batch.is_empty()
+    {
+        let paths: Vec<&Path> = batch.iter().map(PathBuf::as_path).collect();
+        if let Err(e) = exec_result(exec_command(cmd, &paths, &[]))
+            && p.is_ok()
+        {
+            p = Err(e);
+        }
+    }
+
+    // 搜索完整结束后检查点已经没有用了
+    if let Some(ck) = &args.checkpoint
+        && p.is_ok()
+        && let Err(e) = std::fs::remove_file(ck)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        ef(e.into());
+    }
+
+    let n = suppressed.into_inner();
+    if n > 0 {
+        eprintln!("{} 处匹配被抑制", n);
+    }
+    if let Some(s) = &suppressions
+        && p.is_ok()
+        && !args.no_warnings
+    {
+        for e in s.unused() {
+            eprintln!("警告: 抑制条目没有用到: {}", e);
+        }
+    }
+    if let Some(p) = &args.save_baseline
+        && let Err(e) = baseline::save(p, saved.into_inner())
+    {
+        ef(e);
+    }
+    // 退出状态 3 表示检查没有通过，优先于之后的其他非零状态
+    if p.is_ok()
+        && let Some(why) = gate.into_inner().failure(args.error_if_matches, args.error_if_no_matches, &rules)
+    {
+        eprintln!("{}", why);
+        p = Err(ExitStatus(3).into());
+    }
+    if let Some(b) = &known {
+        let n = new_matches.into_inner();
+        eprintln!("基线之外有 {} 处新的匹配（基线中记录了 {} 处）", n, b.len());
+        if n > 0 && p.is_ok() {
+            p = Err(ExitStatus(1).into());
+        }
+    }
+
+    if rule_errors.get() > 0 && p.is_ok() {
+        eprintln!("有 {} 处匹配了 error 级别的规则", rule_errors.get());
+        p = Err(ExitStatus(1).into());
+    }
+
+    // 退出状态只有一个字节，超过 255 的文件数都报告为 255
+    if args.count_exit && p.is_ok() && exit_files.get() > 0 {
+        p = Err(ExitStatus(exit_files.get().min(255) as i32).into());
+    }
+
+    if args.diff {
+        // 补丁之外的内容不写标准输出，输出可以直接保存为补丁文件
+        let (files, total) = rewritten.into_inner();
+        eprintln!("共有 {} 个文件会被修改，{} 处替换", files, total);
+        if files > 0 && p.is_ok() {
+            p = Err(ExitStatus(1).into());
+        }
+    } else if args.write_replace.is_some() {
+        if let Some(expected) = args.expect_matches
+            && p.is_ok()
+        {
+            let pending = pending.into_inner();
+            let actual = pending
+                .iter()
+                .map(|(_, plan)| match plan {
+                    rewrite::Plan::Change { count, .. } => *count,
+                    _ => 0,
+                })
+                .sum();
+            if actual == expected {
+                for (pt, plan) in pending {
+                    report(&pt, rewrite::apply(&pt, plan, &write_opts));
+                }
+            } else {
+                p = Err(rewrite::ExpectMismatch {
+                    expected,
+                    actual,
+                    files: pending.len(),
+                }
+                .into());
+            }
+        }
+        // 数量不符时一个文件也没有修改，错误信息已经说明了，不再输出"共修改 0 个文件"
+        let mismatch = p.as_ref().is_err_and(|e| e.downcast_ref::<rewrite::ExpectMismatch>().is_some());
+        if !mismatch {
+            let (files, total) = rewritten.into_inner();
+            outln!(out, "共修改 {} 个文件，{} 处替换", files, total);
+        }
+    }
+
+    // 按模式分组：搜索结束后每个模式输出一节
+    for (label, lines) in labels.iter().zip(groups.into_inner()) {
+        out.heading(format_args!("模式 {} ({} 行):", label, lines.len()));
+        for l in lines {
+            outln!(out, "  {}", l);
+        }
+    }
+    // 目录小节的标题用路径的颜色加粗
+    let heading_style = style::Style {
+        bold: !args.no_bold,
+        ..hl.filename.clone()
+    };
+    for (dir, lines) in dir_groups.into_inner() {
+        if lines.is_empty() {
+            continue;
+        }
+        let dir = if color { heading_style.paint(&dir) } else { dir };
+        out.heading(format_args!("目录 {} ({} 行):", dir, lines.len()));
+        for l in lines {
+            outln!(out, "  {}", l);
+        }
+    }
+
+    if let Some(t) = &cfg.timing
+        && (args.profile_regex || args.profile_per_line)
+    {
+        eprint!("{}", t.regex_report(args.profile_regex, args.profile_per_line));
+    }
+    if let Some(t) = &cfg.timing
+        && args.debug_timing
+    {
+        eprint!("{}", t.report(args.debug_timing_top));
+    }
+    if args.dedup_by.is_some() {
+        info!("--dedup-by: {} 个不同的值，省略了 {} 条重复的结果", seen.borrow().len(), dups.borrow());
+    }
+
+    // 交互模式：所有结果都输出之后再提示选择
+    if args.interactive {
+        interactive::prompt_loop(&hits.borrow());
+    }
+
+    // 整体处理结果：致命错误由 main 报告，其他错误已经在 ef 中报告过了
+    info!("搜索结束，耗时 {:?}", started.elapsed());
+    p
+}
+
+/// 程序主入口函数
+///
+/// 这是程序的入口点，负责：
+/// 1. 调用主运行函数
+/// 2. 处理顶层错误并优雅退出
+///
+/// # 错误处理模式
+/// 使用 Rust 推荐的错误处理模式：
+/// - 使用 if let Err(e) 来检查 Result
+/// - 打印友好的错误信息给用户
+/// - 程序以非零状态码退出（通过 panic! 或 std::process::exit）
+///
+/// # 设计原则
+/// 这种设计遵循了 Rust 的最佳实践：
+/// 1. 将核心逻辑与错误处理分离
+/// 2. main 函数保持简洁
+/// 3. 提供清晰的错误信息
+///
+/// # 相关文档
+/// * main 函数文档: <https://doc.rust-lang.org/std/fn.main.html>
+/// * 错误处理指南: <https://doc.rust-lang.org/book/ch09.html>
+fn main() {
+    // 调用主运行函数并处理可能发生的错误
+    // 这种模式确保程序在遇到错误时能够优雅地退出
+    if let Err(e) = run() {
+        // 不是真正的错误，只是需要非零的退出状态
+        if let Some(ExitStatus(code)) = e.downcast_ref() {
+            std::process::exit(*code);
+        }
+        // 打印用户友好的错误信息，和其他诊断信息一样输出到标准错误
+        eprintln!("程序执行时发生错误: {}", e);
+        // 替换被中止了（--expect-matches 或者 --replace-verify），CI 中的调用者需要从退出状态知道这一点
+        if e.downcast_ref::<rewrite::ExpectMismatch>().is_some()
+            || e.downcast_ref::<ReplaceVerifyFailed>().is_some()
+        {
+            std::process::exit(1);
+        }
+        // 基线读不出来时不能让 CI 当作没有新的匹配，和 grep 一样用 2 表示出错
+        if e.downcast_ref::<baseline::BaselineErr>().is_some() {
+            std::process::exit(2);
+        }
+        // 规则文件有错误时同样不能当作检查通过；配置文件的语法错误也是这个类型，
+        // 请求的 profile 不存在或者继承出现循环同样是配置的错误
+        if e.downcast_ref::<config::ConfigErr>().is_some()
+            || e.downcast_ref::<config::UnknownProfile>().is_some()
+            || e.downcast_ref::<config::ProfileCycle>().is_some()
+        {
+            std::process::exit(2);
+        }
 
         // 在实际的应用程序中，这里可能需要：
         // 1. 记录错误日志
@@ -0,0 +1,183 @@
+// man 手册
+//
+// --generate-man-page 根据 clap 中定义的参数生成 roff 格式的 man 手册，
+// 内容和 `--help` 相同：每个选项的完整说明、可选的值和默认值，
+// 各个选项说明中的 `# 示例` 另外汇总到 EXAMPLES 一节。
+//
+// 安装方式：
+// * `pgrep --generate-man-page --man-output /usr/local/share/man/man1/pgrep.1`
+// * 不安装直接查看: `pgrep --generate-man-page | man -l -`
+//
+// 相关文档:
+// * man-pages(7): <https://man7.org/linux/man-pages/man7/man-pages.7.html>
+// * groff_man(7): <https://man7.org/linux/man-pages/man7/groff_man.7.html>
+
+use clap::{Arg, Command};
+
+/// 生成 man 手册
+///
+/// # 参数
+/// * `cmd` - 完整的命令定义，通常是 `Args::command()`
+/// * `section` - man 手册的章节号，命令行工具是 1
+pub fn generate(mut cmd: Command, section: u8) -> String {
+    // build 之后才有 clap 自动添加的 --help 和 --version
+    cmd.build();
+    let name = cmd.get_name().to_string();
+    let about = cmd.get_about().map(|a| a.to_string()).unwrap_or_default();
+    let version = cmd.get_version().unwrap_or_default().to_string();
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        ".TH {} {} \"\" \"{} {}\"\n",
+        name.to_uppercase(),
+        section,
+        name,
+        version
+    ));
+    out.push_str(&format!(".SH NAME\n{} \\- {}\n", name, escape(&about)));
+    out.push_str(&format!(
+        ".SH SYNOPSIS\n\\fB{}\\fR [\\fIOPTIONS\\fR] [\\fIPATTERN\\fR] [\\fIPATH\\fR]...\n",
+        name
+    ));
+    out.push_str(&format!(".SH DESCRIPTION\n{}\n", escape(&about)));
+
+    let mut examples = Vec::new();
+    let (positionals, options): (Vec<&Arg>, Vec<&Arg>) =
+        cmd.get_arguments().filter(|a| !a.is_hide_set()).partition(|a| a.is_positional());
+    if !positionals.is_empty() {
+        out.push_str(".SH ARGUMENTS\n");
+        for a in positionals {
+            let value = a.get_value_names().map(|n| n.join(" ")).unwrap_or_else(|| a.get_id().to_string());
+            out.push_str(&format!(".TP\n\\fI{}\\fR...\n", escape(&value)));
+            describe(a, &name, &mut out, &mut examples);
+        }
+    }
+    out.push_str(".SH OPTIONS\n");
+    for a in options {
+        out.push_str(&format!(".TP\n{}\n", flags(a)));
+        describe(a, &name, &mut out, &mut examples);
+    }
+
+    if !examples.is_empty() {
+        out.push_str(".SH EXAMPLES\n");
+        for e in examples {
+            out.push_str(&format!(".IP \\(bu 2\n{}\n", inline(&e)));
+        }
+    }
+    out
+}
+
+/// 选项的写法，例如 `\fB\-p\fR, \fB\-\-pattern\fR \fIPATTERN\fR`
+fn flags(a: &Arg) -> String {
+    let mut names: Vec<String> = a.get_short().map(|s| format!("\\fB\\-{}\\fR", s)).into_iter().collect();
+    names.extend(a.get_long().map(|l| format!("\\fB\\-\\-{}\\fR", escape(l))));
+    names.extend(
+        a.get_visible_aliases()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|l| format!("\\fB\\-\\-{}\\fR", escape(l))),
+    );
+    let mut s = names.join(", ");
+    if a.get_action().takes_values()
+        && let Some(v) = a.get_value_names()
+    {
+        let v = v.iter().map(|v| format!("\\fI{}\\fR", escape(v))).collect::<Vec<_>>().join(" ");
+        // num_args = 0..=1 的值可以省略，例如 --debug-skip[=GLOB]
+        if a.get_num_args().is_some_and(|n| n.min_values() == 0) {
+            s.push_str(&format!("[={}]", v));
+        } else {
+            s.push_str(&format!(" {}", v));
+        }
+    }
+    s
+}
+
+/// 输出一个参数的完整说明，`# 示例` 段落中的每一条同时收集到 `examples`
+fn describe(a: &Arg, name: &str, out: &mut String, examples: &mut Vec<String>) {
+    let help = a.get_long_help().or(a.get_help()).map(|h| h.to_string()).unwrap_or_default();
+    let mut first = true;
+    for para in help.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        if !first {
+            out.push_str(".IP\n");
+        }
+        first = false;
+        // clap 把段落中的各行连成了一行，例子之间以 ` * ` 分隔
+        if let Some(rest) = para.strip_prefix('#') {
+            let rest = rest.trim_start();
+            let (title, items) = match rest.split_once(" * ") {
+                Some((t, items)) => (t, format!("* {}", items)),
+                None => (rest, String::new()),
+            };
+            out.push_str(&format!("\\fB{}\\fR\n", escape(title)));
+            let items: Vec<&str> = items.split(" * ").map(|i| i.trim_start_matches("* ")).collect();
+            for i in items.iter().filter(|i| !i.is_empty()) {
+                out.push_str(&format!(".br\n\\(bu {}\n", inline(i)));
+                // 只写了选项的例子在 EXAMPLES 中补上命令名
+                if title == "示例" {
+                    examples.push(match i.strip_prefix("`-") {
+                        Some(rest) => format!("`{} -{}", name, rest),
+                        None => i.to_string(),
+                    });
+                }
+            }
+            continue;
+        }
+        out.push_str(&inline(para));
+        out.push('\n');
+    }
+
+    let values: Vec<String> = a
+        .get_possible_values()
+        .iter()
+        .filter(|v| !v.is_hide_set())
+        .map(|v| match v.get_help() {
+            Some(h) => format!("\\fB{}\\fR（{}）", escape(v.get_name()), escape(&h.to_string())),
+            None => format!("\\fB{}\\fR", escape(v.get_name())),
+        })
+        .collect();
+    // 没有取值的开关由 clap 记录成 true / false 两个值，不需要列出
+    if !values.is_empty() && a.get_action().takes_values() {
+        out.push_str(&format!(".IP\n可选的值: {}\n", values.join(", ")));
+    }
+    let defaults: Vec<String> = a
+        .get_default_values()
+        .iter()
+        .map(|v| escape(&v.to_string_lossy()))
+        .collect();
+    if !defaults.is_empty() && a.get_action().takes_values() {
+        out.push_str(&format!(".IP\n默认值: {}\n", defaults.join(", ")));
+    }
+}
+
+/// 转义文本，并把 `code` 显示为粗体
+fn inline(s: &str) -> String {
+    escape(s)
+        .split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                format!("\\fB{}\\fR", part)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect()
+}
+
+/// 转义 roff 中有特殊含义的字符
+///
+/// 反斜杠写成 `\e`，`-` 写成 `\-` 以免被排版成连字符，
+/// 行首的 `.` 和 `'` 会被当作请求，前面加上零宽的 `\&`
+fn escape(s: &str) -> String {
+    let s = s.replace('\\', "\\e").replace('-', "\\-");
+    s.lines()
+        .map(|l| {
+            if l.starts_with('.') || l.starts_with('\'') {
+                format!("\\&{}", l)
+            } else {
+                l.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
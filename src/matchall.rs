@@ -0,0 +1,150 @@
+// 要求一行匹配所有的模式
+//
+// 多个 -p / --builtin-pattern 默认合并成一个正则表达式，任意一个模式匹配这一行就输出（--match-any）。
+// --match-all 时一行必须匹配每一个模式：合并后的表达式先在搜索中找出候选行，
+// 这里再用各个模式单独检查每一个候选行，只保留全部匹配的行。
+//
+// 模式很多、候选行也很多时，逐个模式检查是主要的开销。--parallel-regex N 把模式分成 N 组，
+// 每组在一个线程中检查所有的候选行，得到一个"这一组是否全部匹配"的向量，所有线程结束之后再逐行取交集：
+// * 每个线程只写自己的结果向量，Record 只被共享读取，合并在 scope 结束之后进行，没有需要加锁的状态
+// * 结果和逐个检查完全相同，输出的顺序也不变
+// * 候选行少于 PARALLEL_MIN 时创建线程的开销比检查本身还大，这时直接在当前线程中检查
+//
+// 这个构建没有包含 rayon，线程用标准库的 std::thread::scope 创建，每个文件的候选行检查完线程就结束。
+//
+// 相关文档:
+// * std::thread::scope: <https://doc.rust-lang.org/std/thread/fn.scope.html>
+
+use failure::Error;
+use regex::Regex;
+
+use crate::Record;
+
+/// 候选行至少有这么多时才使用多个线程
+pub const PARALLEL_MIN: usize = 64;
+
+/// 编译好的所有模式
+#[derive(Debug)]
+pub struct MatchAll {
+    patterns: Vec<Regex>,
+    threads: usize,
+}
+
+impl MatchAll {
+    /// # 参数
+    /// * `sources` - 每个模式的正则表达式，内置模式也各算一个
+    /// * `threads` - `--parallel-regex` 的线程数，1 表示逐个检查
+    pub fn new(sources: &[String], threads: usize) -> Result<MatchAll, Error> {
+        let patterns = sources.iter().map(|s| Regex::new(s)).collect::<Result<_, _>>()?;
+        Ok(MatchAll {
+            patterns,
+            threads: threads.max(1),
+        })
+    }
+
+    /// 只保留匹配所有模式的记录
+    ///
+    /// # 示例
+    /// ```
+    /// use pgrep::matchall::MatchAll;
+    /// use pgrep::{GrepConfig, process_bytes};
+    /// use regex::Regex;
+    /// use std::path::Path;
+    ///
+    /// let re = Regex::new("a|b").unwrap();
+    /// let v = process_bytes(Path::new("x"), b"a\nb\na b\n".to_vec(), &re, &GrepConfig::default()).unwrap();
+    /// let all = MatchAll::new(&["a".to_string(), "b".to_string()], 2).unwrap();
+    /// let kept: Vec<_> = all.filter(v).into_iter().map(|r| r.tx).collect();
+    /// assert_eq!(kept, ["a b"]);
+    /// ```
+    pub fn filter(&self, v: Vec<Record>) -> Vec<Record> {
+        let keep = if self.threads > 1 && v.len() >= PARALLEL_MIN && self.patterns.len() > 1 {
+            self.check_parallel(&v)
+        } else {
+            check(&self.patterns, &v)
+        };
+        v.into_iter().zip(keep).filter_map(|(r, k)| k.then_some(r)).collect()
+    }
+
+    /// 模式分组后每组一个线程，各组的结果逐行取交集
+    fn check_parallel(&self, v: &[Record]) -> Vec<bool> {
+        let chunk = self.patterns.len().div_ceil(self.threads);
+        let parts: Vec<Vec<bool>> = std::thread::scope(|s| {
+            let handles: Vec<_> = self
+                .patterns
+                .chunks(chunk)
+                .map(|group| s.spawn(move || check(group, v)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect()
+        });
+        (0..v.len()).map(|i| parts.iter().all(|p| p[i])).collect()
+    }
+}
+
+/// 每条记录是否匹配 `patterns` 中的所有模式
+fn check(patterns: &[Regex], v: &[Record]) -> Vec<bool> {
+    v.iter().map(|r| patterns.iter().all(|re| re.is_match(&r.tx))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10 个模式 `w0` 到 `w9`
+    fn patterns() -> Vec<String> {
+        (0..10).map(|i| format!("\\bw{}\\b", i)).collect()
+    }
+
+    /// 每行缺少 `w0` 到 `w9` 中的几个词，每个词缺少的概率大约是 1/4，大约 5% 的行含有全部的词
+    fn records(n: usize) -> Vec<Record> {
+        (0..n)
+            .map(|i| {
+                let h = (i as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                let missing = (h >> 20) & (h >> 40);
+                let tx: Vec<String> = (0..10).filter(|d| missing & (1 << d) == 0).map(|d| format!("w{}", d)).collect();
+                Record {
+                    line: i,
+                    tx: tx.join(" "),
+                    fuzzy: None,
+                    replaced: None,
+                    word: None,
+                    suppressed: false,
+                    rules: Vec::new(),
+                    is_context: false,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parallel_check_agrees_with_the_sequential_check() {
+        let v = records(1000);
+        let sequential = MatchAll::new(&patterns(), 1).unwrap();
+        let expected = check(&sequential.patterns, &v);
+        // 两种结果都有不少，比较才有意义
+        let kept = expected.iter().filter(|&&k| k).count();
+        assert!(kept > 10 && kept < 200, "{}", kept);
+        // 线程数整除、不整除模式数，以及比模式还多
+        for threads in [2, 3, 4, 7, 10, 16] {
+            let m = MatchAll::new(&patterns(), threads).unwrap();
+            assert_eq!(m.check_parallel(&v), expected, "{} 个线程", threads);
+        }
+    }
+
+    #[test]
+    fn filter_keeps_the_same_records_in_order() {
+        let keep = |threads: usize, n: usize| -> Vec<usize> {
+            let m = MatchAll::new(&patterns(), threads).unwrap();
+            m.filter(records(n)).iter().map(|r| r.line).collect()
+        };
+        // 候选行少于 PARALLEL_MIN 时不使用线程，结果也一样
+        for n in [0, PARALLEL_MIN - 1, PARALLEL_MIN, 1000] {
+            let expected = keep(1, n);
+            assert!(expected.windows(2).all(|w| w[0] < w[1]));
+            assert_eq!(keep(4, n), expected);
+        }
+    }
+}
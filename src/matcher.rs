@@ -0,0 +1,123 @@
+// 按字面查找的匹配器
+//
+// `-F` 的模式只有一个时，它本身就是要查找的字节序列，不需要正则表达式引擎。
+// `Matcher` 是判断一行是否匹配、找出第一个匹配位置的最小接口，有两种实现：
+// * `regex::Regex` - 一般的模式
+// * `LiteralMatcher` - 用 memchr 的 `memmem::Finder` 查找子串，用于 `--literal-match-only`
+//
+// 两者的结果完全相同：转义之后的模式在正则表达式中也只能按字面匹配，第一个匹配就是子串第一次出现的位置。
+// 只有判断哪些行匹配时使用 `LiteralMatcher`，输出中的匹配位置、`--replace` 的替换等仍然使用转义之后的正则表达式。
+//
+// 相关文档:
+// * memchr::memmem::Finder: <https://docs.rs/memchr/latest/memchr/memmem/struct.Finder.html>
+
+use std::ops::Range;
+
+use memchr::memmem::Finder;
+use regex::Regex;
+
+/// 在一行文本中查找模式
+pub trait Matcher {
+    /// 这一行中是否有匹配
+    fn is_match(&self, line: &str) -> bool;
+
+    /// 第一个匹配的字节范围
+    fn find(&self, line: &str) -> Option<Range<usize>>;
+}
+
+impl Matcher for Regex {
+    fn is_match(&self, line: &str) -> bool {
+        Regex::is_match(self, line)
+    }
+
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        Regex::find(self, line).map(|m| m.range())
+    }
+}
+
+/// 按字面查找一个子串
+///
+/// # 示例
+/// ```
+/// use pgrep::matcher::{LiteralMatcher, Matcher};
+///
+/// let m = LiteralMatcher::new("a.b");
+/// assert!(m.is_match("x a.b y"));
+/// assert!(!m.is_match("x aXb y"));
+/// assert_eq!(m.find("x a.b y"), Some(2..5));
+/// assert_eq!(m.find_at("a.b a.b", 1), Some(4..7));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LiteralMatcher {
+    finder: Finder<'static>,
+}
+
+impl LiteralMatcher {
+    pub fn new(literal: &str) -> LiteralMatcher {
+        LiteralMatcher {
+            finder: Finder::new(literal.as_bytes()).into_owned(),
+        }
+    }
+
+    /// 要查找的子串
+    pub fn literal(&self) -> &str {
+        // 构造时给出的是 &str，字节一定是合法的 UTF-8
+        std::str::from_utf8(self.finder.needle()).unwrap_or_default()
+    }
+
+    /// 从 `start` 开始的第一个匹配的字节范围，范围相对于整个 `text`
+    pub fn find_at(&self, text: &str, start: usize) -> Option<Range<usize>> {
+        let i = self.finder.find(&text.as_bytes()[start..])?;
+        Some(start + i..start + i + self.finder.needle().len())
+    }
+}
+
+impl Matcher for LiteralMatcher {
+    fn is_match(&self, line: &str) -> bool {
+        self.finder.find(line.as_bytes()).is_some()
+    }
+
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        self.find_at(line, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 元字符、多字节字符、重叠的候选、在行首行尾和不出现的情况
+    const LITERALS: &[&str] = &["a.b", "(x)", "$1", "\\d+", "[a-z]*", "你好", "aab", "é", " ", "\t|\t"];
+    const LINES: &[&str] = &[
+        "",
+        "a.b",
+        "aXb a.b",
+        "call(x) and (x)",
+        "cost $1 or $10",
+        "\\d+ literally, not 123",
+        "[a-z]* glob",
+        "说你好，你好",
+        "aaab aab",
+        "cafe\u{301} café",
+        "no match here",
+        "col\t|\tcol",
+    ];
+
+    #[test]
+    fn agrees_with_the_escaped_regex() {
+        for &lit in LITERALS {
+            let m = LiteralMatcher::new(lit);
+            let re = Regex::new(&regex::escape(lit)).unwrap();
+            assert_eq!(m.literal(), lit);
+            for &line in LINES {
+                assert_eq!(Matcher::is_match(&m, line), Matcher::is_match(&re, line), "{:?} in {:?}", lit, line);
+                assert_eq!(Matcher::find(&m, line), Matcher::find(&re, line), "{:?} in {:?}", lit, line);
+                // 每个字符边界开始的下一个匹配
+                for (start, _) in line.char_indices() {
+                    let expected = re.find_at(line, start).map(|m| m.range());
+                    assert_eq!(m.find_at(line, start), expected, "{:?} in {:?} from {}", lit, line, start);
+                }
+            }
+        }
+    }
+}
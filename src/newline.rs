@@ -0,0 +1,213 @@
+// 行尾的识别
+//
+// `str::lines` 只认 `\n` 和 `\r\n`，旧式 Mac 文件、一些打印机和串口日志只用单独的 `\r` 作为行尾，
+// 整个文件会被当作一行：行号全是 1，一个匹配就输出整个文件。`Newline` 决定哪些字符序列是行尾：
+//
+// | 取值    | 行尾                                   |
+// |---------|----------------------------------------|
+// | `auto`  | `\n` 和 `\r\n`；内容中没有 `\n` 但有 `\r` 时是单独的 `\r`（默认） |
+// | `lf`    | 只有 `\n`，`\r` 是行内容的一部分        |
+// | `crlf`  | 只有 `\r\n`，单独的 `\n` 和 `\r` 是行内容的一部分 |
+// | `cr`    | 只有 `\r`                               |
+// | `any`   | `\n`、`\r\n` 和单独的 `\r`，混用行尾的文件按出现的顺序编号 |
+//
+// 行尾不属于行的内容，匹配和输出都不包含它；和 `str::lines` 一样，最后一个行尾之后的空字符串不算一行。
+// 每一行和它的行尾按顺序首尾相接，拼起来就是原来的内容，`--write-replace` 因此可以逐行替换后原样写回。
+//
+// 相关文档:
+// * 换行符: <https://en.wikipedia.org/wiki/Newline#Representation>
+
+/// 行尾的识别方式，见 `GrepConfig::newline`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Newline {
+    /// `\n` 和 `\r\n`，整个内容中只有 `\r` 时按 `cr` 处理
+    #[default]
+    Auto,
+    /// 只有 `\n`
+    Lf,
+    /// 只有 `\r\n`
+    Crlf,
+    /// 只有单独的 `\r`
+    Cr,
+    /// `\n`、`\r\n` 和单独的 `\r` 都是行尾
+    Any,
+}
+
+impl Newline {
+    /// 把内容分成行，每一项是 (行内容, 行尾)，最后一行没有行尾时行尾是空字符串
+    ///
+    /// # 示例
+    /// ```
+    /// use pgrep::newline::Newline;
+    ///
+    /// let lines: Vec<_> = Newline::Auto.split("a\rb\rc").map(|(l, _)| l).collect();
+    /// assert_eq!(lines, ["a", "b", "c"]);
+    ///
+    /// let mixed: Vec<_> = Newline::Any.split("a\r\nb\rc\n").collect();
+    /// assert_eq!(mixed, [("a", "\r\n"), ("b", "\r"), ("c", "\n")]);
+    /// ```
+    pub fn split(self, text: &str) -> Lines<'_> {
+        let mode = match self {
+            Newline::Auto
+                if memchr::memchr(b'\n', text.as_bytes()).is_none() && memchr::memchr(b'\r', text.as_bytes()).is_some() =>
+            {
+                Newline::Cr
+            }
+            m => m,
+        };
+        Lines { rest: text, mode }
+    }
+
+    /// 只要行内容，相当于按这种行尾识别方式的 `str::lines`
+    pub fn lines(self, text: &str) -> impl Iterator<Item = &str> {
+        self.split(text).map(|(l, _)| l)
+    }
+}
+
+/// 内容中每种行尾的个数，见 `Endings::count`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Endings {
+    pub lf: usize,
+    pub crlf: usize,
+    /// 后面不是 `\n` 的 `\r`
+    pub cr: usize,
+}
+
+impl Endings {
+    /// 数出内容中的 `\n`、`\r\n` 和单独的 `\r`
+    ///
+    /// # 示例
+    /// ```
+    /// use pgrep::newline::Endings;
+    ///
+    /// let e = Endings::count("a\r\nb\nc\r\n");
+    /// assert_eq!((e.lf, e.crlf, e.cr), (1, 2, 0));
+    /// assert!(e.is_mixed());
+    /// ```
+    pub fn count(text: &str) -> Endings {
+        let b = text.as_bytes();
+        let mut e = Endings::default();
+        for i in memchr::memchr2_iter(b'\r', b'\n', b) {
+            match b[i] {
+                b'\n' if i > 0 && b[i - 1] == b'\r' => e.crlf += 1,
+                b'\n' => e.lf += 1,
+                _ if b.get(i + 1) == Some(&b'\n') => {}
+                _ => e.cr += 1,
+            }
+        }
+        e
+    }
+
+    /// 是否有不止一种行尾
+    pub fn is_mixed(&self) -> bool {
+        [self.lf, self.crlf, self.cr].iter().filter(|&&n| n > 0).count() > 1
+    }
+}
+
+/// `Newline::split` 返回的迭代器
+#[derive(Debug, Clone)]
+pub struct Lines<'a> {
+    rest: &'a str,
+    /// 已经确定下来的方式，`Auto` 表示 `\n` 和 `\r\n`
+    mode: Newline,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<(&'a str, &'a str)> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let b = self.rest.as_bytes();
+        // 行尾开始的位置和长度
+        let end = match self.mode {
+            Newline::Auto => memchr::memchr(b'\n', b).map(|i| match i.checked_sub(1) {
+                Some(j) if b[j] == b'\r' => (j, 2),
+                _ => (i, 1),
+            }),
+            Newline::Lf => memchr::memchr(b'\n', b).map(|i| (i, 1)),
+            Newline::Crlf => memchr::memmem::find(b, b"\r\n").map(|i| (i, 2)),
+            Newline::Cr => memchr::memchr(b'\r', b).map(|i| (i, 1)),
+            Newline::Any => memchr::memchr2(b'\r', b'\n', b).map(|i| {
+                if b[i] == b'\r' && b.get(i + 1) == Some(&b'\n') {
+                    (i, 2)
+                } else {
+                    (i, 1)
+                }
+            }),
+        };
+        let (line, eol, rest) = match end {
+            Some((i, n)) => (&self.rest[..i], &self.rest[i..i + n], &self.rest[i + n..]),
+            None => (self.rest, "", ""),
+        };
+        self.rest = rest;
+        Some((line, eol))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CR_ONLY: &str = "one\rtwo\rthree\r";
+    const MIXED: &str = "a\r\nb\rc\nd\r\ne";
+
+    fn split(mode: Newline, text: &str) -> Vec<(&str, &str)> {
+        mode.split(text).collect()
+    }
+
+    #[test]
+    fn auto_mode() {
+        assert_eq!(split(Newline::Auto, "a\nb\r\nc"), [("a", "\n"), ("b", "\r\n"), ("c", "")]);
+        // 没有 \n 时按 cr 处理
+        assert_eq!(split(Newline::Auto, CR_ONLY), [("one", "\r"), ("two", "\r"), ("three", "\r")]);
+        // 有 \n 时单独的 \r 是行内容
+        assert_eq!(split(Newline::Auto, MIXED), [("a", "\r\n"), ("b\rc", "\n"), ("d", "\r\n"), ("e", "")]);
+    }
+
+    #[test]
+    fn lf_mode() {
+        assert_eq!(split(Newline::Lf, CR_ONLY), [(CR_ONLY, "")]);
+        assert_eq!(split(Newline::Lf, MIXED), [("a\r", "\n"), ("b\rc", "\n"), ("d\r", "\n"), ("e", "")]);
+    }
+
+    #[test]
+    fn crlf_mode() {
+        assert_eq!(split(Newline::Crlf, CR_ONLY), [(CR_ONLY, "")]);
+        assert_eq!(split(Newline::Crlf, MIXED), [("a", "\r\n"), ("b\rc\nd", "\r\n"), ("e", "")]);
+    }
+
+    #[test]
+    fn cr_mode() {
+        assert_eq!(split(Newline::Cr, CR_ONLY), [("one", "\r"), ("two", "\r"), ("three", "\r")]);
+        assert_eq!(split(Newline::Cr, MIXED), [("a", "\r"), ("\nb", "\r"), ("c\nd", "\r"), ("\ne", "")]);
+    }
+
+    #[test]
+    fn any_mode() {
+        assert_eq!(split(Newline::Any, CR_ONLY), [("one", "\r"), ("two", "\r"), ("three", "\r")]);
+        assert_eq!(split(Newline::Any, MIXED), [("a", "\r\n"), ("b", "\r"), ("c", "\n"), ("d", "\r\n"), ("e", "")]);
+        // 连续的行尾之间是空行，\n\r 是两个行尾
+        assert_eq!(split(Newline::Any, "a\n\rb\r\r\n"), [("a", "\n"), ("", "\r"), ("b", "\r"), ("", "\r\n")]);
+    }
+
+    #[test]
+    fn lines_join_back_to_the_input() {
+        for mode in [Newline::Auto, Newline::Lf, Newline::Crlf, Newline::Cr, Newline::Any] {
+            for text in [CR_ONLY, MIXED, "", "\r\n", "\r", "x\r\r\n\n"] {
+                let joined: String = mode.split(text).map(|(l, e)| format!("{}{}", l, e)).collect();
+                assert_eq!(joined, text, "{:?}", mode);
+            }
+        }
+        assert_eq!(Newline::Any.split("").count(), 0);
+    }
+
+    #[test]
+    fn endings_are_counted_once() {
+        assert_eq!(Endings::count(MIXED), Endings { lf: 1, crlf: 2, cr: 1 });
+        assert_eq!(Endings::count(CR_ONLY), Endings { lf: 0, crlf: 0, cr: 3 });
+        assert!(!Endings::count(CR_ONLY).is_mixed());
+        assert!(Endings::count(MIXED).is_mixed());
+    }
+}
@@ -0,0 +1,496 @@
+// 输出
+//
+// 搜索结果统一通过 Output 输出，它可以直接写到标准输出，
+// 也可以写到分页器（默认 `less -R`）的标准输入。
+//
+// 使用分页器时：
+// * 分页器退出后（例如在 less 中按了 q）再写入会得到 BrokenPipe，此时 pgrep 直接退出
+// * Output 被 drop 时关闭分页器的标准输入并等待它退出，保证终端状态被正确恢复
+//
+// 搜索结果之外的结构行分为两种，可以分别改为写到标准错误，让标准输出上只剩下结果：
+// * 分隔行: `--watch` 每次重新搜索之前的 `==> ... <==`（--emit-separators-to-stderr）
+// * 标题行: `--group-by` 每一节开头的 `模式 ... (N 行):`（--emit-headings-to-stderr）
+// 写到标准错误之前先刷新标准输出，两者都是终端时行的先后顺序不变。
+//
+// 相关文档:
+// * std::process::Stdio::piped: <https://doc.rust-lang.org/std/process/struct.Stdio.html#method.piped>
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{BufWriter, Write};
+use std::process::{Child, Command, Stdio};
+
+/// 每条搜索结果之后的行尾
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LineEnding {
+    /// `\n`
+    Lf,
+    /// `\r\n`，Windows 上的一些工具需要
+    Crlf,
+    /// 不加行尾
+    None,
+}
+
+impl LineEnding {
+    /// 当前平台的行尾：Windows 上是 `\r\n`，其他平台是 `\n`
+    pub fn native() -> LineEnding {
+        if cfg!(windows) { LineEnding::Crlf } else { LineEnding::Lf }
+    }
+
+    pub fn bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::Crlf => b"\r\n",
+            LineEnding::None => b"",
+        }
+    }
+}
+
+/// 输出目标
+pub struct Output {
+    inner: RefCell<Box<dyn Write>>,
+    pager: Option<Child>,
+    // 每条搜索结果之后写入的分隔符，默认是换行，见 `--line-ending-output` 和 `--print0`
+    record_sep: &'static [u8],
+    // 分隔行和标题行是否写到标准错误
+    separators_to_stderr: bool,
+    headings_to_stderr: bool,
+}
+
+impl Output {
+    /// 直接输出到标准输出
+    pub fn stdout() -> Output {
+        Output {
+            inner: RefCell::new(Box::new(std::io::stdout())),
+            pager: None,
+            record_sep: b"\n",
+            separators_to_stderr: false,
+            headings_to_stderr: false,
+        }
+    }
+
+    /// 通过分页器输出
+    ///
+    /// 分页器命令按空白切分成参数，不经过 shell。没有设置 `LESS` 环境变量时
+    /// 设置为 `FRX`：内容不足一屏时 less 直接退出，并保留 ANSI 颜色。
+    /// 分页器无法启动（例如没有安装）时退回到直接输出到标准输出。
+    pub fn pager(cmd: &str) -> Output {
+        let argv = crate::split_command(cmd);
+        let Some((prog, rest)) = argv.split_first() else {
+            return Output::stdout();
+        };
+
+        let mut c = Command::new(prog);
+        c.args(rest).stdin(Stdio::piped());
+        if std::env::var_os("LESS").is_none() {
+            c.env("LESS", "FRX");
+        }
+
+        match c.spawn() {
+            Ok(mut child) => match child.stdin.take() {
+                Some(stdin) => Output {
+                    inner: RefCell::new(Box::new(BufWriter::new(stdin))),
+                    pager: Some(child),
+                    record_sep: b"\n",
+                    separators_to_stderr: false,
+                    headings_to_stderr: false,
+                },
+                None => Output::stdout(),
+            },
+            Err(_) => Output::stdout(),
+        }
+    }
+
+    /// 设置搜索结果之间的分隔符
+    pub fn set_record_separator(&mut self, sep: &'static [u8]) {
+        self.record_sep = sep;
+    }
+
+    /// 分隔行写到标准错误，见 `--emit-separators-to-stderr`
+    pub fn set_separators_to_stderr(&mut self, on: bool) {
+        self.separators_to_stderr = on;
+    }
+
+    /// 标题行写到标准错误，见 `--emit-headings-to-stderr`
+    pub fn set_headings_to_stderr(&mut self, on: bool) {
+        self.headings_to_stderr = on;
+    }
+
+    /// 输出一行分隔行
+    pub fn separator(&self, args: fmt::Arguments) {
+        self.structural(args, self.separators_to_stderr);
+    }
+
+    /// 输出一行标题行
+    pub fn heading(&self, args: fmt::Arguments) {
+        self.structural(args, self.headings_to_stderr);
+    }
+
+    fn structural(&self, args: fmt::Arguments, to_stderr: bool) {
+        if !to_stderr {
+            return self.line(args);
+        }
+        let _ = self.inner.borrow_mut().flush();
+        eprintln!("{}", args);
+    }
+
+    /// 输出一行
+    ///
+    /// 分页器已经退出时直接结束进程，其他写入错误被忽略
+    pub fn line(&self, args: fmt::Arguments) {
+        self.write_with(args, b"\n");
+    }
+
+    /// 输出一条搜索结果，后面跟着结果分隔符而不是固定的换行
+    pub fn record(&self, args: fmt::Arguments) {
+        self.write_with(args, self.record_sep);
+    }
+
+    fn write_with(&self, args: fmt::Arguments, end: &[u8]) {
+        let mut w = self.inner.borrow_mut();
+        if let Err(e) = w.write_fmt(args).and_then(|_| w.write_all(end))
+            && e.kind() == std::io::ErrorKind::BrokenPipe
+        {
+            std::process::exit(0);
+        }
+    }
+
+    /// 原样输出一段文本，不追加换行符
+    pub fn raw(&self, s: &str) {
+        if let Err(e) = self.inner.borrow_mut().write_all(s.as_bytes())
+            && e.kind() == std::io::ErrorKind::BrokenPipe
+        {
+            std::process::exit(0);
+        }
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        let _ = self.inner.get_mut().flush();
+        if let Some(mut child) = self.pager.take() {
+            // 替换掉写入端即关闭分页器的标准输入，分页器读到 EOF 后才会在用户退出时结束
+            *self.inner.get_mut() = Box::new(std::io::sink());
+            let _ = child.wait();
+        }
+    }
+}
+
+/// 把字节格式化成类似 `xxd` 的十六进制转储
+///
+/// 每行 16 个字节，格式为 `{偏移:08x}: {十六进制:<48} {ASCII}`，
+/// ASCII 部分中的不可打印字符显示为 `.`。结果的每一行都以换行符结尾，空输入返回空字符串。
+///
+/// # 示例
+/// `hex_dump(b"Hi\n")` 返回 `00000000: 48 69 0a` 加上补齐的空格和 `Hi.`
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        let hex = chunk
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| if (0x20..0x7f).contains(&b) { b as char } else { '.' })
+            .collect();
+        out.push_str(&format!("{:08x}: {:<48} {}\n", row * 16, hex, ascii));
+    }
+    out
+}
+
+/// 显示一行时只保留前 `max` 个字符，后面接上 `suffix`
+///
+/// 按字符而不是字节截断，不会切开多字节的字符；由多个码点组成的 emoji 也不会被切开，整个算一个字符，
+/// 见 joins_previous。已经加了颜色的文本中，`\x1b[...m` 转义序列不算字符，
+/// 截断在高亮的匹配中间时补上 `\x1b[0m` 恢复默认样式。不超过 `max` 个字符的行原样返回。
+///
+/// # 示例
+/// `display_tx("日志🙂🙂", 3, "...")` 返回 `日志🙂...`
+pub fn display_tx<'a>(tx: &'a str, max: usize, suffix: &str) -> Cow<'a, str> {
+    let mut chars = 0;
+    // 截断的位置是否在一段样式中间
+    let mut styled = false;
+    // 上一个码点，以及到它为止连续的区域指示符个数
+    let mut prev = None;
+    let mut flags = 0;
+    let mut it = tx.char_indices();
+    while let Some((i, c)) = it.next() {
+        if c == '\x1b' {
+            // 跳过整个 SGR 序列，到结尾的 `m` 为止
+            let end = it.by_ref().find(|&(_, c)| c == 'm').map_or(tx.len(), |(j, _)| j + 1);
+            styled = &tx[i..end] != "\x1b[0m";
+            continue;
+        }
+        let joined = prev.is_some_and(|p| joins_previous(p, c, flags));
+        flags = if is_regional_indicator(c) { flags + 1 } else { 0 };
+        prev = Some(c);
+        if joined {
+            continue;
+        }
+        if chars == max {
+            let reset = if styled { "\x1b[0m" } else { "" };
+            return Cow::Owned(format!("{}{}{}", &tx[..i], reset, suffix));
+        }
+        chars += 1;
+    }
+    Cow::Borrowed(tx)
+}
+
+/// `c` 是否和之前的码点显示为同一个字符，不能在它之前截断
+///
+/// 只处理 emoji 和组合符号中常见的几种情况，不是完整的 Unicode 字形簇规则（UAX #29）：
+/// * `c` 是零宽连接符 ZWJ（U+200D），或者 `prev` 是 ZWJ，例如 👨‍👩‍👧 中的每个成员
+/// * `c` 是变体选择符（U+FE00-U+FE0F、U+E0100-U+E01EF），例如 ❤️ 的 U+FE0F
+/// * `c` 是肤色修饰符（U+1F3FB-U+1F3FF），例如 👍🏽
+/// * `c` 是组合符号（U+0300-U+036F、U+20D0-U+20FF，包括 keycap 的 U+20E3）或者标签字符
+///   （U+E0020-U+E007F，英格兰等地区旗帜的后缀）
+/// * `c` 是区域指示符，并且之前已经有奇数个连续的区域指示符，两个一组组成一面国旗，例如 🇨🇳
+///
+/// `flags` 是到 `prev` 为止连续的区域指示符个数
+fn joins_previous(prev: char, c: char, flags: usize) -> bool {
+    prev == '\u{200D}'
+        || matches!(c as u32,
+            0x200D | 0xFE00..=0xFE0F | 0xE0100..=0xE01EF | 0x1F3FB..=0x1F3FF
+            | 0x0300..=0x036F | 0x20D0..=0x20FF | 0xE0020..=0xE007F)
+        || (is_regional_indicator(c) && flags % 2 == 1)
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+/// 终端的列数和行数，标准输出不是终端时返回 None
+pub fn terminal_size() -> Option<(usize, usize)> {
+    // SAFETY: TIOCGWINSZ 只写入传入的 winsize
+    let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) } == 0 && ws.ws_col > 0 && ws.ws_row > 0 {
+        Some((ws.ws_col as usize, ws.ws_row as usize))
+    } else {
+        None
+    }
+}
+
+/// 字符在终端中占的列数，中日韩文字和全角符号占两列
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// 文本在终端中占的列数，`\x1b[...m` 转义序列不占位置
+pub fn display_width(s: &str) -> usize {
+    let mut w = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            w += char_width(c);
+        }
+    }
+    w
+}
+
+/// 把一行在单词之间折行，每行不超过 `width` 列
+///
+/// 第一行前面已经有调用者输出的 `indent` 列前缀（例如 `路径:行号:`），之后的每一行用 `indent` 个空格缩进，
+/// 和第一行的内容对齐，所以每行的内容最多 `width - indent` 列。单词之间的一个空格在折行处被去掉；
+/// 比一整行还长的单词无法在单词之间折开，只能在字符之间折开。转义序列不占宽度，折行不会切开它们。
+///
+/// # 示例
+/// `word_wrap("alpha beta gamma", 14, 4)` 返回 `"alpha beta\n    gamma"`
+pub fn word_wrap(s: &str, width: usize, indent: usize) -> String {
+    let avail = width.saturating_sub(indent).max(1);
+    if display_width(s) <= avail {
+        return s.to_string();
+    }
+    let pad = " ".repeat(indent);
+    let mut out = String::with_capacity(s.len() + s.len() / avail * (indent + 1));
+    let mut used = 0;
+    for (i, word) in s.split(' ').enumerate() {
+        let w = display_width(word);
+        if i > 0 {
+            if used > 0 && used + 1 + w > avail {
+                out.push('\n');
+                out.push_str(&pad);
+                used = 0;
+            } else {
+                out.push(' ');
+                used += 1;
+            }
+        }
+        if used + w <= avail {
+            out.push_str(word);
+            used += w;
+            continue;
+        }
+        // 单词比一整行还长，按字符折开
+        let mut chars = word.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                out.push(c);
+                for c in chars.by_ref() {
+                    out.push(c);
+                    if c == 'm' {
+                        break;
+                    }
+                }
+                continue;
+            }
+            let cw = char_width(c);
+            if used > 0 && used + cw > avail {
+                out.push('\n');
+                out.push_str(&pad);
+                used = 0;
+            }
+            out.push(c);
+            used += cw;
+        }
+    }
+    out
+}
+
+/// 类似 println!，输出到 Output
+macro_rules! outln {
+    ($out:expr, $($arg:tt)*) => {
+        $out.line(format_args!($($arg)*))
+    };
+}
+pub(crate) use outln;
+
+/// 输出一条搜索结果，以结果分隔符结尾
+macro_rules! outrec {
+    ($out:expr, $($arg:tt)*) => {
+        $out.record(format_args!($($arg)*))
+    };
+}
+pub(crate) use outrec;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_tx_keeps_short_lines() {
+        assert!(matches!(display_tx("abc", 3, "..."), Cow::Borrowed("abc")));
+        assert_eq!(display_tx("abcd", 3, "..."), "abc...");
+        assert_eq!(display_tx("日志🙂🙂", 3, "..."), "日志🙂...");
+    }
+
+    #[test]
+    fn display_tx_does_not_split_emoji_sequences() {
+        // 国旗：两个区域指示符
+        let flags = "🇨🇳🇯🇵🇺🇸";
+        assert_eq!(display_tx(flags, 1, "…"), "🇨🇳…");
+        assert_eq!(display_tx(flags, 2, "…"), "🇨🇳🇯🇵…");
+        assert_eq!(display_tx(flags, 3, "…"), flags);
+        // 肤色修饰符
+        assert_eq!(display_tx("👍🏽👍🏿x", 1, "…"), "👍🏽…");
+        assert_eq!(display_tx("👍🏽👍🏿x", 2, "…"), "👍🏽👍🏿…");
+        // ZWJ 连接的家庭和职业
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        assert_eq!(display_tx(&format!("{}{}", family, family), 1, "…"), format!("{}…", family));
+        let cook = "\u{1F469}\u{1F3FD}\u{200D}\u{1F373}";
+        assert_eq!(display_tx(&format!("{}ab", cook), 1, "…"), format!("{}…", cook));
+        // 变体选择符和 keycap
+        assert_eq!(display_tx("\u{2764}\u{FE0F}\u{2764}", 1, "…"), "\u{2764}\u{FE0F}…");
+        assert_eq!(display_tx("1\u{FE0F}\u{20E3}2", 1, "…"), "1\u{FE0F}\u{20E3}…");
+        // 英格兰旗帜：黑旗加上标签字符
+        let england = "\u{1F3F4}\u{E0067}\u{E0062}\u{E0065}\u{E006E}\u{E0067}\u{E007F}";
+        assert_eq!(display_tx(&format!("{}x", england), 1, "…"), format!("{}…", england));
+        // 组合符号
+        assert_eq!(display_tx("e\u{0301}e", 1, "…"), "e\u{0301}…");
+    }
+
+    #[test]
+    fn display_tx_skips_escapes_inside_sequences() {
+        let hl = "\x1b[1;31m🇨\x1b[0m🇳x";
+        assert_eq!(display_tx(hl, 1, "…"), "\x1b[1;31m🇨\x1b[0m🇳…");
+        assert_eq!(display_tx("\x1b[1;31m👍🏽x\x1b[0m", 1, "…"), "\x1b[1;31m👍🏽\x1b[0m…");
+    }
+
+    #[test]
+    fn hex_dump_full_row() {
+        // 十六进制部分补齐到 48 列，16 个字节只占 47 列，和 ASCII 部分之间是两个空格
+        assert_eq!(
+            hex_dump(b"0123456789abcdef"),
+            "00000000: 30 31 32 33 34 35 36 37 38 39 61 62 63 64 65 66  0123456789abcdef\n"
+        );
+    }
+
+    #[test]
+    fn hex_dump_partial_row_is_padded() {
+        assert_eq!(hex_dump(b"Hi\n"), format!("00000000: 48 69 0a{} Hi.\n", " ".repeat(40)));
+        assert_eq!(
+            hex_dump(b"0123456789abcdefXY"),
+            format!(
+                "00000000: 30 31 32 33 34 35 36 37 38 39 61 62 63 64 65 66  0123456789abcdef\n\
+                 00000010: 58 59{} XY\n",
+                " ".repeat(43)
+            )
+        );
+        assert_eq!(hex_dump(b""), "");
+    }
+
+    #[test]
+    fn hex_dump_non_printable_bytes() {
+        let bytes = [0x00, 0x09, 0x1b, b' ', b'~', 0x7f, 0x80, 0xe6, 0x97, 0xa5];
+        assert_eq!(hex_dump(&bytes), format!("00000000: 00 09 1b 20 7e 7f 80 e6 97 a5{} ... ~.....\n", " ".repeat(19)));
+    }
+
+    #[test]
+    fn word_wrap_at_several_widths() {
+        let s = "the quick brown fox";
+        assert_eq!(word_wrap(s, 20, 0), s);
+        assert_eq!(word_wrap(s, 10, 0), "the quick\nbrown fox");
+        assert_eq!(word_wrap(s, 5, 0), "the\nquick\nbrown\nfox");
+        // 之后的行用 indent 个空格和第一行的内容对齐
+        assert_eq!(word_wrap("alpha beta gamma", 14, 4), "alpha beta\n    gamma");
+        assert_eq!(word_wrap("short", 80, 10), "short");
+    }
+
+    #[test]
+    fn word_wrap_counts_wide_characters() {
+        assert_eq!(word_wrap("中文 字符 测试", 6, 0), "中文\n字符\n测试");
+        assert_eq!(word_wrap("中文 字符 测试", 9, 0), "中文 字符\n测试");
+        // 整个单词放不下时按字符折开，宽字符不会被分到两行
+        assert_eq!(word_wrap("中文字符", 5, 0), "中文\n字符");
+    }
+
+    #[test]
+    fn word_wrap_splits_over_long_words() {
+        assert_eq!(word_wrap("abcdefghij xy", 6, 2), "abcd\n  efgh\n  ij\n  xy");
+        // 转义序列不占宽度，也不会被切开
+        assert_eq!(word_wrap("\x1b[1mabcdef\x1b[0m gh", 3, 0), "\x1b[1mabc\ndef\x1b[0m\ngh");
+        // 前缀比宽度还长时每行至少放一个字符
+        assert_eq!(word_wrap("ab", 2, 5), "a\n     b");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn native_line_ending_is_lf() {
+        assert_eq!(LineEnding::native(), LineEnding::Lf);
+        assert_eq!(LineEnding::native().bytes(), b"\n");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn native_line_ending_is_crlf() {
+        assert_eq!(LineEnding::native(), LineEnding::Crlf);
+        assert_eq!(LineEnding::native().bytes(), b"\r\n");
+    }
+}
@@ -0,0 +1,329 @@
+// 语音（phonetic）匹配
+//
+// 为 --sound-like 提供按发音比较单词的能力：把单词转换成语音编码，
+// 编码相同的单词被认为"读起来一样"，例如 Jeffrey 和 Jeffery 的 Metaphone 编码都是 JFR。
+//
+// 实现了两种经典算法：
+// * Soundex: 首字母加三位数字，简单、稳定，适合英文姓氏
+// * Metaphone: 按英语发音规则转换，区分度比 Soundex 高
+//
+// 两种算法都只处理 ASCII 字母，其他字符会被忽略。
+//
+// 相关资料：
+// * Soundex: <https://en.wikipedia.org/wiki/Soundex>
+// * Metaphone: <https://en.wikipedia.org/wiki/Metaphone>
+
+use clap::ValueEnum;
+
+/// 语音编码算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PhoneticMode {
+    /// Lawrence Philips 的 Metaphone 算法
+    Metaphone,
+    /// 美国 Soundex 算法
+    Soundex,
+}
+
+impl PhoneticMode {
+    /// 计算单词的语音编码，单词中没有任何字母时返回空字符串
+    pub fn encode(self, word: &str) -> String {
+        match self {
+            PhoneticMode::Metaphone => metaphone(word),
+            PhoneticMode::Soundex => soundex(word),
+        }
+    }
+}
+
+/// 语音匹配配置
+///
+/// # 字段
+/// * `mode` - 使用的编码算法
+/// * `codes` - 模式中每个单词的编码，按顺序排列
+#[derive(Debug)]
+pub struct PhoneticConfig {
+    pub mode: PhoneticMode,
+    pub codes: Vec<String>,
+}
+
+impl PhoneticConfig {
+    /// 根据模式构造配置，模式按非字母字符切分成单词
+    pub fn new(mode: PhoneticMode, pattern: &str) -> PhoneticConfig {
+        PhoneticConfig {
+            mode,
+            codes: words(pattern).map(|w| mode.encode(w)).collect(),
+        }
+    }
+
+    /// 判断一行中是否有连续的单词与模式的编码依次相同
+    pub fn is_match(&self, line: &str) -> bool {
+        if self.codes.is_empty() {
+            return false;
+        }
+        let codes: Vec<String> = words(line).map(|w| self.mode.encode(w)).collect();
+        codes.windows(self.codes.len()).any(|w| w == self.codes.as_slice())
+    }
+}
+
+/// 把文本按非字母字符切分成单词
+fn words(s: &str) -> impl Iterator<Item = &str> {
+    s.split(|c: char| !c.is_ascii_alphabetic())
+        .filter(|w| !w.is_empty())
+}
+
+/// Soundex 编码
+///
+/// 保留首字母，其余字母按发音分组映射为数字，相邻的相同数字只保留一个，
+/// 元音分隔的相同数字分别保留，而 H、W 不起分隔作用；结果不足四位时补 0。
+///
+/// # 示例
+/// * `Robert` 和 `Rupert` -> `R163`
+/// * `Tymczak` -> `T522`
+pub fn soundex(word: &str) -> String {
+    fn digit(c: u8) -> u8 {
+        match c {
+            b'B' | b'F' | b'P' | b'V' => b'1',
+            b'C' | b'G' | b'J' | b'K' | b'Q' | b'S' | b'X' | b'Z' => b'2',
+            b'D' | b'T' => b'3',
+            b'L' => b'4',
+            b'M' | b'N' => b'5',
+            b'R' => b'6',
+            // H 和 W 不分隔相同的数字
+            b'H' | b'W' => b'-',
+            // 元音
+            _ => b'0',
+        }
+    }
+
+    let letters: Vec<u8> = word
+        .bytes()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|b| b.to_ascii_uppercase())
+        .collect();
+    let Some(&first) = letters.first() else {
+        return String::new();
+    };
+
+    let mut out = vec![first];
+    let mut last = digit(first);
+    for &c in &letters[1..] {
+        let d = digit(c);
+        if d == b'-' {
+            continue;
+        }
+        if d != b'0' && d != last {
+            out.push(d);
+            if out.len() == 4 {
+                break;
+            }
+        }
+        last = d;
+    }
+    while out.len() < 4 {
+        out.push(b'0');
+    }
+    String::from_utf8(out).unwrap_or_default()
+}
+
+/// Metaphone 编码
+///
+/// 按 Lawrence Philips 1990 年发表的原始规则实现，`0` 表示 "th" 音，`X` 表示 "sh" 音。
+///
+/// # 示例
+/// * `Jeffrey` 和 `Jeffery` -> `JFR`
+/// * `Knight` -> `NT`
+pub fn metaphone(word: &str) -> String {
+    let w: Vec<u8> = word
+        .bytes()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|b| b.to_ascii_uppercase())
+        .collect();
+    if w.is_empty() {
+        return String::new();
+    }
+
+    let at = |i: usize| w.get(i).copied().unwrap_or(0);
+    let is_vowel = |c: u8| matches!(c, b'A' | b'E' | b'I' | b'O' | b'U');
+    let mut out = String::new();
+
+    // 词首的特殊组合
+    let mut start = 0;
+    match (at(0), at(1)) {
+        (b'A', b'E') | (b'G', b'N') | (b'K', b'N') | (b'P', b'N') | (b'W', b'R') => start = 1,
+        (b'X', _) => {
+            out.push('S');
+            start = 1;
+        }
+        (b'W', b'H') => {
+            out.push('W');
+            start = 2;
+        }
+        _ => {}
+    }
+
+    let n = w.len();
+    for (i, &c) in w.iter().enumerate().skip(start) {
+        let prev = if i > 0 { at(i - 1) } else { 0 };
+        let next = at(i + 1);
+        let next2 = at(i + 2);
+
+        // 相邻的重复字母只处理一次，C 除外
+        if c == prev && c != b'C' {
+            continue;
+        }
+
+        match c {
+            // 元音只在词首保留
+            b'A' | b'E' | b'I' | b'O' | b'U' => {
+                if i == 0 {
+                    out.push(c as char);
+                }
+            }
+            // 词尾的 MB 中 B 不发音
+            b'B' => {
+                if !(prev == b'M' && i + 1 == n) {
+                    out.push('B');
+                }
+            }
+            b'C' => {
+                if next == b'I' && next2 == b'A' {
+                    out.push('X');
+                } else if next == b'H' {
+                    out.push(if prev == b'S' { 'K' } else { 'X' });
+                } else if matches!(next, b'I' | b'E' | b'Y') {
+                    // SCI、SCE、SCY 中的 C 不发音
+                    if prev != b'S' {
+                        out.push('S');
+                    }
+                } else {
+                    out.push('K');
+                }
+            }
+            b'D' => {
+                if next == b'G' && matches!(next2, b'E' | b'Y' | b'I') {
+                    out.push('J');
+                } else {
+                    out.push('T');
+                }
+            }
+            b'G' => {
+                if next == b'H' && !(i + 2 >= n || is_vowel(next2)) {
+                    // GH 不在词尾且后面不是元音时不发音，如 night
+                } else if next == b'N' && (i + 2 == n || (next2 == b'E' && at(i + 3) == b'D' && i + 4 == n)) {
+                    // 词尾的 GN、GNED 中 G 不发音，如 sign、signed
+                } else if matches!(next, b'I' | b'E' | b'Y') && prev != b'G' {
+                    out.push('J');
+                } else {
+                    out.push('K');
+                }
+            }
+            b'H' => {
+                let after_silencer = matches!(prev, b'C' | b'S' | b'P' | b'T' | b'G');
+                let between_silent = is_vowel(prev) && !is_vowel(next);
+                if !after_silencer && !between_silent {
+                    out.push('H');
+                }
+            }
+            b'K' => {
+                if prev != b'C' {
+                    out.push('K');
+                }
+            }
+            b'P' => out.push(if next == b'H' { 'F' } else { 'P' }),
+            b'Q' => out.push('K'),
+            b'S' => {
+                if next == b'H' || (next == b'I' && matches!(next2, b'O' | b'A')) {
+                    out.push('X');
+                } else {
+                    out.push('S');
+                }
+            }
+            b'T' => {
+                if next == b'I' && matches!(next2, b'O' | b'A') {
+                    out.push('X');
+                } else if next == b'H' {
+                    out.push('0');
+                } else if !(next == b'C' && next2 == b'H') {
+                    out.push('T');
+                }
+            }
+            b'V' => out.push('F'),
+            b'W' | b'Y' => {
+                if is_vowel(next) {
+                    out.push(c as char);
+                }
+            }
+            b'X' => out.push_str("KS"),
+            b'Z' => out.push('S'),
+            // F J L M N R 保持不变
+            c => out.push(c as char),
+        }
+    }
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soundex_codes() {
+        for (word, code) in [
+            ("Robert", "R163"),
+            ("Rupert", "R163"),
+            ("Rubin", "R150"),
+            ("Ashcraft", "A261"),
+            ("Ashcroft", "A261"),
+            ("Tymczak", "T522"),
+            ("Pfister", "P236"),
+            ("Honeyman", "H555"),
+            ("Lee", "L000"),
+            ("o'brien", "O165"),
+            ("123", ""),
+        ] {
+            assert_eq!(soundex(word), code, "{}", word);
+        }
+    }
+
+    #[test]
+    fn metaphone_codes() {
+        for (word, code) in [
+            ("Jeffrey", "JFR"),
+            ("Jeffery", "JFR"),
+            ("Smith", "SM0"),
+            ("Smyth", "SM0"),
+            ("Knight", "NT"),
+            ("Night", "NT"),
+            ("Catherine", "K0RN"),
+            ("Kathryn", "K0RN"),
+            ("Philip", "FLP"),
+            ("Filip", "FLP"),
+            ("Stephen", "STFN"),
+            ("Steven", "STFN"),
+            ("Wright", "RT"),
+            ("Rite", "RT"),
+            ("Robert", "RBRT"),
+            ("Rupert", "RPRT"),
+            ("123", ""),
+        ] {
+            assert_eq!(metaphone(word), code, "{}", word);
+        }
+    }
+
+    #[test]
+    fn equivalent_names_match_in_both_modes() {
+        let pairs = [("Robert", "Rupert", true, false), ("Jeffrey", "Jeffery", true, true), ("Smith", "Smyth", true, true)];
+        for (a, b, soundex_same, metaphone_same) in pairs {
+            assert_eq!(PhoneticConfig::new(PhoneticMode::Soundex, a).is_match(b), soundex_same, "{} {}", a, b);
+            assert_eq!(PhoneticConfig::new(PhoneticMode::Metaphone, a).is_match(b), metaphone_same, "{} {}", a, b);
+        }
+    }
+
+    #[test]
+    fn multi_word_patterns_match_consecutive_words() {
+        let cfg = PhoneticConfig::new(PhoneticMode::Metaphone, "Steven Smith");
+        assert!(cfg.is_match("signed: stephen smyth, 1990"));
+        assert!(!cfg.is_match("stephen and smyth"));
+        assert!(!PhoneticConfig::new(PhoneticMode::Soundex, "--").is_match("anything"));
+    }
+}
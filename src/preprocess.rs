@@ -0,0 +1,152 @@
+// 预处理命令
+//
+// --pre 把文件交给外部命令转换成文本后再搜索，例如用 pdftotext 提取 PDF 中的文字。
+// 命令以文件路径作为唯一的参数运行，搜索的是它的标准输出，而不是文件本身的内容。
+//
+// * 设置了 --pre-glob 时只有匹配其中某个通配符的文件才经过预处理，其他文件照常读取
+// * 命令以非零状态退出时，它的标准错误输出附在这个文件的错误信息中
+// * 命令运行超过 --pre-timeout 时被杀掉，同样作为这个文件的错误报告出来
+//
+// compress 模块也通过这里的 `pipe` 调用外部的 bzip2、xz、zstd 程序解压，数据从标准输入传入。
+//
+// 相关文档:
+// * ripgrep 的 --pre: <https://github.com/BurntSushi/ripgrep/blob/master/GUIDE.md#preprocessor>
+// * std::process::Child::try_wait: <https://doc.rust-lang.org/std/process/struct.Child.html#method.try_wait>
+
+use failure::{Error, Fail};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::glob::Glob;
+
+/// 预处理命令以非零状态退出或者超时
+#[derive(Debug, Fail)]
+#[fail(display = "预处理命令 {} 处理 {} 失败（{}）{}", cmd, path, reason, stderr)]
+pub struct PreprocessErr {
+    cmd: String,
+    path: String,
+    reason: String,
+    // 为空，或者是以换行开头的命令的标准错误输出
+    stderr: String,
+}
+
+/// 预处理配置
+///
+/// # 字段
+/// * `cmd` - 预处理命令，直接运行而不经过 shell
+/// * `globs` - 需要预处理的文件，为空时所有文件都经过预处理
+/// * `timeout` - 单个文件允许的最长处理时间
+#[derive(Debug)]
+pub struct Preprocessor {
+    pub cmd: PathBuf,
+    pub globs: Vec<Glob>,
+    pub timeout: Option<Duration>,
+}
+
+impl Preprocessor {
+    /// 判断文件是否需要预处理
+    pub fn applies_to(&self, p: &Path) -> bool {
+        self.globs.is_empty() || self.globs.iter().any(|g| g.is_match(p))
+    }
+
+    /// 运行预处理命令，返回它的标准输出
+    pub fn run(&self, p: &Path) -> Result<Vec<u8>, Error> {
+        let mut cmd = Command::new(&self.cmd);
+        cmd.arg(p);
+        run_command(cmd, &self.cmd.display().to_string(), p, None, self.timeout)
+    }
+}
+
+/// 把 `input` 写到命令的标准输入，返回它的标准输出
+///
+/// compress 模块用它调用外部的解压程序：压缩数据可能是包中的成员，没有可以作为参数的路径。
+/// 命令的错误和 `--pre` 一样报告为 PreprocessErr，`p` 只用于错误信息。
+pub fn pipe(program: &str, args: &[&str], p: &Path, input: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    let label = std::iter::once(program).chain(args.iter().copied()).collect::<Vec<_>>().join(" ");
+    run_command(cmd, &label, p, Some(input.to_vec()), None)
+}
+
+/// 运行命令并收集它的标准输出，`input` 为 None 时标准输入是空的
+fn run_command(
+    mut cmd: Command,
+    label: &str,
+    p: &Path,
+    input: Option<Vec<u8>>,
+    timeout: Option<Duration>,
+) -> Result<Vec<u8>, Error> {
+    let err = |reason: String, stderr: &[u8]| -> Error {
+        let stderr = String::from_utf8_lossy(stderr);
+        let stderr = stderr.trim_end();
+        PreprocessErr {
+            cmd: label.to_string(),
+            path: p.display().to_string(),
+            reason,
+            stderr: if stderr.is_empty() {
+                String::new()
+            } else {
+                format!("\n{}", stderr)
+            },
+        }
+        .into()
+    };
+
+    let mut child = cmd
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| err(format!("无法启动: {}", e), b""))?;
+
+    // 标准输入也在后台写，命令读完之前就退出时写入失败，结果以退出状态为准
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(&input);
+        });
+    }
+
+    // 两个管道都要在后台读，否则输出较多时命令会因为管道写满而卡住
+    let drain = |r: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut r) = r {
+                let _ = r.read_to_end(&mut buf);
+            }
+            buf
+        })
+    };
+    let out = drain(child.stdout.take().map(|r| Box::new(r) as _));
+    let errs = drain(child.stderr.take().map(|r| Box::new(r) as _));
+
+    let status = match timeout {
+        None => child.wait()?,
+        Some(limit) => {
+            let started = Instant::now();
+            // 大多数命令很快就结束，轮询的间隔从 1 毫秒开始逐渐加长
+            let mut poll = Duration::from_millis(1);
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+                if started.elapsed() > limit {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    // 命令启动的子进程可能还拿着管道，这里不再等待读取线程
+                    return Err(err(format!("超过了 {} 秒的时间限制", limit.as_secs_f64()), b""));
+                }
+                std::thread::sleep(poll);
+                poll = (poll * 2).min(Duration::from_millis(50));
+            }
+        }
+    };
+
+    let stdout = out.join().unwrap_or_default();
+    let stderr = errs.join().unwrap_or_default();
+    if !status.success() {
+        return Err(err(status.to_string(), &stderr));
+    }
+    Ok(stdout)
+}
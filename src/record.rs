@@ -0,0 +1,254 @@
+// 自定义输出记录的格式
+//
+// --record-format 用模板代替默认的 `路径:行号:内容`，例如加上时间戳，便于和监控系统中的事件对照：
+//
+//     --record-format "{ts:iso} {file}:{line} {text}"
+//
+// 可以使用的占位符：
+// * `{file}` / `{line}` / `{text}`: 路径、从 1 开始的行号、行内容（`--replace` 时是替换后的内容）
+// * `{ts}`: 当前的 Unix 时间戳，单位由 --timestamp-precision 决定（默认是毫秒）
+// * `{ts:iso}`: ISO 8601 格式的 UTC 时间，例如 `2024-05-01T08:30:00.123Z`，
+//   小数部分的位数同样由 --timestamp-precision 决定（s 时没有小数部分）
+// * `{{` 和 `}}` 表示字面的 `{` 和 `}`
+//
+// 时间戳是在输出这条记录时取得的，而不是读取文件的时间，所以能反映结果实际出现在下游的时刻。
+// 系统时钟被往回调整时，时间戳保持为已经输出过的最大值，同一次运行中的时间戳不会减小。
+// 模板在启动时解析，写错的占位符直接报错，而不是在输出中原样出现。
+//
+// 相关文档:
+// * std::time::SystemTime: <https://doc.rust-lang.org/std/time/struct.SystemTime.html>
+// * ISO 8601 / RFC 3339: <https://www.rfc-editor.org/rfc/rfc3339>
+// * 日期计算: <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+
+use failure::Fail;
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 模板无法解析
+#[derive(Debug, Fail)]
+#[fail(display = "无法解析 --record-format {:?}: {}", template, reason)]
+pub struct RecordFormatErr {
+    template: String,
+    reason: String,
+}
+
+/// `--timestamp-precision` 的取值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Precision {
+    /// 秒
+    S,
+    /// 毫秒
+    Ms,
+    /// 微秒
+    Us,
+    /// 纳秒
+    Ns,
+}
+
+impl Precision {
+    /// ISO 8601 格式中小数部分的位数
+    fn digits(self) -> u32 {
+        match self {
+            Precision::S => 0,
+            Precision::Ms => 3,
+            Precision::Us => 6,
+            Precision::Ns => 9,
+        }
+    }
+}
+
+/// 模板中的一段
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    File,
+    Line,
+    Text,
+    Ts,
+    TsIso,
+}
+
+/// 解析好的模板
+#[derive(Debug)]
+pub struct RecordFormat {
+    parts: Vec<Part>,
+    precision: Precision,
+    /// 已经输出过的最大时间戳（纳秒），保证时间戳不会减小
+    last: Cell<u128>,
+}
+
+impl RecordFormat {
+    /// 解析 `--record-format` 的模板
+    ///
+    /// # 示例
+    /// * `{ts} {file}:{line}:{text}`
+    /// * `[{ts:iso}] {text}`
+    pub fn parse(template: &str, precision: Precision) -> Result<RecordFormat, RecordFormatErr> {
+        let err = |reason: String| RecordFormatErr {
+            template: template.to_string(),
+            reason,
+        };
+        let mut parts = Vec::new();
+        let mut lit = String::new();
+        let mut rest = template;
+        while let Some(i) = rest.find(['{', '}']) {
+            lit.push_str(&rest[..i]);
+            let after = &rest[i + 1..];
+            if rest[i..].starts_with("{{") || rest[i..].starts_with("}}") {
+                lit.push_str(&rest[i..i + 1]);
+                rest = &after[1..];
+                continue;
+            }
+            if rest[i..].starts_with('}') {
+                return Err(err("单独的 `}`，字面的 `}` 要写成 `}}`".to_string()));
+            }
+            let close = after
+                .find('}')
+                .ok_or_else(|| err("`{` 没有对应的 `}`，字面的 `{` 要写成 `{{`".to_string()))?;
+            let part = match &after[..close] {
+                "file" => Part::File,
+                "line" => Part::Line,
+                "text" => Part::Text,
+                "ts" => Part::Ts,
+                "ts:iso" => Part::TsIso,
+                other => {
+                    return Err(err(format!(
+                        "未知的占位符 {{{}}}，可以是 {{file}}、{{line}}、{{text}}、{{ts}}、{{ts:iso}}",
+                        other
+                    )));
+                }
+            };
+            if !lit.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut lit)));
+            }
+            parts.push(part);
+            rest = &after[close + 1..];
+        }
+        lit.push_str(rest);
+        if !lit.is_empty() {
+            parts.push(Part::Literal(lit));
+        }
+        Ok(RecordFormat {
+            parts,
+            precision,
+            last: Cell::new(0),
+        })
+    }
+
+    /// 生成一条记录，需要时间戳时在这里取得当前时间
+    ///
+    /// # 参数
+    /// * `file` / `line` / `text` - 已经按需要加上颜色的路径、行号和内容
+    pub fn render(&self, file: &str, line: &str, text: &str) -> String {
+        let needs_ts = self.parts.iter().any(|p| matches!(p, Part::Ts | Part::TsIso));
+        let nanos = if needs_ts { self.now() } else { 0 };
+        let mut out = String::new();
+        for p in &self.parts {
+            match p {
+                Part::Literal(s) => out.push_str(s),
+                Part::File => out.push_str(file),
+                Part::Line => out.push_str(line),
+                Part::Text => out.push_str(text),
+                Part::Ts => {
+                    let unit = 10u128.pow(9 - self.precision.digits());
+                    out.push_str(&(nanos / unit).to_string());
+                }
+                Part::TsIso => out.push_str(&iso8601(nanos, self.precision.digits())),
+            }
+        }
+        out
+    }
+
+    /// 当前时间（纳秒），不小于之前取得的最大值
+    fn now(&self) -> u128 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let now = now.max(self.last.get());
+        self.last.set(now);
+        now
+    }
+}
+
+/// 把 Unix 时间（纳秒）写成 ISO 8601 格式的 UTC 时间，`digits` 是小数部分的位数
+fn iso8601(nanos: u128, digits: u32) -> String {
+    let secs = (nanos / 1_000_000_000) as i64;
+    let (days, sod) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // civil_from_days: 1970-01-01 起的天数换算成公历日期
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let mut s = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        sod / 3600,
+        sod % 3600 / 60,
+        sod % 60
+    );
+    if digits > 0 {
+        let frac = nanos % 1_000_000_000 / 10u128.pow(9 - digits);
+        s.push_str(&format!(".{:0width$}", frac, width = digits as usize));
+    }
+    s.push('Z');
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ts_is_non_decreasing_within_a_run() {
+        let f = RecordFormat::parse("{ts}", Precision::Ns).unwrap();
+        let stamps: Vec<u128> = (0..1000).map(|_| f.render("a", "1", "x").parse().unwrap()).collect();
+        assert!(stamps.windows(2).all(|w| w[0] <= w[1]), "{:?}", stamps);
+
+        // 时钟往回调整时保持已经输出过的最大值
+        let ahead = stamps[999] + 3_600_000_000_000;
+        f.last.set(ahead);
+        assert_eq!(f.render("a", "1", "x"), ahead.to_string());
+    }
+
+    #[test]
+    fn ts_precision() {
+        let width = |p| RecordFormat::parse("{ts}", p).unwrap().render("", "", "").len();
+        // 2001 年之后的时间戳：秒 10 位，毫秒 13 位，微秒 16 位，纳秒 19 位
+        assert_eq!(
+            [width(Precision::S), width(Precision::Ms), width(Precision::Us), width(Precision::Ns)],
+            [10, 13, 16, 19]
+        );
+    }
+
+    #[test]
+    fn iso8601_dates() {
+        assert_eq!(iso8601(0, 0), "1970-01-01T00:00:00Z");
+        assert_eq!(iso8601(951_782_400_123_456_789, 3), "2000-02-29T00:00:00.123Z");
+        assert_eq!(iso8601(1_714_552_200_123_456_789, 6), "2024-05-01T08:30:00.123456Z");
+        assert_eq!(iso8601(1_714_552_200_123_456_789, 9), "2024-05-01T08:30:00.123456789Z");
+    }
+
+    #[test]
+    fn parse_placeholders_and_escapes() {
+        let f = RecordFormat::parse("{{{file}}}:{line} {text}", Precision::Ms).unwrap();
+        assert_eq!(f.render("a.txt", "3", "hit"), "{a.txt}:3 hit");
+        for (template, reason) in [
+            ("{nope}", "未知的占位符 {nope}"),
+            ("{file", "`{` 没有对应的 `}`"),
+            ("a}b", "单独的 `}`"),
+        ] {
+            let err = RecordFormat::parse(template, Precision::Ms).unwrap_err().to_string();
+            assert!(err.contains(reason), "{}", err);
+        }
+    }
+}
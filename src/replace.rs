@@ -0,0 +1,388 @@
+// 替换模板
+//
+// --replace 的模板引擎。regex 库自带的 `Captures::expand` 只支持 `$1` 这类分组引用，
+// 这里在此基础上增加了 Perl / sed 风格的大小写转换运算符，方便重命名标识符。
+//
+// 支持的语法：
+//
+// | 写法              | 含义                                                |
+// |-------------------|-----------------------------------------------------|
+// | `$1` `${1}`       | 第 1 个捕获分组，`$0` 为整个匹配                     |
+// | `$name` `${name}` | 命名分组 `(?P<name>...)`                             |
+// | `$$`              | 字面的 `$`                                           |
+// | `\U`              | 之后的文本转换为大写，直到 `\E`、`\L` 或模板结束      |
+// | `\L`              | 之后的文本转换为小写，直到 `\E`、`\U` 或模板结束      |
+// | `\E`              | 结束 `\U` / `\L` 的作用范围                          |
+// | `\u`              | 只把紧随其后的一个字符转换为大写                      |
+// | `\l`              | 只把紧随其后的一个字符转换为小写                      |
+// | `\\`              | 字面的 `\`                                           |
+// | `{{n}}`           | 这是第几次替换，从 1 开始                            |
+// | `{{n:04}}`        | 同上，宽度为 4，不足时在前面补 0（`{{n:4}}` 补空格）  |
+//
+// 作用范围按模板计算：每次展开模板都从"不转换"状态开始，不会延续到下一个匹配。
+// 转换同时作用于字面文本和分组内容。`\u` / `\l` 可以和 `\L` / `\U` 组合，
+// 例如 `\u\L$1` 把首字母大写、其余字母小写。
+//
+// 名字形式的 `$name` 与 regex 库一致，会尽可能长地读取 `[0-9A-Za-z_]`，
+// 需要紧跟其他字母时请使用 `${name}`。
+//
+// 计数器 `{{n}}` 在整个运行中递增，每展开一次模板（即每个匹配）加 1，同一个模板中的几个 `{{n}}` 是同一个数。
+// 编号的顺序就是搜索的顺序：文件逐个搜索，使用计数器时目录中的条目按名字排序（见 `GrepConfig::sorted_walk`），
+// 同样的文件树每次运行得到同样的编号。`--write-replace` 等改写整个文件的操作从这个文件在搜索中开始的编号
+// 重新计数（见 `Template::replace_lines`），和搜索结果中显示的编号一致。其他形式的 `{{` 按字面处理。
+//
+// --insert-before / --insert-after 也是模板，用 `Template::concat` 和匹配本身（`$0`，或者 --replace 的模板）
+// 连接成一个模板，效果是 `前 + 匹配 + 后`。连接处重新从"不转换"状态开始，
+// 插入文本中没有结束的 `\U` 不会作用到匹配上。
+
+use std::sync::{Arc, Mutex};
+
+use failure::{Error, Fail};
+use regex::{Captures, Regex};
+
+use crate::newline::Newline;
+
+/// 模板语法错误
+#[derive(Debug, Fail)]
+#[fail(display = "替换模板错误: {}", msg)]
+pub struct TemplateErr {
+    msg: String,
+}
+
+/// 大小写转换方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Upper,
+    Lower,
+}
+
+/// 分组引用
+#[derive(Debug, Clone)]
+enum Group {
+    Index(usize),
+    Name(String),
+}
+
+/// 模板中的一个片段
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Group(Group),
+    // \U 或 \L，None 表示 \E
+    Span(Option<Case>),
+    // \u 或 \l
+    Next(Case),
+    // 连接的模板之间，同时结束 \U / \L 和 \u / \l
+    Reset,
+    // {{n}}，`width` 是最小宽度，`zero` 表示用 0 补齐
+    Counter { width: usize, zero: bool },
+}
+
+/// `{{n}}` 的计数状态，模板的所有副本共享同一个计数器
+#[derive(Debug, Default)]
+struct CounterState {
+    /// 已经展开的次数
+    value: usize,
+    /// 当前文件开始时的 `value`
+    file_start: usize,
+    /// 每个文件重新从 1 开始
+    per_file: bool,
+}
+
+/// 解析好的替换模板
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+    /// 模板中有 `{{n}}` 时才有计数器
+    counter: Option<Arc<Mutex<CounterState>>>,
+}
+
+impl Template {
+    /// 解析模板，并检查其中引用的分组在正则表达式中确实存在
+    ///
+    /// # 错误
+    /// 未知的 `\` 运算符、未闭合的 `${`、引用不存在的分组都会返回 TemplateErr
+    pub fn parse(s: &str, re: &Regex) -> Result<Template, Error> {
+        let err = |msg: String| -> Error { TemplateErr { msg }.into() };
+        let mut parts = Vec::new();
+        let mut lit = String::new();
+        let mut chars = s.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    let op = match chars.next() {
+                        Some((_, op)) => op,
+                        None => return Err(err("模板以单独的 '\\' 结尾".to_string())),
+                    };
+                    let part = match op {
+                        '\\' => {
+                            lit.push('\\');
+                            continue;
+                        }
+                        'U' => Part::Span(Some(Case::Upper)),
+                        'L' => Part::Span(Some(Case::Lower)),
+                        'E' => Part::Span(None),
+                        'u' => Part::Next(Case::Upper),
+                        'l' => Part::Next(Case::Lower),
+                        op => {
+                            return Err(err(format!(
+                                "不支持的运算符 '\\{}'，只支持 \\U \\L \\E \\u \\l 和 \\\\",
+                                op
+                            )));
+                        }
+                    };
+                    if !lit.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut lit)));
+                    }
+                    parts.push(part);
+                }
+                '$' => {
+                    let rest = &s[i + 1..];
+                    let (name, used) = if rest.starts_with('$') {
+                        lit.push('$');
+                        chars.next();
+                        continue;
+                    } else if let Some(body) = rest.strip_prefix('{') {
+                        let end = body
+                            .find('}')
+                            .ok_or_else(|| err(format!("'${{' 没有闭合: ${}", rest)))?;
+                        (&body[..end], end + 2)
+                    } else {
+                        let end = rest
+                            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                            .unwrap_or(rest.len());
+                        (&rest[..end], end)
+                    };
+                    // 和 regex 库一样，`$` 后面不是合法的分组名时按字面处理
+                    if name.is_empty() {
+                        lit.push('$');
+                        continue;
+                    }
+                    for _ in 0..rest[..used].chars().count() {
+                        chars.next();
+                    }
+                    let g = match name.parse::<usize>() {
+                        Ok(n) if n < re.captures_len() => Group::Index(n),
+                        Ok(n) => {
+                            return Err(err(format!(
+                                "引用了不存在的分组 ${}（模式中只有 {} 个分组）",
+                                n,
+                                re.captures_len() - 1
+                            )));
+                        }
+                        Err(_) if re.capture_names().flatten().any(|n| n == name) => {
+                            Group::Name(name.to_string())
+                        }
+                        Err(_) => return Err(err(format!("引用了不存在的命名分组 ${{{}}}", name))),
+                    };
+                    if !lit.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut lit)));
+                    }
+                    parts.push(Part::Group(g));
+                }
+                '{' => match counter_token(&s[i..]) {
+                    Some((part, used)) => {
+                        if !lit.is_empty() {
+                            parts.push(Part::Literal(std::mem::take(&mut lit)));
+                        }
+                        parts.push(part);
+                        // 记号只包含 ASCII 字符，字节数就是字符数
+                        for _ in 1..used {
+                            chars.next();
+                        }
+                    }
+                    None => lit.push('{'),
+                },
+                c => lit.push(c),
+            }
+        }
+        if !lit.is_empty() {
+            parts.push(Part::Literal(lit));
+        }
+        let counter = parts
+            .iter()
+            .any(|p| matches!(p, Part::Counter { .. }))
+            .then(Arc::default);
+        Ok(Template { parts, counter })
+    }
+
+    /// 整个匹配，即 `$0`
+    pub fn whole_match() -> Template {
+        Template {
+            parts: vec![Part::Group(Group::Index(0))],
+            counter: None,
+        }
+    }
+
+    /// 依次连接多个模板，每个模板的大小写转换只作用于它自己
+    ///
+    /// # 示例
+    /// `--insert-before "<<" --insert-after ">>"` 是 `concat(["<<", $0, ">>"])`
+    pub fn concat(templates: &[Template]) -> Template {
+        let mut parts = Vec::new();
+        for (i, t) in templates.iter().enumerate() {
+            if i > 0 {
+                parts.push(Part::Reset);
+            }
+            parts.extend(t.parts.iter().cloned());
+        }
+        // 几个模板中都有 `{{n}}` 时共用第一个计数器，一次展开仍然只加 1
+        let counter = templates.iter().find_map(|t| t.counter.clone());
+        Template { parts, counter }
+    }
+
+    /// 模板中是否有 `{{n}}`
+    pub fn has_counter(&self) -> bool {
+        self.counter.is_some()
+    }
+
+    /// `{{n}}` 在每个文件中重新从 1 开始，而不是在整个运行中递增
+    pub fn set_counter_per_file(&self) {
+        if let Some(c) = &self.counter {
+            lock(c).per_file = true;
+        }
+    }
+
+    /// 开始搜索一个文件，记下这个文件开始时的编号
+    pub fn begin_file(&self) {
+        if let Some(c) = &self.counter {
+            let mut c = lock(c);
+            if c.per_file {
+                c.value = 0;
+            }
+            c.file_start = c.value;
+        }
+    }
+
+    /// 回到当前文件开始时的编号，再次展开这个文件的匹配时得到同样的编号
+    pub fn rewind_file(&self) {
+        if let Some(c) = &self.counter {
+            let mut c = lock(c);
+            c.value = c.file_start;
+        }
+    }
+
+    /// 用一次匹配的捕获结果展开模板，追加到 `out`
+    pub fn expand(&self, caps: &Captures, out: &mut String) {
+        let n = self.counter.as_ref().map_or(0, |c| {
+            let mut c = lock(c);
+            c.value += 1;
+            c.value
+        });
+        let mut span: Option<Case> = None;
+        let mut next: Option<Case> = None;
+
+        let mut emit = |text: &str, span: Option<Case>, next: &mut Option<Case>| {
+            for c in text.chars() {
+                match next.take().or(span) {
+                    Some(Case::Upper) => out.extend(c.to_uppercase()),
+                    Some(Case::Lower) => out.extend(c.to_lowercase()),
+                    None => out.push(c),
+                }
+            }
+        };
+
+        for part in &self.parts {
+            match part {
+                Part::Literal(s) => emit(s, span, &mut next),
+                Part::Group(g) => {
+                    let m = match g {
+                        Group::Index(n) => caps.get(*n),
+                        Group::Name(n) => caps.name(n),
+                    };
+                    // 没有参与匹配的分组展开为空字符串
+                    if let Some(m) = m {
+                        emit(m.as_str(), span, &mut next);
+                    }
+                }
+                Part::Span(c) => span = *c,
+                Part::Next(c) => next = Some(*c),
+                Part::Reset => {
+                    span = None;
+                    next = None;
+                }
+                Part::Counter { width, zero } => {
+                    let s = if *zero {
+                        format!("{:0width$}", n, width = *width)
+                    } else {
+                        format!("{:width$}", n, width = *width)
+                    };
+                    emit(&s, span, &mut next);
+                }
+            }
+        }
+    }
+
+    /// 替换一行文本中的所有匹配
+    pub fn replace_all(&self, re: &Regex, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for caps in re.captures_iter(text) {
+            let Some(m) = caps.get(0) else { continue };
+            out.push_str(&text[last..m.start()]);
+            self.expand(&caps, &mut out);
+            last = m.end();
+        }
+        out.push_str(&text[last..]);
+        out
+    }
+
+    /// 逐行替换整个文件的内容
+    ///
+    /// 和搜索时一样按行匹配，匹配不会跨越行尾；
+    /// 行尾按 `newline` 识别，每行原来的行尾（`\n`、`\r\n`、`\r` 或者没有）保持不变，`--patch` 生成的补丁中
+    /// 只有真正被替换的行才会出现差异。`only` 返回 false 的行保持原样，
+    /// 替换结果让 `accept` 返回 false 的行也保持原样（见 `--replace-verify-skip`）。
+    ///
+    /// 整个文件在搜索时已经展开过一遍，`{{n}}` 先回到这个文件开始时的编号，见 rewind_file
+    pub fn replace_lines(
+        &self,
+        re: &Regex,
+        text: &str,
+        newline: Newline,
+        only: impl Fn(&str) -> bool,
+        accept: impl Fn(&str) -> bool,
+    ) -> String {
+        self.rewind_file();
+        let mut out = String::with_capacity(text.len());
+        for (body, eol) in newline.split(text) {
+            match only(body).then(|| self.replace_all(re, body)) {
+                Some(new) if accept(&new) => out.push_str(&new),
+                _ => out.push_str(body),
+            }
+            out.push_str(eol);
+        }
+        out
+    }
+}
+
+/// 解析 `s` 开头的 `{{n}}` / `{{n:WIDTH}}`，返回片段和记号占用的字节数
+fn counter_token(s: &str) -> Option<(Part, usize)> {
+    let rest = s.strip_prefix("{{n")?;
+    let spec = match rest.strip_prefix("}}") {
+        Some(_) => "",
+        None => {
+            let spec = rest.strip_prefix(':')?;
+            let end = spec.find("}}")?;
+            &spec[..end]
+        }
+    };
+    if !spec.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let width = if spec.is_empty() { 0 } else { spec.parse().ok()? };
+    let used = if spec.is_empty() { 5 } else { 6 + spec.len() };
+    Some((
+        Part::Counter {
+            width,
+            zero: spec.starts_with('0'),
+        },
+        used,
+    ))
+}
+
+/// 锁住计数器，持有锁的线程 panic 之后计数仍然可以继续使用
+fn lock(c: &Mutex<CounterState>) -> std::sync::MutexGuard<'_, CounterState> {
+    c.lock().unwrap_or_else(|e| e.into_inner())
+}
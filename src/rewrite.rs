@@ -0,0 +1,200 @@
+// 直接修改文件
+//
+// --write-replace 用替换模板改写每个包含匹配的文件，替换规则和 `--replace` / `--patch` 相同。
+//
+// 写入过程：
+// * 先在同一个目录中写一个临时文件并刷到磁盘，再重命名覆盖原文件；
+//   同一文件系统内的 rename 是原子的，中途崩溃时原文件要么完全是旧内容，要么完全是新内容，
+//   最多留下一个以 `.pgrep-` 开头的临时文件
+// * 临时文件的权限和原文件相同，以 root 运行时还会保留原文件的所有者和组
+// * 替换后内容没有变化的文件不会被重写，修改时间保持不变
+// * `--backup SUFFIX` 在覆盖之前把原文件复制为 `文件名SUFFIX`
+//
+// `--diff` 只计算替换结果（plan），以补丁的形式显示出来，不写任何文件。
+//
+// `--expect-matches N` 先为所有文件计算替换结果，替换总数等于 N 时才写入（apply），
+// 不等时一个文件都不修改，防止模式意外匹配了多得多的内容。
+//
+// 相关文档:
+// * std::fs::rename: <https://doc.rust-lang.org/std/fs/fn.rename.html>
+// * fchown(2): <https://man7.org/linux/man-pages/man2/fchown.2.html>
+
+use failure::{Error, Fail};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use pgrep::GrepConfig;
+use pgrep::replace::Template;
+use regex::Regex;
+
+/// `--write-replace` 需要写回文件，标准输入没有可以写回的地方
+#[derive(Debug, Fail)]
+#[fail(display = "--write-replace 不支持标准输入")]
+pub struct WriteStdin;
+
+/// `--expect-matches` 给出的替换次数和实际的不同
+#[derive(Debug, Fail)]
+#[fail(
+    display = "--expect-matches: 预期 {} 处替换，实际有 {} 处（{} 个文件），没有修改任何文件",
+    expected, actual, files
+)]
+pub struct ExpectMismatch {
+    pub expected: usize,
+    pub actual: usize,
+    pub files: usize,
+}
+
+/// 替换后的内容含有 latin1 无法表示的字符，无法按原来的编码写回
+#[derive(Debug, Fail)]
+#[fail(display = "{} 不是 UTF-8 文件，替换结果中的 {:?} 无法按单字节编码写回", path, ch)]
+pub struct Unencodable {
+    path: String,
+    ch: char,
+}
+
+/// 替换的计算结果，还没有写入文件
+pub enum Plan {
+    /// 内容会发生变化
+    ///
+    /// `old` / `new` 是替换前后的文本，`count` 是替换的次数；
+    /// `utf8` 为 false 时文本是按 latin1 逐字节解码的结果
+    Change {
+        old: String,
+        new: String,
+        count: usize,
+        utf8: bool,
+    },
+    /// 替换后内容没有变化
+    Unchanged,
+    /// 文件被跳过，以及原因
+    Skipped(&'static str),
+}
+
+/// 改写一个文件的结果
+pub enum Outcome {
+    /// 文件已被改写，以及替换的次数
+    Changed(usize),
+    /// 替换后内容没有变化，文件没有被改写
+    Unchanged,
+    /// 文件被跳过，以及原因
+    Skipped(&'static str),
+}
+
+/// 写入选项
+///
+/// # 字段
+/// * `backup` - 设置后覆盖之前把原文件复制一份，文件名加上这个后缀
+/// * `force` - 也改写二进制文件和不是 UTF-8 的文件
+pub struct Options<'a> {
+    pub backup: Option<&'a str>,
+    pub force: bool,
+}
+
+/// 计算用模板替换文件中的所有匹配之后的内容
+///
+/// 不是 UTF-8 的文件在 `force` 时按 latin1 逐字节解码，写回时再逐字节编码，
+/// 不属于匹配的字节保持不变。
+///
+/// # 参数
+/// * `p` - 包含匹配的文件
+/// * `re` - 编译好的正则表达式对象
+/// * `cfg` - 搜索配置，被 `--line-prefix` 等排除的行不替换
+/// * `t` - 替换模板
+/// * `force` - 也处理二进制文件和不是 UTF-8 的文件
+pub fn plan(p: &Path, re: &Regex, cfg: &GrepConfig, t: &Template, force: bool) -> Result<Plan, Error> {
+    if p == Path::new("-") {
+        return Err(WriteStdin.into());
+    }
+    let bts = std::fs::read(p)?;
+    if bts.contains(&0) && !force {
+        return Ok(Plan::Skipped("看起来是二进制文件"));
+    }
+    let (old, utf8) = match String::from_utf8(bts) {
+        Ok(s) => (s, true),
+        Err(_) if !force => return Ok(Plan::Skipped("不是合法的 UTF-8")),
+        Err(e) => (e.into_bytes().iter().map(|&b| b as char).collect(), false),
+    };
+
+    let allowed = |l: &str| cfg.line_allowed(l);
+    let new = t.replace_lines(re, &old, cfg.newline, allowed, |new| cfg.verified(new));
+    if new == old {
+        return Ok(Plan::Unchanged);
+    }
+    let count = cfg
+        .newline
+        .lines(&old)
+        .filter(|l| allowed(l))
+        .map(|l| re.find_iter(l).count())
+        .sum();
+    Ok(Plan::Change { old, new, count, utf8 })
+}
+
+/// 用模板替换文件中的所有匹配并写回，见 plan
+pub fn rewrite(p: &Path, re: &Regex, cfg: &GrepConfig, t: &Template, opts: &Options) -> Result<Outcome, Error> {
+    apply(p, plan(p, re, cfg, t, opts.force)?, opts)
+}
+
+/// 把 plan 计算好的结果写回文件
+pub fn apply(p: &Path, plan: Plan, opts: &Options) -> Result<Outcome, Error> {
+    let (new, count, utf8) = match plan {
+        Plan::Change { new, count, utf8, .. } => (new, count, utf8),
+        Plan::Unchanged => return Ok(Outcome::Unchanged),
+        Plan::Skipped(why) => return Ok(Outcome::Skipped(why)),
+    };
+
+    let data = if utf8 {
+        new.into_bytes()
+    } else {
+        new.chars()
+            .map(|ch| {
+                u8::try_from(ch as u32).map_err(|_| Unencodable {
+                    path: p.display().to_string(),
+                    ch,
+                })
+            })
+            .collect::<Result<Vec<u8>, _>>()?
+    };
+
+    if let Some(suffix) = opts.backup {
+        let mut bak = p.as_os_str().to_os_string();
+        bak.push(suffix);
+        std::fs::copy(p, PathBuf::from(bak))?;
+    }
+    replace_atomically(p, &data)?;
+    Ok(Outcome::Changed(count))
+}
+
+/// 写入同目录下的临时文件，复制原文件的权限和所有者后重命名覆盖原文件
+///
+/// 符号链接先解析到它最终指向的文件，临时文件建在目标所在的目录中、重命名覆盖目标，链接本身保持不变
+fn replace_atomically(p: &Path, data: &[u8]) -> Result<(), Error> {
+    let p = &std::fs::canonicalize(p)?;
+    let md = p.metadata()?;
+    let dir = match p.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    };
+    let name = p.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let tmp = dir.join(format!(".pgrep-{}.{}.tmp", name, std::process::id()));
+
+    let written = (|| -> Result<(), Error> {
+        let mut f: File = OpenOptions::new().write(true).create_new(true).open(&tmp)?;
+        f.write_all(data)?;
+        f.set_permissions(md.permissions())?;
+        // 只有 root 才能把文件交给其他用户，普通用户失败时保留自己作为所有者
+        // SAFETY: fd 在 f 的生命周期内有效
+        unsafe {
+            libc::fchown(f.as_raw_fd(), md.uid(), md.gid());
+        }
+        f.sync_all()?;
+        std::fs::rename(&tmp, p)?;
+        Ok(())
+    })();
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    written
+}
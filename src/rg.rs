@@ -0,0 +1,361 @@
+// 翻译成 ripgrep 命令
+//
+// --print-rg-command 把这次的参数翻译成效果相同的 `rg` 命令行后退出，不执行搜索，
+// 方便迁移到 ripgrep，也可以用来对照两者的选项。
+//
+// 翻译规则：
+// * pgrep 总是输出 `路径:行号:内容`，对应 `--with-filename --line-number --no-heading`
+// * pgrep 不读取 .gitignore、会搜索隐藏文件并且跟随符号链接，对应 `--no-ignore --hidden --follow`
+// * 所有模式（包括内置模式展开后的正则表达式）都用 `-e` 给出，不会被当成选项
+// * 只影响诊断输出或者性能的选项（`-v`、`--io-retry` 等）在 rg 中没有对应时给出警告，翻译照常进行
+// * 改变匹配结果或者执行额外操作的选项（`--fuzzy`、`--rules`、`--exec` 等）无法翻译，返回错误
+//
+// 相关文档:
+// * ripgrep 的选项: <https://github.com/BurntSushi/ripgrep/blob/master/FAQ.md>
+// * rg --help: <https://github.com/BurntSushi/ripgrep/blob/master/GUIDE.md>
+
+use failure::Fail;
+
+use crate::builtin::{self, BuiltinPattern};
+use crate::output::LineEnding;
+use crate::suppress;
+use crate::{Args, CountMode};
+use pgrep::TypeFilter;
+use pgrep::filetype::TypeDefs;
+use pgrep::newline::Newline;
+
+/// 选项在 ripgrep 中没有等价的写法
+#[derive(Debug, Fail)]
+#[fail(display = "{} 在 ripgrep 中没有对应的选项，无法生成等价的命令", flag)]
+pub struct UnsupportedFlag {
+    flag: &'static str,
+}
+
+/// 翻译的结果
+///
+/// # 字段
+/// * `command` - 可以直接在 shell 中运行的 rg 命令
+/// * `warnings` - 被忽略的选项，它们不影响匹配到哪些行
+pub struct Translation {
+    pub command: String,
+    pub warnings: Vec<String>,
+}
+
+/// 把参数翻译成 rg 命令行
+///
+/// # 参数
+/// * `args` - 解析后的命令行参数
+/// * `patterns` - 用户给出的模式，包括 `--match-empty-lines` 添加的 `^$`
+/// * `builtins` - 使用的内置模式
+/// * `paths` - 要搜索的路径
+pub fn translate(
+    args: &Args,
+    patterns: &[String],
+    builtins: &[BuiltinPattern],
+    paths: &[String],
+    types: &TypeDefs,
+) -> Result<Translation, UnsupportedFlag> {
+    let unsupported = |set: bool, flag: &'static str| if set { Err(UnsupportedFlag { flag }) } else { Ok(()) };
+    unsupported(args.fuzzy.is_some(), "--fuzzy")?;
+    unsupported(args.sound_like, "--sound-like")?;
+    unsupported(args.rules.is_some(), "--rules")?;
+    unsupported(!args.line_prefix.is_empty(), "--line-prefix")?;
+    unsupported(!args.skip_prefix.is_empty(), "--skip-prefix")?;
+    unsupported(args.archives, "--archives")?;
+    unsupported(args.type_filter == TypeFilter::Symlink, "--type-filter symlink")?;
+    unsupported(args.patch, "--patch")?;
+    unsupported(args.write_replace.is_some(), "--write-replace")?;
+    unsupported(args.interactive, "--interactive")?;
+    unsupported(args.confirm, "--confirm")?;
+    unsupported(args.group_by.is_some(), "--group-by")?;
+    unsupported(args.show_pattern, "--show-pattern")?;
+    unsupported(args.count_exit, "--count-exit")?;
+    unsupported(args.top.is_some(), "--top")?;
+    unsupported(args.output_sorted_unique_texts, "--output-sorted-unique-texts")?;
+    unsupported(args.context_pattern.is_some(), "--context-pattern")?;
+    unsupported(args.field_separator.is_some(), "--field-separator")?;
+    unsupported(args.error_if_matches.is_some(), "--error-if-matches")?;
+    unsupported(args.error_if_no_matches, "--error-if-no-matches")?;
+    unsupported(args.replace_verify.is_some(), "--replace-verify")?;
+    unsupported(args.exec.is_some(), "--exec")?;
+    unsupported(args.exec_file.is_some(), "--exec-file")?;
+    unsupported(args.exec_batch.is_some(), "--exec-batch")?;
+    unsupported(args.checkpoint.is_some(), "--checkpoint")?;
+    unsupported(args.watch, "--watch")?;
+    unsupported(args.follow_lines, "--follow-lines")?;
+    unsupported(args.tui, "--tui")?;
+    unsupported(args.serve, "--serve")?;
+    unsupported(args.match_all, "--match-all")?;
+    unsupported(args.git_tracked, "--git-tracked")?;
+    unsupported(args.staged, "--staged")?;
+    unsupported(args.changed.is_some(), "--changed")?;
+    unsupported(args.git_untracked, "--git-untracked")?;
+    unsupported(args.git_relative, "--git-relative")?;
+    unsupported(args.hex_dump, "--hex-dump")?;
+    unsupported(args.baseline.is_some(), "--baseline")?;
+    unsupported(args.save_baseline.is_some(), "--save-baseline")?;
+    unsupported(args.record_format.is_some(), "--record-format")?;
+    unsupported(args.suppressions.is_some(), "--suppressions")?;
+    unsupported(args.format == Some(crate::OutputFormat::Sqlite), "--format sqlite")?;
+    unsupported(args.format == Some(crate::OutputFormat::Json), "--format json")?;
+    unsupported(args.format == Some(crate::OutputFormat::Sarif), "--format sarif")?;
+    unsupported(args.format == Some(crate::OutputFormat::Github), "--format github")?;
+    unsupported(args.json_array, "--json-array")?;
+    unsupported(args.skip_matches > 0, "--skip-matches")?;
+    unsupported(args.truncate_matches.is_some(), "--truncate-matches")?;
+    unsupported(args.wrap_matches, "--wrap-matches")?;
+    unsupported(args.overlapping, "--overlapping")?;
+    unsupported(args.dedup_by.is_some(), "--dedup-by")?;
+
+    let mut argv: Vec<String> = ["rg", "--with-filename", "--line-number", "--no-heading", "--no-ignore", "--hidden"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let mut warnings = Vec::new();
+    let mut push = |a: &str, v: Option<&str>| {
+        argv.push(a.to_string());
+        argv.extend(v.map(str::to_string));
+    };
+
+    // --type-filter regular 不跟随符号链接，这和 rg 的默认行为相同
+    if args.type_filter == TypeFilter::All {
+        push("--follow", None);
+    }
+    // rg 的类型只和文件名比较，这里把类型展开成 --glob；后出现的 --glob 优先，所以排除的放在后面
+    for (names, negate) in [(&args.file_type, ""), (&args.type_not, "!")] {
+        for name in names {
+            for g in types.globs(name).unwrap_or_default() {
+                push("--glob", Some(&format!("{}{}", negate, g)));
+            }
+        }
+    }
+    // rg 的 --max-columns 同样按字节计算，省略的行写成 `[Omitted long line ...]`
+    if let Some(n) = args.max_columns {
+        push("--max-columns", Some(&n.to_string()));
+    }
+    let fixed = args.fixed_strings || args.literal_match_only;
+    if fixed {
+        push("--fixed-strings", None);
+    }
+    if let Some(wl) = &args.word_list {
+        if !fixed {
+            push("--fixed-strings", None);
+        }
+        push("--file", Some(&wl.to_string_lossy()));
+        if args.word_list_case_insensitive {
+            push("--ignore-case", None);
+        }
+        if args.word_list_whole_word {
+            push("--word-regexp", None);
+        }
+    }
+    for p in patterns {
+        push("-e", Some(p));
+    }
+    // rg 的空模式匹配每一行
+    if args.all_lines {
+        push("-e", Some(""));
+    }
+    for b in builtins {
+        push("-e", Some(builtin::resolve_builtin(*b)));
+    }
+    // --insert-before / --insert-after 写成 rg 的 `前${0}后`
+    let template = match (&args.replace, &args.insert_before, &args.insert_after) {
+        (None, None, None) => None,
+        (t, before, after) => Some(format!(
+            "{}{}{}",
+            before.as_deref().unwrap_or_default(),
+            t.as_deref().unwrap_or("${0}"),
+            after.as_deref().unwrap_or_default()
+        )),
+    };
+    if let Some(t) = &template {
+        // rg 的替换模板没有大小写转换
+        if ["\\u", "\\l", "\\U", "\\L", "\\E"].iter().any(|op| t.contains(op)) {
+            return Err(UnsupportedFlag {
+                flag: "--replace 中的大小写转换",
+            });
+        }
+        push("--replace", Some(t));
+    }
+    if args.filter {
+        // 从标准输入读取，输出所有的行，没有文件名和行号；rg 中后出现的选项覆盖前面的
+        push("--passthru", None);
+        push("--no-filename", None);
+        push("--no-line-number", None);
+    }
+    // pgrep 也为没有匹配的文件输出 0，对应 rg 的 --include-zero
+    let unique = (args.count_unique_matches || args.frequency_analysis).then_some(CountMode::Unique);
+    match args.count_mode.or(args.count.then_some(CountMode::Lines)).or(unique) {
+        Some(CountMode::Lines) => {
+            push("--count", None);
+            push("--include-zero", None);
+        }
+        Some(CountMode::Matches) => {
+            push("--count-matches", None);
+            push("--include-zero", None);
+        }
+        Some(CountMode::Bytes) => return Err(UnsupportedFlag { flag: "--count-mode bytes" }),
+        Some(CountMode::Files) => return Err(UnsupportedFlag { flag: "--count-mode files" }),
+        Some(CountMode::Unique) => return Err(UnsupportedFlag { flag: "--count-mode unique" }),
+        None => {}
+    }
+    if args.only_matching {
+        push("--only-matching", None);
+    }
+    if args.search_zip {
+        push("--search-zip", None);
+    }
+    if args.crlf_is_lf {
+        // rg 的 --crlf 只处理 `\r\n`，单独的 `\r` 不算行尾
+        push("--crlf", None);
+        warnings.push("--crlf-is-lf 翻译为 --crlf，单独的 \\r 不会被当作行尾".to_string());
+    }
+    // rg 同样按 NUL 字节识别二进制文件，但检查的范围不能调整
+    if args.binary_scan_bytes == 0 {
+        push("--text", None);
+    }
+    match args.newline {
+        // rg 默认只认 `\n`，行尾的 `\r` 是行内容的一部分
+        Newline::Auto | Newline::Lf => {}
+        Newline::Crlf => push("--crlf", None),
+        Newline::Cr => return Err(UnsupportedFlag { flag: "--newline cr" }),
+        Newline::Any => {
+            push("--crlf", None);
+            warnings.push("--newline any 翻译为 --crlf，单独的 \\r 不会被当作行尾".to_string());
+        }
+    }
+    match args.encoding_chain.as_slice() {
+        [] => {}
+        [enc] => push("--encoding", Some(&enc.to_string())),
+        [enc, ..] => {
+            push("--encoding", Some(&enc.to_string()));
+            warnings.push(format!("rg 只能指定一种编码，--encoding-chain 只保留了第一个 {}", enc));
+        }
+    }
+    if let Some(pre) = &args.pre {
+        push("--pre", Some(&pre.to_string_lossy()));
+        for g in &args.pre_glob {
+            push("--pre-glob", Some(g));
+        }
+    }
+    if args.print0 {
+        warnings.push("rg 只能在文件名之后输出 NUL，--print0 被忽略".to_string());
+    }
+
+    // 不影响匹配结果的选项
+    let ignored = [
+        (args.skip_empty_lines, "--skip-empty-lines"),
+        (args.squeeze_blank, "--squeeze-blank"),
+        (args.o_inline, "--o-inline"),
+        (args.debug_skip.is_some(), "--debug-skip"),
+        (args.verbose > 0, "-v"),
+        (args.profile_regex, "--profile-regex"),
+        (args.profile_per_line, "--profile-per-line"),
+        (args.debug_timing, "--debug-timing"),
+        (args.match_buffer_size.is_some(), "--match-buffer-size"),
+        (args.max_line_bytes != 64 << 20, "--max-line-bytes"),
+        (args.warn_mixed_endings, "--warn-mixed-endings"),
+        (args.binary_scan_bytes != 0 && args.binary_scan_bytes != 8192, "--binary-scan-bytes"),
+        (args.null_ratio.is_some(), "--null-ratio"),
+        (args.match_highlight_style.is_some(), "--match-highlight-style"),
+        (args.no_bold, "--no-bold"),
+        (args.align, "--align"),
+        (
+            !args.no_ignore_marker && args.ignore_marker != suppress::DEFAULT_MARKER,
+            "--ignore-marker",
+        ),
+        (args.show_suppressed, "--show-suppressed"),
+        (args.emit_separators_to_stderr, "--emit-separators-to-stderr"),
+        (args.emit_headings_to_stderr, "--emit-headings-to-stderr"),
+        (
+            args.line_ending_output.is_some_and(|e| e != LineEnding::native()),
+            "--line-ending-output",
+        ),
+        (args.io_retry != 3, "--io-retry"),
+        (args.max_symlink_depth != 40, "--max-symlink-depth"),
+        (args.pre.is_some() && args.pre_timeout != 60.0, "--pre-timeout"),
+    ];
+    for (_, flag) in ignored.iter().filter(|(set, _)| *set) {
+        warnings.push(format!("{} 在 rg 中没有对应的选项，已忽略", flag));
+    }
+
+    argv.push("--".to_string());
+    argv.extend(paths.iter().cloned());
+    Ok(Translation {
+        command: argv.iter().map(|a| quote(a)).collect::<Vec<_>>().join(" "),
+        warnings,
+    })
+}
+
+/// 需要时用单引号包围参数，内容中的单引号按 POSIX shell 的方式转义
+pub fn quote(s: &str) -> String {
+    let plain = !s.is_empty()
+        && s
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// 用 `pgrep ARGV -f src` 的参数翻译，模式取自 `-p`
+    fn rg(argv: &[&str]) -> Result<Translation, UnsupportedFlag> {
+        let args = Args::try_parse_from([&["pgrep"], argv, &["-f", "src"]].concat()).unwrap();
+        let patterns = args.pattern.clone();
+        translate(&args, &patterns, &[], &["src".to_string()], &TypeDefs::default())
+    }
+
+    const PREFIX: &str = "rg --with-filename --line-number --no-heading --no-ignore --hidden --follow";
+
+    #[test]
+    fn patterns_and_fixed_strings() {
+        let t = rg(&["-p", "a.b"]).unwrap();
+        assert_eq!(t.command, format!("{} -e a.b -- src", PREFIX));
+        assert!(t.warnings.is_empty());
+
+        let t = rg(&["-F", "-p", "a.b", "-p", "it's"]).unwrap();
+        assert_eq!(t.command, format!("{} --fixed-strings -e a.b -e 'it'\\''s' -- src", PREFIX));
+    }
+
+    #[test]
+    fn types_become_globs_with_negations_last() {
+        let t = rg(&["--type-not", "md", "--type", "rust", "-p", "x"]).unwrap();
+        assert_eq!(
+            t.command,
+            format!("{} --glob '*.rs' --glob '!*.md' --glob '!*.markdown' -e x -- src", PREFIX)
+        );
+    }
+
+    #[test]
+    fn only_matching_and_crlf() {
+        let t = rg(&["-o", "-p", "x"]).unwrap();
+        assert_eq!(t.command, format!("{} -e x --only-matching -- src", PREFIX));
+
+        let t = rg(&["--crlf-is-lf", "-p", "x$"]).unwrap();
+        assert_eq!(t.command, format!("{} -e 'x$' --crlf -- src", PREFIX));
+        assert_eq!(t.warnings, ["--crlf-is-lf 翻译为 --crlf，单独的 \\r 不会被当作行尾"]);
+    }
+
+    #[test]
+    fn unsupported_flag_is_an_error() {
+        let err = rg(&["--fuzzy", "1", "-p", "x"]).err().unwrap();
+        assert_eq!(err.to_string(), "--fuzzy 在 ripgrep 中没有对应的选项，无法生成等价的命令");
+        let err = rg(&["--count-mode", "bytes", "-p", "x"]).err().unwrap();
+        assert_eq!(err.flag, "--count-mode bytes");
+    }
+
+    #[test]
+    fn diagnostic_flags_only_warn() {
+        let t = rg(&["-v", "--no-bold", "-p", "x"]).unwrap();
+        assert_eq!(t.command, format!("{} -e x -- src", PREFIX));
+        assert_eq!(t.warnings, ["-v 在 rg 中没有对应的选项，已忽略", "--no-bold 在 rg 中没有对应的选项，已忽略"]);
+    }
+}
@@ -0,0 +1,112 @@
+// --format sarif 的输出
+//
+// SARIF 是代码扫描工具通用的结果格式，GitHub 的 code scanning、很多 IDE 插件和 CI 平台都能直接读取。
+// 整个输出是一个 JSON 文档，结果要等搜索结束之后才能写出：
+// * `runs[0].tool.driver.rules` 是 `--rules` 中的每条规则：id、说明（message，没有时是模式）和默认级别
+// * `runs[0].results` 每个匹配一项：规则的 id 和下标、级别、说明，以及文件、行号、列号和匹配的行
+// * 一行匹配了几条规则就有几项，没有使用 `--rules` 时每个匹配一项，没有规则的信息，级别是 warning
+//
+// 严重程度对应的 SARIF 级别：error → error，warning → warning，info → note。
+// 路径写成相对的 URI：去掉开头的 `./`，非 ASCII 字符、空格和 `%` 等按 UTF-8 字节转义。
+//
+// 相关文档:
+// * SARIF 2.1.0: <https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html>
+// * GitHub 的 SARIF 支持: <https://docs.github.com/en/code-security/code-scanning/integrating-with-code-scanning/sarif-support-for-code-scanning>
+
+use crate::config::{RuleDef, Severity};
+use crate::json::Json;
+
+/// 输出中声明的 SARIF 版本和对应的 schema
+const VERSION: &str = "2.1.0";
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// 严重程度对应的 SARIF 级别
+fn level(s: Severity) -> &'static str {
+    match s {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "note",
+    }
+}
+
+/// 对象的简写
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+fn text(s: &str) -> Json {
+    object(vec![("text", Json::String(s.to_string()))])
+}
+
+/// 一个匹配对应的 result
+///
+/// # 参数
+/// * `path` - 输出中的路径
+/// * `line`、`column` - 从 1 开始的行号和列号
+/// * `tx` - 匹配的行，也是没有规则或者规则没有 message 时的说明
+/// * `rule` - 匹配的规则和它在规则文件中的下标
+pub fn result(path: &str, line: usize, column: usize, tx: &str, rule: Option<(usize, &RuleDef)>) -> Json {
+    let location = object(vec![(
+        "physicalLocation",
+        object(vec![
+            ("artifactLocation", object(vec![("uri", Json::String(uri(path)))])),
+            (
+                "region",
+                object(vec![
+                    ("startLine", Json::Number(line as f64)),
+                    ("startColumn", Json::Number(column as f64)),
+                    ("snippet", text(tx)),
+                ]),
+            ),
+        ]),
+    )]);
+    let mut fields = Vec::new();
+    if let Some((i, r)) = rule {
+        fields.push(("ruleId", Json::String(r.name.clone())));
+        fields.push(("ruleIndex", Json::Number(i as f64)));
+    }
+    let severity = rule.map_or(Severity::Warning, |(_, r)| r.severity);
+    fields.push(("level", Json::String(level(severity).to_string())));
+    fields.push(("message", text(rule.map_or(tx, |(_, r)| r.message_or(tx)))));
+    fields.push(("locations", Json::Array(vec![location])));
+    object(fields)
+}
+
+/// 整个 SARIF 文档，`rules` 是 `--rules` 中的规则，没有使用时为空
+pub fn document(rules: &[RuleDef], results: Vec<Json>) -> String {
+    let rules = rules
+        .iter()
+        .map(|r| {
+            object(vec![
+                ("id", Json::String(r.name.clone())),
+                ("shortDescription", text(r.message.as_deref().unwrap_or(&r.pattern))),
+                ("defaultConfiguration", object(vec![("level", Json::String(level(r.severity).to_string()))])),
+            ])
+        })
+        .collect();
+    let driver = object(vec![
+        ("name", Json::String("pgrep".to_string())),
+        ("version", Json::String(env!("CARGO_PKG_VERSION").to_string())),
+        ("rules", Json::Array(rules)),
+    ]);
+    let run = object(vec![("tool", object(vec![("driver", driver)])), ("results", Json::Array(results))]);
+    object(vec![
+        ("$schema", Json::String(SCHEMA.to_string())),
+        ("version", Json::String(VERSION.to_string())),
+        ("runs", Json::Array(vec![run])),
+    ])
+    .render()
+}
+
+/// 把输出中的路径写成相对的 URI
+fn uri(path: &str) -> String {
+    let path = path.strip_prefix("./").unwrap_or(path);
+    let mut s = String::with_capacity(path.len());
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => s.push(b as char),
+            b => s.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    s
+}
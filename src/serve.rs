@@ -0,0 +1,541 @@
+// 常驻的搜索服务
+//
+// --serve 从标准输入逐行读取 JSON-RPC 2.0 请求，把响应和通知写到标准输出，每条消息占一行。
+// 编辑器插件可以一直保留这个进程，不必每次搜索都启动一个新的进程：
+// 文件类型（包括 --type-add 定义的类型）只解析一次，编译好的正则表达式按模式缓存，
+// 同一个模式的后续请求不再重新编译。
+//
+// 请求：
+//
+//     {"jsonrpc":"2.0","id":1,"method":"search","params":{"pattern":"TODO","paths":["src"],"options":{"ignore_case":true}}}
+//     {"jsonrpc":"2.0","id":2,"method":"cancel","params":{"id":1}}
+//     {"jsonrpc":"2.0","id":3,"method":"shutdown"}
+//
+// search 在单独的线程中进行，多个搜索可以同时进行。搜索的过程中发送 `matches` 通知，每个通知是一批匹配，
+// 无法读取的文件发送 `error` 通知，搜索结束时才发送这个请求的响应，其中是汇总：
+//
+//     {"jsonrpc":"2.0","method":"matches","params":{"id":1,"matches":[{"path":"src/a.rs","line":3,"column":8,"text":"    // TODO"}]}}
+//     {"jsonrpc":"2.0","method":"error","params":{"id":1,"message":"..."}}
+//     {"jsonrpc":"2.0","id":1,"result":{"matches":12,"files":3,"errors":0,"cancelled":false,"truncated":false}}
+//
+// * `paths` 可以省略，默认搜索当前目录；行号和列号从 1 开始，列号按字符计算，和命令行的输出一致
+// * `options` 可以省略，其中可以有：
+//   - `ignore_case`: 忽略大小写
+//   - `fixed_strings`: 模式按字面文本匹配
+//   - `types` / `types_not`: 类型名的数组，和 --type / --type-not 相同
+//   - `max_count`: 最多报告这么多条匹配，达到之后停止搜索，汇总中的 `truncated` 为 true
+//   - `batch_size`: 每个 matches 通知最多有多少条匹配，默认是 BATCH_SIZE；
+//     不满一批的匹配最多等待 FLUSH_MS 毫秒就会发送，匹配较少时也能及时看到结果
+//
+// cancel 让对应的搜索尽快停下：正在搜索的文件处理完之后就不再搜索其他文件。
+// 被取消的搜索照常发送汇总，其中的 `cancelled` 为 true。cancel 自己的响应是 `{"cancelled":true}`，
+// 搜索已经结束或者不存在时是 false。shutdown 取消所有的搜索，等它们的汇总都发送之后响应 null 并退出；
+// 标准输入结束时等待进行中的搜索完成后退出。
+//
+// 有问题的请求得到 JSON-RPC 的错误响应，进程继续运行：
+// * -32700: 不是合法的 JSON，或者不是 UTF-8
+// * -32600: 不是 JSON-RPC 2.0 请求；search 没有 id，或者 id 和进行中的搜索重复
+// * -32601: 未知的方法
+// * -32602: 参数错误，包括未知的选项、模式无法编译和未知的文件类型
+// 没有 id 的请求是通知，不会得到响应；search 需要用 id 对应通知和汇总，所以不能作为通知发送。
+//
+// 所有的消息都经过一个通道交给同一个写线程，不同搜索的消息不会交织在同一行中。
+//
+// 相关文档:
+// * JSON-RPC 2.0: <https://www.jsonrpc.org/specification>
+// * std::sync::mpsc: <https://doc.rust-lang.org/std/sync/mpsc/>
+
+use failure::Error;
+use regex::Regex;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::json::{Json, Parser};
+use pgrep::filetype::TypeDefs;
+use pgrep::{GrepConfig, Halt, Record, WalkContext, process_path, record_match};
+
+/// 每个 matches 通知默认最多有多少条匹配
+pub const BATCH_SIZE: usize = 100;
+
+/// 不满一批的匹配最多等待多久（毫秒）就发送
+const FLUSH_MS: u64 = 50;
+
+/// 最多缓存多少个编译好的正则表达式，超过时清空重新开始
+const REGEX_CACHE: usize = 64;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// 写成 JSON-RPC error 对象的错误
+#[derive(Debug)]
+struct RpcErr {
+    code: i64,
+    message: String,
+}
+
+impl RpcErr {
+    fn new(code: i64, message: impl Into<String>) -> RpcErr {
+        RpcErr {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn params(message: impl Into<String>) -> RpcErr {
+        RpcErr::new(INVALID_PARAMS, message)
+    }
+}
+
+/// 由字段组成 JSON 对象
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+/// 请求的响应
+fn response(id: &Json, result: Json) -> String {
+    object(vec![("jsonrpc", Json::String("2.0".to_string())), ("id", id.clone()), ("result", result)]).render()
+}
+
+/// 请求的错误响应，无法确定 id 时 `id` 是 null
+fn error_response(id: &Json, e: RpcErr) -> String {
+    let error = object(vec![
+        ("code", Json::Number(e.code as f64)),
+        ("message", Json::String(e.message)),
+    ]);
+    object(vec![("jsonrpc", Json::String("2.0".to_string())), ("id", id.clone()), ("error", error)]).render()
+}
+
+/// 不需要回复的通知
+fn notification(method: &str, params: Json) -> String {
+    object(vec![
+        ("jsonrpc", Json::String("2.0".to_string())),
+        ("method", Json::String(method.to_string())),
+        ("params", params),
+    ])
+    .render()
+}
+
+/// search 的参数
+#[derive(Debug)]
+struct SearchParams {
+    pattern: String,
+    paths: Vec<String>,
+    ignore_case: bool,
+    fixed_strings: bool,
+    types: Vec<String>,
+    types_not: Vec<String>,
+    max_count: Option<usize>,
+    batch_size: usize,
+}
+
+impl SearchParams {
+    fn parse(params: Option<&Json>) -> Result<SearchParams, RpcErr> {
+        let Some(Json::Object(fields)) = params else {
+            return Err(RpcErr::params("search 的 params 应该是对象"));
+        };
+        let mut p = SearchParams {
+            pattern: String::new(),
+            paths: vec![".".to_string()],
+            ignore_case: false,
+            fixed_strings: false,
+            types: Vec::new(),
+            types_not: Vec::new(),
+            max_count: None,
+            batch_size: BATCH_SIZE,
+        };
+        let mut pattern = None;
+        for (k, v) in fields {
+            match k.as_str() {
+                "pattern" => pattern = Some(string(k, v)?),
+                "paths" => p.paths = strings(k, v)?,
+                "options" => {
+                    let Json::Object(options) = v else {
+                        return Err(RpcErr::params("options 应该是对象"));
+                    };
+                    for (k, v) in options {
+                        match k.as_str() {
+                            "ignore_case" => p.ignore_case = boolean(k, v)?,
+                            "fixed_strings" => p.fixed_strings = boolean(k, v)?,
+                            "types" => p.types = strings(k, v)?,
+                            "types_not" => p.types_not = strings(k, v)?,
+                            "max_count" => p.max_count = Some(count(k, v)?),
+                            "batch_size" => p.batch_size = count(k, v)?.max(1),
+                            _ => return Err(RpcErr::params(format!("未知的选项 {:?}", k))),
+                        }
+                    }
+                }
+                _ => return Err(RpcErr::params(format!("未知的参数 {:?}", k))),
+            }
+        }
+        p.pattern = pattern.ok_or_else(|| RpcErr::params("缺少 pattern"))?;
+        if p.paths.is_empty() {
+            return Err(RpcErr::params("paths 不能是空数组"));
+        }
+        Ok(p)
+    }
+
+    /// 实际编译的正则表达式
+    fn regex_source(&self) -> String {
+        let re = if self.fixed_strings {
+            regex::escape(&self.pattern)
+        } else {
+            self.pattern.clone()
+        };
+        if self.ignore_case { format!("(?i){}", re) } else { re }
+    }
+}
+
+fn string(k: &str, v: &Json) -> Result<String, RpcErr> {
+    match v {
+        Json::String(s) => Ok(s.clone()),
+        _ => Err(RpcErr::params(format!("{} 应该是字符串", k))),
+    }
+}
+
+fn strings(k: &str, v: &Json) -> Result<Vec<String>, RpcErr> {
+    match v {
+        Json::Array(items) => items.iter().map(|v| string(k, v)).collect(),
+        _ => Err(RpcErr::params(format!("{} 应该是字符串数组", k))),
+    }
+}
+
+fn boolean(k: &str, v: &Json) -> Result<bool, RpcErr> {
+    match v {
+        Json::Bool(b) => Ok(*b),
+        _ => Err(RpcErr::params(format!("{} 应该是 true 或 false", k))),
+    }
+}
+
+fn count(k: &str, v: &Json) -> Result<usize, RpcErr> {
+    match v {
+        Json::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+        _ => Err(RpcErr::params(format!("{} 应该是非负整数", k))),
+    }
+}
+
+/// 进行中的搜索，键是 id 的 JSON 文本，值是取消标志
+type Running = Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>;
+
+/// 读取请求后要不要继续
+enum Next {
+    Continue,
+    Shutdown(Json),
+}
+
+/// 服务的状态，在所有请求之间共享
+struct Server {
+    tx: mpsc::Sender<String>,
+    types: TypeDefs,
+    max_symlink_depth: usize,
+    regexes: HashMap<String, Regex>,
+    running: Running,
+    workers: Vec<JoinHandle<()>>,
+}
+
+/// 运行服务，直到标准输入结束或者收到 shutdown
+///
+/// # 参数
+/// * `input` - 请求的来源，通常是标准输入
+/// * `output` - 响应和通知的去处，通常是标准输出
+/// * `types` - 内置的类型加上 `--type-add` 定义的类型
+/// * `max_symlink_depth` - 和 `--max-symlink-depth` 相同，所有请求共用
+pub fn run<R, W>(mut input: R, mut output: W, types: TypeDefs, max_symlink_depth: usize) -> Result<(), Error>
+where
+    R: BufRead,
+    W: Write + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel::<String>();
+    // 写线程在通道关闭或者输出出错（例如客户端已经退出）时结束
+    let writer = std::thread::spawn(move || -> std::io::Result<()> {
+        for msg in rx {
+            output.write_all(msg.as_bytes())?;
+            output.write_all(b"\n")?;
+            output.flush()?;
+        }
+        Ok(())
+    });
+
+    let mut server = Server {
+        tx,
+        types,
+        max_symlink_depth,
+        regexes: HashMap::new(),
+        running: Arc::default(),
+        workers: Vec::new(),
+    };
+    let mut buf = Vec::new();
+    let mut shutdown = None;
+    loop {
+        buf.clear();
+        if input.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        let next = match std::str::from_utf8(&buf) {
+            Ok(line) if line.trim().is_empty() => Next::Continue,
+            Ok(line) => server.handle(line),
+            Err(e) => {
+                server.send(error_response(&Json::Null, RpcErr::new(PARSE_ERROR, format!("请求不是 UTF-8: {}", e))));
+                Next::Continue
+            }
+        };
+        if let Next::Shutdown(id) = next {
+            shutdown = Some(id);
+            break;
+        }
+        server.workers.retain(|h| !h.is_finished());
+    }
+
+    for h in server.workers.drain(..) {
+        let _ = h.join();
+    }
+    if let Some(id) = shutdown {
+        server.send(response(&id, Json::Null));
+    }
+    drop(server);
+    match writer.join() {
+        Ok(Err(e)) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+impl Server {
+    fn send(&self, msg: String) {
+        let _ = self.tx.send(msg);
+    }
+
+    /// 处理一行请求
+    fn handle(&mut self, line: &str) -> Next {
+        let req = match Parser::new(line).document() {
+            Ok(req) => req,
+            Err(e) => {
+                self.send(error_response(&Json::Null, RpcErr::new(PARSE_ERROR, format!("无法解析请求: {}", e))));
+                return Next::Continue;
+            }
+        };
+        let id = req.get("id").cloned();
+        let invalid = |what: &str| RpcErr::new(INVALID_REQUEST, what);
+        let checked = match (&req, &id, req.get("jsonrpc"), req.get("method")) {
+            (Json::Object(_), _, _, _) if !matches!(id, None | Some(Json::Number(_) | Json::String(_))) => {
+                Err(invalid("id 应该是数字或字符串"))
+            }
+            (Json::Object(_), _, Some(Json::String(v)), Some(Json::String(m))) if v == "2.0" => Ok(m.clone()),
+            (Json::Object(_), _, _, _) => Err(invalid("请求应该有 \"jsonrpc\": \"2.0\" 和字符串 method")),
+            _ => Err(invalid("请求应该是 JSON 对象")),
+        };
+        let method = match checked {
+            Ok(m) => m,
+            Err(e) => {
+                let id = match &id {
+                    Some(id @ (Json::Number(_) | Json::String(_))) => id.clone(),
+                    _ => Json::Null,
+                };
+                self.send(error_response(&id, e));
+                return Next::Continue;
+            }
+        };
+
+        let params = req.get("params");
+        let result = match method.as_str() {
+            "search" => match &id {
+                Some(id) => self.search(id, params).map(|()| None),
+                None => Err(invalid("search 需要 id，不能作为通知发送")),
+            },
+            "cancel" => self.cancel(params).map(Some),
+            "shutdown" => {
+                for flag in self.running.lock().unwrap().values() {
+                    flag.store(true, Ordering::Relaxed);
+                }
+                return Next::Shutdown(id.unwrap_or(Json::Null));
+            }
+            m => Err(RpcErr::new(METHOD_NOT_FOUND, format!("未知的方法 {:?}", m))),
+        };
+        match (result, &id) {
+            // search 的响应是搜索结束时的汇总，由搜索线程发送
+            (Ok(None), _) => {}
+            (Ok(Some(r)), Some(id)) => self.send(response(id, r)),
+            (Err(e), Some(id)) => self.send(error_response(id, e)),
+            // 通知出错时也没有人等待回复；search 没有 id 时 id 为 null
+            (Err(e), None) if method == "search" => self.send(error_response(&Json::Null, e)),
+            (_, None) => {}
+        }
+        Next::Continue
+    }
+
+    /// 开始一个搜索
+    fn search(&mut self, id: &Json, params: Option<&Json>) -> Result<(), RpcErr> {
+        let key = id.render();
+        if self.running.lock().unwrap().contains_key(&key) {
+            return Err(RpcErr::new(INVALID_REQUEST, format!("id 为 {} 的搜索还没有结束", key)));
+        }
+        let p = SearchParams::parse(params)?;
+
+        let source = p.regex_source();
+        let re = match self.regexes.get(&source) {
+            Some(re) => re.clone(),
+            None => {
+                let re = Regex::new(&source).map_err(|e| RpcErr::params(format!("模式无法编译: {}", e)))?;
+                if self.regexes.len() >= REGEX_CACHE {
+                    self.regexes.clear();
+                }
+                self.regexes.insert(source, re.clone());
+                re
+            }
+        };
+        let mut cfg = GrepConfig {
+            max_symlink_depth: self.max_symlink_depth,
+            ..GrepConfig::default()
+        };
+        if !p.types.is_empty() || !p.types_not.is_empty() {
+            let select = self
+                .types
+                .select(&p.types, &p.types_not)
+                .map_err(|e| RpcErr::params(e.to_string()))?;
+            cfg.type_select = Some(select);
+        }
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.running.lock().unwrap().insert(key.clone(), cancel.clone());
+        let job = Job {
+            id: id.clone(),
+            params: p,
+            re,
+            cfg,
+            cancel,
+            tx: self.tx.clone(),
+        };
+        let running = self.running.clone();
+        self.workers.push(std::thread::spawn(move || {
+            let summary = job.run();
+            // 先从进行中的搜索里去掉，收到汇总的客户端马上就可以重新使用这个 id
+            running.lock().unwrap().remove(&key);
+            let _ = job.tx.send(response(&job.id, summary));
+        }));
+        Ok(())
+    }
+
+    /// 取消一个搜索，返回 `{"cancelled": 是否找到了进行中的搜索}`
+    fn cancel(&self, params: Option<&Json>) -> Result<Json, RpcErr> {
+        let Some(target) = params.and_then(|p| p.get("id")) else {
+            return Err(RpcErr::params("cancel 的 params 中应该有要取消的 id"));
+        };
+        let found = match self.running.lock().unwrap().get(&target.render()) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        };
+        Ok(object(vec![("cancelled", Json::Bool(found))]))
+    }
+}
+
+/// 在搜索线程中进行的一个搜索
+struct Job {
+    id: Json,
+    params: SearchParams,
+    re: Regex,
+    cfg: GrepConfig,
+    cancel: Arc<AtomicBool>,
+    tx: mpsc::Sender<String>,
+}
+
+impl Job {
+    /// 搜索所有路径，边搜索边发送 matches 通知，返回汇总
+    fn run(&self) -> Json {
+        let found = Cell::new(0usize);
+        let files = Cell::new(0usize);
+        let errors = Cell::new(0usize);
+        let truncated = Cell::new(false);
+        let batch: RefCell<Vec<Json>> = RefCell::new(Vec::new());
+        let last_flush = Cell::new(Instant::now());
+
+        // 发送失败说明写线程已经结束，没有必要继续搜索
+        let notify = |method: &str, fields: Vec<(&str, Json)>| -> Result<(), Error> {
+            let mut params = vec![("id", self.id.clone())];
+            params.extend(fields);
+            self.tx.send(notification(method, object(params))).map_err(|_| {
+                Error::from(Halt {
+                    reason: "输出已经关闭".to_string(),
+                })
+            })
+        };
+        let flush = || -> Result<(), Error> {
+            last_flush.set(Instant::now());
+            let matches = batch.take();
+            if matches.is_empty() {
+                return Ok(());
+            }
+            notify("matches", vec![("matches", Json::Array(matches))])
+        };
+        let halt = |reason: &str| -> Error {
+            Halt {
+                reason: reason.to_string(),
+            }
+            .into()
+        };
+
+        let ff = |p: &Path, v: Vec<Record>| -> Result<(), Error> {
+            if self.cancel.load(Ordering::Relaxed) {
+                return Err(halt("请求被取消"));
+            }
+            let path = p.to_string_lossy();
+            for (i, r) in v.iter().enumerate() {
+                if self.params.max_count.is_some_and(|n| found.get() >= n) {
+                    truncated.set(true);
+                    return Err(halt("达到 max_count"));
+                }
+                if i == 0 {
+                    files.set(files.get() + 1);
+                }
+                let (column, _) = record_match(r, &self.re);
+                batch.borrow_mut().push(object(vec![
+                    ("path", Json::String(path.to_string())),
+                    ("line", Json::Number((r.line + 1) as f64)),
+                    ("column", Json::Number(column as f64)),
+                    ("text", Json::String(r.tx.clone())),
+                ]));
+                found.set(found.get() + 1);
+                if batch.borrow().len() >= self.params.batch_size {
+                    flush()?;
+                }
+            }
+            if last_flush.get().elapsed() >= Duration::from_millis(FLUSH_MS) {
+                flush()?;
+            }
+            Ok(())
+        };
+        let ef = |e: Error| {
+            errors.set(errors.get() + 1);
+            let _ = notify("error", vec![("message", Json::String(e.to_string()))]);
+        };
+
+        for path in &self.params.paths {
+            match process_path(path, &self.re, &self.cfg, &WalkContext::default(), &ff, &|_: &Path| {}, &ef) {
+                Ok(()) => {}
+                Err(e) if e.downcast_ref::<Halt>().is_some() => break,
+                Err(e) => ef(failure::err_msg(format!("无法搜索 {}: {}", path, e))),
+            }
+            if self.cancel.load(Ordering::Relaxed) || truncated.get() {
+                break;
+            }
+        }
+        let _ = flush();
+
+        let n = |c: &Cell<usize>| Json::Number(c.get() as f64);
+        object(vec![
+            ("matches", n(&found)),
+            ("files", n(&files)),
+            ("errors", n(&errors)),
+            ("cancelled", Json::Bool(self.cancel.load(Ordering::Relaxed))),
+            ("truncated", Json::Bool(truncated.get())),
+        ])
+    }
+}
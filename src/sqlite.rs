@@ -0,0 +1,448 @@
+// SQLite 输出
+//
+// `--format sqlite --output results.db` 不在终端中输出结果，而是把结果写入 SQLite 数据库，
+// 大规模审计之后可以再用 SQL 查询。需要用 `cargo build --features sqlite` 构建，链接系统的 libsqlite3。
+//
+// 数据库的结构（第 1 版，记录在 `PRAGMA user_version` 中）：
+//
+//     runs(id INTEGER PRIMARY KEY, timestamp INTEGER, pattern TEXT, args TEXT)
+//     files(id INTEGER PRIMARY KEY, run_id INTEGER, path, path_is_blob INTEGER)
+//     matches(file_id INTEGER, line INTEGER, column INTEGER, text TEXT)
+//
+// * 每次运行在 runs 中加一行：开始的 Unix 时间（秒）、合并后的正则表达式和 shell 引用过的命令行参数
+// * files 中只有包含匹配的文件。路径不是合法的 UTF-8 时按原始字节存为 BLOB，path_is_blob 为 1
+// * matches 的 line 和 column 从 1 开始，column 按字符计算；text 是整行内容。
+//   只有解码成文本的内容才会被搜索，所以 text 总是 TEXT
+// * 每个文件的 files 和 matches 行在一个事务中写入，比每行一个事务快得多
+//
+// 数据库已经存在时默认报错，`--append` 时追加一次新的运行。以后结构有变化时 user_version 会增加，
+// 遇到比自己新的版本时拒绝写入，旧的版本由新的 pgrep 负责迁移。
+//
+// 这个构建没有包含 rusqlite，这里直接声明用到的几个 C 函数。
+//
+// 相关文档:
+// * C 接口: <https://www.sqlite.org/c3ref/intro.html>
+// * PRAGMA user_version: <https://www.sqlite.org/pragma.html#pragma_user_version>
+
+use failure::{Error, Fail};
+use std::ffi::{CStr, CString, c_char, c_int, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+
+/// 数据库结构的版本
+pub const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs(
+        id INTEGER PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        pattern TEXT NOT NULL,
+        args TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS files(
+        id INTEGER PRIMARY KEY,
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        path NOT NULL,
+        path_is_blob INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS matches(
+        file_id INTEGER NOT NULL REFERENCES files(id),
+        line INTEGER NOT NULL,
+        column INTEGER NOT NULL,
+        text TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS matches_file_id ON matches(file_id);
+";
+
+#[allow(non_camel_case_types)]
+mod ffi {
+    use std::ffi::{c_char, c_int, c_void};
+
+    pub enum sqlite3 {}
+    pub enum sqlite3_stmt {}
+
+    pub const SQLITE_OK: c_int = 0;
+    pub const SQLITE_ROW: c_int = 100;
+    pub const SQLITE_DONE: c_int = 101;
+    pub const SQLITE_OPEN_READWRITE: c_int = 0x2;
+    pub const SQLITE_OPEN_CREATE: c_int = 0x4;
+
+    #[link(name = "sqlite3")]
+    unsafe extern "C" {
+        pub fn sqlite3_open_v2(name: *const c_char, db: *mut *mut sqlite3, flags: c_int, vfs: *const c_char) -> c_int;
+        pub fn sqlite3_close_v2(db: *mut sqlite3) -> c_int;
+        pub fn sqlite3_errmsg(db: *mut sqlite3) -> *const c_char;
+        pub fn sqlite3_exec(
+            db: *mut sqlite3,
+            sql: *const c_char,
+            cb: *const c_void,
+            arg: *mut c_void,
+            err: *mut *mut c_char,
+        ) -> c_int;
+        pub fn sqlite3_prepare_v2(
+            db: *mut sqlite3,
+            sql: *const c_char,
+            len: c_int,
+            stmt: *mut *mut sqlite3_stmt,
+            tail: *mut *const c_char,
+        ) -> c_int;
+        pub fn sqlite3_bind_int64(stmt: *mut sqlite3_stmt, i: c_int, v: i64) -> c_int;
+        pub fn sqlite3_bind_text(
+            stmt: *mut sqlite3_stmt,
+            i: c_int,
+            v: *const c_char,
+            len: c_int,
+            free: isize,
+        ) -> c_int;
+        pub fn sqlite3_bind_blob(
+            stmt: *mut sqlite3_stmt,
+            i: c_int,
+            v: *const c_void,
+            len: c_int,
+            free: isize,
+        ) -> c_int;
+        pub fn sqlite3_step(stmt: *mut sqlite3_stmt) -> c_int;
+        pub fn sqlite3_column_int64(stmt: *mut sqlite3_stmt, i: c_int) -> i64;
+        pub fn sqlite3_reset(stmt: *mut sqlite3_stmt) -> c_int;
+        pub fn sqlite3_finalize(stmt: *mut sqlite3_stmt) -> c_int;
+        pub fn sqlite3_last_insert_rowid(db: *mut sqlite3) -> i64;
+    }
+
+    /// SQLITE_TRANSIENT: 让 SQLite 在绑定时复制一份数据
+    pub const SQLITE_TRANSIENT: isize = -1;
+}
+
+/// SQLite 返回的错误
+#[derive(Debug, Fail)]
+#[fail(display = "无法写入 SQLite 数据库 {}: {}", path, msg)]
+pub struct SqliteErr {
+    path: String,
+    msg: String,
+}
+
+/// 没有 `--append` 时数据库已经存在
+#[derive(Debug, Fail)]
+#[fail(display = "数据库 {} 已经存在，用 --append 追加到其中", _0)]
+pub struct DbExists(String);
+
+/// 数据库的结构比这个版本的 pgrep 新
+#[derive(Debug, Fail)]
+#[fail(display = "数据库 {} 的结构是第 {} 版，这个版本的 pgrep 只支持到第 {} 版", path, found, supported)]
+pub struct NewerSchema {
+    path: String,
+    found: i64,
+    supported: i64,
+}
+
+/// 准备好的语句，drop 时释放
+struct Stmt(*mut ffi::sqlite3_stmt);
+
+impl Drop for Stmt {
+    fn drop(&mut self) {
+        // SAFETY: 语句由 sqlite3_prepare_v2 创建，只在这里释放一次
+        unsafe { ffi::sqlite3_finalize(self.0) };
+    }
+}
+
+/// 写入一次运行结果的数据库连接
+pub struct Sink {
+    db: *mut ffi::sqlite3,
+    path: String,
+    run_id: i64,
+    insert_file: Stmt,
+    insert_match: Stmt,
+}
+
+impl Sink {
+    /// 打开（`append` 为 false 时新建）数据库，建好表并记录这次运行
+    ///
+    /// # 参数
+    /// * `p` - 数据库文件
+    /// * `append` - 是否允许写入已经存在的数据库
+    /// * `pattern` - 这次运行的正则表达式
+    /// * `args` - 这次运行的命令行参数
+    pub fn open(p: &Path, append: bool, pattern: &str, args: &str) -> Result<Sink, Error> {
+        if !append && p.exists() {
+            return Err(DbExists(p.display().to_string()).into());
+        }
+        let path = p.display().to_string();
+        let name = CString::new(p.as_os_str().as_bytes())?;
+        let mut db = ptr::null_mut();
+        // SAFETY: name 是以 NUL 结尾的字符串，db 由 SQLite 写入
+        let rc = unsafe {
+            ffi::sqlite3_open_v2(
+                name.as_ptr(),
+                &mut db,
+                ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE,
+                ptr::null(),
+            )
+        };
+        if db.is_null() {
+            return Err(SqliteErr {
+                path,
+                msg: "内存不足".to_string(),
+            }
+            .into());
+        }
+        // 出错时 db 也需要关闭，先放进 Sink 里再检查，之后的错误由 drop 负责关闭
+        let mut sink = Sink {
+            db,
+            path,
+            run_id: 0,
+            insert_file: Stmt(ptr::null_mut()),
+            insert_match: Stmt(ptr::null_mut()),
+        };
+        sink.check(rc)?;
+
+        let found = sink.query_i64("PRAGMA user_version")?;
+        if found > SCHEMA_VERSION {
+            return Err(NewerSchema {
+                path: sink.path.clone(),
+                found,
+                supported: SCHEMA_VERSION,
+            }
+            .into());
+        }
+        sink.exec("BEGIN")?;
+        sink.exec(SCHEMA)?;
+        sink.exec(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))?;
+        let run = sink.prepare("INSERT INTO runs(timestamp, pattern, args) VALUES (?1, ?2, ?3)")?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        sink.bind_i64(&run, 1, now)?;
+        sink.bind_text(&run, 2, pattern)?;
+        sink.bind_text(&run, 3, args)?;
+        sink.step(&run)?;
+        drop(run);
+        sink.exec("COMMIT")?;
+        // SAFETY: db 是打开的连接
+        sink.run_id = unsafe { ffi::sqlite3_last_insert_rowid(sink.db) };
+
+        sink.insert_file = sink.prepare("INSERT INTO files(run_id, path, path_is_blob) VALUES (?1, ?2, ?3)")?;
+        sink.insert_match = sink.prepare("INSERT INTO matches(file_id, line, column, text) VALUES (?1, ?2, ?3, ?4)")?;
+        Ok(sink)
+    }
+
+    /// 在一个事务中写入一个文件和它的所有匹配
+    ///
+    /// # 参数
+    /// * `p` - 文件路径
+    /// * `rows` - 每个匹配的行号、列号（都从 1 开始）和行内容
+    pub fn write_file(&self, p: &Path, rows: &[(usize, usize, &str)]) -> Result<(), Error> {
+        self.exec("BEGIN")?;
+        let res = self.insert(p, rows);
+        // 失败时回滚，不留下只写了一半的文件
+        self.exec(if res.is_ok() { "COMMIT" } else { "ROLLBACK" })?;
+        res
+    }
+
+    fn insert(&self, p: &Path, rows: &[(usize, usize, &str)]) -> Result<(), Error> {
+        let f = &self.insert_file;
+        self.bind_i64(f, 1, self.run_id)?;
+        match p.to_str() {
+            Some(s) => {
+                self.bind_text(f, 2, s)?;
+                self.bind_i64(f, 3, 0)?;
+            }
+            None => {
+                self.bind_blob(f, 2, p.as_os_str().as_bytes())?;
+                self.bind_i64(f, 3, 1)?;
+            }
+        }
+        self.step(f)?;
+        // SAFETY: db 是打开的连接
+        let file_id = unsafe { ffi::sqlite3_last_insert_rowid(self.db) };
+
+        let m = &self.insert_match;
+        for (line, column, text) in rows {
+            self.bind_i64(m, 1, file_id)?;
+            self.bind_i64(m, 2, *line as i64)?;
+            self.bind_i64(m, 3, *column as i64)?;
+            self.bind_text(m, 4, text)?;
+            self.step(m)?;
+        }
+        Ok(())
+    }
+
+    /// 把非 SQLITE_OK 的返回值转换成带有错误信息的错误
+    fn check(&self, rc: c_int) -> Result<(), SqliteErr> {
+        if rc == ffi::SQLITE_OK {
+            return Ok(());
+        }
+        // SAFETY: sqlite3_errmsg 返回的字符串在下一次调用之前有效，这里立即复制
+        let msg = unsafe { CStr::from_ptr(ffi::sqlite3_errmsg(self.db)) };
+        Err(SqliteErr {
+            path: self.path.clone(),
+            msg: msg.to_string_lossy().into_owned(),
+        })
+    }
+
+    fn exec(&self, sql: &str) -> Result<(), Error> {
+        let sql = CString::new(sql)?;
+        // SAFETY: db 是打开的连接，sql 以 NUL 结尾，没有回调函数
+        let rc = unsafe { ffi::sqlite3_exec(self.db, sql.as_ptr(), ptr::null(), ptr::null_mut(), ptr::null_mut()) };
+        Ok(self.check(rc)?)
+    }
+
+    fn prepare(&self, sql: &str) -> Result<Stmt, Error> {
+        let sql = CString::new(sql)?;
+        let mut stmt = ptr::null_mut();
+        // SAFETY: db 是打开的连接，stmt 由 SQLite 写入
+        let rc = unsafe { ffi::sqlite3_prepare_v2(self.db, sql.as_ptr(), -1, &mut stmt, ptr::null_mut()) };
+        self.check(rc)?;
+        Ok(Stmt(stmt))
+    }
+
+    /// 执行只返回一个整数的查询
+    fn query_i64(&self, sql: &str) -> Result<i64, Error> {
+        let stmt = self.prepare(sql)?;
+        // SAFETY: stmt 是刚准备好的语句
+        match unsafe { ffi::sqlite3_step(stmt.0) } {
+            ffi::SQLITE_ROW => Ok(unsafe { ffi::sqlite3_column_int64(stmt.0, 0) }),
+            rc => Err(self.check(rc).err().map_or_else(|| self.unexpected(rc), Error::from)),
+        }
+    }
+
+    /// 执行插入语句，之后重置语句以便再次绑定
+    fn step(&self, stmt: &Stmt) -> Result<(), Error> {
+        // SAFETY: stmt 是这个连接上准备好的语句
+        let rc = unsafe { ffi::sqlite3_step(stmt.0) };
+        unsafe { ffi::sqlite3_reset(stmt.0) };
+        if rc == ffi::SQLITE_DONE {
+            Ok(())
+        } else {
+            Err(self.check(rc).err().map_or_else(|| self.unexpected(rc), Error::from))
+        }
+    }
+
+    fn unexpected(&self, rc: c_int) -> Error {
+        SqliteErr {
+            path: self.path.clone(),
+            msg: format!("意外的返回值 {}", rc),
+        }
+        .into()
+    }
+
+    fn bind_i64(&self, stmt: &Stmt, i: c_int, v: i64) -> Result<(), SqliteErr> {
+        // SAFETY: stmt 是这个连接上准备好的语句
+        self.check(unsafe { ffi::sqlite3_bind_int64(stmt.0, i, v) })
+    }
+
+    fn bind_text(&self, stmt: &Stmt, i: c_int, v: &str) -> Result<(), Error> {
+        let len = c_int::try_from(v.len())?;
+        // SAFETY: SQLITE_TRANSIENT 让 SQLite 立即复制数据，v 不需要活得比语句长
+        let rc = unsafe { ffi::sqlite3_bind_text(stmt.0, i, v.as_ptr() as *const c_char, len, ffi::SQLITE_TRANSIENT) };
+        Ok(self.check(rc)?)
+    }
+
+    fn bind_blob(&self, stmt: &Stmt, i: c_int, v: &[u8]) -> Result<(), Error> {
+        let len = c_int::try_from(v.len())?;
+        // SAFETY: 同 bind_text
+        let rc = unsafe { ffi::sqlite3_bind_blob(stmt.0, i, v.as_ptr() as *const c_void, len, ffi::SQLITE_TRANSIENT) };
+        Ok(self.check(rc)?)
+    }
+}
+
+impl Drop for Sink {
+    fn drop(&mut self) {
+        // close_v2 等到之后 drop 的语句都释放了才真正关闭连接
+        // SAFETY: db 由 sqlite3_open_v2 打开，只在这里关闭一次
+        unsafe { ffi::sqlite3_close_v2(self.db) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::path::PathBuf;
+
+    #[link(name = "sqlite3")]
+    unsafe extern "C" {
+        fn sqlite3_column_count(stmt: *mut ffi::sqlite3_stmt) -> c_int;
+        fn sqlite3_column_text(stmt: *mut ffi::sqlite3_stmt, i: c_int) -> *const c_char;
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        let p = std::env::temp_dir().join(format!("pgrep-sqlite-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&p);
+        p
+    }
+
+    /// 执行查询，每一列都按文本读出
+    fn rows(s: &Sink, sql: &str) -> Vec<Vec<String>> {
+        let stmt = s.prepare(sql).unwrap();
+        let mut out = Vec::new();
+        // SAFETY: stmt 是刚准备好的语句，列的文本在下一次 step 之前有效，这里立即复制
+        unsafe {
+            while ffi::sqlite3_step(stmt.0) == ffi::SQLITE_ROW {
+                let row = (0..sqlite3_column_count(stmt.0))
+                    .map(|i| CStr::from_ptr(sqlite3_column_text(stmt.0, i)).to_string_lossy().into_owned())
+                    .collect();
+                out.push(row);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn writes_runs_files_and_matches() {
+        let db = scratch("write");
+        let s = Sink::open(&db, false, "TODO|FIXME", "pgrep -p 'TODO|FIXME'").unwrap();
+        s.write_file(Path::new("src/a.rs"), &[(3, 5, "// TODO 一"), (9, 1, "FIXME")]).unwrap();
+        s.write_file(Path::new("b.txt"), &[(1, 2, " TODO")]).unwrap();
+        s.write_file(Path::new(OsStr::from_bytes(b"bad\xff.txt")), &[(4, 1, "TODO")]).unwrap();
+
+        assert_eq!(s.query_i64("PRAGMA user_version").unwrap(), SCHEMA_VERSION);
+        assert_eq!(s.query_i64("SELECT count(*) FROM runs").unwrap(), 1);
+        assert_eq!(s.query_i64("SELECT count(*) FROM files").unwrap(), 3);
+        assert_eq!(s.query_i64("SELECT count(*) FROM matches").unwrap(), 4);
+        assert_eq!(
+            rows(&s, "SELECT pattern, args FROM runs"),
+            [["TODO|FIXME", "pgrep -p 'TODO|FIXME'"]]
+        );
+        assert_eq!(
+            rows(
+                &s,
+                "SELECT f.path, f.path_is_blob, m.line, m.column, m.text FROM matches m \
+                 JOIN files f ON f.id = m.file_id WHERE f.path = 'src/a.rs' ORDER BY m.line"
+            ),
+            [["src/a.rs", "0", "3", "5", "// TODO 一"], ["src/a.rs", "0", "9", "1", "FIXME"]]
+        );
+        // 不是 UTF-8 的路径按原始字节存为 BLOB
+        let blob = "SELECT count(*) FROM files WHERE path_is_blob = 1 AND typeof(path) = 'blob' AND path = x'626164ff2e747874'";
+        assert_eq!(s.query_i64(blob).unwrap(), 1);
+        drop(s);
+        let _ = std::fs::remove_file(&db);
+    }
+
+    #[test]
+    fn existing_databases_need_append() {
+        let db = scratch("append");
+        drop(Sink::open(&db, false, "a", "pgrep -p a").unwrap());
+        let e = Sink::open(&db, false, "b", "pgrep -p b").err().unwrap();
+        assert!(e.downcast_ref::<DbExists>().is_some(), "{}", e);
+
+        let s = Sink::open(&db, true, "b", "pgrep -p b --append").unwrap();
+        s.write_file(Path::new("x"), &[(1, 1, "b")]).unwrap();
+        assert_eq!(rows(&s, "SELECT id, pattern FROM runs ORDER BY id"), [["1", "a"], ["2", "b"]]);
+        assert_eq!(rows(&s, "SELECT run_id FROM files"), [["2"]]);
+        drop(s);
+        let _ = std::fs::remove_file(&db);
+    }
+
+    #[test]
+    fn newer_schema_is_rejected() {
+        let db = scratch("newer");
+        let s = Sink::open(&db, false, "a", "").unwrap();
+        s.exec(&format!("PRAGMA user_version = {}", SCHEMA_VERSION + 1)).unwrap();
+        drop(s);
+        let e = Sink::open(&db, true, "a", "").err().unwrap();
+        assert!(e.downcast_ref::<NewerSchema>().is_some(), "{}", e);
+        assert!(e.to_string().ends_with("的结构是第 2 版，这个版本的 pgrep 只支持到第 1 版"), "{}", e);
+        let _ = std::fs::remove_file(&db);
+    }
+}
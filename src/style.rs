@@ -0,0 +1,278 @@
+// 高亮样式
+//
+// 输出使用颜色时（见 `--color`），路径、行号和匹配到的文本分别用不同的样式显示。
+// --match-highlight-style 用类似 CSS 的写法修改这些样式，例如：
+//
+//     match:fg=red;bold, filename:fg=cyan, lineno:fg=green;dim, context:fg=white;dim
+//
+// * 各个部分之间用逗号分隔，部分名之后是冒号，属性之间用分号
+// * 部分: `match`（匹配到的文本）、`filename`（路径）、`lineno`（行号）、
+//   `context`（上下文行：`--tui` 中的上下文，以及 `--highlight-context-lines` 时 `-A` / `-B` 输出的上下文行）
+// * 属性: `fg=颜色`、`bg=颜色`、`bold`、`dim`、`italic`、`underline`、`reverse`，
+//   `none` 表示不使用任何样式
+// * 颜色: `black` `red` `green` `yellow` `blue` `magenta` `cyan` `white`，
+//   加上 `bright-` 前缀是对应的亮色；也可以写 0 到 255 的 256 色编号
+// * 写到的部分整个替换默认样式，没有写到的部分保持默认样式
+//
+// 默认样式和 GNU grep 相同：匹配是粗体红色，路径是品红色，行号是绿色；上下文行是暗色。
+// `--no-bold` 去掉所有样式中的粗体，便于在粗体难以辨认的终端上阅读。
+//
+// 这个构建没有包含 termcolor，样式直接写成 SGR 转义序列。
+//
+// 相关文档:
+// * SGR 参数: <https://en.wikipedia.org/wiki/ANSI_escape_code#SGR_(Select_Graphic_Rendition)_parameters>
+// * GREP_COLORS: <https://www.gnu.org/software/grep/manual/html_node/Environment-Variables.html>
+
+use failure::Fail;
+
+/// 样式字符串无法解析
+#[derive(Debug, Fail)]
+#[fail(display = "无法解析高亮样式 {:?}: {}", spec, reason)]
+pub struct StyleErr {
+    spec: String,
+    reason: String,
+}
+
+/// 一种颜色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// 8 种基本颜色之一，0 到 7
+    Basic(u8),
+    /// 基本颜色对应的亮色，0 到 7
+    Bright(u8),
+    /// 256 色编号
+    Indexed(u8),
+}
+
+const COLOR_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+impl Color {
+    fn parse(s: &str) -> Option<Color> {
+        if let Ok(n) = s.parse::<u8>() {
+            return Some(Color::Indexed(n));
+        }
+        let (bright, name) = match s.strip_prefix("bright-") {
+            Some(name) => (true, name),
+            None => (false, s),
+        };
+        let n = COLOR_NAMES.iter().position(|c| *c == name)? as u8;
+        Some(if bright { Color::Bright(n) } else { Color::Basic(n) })
+    }
+
+    /// SGR 参数，`base` 是前景色 30 / 背景色 40
+    fn sgr(self, base: u8) -> String {
+        match self {
+            Color::Basic(n) => (base + n).to_string(),
+            Color::Bright(n) => (base + 60 + n).to_string(),
+            Color::Indexed(n) => format!("{};5;{}", base + 8, n),
+        }
+    }
+}
+
+/// 一个部分的样式
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+impl Style {
+    /// 开始这个样式的转义序列，没有任何样式时为空字符串
+    pub fn sgr(&self) -> String {
+        let mut codes: Vec<String> = [
+            (self.bold, "1"),
+            (self.dim, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+            (self.reverse, "7"),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, c)| c.to_string())
+        .collect();
+        codes.extend(self.fg.map(|c| c.sgr(30)));
+        codes.extend(self.bg.map(|c| c.sgr(40)));
+        if codes.is_empty() {
+            String::new()
+        } else {
+            format!("\x1b[{}m", codes.join(";"))
+        }
+    }
+
+    /// 用这个样式显示一段文本，之后恢复默认样式
+    pub fn paint(&self, s: &str) -> String {
+        let sgr = self.sgr();
+        if sgr.is_empty() || s.is_empty() {
+            s.to_string()
+        } else {
+            format!("{}{}\x1b[0m", sgr, s)
+        }
+    }
+}
+
+/// 输出中各个部分的样式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighlightStyle {
+    pub matched: Style,
+    pub filename: Style,
+    pub lineno: Style,
+    pub context: Style,
+}
+
+impl Default for HighlightStyle {
+    fn default() -> HighlightStyle {
+        HighlightStyle {
+            matched: Style {
+                fg: Some(Color::Basic(1)),
+                bold: true,
+                ..Style::default()
+            },
+            filename: Style {
+                fg: Some(Color::Basic(5)),
+                ..Style::default()
+            },
+            lineno: Style {
+                fg: Some(Color::Basic(2)),
+                ..Style::default()
+            },
+            context: Style {
+                dim: true,
+                ..Style::default()
+            },
+        }
+    }
+}
+
+impl HighlightStyle {
+    /// 在默认样式的基础上解析 `--match-highlight-style` 的值
+    ///
+    /// # 示例
+    /// * `match:fg=yellow;underline` - 只修改匹配的样式
+    /// * `filename:none, lineno:none` - 路径和行号不使用颜色
+    pub fn parse(spec: &str) -> Result<HighlightStyle, StyleErr> {
+        let err = |reason: String| StyleErr {
+            spec: spec.to_string(),
+            reason,
+        };
+        let mut hl = HighlightStyle::default();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, attrs) = part
+                .split_once(':')
+                .ok_or_else(|| err(format!("{:?} 缺少冒号，应该写成 `部分:属性;属性`", part)))?;
+            let target = match name.trim() {
+                "match" => &mut hl.matched,
+                "filename" => &mut hl.filename,
+                "lineno" => &mut hl.lineno,
+                "context" => &mut hl.context,
+                other => {
+                    return Err(err(format!(
+                        "未知的部分 {:?}，可以是 match、filename、lineno、context",
+                        other
+                    )));
+                }
+            };
+            let mut style = Style::default();
+            for attr in attrs.split(';').map(str::trim).filter(|a| !a.is_empty()) {
+                match attr.split_once('=') {
+                    Some((key, value)) => {
+                        let c = Color::parse(value.trim()).ok_or_else(|| err(format!("未知的颜色 {:?}", value)))?;
+                        match key.trim() {
+                            "fg" => style.fg = Some(c),
+                            "bg" => style.bg = Some(c),
+                            other => return Err(err(format!("未知的属性 {:?}，可以是 fg、bg", other))),
+                        }
+                    }
+                    None => match attr {
+                        "bold" => style.bold = true,
+                        "dim" => style.dim = true,
+                        "italic" => style.italic = true,
+                        "underline" => style.underline = true,
+                        "reverse" => style.reverse = true,
+                        "none" => style = Style::default(),
+                        other => return Err(err(format!("未知的属性 {:?}", other))),
+                    },
+                }
+            }
+            *target = style;
+        }
+        Ok(hl)
+    }
+
+    /// 去掉所有部分的粗体
+    pub fn without_bold(mut self) -> HighlightStyle {
+        for s in [&mut self.matched, &mut self.filename, &mut self.lineno, &mut self.context] {
+            s.bold = false;
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 解析之后四个部分的转义序列：match、filename、lineno、context
+    fn sgrs(hl: &HighlightStyle) -> [String; 4] {
+        [hl.matched.sgr(), hl.filename.sgr(), hl.lineno.sgr(), hl.context.sgr()]
+    }
+
+    #[test]
+    fn defaults_match_gnu_grep() {
+        assert_eq!(sgrs(&HighlightStyle::parse("").unwrap()), ["\x1b[1;31m", "\x1b[35m", "\x1b[32m", "\x1b[2m"]);
+        assert_eq!(HighlightStyle::default().matched.paint("x"), "\x1b[1;31mx\x1b[0m");
+    }
+
+    #[test]
+    fn parse_to_exact_sequences() {
+        for (spec, part, sgr) in [
+            ("match:fg=yellow;underline", 0, "\x1b[4;33m"),
+            ("match:fg=bright-red;bg=blue;bold", 0, "\x1b[1;91;44m"),
+            ("match:fg=208;bg=17", 0, "\x1b[38;5;208;48;5;17m"),
+            ("match:bg=bright-white;reverse;italic", 0, "\x1b[3;7;107m"),
+            ("filename:fg=cyan", 1, "\x1b[36m"),
+            (" lineno : fg=green ; dim ", 2, "\x1b[2;32m"),
+            ("context:fg=white;dim", 3, "\x1b[2;37m"),
+            ("match:none", 0, ""),
+            ("match:fg=red;none;underline", 0, "\x1b[4m"),
+        ] {
+            let hl = HighlightStyle::parse(spec).unwrap();
+            assert_eq!(sgrs(&hl)[part], sgr, "{}", spec);
+        }
+    }
+
+    #[test]
+    fn unnamed_parts_keep_defaults() {
+        let hl = HighlightStyle::parse("filename:none, lineno:none").unwrap();
+        assert_eq!(sgrs(&hl), ["\x1b[1;31m", "", "", "\x1b[2m"]);
+        assert_eq!(hl.filename.paint("a.txt"), "a.txt");
+    }
+
+    #[test]
+    fn without_bold_drops_only_bold() {
+        let hl = HighlightStyle::default().without_bold();
+        assert_eq!(sgrs(&hl), ["\x1b[31m", "\x1b[35m", "\x1b[32m", "\x1b[2m"]);
+        let hl = HighlightStyle::parse("match:bold;underline, filename:bold").unwrap().without_bold();
+        assert_eq!(sgrs(&hl), ["\x1b[4m", "", "\x1b[32m", "\x1b[2m"]);
+    }
+
+    #[test]
+    fn errors_name_the_problem() {
+        for (spec, reason) in [
+            ("match", "\"match\" 缺少冒号"),
+            ("title:bold", "未知的部分 \"title\""),
+            ("match:fg=pink", "未知的颜色 \"pink\""),
+            ("match:fg=256", "未知的颜色 \"256\""),
+            ("match:color=red", "未知的属性 \"color\""),
+            ("match:blink", "未知的属性 \"blink\""),
+        ] {
+            let err = HighlightStyle::parse(spec).unwrap_err().to_string();
+            assert!(err.starts_with(&format!("无法解析高亮样式 {:?}: ", spec)), "{}", err);
+            assert!(err.contains(reason), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// 不存在的搜索路径的提示
+//
+// 命令行上给出的路径不存在时，系统错误只有一句 "No such file or directory"，也不说是哪个路径。
+// 这里在搜索之前检查每个路径，不存在时报告路径本身，再根据情况附上提示：
+// * 路径中有 `*`、`?`、`[`：多半是被引号括起来、没有被 shell 展开的通配符，pgrep 不会自己展开
+// * 否则从第一个不存在的部分开始，在它所在的目录中找编辑距离最近的名字，逐级替换，
+//   例如 `sr/main.rs` 先把 `sr` 换成 `src`，再在 `src` 中确认 `main.rs`
+//
+// 找不到足够接近的名字时只报告路径不存在，不会给出牵强的建议。
+//
+// 相关文档:
+// * strsim::levenshtein: <https://docs.rs/strsim/latest/strsim/fn.levenshtein.html>
+
+use std::path::{Component, Path, PathBuf};
+
+use failure::Fail;
+
+/// 命令行上给出的搜索路径不存在
+#[derive(Debug, Fail)]
+#[fail(display = "{}: 没有这个文件或目录{}", path, hint)]
+pub struct MissingPath {
+    pub path: String,
+    /// 以 `；` 开头的提示，没有提示时为空
+    pub hint: String,
+}
+
+/// 检查一个搜索路径，不存在时返回带有提示的错误
+///
+/// 存在的路径（包括指向不存在的目标的符号链接）和标准输入 `-` 返回 None，由搜索本身报告其他错误
+pub fn check_root(path: &str) -> Option<MissingPath> {
+    if path == "-" || std::fs::symlink_metadata(path).is_ok() {
+        return None;
+    }
+    let hint = if path.contains(['*', '?', '[']) {
+        "；看起来是没有被 shell 展开的通配符（是不是加了引号？），pgrep 不展开通配符，\
+         请去掉引号让 shell 展开，或者搜索所在的目录并用 --type-add 'mine:GLOB' --type mine 筛选"
+            .to_string()
+    } else {
+        match correct(Path::new(path)) {
+            Some(p) => format!("；是不是 '{}'？", p.display()),
+            None => String::new(),
+        }
+    };
+    Some(MissingPath {
+        path: path.to_string(),
+        hint,
+    })
+}
+
+/// 逐级纠正路径中不存在的部分，每一级都换成所在目录中最接近的名字
+fn correct(path: &Path) -> Option<PathBuf> {
+    let mut fixed = PathBuf::new();
+    let mut changed = false;
+    for c in path.components() {
+        let Component::Normal(name) = c else {
+            fixed.push(c);
+            continue;
+        };
+        let next = fixed.join(name);
+        if std::fs::symlink_metadata(&next).is_ok() {
+            fixed = next;
+            continue;
+        }
+        let dir = if fixed.as_os_str().is_empty() { Path::new(".") } else { &fixed };
+        let siblings: Vec<String> = std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|e| e.ok()?.file_name().into_string().ok())
+            .collect();
+        let best = closest(&name.to_string_lossy(), siblings.iter().map(String::as_str))?;
+        fixed.push(best);
+        changed = true;
+    }
+    changed.then_some(fixed)
+}
+
+/// 在候选的名字中找和 `name` 最接近的一个
+///
+/// 比较时不区分大小写，编辑距离不超过名字长度的三分之一（至少允许 1 处差异）才算接近；
+/// 距离相同时取先出现的候选。和 `name` 完全相同的候选不算，它不需要纠正。
+///
+/// # 示例
+/// ```
+/// use pgrep::suggest::closest;
+///
+/// assert_eq!(closest("sr", ["src", "target"]), Some("src"));
+/// assert_eq!(closest("docs", ["src", "target"]), None);
+/// ```
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let lower = name.to_lowercase();
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|&c| c != name)
+        .map(|c| (strsim::levenshtein(&lower, &c.to_lowercase()), c))
+        .filter(|&(d, _)| d <= limit)
+        .min_by_key(|&(d, _)| d)
+        .map(|(_, c)| c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_within_a_third_of_the_length() {
+        let names = ["src", "target", "Cargo.toml", "README.md", "tests"];
+        assert_eq!(closest("sr", names), Some("src"));
+        assert_eq!(closest("trget", names), Some("target"));
+        assert_eq!(closest("cargo.toml", names), Some("Cargo.toml"));
+        assert_eq!(closest("Crago.tmol", names), None);
+        assert_eq!(closest("test", names), Some("tests"));
+        assert_eq!(closest("docs", names), None);
+        assert_eq!(closest("src", names), None);
+        assert_eq!(closest("x", [] as [&str; 0]), None);
+    }
+
+    #[test]
+    fn ties_keep_the_first_candidate() {
+        assert_eq!(closest("ab", ["ax", "ay"]), Some("ax"));
+        assert_eq!(closest("ab", ["ay", "ax", "abc"]), Some("ay"));
+    }
+
+    fn tree(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pgrep-suggest-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src/bin")).unwrap();
+        std::fs::write(dir.join("src/main.rs"), "").unwrap();
+        std::fs::write(dir.join("src/bin/tool.rs"), "").unwrap();
+        dir
+    }
+
+    #[test]
+    fn correct_fixes_each_missing_component() {
+        let dir = tree("correct");
+        assert_eq!(correct(&dir.join("sr/main.rs")), Some(dir.join("src/main.rs")));
+        assert_eq!(correct(&dir.join("src/mian.rs")), Some(dir.join("src/main.rs")));
+        assert_eq!(correct(&dir.join("sr/bn/tool.rs")), Some(dir.join("src/bin/tool.rs")));
+        assert_eq!(correct(&dir.join("src/main.rs")), None);
+        assert_eq!(correct(&dir.join("src/zzzzzz.rs")), None);
+        assert_eq!(correct(&dir.join("nothing/main.rs")), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn check_root_tells_globs_from_typos() {
+        let dir = tree("check");
+        assert!(check_root("-").is_none());
+        assert!(check_root(dir.join("src").to_str().unwrap()).is_none());
+
+        let typo = dir.join("sr/main.rs");
+        let err = check_root(typo.to_str().unwrap()).unwrap();
+        assert_eq!(err.to_string(), format!("{}: 没有这个文件或目录；是不是 '{}'？", typo.display(), dir.join("src/main.rs").display()));
+
+        let glob = dir.join("src/*.rs");
+        let err = check_root(glob.to_str().unwrap()).unwrap();
+        assert!(err.hint.contains("没有被 shell 展开的通配符"), "{}", err);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+// 抑制已知的匹配
+//
+// 和 linter 一样，确认过的匹配可以被抑制，不再出现在结果中：
+// * 行内标记: 匹配的行或者它的上一行含有 `pgrep-ignore`（用 --ignore-marker 修改，--no-ignore-marker 关闭），
+//   例如 `legacy_api(); // pgrep-ignore`。这一步在 lib 的逐行匹配中完成，见 `Record::suppressed`
+// * 抑制文件: --suppressions FILE 集中列出要抑制的匹配，每行一个条目：
+//
+//       # 注释和空行被忽略
+//       src/compat.rs:legacy_api        路径:正则表达式，抑制这个文件中内容匹配表达式的行
+//       src/compat.rs:42                路径:行号，抑制这个文件中的第 42 行
+//       tests/**:unwrap\(\)              路径可以是通配符，语法见 glob 模块
+//
+//   冒号之后全是数字时是行号，否则是正则表达式；路径中不能含有冒号。
+//
+// 被抑制的匹配不输出，搜索结束后在标准错误中报告被抑制的数量；--show-suppressed 时照常输出它们。
+// 没有抑制过任何匹配的条目在搜索结束后报告出来，已经修复的匹配对应的条目可以及时删除。
+// 抑制只影响搜索结果，--write-replace、--diff 和 --patch 仍然替换文件中所有的匹配。
+//
+// 相关文档:
+// * ESLint 的行内注释: <https://eslint.org/docs/latest/use/configure/rules#disabling-rules>
+
+use failure::{Error, Fail};
+use regex::Regex;
+use std::cell::Cell;
+use std::path::Path;
+
+use pgrep::Record;
+use pgrep::glob::Glob;
+
+/// 默认的行内标记
+pub const DEFAULT_MARKER: &str = pgrep::DEFAULT_IGNORE_MARKER;
+
+/// 抑制文件中无法解析的条目
+#[derive(Debug, Fail)]
+#[fail(display = "{}:{}: 无法解析抑制条目 {:?}: {}", path, line, entry, reason)]
+pub struct SuppressionErr {
+    path: String,
+    line: usize,
+    entry: String,
+    reason: String,
+}
+
+/// 条目中冒号之后的部分
+#[derive(Debug)]
+enum Target {
+    /// 从 1 开始的行号
+    Line(usize),
+    /// 和行内容比较的正则表达式
+    Pattern(Regex),
+}
+
+/// 抑制文件中的一个条目
+#[derive(Debug)]
+struct Entry {
+    /// 在抑制文件中的行号，用于报告
+    at: usize,
+    text: String,
+    path: Glob,
+    target: Target,
+    /// 是否抑制过至少一个匹配
+    used: Cell<bool>,
+}
+
+/// 读入的抑制文件
+#[derive(Debug)]
+pub struct Suppressions {
+    file: String,
+    entries: Vec<Entry>,
+}
+
+impl Suppressions {
+    /// 读取 `--suppressions` 指定的文件
+    pub fn load(p: &Path) -> Result<Suppressions, Error> {
+        let text = std::fs::read_to_string(p)?;
+        let file = p.display().to_string();
+        let mut entries = Vec::new();
+        for (i, l) in text.lines().enumerate() {
+            let l = l.trim();
+            if l.is_empty() || l.starts_with('#') {
+                continue;
+            }
+            let err = |reason: String| SuppressionErr {
+                path: file.clone(),
+                line: i + 1,
+                entry: l.to_string(),
+                reason,
+            };
+            let (path, rest) = l
+                .split_once(':')
+                .ok_or_else(|| err("缺少冒号，应该写成 `路径:正则表达式` 或 `路径:行号`".to_string()))?;
+            let target = if !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit()) {
+                Target::Line(rest.parse().map_err(|e| err(format!("{}", e)))?)
+            } else {
+                Target::Pattern(Regex::new(rest).map_err(|e| err(e.to_string()))?)
+            };
+            entries.push(Entry {
+                at: i + 1,
+                text: l.to_string(),
+                path: Glob::new(path).map_err(|e| err(e.to_string()))?,
+                target,
+                used: Cell::new(false),
+            });
+        }
+        Ok(Suppressions { file, entries })
+    }
+
+    /// 是否有条目抑制 `p` 中的匹配 `r`，有的话把这些条目记为用过
+    pub fn matches(&self, p: &Path, r: &Record) -> bool {
+        let mut hit = false;
+        for e in &self.entries {
+            let m = e.path.is_match(p)
+                && match &e.target {
+                    Target::Line(n) => *n == r.line + 1,
+                    Target::Pattern(re) => re.is_match(&r.tx),
+                };
+            if m {
+                e.used.set(true);
+                hit = true;
+            }
+        }
+        hit
+    }
+
+    /// 没有用过的条目，写成 `文件:行号: 条目` 的形式
+    pub fn unused(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|e| !e.used.get())
+            .map(|e| format!("{}:{}: {}", self.file, e.at, e.text))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 把 `text` 写成一个临时的抑制文件再读取
+    fn load(name: &str, text: &str) -> Result<Suppressions, Error> {
+        let p = std::env::temp_dir().join(format!("pgrep-suppress-{}-{}.txt", name, std::process::id()));
+        std::fs::write(&p, text).unwrap();
+        let r = Suppressions::load(&p);
+        let _ = std::fs::remove_file(&p);
+        r
+    }
+
+    fn record(line: usize, tx: &str) -> Record {
+        Record {
+            line,
+            tx: tx.to_string(),
+            fuzzy: None,
+            replaced: None,
+            word: None,
+            suppressed: false,
+            rules: Vec::new(),
+            is_context: false,
+        }
+    }
+
+    #[test]
+    fn line_and_pattern_entries() {
+        let s = load("entries", "# 注释\n\n  src/a.rs:3  \nsrc/a.rs:legacy_api\ntests/**:unwrap\\(\\)\n").unwrap();
+        assert_eq!(s.entries.len(), 3);
+        let a = Path::new("src/a.rs");
+        // 行号从 1 开始，Record::line 从 0 开始
+        assert!(s.matches(a, &record(2, "anything")));
+        assert!(!s.matches(a, &record(3, "anything")));
+        assert!(s.matches(a, &record(9, "x = legacy_api();")));
+        assert!(!s.matches(Path::new("src/b.rs"), &record(2, "legacy_api")));
+        assert!(s.matches(Path::new("tests/deep/t.rs"), &record(0, "v.unwrap()")));
+        assert!(!s.matches(Path::new("tests/deep/t.rs"), &record(0, "v.expect(\"\")")));
+    }
+
+    #[test]
+    fn unused_entries_are_reported() {
+        let s = load("unused", "src/a.rs:1\n# x\nsrc/a.rs:gone\nother.rs:2\n").unwrap();
+        assert!(s.matches(Path::new("src/a.rs"), &record(0, "hit")));
+        let unused = s.unused();
+        assert_eq!(unused.len(), 2);
+        assert!(unused[0].ends_with(":3: src/a.rs:gone"), "{}", unused[0]);
+        assert!(unused[1].ends_with(":4: other.rs:2"), "{}", unused[1]);
+        // 用过一次就不再报告
+        assert!(s.matches(Path::new("other.rs"), &record(1, "")));
+        assert_eq!(s.unused().len(), 1);
+    }
+
+    #[test]
+    fn malformed_entries_are_rejected() {
+        let message = |text: &str| {
+            let e = load("bad", text).unwrap_err();
+            assert!(e.downcast_ref::<SuppressionErr>().is_some(), "{}", e);
+            e.to_string()
+        };
+        let m = message("# ok\nsrc/a.rs\n");
+        assert!(m.ends_with(":2: 无法解析抑制条目 \"src/a.rs\": 缺少冒号，应该写成 `路径:正则表达式` 或 `路径:行号`"), "{}", m);
+        let m = message("src/a.rs:(\n");
+        assert!(m.contains(":1: 无法解析抑制条目 \"src/a.rs:(\": "), "{}", m);
+        let m = message("src/a.rs:99999999999999999999999\n");
+        assert!(m.contains("无法解析抑制条目"), "{}", m);
+    }
+}
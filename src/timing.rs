@@ -0,0 +1,224 @@
+// 每个文件各阶段的耗时
+//
+// --profile-regex 只计算正则表达式本身的时间，搜索变慢的原因也可能是读取大文件、解压、解码或者输出。
+// --debug-timing 把每个文件的时间分成三个阶段：
+// * 读取和解码：读文件（或者运行预处理命令）、解压、检查二进制、解码成文本、统一行尾
+// * 匹配：逐行匹配（或者在整个内容上查找）并生成结果，包括 `--replace` 的替换
+// * 输出：把这个文件的结果交给输出回调，包括格式化、写标准输出和 `--exec` 之类的命令
+// 同时记下每个文件解码之前的字节数和行数，搜索结束后报告最慢的几个文件和各阶段的合计。
+//
+// --profile-regex 和 --profile-per-line 的正则表达式匹配耗时也按文件记在这里，搜索结束后由 main 输出，
+// 不会和搜索结果或者其他线程的输出交错在一起，见 Timings::regex_report。
+//
+// 没有使用这几个选项时不读取时钟，见 GrepConfig 的 `timing`。
+// 各阶段的时间按路径累加在一个加锁的表中，在多个线程中搜索时合计也是所有线程的总和；
+// 包中的成员按 `包的路径!成员名` 分别记录，包本身只有读取的时间。
+//
+// 相关文档:
+// * std::time::Instant: <https://doc.rust-lang.org/std/time/struct.Instant.html>
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 计时的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// 读取和解码
+    Read,
+    /// 匹配
+    Match,
+    /// 输出
+    Output,
+}
+
+/// 一个文件（或者所有文件合计）的耗时和大小
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileTiming {
+    pub read: Duration,
+    pub matching: Duration,
+    pub output: Duration,
+    /// 解码之前（解压之后）的字节数
+    pub bytes: u64,
+    pub lines: usize,
+    /// 正则表达式匹配的总耗时、匹配过的行数和匹配的行数，见 `Timings::add_regex`
+    pub regex: Duration,
+    pub regex_lines: usize,
+    pub matches: usize,
+    /// 每一行（从 0 开始的行号）的正则表达式匹配耗时，只有 `--profile-per-line` 时记录
+    pub per_line: Vec<(usize, Duration)>,
+}
+
+impl FileTiming {
+    /// 三个阶段的总耗时
+    pub fn total(&self) -> Duration {
+        self.read + self.matching + self.output
+    }
+
+    fn add(&mut self, other: &FileTiming) {
+        self.read += other.read;
+        self.matching += other.matching;
+        self.output += other.output;
+        self.bytes += other.bytes;
+        self.lines += other.lines;
+        self.regex += other.regex;
+        self.regex_lines += other.regex_lines;
+        self.matches += other.matches;
+    }
+}
+
+/// 整次搜索中每个文件的耗时
+#[derive(Debug, Default)]
+pub struct Timings {
+    files: Mutex<HashMap<PathBuf, FileTiming>>,
+}
+
+impl Timings {
+    /// 把一段耗时累加到文件的一个阶段上
+    pub fn add(&self, p: &Path, phase: Phase, d: Duration) {
+        let mut files = self.lock();
+        let t = files.entry(p.to_path_buf()).or_default();
+        match phase {
+            Phase::Read => t.read += d,
+            Phase::Match => t.matching += d,
+            Phase::Output => t.output += d,
+        }
+    }
+
+    /// 记下文件的字节数和行数
+    pub fn count(&self, p: &Path, bytes: usize, lines: usize) {
+        let mut files = self.lock();
+        let t = files.entry(p.to_path_buf()).or_default();
+        t.bytes += bytes as u64;
+        t.lines += lines;
+    }
+
+    /// 记下一个文件的正则表达式匹配耗时
+    ///
+    /// # 参数
+    /// * `regex` - 正则表达式匹配的总耗时
+    /// * `regex_lines` - 运行过正则表达式的行数
+    /// * `matches` - 匹配的行数
+    /// * `per_line` - 每一行的耗时，不需要时为空
+    pub fn add_regex(&self, p: &Path, regex: Duration, regex_lines: usize, matches: usize, per_line: Vec<(usize, Duration)>) {
+        let mut files = self.lock();
+        let t = files.entry(p.to_path_buf()).or_default();
+        t.regex += regex;
+        t.regex_lines += regex_lines;
+        t.matches += matches;
+        t.per_line.extend(per_line);
+    }
+
+    /// 所有文件的合计
+    pub fn totals(&self) -> FileTiming {
+        let mut sum = FileTiming::default();
+        for t in self.lock().values() {
+            sum.add(t);
+        }
+        sum
+    }
+
+    /// 按总耗时从慢到快排列的文件，耗时相同时按路径排列
+    pub fn slowest(&self) -> Vec<(PathBuf, FileTiming)> {
+        let mut all: Vec<_> = self.lock().iter().map(|(p, t)| (p.clone(), t.clone())).collect();
+        all.sort_by(|a, b| b.1.total().cmp(&a.1.total()).then_with(|| a.0.cmp(&b.0)));
+        all
+    }
+
+    /// 搜索结束后输出到标准错误的报告：最慢的 `top` 个文件，最后一行是所有文件的合计
+    ///
+    /// # 示例
+    /// ```
+    /// use pgrep::timing::{Phase, Timings};
+    /// use std::path::Path;
+    /// use std::time::Duration;
+    ///
+    /// let t = Timings::default();
+    /// t.add(Path::new("a.log"), Phase::Read, Duration::from_millis(3));
+    /// t.add(Path::new("a.log"), Phase::Match, Duration::from_millis(5));
+    /// t.add(Path::new("b.log"), Phase::Output, Duration::from_millis(1));
+    /// let report = t.report(1);
+    /// let lines: Vec<&str> = report.lines().collect();
+    /// assert_eq!(lines.len(), 4);
+    /// assert!(lines[0].starts_with("--debug-timing: 最慢的 1 个文件（共 2 个）"));
+    /// assert!(lines[2].ends_with("a.log"));
+    /// assert!(lines[3].ends_with("（合计）"));
+    /// assert_eq!(t.totals().total(), Duration::from_millis(9));
+    /// ```
+    pub fn report(&self, top: usize) -> String {
+        let all = self.slowest();
+        let mut s = String::new();
+        let _ = writeln!(s, "--debug-timing: 最慢的 {} 个文件（共 {} 个）", top.min(all.len()), all.len());
+        let heads = [("总计", 12), ("读取和解码", 12), ("匹配", 12), ("输出", 12), ("字节", 12), ("行", 10)];
+        let heads: Vec<String> = heads.iter().map(|&(h, w)| pad(h, w)).collect();
+        let _ = writeln!(s, "{}  路径", heads.join(" "));
+        for (p, t) in all.iter().take(top) {
+            let _ = writeln!(s, "{}  {}", row(t), p.display());
+        }
+        let _ = writeln!(s, "{}  （合计）", row(&self.totals()));
+        s
+    }
+
+    /// `--profile-regex` 和 `--profile-per-line` 的报告，文件按路径排列
+    ///
+    /// 每个文件先是 `per_line` 时每一行的 `正则耗时 路径:行号: 耗时`，
+    /// 然后是 `per_file` 时的 `正则耗时 路径: 耗时（N 行，M 个匹配）`。
+    /// 只有运行过正则表达式或者有匹配的文件出现在报告中。
+    ///
+    /// # 示例
+    /// ```
+    /// use pgrep::timing::Timings;
+    /// use std::path::Path;
+    /// use std::time::Duration;
+    ///
+    /// let t = Timings::default();
+    /// let per_line = vec![(0, Duration::from_micros(3)), (1, Duration::from_micros(2))];
+    /// t.add_regex(Path::new("a.log"), Duration::from_micros(5), 2, 1, per_line);
+    /// assert_eq!(t.regex_report(true, false), "正则耗时 a.log: 5µs（2 行，1 个匹配）\n");
+    /// assert_eq!(t.regex_report(false, true), "正则耗时 a.log:1: 3µs\n正则耗时 a.log:2: 2µs\n");
+    /// ```
+    pub fn regex_report(&self, per_file: bool, per_line: bool) -> String {
+        let files = self.lock();
+        let mut all: Vec<_> = files.iter().filter(|(_, t)| t.regex_lines > 0 || t.matches > 0).collect();
+        all.sort_by(|a, b| a.0.cmp(b.0));
+        let mut s = String::new();
+        for (p, t) in all {
+            if per_line {
+                for (i, d) in &t.per_line {
+                    let _ = writeln!(s, "正则耗时 {}:{}: {:?}", p.display(), i + 1, d);
+                }
+            }
+            if per_file {
+                let _ = writeln!(s, "正则耗时 {}: {:?}（{} 行，{} 个匹配）", p.display(), t.regex, t.regex_lines, t.matches);
+            }
+        }
+        s
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, FileTiming>> {
+        // 记录耗时的线程 panic 之后表中的数据仍然可以使用
+        self.files.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 在左边补空格右对齐到 `w` 列，中文字符占两列
+fn pad(h: &str, w: usize) -> String {
+    let width: usize = h.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum();
+    format!("{}{}", " ".repeat(w.saturating_sub(width)), h)
+}
+
+/// 报告中的一行数字
+fn row(t: &FileTiming) -> String {
+    let d = |d: Duration| format!("{:.2?}", d);
+    format!(
+        "{:>12} {:>12} {:>12} {:>12} {:>12} {:>10}",
+        d(t.total()),
+        d(t.read),
+        d(t.matching),
+        d(t.output),
+        t.bytes,
+        t.lines
+    )
+}
@@ -0,0 +1,546 @@
+// 终端界面
+//
+// --tui 打开一个全屏的浏览界面，用来边改模式边查看结果：
+// * 第一行是模式，直接输入即可修改，停止输入后立即重新搜索
+// * 左栏列出包含匹配的文件和每个文件中匹配的行数
+// * 右栏显示选中文件中的匹配行，以及前后各 CONTEXT 行上下文，匹配到的文本高亮显示
+//   （样式和 `--match-highlight-style` 相同）
+// * 最后一行是状态栏：文件数、匹配行数和错误数；模式无法编译时显示编译错误
+//
+// 按键：
+// * 上 / 下: 选择文件；PageUp / PageDown: 滚动右栏
+// * 可见字符、Backspace: 编辑模式；Ctrl-U: 清空模式
+// * Esc / Ctrl-C / Ctrl-D: 退出
+//
+// 这个构建没有包含 ratatui / crossterm 之类的终端界面库：
+// 原始模式用 termios 设置，界面用 ANSI 转义序列在备用屏幕上绘制，退出后终端恢复原样。
+// 目前只能浏览，不能修改文件。
+//
+// 相关文档:
+// * termios(3): <https://man7.org/linux/man-pages/man3/termios.3.html>
+// * XTerm 控制序列: <https://invisible-island.net/xterm/ctlseqs/ctlseqs.html>
+
+use failure::{Error, Fail};
+use std::cell::{Cell, RefCell};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::output::{char_width, terminal_size};
+use crate::style::HighlightStyle;
+use pgrep::{GrepConfig, Record, WalkContext, match_spans, process_path};
+use regex::Regex;
+
+/// 右栏中匹配行前后显示的行数
+const CONTEXT: usize = 2;
+
+/// 没有按键时多久检查一次终端大小，毫秒
+const POLL_MS: i32 = 200;
+
+/// 连续输入时，停顿这么久（毫秒）之后才重新搜索
+const TYPING_MS: i32 = 50;
+
+/// `--tui` 需要终端
+#[derive(Debug, Fail)]
+#[fail(display = "--tui 只能在标准输入和标准输出都是终端时使用")]
+pub struct NotATerminal;
+
+/// `--tui` 从终端读取按键，不能同时搜索标准输入
+#[derive(Debug, Fail)]
+#[fail(display = "--tui 不支持搜索标准输入")]
+pub struct TuiStdin;
+
+/// 一个包含匹配的文件
+struct FileHits {
+    path: PathBuf,
+    records: Vec<Record>,
+}
+
+/// 右栏中的一行
+enum Row<'a> {
+    /// 行号（从 0 开始）、内容以及是否是匹配行
+    Line(usize, &'a str, bool),
+    /// 两段不相邻的上下文之间的分隔
+    Gap,
+}
+
+/// 一个按键
+enum Key {
+    Char(char),
+    Backspace,
+    ClearPattern,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Quit,
+}
+
+/// 各个部分的样式对应的转义序列
+struct Sgr {
+    matched: String,
+    filename: String,
+    lineno: String,
+    context: String,
+}
+
+/// 界面的全部状态
+struct State<'a> {
+    paths: &'a [String],
+    cfg: &'a GrepConfig,
+    sgr: Sgr,
+    pattern: String,
+    // 当前模式编译后的结果，模式为空或者无法编译时为 None
+    re: Option<Regex>,
+    files: Vec<FileHits>,
+    errors: usize,
+    // 状态栏中代替统计信息显示的提示，例如编译错误
+    message: Option<String>,
+    selected: usize,
+    scroll: usize,
+    // 选中文件的内容，用来显示上下文；无法直接读取（例如包中的成员）或者和搜索到的内容对不上
+    // （例如 `-z`、`--pre` 转换过的文件）时为空，只显示匹配行
+    content: Vec<String>,
+}
+
+impl<'a> State<'a> {
+    /// 用当前的模式重新搜索
+    fn search(&mut self) {
+        self.files.clear();
+        self.errors = 0;
+        self.message = None;
+        self.selected = 0;
+        self.re = None;
+        if self.pattern.is_empty() {
+            self.message = Some("输入模式开始搜索".to_string());
+            self.load_selected();
+            return;
+        }
+        let re = match Regex::new(&self.pattern) {
+            Ok(re) => re,
+            Err(e) => {
+                // regex 的错误信息有多行，最后一行是错误的原因
+                let e = e.to_string();
+                self.message = Some(e.lines().rfind(|l| !l.trim().is_empty()).unwrap_or(&e).trim().to_string());
+                self.load_selected();
+                return;
+            }
+        };
+
+        let found = RefCell::new(Vec::new());
+        let errors = Cell::new(0);
+        let ff = |p: &Path, v: Vec<Record>| {
+            // 带有行内抑制标记的匹配不显示
+            let v: Vec<Record> = v.into_iter().filter(|r| !r.suppressed).collect();
+            if !v.is_empty() {
+                found.borrow_mut().push(FileHits {
+                    path: p.to_path_buf(),
+                    records: v,
+                });
+            }
+            Ok(())
+        };
+        let ef = |_: Error| errors.set(errors.get() + 1);
+        for p in self.paths {
+            if process_path(p, &re, self.cfg, &WalkContext::default(), &ff, &|_: &Path| {}, &ef).is_err() {
+                errors.set(errors.get() + 1);
+            }
+        }
+        self.files = found.into_inner();
+        self.errors = errors.get();
+        self.re = Some(re);
+        self.load_selected();
+    }
+
+    /// 选中上一个或下一个文件
+    fn select(&mut self, down: bool) {
+        let next = if down {
+            (self.selected + 1).min(self.files.len().saturating_sub(1))
+        } else {
+            self.selected.saturating_sub(1)
+        };
+        if next != self.selected {
+            self.selected = next;
+            self.load_selected();
+        }
+    }
+
+    /// 读取选中文件的内容，回到右栏的顶部
+    fn load_selected(&mut self) {
+        self.scroll = 0;
+        self.content = Vec::new();
+        let Some(f) = self.files.get(self.selected) else { return };
+        let Ok(bts) = std::fs::read(&f.path) else { return };
+        let content: Vec<String> = self.cfg.newline.lines(&String::from_utf8_lossy(&bts)).map(str::to_string).collect();
+        if f.records.iter().all(|r| content.get(r.line) == Some(&r.tx)) {
+            self.content = content;
+        }
+    }
+
+    /// 选中文件在右栏中显示的所有行
+    fn rows(&self) -> Vec<Row<'_>> {
+        let Some(f) = self.files.get(self.selected) else {
+            return Vec::new();
+        };
+        let mut rows = Vec::new();
+        if self.content.is_empty() {
+            for r in &f.records {
+                rows.push(Row::Line(r.line, r.tx.as_str(), true));
+            }
+            return rows;
+        }
+        // 下一个还没有显示的行
+        let mut next = 0;
+        for (i, r) in f.records.iter().enumerate() {
+            let start = r.line.saturating_sub(CONTEXT).max(next);
+            if start > next && next > 0 {
+                rows.push(Row::Gap);
+            }
+            // 和下一条匹配之间的上下文只显示一次
+            let end = match f.records.get(i + 1) {
+                Some(n) => (r.line + CONTEXT).min(n.line.saturating_sub(1)),
+                None => r.line + CONTEXT,
+            }
+            .min(self.content.len().saturating_sub(1));
+            for l in start..=end.max(r.line) {
+                let tx = if l == r.line {
+                    r.tx.as_str()
+                } else {
+                    self.content.get(l).map_or("", String::as_str)
+                };
+                rows.push(Row::Line(l, tx, l == r.line));
+            }
+            next = end.max(r.line) + 1;
+        }
+        rows
+    }
+
+    /// 绘制整个界面
+    fn render(&self, w: usize, h: usize) -> String {
+        let mut s = String::from("\x1b[?25l\x1b[H");
+        let prompt = "模式: ";
+        s.push_str(&format!("\x1b[1m{}\x1b[0m{}\x1b[K", prompt, fit(&[("", &self.pattern)], w.saturating_sub(width(prompt)))));
+
+        let body = h.saturating_sub(2);
+        let lw = (w / 3).clamp(10, 40).min(w.saturating_sub(2));
+        let rw = w - lw - 1;
+        let ltop = (self.selected + 1).saturating_sub(body);
+        let rows = self.rows();
+        let numw = rows
+            .iter()
+            .map(|r| match r {
+                Row::Line(l, _, _) => (l + 1).to_string().len(),
+                Row::Gap => 0,
+            })
+            .max()
+            .unwrap_or(1);
+        for i in 0..body {
+            s.push_str("\r\n");
+            let left = match self.files.get(ltop + i) {
+                Some(f) => {
+                    let item = format!("{} ({})", f.path.display(), f.records.len());
+                    let style = if ltop + i == self.selected {
+                        format!("{}\x1b[7m", self.sgr.filename)
+                    } else {
+                        self.sgr.filename.clone()
+                    };
+                    fit(&[(style.as_str(), &item)], lw)
+                }
+                None => " ".repeat(lw),
+            };
+            s.push_str(&left);
+            s.push_str("\x1b[2m│\x1b[0m");
+            match rows.get(self.scroll + i) {
+                Some(Row::Line(l, tx, hit)) => {
+                    let num = format!("{:>numw$} ", l + 1);
+                    let style = if *hit { &self.sgr.lineno } else { &self.sgr.context };
+                    let mut segs = vec![(style.as_str(), num.as_str())];
+                    segs.extend(self.highlight(tx, *hit));
+                    s.push_str(&fit(&segs, rw));
+                }
+                Some(Row::Gap) => s.push_str(&fit(&[(self.sgr.context.as_str(), "--")], rw)),
+                None => s.push_str(&" ".repeat(rw)),
+            }
+        }
+
+        s.push_str("\r\n");
+        let status = match &self.message {
+            Some(m) => fit(&[("\x1b[31m", m)], w),
+            None => {
+                let lines: usize = self.files.iter().map(|f| f.records.len()).sum();
+                let mut st = format!("{} 个文件，{} 行匹配", self.files.len(), lines);
+                if self.errors > 0 {
+                    st.push_str(&format!("，{} 个错误", self.errors));
+                }
+                st.push_str("  ↑↓ 选择文件  PgUp/PgDn 滚动  Ctrl-U 清空  Esc 退出");
+                fit(&[("\x1b[7m", &st)], w)
+            }
+        };
+        s.push_str(&status);
+        // 光标停在模式的末尾
+        let col = (width(prompt) + width(&self.pattern) + 1).min(w);
+        s.push_str(&format!("\x1b[1;{}H\x1b[?25h", col));
+        s
+    }
+
+    /// 把一行分成普通和高亮的片段，匹配行标出匹配到的文本，上下文行整行使用上下文的样式
+    fn highlight<'s, 't>(&'s self, tx: &'t str, hit: bool) -> Vec<(&'s str, &'t str)> {
+        if !hit {
+            return vec![(self.sgr.context.as_str(), tx)];
+        }
+        let mut segs = Vec::new();
+        let mut last = 0;
+        if let Some(re) = &self.re {
+            for (start, end) in match_spans(re, tx, self.cfg.overlapping) {
+                // 重叠的匹配合并成一段
+                let start = start.max(last);
+                if start >= end {
+                    continue;
+                }
+                segs.push(("", &tx[last..start]));
+                segs.push((self.sgr.matched.as_str(), &tx[start..end]));
+                last = end;
+            }
+        }
+        segs.push(("", &tx[last..]));
+        segs
+    }
+}
+
+/// 按显示宽度截断并用空格补齐到 `w` 列
+///
+/// 每个片段是 `(样式, 文本)`，样式为空时不加转义序列；控制字符（包括制表符）显示为空格
+fn fit(segs: &[(&str, &str)], w: usize) -> String {
+    let mut s = String::new();
+    let mut used = 0;
+    for (style, text) in segs {
+        if used >= w {
+            break;
+        }
+        s.push_str(style);
+        for c in text.chars() {
+            let c = if c.is_control() { ' ' } else { c };
+            let cw = char_width(c);
+            if used + cw > w {
+                used = w;
+                break;
+            }
+            s.push(c);
+            used += cw;
+        }
+        if !style.is_empty() {
+            s.push_str("\x1b[0m");
+        }
+    }
+    s.push_str(&" ".repeat(w.saturating_sub(used)));
+    s
+}
+
+fn width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+/// 把读到的字节解析成按键，不认识的转义序列被忽略
+fn parse_keys(bts: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bts.len() {
+        match bts[i] {
+            0x1b => {
+                // 单独的 Esc 是退出，否则是方向键等按键的转义序列
+                let Some(&kind) = bts.get(i + 1) else {
+                    keys.push(Key::Quit);
+                    break;
+                };
+                let start = i + 2;
+                let mut end = start;
+                if kind == b'[' {
+                    while end < bts.len() && !(0x40..=0x7e).contains(&bts[end]) {
+                        end += 1;
+                    }
+                }
+                match (kind, &bts[start.min(bts.len())..(end + 1).min(bts.len())]) {
+                    (b'[' | b'O', b"A") => keys.push(Key::Up),
+                    (b'[' | b'O', b"B") => keys.push(Key::Down),
+                    (b'[', b"5~") => keys.push(Key::PageUp),
+                    (b'[', b"6~") => keys.push(Key::PageDown),
+                    _ => {}
+                }
+                i = end + 1;
+            }
+            0x03 | 0x04 => keys.push(Key::Quit),
+            0x7f | 0x08 => keys.push(Key::Backspace),
+            0x15 => keys.push(Key::ClearPattern),
+            b if b < 0x20 => {}
+            _ => {
+                // 一段连续的可见字符，可能是粘贴进来的多个字符
+                let end = bts[i..]
+                    .iter()
+                    .position(|&b| b < 0x20 || b == 0x7f)
+                    .map_or(bts.len(), |n| i + n);
+                keys.extend(String::from_utf8_lossy(&bts[i..end]).chars().map(Key::Char));
+                i = end;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    keys
+}
+
+/// 进入原始模式和备用屏幕，离开时恢复原来的终端设置
+struct RawTerminal {
+    saved: libc::termios,
+}
+
+impl RawTerminal {
+    fn enter() -> Result<RawTerminal, Error> {
+        // SAFETY: tcgetattr / tcsetattr 只读写传入的 termios
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut saved) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let mut raw = saved;
+        unsafe {
+            libc::cfmakeraw(&mut raw);
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+        }
+        let mut out = std::io::stdout();
+        out.write_all(b"\x1b[?1049h")?;
+        out.flush()?;
+        Ok(RawTerminal { saved })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let mut out = std::io::stdout();
+        let _ = out.write_all(b"\x1b[?25h\x1b[?1049l");
+        let _ = out.flush();
+        // SAFETY: 恢复进入时保存的设置
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved);
+        }
+    }
+}
+
+/// 终端的列数和行数，取不到时按 80 × 24 计算
+fn term_size() -> (usize, usize) {
+    terminal_size().unwrap_or((80, 24))
+}
+
+/// 等待最多 `ms` 毫秒，返回期间读到的字节；超时返回空
+fn read_input(ms: i32) -> Result<Vec<u8>, Error> {
+    let mut fd = libc::pollfd {
+        fd: libc::STDIN_FILENO,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // SAFETY: poll 只读写传入的 pollfd，read 最多写入 buf 的长度
+    let ready = unsafe { libc::poll(&mut fd, 1, ms) };
+    if ready < 0 {
+        let e = std::io::Error::last_os_error();
+        // 终端大小变化时 SIGWINCH 会打断 poll
+        if e.kind() == std::io::ErrorKind::Interrupted {
+            return Ok(Vec::new());
+        }
+        return Err(e.into());
+    }
+    if ready == 0 {
+        return Ok(Vec::new());
+    }
+    let mut buf = vec![0u8; 4096];
+    let n = unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr().cast(), buf.len()) };
+    if n < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    if n == 0 {
+        // 终端关闭，当作退出
+        return Ok(vec![0x04]);
+    }
+    buf.truncate(n as usize);
+    Ok(buf)
+}
+
+/// 打开界面，直到用户退出
+///
+/// # 参数
+/// * `paths` - 要搜索的路径，不能包含标准输入
+/// * `pattern` - 初始的模式，可以为空
+/// * `cfg` - 搜索配置，修改模式时保持不变
+/// * `hl` - 匹配、路径、行号和上下文行的样式
+pub fn run(paths: &[String], pattern: &str, cfg: &GrepConfig, hl: &HighlightStyle) -> Result<(), Error> {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return Err(NotATerminal.into());
+    }
+    if paths.iter().any(|p| p == "-") {
+        return Err(TuiStdin.into());
+    }
+
+    let _term = RawTerminal::enter()?;
+    let mut st = State {
+        paths,
+        cfg,
+        sgr: Sgr {
+            matched: hl.matched.sgr(),
+            filename: hl.filename.sgr(),
+            lineno: hl.lineno.sgr(),
+            context: hl.context.sgr(),
+        },
+        pattern: pattern.to_string(),
+        re: None,
+        files: Vec::new(),
+        errors: 0,
+        message: None,
+        selected: 0,
+        scroll: 0,
+        content: Vec::new(),
+    };
+    st.search();
+
+    let mut out = std::io::stdout();
+    let mut drawn = None;
+    let mut dirty = true;
+    let mut pending = false;
+    loop {
+        let (w, h) = term_size();
+        if dirty || drawn != Some((w, h)) {
+            out.write_all(st.render(w, h).as_bytes())?;
+            out.flush()?;
+            drawn = Some((w, h));
+            dirty = false;
+        }
+
+        let bts = read_input(if pending { TYPING_MS } else { POLL_MS })?;
+        if bts.is_empty() {
+            // 停止输入了，用新的模式搜索
+            if pending {
+                st.search();
+                pending = false;
+                dirty = true;
+            }
+            continue;
+        }
+        let page = h.saturating_sub(2).max(1);
+        for key in parse_keys(&bts) {
+            match key {
+                Key::Quit => return Ok(()),
+                Key::Char(c) => {
+                    st.pattern.push(c);
+                    pending = true;
+                }
+                Key::Backspace => pending |= st.pattern.pop().is_some(),
+                Key::ClearPattern => {
+                    pending |= !st.pattern.is_empty();
+                    st.pattern.clear();
+                }
+                Key::Up => st.select(false),
+                Key::Down => st.select(true),
+                Key::PageUp => st.scroll = st.scroll.saturating_sub(page),
+                Key::PageDown => st.scroll = (st.scroll + page).min(st.rows().len().saturating_sub(1)),
+            }
+        }
+        dirty = true;
+    }
+}
@@ -0,0 +1,209 @@
+// 监视模式
+//
+// --watch 在第一次搜索结束后不退出，而是定期检查搜索路径下的文件，
+// 有文件新增、删除或者修改时间、大小发生变化时重新搜索一次。
+//
+// 这个构建没有包含 notify 之类的文件系统事件库，所以采用轮询：
+// * 每隔 POLL_INTERVAL 遍历一次搜索路径，记录每个文件的修改时间和大小
+// * 发现变化后继续等待，直到连续 --watch-debounce 毫秒内没有新的变化才重新搜索，
+//   编辑器保存、git checkout 之类一次改动多个文件的操作只会触发一次搜索
+// * 不会被搜索的文件（`--type-filter`、`--rules` 排除的文件）的变化不触发重新搜索
+// * 标准输出重定向到搜索路径中的文件时，这个文件的变化不触发重新搜索，否则每次输出结果都会再触发一次
+// * 不跟随指向目录的符号链接，这些目录中的变化不会被发现
+//
+// 等待变化时按 Ctrl-C 结束程序，结果和最后一次搜索相同；搜索进行中按 Ctrl-C 照常立即终止。
+//
+// 相关文档:
+// * std::fs::Metadata::modified: <https://doc.rust-lang.org/std/fs/struct.Metadata.html#method.modified>
+// * signal(2): <https://man7.org/linux/man-pages/man2/signal.2.html>
+
+use std::collections::HashMap;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+use pgrep::{GrepConfig, TypeFilter};
+
+/// 两次检查之间的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// 每个文件的修改时间和大小
+type Snapshot = HashMap<PathBuf, (Option<SystemTime>, u64)>;
+
+/// 监视一组搜索路径
+pub struct Watcher<'a> {
+    roots: Vec<PathBuf>,
+    cfg: &'a GrepConfig,
+    debounce: Duration,
+    last: Snapshot,
+    // 标准输出是普通文件时它的设备号和 inode
+    stdout: Option<(u64, u64)>,
+}
+
+impl<'a> Watcher<'a> {
+    /// 记录当前的状态，之后的变化都相对于这个状态
+    ///
+    /// # 参数
+    /// * `roots` - 搜索路径，`-`（标准输入）被忽略
+    /// * `cfg` - 搜索配置，用来排除不会被搜索的文件
+    /// * `debounce` - 最后一次变化之后需要保持不变多久才算变化结束
+    pub fn new<P: AsRef<Path>>(roots: &[P], cfg: &'a GrepConfig, debounce: Duration) -> Watcher<'a> {
+        let mut w = Watcher {
+            roots: roots.iter().map(|r| r.as_ref().to_path_buf()).collect(),
+            cfg,
+            debounce,
+            last: Snapshot::new(),
+            stdout: stdout_id(),
+        };
+        w.last = w.snapshot();
+        w
+    }
+
+    /// 等待下一次变化结束
+    ///
+    /// # 返回值
+    /// * `true` - 文件发生了变化，需要重新搜索
+    /// * `false` - 等待时用户按了 Ctrl-C
+    pub fn wait(&mut self) -> bool {
+        let _guard = InterruptGuard::install();
+        loop {
+            if interrupted(POLL_INTERVAL) {
+                return false;
+            }
+            let mut now = self.snapshot();
+            if now == self.last {
+                continue;
+            }
+            // 有变化之后每隔 debounce 再看一次，直到两次之间没有新的变化
+            loop {
+                if interrupted(self.debounce) {
+                    return false;
+                }
+                let settled = self.snapshot();
+                if settled == now {
+                    break;
+                }
+                now = settled;
+            }
+            self.last = now;
+            return true;
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        let mut s = Snapshot::new();
+        for r in self.roots.iter().filter(|r| r.as_path() != Path::new("-")) {
+            self.visit(r, &mut s);
+        }
+        s
+    }
+
+    // 无法读取的条目直接忽略，它们在搜索时会作为错误报告出来
+    fn visit(&self, p: &Path, s: &mut Snapshot) {
+        let Ok(lmd) = p.symlink_metadata() else { return };
+        let is_link = lmd.file_type().is_symlink();
+        if is_link && self.cfg.type_filter == TypeFilter::Regular {
+            return;
+        }
+        let md = if is_link {
+            match p.metadata() {
+                Ok(md) => md,
+                Err(_) => return,
+            }
+        } else {
+            lmd
+        };
+
+        if md.is_dir() {
+            if is_link {
+                return;
+            }
+            let Ok(dd) = std::fs::read_dir(p) else { return };
+            for entry in dd.flatten() {
+                self.visit(&entry.path(), s);
+            }
+        } else if md.is_file() && self.stdout != Some((md.dev(), md.ino())) && self.relevant(p, is_link) {
+            s.insert(p.to_path_buf(), (md.modified().ok(), md.len()));
+        }
+    }
+
+    /// 和 process_path 一样判断文件会不会被搜索
+    fn relevant(&self, p: &Path, is_link: bool) -> bool {
+        if self.cfg.type_filter == TypeFilter::Symlink && !is_link {
+            return false;
+        }
+        self.cfg.search_archives || self.cfg.rules.is_empty() || self.cfg.rules.iter().any(|r| r.applies_to(p))
+    }
+}
+
+/// 标准输出重定向到的普通文件
+fn stdout_id() -> Option<(u64, u64)> {
+    // SAFETY: fstat 只写入传入的 stat
+    let mut st: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(libc::STDOUT_FILENO, &mut st) } != 0 || st.st_mode & libc::S_IFMT != libc::S_IFREG {
+        return None;
+    }
+    Some((st.st_dev as u64, st.st_ino as u64))
+}
+
+// 等待变化期间收到 SIGINT 时设置
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// 只在等待变化的期间接管 SIGINT，离开时恢复默认的处理方式
+struct InterruptGuard;
+
+impl InterruptGuard {
+    fn install() -> InterruptGuard {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        // SAFETY: 处理函数只写一个原子变量，是异步信号安全的
+        unsafe {
+            libc::signal(libc::SIGINT, on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        }
+        InterruptGuard
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        // SAFETY: 恢复为默认处理方式
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+        }
+    }
+}
+
+/// 睡眠一段时间，期间收到 SIGINT 时返回 true
+fn interrupted(d: Duration) -> bool {
+    // 分成小段睡眠，按下 Ctrl-C 之后很快就能退出
+    let step = Duration::from_millis(20);
+    let mut left = d;
+    while !left.is_zero() {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return true;
+        }
+        let s = left.min(step);
+        std::thread::sleep(s);
+        left -= s;
+    }
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// 当前的本地时间，格式为 `HH:MM:SS`
+pub fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default() as libc::time_t;
+    // SAFETY: localtime_r 只写入传入的 tm
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        let s = now as u64 % 86400;
+        return format!("{:02}:{:02}:{:02}", s / 3600, s % 3600 / 60, s % 60);
+    }
+    format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
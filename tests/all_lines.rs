@@ -0,0 +1,50 @@
+// 空模式被拒绝，选中每一行要明确地使用 --all-lines
+
+mod common;
+
+const REJECTED: &str = "程序执行时发生错误: 模式是空的，会匹配每一行；确实要选中每一行时请使用 --all-lines\n";
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    let dir = common::scratch(name);
+    common::write(&dir, "a.txt", "one\n\n# c\ntwo\n");
+    common::write(&dir, "b.txt", "x\ny\n");
+    dir
+}
+
+#[test]
+fn empty_patterns_are_rejected() {
+    let dir = fixture("all-lines-empty");
+    for args in [&["-p", "", "-f", "a.txt"][..], &["", "a.txt"], &["-p", "one", "-p", "", "-f", "a.txt"]] {
+        let out = common::pgrep(&dir, args);
+        assert_eq!(common::stdout(&out), "", "{:?}", args);
+        assert_eq!(common::stderr(&out), REJECTED, "{:?}", args);
+    }
+    // --pattern-file 中的空行被跳过，不算空模式
+    common::write(&dir, "pats", "one\n\nx\n");
+    let out = common::pgrep(&dir, &["--pattern-file", "pats", "-f", "a.txt", "b.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:1:one\nb.txt:1:x\n");
+    assert_eq!(common::stderr(&out), "");
+}
+
+#[test]
+fn all_lines_selects_every_line() {
+    let dir = fixture("all-lines-flag");
+    let out = common::pgrep(&dir, &["--all-lines", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:1:one\na.txt:2:\na.txt:3:# c\na.txt:4:two\n");
+    // 没有模式，位置参数都是路径
+    let out = common::pgrep(&dir, &["--all-lines", "a.txt", "b.txt"]);
+    assert_eq!(common::sorted_lines(&out).len(), 6);
+
+    let out = common::pgrep(&dir, &["--all-lines", "-p", "x", "-f", "a.txt"]);
+    assert!(common::stderr(&out).contains("cannot be used with"), "{}", common::stderr(&out));
+}
+
+#[test]
+fn counting_all_lines() {
+    let dir = fixture("all-lines-count");
+    let out = common::pgrep(&dir, &["--all-lines", "-c", "-f", "a.txt", "b.txt"]);
+    assert_eq!(common::sorted_lines(&out), ["a.txt:4", "b.txt:2"]);
+    // 行过滤照常生效
+    let out = common::pgrep(&dir, &["--all-lines", "--skip-prefix", "#", "-c", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:3\n");
+}
@@ -0,0 +1,66 @@
+// --archives 中成员的虚拟路径和被截断的包
+//
+// fixtures/archive 中：
+// * snap.tgz - etc/config 和 docs/readme.txt 两个成员
+// * truncated.tgz - snap.tgz 的前 100 字节，gzip 数据被截断
+// * cut.tar.gz - gzip 完整，其中的 tar 在 docs/readme.txt 之前被截断，etc/config 完整
+// * plain.txt - 普通文件
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/archive")
+}
+
+#[test]
+fn members_are_labelled_with_the_archive_path() {
+    let out = common::pgrep(&fixtures(), &["--archives", "-p", "listen_port", "-f", "snap.tgz"]);
+    assert_eq!(
+        common::stdout(&out),
+        "snap.tgz!etc/config:1:listen_port = 8080\nsnap.tgz!docs/readme.txt:2:listen_port is documented here\n"
+    );
+    assert_eq!(common::stderr(&out), "");
+
+    // 遍历目录时是目录中的路径加上成员名，.tar.gz 也一样
+    let out = common::pgrep(&fixtures(), &["--archives", "-p", "host", "-f", "."]);
+    assert_eq!(
+        common::sorted_lines(&out),
+        ["./cut.tar.gz!etc/config:2:host = example.org", "./snap.tgz!etc/config:2:host = example.org"]
+    );
+}
+
+#[test]
+fn truncated_gzip_is_reported_and_the_search_continues() {
+    let out = common::pgrep(&fixtures(), &["--archives", "-p", "listen_port", "-f", "truncated.tgz", "plain.txt"]);
+    assert_eq!(common::stdout(&out), "plain.txt:1:listen_port in a plain file\n");
+    assert_eq!(common::stderr(&out), "处理错误: 无法解压 gzip 文件 truncated.tgz: 文件被截断\n");
+}
+
+#[test]
+fn truncated_tar_keeps_the_complete_members() {
+    let out = common::pgrep(&fixtures(), &["--archives", "-p", "listen_port", "-f", "cut.tar.gz", "plain.txt"]);
+    assert_eq!(
+        common::stdout(&out),
+        "cut.tar.gz!etc/config:1:listen_port = 8080\nplain.txt:1:listen_port in a plain file\n"
+    );
+    assert_eq!(common::stderr(&out), "处理错误: 无法读取 tar 包 cut.tar.gz: 包在第 1100 字节处被截断\n");
+}
+
+#[test]
+fn whole_directory_with_a_truncated_archive() {
+    let out = common::pgrep(&fixtures(), &["--archives", "-p", "listen_port", "-f", "."]);
+    assert_eq!(
+        common::sorted_lines(&out),
+        [
+            "./cut.tar.gz!etc/config:1:listen_port = 8080",
+            "./plain.txt:1:listen_port in a plain file",
+            "./snap.tgz!docs/readme.txt:2:listen_port is documented here",
+            "./snap.tgz!etc/config:1:listen_port = 8080",
+        ]
+    );
+    let stderr = common::stderr(&out);
+    assert!(stderr.contains("./truncated.tgz: 文件被截断"), "{}", stderr);
+    assert!(stderr.contains("./cut.tar.gz: 包在第 1100 字节处被截断"), "{}", stderr);
+}
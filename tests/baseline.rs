@@ -0,0 +1,62 @@
+// --save-baseline 和 --baseline 的端到端测试
+
+mod common;
+
+#[test]
+fn only_the_added_match_is_reported() {
+    let dir = common::scratch("baseline");
+    let a = common::write(&dir, "a.rs", "fn main() {\n    legacy_api();\n    other();\n}\n\nfn b() {\n    let x = legacy_api();\n}\n");
+    let out = common::pgrep(&dir, &["-p", r"legacy_api\(", "-f", "a.rs", "--save-baseline", "base.json"]);
+    assert_eq!(out.status.code(), Some(0), "{}", common::stderr(&out));
+    assert_eq!(
+        std::fs::read_to_string(dir.join("base.json")).unwrap(),
+        concat!(
+            "{\n",
+            "  \"version\": 1,\n",
+            "  \"matches\": [\n",
+            "    {\"path\": \"a.rs\", \"line\": \"legacy_api();\"},\n",
+            "    {\"path\": \"a.rs\", \"line\": \"let x = legacy_api();\"}\n",
+            "  ]\n",
+            "}\n"
+        )
+    );
+
+    // 上方插入了几行，let x 移到了另一个函数中并且改了缩进，另外新增了一处匹配
+    std::fs::write(
+        &a,
+        "use x;\n\n\nfn main() {\n    legacy_api();\n    other();\n}\n\nfn c() {\n    if y {\n        let x = legacy_api();\n    }\n}\n\nfn d() {\n    let z = legacy_api();\n}\n",
+    )
+    .unwrap();
+    let out = common::pgrep(&dir, &["-p", r"legacy_api\(", "-f", "a.rs", "--baseline", "base.json"]);
+    let stdout = common::stdout(&out);
+    assert!(stdout.contains("let z = legacy_api();"), "{}", stdout);
+    assert!(!stdout.contains("    legacy_api();\n"), "{}", stdout);
+    assert!(!stdout.contains("let x"), "{}", stdout);
+    assert_eq!(stdout.matches("legacy_api").count(), 1, "{}", stdout);
+    assert_eq!(out.status.code(), Some(1));
+}
+
+#[test]
+fn unchanged_tree_passes_the_baseline() {
+    let dir = common::scratch("baseline-clean");
+    common::write(&dir, "a.rs", "legacy_api();\nlegacy_api();\n");
+    common::pgrep(&dir, &["-p", "legacy_api", "-f", "a.rs", "--save-baseline", "base.json"]);
+    let out = common::pgrep(&dir, &["-p", "legacy_api", "-f", "a.rs", "--baseline", "base.json"]);
+    assert_eq!(common::stdout(&out), "");
+    assert_eq!(out.status.code(), Some(0));
+
+    // 同样的一行多出现一次也是新的匹配
+    common::write(&dir, "a.rs", "legacy_api();\nlegacy_api();\nlegacy_api();\n");
+    let out = common::pgrep(&dir, &["-p", "legacy_api", "-f", "a.rs", "--baseline", "base.json"]);
+    assert_eq!(common::stdout(&out).matches("legacy_api").count(), 1);
+    assert_eq!(out.status.code(), Some(1));
+}
+
+#[test]
+fn missing_baseline_exits_2() {
+    let dir = common::scratch("baseline-missing");
+    common::write(&dir, "a.rs", "legacy_api();\n");
+    let out = common::pgrep(&dir, &["-p", "legacy_api", "-f", "a.rs", "--baseline", "none.json"]);
+    assert_eq!(common::stdout(&out), "");
+    assert_eq!(out.status.code(), Some(2));
+}
@@ -0,0 +1,38 @@
+// 下游提前关闭管道（`| head`）时安静地退出
+//
+// Rust 程序忽略 SIGPIPE，写入关闭了的管道得到 BrokenPipe 错误；
+// 用 print! 写标准输出时这个错误会变成 panic，所有输出都要经过 Output。
+
+mod common;
+
+/// 正常退出，标准错误中没有 panic 的信息
+fn assert_quiet(out: &std::process::Output) {
+    assert!(out.status.success(), "{:?} {}", out.status, common::stderr(out));
+    assert!(!common::stderr(out).contains("panicked"), "{}", common::stderr(out));
+}
+
+#[test]
+fn completions() {
+    let dir = common::scratch("pipe-completions");
+    assert_quiet(&common::pgrep_closed_stdout(&dir, &["--generate-completions", "zsh"]));
+}
+
+#[test]
+fn man_page() {
+    let dir = common::scratch("pipe-man-page");
+    assert_quiet(&common::pgrep_closed_stdout(&dir, &["--generate-man-page"]));
+}
+
+#[test]
+fn rg_command() {
+    let dir = common::scratch("pipe-rg-command");
+    assert_quiet(&common::pgrep_closed_stdout(&dir, &["--print-rg-command", "-p", "x", "-f", "."]));
+}
+
+#[test]
+fn list_profiles() {
+    let dir = common::scratch("pipe-list-profiles");
+    let conf: String = (0..2000).map(|i| format!("[profile.p{}]\nskip-empty-lines = true\n", i)).collect();
+    common::write(&dir, "conf.toml", conf);
+    assert_quiet(&common::pgrep_closed_stdout(&dir, &["--config", "conf.toml", "--list-profiles"]));
+}
@@ -0,0 +1,76 @@
+// --cache-dir 的第二次运行从缓存文件读取 DFA，不再构建
+
+mod common;
+
+use std::time::Instant;
+
+fn cache_files(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    match std::fs::read_dir(dir) {
+        Ok(rd) => rd.map(|e| e.unwrap().path()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+#[test]
+fn second_run_reads_the_cache() {
+    let dir = common::scratch("cache-hit");
+    common::write(&dir, "a.txt", "alpha\nw7919x1 here\nomega\n");
+    let words: Vec<String> = (0..1500).map(|i| format!("w{}x{}", i * 7919 % 10007, i)).collect();
+    let pattern = words.join("|");
+    let args = ["-vv", "--cache-dir", "cache", "-p", &pattern, "-f", "a.txt"];
+
+    let started = Instant::now();
+    let first = common::pgrep(&dir, &args);
+    let built = started.elapsed();
+    assert_eq!(common::stdout(&first), "a.txt:2:w7919x1 here\n");
+    let stderr = common::stderr(&first);
+    assert!(stderr.contains("构建 DFA"), "{}", stderr);
+    assert!(stderr.contains("写入缓存: cache/"), "{}", stderr);
+    let files = cache_files(&dir.join("cache"));
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert_eq!(files[0].extension().unwrap(), "rc");
+    let mtime = std::fs::metadata(&files[0]).unwrap().modified().unwrap();
+
+    let started = Instant::now();
+    let second = common::pgrep(&dir, &args);
+    let read = started.elapsed();
+    assert_eq!(common::stdout(&second), common::stdout(&first));
+    let stderr = common::stderr(&second);
+    assert!(stderr.contains("从缓存读取 DFA: cache/"), "{}", stderr);
+    assert!(!stderr.contains("构建 DFA"), "{}", stderr);
+    assert_eq!(std::fs::metadata(&files[0]).unwrap().modified().unwrap(), mtime);
+    assert!(read < built, "读取 {:?}，构建 {:?}", read, built);
+}
+
+#[test]
+fn corrupt_cache_file_is_replaced() {
+    let dir = common::scratch("cache-corrupt");
+    common::write(&dir, "a.txt", "one\ntwo\n");
+    let args = ["--cache-dir", "cache", "-p", "tw.", "-f", "a.txt"];
+    common::pgrep(&dir, &args);
+    let files = cache_files(&dir.join("cache"));
+    std::fs::write(&files[0], "garbage").unwrap();
+
+    let out = common::pgrep(&dir, &args);
+    assert_eq!(common::stdout(&out), "a.txt:2:two\n");
+    assert!(common::stderr(&out).contains("无法使用（缓存文件格式错误"), "{}", common::stderr(&out));
+    let out = common::pgrep(&dir, &args);
+    assert_eq!(common::stdout(&out), "a.txt:2:two\n");
+    assert_eq!(common::stderr(&out), "");
+}
+
+#[test]
+fn no_cache_and_unsupported_patterns_write_nothing() {
+    let dir = common::scratch("cache-skip");
+    common::write(&dir, "a.txt", "a word\n");
+
+    let out = common::pgrep(&dir, &["--cache-dir", "cache", "--no-cache", "-p", "word", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:1:a word\n");
+    assert!(!dir.join("cache").exists());
+
+    // 完整 DFA 不支持 Unicode 单词边界，照常用 regex 搜索
+    let out = common::pgrep(&dir, &["-vv", "--cache-dir", "cache", "-p", r"\bword\b", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:1:a word\n");
+    assert!(common::stderr(&out).contains("不使用 --cache-dir: 模式不能编译成完整 DFA"), "{}", common::stderr(&out));
+    assert!(!dir.join("cache").exists());
+}
@@ -0,0 +1,162 @@
+// 集成测试共用的工具
+//
+// 每个测试在系统临时目录下有自己的空目录，名字里带着测试名和进程号，
+// 并行运行的测试、同时运行的多次 `cargo test` 互不干扰。
+// 命令行工具用 Cargo 为集成测试构建的 `CARGO_BIN_EXE_pgrep` 运行，工作目录是测试目录。
+
+// 每个测试文件只用到其中的一部分
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// 为测试新建一个空目录，同名的旧目录先删掉
+pub fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pgrep-test-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 在目录下写一个文件，需要时先建立上级目录
+pub fn write(dir: &Path, name: &str, content: impl AsRef<[u8]>) -> PathBuf {
+    let p = dir.join(name);
+    if let Some(parent) = p.parent() {
+        std::fs::create_dir_all(parent).unwrap();
+    }
+    std::fs::write(&p, content).unwrap();
+    p
+}
+
+/// 准备在 `dir` 中运行的 pgrep 命令
+pub fn command(dir: &Path, args: &[&str]) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_pgrep"));
+    cmd.args(args).current_dir(dir).env_remove("EDITOR").env_remove("VISUAL");
+    cmd
+}
+
+/// 在 `dir` 中运行 pgrep，等它结束
+pub fn pgrep(dir: &Path, args: &[&str]) -> Output {
+    command(dir, args).output().unwrap()
+}
+
+/// 在 `dir` 中运行 pgrep，它还没开始输出时就关闭标准输出的读取端，相当于 `pgrep ... | head -0`
+pub fn pgrep_closed_stdout(dir: &Path, args: &[&str]) -> Output {
+    let mut child = command(dir, args).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    drop(child.stdout.take());
+    child.wait_with_output().unwrap()
+}
+
+/// 标准输出的全部内容
+pub fn stdout(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stdout).into_owned()
+}
+
+/// 标准错误的全部内容
+pub fn stderr(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stderr).into_owned()
+}
+
+/// 标准输出中的行，排好序，不受目录遍历顺序的影响
+pub fn sorted_lines(out: &Output) -> Vec<String> {
+    let mut lines: Vec<String> = stdout(out).lines().map(str::to_string).collect();
+    lines.sort();
+    lines
+}
+
+/// 伪终端：测试读写主设备，被测的程序把从设备当作控制终端（`/dev/tty`）
+///
+/// 主设备上的输出由一个线程一直读到 `output` 中。从设备一次也没有被打开过时读取主设备立即得到 EIO，
+/// 所以测试自己也一直打开着从设备
+pub struct Pty {
+    master: std::fs::File,
+    slave: std::ffi::CString,
+    _slave_fd: std::fs::File,
+    pub output: std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+}
+
+impl Pty {
+    pub fn open() -> Pty {
+        use std::os::fd::FromRawFd;
+        use std::os::unix::fs::OpenOptionsExt;
+        // SAFETY: 只调用 pty 相关的几个函数，返回值都检查过；ptsname_r 写入的缓冲区足够大
+        unsafe {
+            let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            assert!(fd >= 0, "posix_openpt: {}", std::io::Error::last_os_error());
+            assert_eq!(libc::grantpt(fd), 0);
+            assert_eq!(libc::unlockpt(fd), 0);
+            let mut name = [0 as libc::c_char; 128];
+            assert_eq!(libc::ptsname_r(fd, name.as_mut_ptr(), name.len()), 0);
+            let slave = std::ffi::CStr::from_ptr(name.as_ptr()).to_owned();
+            let master = std::fs::File::from_raw_fd(fd);
+            let slave_fd = std::fs::File::options()
+                .read(true)
+                .write(true)
+                .custom_flags(libc::O_NOCTTY)
+                .open(slave.to_str().unwrap())
+                .unwrap();
+
+            let output = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+            let shared = std::sync::Arc::clone(&output);
+            let mut reader = master.try_clone().unwrap();
+            std::thread::spawn(move || {
+                use std::io::Read;
+                let mut chunk = [0; 4096];
+                while let Ok(n) = reader.read(&mut chunk) {
+                    if n == 0 {
+                        break;
+                    }
+                    shared.lock().unwrap().extend_from_slice(&chunk[..n]);
+                }
+            });
+            Pty { master, slave, _slave_fd: slave_fd, output }
+        }
+    }
+
+    /// 让命令在新的会话中运行，从设备是它的控制终端；`stdio` 中为 true 的标准输入、输出、错误也接到从设备上
+    pub fn attach(&self, cmd: &mut Command, stdio: [bool; 3]) {
+        use std::os::unix::process::CommandExt;
+        let slave = self.slave.clone();
+        // SAFETY: fork 之后只调用异步信号安全的系统调用
+        unsafe {
+            cmd.pre_exec(move || {
+                if libc::setsid() < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                let fd = libc::open(slave.as_ptr(), libc::O_RDWR);
+                if fd < 0 || libc::ioctl(fd, libc::TIOCSCTTY, 0) < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                for (target, on) in stdio.iter().enumerate() {
+                    if *on && libc::dup2(fd, target as libc::c_int) < 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                if fd > 2 {
+                    libc::close(fd);
+                }
+                Ok(())
+            });
+        }
+    }
+
+    /// 在终端上输入
+    pub fn send(&self, s: &str) {
+        use std::io::Write;
+        (&self.master).write_all(s.as_bytes()).unwrap();
+    }
+
+    /// 到目前为止终端上的全部输出
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.output.lock().unwrap()).into_owned()
+    }
+
+    /// 等到终端上的输出中 `needle` 出现了 `count` 次，最多等 10 秒
+    pub fn wait_for(&self, needle: &str, count: usize) {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while self.text().matches(needle).count() < count {
+            assert!(std::time::Instant::now() < deadline, "没有等到第 {} 个 {:?}: {:?}", count, needle, self.text());
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+    }
+}
@@ -0,0 +1,40 @@
+// --generate-completions 输出的补全脚本
+
+mod common;
+
+fn script(shell: &str) -> String {
+    let dir = common::scratch(&format!("completions-{}", shell));
+    let out = common::pgrep(&dir, &["--generate-completions", shell]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    assert_eq!(common::stderr(&out), "");
+    common::stdout(&out)
+}
+
+#[test]
+fn bash_defines_and_registers_the_function() {
+    let s = script("bash");
+    assert!(s.starts_with("# bash completion for pgrep\n_pgrep() {\n"), "{}", s);
+    assert!(s.ends_with("complete -F _pgrep -o bashdefault -o default pgrep\n"), "{}", s);
+    assert!(s.contains("--fixed-strings"), "{}", s);
+    assert!(s.contains("-f|--file)"), "{}", s);
+}
+
+#[test]
+fn zsh_and_fish() {
+    let s = script("zsh");
+    assert!(s.starts_with("#compdef pgrep\n"), "{}", s);
+    assert!(s.contains("_pgrep() {"), "{}", s);
+
+    let s = script("fish");
+    assert!(s.starts_with("# fish completion for pgrep\n"), "{}", s);
+    assert!(s.contains("complete -c pgrep -s f -l file "), "{}", s);
+}
+
+#[test]
+fn completions_output_writes_the_file() {
+    let dir = common::scratch("completions-file");
+    let out = common::pgrep(&dir, &["--generate-completions", "bash", "--completions-output", "pgrep.bash"]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    assert_eq!(common::stdout(&out), "");
+    assert_eq!(std::fs::read_to_string(dir.join("pgrep.bash")).unwrap(), script("bash"));
+}
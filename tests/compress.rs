@@ -0,0 +1,74 @@
+// -z 解压 bzip2、xz、zstd 文件
+//
+// fixtures/compress 中是同一个三行文本分别用三种格式压缩的结果。
+// 这三种格式由 PATH 中的同名程序解压，关闭了对应的 feature 或者机器上没有对应的程序时跳过这个格式的测试。
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/compress")
+}
+
+/// 构建启用了这个格式的 feature，而且 PATH 中有对应的程序
+fn available(enabled: bool, program: &str) -> bool {
+    if !enabled {
+        eprintln!("跳过: 这个构建关闭了 {} feature", program);
+        return false;
+    }
+    let found = Command::new(program).arg("--version").output().is_ok();
+    if !found {
+        eprintln!("跳过: PATH 中没有 {}", program);
+    }
+    found
+}
+
+fn search(name: &str) {
+    let out = common::pgrep(&fixtures(), &["-z", "-p", "needle", "-f", name]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    assert_eq!(common::stdout(&out), format!("{}:2:needle in a compressed file\n", name));
+    assert_eq!(common::stderr(&out), "");
+}
+
+#[test]
+fn bzip2() {
+    if available(cfg!(feature = "bzip2"), "bzip2") {
+        search("plain.txt.bz2");
+    }
+}
+
+#[test]
+fn xz() {
+    if available(cfg!(feature = "xz"), "xz") {
+        search("plain.txt.xz");
+    }
+}
+
+#[test]
+fn zstd() {
+    if available(cfg!(feature = "zstd"), "zstd") {
+        search("plain.txt.zst");
+    }
+}
+
+#[test]
+fn truncated_file_is_reported() {
+    if !available(cfg!(feature = "xz"), "xz") {
+        return;
+    }
+    let dir = common::scratch("compress-truncated");
+    let data = std::fs::read(fixtures().join("plain.txt.xz")).unwrap();
+    common::write(&dir, "cut.xz", &data[..data.len() / 2]);
+    common::write(&dir, "ok.txt", "needle\n");
+    let out = common::pgrep(&dir, &["-z", "-p", "needle", "-f", "cut.xz", "ok.txt"]);
+    assert_eq!(common::stdout(&out), "ok.txt:1:needle\n");
+    assert!(common::stderr(&out).contains("xz -dc"), "{}", common::stderr(&out));
+}
+
+#[test]
+fn compressed_files_are_skipped_without_z() {
+    let out = common::pgrep(&fixtures(), &["-p", "needle", "-f", "plain.txt.xz"]);
+    assert_eq!(common::stdout(&out), "");
+}
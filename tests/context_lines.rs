@@ -0,0 +1,78 @@
+// -A / -B 输出的上下文行，以及 --highlight-context-lines 的样式
+
+mod common;
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    let dir = common::scratch(name);
+    let lines: Vec<String> = (1..=12)
+        .map(|i| if [3, 5, 11].contains(&i) { format!("line {} ERR", i) } else { format!("line {}", i) })
+        .collect();
+    common::write(&dir, "a.txt", &(lines.join("\n") + "\n"));
+    common::write(&dir, "b.txt", "x ERR\ny\n");
+    dir
+}
+
+#[test]
+fn after_and_before_context() {
+    let dir = fixture("context-lines");
+    let out = common::pgrep(&dir, &["-A", "1", "-p", "ERR", "-f", "a.txt", "b.txt"]);
+    assert_eq!(
+        common::stdout(&out),
+        "a.txt:3:line 3 ERR\na.txt-4-line 4\na.txt:5:line 5 ERR\na.txt-6-line 6\n--\n\
+         a.txt:11:line 11 ERR\na.txt-12-line 12\n--\nb.txt:1:x ERR\nb.txt-2-y\n"
+    );
+    // 重叠的上下文只输出一次，窗口在文件开头截断
+    let out = common::pgrep(&dir, &["-B", "3", "-p", "ERR", "-f", "a.txt"]);
+    assert_eq!(
+        common::stdout(&out),
+        "a.txt-1-line 1\na.txt-2-line 2\na.txt:3:line 3 ERR\na.txt-4-line 4\na.txt:5:line 5 ERR\n--\n\
+         a.txt-8-line 8\na.txt-9-line 9\na.txt-10-line 10\na.txt:11:line 11 ERR\n"
+    );
+    let out = common::pgrep(&dir, &["--before-context", "1", "--after-context", "1", "-p", "9", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt-8-line 8\na.txt:9:line 9\na.txt-10-line 10\n");
+
+    let out = common::pgrep(&dir, &["-A", "1", "-c", "-p", "ERR", "-f", "a.txt"]);
+    assert!(common::stderr(&out).contains("cannot be used with"), "{}", common::stderr(&out));
+}
+
+#[test]
+fn filtered_matches_can_be_context() {
+    let dir = fixture("context-filtered");
+    // 被跳过的第一个匹配作为第二个匹配的上文出现
+    let out = common::pgrep(&dir, &["-B", "2", "--skip-matches", "1", "-p", "ERR", "-f", "a.txt"]);
+    assert_eq!(
+        common::stdout(&out),
+        "a.txt-3-line 3 ERR\na.txt-4-line 4\na.txt:5:line 5 ERR\n--\n\
+         a.txt-9-line 9\na.txt-10-line 10\na.txt:11:line 11 ERR\n"
+    );
+    // 被抑制的匹配也是
+    common::write(&dir, "c.txt", "a\nb ERR // pgrep-ignore\nc\nd ERR\n");
+    let out = common::pgrep(&dir, &["-B", "2", "-p", "ERR", "-f", "c.txt"]);
+    assert_eq!(common::stdout(&out), "c.txt-2-b ERR // pgrep-ignore\nc.txt-3-c\nc.txt:4:d ERR\n");
+    assert_eq!(common::stderr(&out), "1 处匹配被抑制\n");
+}
+
+#[test]
+fn context_lines_are_dimmed() {
+    let dir = fixture("context-dim");
+    let args = ["--color", "always", "-B", "1", "-p", "ERR", "-f", "b.txt", "a.txt"];
+    let out = common::pgrep(&dir, &[&args[..], &["--highlight-context-lines"]].concat());
+    let stdout = common::stdout(&out);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "\x1b[35mb.txt\x1b[0m:\x1b[32m1\x1b[0m:x \x1b[1;31mERR\x1b[0m");
+    assert_eq!(lines[1], "--");
+    assert_eq!(lines[2], "\x1b[35ma.txt\x1b[0m-\x1b[32m2\x1b[0m-\x1b[2mline 2\x1b[0m");
+    assert_eq!(lines[3], "\x1b[35ma.txt\x1b[0m:\x1b[32m3\x1b[0m:line 3 \x1b[1;31mERR\x1b[0m");
+
+    // 没有 --highlight-context-lines 时上下文行的内容没有样式，路径和行号照常有颜色
+    let out = common::pgrep(&dir, &args);
+    let stdout = common::stdout(&out);
+    assert!(stdout.contains("\x1b[35ma.txt\x1b[0m-\x1b[32m2\x1b[0m-line 2\n"), "{:?}", stdout);
+    // context 样式可以修改；不使用颜色时不输出转义序列
+    let style = ["--highlight-context-lines", "--match-highlight-style", "context:fg=blue"];
+    let out = common::pgrep(&dir, &[&args[..], &style].concat());
+    assert!(common::stdout(&out).contains("-\x1b[34mline 2\x1b[0m\n"), "{:?}", common::stdout(&out));
+    let plain = ["--color", "never", "--highlight-context-lines", "-B", "1", "-p", "ERR", "-f", "a.txt"];
+    let out = common::pgrep(&dir, &plain);
+    assert!(common::stdout(&out).starts_with("a.txt-2-line 2\na.txt:3:line 3 ERR\n"), "{}", common::stdout(&out));
+}
@@ -0,0 +1,24 @@
+// --context-pattern：只输出之后几行之内出现了另一个模式的匹配
+
+mod common;
+
+#[test]
+fn matches_without_the_context_pattern_are_dropped() {
+    let dir = common::scratch("context-pattern");
+    common::write(&dir, "app.log", "retry 1\nok\nretry 2\nwait\ngave up\nretry 3\ngave up\nretry 4\n");
+    let search = |context: &str, n: &str, extra: &[&str]| {
+        let args = ["-p", "retry", "--context-pattern", context, "--context-after", n, "-f", "app.log"];
+        common::pgrep(&dir, &[extra, &args[..]].concat())
+    };
+    let out = search("gave up", "2", &[]);
+    // 之后的几行只用来筛选，本身不输出
+    assert_eq!(common::stdout(&out), "app.log:3:retry 2\napp.log:6:retry 3\n");
+
+    let out = search("gave up", "1", &["-c"]);
+    assert_eq!(common::stdout(&out), "app.log:1\n");
+    let out = search("nothing", "9", &[]);
+    assert_eq!(common::stdout(&out), "");
+
+    let out = common::pgrep(&dir, &["-p", "retry", "--context-pattern", "gave up", "-f", "app.log"]);
+    assert!(common::stderr(&out).contains("--context-after <N>"), "{}", common::stderr(&out));
+}
@@ -0,0 +1,35 @@
+// --count-unique-matches：同一个匹配文本无论出现多少次只算一个
+
+mod common;
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    let dir = common::scratch(name);
+    common::write(&dir, "a.txt", "foo foo bar\nfoo\nbaz bar\nnone\n");
+    common::write(&dir, "b.txt", "foo\n");
+    common::write(&dir, "c.txt", "fo fooo\n");
+    dir
+}
+
+#[test]
+fn repeated_text_counts_once() {
+    let dir = fixture("unique-repeat");
+    // foo 出现 4 次、bar 出现 2 次，分布在同一行、不同行和不同文件中
+    for flag in [&["--count-unique-matches"][..], &["--count-mode", "unique"]] {
+        let out = common::pgrep(&dir, &[flag, &["-p", "foo|bar", "-f", "a.txt", "b.txt"]].concat());
+        assert_eq!(common::stdout(&out), "2\n", "{:?}", flag);
+    }
+    let out = common::pgrep(&dir, &["-c", "-p", "foo|bar", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:3\n");
+}
+
+#[test]
+fn distinct_texts_of_one_pattern() {
+    let dir = fixture("unique-distinct");
+    let out = common::pgrep(&dir, &["--count-unique-matches", "-p", "fo+", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "1\n");
+    // fo、foo 和 fooo 是不同的文本
+    let out = common::pgrep(&dir, &["--count-unique-matches", "-p", "fo+", "-f", "a.txt", "c.txt"]);
+    assert_eq!(common::stdout(&out), "3\n");
+    let out = common::pgrep(&dir, &["--count-unique-matches", "-p", "nomatch", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "0\n");
+}
@@ -0,0 +1,51 @@
+// --crlf-is-lf：匹配之前把 `\r\n` 和单独的 `\r` 都换成 `\n`，`$` 在 `\r` 之前也能成立
+
+mod common;
+
+fn search(name: &str, file: &str, content: &[u8], args: &[&str]) -> String {
+    let dir = common::scratch(name);
+    common::write(&dir, file, content);
+    let out = common::pgrep(&dir, &[args, &["-f", file]].concat());
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    common::stdout(&out)
+}
+
+#[test]
+fn dollar_matches_before_crlf() {
+    let text = b"one done\r\ntwo\r\nlast done\r\n";
+    let out = search("crlf-dollar", "w.txt", text, &["--crlf-is-lf", "-p", "done$"]);
+    // 输出的行中不再有 `\r`
+    assert_eq!(out, "w.txt:1:one done\nw.txt:3:last done\n");
+    assert_eq!(search("crlf-anchored", "w.txt", text, &["--crlf-is-lf", "-p", "^two$"]), "w.txt:2:two\n");
+    // 只认 `\n` 时 `\r` 留在行中，`$` 不成立
+    assert_eq!(search("crlf-lf-only", "w.txt", text, &["--newline", "lf", "-p", "done$"]), "");
+}
+
+#[test]
+fn lone_cr_starts_a_new_line() {
+    let out = search("crlf-mac", "mac.txt", b"a done\rb\rc done", &["--crlf-is-lf", "-p", "done$"]);
+    assert_eq!(out, "mac.txt:1:a done\nmac.txt:3:c done\n");
+
+    let out = search("crlf-mixed", "mixed.txt", b"a\r\nb x\rc x\nd\r\ne x", &["--crlf-is-lf", "-p", "x$"]);
+    assert_eq!(out, "mixed.txt:2:b x\nmixed.txt:3:c x\nmixed.txt:5:e x\n");
+}
+
+#[test]
+fn only_matching_and_count() {
+    let text = b"one done\r\ntwo\r\nlast done\r\n";
+    assert_eq!(search("crlf-o", "w.txt", text, &["--crlf-is-lf", "-o", "-p", "\\w+$"]), "w.txt:1:done\nw.txt:2:two\nw.txt:3:done\n");
+    assert_eq!(search("crlf-count", "w.txt", text, &["--crlf-is-lf", "-c", "-p", "done$"]), "w.txt:2\n");
+}
+
+#[test]
+fn standard_input() {
+    let dir = common::scratch("crlf-stdin");
+    let mut child = common::command(&dir, &["--crlf-is-lf", "-p", "x$", "-f", "-"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::io::Write::write_all(&mut child.stdin.take().unwrap(), b"x\r\ny\rz x\r\n").unwrap();
+    let out = child.wait_with_output().unwrap();
+    assert_eq!(common::stdout(&out), "-:1:x\n-:3:z x\n");
+}
@@ -0,0 +1,58 @@
+// 以 `-` 开头的模式：`-e`、`--regexp=` 和 `--` 之后的位置参数都按模式处理
+
+mod common;
+
+use std::io::Write;
+use std::process::Stdio;
+
+const TEXT: &str = "a -foo b\nplain\n-v x\n--> y\n- dash\n";
+
+fn grep(name: &str, args: &[&str]) -> String {
+    let dir = common::scratch(name);
+    common::write(&dir, "d.txt", TEXT);
+    let out = common::pgrep(&dir, args);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    assert_eq!(common::stderr(&out), "");
+    common::stdout(&out)
+}
+
+#[test]
+fn regexp_flag_takes_a_dash_value() {
+    assert_eq!(grep("dash-e", &["-e", "-foo", "d.txt"]), "d.txt:1:a -foo b\n");
+    assert_eq!(grep("dash-e-v", &["-e", "-v", "d.txt"]), "d.txt:3:-v x\n");
+}
+
+#[test]
+fn regexp_with_equals() {
+    assert_eq!(grep("dash-eq", &["--regexp=-foo", "d.txt"]), "d.txt:1:a -foo b\n");
+}
+
+#[test]
+fn double_dash_ends_options() {
+    assert_eq!(grep("dash-dd", &["--", "-foo", "d.txt"]), "d.txt:1:a -foo b\n");
+    assert_eq!(grep("dash-arrow", &["--", "-->", "d.txt"]), "d.txt:4:--> y\n");
+}
+
+#[test]
+fn lone_dash_is_a_pattern_first_and_stdin_after() {
+    assert_eq!(grep("dash-lone", &["--", "-", "d.txt"]), "d.txt:1:a -foo b\nd.txt:3:-v x\nd.txt:4:--> y\nd.txt:5:- dash\n");
+
+    let dir = common::scratch("dash-stdin");
+    let mut child = common::command(&dir, &["-p", "x", "-f", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(TEXT.as_bytes()).unwrap();
+    let out = child.wait_with_output().unwrap();
+    assert_eq!(common::stdout(&out), "-:3:-v x\n");
+}
+
+#[test]
+fn missing_pattern_suggests_the_idioms() {
+    let dir = common::scratch("dash-missing");
+    let out = common::pgrep(&dir, &[]);
+    let stderr = common::stderr(&out);
+    assert!(stderr.contains("没有提供搜索模式"), "{}", stderr);
+    assert!(stderr.contains("`-e -foo`") && stderr.contains("`pgrep -- -foo PATH`"), "{}", stderr);
+}
@@ -0,0 +1,97 @@
+// --debug-skip：每个没有被搜索的路径在标准错误中占一行，说明排除它的规则
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+/// 每种跳过的规则都有一个对应的文件：
+/// * bin.dat - 第 1 字节是 NUL
+/// * plain.txt.xz - 压缩文件，没有 -z
+/// * latin1.txt - 第 2 字节开始不是合法的 UTF-8
+/// * notes.md - 被 --type-not md 排除
+/// * link.txt - 符号链接，被 --type-filter regular 排除
+/// * pipe - 命名管道
+fn tree(name: &str) -> PathBuf {
+    let dir = common::scratch(name);
+    common::write(&dir, "t/a.txt", "x\n");
+    common::write(&dir, "t/sub/s.txt", "x\n");
+    common::write(&dir, "t/bin.dat", "x\0y\n");
+    common::write(&dir, "t/latin1.txt", b"x \xe9\n");
+    common::write(&dir, "t/notes.md", "x\n");
+    let xz = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/compress/plain.txt.xz");
+    std::fs::copy(xz, dir.join("t/plain.txt.xz")).unwrap();
+    std::os::unix::fs::symlink("a.txt", dir.join("t/link.txt")).unwrap();
+    let pipe = std::ffi::CString::new(dir.join("t/pipe").to_str().unwrap()).unwrap();
+    // SAFETY: 路径是以 NUL 结尾的 C 字符串
+    assert_eq!(unsafe { libc::mkfifo(pipe.as_ptr(), 0o644) }, 0);
+    dir
+}
+
+/// 标准错误中的跳过记录，按路径排序
+fn skipped(out: &std::process::Output) -> Vec<String> {
+    let mut lines: Vec<String> = common::stderr(out).lines().map(str::to_string).collect();
+    lines.sort();
+    lines
+}
+
+#[test]
+fn each_rule_names_its_reason() {
+    let dir = tree("skip-rules");
+    let out = common::pgrep(&dir, &["--debug-skip", "-p", "x", "-f", "t", "--type-filter", "regular", "--type-not", "md"]);
+    assert_eq!(common::sorted_lines(&out), ["t/a.txt:1:x", "t/sub/s.txt:1:x"]);
+    assert_eq!(
+        skipped(&out),
+        [
+            "跳过 t/bin.dat: 看起来是二进制文件（第 1 字节是 NUL）",
+            "跳过 t/latin1.txt: 不是合法的 UTF-8（第 2 字节）",
+            "跳过 t/link.txt: --type-filter regular 不搜索符号链接",
+            "跳过 t/notes.md: --type / --type-not 没有选中这种文件",
+            "跳过 t/pipe: 既不是普通文件也不是目录",
+            "跳过 t/plain.txt.xz: xz 压缩文件，使用 -z 搜索其中的内容",
+        ]
+    );
+}
+
+#[test]
+fn glob_limits_the_report() {
+    let dir = tree("skip-glob");
+    let out = common::pgrep(&dir, &["--debug-skip=*.dat", "-p", "x", "-f", "t"]);
+    assert_eq!(skipped(&out), ["跳过 t/bin.dat: 看起来是二进制文件（第 1 字节是 NUL）"]);
+}
+
+#[test]
+fn silent_without_the_flag() {
+    let dir = tree("skip-quiet");
+    let out = common::pgrep(&dir, &["-p", "x", "-f", "t"]);
+    assert_eq!(common::stderr(&out), "");
+}
+
+#[test]
+fn symlink_filter_and_rules() {
+    let dir = tree("skip-symlink");
+    let out = common::pgrep(&dir, &["--debug-skip=t/*.txt", "-p", "x", "-f", "t", "--type-filter", "symlink"]);
+    assert_eq!(common::stdout(&out), "t/link.txt:1:x\n");
+    assert_eq!(
+        skipped(&out),
+        ["跳过 t/a.txt: --type-filter symlink 不搜索普通文件", "跳过 t/latin1.txt: --type-filter symlink 不搜索普通文件"]
+    );
+
+    common::write(&dir, "r.toml", "[[rule]]\nid = \"md\"\npaths = [\"**/*.md\"]\npattern = \"x\"\n");
+    let out = common::pgrep(&dir, &["--debug-skip=t/sub/*", "--rules", "r.toml", "-f", "t"]);
+    assert_eq!(skipped(&out), ["跳过 t/sub/s.txt: 没有适用于这个路径的规则"]);
+}
+
+#[test]
+fn checkpoint_and_archive_members() {
+    let dir = common::scratch("skip-checkpoint");
+    common::write(&dir, "c/d1/a.txt", "x\n");
+    common::write(&dir, "c/d2/b.txt", "x\n");
+    common::write(&dir, "ck", "c/d1\n");
+    let out = common::pgrep(&dir, &["--debug-skip", "-p", "x", "-f", "c", "--checkpoint", "ck", "--resume"]);
+    assert_eq!(common::stdout(&out), "c/d2/b.txt:1:x\n");
+    assert_eq!(skipped(&out), ["跳过 c/d1: 检查点中记录为已完成"]);
+
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/archive");
+    let out = common::pgrep(&fixtures, &["--debug-skip", "--archives", "--max-member-size", "40", "-p", ".", "-f", "snap.tgz"]);
+    assert_eq!(skipped(&out), ["跳过 snap.tgz!docs/readme.txt: 解压后有 46 字节，超过了 --max-member-size 的 40 字节"]);
+}
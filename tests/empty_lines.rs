@@ -0,0 +1,49 @@
+// --match-empty-lines 和 --skip-empty-lines：空行在文件开头、中间和结尾
+
+mod common;
+
+/// 第 1、2 行在开头，第 4 行在中间，第 6 行只有空白，第 7 行在结尾
+const TEXT: &str = "\n\nfoo\n\nbar\n  \n\n";
+
+fn search(name: &str, args: &[&str]) -> std::process::Output {
+    let dir = common::scratch(name);
+    common::write(&dir, "e.txt", TEXT);
+    common::write(&dir, "last.txt", "a\n\nb");
+    common::pgrep(&dir, args)
+}
+
+#[test]
+fn reports_every_empty_line() {
+    let out = search("empty-all", &["--match-empty-lines", "-f", "e.txt"]);
+    assert_eq!(common::stdout(&out), "e.txt:1:\ne.txt:2:\ne.txt:4:\ne.txt:7:\n");
+    assert_eq!(common::stderr(&out), "");
+    // 最后一行没有换行符时不算多出一个空行
+    let out = search("empty-last", &["--blank-lines", "last.txt"]);
+    assert_eq!(common::stdout(&out), "last.txt:2:\n");
+}
+
+#[test]
+fn combined_with_a_pattern_is_either() {
+    let out = search("empty-or", &["--match-empty-lines", "-p", "foo", "-f", "e.txt"]);
+    assert_eq!(common::stdout(&out), "e.txt:1:\ne.txt:2:\ne.txt:3:foo\ne.txt:4:\ne.txt:7:\n");
+    let out = search("empty-count", &["--match-empty-lines", "-c", "-f", "e.txt"]);
+    assert_eq!(common::stdout(&out), "e.txt:4\n");
+}
+
+#[test]
+fn match_all_warns() {
+    let out = search("empty-match-all", &["--match-empty-lines", "--match-all", "-p", "foo", "-f", "e.txt"]);
+    assert_eq!(common::stdout(&out), "");
+    assert!(common::stderr(&out).contains("--match-empty-lines 和 --match-all 同时使用时"), "{}", common::stderr(&out));
+}
+
+#[test]
+fn skip_empty_lines_before_matching() {
+    // 空行和只有空白的第 6 行都不参与匹配
+    let out = search("empty-skip", &["--skip-empty-lines", "-p", "^\\s*$", "-f", "e.txt"]);
+    assert_eq!(common::stdout(&out), "");
+    let out = search("empty-skip-count", &["--skip-empty-lines", "-c", "-p", ".", "-f", "e.txt"]);
+    assert_eq!(common::stdout(&out), "e.txt:2\n");
+    let out = search("empty-skip-conflict", &["--skip-empty-lines", "--match-empty-lines", "-f", "e.txt"]);
+    assert!(!out.status.success());
+}
@@ -0,0 +1,78 @@
+// --exec、--exec-file、--exec-batch：用一个记录参数的脚本作为命令
+//
+// rec.sh 把收到的参数用 `|` 连成一行追加到 log，fail.sh 记录之后以状态 3 退出。
+
+mod common;
+
+fn tree(name: &str) -> std::path::PathBuf {
+    let dir = common::scratch(name);
+    common::write(&dir, "rec.sh", "printf '%s|' \"$@\" >> log\necho >> log\n");
+    common::write(&dir, "fail.sh", "printf '%s|' \"$@\" >> log\necho >> log\nexit 3\n");
+    common::write(&dir, "a.txt", "x it's \"q\"\nno\néé x2\n");
+    common::write(&dir, "b.txt", "x\n");
+    common::write(&dir, "none.txt", "no\n");
+    dir
+}
+
+fn log(dir: &std::path::Path) -> String {
+    std::fs::read_to_string(dir.join("log")).unwrap_or_default()
+}
+
+#[test]
+fn exec_substitutes_each_placeholder_into_one_argument() {
+    let dir = tree("exec-each");
+    let out = common::pgrep(&dir, &["-p", "x\\d?", "-f", "a.txt", "--exec", "sh rec.sh {path} {line} {column} {text} {match}"]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    assert_eq!(common::stdout(&out), "a.txt:1:x it's \"q\"\na.txt:3:éé x2\n");
+    assert_eq!(log(&dir), "a.txt|1|1|x it's \"q\"|x|\na.txt|3|4|éé x2|x2|\n");
+}
+
+#[test]
+fn exec_without_placeholders_appends_the_path() {
+    let dir = tree("exec-append");
+    common::pgrep(&dir, &["-p", "x", "-f", "a.txt", "--exec", "sh rec.sh -n"]);
+    assert_eq!(log(&dir), "-n|a.txt|\n-n|a.txt|\n");
+}
+
+#[test]
+fn exec_file_runs_once_per_matching_file() {
+    let dir = tree("exec-file");
+    let out = common::pgrep(&dir, &["-p", "x", "-f", "a.txt", "none.txt", "b.txt", "--exec-file", "sh rec.sh {}"]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    assert_eq!(log(&dir), "a.txt|\nb.txt|\n");
+}
+
+#[test]
+fn exec_batch_runs_once_with_every_path() {
+    let dir = tree("exec-batch");
+    common::pgrep(&dir, &["-p", "x", "-f", "a.txt", "none.txt", "b.txt", "--exec-batch", "sh rec.sh -a {} -z"]);
+    assert_eq!(log(&dir), "-a|a.txt|b.txt|-z|\n");
+
+    // 没有匹配的文件时不执行
+    let dir = tree("exec-batch-none");
+    common::pgrep(&dir, &["-p", "x", "-f", "none.txt", "--exec-batch", "sh rec.sh"]);
+    assert!(!dir.join("log").exists());
+}
+
+#[test]
+fn failures_are_reported_and_the_search_continues() {
+    let dir = tree("exec-fail");
+    let out = common::pgrep(&dir, &["-p", "x", "-f", "a.txt", "b.txt", "--exec-file", "sh fail.sh"]);
+    assert_eq!(log(&dir), "a.txt|\nb.txt|\n");
+    let stderr = common::stderr(&out);
+    assert_eq!(stderr.matches("命令 `sh fail.sh` 执行失败: exit status: 3").count(), 2, "{}", stderr);
+}
+
+#[test]
+fn halt_on_error_stops_at_the_first_failure() {
+    for (flag, expect) in [("--exec", "a.txt|\n"), ("--exec-file", "a.txt|\n"), ("--exec-batch", "a.txt|b.txt|\n")] {
+        let dir = tree(&format!("exec-halt{}", flag));
+        let out = common::pgrep(&dir, &["-p", "x", "-f", "a.txt", "b.txt", flag, "sh fail.sh", "--exec-halt-on-error"]);
+        assert_eq!(log(&dir), expect, "{}", flag);
+        let stderr = common::stderr(&out);
+        assert!(stderr.contains("停止搜索: 外部命令失败: 命令 `sh fail.sh` 执行失败: exit status: 3"), "{}", stderr);
+        if flag != "--exec-batch" {
+            assert!(!common::stdout(&out).contains("b.txt"), "{}", common::stdout(&out));
+        }
+    }
+}
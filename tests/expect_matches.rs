@@ -0,0 +1,48 @@
+// --write-replace --expect-matches：数量不符时不修改任何文件，也不输出修改的汇总
+
+mod common;
+
+/// 目录中的文件名和内容，按文件名排序
+fn snapshot(dir: &std::path::Path) -> Vec<(String, Vec<u8>, std::time::SystemTime)> {
+    let mut v: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| {
+            let e = e.unwrap();
+            let md = e.metadata().unwrap();
+            (e.file_name().to_string_lossy().into_owned(), std::fs::read(e.path()).unwrap(), md.modified().unwrap())
+        })
+        .collect();
+    v.sort();
+    v
+}
+
+#[test]
+fn mismatch_touches_no_file() {
+    let dir = common::scratch("expect-mismatch");
+    common::write(&dir, "a.txt", "foo\nfoo bar\n");
+    common::write(&dir, "b.txt", "foo\n");
+    let before = snapshot(&dir);
+
+    let out = common::pgrep(&dir, &["-p", "foo", "--write-replace=baz", "--expect-matches", "2", "-f", "a.txt", "b.txt"]);
+    assert_eq!(common::stdout(&out), "");
+    assert_eq!(
+        common::stderr(&out),
+        "程序执行时发生错误: --expect-matches: 预期 2 处替换，实际有 3 处（2 个文件），没有修改任何文件\n"
+    );
+    assert_eq!(out.status.code(), Some(1));
+    // 内容、修改时间都没有变，也没有留下临时文件
+    assert_eq!(snapshot(&dir), before);
+}
+
+#[test]
+fn matching_count_writes_every_file() {
+    let dir = common::scratch("expect-match");
+    common::write(&dir, "a.txt", "foo\nfoo bar\n");
+    common::write(&dir, "b.txt", "foo\n");
+
+    let out = common::pgrep(&dir, &["-p", "foo", "--write-replace=baz", "--expect-matches", "3", "-f", "a.txt", "b.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt: 2 处替换\nb.txt: 1 处替换\n共修改 2 个文件，3 处替换\n");
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt")).unwrap(), "baz\nbaz bar\n");
+    assert_eq!(std::fs::read_to_string(dir.join("b.txt")).unwrap(), "baz\n");
+}
@@ -0,0 +1,82 @@
+// --field-separator：按制表符分成字段，只用一部分字段匹配
+
+mod common;
+
+/// 第 3、4 行字段不够，第 6 行前三个字段是空的
+const TSV: &str = "10:01\tGET\t/index\t200\n10:02\tPOST\t/login\t500\n10:03\tGET\n10:04\n\
+                   10:05\tGET\t/err500\t404\n\t\t\t503\n";
+
+/// 用制表符分隔字段搜索 a.tsv，返回输出的行号
+fn lines(dir: &std::path::Path, pattern: &str, extra: &[&str]) -> Vec<usize> {
+    let args = [&["-p", pattern, "--field-separator", "\t", "-f", "a.tsv"][..], extra].concat();
+    let out = common::pgrep(dir, &args);
+    assert_eq!(common::stderr(&out), "", "{:?}", args);
+    common::stdout(&out)
+        .lines()
+        .map(|l| l.split(':').nth(1).unwrap().parse().unwrap())
+        .collect()
+}
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    let dir = common::scratch(name);
+    common::write(&dir, "a.tsv", TSV);
+    dir
+}
+
+#[test]
+fn any_field_matches() {
+    let dir = fixture("fields-any");
+    assert_eq!(lines(&dir, "500", &[]), [2, 5]);
+    // ^ 和 $ 是字段的开头和结尾
+    assert_eq!(lines(&dir, "^500$", &[]), [2]);
+    assert_eq!(lines(&dir, "^GET$", &[]), [1, 3, 5]);
+    // 分隔符不属于任何字段
+    assert_eq!(lines(&dir, "GET\t/", &[]), Vec::<usize>::new());
+}
+
+#[test]
+fn field_number() {
+    let dir = fixture("fields-number");
+    assert_eq!(lines(&dir, "^GET$", &["--field-number", "2"]), [1, 3, 5]);
+    assert_eq!(lines(&dir, "^GET$", &["--field-number", "3"]), Vec::<usize>::new());
+    assert_eq!(lines(&dir, "^5..$", &["--field-number", "4"]), [2, 6]);
+    // 相邻的两个分隔符之间是一个空字段
+    assert_eq!(lines(&dir, "^$", &["--field-number", "1"]), [6]);
+
+    let args = ["-p", "^5..$", "--field-separator", "\t", "--field-number", "4", "--only-field", "-f", "a.tsv"];
+    let out = common::pgrep(&dir, &args);
+    assert_eq!(common::stdout(&out), "a.tsv:2:500\na.tsv:6:503\n");
+}
+
+#[test]
+fn field_range() {
+    let dir = fixture("fields-range");
+    // 第 5 行的 /err500 在范围之内，第 1 列的时间不在
+    assert_eq!(lines(&dir, "500", &["--field-range", "3-4"]), [2, 5]);
+    assert_eq!(lines(&dir, "^10:0", &["--field-range", "2-"]), Vec::<usize>::new());
+    assert_eq!(lines(&dir, ".", &["--field-range", "3-"]), [1, 2, 5, 6]);
+}
+
+#[test]
+fn lines_with_too_few_fields() {
+    let dir = fixture("fields-short");
+    // 第 3 行只有两个字段：只检查存在的字段；第 4 行一个字段也不在范围之内
+    assert_eq!(lines(&dir, "GET", &["--field-range", "2-3"]), [1, 3, 5]);
+    assert_eq!(lines(&dir, ".", &["--field-range", "2-3"]), [1, 2, 3, 5]);
+    assert_eq!(lines(&dir, ".", &["--field-number", "4"]), [1, 2, 5, 6]);
+}
+
+#[test]
+fn malformed_ranges_are_rejected() {
+    let dir = fixture("fields-bad");
+    for r in ["3-2", "0-2", "abc"] {
+        let out = common::pgrep(&dir, &["-p", "x", "--field-separator", "\t", "--field-range", r, "-f", "a.tsv"]);
+        let stderr = common::stderr(&out);
+        assert!(stderr.starts_with(&format!("程序执行时发生错误: 无法解析 --field-range '{}'：", r)), "{}", stderr);
+        assert!(stderr.ends_with("应该写成 START-END 或 START-，字段从 1 开始，START 不能大于 END\n"), "{}", stderr);
+    }
+    let out = common::pgrep(&dir, &["-p", "x", "--field-separator", "\t", "--field-number", "0", "-f", "a.tsv"]);
+    assert!(common::stderr(&out).contains("invalid value '0' for '--field-number <N>'"), "{}", common::stderr(&out));
+    let out = common::pgrep(&dir, &["-p", "x", "--field-number", "2", "-f", "a.tsv"]);
+    assert!(common::stderr(&out).contains("--field-separator <CHAR>"), "{}", common::stderr(&out));
+}
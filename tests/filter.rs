@@ -0,0 +1,27 @@
+// --filter 没有匹配时输出与输入逐字节相同
+
+mod common;
+
+use std::io::Write;
+use std::process::Stdio;
+
+/// 把 `input` 交给 pgrep 的标准输入，返回标准输出和退出状态
+fn filter(input: &[u8], args: &[&str]) -> (Vec<u8>, Option<i32>) {
+    let dir = common::scratch("filter");
+    let mut child = common::command(&dir, args).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let out = child.wait_with_output().unwrap();
+    (out.stdout, out.status.code())
+}
+
+#[test]
+fn no_match_round_trips_byte_for_byte() {
+    let input: &[u8] = b"plain\n\ncrlf\r\n\r\nlone\rcr\n  trailing \t \n\xff\xfe bytes\n\xe4\xbd\xa0\xe5\xa5\xbd\nno eol";
+    // 和 grep 一样，一行也没有替换时以状态 1 退出
+    assert_eq!(filter(input, &["-p", "needle", "--replace", "x", "--filter"]), (input.to_vec(), Some(1)));
+    assert_eq!(filter(b"", &["-p", "needle", "--replace", "x", "--filter"]), (Vec::new(), Some(1)));
+
+    let (out, code) = filter(input, &["-p", "eol", "--replace", "EOL", "--filter"]);
+    assert_eq!(out, [&input[..input.len() - 3], b"EOL"].concat());
+    assert_eq!(code, Some(0));
+}
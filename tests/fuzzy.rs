@@ -0,0 +1,23 @@
+// --fuzzy 和 --fuzzy-cpu-limit
+
+mod common;
+
+#[test]
+fn lines_with_known_typos() {
+    let dir = common::scratch("fuzzy-typos");
+    common::write(&dir, "fz.txt", "please recive this\nrecieve\nreceive ok\nunrelated\n");
+    let out = common::pgrep(&dir, &["--fuzzy", "1", "-p", "receive", "-f", "fz.txt"]);
+    assert_eq!(common::stdout(&out), "fz.txt:1:please recive this\nfz.txt:3:receive ok\n");
+
+    let out = common::pgrep(&dir, &["--fuzzy", "2", "-p", "receive", "-f", "fz.txt"]);
+    assert_eq!(common::stdout(&out).lines().count(), 3);
+}
+
+#[test]
+fn cpu_limit_stops_the_whole_search() {
+    let dir = common::scratch("fuzzy-limit");
+    common::write(&dir, "fz.txt", "recive\n");
+    let out = common::pgrep(&dir, &["--fuzzy", "1", "--fuzzy-cpu-limit", "0", "-p", "receive", "-f", "fz.txt"]);
+    assert_eq!(common::stdout(&out), "");
+    assert!(common::stderr(&out).contains("模糊匹配超过了 0 秒的时间限制"), "{}", common::stderr(&out));
+}
@@ -0,0 +1,73 @@
+// --error-if-matches 和 --error-if-no-matches 的退出状态 3 和说明
+
+mod common;
+
+/// 在测试 `name` 自己的目录中运行，返回标准输出、标准错误和退出状态
+fn gate(name: &str, args: &[&str]) -> (String, String, Option<i32>) {
+    let dir = common::scratch(name);
+    common::write(&dir, "a.txt", "TODO a\nok\nTODO b\n");
+    common::write(&dir, "none.txt", "nothing here\n");
+    common::write(&dir, "many.txt", "TODO\n".repeat(7));
+    let out = common::pgrep(&dir, args);
+    (common::stdout(&out), common::stderr(&out), out.status.code())
+}
+
+#[test]
+fn error_if_matches_fails_above_the_threshold() {
+    let (stdout, stderr, code) = gate("gate-max-0", &["--error-if-matches", "-p", "TODO", "-f", "a.txt"]);
+    // 结果照常输出
+    assert_eq!(stdout, "a.txt:1:TODO a\na.txt:3:TODO b\n");
+    assert_eq!(stderr, "--error-if-matches: 找到了 2 处匹配，最多允许 0 处，匹配的位置:\n  a.txt:1\n  a.txt:3\n");
+    assert_eq!(code, Some(3));
+
+    let (_, stderr, code) = gate("gate-max-1", &["--error-if-matches=1", "-p", "TODO", "-f", "a.txt"]);
+    assert!(stderr.starts_with("--error-if-matches: 找到了 2 处匹配，最多允许 1 处"), "{}", stderr);
+    assert_eq!(code, Some(3));
+
+    // 只列出前 5 处
+    let (_, stderr, code) = gate("gate-max-3", &["--error-if-matches=3", "-p", "TODO", "-f", "many.txt"]);
+    assert_eq!(
+        stderr,
+        "--error-if-matches: 找到了 7 处匹配，最多允许 3 处，前几处匹配:\n  \
+         many.txt:1\n  many.txt:2\n  many.txt:3\n  many.txt:4\n  many.txt:5\n"
+    );
+    assert_eq!(code, Some(3));
+}
+
+#[test]
+fn error_if_matches_passes_at_or_below_the_threshold() {
+    let (stdout, stderr, code) = gate("gate-max-2", &["--error-if-matches=2", "-p", "TODO", "-f", "a.txt"]);
+    assert_eq!(stdout, "a.txt:1:TODO a\na.txt:3:TODO b\n");
+    assert_eq!(stderr, "");
+    assert_eq!(code, Some(0));
+
+    let (stdout, stderr, code) = gate("gate-max-0-none", &["--error-if-matches", "-p", "TODO", "-f", "none.txt"]);
+    assert_eq!((stdout.as_str(), stderr.as_str(), code), ("", "", Some(0)));
+
+    // = 之后才是 N，分开写的 TODO 仍然是位置参数中的模式
+    let (stdout, _, code) = gate("gate-pattern", &["--error-if-matches", "TODO", "a.txt"]);
+    assert_eq!(stdout, "a.txt:1:TODO a\na.txt:3:TODO b\n");
+    assert_eq!(code, Some(3));
+}
+
+#[test]
+fn error_if_no_matches_in_both_directions() {
+    let (stdout, stderr, code) = gate("gate-required-none", &["--error-if-no-matches", "-p", "TODO", "-f", "none.txt"]);
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "--error-if-no-matches: 没有找到任何匹配\n");
+    assert_eq!(code, Some(3));
+
+    let (stdout, stderr, code) = gate("gate-required", &["--error-if-no-matches", "-p", "TODO", "-f", "a.txt"]);
+    assert_eq!(stdout, "a.txt:1:TODO a\na.txt:3:TODO b\n");
+    assert_eq!((stderr.as_str(), code), ("", Some(0)));
+}
+
+#[test]
+fn both_gates_require_a_range() {
+    let range = ["--error-if-no-matches", "--error-if-matches=2", "-p", "TODO", "-f"];
+    assert_eq!(gate("gate-range-a", &[&range[..], &["a.txt"]].concat()).2, Some(0));
+    assert_eq!(gate("gate-range-none", &[&range[..], &["none.txt"]].concat()).2, Some(3));
+    let (_, stderr, code) = gate("gate-range-many", &[&range[..], &["many.txt"]].concat());
+    assert!(stderr.starts_with("--error-if-matches: 找到了 7 处匹配，最多允许 2 处"), "{}", stderr);
+    assert_eq!(code, Some(3));
+}
@@ -0,0 +1,126 @@
+// --staged、--changed、--git-tracked 和 --git-untracked 在临时仓库中的测试
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// 在 `dir` 中运行 git，失败时让测试失败；不读取用户和系统的 git 配置
+fn git(dir: &Path, args: &[&str]) {
+    let out = Command::new("git")
+        .args(["-c", "user.name=pgrep", "-c", "user.email=pgrep@example.com", "-c", "commit.gpgsign=false"])
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&out.stderr));
+}
+
+/// 新建一个仓库，提交 `files` 中的文件；没有 git 时返回 None，测试跳过
+fn repo(name: &str, files: &[(&str, &str)]) -> Option<PathBuf> {
+    if Command::new("git").arg("--version").output().is_err() {
+        eprintln!("跳过: PATH 中没有 git");
+        return None;
+    }
+    let dir = common::scratch(name);
+    git(&dir, &["init", "-q"]);
+    for (f, content) in files {
+        common::write(&dir, f, content);
+    }
+    git(&dir, &["add", "-A"]);
+    git(&dir, &["commit", "-qm", "init"]);
+    Some(dir)
+}
+
+#[test]
+fn staged_searches_only_the_staged_file() {
+    let Some(dir) = repo("git-staged", &[("a.txt", "old\n"), ("b.txt", "old\n"), ("c.txt", "x\n")]) else {
+        return;
+    };
+    common::write(&dir, "a.txt", "x staged\n");
+    common::write(&dir, "b.txt", "x not staged\n");
+    git(&dir, &["add", "a.txt"]);
+
+    let out = common::pgrep(&dir, &["--staged", "-p", "x"]);
+    assert_eq!(common::stdout(&out), "./a.txt:1:x staged\n");
+    assert_eq!(common::stderr(&out), "");
+
+    // --changed 还包括没有暂存的修改，没有修改的 c.txt 仍然不搜索
+    let out = common::pgrep(&dir, &["--changed", "-p", "x"]);
+    assert_eq!(common::sorted_lines(&out), ["./a.txt:1:x staged", "./b.txt:1:x not staged"]);
+}
+
+#[test]
+fn staged_deleted_and_renamed_files() {
+    let Some(dir) = repo("git-renamed", &[("gone.txt", "x\n"), ("old.txt", "x moved\n"), ("keep.txt", "x\n")]) else {
+        return;
+    };
+    git(&dir, &["rm", "-q", "gone.txt"]);
+    git(&dir, &["mv", "old.txt", "new.txt"]);
+
+    // 删除的文件被跳过，重命名的文件使用新的路径
+    let out = common::pgrep(&dir, &["--staged", "-p", "x"]);
+    assert_eq!(common::stdout(&out), "./new.txt:1:x moved\n");
+    assert_eq!(common::stderr(&out), "");
+
+    // 只删除了文件时没有可以搜索的文件
+    git(&dir, &["commit", "-qm", "rename"]);
+    git(&dir, &["rm", "-q", "keep.txt"]);
+    let out = common::pgrep(&dir, &["--staged", "-p", "x"]);
+    assert_eq!(common::stdout(&out), "");
+}
+
+#[test]
+fn staged_in_a_subdirectory() {
+    let Some(dir) = repo("git-subdir", &[("src/a.txt", "old\n"), ("top.txt", "old\n")]) else {
+        return;
+    };
+    common::write(&dir, "src/a.txt", "x\n");
+    common::write(&dir, "top.txt", "x\n");
+    git(&dir, &["add", "-A"]);
+
+    let out = common::pgrep(&dir.join("src"), &["--staged", "-p", "x"]);
+    assert_eq!(common::stdout(&out), "./a.txt:1:x\n");
+    let out = common::pgrep(&dir, &["--staged", "-p", "x", "-f", "src/a.txt"]);
+    assert_eq!(common::stdout(&out), "src/a.txt:1:x\n");
+}
+
+#[test]
+fn untracked_files_are_added() {
+    let Some(dir) = repo("git-untracked", &[("tracked.txt", "x\n"), (".gitignore", "*.log\n")]) else {
+        return;
+    };
+    common::write(&dir, "new.txt", "x new\n");
+    common::write(&dir, "ignored.log", "x ignored\n");
+
+    let out = common::pgrep(&dir, &["--git-tracked", "-p", "x", "-f", "."]);
+    assert_eq!(common::stdout(&out), "./tracked.txt:1:x\n");
+
+    // 新文件被加上，被 .gitignore 忽略的文件仍然不搜索
+    let out = common::pgrep(&dir, &["--git-tracked", "--git-untracked", "-p", "x", "-f", "."]);
+    assert_eq!(common::sorted_lines(&out), ["./new.txt:1:x new", "./tracked.txt:1:x"]);
+
+    let out = common::pgrep(&dir, &["--staged", "--git-untracked", "-p", "x"]);
+    assert_eq!(common::stdout(&out), "./new.txt:1:x new\n");
+}
+
+#[test]
+fn outside_a_repository_everything_is_searched() {
+    let dir = common::scratch("git-none");
+    common::write(&dir, "a.txt", "x\n");
+    common::write(&dir, "b.log", "x\n");
+
+    let out = common::pgrep(&dir, &["--git-tracked", "-p", "x", "-f", "."]);
+    assert_eq!(common::sorted_lines(&out), ["./a.txt:1:x", "./b.log:1:x"]);
+    assert_eq!(common::stderr(&out), "警告: --git-tracked: . 不在 git 仓库中，搜索其中所有的文件\n");
+
+    let out = common::pgrep(&dir, &["--staged", "--git-untracked", "-p", "x", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:1:x\n");
+    assert_eq!(common::stderr(&out), "警告: --staged: a.txt 不在 git 仓库中，搜索其中所有的文件\n");
+
+    let out = common::pgrep(&dir, &["--git-tracked", "--no-warnings", "-p", "x", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:1:x\n");
+    assert_eq!(common::stderr(&out), "");
+}
@@ -0,0 +1,46 @@
+// --group-by directory：目录按字典序分节，直接在搜索目录下的文件在 `.` 一节中
+
+mod common;
+
+fn tree(name: &str) -> std::path::PathBuf {
+    let dir = common::scratch(name);
+    common::write(&dir, "t/r.txt", "x\n");
+    common::write(&dir, "t/b/z/q.txt", "y\nx\n");
+    common::write(&dir, "t/a/p.txt", "x\nx\n");
+    common::write(&dir, "t/b/m.txt", "x\n");
+    common::write(&dir, "t/c/none.txt", "y\n");
+    dir
+}
+
+#[test]
+fn directories_in_order_relative_to_the_root() {
+    let dir = tree("group-dir");
+    let out = common::pgrep(&dir, &["-p", "x", "-f", "t", "--group-by", "directory"]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    assert_eq!(
+        common::stdout(&out),
+        "目录 . (1 行):\n  r.txt:1: x\n\
+         目录 a (2 行):\n  p.txt:1: x\n  p.txt:2: x\n\
+         目录 b (1 行):\n  m.txt:1: x\n\
+         目录 b/z (1 行):\n  q.txt:2: x\n"
+    );
+}
+
+#[test]
+fn several_paths_group_by_the_printed_directory() {
+    let dir = tree("group-paths");
+    let out = common::pgrep(&dir, &["-p", "x", "-f", "t/a", "t/r.txt", "--group-by", "directory"]);
+    assert_eq!(common::stdout(&out), "目录 t (1 行):\n  r.txt:1: x\n目录 t/a (2 行):\n  p.txt:1: x\n  p.txt:2: x\n");
+}
+
+#[test]
+fn heading_is_styled_with_color() {
+    let dir = tree("group-color");
+    let out = common::pgrep(&dir, &["-p", "x", "-f", "t/b", "--group-by", "directory", "--color", "always"]);
+    let stdout = common::stdout(&out);
+    assert!(stdout.starts_with("目录 \x1b[1;35m.\x1b[0m (1 行):\n"), "{:?}", stdout);
+    assert!(stdout.contains("目录 \x1b[1;35mz\x1b[0m (1 行):\n"), "{:?}", stdout);
+
+    let out = common::pgrep(&dir, &["-p", "x", "-f", "t/b", "--group-by", "directory", "--color", "always", "--no-bold"]);
+    assert!(common::stdout(&out).starts_with("目录 \x1b[35m.\x1b[0m (1 行):\n"), "{:?}", common::stdout(&out));
+}
@@ -0,0 +1,77 @@
+// -z 解压 gzip 文件：测试中生成一个很大的 .gz，匹配的行在解压后的深处
+
+mod common;
+
+/// gzip 尾部使用的 CRC-32（IEEE 802.3，反射多项式 0xEDB88320）
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// 没有文件名等可选字段的单个 gzip 成员
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255];
+    out.extend(miniz_oxide::deflate::compress_to_vec(data, 6));
+    out.extend(crc32(data).to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out
+}
+
+/// 20 万行的日志，第 150000 行是唯一的匹配
+fn log_text() -> String {
+    (1..=200_000)
+        .map(|i| if i == 150_000 { "ERROR disk full\n".to_string() } else { format!("line {} ok\n", i) })
+        .collect()
+}
+
+#[test]
+fn match_deep_inside_a_generated_gz() {
+    let dir = common::scratch("gzip-deep");
+    let gz = gzip(log_text().as_bytes());
+    // 压缩后远小于原文，确实经过了解压
+    assert!(gz.len() < log_text().len() / 4, "{}", gz.len());
+    common::write(&dir, "app.log.gz", &gz);
+
+    let out = common::pgrep(&dir, &["-z", "-p", "ERROR", "-f", "app.log.gz"]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    assert_eq!(common::stdout(&out), "app.log.gz:150000:ERROR disk full\n");
+    assert_eq!(common::stderr(&out), "");
+
+    let out = common::pgrep(&dir, &["-z", "-c", "-p", "ok$", "-f", "app.log.gz"]);
+    assert_eq!(common::stdout(&out), "app.log.gz:199999\n");
+}
+
+#[test]
+fn detected_by_magic_bytes_and_skipped_without_z() {
+    let dir = common::scratch("gzip-magic");
+    common::write(&dir, "app.log.1", gzip(log_text().as_bytes()));
+
+    let out = common::pgrep(&dir, &["-z", "-p", "ERROR", "-f", "app.log.1"]);
+    assert_eq!(common::stdout(&out), "app.log.1:150000:ERROR disk full\n");
+
+    let out = common::pgrep(&dir, &["-p", "ERROR", "-f", "app.log.1"]);
+    assert_eq!(common::stdout(&out), "");
+    assert_eq!(common::stderr(&out), "");
+}
+
+#[test]
+fn corrupt_file_is_a_per_file_error() {
+    let dir = common::scratch("gzip-corrupt");
+    let mut gz = gzip(log_text().as_bytes());
+    let n = gz.len();
+    // 改坏尾部的 CRC
+    gz[n - 8] ^= 0xff;
+    common::write(&dir, "bad.gz", &gz);
+    common::write(&dir, "good.gz", gzip(b"ERROR here\n"));
+
+    let out = common::pgrep(&dir, &["-z", "-p", "ERROR", "-f", "bad.gz", "good.gz"]);
+    assert_eq!(common::stdout(&out), "good.gz:1:ERROR here\n");
+    let stderr = common::stderr(&out);
+    assert!(stderr.contains("无法解压 gzip 文件 bad.gz"), "{}", stderr);
+}
@@ -0,0 +1,28 @@
+// --match-highlight-style 和 --no-bold 在输出中的转义序列
+
+mod common;
+
+fn colored(name: &str, extra: &[&str]) -> String {
+    let dir = common::scratch(name);
+    common::write(&dir, "h.txt", "a x b\n");
+    let out = common::pgrep(&dir, &[&["--color", "always", "-p", "x", "-f", "h.txt"], extra].concat());
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    common::stdout(&out)
+}
+
+#[test]
+fn default_and_custom_styles() {
+    assert_eq!(colored("hl-default", &[]), "\x1b[35mh.txt\x1b[0m:\x1b[32m1\x1b[0m:a \x1b[1;31mx\x1b[0m b\n");
+    assert_eq!(
+        colored("hl-custom", &["--match-highlight-style", "match:fg=yellow;bold, filename:none"]),
+        "h.txt:\x1b[32m1\x1b[0m:a \x1b[1;33mx\x1b[0m b\n"
+    );
+}
+
+#[test]
+fn no_bold_applies_to_custom_styles() {
+    assert_eq!(
+        colored("hl-no-bold", &["--match-highlight-style", "match:fg=yellow;bold", "--no-bold"]),
+        "\x1b[35mh.txt\x1b[0m:\x1b[32m1\x1b[0m:a \x1b[33mx\x1b[0m b\n"
+    );
+}
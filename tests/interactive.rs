@@ -0,0 +1,52 @@
+// --interactive 需要终端
+
+mod common;
+
+use std::process::Stdio;
+
+#[test]
+fn rejected_when_stdout_is_not_a_terminal() {
+    let dir = common::scratch("interactive-pipe");
+    common::write(&dir, "a.txt", "x\n");
+    let out = common::pgrep(&dir, &["--interactive", "-p", "x", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "");
+    assert!(common::stderr(&out).contains("--interactive 只能在标准输出是终端时使用"), "{}", common::stderr(&out));
+}
+
+/// 在伪终端中运行 `--confirm`，每出现一次提示输入一个回答，返回 (标准输出, 终端上的内容)
+fn confirm(name: &str, answers: &[&str]) -> (String, String) {
+    let dir = common::scratch(name);
+    common::write(&dir, "a.txt", "x1\nx2\nx3\nx4\nx5\n");
+    let pty = common::Pty::open();
+    let mut cmd = common::command(&dir, &["--confirm", "-p", "x", "-f", "a.txt"]);
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    pty.attach(&mut cmd, [false, false, false]);
+    let child = cmd.spawn().unwrap();
+    for (i, a) in answers.iter().enumerate() {
+        pty.wait_for("[y/n/q/a]? ", i + 1);
+        pty.send(&format!("{}\n", a));
+    }
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success(), "{:?} {}", out.status, common::stderr(&out));
+    (common::stdout(&out), pty.text())
+}
+
+#[test]
+fn confirm_yes_no_all() {
+    let (stdout, tty) = confirm("confirm-yna", &["maybe", "y", "n", "a"]);
+    // a 之后不再询问，剩下的结果都输出
+    assert_eq!(stdout, "a.txt:1:x1\na.txt:3:x3\na.txt:4:x4\na.txt:5:x5\n");
+    assert_eq!(tty.matches("[y/n/q/a]? ").count(), 4, "{}", tty);
+    // 无法识别的回答显示帮助之后再问同一条
+    assert!(tty.contains("y 输出，n 跳过，q 停止搜索，a 输出这一条和之后的所有结果"), "{}", tty);
+    assert_eq!(tty.matches("a.txt:1: x1").count(), 2, "{}", tty);
+    assert!(!tty.contains("a.txt:4: x4"), "{}", tty);
+}
+
+#[test]
+fn confirm_quit_stops_the_search() {
+    let (stdout, tty) = confirm("confirm-quit", &["y", "q"]);
+    assert_eq!(stdout, "a.txt:1:x1\n");
+    assert!(tty.contains("a.txt:2: x2"), "{}", tty);
+    assert!(!tty.contains("a.txt:3: x3"), "{}", tty);
+}
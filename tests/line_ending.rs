@@ -0,0 +1,39 @@
+// --line-ending-output 选择每条结果之后的行尾，--print0 总是用 NUL
+
+mod common;
+
+fn run(name: &str, extra: &[&str]) -> Vec<u8> {
+    let dir = common::scratch(name);
+    common::write(&dir, "le.txt", "x1\ny\nx2\n");
+    let out = common::pgrep(&dir, &[&["-p", "x", "-f", "le.txt"], extra].concat());
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    out.stdout
+}
+
+#[test]
+fn each_mode() {
+    assert_eq!(run("le-lf", &["--line-ending-output", "lf"]), b"le.txt:1:x1\nle.txt:3:x2\n");
+    assert_eq!(run("le-crlf", &["--line-ending-output", "crlf"]), b"le.txt:1:x1\r\nle.txt:3:x2\r\n");
+    assert_eq!(run("le-none", &["--line-ending-output", "none"]), b"le.txt:1:x1le.txt:3:x2");
+    assert_eq!(run("le-count", &["--line-ending-output", "crlf", "-c"]), b"le.txt:2\r\n");
+}
+
+#[test]
+fn print0_takes_precedence() {
+    for mode in ["lf", "crlf", "none"] {
+        let out = run(&format!("le-print0-{}", mode), &["--line-ending-output", mode, "--print0"]);
+        assert_eq!(out, b"le.txt:1:x1\0le.txt:3:x2\0", "{}", mode);
+    }
+}
+
+#[cfg(not(windows))]
+#[test]
+fn native_default_is_lf() {
+    assert_eq!(run("le-native", &[]), b"le.txt:1:x1\nle.txt:3:x2\n");
+}
+
+#[cfg(windows)]
+#[test]
+fn native_default_is_crlf() {
+    assert_eq!(run("le-native", &[]), b"le.txt:1:x1\r\nle.txt:3:x2\r\n");
+}
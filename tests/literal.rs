@@ -0,0 +1,32 @@
+// --literal-match-only 和 -F 的结果与转义之后的正则表达式相同
+
+mod common;
+
+const TEXT: &str = "a.b\naXb\r\ncall(x) and (x)\ncost $1 or $10\n\\d+ literally\n说你好，你好\n\nlast a.b";
+
+#[test]
+fn literal_search_agrees_with_the_escaped_regex() {
+    let dir = common::scratch("literal");
+    common::write(&dir, "a.txt", TEXT);
+    for lit in ["a.b", "(x)", "$1", "\\d+", "你好", "aXb"] {
+        let regex = common::pgrep(&dir, &["-p", &regex::escape(lit), "-f", "a.txt"]);
+        assert!(!common::stdout(&regex).is_empty(), "{:?}", lit);
+        for flag in ["-F", "--literal-match-only"] {
+            let lit_out = common::pgrep(&dir, &[flag, "-p", lit, "-f", "a.txt"]);
+            assert_eq!(common::stdout(&lit_out), common::stdout(&regex), "{} {:?}", flag, lit);
+            assert_eq!(lit_out.status.code(), regex.status.code());
+        }
+        // 替换使用的匹配位置也相同
+        let regex = common::pgrep(&dir, &["-p", &regex::escape(lit), "-r", "<$0>", "-f", "a.txt"]);
+        let lit_out = common::pgrep(&dir, &["--literal-match-only", "-p", lit, "-r", "<$0>", "-f", "a.txt"]);
+        assert_eq!(common::stdout(&lit_out), common::stdout(&regex), "{:?}", lit);
+    }
+}
+
+#[test]
+fn literal_search_that_finds_nothing() {
+    let dir = common::scratch("literal-none");
+    common::write(&dir, "a.txt", TEXT);
+    let out = common::pgrep(&dir, &["--literal-match-only", "-p", "a.c", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "");
+}
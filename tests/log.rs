@@ -0,0 +1,53 @@
+// -v / -vv 和 RUST_LOG 控制的诊断日志，日志只写到标准错误
+
+mod common;
+
+fn run(name: &str, args: &[&str], rust_log: Option<&str>) -> std::process::Output {
+    let dir = common::scratch(name);
+    common::write(&dir, "t/a.txt", "x\n");
+    common::write(&dir, "t/sub/b.txt", "y\nx\n");
+    let mut cmd = common::command(&dir, &[args, &["-p", "x", "-f", "t"]].concat());
+    cmd.env_remove("RUST_LOG");
+    if let Some(spec) = rust_log {
+        cmd.env("RUST_LOG", spec);
+    }
+    let out = cmd.output().unwrap();
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    assert_eq!(common::sorted_lines(&out), ["t/a.txt:1:x", "t/sub/b.txt:2:x"]);
+    out
+}
+
+#[test]
+fn silent_on_success_without_flags() {
+    assert_eq!(common::stderr(&run("log-quiet", &[], None)), "");
+}
+
+#[test]
+fn vv_shows_per_file_debug_lines() {
+    let stderr = common::stderr(&run("log-vv", &["-vv"], None));
+    assert!(stderr.contains("[INFO  pgrep] 搜索路径: [\"t\"]\n"), "{}", stderr);
+    for file in ["t/a.txt", "t/sub/b.txt"] {
+        assert!(stderr.contains(&format!("[DEBUG pgrep] 文件类型: {} 普通文件\n", file)), "{}", stderr);
+        assert!(stderr.contains(&format!("[DEBUG pgrep] 搜索 {}: 1 个匹配，耗时 ", file)), "{}", stderr);
+    }
+    assert!(stderr.lines().all(|l| l.starts_with("[INFO ") || l.starts_with("[DEBUG ")), "{}", stderr);
+}
+
+#[test]
+fn v_shows_only_info() {
+    let stderr = common::stderr(&run("log-v", &["-v"], None));
+    assert!(stderr.contains("[INFO  pgrep] 完成目录 t/sub\n"), "{}", stderr);
+    assert!(!stderr.contains("[DEBUG"), "{}", stderr);
+}
+
+#[test]
+fn rust_log_selects_the_level() {
+    let stderr = common::stderr(&run("log-env", &[], Some("debug")));
+    assert!(stderr.contains("[DEBUG pgrep] 搜索 t/a.txt: 1 个匹配"), "{}", stderr);
+    let stderr = common::stderr(&run("log-env-info", &[], Some("info")));
+    assert!(stderr.contains("[INFO  pgrep] 搜索路径"), "{}", stderr);
+    assert!(!stderr.contains("[DEBUG"), "{}", stderr);
+    // 取 -v 和 RUST_LOG 中更详细的一个
+    let stderr = common::stderr(&run("log-env-off", &["-vv"], Some("off")));
+    assert!(stderr.contains("[DEBUG pgrep] 搜索 t/a.txt: 1 个匹配"), "{}", stderr);
+}
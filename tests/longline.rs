@@ -0,0 +1,41 @@
+// --max-line-bytes 在 100MB 只有一行的文件上的内存占用
+//
+// 要写 100MB 的临时文件，默认不运行: `cargo test --test longline -- --ignored`
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use std::io::Write;
+
+#[test]
+#[ignore]
+fn a_100mb_single_line_file_uses_bounded_memory() {
+    let dir = common::scratch("longline");
+    let p = dir.join("big.txt");
+    let mut f = std::io::BufWriter::new(std::fs::File::create(&p).unwrap());
+    f.write_all(b"needle at the start ").unwrap();
+    let block = vec![b'x'; 1 << 20];
+    for _ in 0..100 {
+        f.write_all(&block).unwrap();
+    }
+    f.write_all(b" needle at the end\nneedle on line 2\n").unwrap();
+    f.into_inner().unwrap().sync_all().unwrap();
+
+    let child = common::command(&dir, &["-p", "needle", "--max-line-bytes", "1048576", "-f", "big.txt", "-c"])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let out = child.wait_with_output().unwrap();
+    // wait_with_output 已经等待了子进程，RUSAGE_CHILDREN 中是这个测试程序等待过的子进程的最大值
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    assert_eq!(unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage) }, 0);
+    let max_rss_mb = usage.ru_maxrss / 1024;
+
+    assert_eq!(common::stdout(&out), "big.txt:2\n");
+    assert!(common::stderr(&out).contains("big.txt 中有 1 行超过了 --max-line-bytes"), "{}", common::stderr(&out));
+    // 整个文件读入内存至少要 100MB
+    assert!(max_rss_mb < 40, "最大常驻内存 {} MB", max_rss_mb);
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,34 @@
+// --generate-man-page 输出的 roff 手册
+
+mod common;
+
+fn page(name: &str, extra: &[&str]) -> String {
+    let dir = common::scratch(name);
+    let out = common::pgrep(&dir, &[&["--generate-man-page"], extra].concat());
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    common::stdout(&out)
+}
+
+#[test]
+fn names_the_program_and_describes_key_flags() {
+    let s = page("man-page", &[]);
+    assert!(s.starts_with(&format!(".TH PGREP 1 \"\" \"pgrep {}\"\n", env!("CARGO_PKG_VERSION"))), "{}", s);
+    assert!(s.contains(".SH NAME\npgrep \\- "), "{}", s);
+    assert!(s.contains("\\fBpgrep\\fR [\\fIOPTIONS\\fR]"), "{}", s);
+    for section in [".SH SYNOPSIS", ".SH DESCRIPTION", ".SH OPTIONS", ".SH EXAMPLES"] {
+        assert!(s.contains(section), "{}", section);
+    }
+    assert!(s.contains("\\fB\\-f\\fR, \\fB\\-\\-file\\fR \\fIFILE\\fR\n要搜索的文件路径\n"), "{}", s);
+    assert!(
+        s.contains("\\fB\\-F\\fR, \\fB\\-\\-fixed\\-strings\\fR\n模式是普通文本而不是正则表达式"),
+        "{}",
+        s
+    );
+    assert!(s.contains("\\fB\\-c\\fR, \\fB\\-\\-count\\fR\n只输出每个文件中匹配的行数"), "{}", s);
+}
+
+#[test]
+fn section_number() {
+    let s = page("man-page-section", &["--man-section", "8"]);
+    assert!(s.starts_with(".TH PGREP 8 "), "{}", s);
+}
@@ -0,0 +1,31 @@
+// 不存在的搜索路径：报告路径本身，拼写接近时给出建议，没有展开的通配符单独提示
+
+mod common;
+
+#[test]
+fn near_miss_suggests_the_existing_path() {
+    let dir = common::scratch("missing-typo");
+    common::write(&dir, "src/main.rs", "fn main() {}\n");
+    let out = common::pgrep(&dir, &["-p", "main", "-f", "sr/main.rs"]);
+    assert_eq!(common::stdout(&out), "");
+    assert!(
+        common::stderr(&out).contains("sr/main.rs: 没有这个文件或目录；是不是 'src/main.rs'？"),
+        "{}",
+        common::stderr(&out)
+    );
+}
+
+#[test]
+fn unexpanded_glob_and_far_miss() {
+    let dir = common::scratch("missing-glob");
+    common::write(&dir, "src/main.rs", "fn main() {}\n");
+    let out = common::pgrep(&dir, &["-p", "main", "-f", "src/*.rs"]);
+    let stderr = common::stderr(&out);
+    assert!(stderr.contains("src/*.rs: 没有这个文件或目录；看起来是没有被 shell 展开的通配符"), "{}", stderr);
+    assert!(!stderr.contains("是不是 '"), "{}", stderr);
+
+    let out = common::pgrep(&dir, &["-p", "main", "-f", "documentation"]);
+    let stderr = common::stderr(&out);
+    assert!(stderr.contains("documentation: 没有这个文件或目录"), "{}", stderr);
+    assert!(!stderr.contains("是不是 '"), "{}", stderr);
+}
@@ -0,0 +1,32 @@
+// --newline 的每种取值在只有 \r 和混用行尾的文件中的行号和输出
+
+mod common;
+
+fn search(dir: &std::path::Path, file: &str, mode: &str) -> String {
+    let out = common::pgrep(dir, &["-p", "x", "-f", file, "--newline", mode]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    common::stdout(&out)
+}
+
+#[test]
+fn cr_only_file() {
+    let dir = common::scratch("newline-cr");
+    common::write(&dir, "cr.txt", "one\rtwo x\rthree\r");
+    assert_eq!(search(&dir, "cr.txt", "auto"), "cr.txt:2:two x\n");
+    assert_eq!(search(&dir, "cr.txt", "cr"), "cr.txt:2:two x\n");
+    assert_eq!(search(&dir, "cr.txt", "any"), "cr.txt:2:two x\n");
+    // 没有 \n，整个文件是一行
+    assert_eq!(search(&dir, "cr.txt", "lf"), "cr.txt:1:one\rtwo x\rthree\r\n");
+    assert_eq!(search(&dir, "cr.txt", "crlf"), "cr.txt:1:one\rtwo x\rthree\r\n");
+}
+
+#[test]
+fn mixed_endings() {
+    let dir = common::scratch("newline-mixed");
+    common::write(&dir, "mixed.txt", "a\r\nb x\rc x\nd\r\ne x");
+    assert_eq!(search(&dir, "mixed.txt", "auto"), "mixed.txt:2:b x\rc x\nmixed.txt:4:e x\n");
+    assert_eq!(search(&dir, "mixed.txt", "lf"), "mixed.txt:2:b x\rc x\nmixed.txt:4:e x\n");
+    assert_eq!(search(&dir, "mixed.txt", "crlf"), "mixed.txt:2:b x\rc x\nd\nmixed.txt:3:e x\n");
+    assert_eq!(search(&dir, "mixed.txt", "cr"), "mixed.txt:2:\nb x\nmixed.txt:3:c x\nd\nmixed.txt:4:\ne x\n");
+    assert_eq!(search(&dir, "mixed.txt", "any"), "mixed.txt:2:b x\nmixed.txt:3:c x\nmixed.txt:5:e x\n");
+}
@@ -0,0 +1,50 @@
+// 标准输出是终端时通过分页器输出
+
+mod common;
+
+use std::process::Stdio;
+
+/// 在伪终端中运行，标准输出接到终端上，等程序退出后返回终端上的内容
+fn run(name: &str, extra: &[&str]) -> String {
+    let dir = common::scratch(name);
+    common::write(&dir, "a.txt", "x1\ny\nx2\n");
+    // 记录下自己被启动过和 LESS 环境变量，再用 cat 把输入原样输出
+    common::write(&dir, "pager.sh", "printf 'pager LESS=%s\\n' \"$LESS\"\nexec cat\n");
+    let pty = common::Pty::open();
+    // 标准输出是终端时默认使用颜色
+    let mut cmd = common::command(&dir, &[&["--color", "never", "-p", "x", "-f", "a.txt"], extra].concat());
+    cmd.stdin(Stdio::null()).stderr(Stdio::piped()).env_remove("LESS");
+    pty.attach(&mut cmd, [false, true, false]);
+    let out = cmd.spawn().unwrap().wait_with_output().unwrap();
+    assert!(out.status.success(), "{:?} {}", out.status, common::stderr(&out));
+    pty.wait_for("a.txt:3:x2", 1);
+    pty.text().replace("\r\n", "\n")
+}
+
+#[test]
+fn cat_as_pager() {
+    assert_eq!(run("pager-cat", &["--pager", "cat"]), "a.txt:1:x1\na.txt:3:x2\n");
+}
+
+#[test]
+fn output_goes_through_the_pager() {
+    assert_eq!(
+        run("pager-script", &["--pager", "sh pager.sh"]),
+        "pager LESS=FRX\na.txt:1:x1\na.txt:3:x2\n"
+    );
+}
+
+#[test]
+fn no_pager_and_missing_pager_write_directly() {
+    assert_eq!(run("pager-off", &["--pager", "sh pager.sh", "--no-pager"]), "a.txt:1:x1\na.txt:3:x2\n");
+    assert_eq!(run("pager-missing", &["--pager", "no-such-pager-program"]), "a.txt:1:x1\na.txt:3:x2\n");
+}
+
+#[test]
+fn pipes_do_not_use_the_pager() {
+    let dir = common::scratch("pager-pipe");
+    common::write(&dir, "a.txt", "x1\n");
+    common::write(&dir, "pager.sh", "echo pager\nexec cat\n");
+    let out = common::pgrep(&dir, &["-p", "x", "-f", "a.txt", "--pager", "sh pager.sh"]);
+    assert_eq!(common::stdout(&out), "a.txt:1:x1\n");
+}
@@ -0,0 +1,50 @@
+// --strip-pattern-comments 和 -E：分成多行、带注释的模式文件
+
+mod common;
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    let dir = common::scratch(name);
+    common::write(&dir, "a.txt", "v1.2 rel\nissue #42\nx y\nxy\nab\nab \n");
+    common::write(&dir, "ver.re", "# 版本号\nv\\d+   # 主版本\n\\.\\d+ # 次版本\n");
+    dir
+}
+
+#[test]
+fn commented_pattern_file() {
+    let dir = fixture("comments-file");
+    for flag in ["--strip-pattern-comments", "-E"] {
+        let out = common::pgrep(&dir, &[flag, "--pattern-file", "ver.re", "-f", "a.txt"]);
+        assert_eq!(common::stdout(&out), "a.txt:1:v1.2 rel\n", "{}", flag);
+    }
+    // 不去掉注释时每一行是一个模式，注释也是模式的一部分
+    let out = common::pgrep(&dir, &["--pattern-file", "ver.re", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "");
+}
+
+#[test]
+fn whitespace_is_kept_unlike_e() {
+    let dir = fixture("comments-space");
+    let out = common::pgrep(&dir, &["--strip-pattern-comments", "-p", "  x y  # 空格", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:3:x y\n");
+    let out = common::pgrep(&dir, &["-E", "-p", "x y  # 空格", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:4:xy\n");
+}
+
+#[test]
+fn escaped_and_class_hashes_are_not_comments() {
+    let dir = fixture("comments-escape");
+    common::write(&dir, "escaped.re", "issue \\#\\d+\n");
+    common::write(&dir, "class.re", "issue [#]\\d+  # 注释\n");
+    for f in ["escaped.re", "class.re"] {
+        let out = common::pgrep(&dir, &["--strip-pattern-comments", "--pattern-file", f, "-f", "a.txt"]);
+        assert_eq!(common::stdout(&out), "a.txt:2:issue #42\n", "{}", f);
+    }
+
+    // 行尾转义的空格保留下来，其余行尾空白被去掉
+    common::write(&dir, "space.re", "^ab\\   \n$\n");
+    let out = common::pgrep(&dir, &["--strip-pattern-comments", "--pattern-file", "space.re", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:6:ab \n");
+    common::write(&dir, "space.re", "^ab   \n$\n");
+    let out = common::pgrep(&dir, &["--strip-pattern-comments", "--pattern-file", "space.re", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:5:ab\n");
+}
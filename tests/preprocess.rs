@@ -0,0 +1,66 @@
+// --pre：用一个 shell 脚本作为预处理命令
+//
+// up.sh 先输出一行说明，再把文件内容转成大写；文件名中有 fail 时写标准错误并以状态 2 退出，
+// 有 slow 时先睡 5 秒。
+
+mod common;
+
+use std::os::unix::fs::PermissionsExt;
+use std::time::{Duration, Instant};
+
+const STUB: &str = "#!/bin/sh
+case \"$1\" in
+  *fail*) echo \"cannot read $1\" >&2; exit 2;;
+  *slow*) sleep 5;;
+esac
+echo \"converted from $1\"
+tr a-z A-Z < \"$1\"
+";
+
+fn tree(name: &str) -> std::path::PathBuf {
+    let dir = common::scratch(name);
+    let stub = common::write(&dir, "up.sh", STUB);
+    std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+    for f in ["a.pdf", "b.txt", "fail.pdf", "slow.pdf"] {
+        common::write(&dir, f, "hello x\n");
+    }
+    dir
+}
+
+#[test]
+fn searches_the_command_output() {
+    let dir = tree("pre-stdout");
+    let out = common::pgrep(&dir, &["--pre", "./up.sh", "-p", "X|a.pdf", "-f", "a.pdf", "b.txt"]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    // 文件本身是小写的，匹配到的是命令输出的大写内容，行号也按输出计算
+    assert_eq!(common::stdout(&out), "a.pdf:1:converted from a.pdf\na.pdf:2:HELLO X\nb.txt:2:HELLO X\n");
+    assert_eq!(common::stderr(&out), "");
+}
+
+#[test]
+fn pre_glob_selects_the_files() {
+    let dir = tree("pre-glob");
+    let out = common::pgrep(&dir, &["--pre", "./up.sh", "--pre-glob", "*.pdf", "-p", "(?i)x", "-f", "a.pdf", "b.txt"]);
+    assert_eq!(common::stdout(&out), "a.pdf:2:HELLO X\nb.txt:1:hello x\n");
+}
+
+#[test]
+fn failure_carries_the_command_stderr() {
+    let dir = tree("pre-fail");
+    let out = common::pgrep(&dir, &["--pre", "./up.sh", "-p", "X", "-f", "fail.pdf", "a.pdf"]);
+    assert_eq!(common::stdout(&out), "a.pdf:2:HELLO X\n");
+    assert_eq!(
+        common::stderr(&out),
+        "处理错误: 预处理命令 ./up.sh 处理 fail.pdf 失败（exit status: 2）\ncannot read fail.pdf\n"
+    );
+}
+
+#[test]
+fn timeout_kills_the_command() {
+    let dir = tree("pre-timeout");
+    let started = Instant::now();
+    let out = common::pgrep(&dir, &["--pre", "./up.sh", "--pre-timeout", "0.3", "-p", "X", "-f", "slow.pdf", "a.pdf"]);
+    assert!(started.elapsed() < Duration::from_secs(4), "{:?}", started.elapsed());
+    assert_eq!(common::stdout(&out), "a.pdf:2:HELLO X\n");
+    assert_eq!(common::stderr(&out), "处理错误: 预处理命令 ./up.sh 处理 slow.pdf 失败（超过了 0.3 秒的时间限制）\n");
+}
@@ -0,0 +1,26 @@
+// --profile 出错时和配置文件的语法错误一样以状态 2 退出
+
+mod common;
+
+#[test]
+fn profile_errors_exit_2() {
+    let dir = common::scratch("profile-errors");
+    common::write(&dir, "a.txt", "x\n");
+    common::write(
+        &dir,
+        "conf.toml",
+        "[profile.a]\ninherits = \"b\"\n\n[profile.b]\ninherits = \"a\"\n\n[profile.ok]\nskip-empty-lines = true\n",
+    );
+
+    let out = common::pgrep(&dir, &["--config", "conf.toml", "--profile", "nope", "-p", "x", "-f", "a.txt"]);
+    assert_eq!(out.status.code(), Some(2));
+    assert!(common::stderr(&out).contains("未知的 profile 'nope'（已定义: a, b, ok）"), "{}", common::stderr(&out));
+
+    let out = common::pgrep(&dir, &["--config", "conf.toml", "--profile", "a", "-p", "x", "-f", "a.txt"]);
+    assert_eq!(out.status.code(), Some(2));
+    assert!(common::stderr(&out).contains("profile 继承出现循环"), "{}", common::stderr(&out));
+
+    let out = common::pgrep(&dir, &["--config", "conf.toml", "--profile", "ok", "-p", "x", "-f", "a.txt"]);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(common::stdout(&out), "a.txt:1:x\n");
+}
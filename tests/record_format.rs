@@ -0,0 +1,53 @@
+// --record-format 的 {ts}：同一次运行中按输出的顺序不会减小
+
+mod common;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn now_nanos() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()
+}
+
+#[test]
+fn timestamps_do_not_decrease_across_records() {
+    let dir = common::scratch("record-ts");
+    let text: String = (0..2000).map(|i| format!("x {}\n", i)).collect();
+    common::write(&dir, "a.txt", &text);
+    common::write(&dir, "b.txt", &text);
+
+    let before = now_nanos();
+    let out = common::pgrep(
+        &dir,
+        &["--record-format", "{ts} {file}:{line}", "--timestamp-precision", "ns", "-p", "x", "-f", "a.txt", "b.txt"],
+    );
+    let after = now_nanos();
+    assert!(out.status.success(), "{}", common::stderr(&out));
+
+    let stdout = common::stdout(&out);
+    let records: Vec<(u128, &str)> = stdout
+        .lines()
+        .map(|l| {
+            let (ts, rest) = l.split_once(' ').unwrap();
+            (ts.parse().unwrap(), rest)
+        })
+        .collect();
+    assert_eq!(records.len(), 4000);
+    assert_eq!(records[0].1, "a.txt:1");
+    assert_eq!(records[3999].1, "b.txt:2000");
+    assert!(records.windows(2).all(|w| w[0].0 <= w[1].0), "时间戳减小了");
+    // 时间戳是输出时取得的，落在这次运行的时间之内
+    assert!(before <= records[0].0 && records[3999].0 <= after);
+    assert!(records[0].0 < records[3999].0);
+}
+
+#[test]
+fn iso_timestamps_with_precision() {
+    let dir = common::scratch("record-iso");
+    common::write(&dir, "a.txt", "x\n");
+    let out = common::pgrep(&dir, &["--record-format", "[{ts:iso}] {text}", "--timestamp-precision", "us", "-p", "x", "-f", "a.txt"]);
+    let line = common::stdout(&out);
+    // [YYYY-MM-DDTHH:MM:SS.ffffffZ] x
+    assert_eq!(line.len(), "[2024-05-01T08:30:00.123456Z] x\n".len(), "{}", line);
+    assert!(line.starts_with("[20") && line.ends_with("Z] x\n"), "{}", line);
+    assert_eq!(&line[11..12], "T");
+}
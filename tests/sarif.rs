@@ -0,0 +1,59 @@
+// --rules 和 --format sarif 的端到端测试
+
+mod common;
+
+const RULES: &str = r#"[[rule]]
+id = "rust-unsafe"
+paths = ["src/**/*.rs"]
+pattern = 'unsafe\s*\{'
+message = "no unsafe"
+severity = "error"
+
+[[rule]]
+pattern = "todo"
+case_insensitive = true
+severity = "info"
+"#;
+
+#[test]
+fn sarif_document_for_a_rules_file() {
+    let dir = common::scratch("sarif");
+    common::write(&dir, "r.toml", RULES);
+    std::fs::create_dir(dir.join("src")).unwrap();
+    common::write(&dir.join("src"), "a.rs", "fn a() {\n    unsafe { x() }\n}\n// TODO later\n");
+
+    let out = common::pgrep(&dir, &["--rules", "r.toml", "--format", "sarif", "-f", "src"]);
+    let expected = format!(
+        concat!(
+            r#"{{"$schema":"https://json.schemastore.org/sarif-2.1.0.json","version":"2.1.0","runs":[{{"tool":{{"driver":"#,
+            r#"{{"name":"pgrep","version":"{}","rules":["#,
+            r#"{{"id":"rust-unsafe","shortDescription":{{"text":"no unsafe"}},"defaultConfiguration":{{"level":"error"}}}},"#,
+            r#"{{"id":"rule-2","shortDescription":{{"text":"todo"}},"defaultConfiguration":{{"level":"note"}}}}]}}}},"#,
+            r#""results":["#,
+            r#"{{"ruleId":"rust-unsafe","ruleIndex":0,"level":"error","message":{{"text":"no unsafe"}},"#,
+            r#""locations":[{{"physicalLocation":{{"artifactLocation":{{"uri":"src/a.rs"}},"#,
+            r#""region":{{"startLine":2,"startColumn":5,"snippet":{{"text":"    unsafe {{ x() }}"}}}}}}}}]}},"#,
+            r#"{{"ruleId":"rule-2","ruleIndex":1,"level":"note","message":{{"text":"// TODO later"}},"#,
+            r#""locations":[{{"physicalLocation":{{"artifactLocation":{{"uri":"src/a.rs"}},"#,
+            r#""region":{{"startLine":4,"startColumn":4,"snippet":{{"text":"// TODO later"}}}}}}}}]}}]}}]}}"#,
+            "\n"
+        ),
+        env!("CARGO_PKG_VERSION")
+    );
+    assert_eq!(common::stdout(&out), expected);
+    // 有 error 级别的匹配
+    assert_eq!(out.status.code(), Some(1));
+    assert!(common::stderr(&out).contains("有 1 处匹配了 error 级别的规则"), "{}", common::stderr(&out));
+}
+
+#[test]
+fn invalid_rule_is_reported_before_searching() {
+    let dir = common::scratch("sarif-invalid");
+    common::write(&dir, "r.toml", "[[rule]]\nid = \"broken\"\npattern = \"a(b\"\n");
+    common::write(&dir, "a.txt", "a(b\n");
+
+    let out = common::pgrep(&dir, &["--rules", "r.toml", "--format", "sarif", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "");
+    assert_eq!(out.status.code(), Some(2));
+    assert!(common::stderr(&out).contains("r.toml:1: 规则 broken 的模式无效"), "{}", common::stderr(&out));
+}
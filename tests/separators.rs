@@ -0,0 +1,78 @@
+// --emit-separators-to-stderr：标准输出和标准错误分别捕获，分隔行只出现在标准错误上
+
+#![cfg(unix)]
+
+mod common;
+
+use std::io::Read;
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 在后台线程中读完一个管道，读到的内容随时可以查看
+fn collect(mut r: impl Read + Send + 'static) -> Arc<Mutex<Vec<u8>>> {
+    let buf = Arc::new(Mutex::new(Vec::new()));
+    let shared = Arc::clone(&buf);
+    std::thread::spawn(move || {
+        let mut chunk = [0; 4096];
+        while let Ok(n) = r.read(&mut chunk) {
+            if n == 0 {
+                break;
+            }
+            shared.lock().unwrap().extend_from_slice(&chunk[..n]);
+        }
+    });
+    buf
+}
+
+/// 等到 `buf` 中出现 `needle`，最多等 10 秒
+fn wait_for(buf: &Mutex<Vec<u8>>, needle: &str) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !String::from_utf8_lossy(&buf.lock().unwrap()).contains(needle) {
+        assert!(Instant::now() < deadline, "没有等到 {:?}: {:?}", needle, String::from_utf8_lossy(&buf.lock().unwrap()));
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// 运行 --watch，第一次搜索之后修改文件，等第二次搜索的结果出来再用 Ctrl-C 结束，返回 (标准输出, 标准错误)
+fn watch_once(name: &str, extra: &[&str]) -> (String, String) {
+    let dir = common::scratch(name);
+    let file = common::write(&dir, "a.txt", "TODO first\n");
+    let args = [&["--watch", "--watch-debounce", "50", "-p", "TODO", "-f", "a.txt"], extra].concat();
+    let mut child: Child = common::command(&dir, &args).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    let stdout = collect(child.stdout.take().unwrap());
+    let stderr = collect(child.stderr.take().unwrap());
+
+    wait_for(&stdout, "TODO first");
+    std::fs::write(&file, "TODO first\nTODO second\n").unwrap();
+    wait_for(&stdout, "TODO second");
+    // 第二次搜索结束之后回到等待变化的状态，Ctrl-C 让它正常结束
+    std::thread::sleep(Duration::from_millis(100));
+    unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGINT) };
+    let status = child.wait().unwrap();
+    assert_eq!(status.code(), Some(0));
+    // 管道关闭之后读取线程很快就会读完
+    std::thread::sleep(Duration::from_millis(50));
+    let out = String::from_utf8_lossy(&stdout.lock().unwrap()).into_owned();
+    let err = String::from_utf8_lossy(&stderr.lock().unwrap()).into_owned();
+    (out, err)
+}
+
+#[test]
+fn separators_go_to_stderr() {
+    let (stdout, stderr) = watch_once("separators-stderr", &["--emit-separators-to-stderr"]);
+    assert_eq!(stdout, "a.txt:1:TODO first\na.txt:1:TODO first\na.txt:2:TODO second\n");
+    assert_eq!(stderr.lines().filter(|l| l.starts_with("==> ")).count(), 1, "{}", stderr);
+    assert!(stderr.contains(" 文件发生变化，重新搜索 <==\n"), "{}", stderr);
+}
+
+#[test]
+fn separators_stay_on_stdout_by_default() {
+    let (stdout, stderr) = watch_once("separators-stdout", &[]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", stdout);
+    assert_eq!(lines[0], "a.txt:1:TODO first");
+    assert!(lines[1].starts_with("==> ") && lines[1].ends_with(" 文件发生变化，重新搜索 <=="), "{}", stdout);
+    assert_eq!(&lines[2..], ["a.txt:1:TODO first", "a.txt:2:TODO second"]);
+    assert!(!stderr.contains("==>"), "{}", stderr);
+}
@@ -0,0 +1,126 @@
+// --serve：一行一个 JSON-RPC 请求，一行一个响应或通知
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// 运行中的服务，输出的每一行由读取线程交给 `lines`
+struct Server {
+    child: Child,
+    stdin: ChildStdin,
+    lines: mpsc::Receiver<String>,
+}
+
+impl Server {
+    fn start(dir: &std::path::Path) -> Server {
+        let mut child = common::command(dir, &["--serve"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (tx, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if tx.send(line.unwrap()).is_err() {
+                    break;
+                }
+            }
+        });
+        Server { child, stdin, lines }
+    }
+
+    fn send(&mut self, req: &str) {
+        writeln!(self.stdin, "{}", req).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    /// 读到包含 `needle` 的行为止，返回读到的所有行（包括这一行）
+    fn until(&self, needle: &str) -> Vec<String> {
+        let mut seen = Vec::new();
+        loop {
+            match self.lines.recv_timeout(Duration::from_secs(10)) {
+                Ok(line) => {
+                    let done = line.contains(needle);
+                    seen.push(line);
+                    if done {
+                        return seen;
+                    }
+                }
+                Err(_) => panic!("没有等到 {:?}: {:?}", needle, seen),
+            }
+        }
+    }
+}
+
+#[test]
+fn search_errors_and_shutdown() {
+    let dir = common::scratch("serve-basic");
+    common::write(&dir, "a.txt", "a TODO\nb\nTODO c\n");
+    let mut s = Server::start(&dir);
+
+    s.send(r#"{"jsonrpc":"2.0","id":1,"method":"search","params":{"pattern":"todo","paths":["a.txt"],"options":{"ignore_case":true}}}"#);
+    let lines = s.until(r#""id":1,"result""#);
+    assert_eq!(
+        lines,
+        [
+            r#"{"jsonrpc":"2.0","method":"matches","params":{"id":1,"matches":[{"path":"a.txt","line":1,"column":3,"text":"a TODO"},{"path":"a.txt","line":3,"column":1,"text":"TODO c"}]}}"#,
+            r#"{"jsonrpc":"2.0","id":1,"result":{"matches":2,"files":1,"errors":0,"cancelled":false,"truncated":false}}"#,
+        ]
+    );
+
+    // 不是合法的 JSON：没有 id 可以对应，id 为 null
+    s.send("{not json");
+    let lines = s.until("-32700");
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].starts_with(r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"#), "{}", lines[0]);
+
+    s.send(r#"{"jsonrpc":"2.0","id":2,"method":"frob"}"#);
+    assert_eq!(
+        s.until("-32601"),
+        [r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32601,"message":"未知的方法 \"frob\""}}"#]
+    );
+
+    // 模式无法编译是参数错误，服务继续运行
+    s.send(r#"{"jsonrpc":"2.0","id":3,"method":"search","params":{"pattern":"("}}"#);
+    let lines = s.until(r#""id":3"#);
+    assert!(lines[0].contains(r#""code":-32602"#), "{:?}", lines);
+
+    s.send(r#"{"jsonrpc":"2.0","id":4,"method":"cancel","params":{"id":99}}"#);
+    assert_eq!(s.until(r#""id":4"#), [r#"{"jsonrpc":"2.0","id":4,"result":{"cancelled":false}}"#]);
+
+    s.send(r#"{"jsonrpc":"2.0","id":5,"method":"shutdown"}"#);
+    assert_eq!(s.until(r#""id":5"#), [r#"{"jsonrpc":"2.0","id":5,"result":null}"#]);
+    assert!(s.child.wait().unwrap().success());
+}
+
+#[test]
+fn cancel_stops_a_running_search() {
+    let dir = common::scratch("serve-cancel");
+    for i in 0..2000 {
+        common::write(&dir, &format!("d{}/f{}.txt", i % 20, i), "hit\n".repeat(50));
+    }
+    let mut s = Server::start(&dir);
+
+    s.send(r#"{"jsonrpc":"2.0","id":"big","method":"search","params":{"pattern":"hit","options":{"batch_size":10}}}"#);
+    s.until(r#""method":"matches""#);
+    s.send(r#"{"jsonrpc":"2.0","id":7,"method":"cancel","params":{"id":"big"}}"#);
+
+    // 取消的响应和匹配通知的先后不确定，汇总一定在最后
+    let lines = s.until(r#""id":"big","result""#);
+    assert!(lines.contains(&r#"{"jsonrpc":"2.0","id":7,"result":{"cancelled":true}}"#.to_string()), "{:?}", lines);
+    let summary = lines.last().unwrap();
+    assert!(summary.contains(r#""cancelled":true"#), "{}", summary);
+    assert!(!summary.contains(r#""matches":100000"#), "{}", summary);
+
+    // 已经结束的搜索不能再取消；关闭标准输入后服务退出
+    s.send(r#"{"jsonrpc":"2.0","id":8,"method":"cancel","params":{"id":"big"}}"#);
+    assert_eq!(s.until(r#""id":8"#), [r#"{"jsonrpc":"2.0","id":8,"result":{"cancelled":false}}"#]);
+    drop(s.stdin);
+    assert!(s.child.wait().unwrap().success());
+}
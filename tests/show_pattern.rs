@@ -0,0 +1,43 @@
+// --show-pattern：每条结果标出匹配了它的所有模式，按命令行上的顺序
+
+mod common;
+
+/// 两个互相重叠的模式：`fail` 的每个匹配也是 `fail(ed|ure)?` 的匹配
+fn show(name: &str, extra: &[&str]) -> String {
+    let dir = common::scratch(name);
+    common::write(&dir, "l.txt", "failed once\nfailure\nok\nno fail here\nwarn\n");
+    let args = [&["--show-pattern", "-e", "short=fail", "-e", "fail(ed|ure)", "-e", "warn", "-f", "l.txt"], extra].concat();
+    let out = common::pgrep(&dir, &args);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    common::stdout(&out)
+}
+
+#[test]
+fn labels_in_text_output() {
+    assert_eq!(
+        show("show-pattern-text", &[]),
+        concat!(
+            "[short,fail(ed|ure)] l.txt:1:failed once\n",
+            "[short,fail(ed|ure)] l.txt:2:failure\n",
+            "[short] l.txt:4:no fail here\n",
+            "[warn] l.txt:5:warn\n",
+        )
+    );
+}
+
+#[test]
+fn labels_in_json_output() {
+    assert_eq!(
+        show("show-pattern-json", &["--format", "json"]),
+        concat!(
+            r#"{"path":"l.txt","line":1,"column":1,"text":"failed once","patterns":["short","fail(ed|ure)"]}"#,
+            "\n",
+            r#"{"path":"l.txt","line":2,"column":1,"text":"failure","patterns":["short","fail(ed|ure)"]}"#,
+            "\n",
+            r#"{"path":"l.txt","line":4,"column":4,"text":"no fail here","patterns":["short"]}"#,
+            "\n",
+            r#"{"path":"l.txt","line":5,"column":1,"text":"warn","patterns":["warn"]}"#,
+            "\n",
+        )
+    );
+}
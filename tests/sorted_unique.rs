@@ -0,0 +1,57 @@
+// --output-sorted-unique-texts：所有文件搜索完之后按文本排序输出不重复的匹配
+
+mod common;
+
+fn run(name: &str, extra: &[&str]) -> String {
+    run_pattern(name, "a", extra)
+}
+
+fn run_pattern(name: &str, pattern: &str, extra: &[&str]) -> String {
+    let dir = common::scratch(name);
+    common::write(&dir, "a.txt", "beta x\nalpha\nZeta alpha\nbeta x\n");
+    common::write(&dir, "b.txt", "gamma\nalpha\n");
+    let args = [&["-p", pattern, "-f", "a.txt", "b.txt"], extra].concat();
+    let out = common::pgrep(&dir, &args);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    common::stdout(&out)
+}
+
+#[test]
+fn whole_lines_ascending_and_descending() {
+    // 按字节排序，大写字母在小写字母之前；两个文件中重复的行只出现一次
+    assert_eq!(run("sorted-asc", &["--output-sorted-unique-texts"]), "Zeta alpha\nalpha\nbeta x\ngamma\n");
+    assert_eq!(
+        run("sorted-desc", &["--output-sorted-unique-texts", "--output-sorted-unique-texts-desc"]),
+        "gamma\nbeta x\nalpha\nZeta alpha\n"
+    );
+}
+
+#[test]
+fn whole_lines_with_counts() {
+    assert_eq!(
+        run("sorted-counts", &["--output-sorted-unique-texts", "--output-sorted-unique-counts"]),
+        "1\tZeta alpha\n2\talpha\n2\tbeta x\n1\tgamma\n"
+    );
+    assert_eq!(
+        run(
+            "sorted-counts-desc",
+            &["--output-sorted-unique-texts", "--output-sorted-unique-texts-desc", "--output-sorted-unique-counts"]
+        ),
+        "1\tgamma\n2\tbeta x\n2\talpha\n1\tZeta alpha\n"
+    );
+}
+
+#[test]
+fn only_matching_collects_matched_substrings() {
+    let word = r"[A-Za-z]+a\b";
+    let o = ["-o", "--output-sorted-unique-texts"];
+    assert_eq!(run_pattern("sorted-o", word, &o), "Zeta\nalpha\nbeta\ngamma\n");
+    assert_eq!(
+        run_pattern("sorted-o-desc", word, &[&o[..], &["--output-sorted-unique-texts-desc"]].concat()),
+        "gamma\nbeta\nalpha\nZeta\n"
+    );
+    assert_eq!(
+        run_pattern("sorted-o-counts", word, &[&o[..], &["--output-sorted-unique-counts"]].concat()),
+        "1\tZeta\n3\talpha\n2\tbeta\n1\tgamma\n"
+    );
+}
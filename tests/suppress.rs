@@ -0,0 +1,62 @@
+// 抑制匹配：行内标记、--suppressions 文件和没有用到的条目
+
+mod common;
+
+/// 第 2 行带行内标记，第 4 行的上一行带行内标记
+fn fixture(name: &str) -> std::path::PathBuf {
+    let dir = common::scratch(name);
+    common::write(&dir, "src/x.rs", "a bad\nb bad // pgrep-ignore\n// pgrep-ignore\nc bad\nd bad\n");
+    common::write(&dir, "tests/t.rs", "e bad\nf bad\n");
+    dir
+}
+
+#[test]
+fn inline_markers() {
+    let dir = fixture("suppress-inline");
+    let out = common::pgrep(&dir, &["-p", "bad", "-f", "src"]);
+    assert_eq!(common::stdout(&out), "src/x.rs:1:a bad\nsrc/x.rs:5:d bad\n");
+    assert_eq!(common::stderr(&out), "2 处匹配被抑制\n");
+
+    let all = "src/x.rs:1:a bad\nsrc/x.rs:2:b bad // pgrep-ignore\nsrc/x.rs:4:c bad\nsrc/x.rs:5:d bad\n";
+    for args in [&["--ignore-marker", "NOPE"][..], &["--no-ignore-marker"], &["--show-suppressed"]] {
+        let out = common::pgrep(&dir, &[&["-p", "bad", "-f", "src"], args].concat());
+        assert_eq!(common::stdout(&out), all, "{:?}", args);
+    }
+
+    common::write(&dir, "src/x.rs", "a bad\nb bad // lint:allow\n");
+    let out = common::pgrep(&dir, &["--ignore-marker", "lint:allow", "-p", "bad", "-f", "src"]);
+    assert_eq!(common::stdout(&out), "src/x.rs:1:a bad\n");
+}
+
+#[test]
+fn suppressions_file_and_unused_entries() {
+    let dir = fixture("suppress-file");
+    common::write(&dir, "sup.txt", "# 注释\n\nsrc/x.rs:5\ntests/**:^f\nsrc/x.rs:nomatch\nother.rs:1\n");
+    let args = ["-p", "bad", "-f", "src", "tests", "--suppressions", "sup.txt"];
+
+    let out = common::pgrep(&dir, &args);
+    assert_eq!(common::stdout(&out), "src/x.rs:1:a bad\ntests/t.rs:1:e bad\n");
+    assert_eq!(
+        common::stderr(&out),
+        "4 处匹配被抑制\n\
+         警告: 抑制条目没有用到: sup.txt:5: src/x.rs:nomatch\n\
+         警告: 抑制条目没有用到: sup.txt:6: other.rs:1\n"
+    );
+
+    // --show-suppressed 照常输出，报告不变
+    let out = common::pgrep(&dir, &[&args[..], &["--show-suppressed"]].concat());
+    assert_eq!(common::sorted_lines(&out).len(), 6);
+    assert!(common::stderr(&out).starts_with("4 处匹配被抑制\n"), "{}", common::stderr(&out));
+}
+
+#[test]
+fn malformed_suppressions_file() {
+    let dir = fixture("suppress-bad");
+    common::write(&dir, "sup.txt", "# ok\nsrc/x.rs\n");
+    let out = common::pgrep(&dir, &["-p", "bad", "-f", "src", "--suppressions", "sup.txt"]);
+    assert_eq!(common::stdout(&out), "");
+    assert_eq!(
+        common::stderr(&out),
+        "程序执行时发生错误: sup.txt:2: 无法解析抑制条目 \"src/x.rs\": 缺少冒号，应该写成 `路径:正则表达式` 或 `路径:行号`\n"
+    );
+}
@@ -0,0 +1,58 @@
+// 符号链接的层数限制
+//
+// `--max-symlink-depth` 和 GrepConfig 的 `max_symlink_depth` 数的是链上的每一跳，
+// 指向另一个符号链接的链接也算一层。
+
+#![cfg(unix)]
+
+mod common;
+
+use std::os::unix::fs::symlink;
+
+use pgrep::{GrepConfig, MAX_SYMLINK_DEPTH, SymlinkDepthExceeded, grep};
+
+/// `l3 -> l2 -> l1 -> real.txt`，l3 经过三层链接
+fn chain(name: &str) -> std::path::PathBuf {
+    let dir = common::scratch(name);
+    common::write(&dir, "real.txt", "hello\n");
+    symlink("real.txt", dir.join("l1")).unwrap();
+    symlink("l1", dir.join("l2")).unwrap();
+    symlink("l2", dir.join("l3")).unwrap();
+    dir
+}
+
+#[test]
+fn chain_of_three_links_exceeds_depth_two() {
+    let dir = chain("symlink-chain");
+    let out = common::pgrep(&dir, &["--max-symlink-depth", "2", "-p", "hello", "-f", "."]);
+    assert_eq!(common::sorted_lines(&out), ["./l1:1:hello", "./l2:1:hello", "./real.txt:1:hello"]);
+    assert!(common::stderr(&out).contains("超过了 2 层，跳过: ./l3"), "{}", common::stderr(&out));
+}
+
+#[test]
+fn chain_of_three_links_within_depth_three() {
+    let dir = chain("symlink-chain-ok");
+    let out = common::pgrep(&dir, &["--max-symlink-depth", "3", "-p", "hello", "-f", "l3"]);
+    assert_eq!(common::stdout(&out), "l3:1:hello\n");
+}
+
+#[test]
+fn library_default_follows_links() {
+    let dir = chain("symlink-default");
+    assert_eq!(GrepConfig::default().max_symlink_depth, MAX_SYMLINK_DEPTH);
+    let res: Vec<_> = grep("hello", &dir, GrepConfig::default()).collect();
+    assert_eq!(res.len(), 4);
+    assert!(res.iter().all(Result::is_ok));
+}
+
+#[test]
+fn library_limit_reports_the_link() {
+    let dir = chain("symlink-lib-limit");
+    let cfg = GrepConfig {
+        max_symlink_depth: 2,
+        ..GrepConfig::default()
+    };
+    let errors: Vec<_> = grep("hello", dir.join("l3"), cfg).filter_map(Result::err).collect();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].downcast_ref::<SymlinkDepthExceeded>().is_some());
+}
@@ -0,0 +1,74 @@
+// --top：按匹配数从多到少列出文件，数量相同时按路径排列
+
+mod common;
+
+/// 每个文件的匹配行数和匹配次数都是已知的：
+/// * b.txt、a.txt、sub/e.txt - 3 行，各 1 次
+/// * sub/c.txt - 1 行，4 次
+/// * d.txt - 1 行，1 次
+/// * none.txt - 没有匹配
+fn tree(name: &str) -> std::path::PathBuf {
+    let dir = common::scratch(name);
+    common::write(&dir, "t/b.txt", "x\nx\nx\n");
+    common::write(&dir, "t/a.txt", "x\nx\nx\n");
+    common::write(&dir, "t/sub/e.txt", "x\nx\nx\n");
+    common::write(&dir, "t/sub/c.txt", "x x x x\n");
+    common::write(&dir, "t/d.txt", "x\nno\n");
+    common::write(&dir, "t/none.txt", "y\n");
+    dir
+}
+
+fn top(name: &str, extra: &[&str]) -> String {
+    let dir = tree(name);
+    let out = common::pgrep(&dir, &[&["-p", "x", "-f", "t"], extra].concat());
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    common::stdout(&out)
+}
+
+#[test]
+fn lines_sorted_with_path_tie_break() {
+    assert_eq!(top("top-2", &["--top", "2"]), "3\tt/a.txt\n3\tt/b.txt\n");
+    assert_eq!(top("top-3", &["--top", "3"]), "3\tt/a.txt\n3\tt/b.txt\n3\tt/sub/e.txt\n");
+}
+
+#[test]
+fn zero_lists_every_file_with_matches() {
+    assert_eq!(
+        top("top-0", &["--top", "0"]),
+        "3\tt/a.txt\n3\tt/b.txt\n3\tt/sub/e.txt\n1\tt/d.txt\n1\tt/sub/c.txt\n"
+    );
+    // 比文件数大的 N 和 0 一样
+    assert_eq!(top("top-100", &["--top", "100"]), top("top-0-again", &["--top", "0"]));
+}
+
+#[test]
+fn counting_matches_instead_of_lines() {
+    assert_eq!(
+        top("top-matches", &["--top", "0", "--count-mode", "matches"]),
+        "4\tt/sub/c.txt\n3\tt/a.txt\n3\tt/b.txt\n3\tt/sub/e.txt\n1\tt/d.txt\n"
+    );
+}
+
+#[test]
+fn json_objects() {
+    assert_eq!(
+        top("top-json", &["--top", "2", "--format", "json"]),
+        "{\"path\":\"t/a.txt\",\"count\":3}\n{\"path\":\"t/b.txt\",\"count\":3}\n"
+    );
+    assert_eq!(
+        top("top-json-matches", &["--top", "1", "--count-mode", "matches", "--format", "json"]),
+        "{\"path\":\"t/sub/c.txt\",\"count\":4}\n"
+    );
+    assert_eq!(
+        top("top-json-array", &["--top", "0", "--format", "json", "--json-array"]),
+        concat!(
+            "[\n",
+            "{\"path\":\"t/a.txt\",\"count\":3},\n",
+            "{\"path\":\"t/b.txt\",\"count\":3},\n",
+            "{\"path\":\"t/sub/e.txt\",\"count\":3},\n",
+            "{\"path\":\"t/d.txt\",\"count\":1},\n",
+            "{\"path\":\"t/sub/c.txt\",\"count\":1}\n",
+            "]\n"
+        )
+    );
+}
@@ -0,0 +1,40 @@
+// --version 输出 Cargo.toml 中的版本号和 build.rs 收集的构建信息
+
+mod common;
+
+/// 测试和被测的程序用同样的 feature 编译
+fn features() -> String {
+    let enabled = [
+        ("bzip2", cfg!(feature = "bzip2")),
+        ("default", cfg!(feature = "default")),
+        ("sqlite", cfg!(feature = "sqlite")),
+        ("xz", cfg!(feature = "xz")),
+        ("zstd", cfg!(feature = "zstd")),
+    ];
+    let names: Vec<&str> = enabled.iter().filter(|(_, on)| *on).map(|(name, _)| *name).collect();
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(",")
+    }
+}
+
+#[test]
+fn long_version_has_build_metadata() {
+    let dir = common::scratch("version-long");
+    let out = common::pgrep(&dir, &["--version"]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    let s = common::stdout(&out);
+    let lines: Vec<&str> = s.lines().collect();
+    assert_eq!(lines[0], format!("pgrep {}", env!("CARGO_PKG_VERSION")));
+    assert!(lines.iter().any(|l| l.starts_with("提交: ")), "{}", s);
+    assert!(lines.iter().any(|l| l.starts_with("编译器: rustc ")), "{}", s);
+    assert!(lines.contains(&format!("启用的 feature: {}", features()).as_str()), "{}", s);
+}
+
+#[test]
+fn short_version_is_only_the_number() {
+    let dir = common::scratch("version-short");
+    let out = common::pgrep(&dir, &["-V"]);
+    assert_eq!(common::stdout(&out), format!("pgrep {}\n", env!("CARGO_PKG_VERSION")));
+}
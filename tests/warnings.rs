@@ -0,0 +1,49 @@
+// 模式的提醒和搜索过程中的警告都是 warn 级别的日志，--no-warnings 对它们的作用相同
+
+mod common;
+
+#[test]
+fn pattern_warnings_use_the_log_channel() {
+    let dir = common::scratch("warnings-pattern");
+    common::write(&dir, "a.txt", "x\n");
+
+    let out = common::pgrep(&dir, &["-p", ".*", "-f", "a.txt"]);
+    assert_eq!(common::stdout(&out), "a.txt:1:x\n");
+    assert!(
+        common::stderr(&out).starts_with("[WARN  pgrep] 模式 \".*\" 能匹配空字符串，会输出几乎所有的行"),
+        "{}",
+        common::stderr(&out)
+    );
+
+    let out = common::pgrep(&dir, &["-p", "a*.txt", "-f", "a.txt"]);
+    assert!(
+        common::stderr(&out).starts_with("[WARN  pgrep] 模式 \"a*.txt\" 看起来像文件名通配符"),
+        "{}",
+        common::stderr(&out)
+    );
+
+    // -v 只会多出 info 级别的日志，警告的写法不变
+    let out = common::pgrep(&dir, &["-v", "-p", ".*", "-f", "a.txt"]);
+    assert!(common::stderr(&out).contains("[WARN  pgrep] 模式 \".*\""), "{}", common::stderr(&out));
+}
+
+#[test]
+fn no_warnings_silences_every_warning() {
+    let dir = common::scratch("warnings-off");
+    common::write(&dir, "long.txt", format!("{}\n", "x".repeat(50)));
+
+    // 模式的提醒（main）和截断的警告（库中的 longline）
+    let args = ["-p", ".*", "--max-line-bytes", "10", "-f", "long.txt"];
+    let out = common::pgrep(&dir, &args);
+    let stderr = common::stderr(&out);
+    assert!(stderr.contains("[WARN  pgrep] 模式 \".*\""), "{}", stderr);
+    assert!(stderr.contains("[WARN  pgrep::longline] long.txt 中有 1 行超过了 --max-line-bytes"), "{}", stderr);
+
+    for extra in [&["--no-warnings"][..], &["--no-warnings", "-vv"]] {
+        let out = common::pgrep(&dir, &[&args[..], extra].concat());
+        assert_eq!(common::stdout(&out), "long.txt:1:xxxxxxxxxx\n");
+        assert!(!common::stderr(&out).contains("WARN"), "{:?}: {}", extra, common::stderr(&out));
+    }
+    let out = common::command(&dir, &[&args[..], &["--no-warnings"]].concat()).env("RUST_LOG", "warn").output().unwrap();
+    assert_eq!(common::stderr(&out), "");
+}
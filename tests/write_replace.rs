@@ -0,0 +1,78 @@
+// --write-replace 原地改写文件
+
+mod common;
+
+#[cfg(unix)]
+#[test]
+fn symlink_is_kept_and_target_is_rewritten() {
+    let dir = common::scratch("write-replace-symlink");
+    common::write(&dir, "w.txt", "foo foo\n");
+    std::os::unix::fs::symlink("w.txt", dir.join("wl.txt")).unwrap();
+
+    let out = common::pgrep(&dir, &["-p", "foo", "--write-replace", "bar", "-f", "wl.txt"]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+
+    let link = std::fs::symlink_metadata(dir.join("wl.txt")).unwrap();
+    assert!(link.file_type().is_symlink());
+    assert_eq!(std::fs::read_link(dir.join("wl.txt")).unwrap(), std::path::Path::new("w.txt"));
+    assert_eq!(std::fs::read_to_string(dir.join("w.txt")).unwrap(), "bar bar\n");
+    // 临时文件没有留在目录中
+    let names: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(names.len(), 2, "{:?}", names);
+}
+
+#[test]
+fn regular_file_is_rewritten() {
+    let dir = common::scratch("write-replace-plain");
+    common::write(&dir, "a.txt", "foo\nkeep\n");
+    let out = common::pgrep(&dir, &["-p", "foo", "--write-replace", "bar", "-f", "a.txt"]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt")).unwrap(), "bar\nkeep\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn permissions_are_preserved() {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    let dir = common::scratch("write-replace-mode");
+    let p = common::write(&dir, "run.sh", "echo foo\n");
+    std::fs::set_permissions(&p, std::fs::Permissions::from_mode(0o741)).unwrap();
+    let before = std::fs::metadata(&p).unwrap();
+
+    let out = common::pgrep(&dir, &["-p", "foo", "--write-replace", "bar", "-f", "run.sh"]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    let after = std::fs::metadata(&p).unwrap();
+    assert_eq!(std::fs::read_to_string(&p).unwrap(), "echo bar\n");
+    assert_eq!(after.permissions().mode() & 0o7777, 0o741);
+    assert_eq!((after.uid(), after.gid()), (before.uid(), before.gid()));
+    // 写的是新文件，重命名后覆盖了原来的文件
+    assert_ne!(after.ino(), before.ino());
+}
+
+#[cfg(unix)]
+#[test]
+fn no_op_files_are_left_untouched() {
+    use std::os::unix::fs::MetadataExt;
+    let dir = common::scratch("write-replace-noop");
+    let past = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+    for (name, text) in [("same.txt", "foo\n"), ("none.txt", "bar\n"), ("hit.txt", "foo foo\n")] {
+        let p = common::write(&dir, name, text);
+        std::fs::File::options().write(true).open(&p).unwrap().set_modified(past).unwrap();
+    }
+    let meta = |name: &str| std::fs::metadata(dir.join(name)).unwrap();
+    let inodes = (meta("same.txt").ino(), meta("none.txt").ino());
+
+    // 替换结果和原来相同、没有匹配的文件都不写回
+    let out = common::pgrep(&dir, &["-p", "foo", "--write-replace", "foo", "-f", "same.txt", "none.txt"]);
+    assert!(out.status.success(), "{}", common::stderr(&out));
+    assert_eq!(common::stdout(&out), "共修改 0 个文件，0 处替换\n");
+    for name in ["same.txt", "none.txt"] {
+        assert_eq!(meta(name).modified().unwrap(), past, "{}", name);
+    }
+    assert_eq!((meta("same.txt").ino(), meta("none.txt").ino()), inodes);
+
+    let out = common::pgrep(&dir, &["-p", "foo", "--write-replace", "baz", "-f", "none.txt", "hit.txt"]);
+    assert_eq!(common::stdout(&out), "hit.txt: 2 处替换\n共修改 1 个文件，2 处替换\n");
+    assert_eq!(meta("none.txt").modified().unwrap(), past);
+    assert_ne!(meta("hit.txt").modified().unwrap(), past);
+}